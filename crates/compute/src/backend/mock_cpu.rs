@@ -253,7 +253,7 @@ mod tests {
 
         // Physics world passes
         assert_eq!(binding_count(&Kernel::IntegrateBodies), 3);
-        assert_eq!(binding_count(&Kernel::DetectContactsSphere), 2);
+        assert_eq!(binding_count(&Kernel::DetectContactsSphere), 3);
         assert_eq!(binding_count(&Kernel::DetectContactsBox), 3);
         assert_eq!(binding_count(&Kernel::DetectContactsSDF), 3);
         assert_eq!(binding_count(&Kernel::SolveContactsPBD), 3);
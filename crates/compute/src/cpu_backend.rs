@@ -81,12 +81,18 @@ impl ComputeBackend for CpuBackend {
             Kernel::DetectContactsBoxCylinder => {
                 kernels::detect_contacts_box_cylinder::handle_detect_contacts_box_cylinder(binds)
             }
+            Kernel::DetectContactsCapsule => {
+                kernels::detect_contacts_capsule::handle_detect_contacts_capsule(binds)
+            }
             Kernel::DetectContactsSDF => {
                 kernels::detect_contacts_sdf_op::handle_detect_contacts_sdf(binds)
             }
             Kernel::SolveContactsPBD => {
                 kernels::solve_contacts_pbd_op::handle_solve_contacts_pbd(binds)
             }
+            Kernel::SolveContactsSoft => {
+                kernels::solve_contacts_soft_op::handle_solve_contacts_soft(binds)
+            }
             Kernel::SolveJointsPBD => kernels::solve_joints_pbd_op::handle_solve_joints_pbd(binds),
             Kernel::SolveRevoluteJoints => kernels::solve_revolute_joints_op::handle_solve_revolute_joints(binds),
             Kernel::SolvePrismaticJoints => kernels::solve_prismatic_joints_op::handle_solve_prismatic_joints(binds),
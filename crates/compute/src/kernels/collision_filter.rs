@@ -0,0 +1,79 @@
+//! Collision filtering for the pairwise contact kernels.
+//!
+//! The filter binding of [`crate::Kernel::DetectContactsSphere`] and
+//! [`crate::Kernel::DetectContactsCapsule`] is an array of `[u32; 2]`
+//! entries: one `[group, mask]` entry per body, in the order of the body
+//! buffer, followed by any number of excluded `[a, b]` body index pairs.
+//! Bodies past the end of the buffer use [`CollisionFilter::DEFAULT`].
+//!
+//! Bodies `i` and `j` collide only if `group_i & mask_j` and
+//! `group_j & mask_i` are both non-zero and neither `[i, j]` nor `[j, i]`
+//! is excluded.
+
+use crate::{BufferView, ComputeError};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, bytemuck::Pod, bytemuck::Zeroable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Collision group bits of a body and the groups it collides with.
+pub struct CollisionFilter {
+    pub group: u32,
+    pub mask: u32,
+}
+
+impl CollisionFilter {
+    /// Filter of a body in group 1 that collides with every group.
+    pub const DEFAULT: Self = Self { group: 1, mask: u32::MAX };
+
+    /// Whether bodies with filters `self` and `other` may collide.
+    #[must_use]
+    pub const fn collides_with(self, other: Self) -> bool {
+        self.group & other.mask != 0 && other.group & self.mask != 0
+    }
+}
+
+/// Packs per-body filters and excluded index pairs into the filter binding
+/// layout described in the module documentation.
+#[must_use]
+pub fn pack_filters(bodies: &[CollisionFilter], excluded: &[[u32; 2]]) -> Vec<[u32; 2]> {
+    bodies
+        .iter()
+        .map(|filter| [filter.group, filter.mask])
+        .chain(excluded.iter().copied())
+        .collect()
+}
+
+/// Filter binding of a contact kernel read on the CPU.
+pub(crate) struct PairFilter<'a> {
+    bodies: &'a [[u32; 2]],
+    excluded: &'a [[u32; 2]],
+}
+
+impl<'a> PairFilter<'a> {
+    /// Splits the filter binding for a kernel dispatched over `body_count`
+    /// bodies.
+    pub(crate) fn new(view: &'a BufferView, body_count: usize) -> Result<Self, ComputeError> {
+        if !view.data.len().is_multiple_of(std::mem::size_of::<[u32; 2]>()) {
+            return Err(ComputeError::ShapeMismatch(
+                "Filter buffer size must be a multiple of two u32 values",
+            ));
+        }
+        let entries: &[[u32; 2]] = bytemuck::cast_slice(&view.data);
+        let (bodies, excluded) = entries.split_at(body_count.min(entries.len()));
+        Ok(Self { bodies, excluded })
+    }
+
+    /// Whether the bodies at indices `a` and `b` may collide.
+    pub(crate) fn allows(&self, a: usize, b: usize) -> bool {
+        let filter = |index: usize| {
+            self.bodies
+                .get(index)
+                .map_or(CollisionFilter::DEFAULT, |&[group, mask]| CollisionFilter { group, mask })
+        };
+        let excluded = |&[first, second]: &[u32; 2]| {
+            let pair = (first as usize, second as usize);
+            pair == (a, b) || pair == (b, a)
+        };
+        filter(a).collides_with(filter(b)) && !self.excluded.iter().any(excluded)
+    }
+}
@@ -0,0 +1,244 @@
+use super::collision_filter::PairFilter;
+use crate::{BufferView, ComputeError};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+/// Minimal 3D vector used by the capsule contact kernel.
+pub struct CapsuleVec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl CapsuleVec3 {
+    fn sub(self, other: Self) -> Self {
+        Self {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        }
+    }
+
+    fn add_scaled(self, other: Self, scale: f32) -> Self {
+        Self {
+            x: self.x + other.x * scale,
+            y: self.y + other.y * scale,
+            z: self.z + other.z * scale,
+        }
+    }
+
+    fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+/// A capsule given by the world-space ends of its core segment and its
+/// radius.
+pub struct GpuCapsule {
+    pub a: CapsuleVec3,
+    pub radius: f32,
+    pub b: CapsuleVec3,
+    pub _pad: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+/// Contact between two overlapping capsules. The normal points from
+/// capsule A to capsule B and the point lies halfway between the surfaces.
+pub struct CapsuleContact {
+    pub capsule_a_index: u32,
+    pub capsule_b_index: u32,
+    pub point: CapsuleVec3,
+    pub normal: CapsuleVec3,
+    pub depth: f32,
+    pub _pad: [f32; 3],
+}
+
+/// CPU implementation of capsule-capsule contact detection.
+///
+/// Every pair of capsules in the first binding that the collision filters in
+/// the second binding allow is tested through the closest
+/// points between their core segments, and one [`CapsuleContact`] is written
+/// to the output for each overlapping pair, in the same order as the WGSL
+/// kernel.
+///
+/// # Errors
+///
+/// Returns [`ComputeError::ShapeMismatch`] if fewer than three buffers are
+/// bound or the capsule or filter buffer does not hold a whole number of
+/// elements.
+pub fn handle_detect_contacts_capsule(binds: &[BufferView]) -> Result<Vec<Vec<u8>>, ComputeError> {
+    if binds.len() < 3 {
+        return Err(ComputeError::ShapeMismatch(
+            "DetectContactsCapsule expects 3 buffers (capsules, filters, contacts)",
+        ));
+    }
+
+    let capsules_view = &binds[0];
+    if !capsules_view.data.len().is_multiple_of(std::mem::size_of::<GpuCapsule>()) {
+        return Err(ComputeError::ShapeMismatch(
+            "Capsules buffer size must be a multiple of GpuCapsule",
+        ));
+    }
+    let num_capsules = capsules_view.data.len() / std::mem::size_of::<GpuCapsule>();
+    if capsules_view.shape != vec![num_capsules] {
+        return Err(ComputeError::ShapeMismatch(
+            "Capsules buffer shape does not match element count",
+        ));
+    }
+    let capsules: &[GpuCapsule] = bytemuck::cast_slice(&capsules_view.data);
+    let filter = PairFilter::new(&binds[1], num_capsules)?;
+
+    let mut contacts = Vec::<CapsuleContact>::new();
+    for (i, ca) in (0u32..).zip(capsules) {
+        for (j, cb) in (0u32..).zip(capsules).skip(i as usize + 1) {
+            if !filter.allows(i as usize, j as usize) {
+                continue;
+            }
+            let (s, t) = closest_between_segments(ca.a, ca.b, cb.a, cb.b);
+            let pa = ca.a.add_scaled(ca.b.sub(ca.a), s);
+            let pb = cb.a.add_scaled(cb.b.sub(cb.a), t);
+            let delta = pb.sub(pa);
+            let dist = delta.dot(delta).sqrt();
+            let rad_sum = ca.radius + cb.radius;
+            if dist < rad_sum {
+                let normal = if dist > 0.0 {
+                    CapsuleVec3 {
+                        x: delta.x / dist,
+                        y: delta.y / dist,
+                        z: delta.z / dist,
+                    }
+                } else {
+                    CapsuleVec3 { x: 0.0, y: 1.0, z: 0.0 }
+                };
+                let depth = rad_sum - dist;
+                contacts.push(CapsuleContact {
+                    capsule_a_index: i,
+                    capsule_b_index: j,
+                    point: pa.add_scaled(normal, ca.radius - 0.5 * depth),
+                    normal,
+                    depth,
+                    _pad: [0.0; 3],
+                });
+            }
+        }
+    }
+
+    Ok(vec![bytemuck::cast_slice(&contacts).to_vec()])
+}
+
+/// Parameters `(s, t)` of the closest points between the segments `p0-p1`
+/// and `q0-q1`.
+fn closest_between_segments(p0: CapsuleVec3, p1: CapsuleVec3, q0: CapsuleVec3, q1: CapsuleVec3) -> (f32, f32) {
+    const EPS: f32 = 1e-7;
+    let (dir_p, dir_q, between) = (p1.sub(p0), q1.sub(q0), p0.sub(q0));
+    let (len_p, len_q, along_q) = (dir_p.dot(dir_p), dir_q.dot(dir_q), dir_q.dot(between));
+    if len_p <= EPS && len_q <= EPS {
+        return (0.0, 0.0);
+    }
+    if len_p <= EPS {
+        return (0.0, (along_q / len_q).clamp(0.0, 1.0));
+    }
+    let along_p = dir_p.dot(between);
+    if len_q <= EPS {
+        return ((-along_p / len_p).clamp(0.0, 1.0), 0.0);
+    }
+    let cross = dir_p.dot(dir_q);
+    let denom = len_p * len_q - cross * cross;
+    let s = if denom > EPS * len_p * len_q {
+        ((cross * along_q - along_p * len_q) / denom).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let t = (cross * s + along_q) / len_q;
+    if t < 0.0 {
+        ((-along_p / len_p).clamp(0.0, 1.0), 0.0)
+    } else if t > 1.0 {
+        (((cross - along_p) / len_p).clamp(0.0, 1.0), 1.0)
+    } else {
+        (s, t)
+    }
+}
+
+#[cfg(feature = "cpu-tests")]
+#[cfg(test)]
+mod tests {
+    use crate::{BufferView, ComputeBackend, CpuBackend, Kernel};
+    use super::{CapsuleContact, CapsuleVec3, GpuCapsule};
+    use crate::kernels::collision_filter::{pack_filters, CollisionFilter};
+    use std::sync::Arc;
+
+    fn capsule(a: [f32; 3], b: [f32; 3], radius: f32) -> GpuCapsule {
+        GpuCapsule {
+            a: CapsuleVec3 { x: a[0], y: a[1], z: a[2] },
+            radius,
+            b: CapsuleVec3 { x: b[0], y: b[1], z: b[2] },
+            _pad: 0.0,
+        }
+    }
+
+    fn dispatch(capsules: &[GpuCapsule]) -> Vec<CapsuleContact> {
+        dispatch_filtered(capsules, &[], &[])
+    }
+
+    fn dispatch_filtered(capsules: &[GpuCapsule], filters: &[CollisionFilter], excluded: &[[u32; 2]]) -> Vec<CapsuleContact> {
+        let cpu = CpuBackend::new();
+        let bytes: Arc<[u8]> = bytemuck::cast_slice(capsules).to_vec().into();
+        let capsules_view = BufferView::new(bytes, vec![capsules.len()], std::mem::size_of::<GpuCapsule>());
+        let entries = pack_filters(filters, excluded);
+        let filter_bytes: Arc<[u8]> = bytemuck::cast_slice(&entries).to_vec().into();
+        let filters_view = BufferView::new(filter_bytes, vec![entries.len()], std::mem::size_of::<[u32; 2]>());
+        let out_placeholder: Arc<[u8]> = vec![0u8; std::mem::size_of::<CapsuleContact>()].into();
+        let out_view = BufferView::new(out_placeholder, vec![1], std::mem::size_of::<CapsuleContact>());
+
+        let result = cpu
+            .dispatch(&Kernel::DetectContactsCapsule, &[capsules_view, filters_view, out_view], [1, 1, 1])
+            .expect("Dispatch failed");
+        assert_eq!(result.len(), 1);
+        if result[0].is_empty() {
+            Vec::new()
+        } else {
+            bytemuck::cast_slice(&result[0]).to_vec()
+        }
+    }
+
+    #[test]
+    fn crossing_capsules_touch_between_their_axes() {
+        // One along x, one along z passing 0.3 above it
+        let contacts = dispatch(&[
+            capsule([-1.0, 0.0, 0.0], [1.0, 0.0, 0.0], 0.2),
+            capsule([0.5, 0.3, -1.0], [0.5, 0.3, 1.0], 0.2),
+        ]);
+        assert_eq!(contacts.len(), 1);
+        let contact = contacts[0];
+        assert_eq!((contact.capsule_a_index, contact.capsule_b_index), (0, 1));
+        assert!((contact.normal.y - 1.0).abs() < 1e-6);
+        assert!((contact.depth - 0.1).abs() < 1e-6);
+        assert!((contact.point.x - 0.5).abs() < 1e-6 && (contact.point.y - 0.15).abs() < 1e-6);
+    }
+
+    #[test]
+    fn separated_capsules_have_no_contacts() {
+        let contacts = dispatch(&[
+            capsule([0.0, -1.0, 0.0], [0.0, 1.0, 0.0], 0.2),
+            capsule([0.0, 1.5, 0.0], [0.0, 2.5, 0.0], 0.2),
+        ]);
+        assert!(contacts.is_empty());
+    }
+
+    #[test]
+    fn filters_skip_capsule_pairs() {
+        let capsules = [
+            capsule([-1.0, 0.0, 0.0], [1.0, 0.0, 0.0], 0.2),
+            capsule([0.5, 0.3, -1.0], [0.5, 0.3, 1.0], 0.2),
+        ];
+        let apart = [CollisionFilter { group: 1, mask: 1 }, CollisionFilter { group: 2, mask: 2 }];
+        assert!(dispatch_filtered(&capsules, &apart, &[]).is_empty());
+        // Excluded pairs follow an entry for every capsule
+        let default = [CollisionFilter::DEFAULT; 2];
+        assert!(dispatch_filtered(&capsules, &default, &[[1, 0]]).is_empty());
+        assert_eq!(dispatch_filtered(&capsules, &default, &[[0, 0]]).len(), 1);
+    }
+}
@@ -1,3 +1,4 @@
+use super::collision_filter::PairFilter;
 use crate::{BufferView, ComputeError};
 
 #[repr(C)]
@@ -29,13 +30,14 @@ pub struct TestContact {
 /// CPU implementation of simple sphere-sphere contact detection.
 ///
 /// Each body in the first binding is treated as a unit sphere. All pairwise
-/// intersections are detected and written as [`TestContact`] structures into the
-/// output buffer provided as the second binding. The format matches what the
-/// position based dynamics solver expects.
+/// intersections allowed by the collision filters in the second binding are
+/// detected and written as [`TestContact`] structures into the output buffer
+/// provided as the third binding. The format matches what the position based
+/// dynamics solver expects.
 pub fn handle_detect_contacts_sphere(binds: &[BufferView]) -> Result<Vec<Vec<u8>>, ComputeError> {
-    if binds.len() < 2 {
+    if binds.len() < 3 {
         return Err(ComputeError::ShapeMismatch(
-            "DetectContactsSphere expects 2 buffers (bodies, contacts)",
+            "DetectContactsSphere expects 3 buffers (bodies, filters, contacts)",
         ));
     }
 
@@ -54,10 +56,14 @@ pub fn handle_detect_contacts_sphere(binds: &[BufferView]) -> Result<Vec<Vec<u8>
     }
 
     let bodies: &[TestBody] = bytemuck::cast_slice(&bodies_view.data);
+    let filter = PairFilter::new(&binds[1], num_bodies)?;
 
     let mut contacts = Vec::<TestContact>::new();
     for i in 0..num_bodies {
         for j in (i + 1)..num_bodies {
+            if !filter.allows(i, j) {
+                continue;
+            }
             let a = &bodies[i];
             let b = &bodies[j];
             let dx = b.pos.x - a.pos.x;
@@ -108,8 +114,15 @@ pub fn handle_detect_contacts_sphere(binds: &[BufferView]) -> Result<Vec<Vec<u8>
 mod tests {
     use crate::{BufferView, ComputeBackend, CpuBackend, Kernel};
     use super::{TestBody, TestContact, TestVec3};
+    use crate::kernels::collision_filter::{pack_filters, CollisionFilter};
     use std::sync::Arc;
 
+    fn filters_view(bodies: &[CollisionFilter], excluded: &[[u32; 2]]) -> BufferView {
+        let entries = pack_filters(bodies, excluded);
+        let bytes: Arc<[u8]> = bytemuck::cast_slice(&entries).to_vec().into();
+        BufferView::new(bytes, vec![entries.len()], std::mem::size_of::<[u32; 2]>())
+    }
+
     #[test]
     fn contacts_generated_for_overlapping_spheres() {
         let cpu = CpuBackend::new();
@@ -132,7 +145,7 @@ mod tests {
         let result = cpu
             .dispatch(
                 &Kernel::DetectContactsSphere,
-                &[bodies_view, out_view],
+                &[bodies_view, filters_view(&[], &[]), out_view],
                 [1, 1, 1],
             )
             .expect("Dispatch failed");
@@ -168,7 +181,7 @@ mod tests {
         let result = cpu
             .dispatch(
                 &Kernel::DetectContactsSphere,
-                &[bodies_view, out_view],
+                &[bodies_view, filters_view(&[], &[]), out_view],
                 [1, 1, 1],
             )
             .expect("Dispatch failed");
@@ -181,4 +194,52 @@ mod tests {
         };
         assert!(contacts.is_empty());
     }
+
+    #[test]
+    fn filtered_pairs_have_no_contacts() {
+        let cpu = CpuBackend::new();
+
+        // Three overlapping spheres in a row
+        let bodies: Vec<TestBody> = (0..3u8)
+            .map(|i| TestBody {
+                pos: TestVec3 { x: f32::from(i), y: 0.0, z: 0.0 },
+            })
+            .collect();
+        let bodies_bytes: Arc<[u8]> = bytemuck::cast_slice(&bodies).to_vec().into();
+        let bodies_view = BufferView::new(bodies_bytes, vec![bodies.len()], std::mem::size_of::<TestBody>());
+        let out_placeholder: Arc<[u8]> = vec![0u8; std::mem::size_of::<TestContact>() * 6].into();
+        let out_view = BufferView::new(out_placeholder, vec![6], std::mem::size_of::<TestContact>());
+
+        // Body 2 ignores group 1 and the pair (0, 1) is excluded outright
+        let filters = [
+            CollisionFilter::DEFAULT,
+            CollisionFilter::DEFAULT,
+            CollisionFilter { group: 2, mask: !1 },
+        ];
+        let result = cpu
+            .dispatch(
+                &Kernel::DetectContactsSphere,
+                &[bodies_view.clone(), filters_view(&filters, &[[1, 0]]), out_view.clone()],
+                [1, 1, 1],
+            )
+            .expect("Dispatch failed");
+        let contacts: &[TestContact] = if result[0].is_empty() {
+            &[]
+        } else {
+            bytemuck::cast_slice(&result[0])
+        };
+        assert!(contacts.is_empty());
+
+        let result = cpu
+            .dispatch(
+                &Kernel::DetectContactsSphere,
+                // Bodies without a filter entry use the default filter
+                &[bodies_view, filters_view(&filters[..2], &[]), out_view],
+                [1, 1, 1],
+            )
+            .expect("Dispatch failed");
+        let contacts: &[TestContact] = bytemuck::cast_slice(&result[0]);
+        let indices: Vec<u32> = contacts.iter().map(|c| c.body_index).collect();
+        assert_eq!(indices, vec![0, 1, 1, 2]);
+    }
 }
@@ -1,8 +1,10 @@
 pub mod add_broadcast_op;
 pub mod add_op;
 pub mod clamp_op;
+pub mod collision_filter;
 pub mod detect_contacts_box_op;
 pub mod detect_contacts_box_cylinder;
+pub mod detect_contacts_capsule;
 pub mod detect_contacts_cylinder_cylinder;
 pub mod detect_contacts_sphere_cylinder;
 pub mod detect_contacts_sdf_op;
@@ -28,6 +30,7 @@ pub mod scatter_add_op;
 pub mod segmented_reduce_sum_op;
 pub mod sigmoid_op;
 pub mod solve_contacts_pbd_op;
+pub mod solve_contacts_soft_op;
 pub mod solve_joints_pbd_op;
 pub mod solve_revolute_joints_op;
 pub mod solve_prismatic_joints_op;
@@ -43,6 +46,7 @@ pub use add_op::handle_add;
 pub use clamp_op::handle_clamp;
 pub use detect_contacts_box_op::handle_detect_contacts_box;
 pub use detect_contacts_box_cylinder::handle_detect_contacts_box_cylinder;
+pub use detect_contacts_capsule::handle_detect_contacts_capsule;
 pub use detect_contacts_cylinder_cylinder::handle_detect_contacts_cylinder_cylinder;
 pub use detect_contacts_sphere_cylinder::handle_detect_contacts_sphere_cylinder;
 pub use detect_contacts_sdf_op::handle_detect_contacts_sdf;
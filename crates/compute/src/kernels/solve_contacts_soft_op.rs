@@ -0,0 +1,235 @@
+use crate::{BufferView, ComputeError};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct TestSphere {
+    pos: [f32; 3],
+    _pad1: f32,
+    vel: [f32; 3],
+    _pad2: f32,
+    orientation: [f32; 4],
+    angular_vel: [f32; 3],
+    _pad3: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct TestContact {
+    body_index: u32,
+    normal: [f32; 3],
+    depth: f32,
+    inv_mass: f32,
+    friction: f32,
+    _pad: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct TestSoftParams {
+    stiffness: f32,
+    damping: f32,
+    smoothing: f32,
+    dt: f32,
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// Applies compliant penalty contacts against static geometry to body
+/// velocities.
+///
+/// The expected bindings are `[bodies_inout, contacts, params]`. Each contact
+/// names one body, the normal pointing away from the surface it touches, its
+/// penetration depth, the body's inverse mass and the friction coefficient.
+/// `params` holds `stiffness`, `damping`, `smoothing` and `dt`. A contact at
+/// depth `d` pushes with `stiffness * smoothing * softplus(d / smoothing)`
+/// plus damping weighted by `sigmoid(d / smoothing)`, integrated implicitly
+/// over `dt`, and adds friction regularized below a slip of `smoothing` per
+/// step. Bodies are treated as point masses. Contacts are applied in order,
+/// and the updated bodies are returned in a single buffer.
+///
+/// # Errors
+///
+/// Returns [`ComputeError::ShapeMismatch`] if a buffer is missing, its size
+/// does not match its shape, or a contact names a body that does not exist.
+pub fn handle_solve_contacts_soft(binds: &[BufferView]) -> Result<Vec<Vec<u8>>, ComputeError> {
+    if binds.len() < 3 {
+        return Err(ComputeError::ShapeMismatch(
+            "SolveContactsSoft expects 3 buffers (bodies, contacts, params)",
+        ));
+    }
+
+    let bodies_view = &binds[0];
+    let contacts_view = &binds[1];
+    let params_view = &binds[2];
+
+    if !bodies_view
+        .data
+        .len()
+        .is_multiple_of(std::mem::size_of::<TestSphere>())
+    {
+        return Err(ComputeError::ShapeMismatch(
+            "Bodies buffer size is not a multiple of TestSphere size",
+        ));
+    }
+    let num_bodies = bodies_view.data.len() / std::mem::size_of::<TestSphere>();
+    if bodies_view.shape != vec![num_bodies] {
+        return Err(ComputeError::ShapeMismatch(
+            "Bodies buffer shape does not match its data length",
+        ));
+    }
+
+    if !contacts_view
+        .data
+        .len()
+        .is_multiple_of(std::mem::size_of::<TestContact>())
+    {
+        return Err(ComputeError::ShapeMismatch(
+            "Contacts buffer size is not a multiple of TestContact size",
+        ));
+    }
+    let num_contacts = contacts_view.data.len() / std::mem::size_of::<TestContact>();
+    if contacts_view.shape != vec![num_contacts] {
+        return Err(ComputeError::ShapeMismatch(
+            "Contacts buffer shape does not match its data length",
+        ));
+    }
+
+    if params_view.data.len() != std::mem::size_of::<TestSoftParams>()
+        || params_view.shape != vec![1]
+    {
+        return Err(ComputeError::ShapeMismatch(
+            "Params buffer for SolveContactsSoft has incorrect size or shape",
+        ));
+    }
+    let params: &TestSoftParams = bytemuck::from_bytes(&params_view.data);
+
+    let mut bodies = bytemuck::cast_slice::<_, TestSphere>(&bodies_view.data).to_vec();
+    let contacts: &[TestContact] = bytemuck::cast_slice(&contacts_view.data);
+
+    for contact in contacts {
+        let idx = contact.body_index as usize;
+        if idx >= bodies.len() {
+            return Err(ComputeError::ShapeMismatch(
+                "Contact body index out of bounds for bodies buffer",
+            ));
+        }
+        let body = &mut bodies[idx];
+        let normal = contact.normal;
+        let (w, dt) = (contact.inv_mass, params.dt);
+
+        let x = contact.depth / params.smoothing;
+        let penetration = params.smoothing * (x.max(0.0) + (-x.abs()).exp().ln_1p());
+        let activation = 1.0 / (1.0 + (-x).exp());
+        let gain = activation * (params.stiffness * dt + params.damping);
+        let normal_speed = dot(body.vel, normal);
+        let impulse =
+            dt * (params.stiffness * penetration - gain * normal_speed) / (1.0 + w * dt * gain);
+        let impulse = impulse.max(0.0);
+        for (v, n) in body.vel.iter_mut().zip(normal) {
+            *v += n * impulse * w;
+        }
+
+        let normal_speed = dot(body.vel, normal);
+        let slip = [
+            body.vel[0] - normal[0] * normal_speed,
+            body.vel[1] - normal[1] * normal_speed,
+            body.vel[2] - normal[2] * normal_speed,
+        ];
+        let limit = contact.friction * impulse;
+        let regularization = params.smoothing / dt;
+        let speed = (dot(slip, slip) + regularization * regularization).sqrt();
+        let scale = limit / (speed + limit * w);
+        for (v, s) in body.vel.iter_mut().zip(slip) {
+            *v -= s * scale * w;
+        }
+    }
+
+    let out_bytes = bytemuck::cast_slice(&bodies).to_vec();
+    Ok(vec![out_bytes])
+}
+
+#[cfg(feature = "cpu-tests")]
+#[cfg(test)]
+mod tests {
+    use super::{TestContact, TestSoftParams, TestSphere};
+    use crate::{BufferView, ComputeBackend, CpuBackend, Kernel};
+    use std::sync::Arc;
+
+    fn solve(sphere: TestSphere, depth: f32, params: TestSoftParams) -> TestSphere {
+        let contact = TestContact {
+            body_index: 0,
+            normal: [0.0, 1.0, 0.0],
+            depth,
+            inv_mass: 1.0,
+            friction: 0.5,
+            _pad: 0.0,
+        };
+        let view = |bytes: &[u8], size: usize| BufferView::new(Arc::from(bytes), vec![1], size);
+        let out = CpuBackend::new()
+            .dispatch(
+                &Kernel::SolveContactsSoft,
+                &[
+                    view(
+                        bytemuck::bytes_of(&sphere),
+                        std::mem::size_of::<TestSphere>(),
+                    ),
+                    view(
+                        bytemuck::bytes_of(&contact),
+                        std::mem::size_of::<TestContact>(),
+                    ),
+                    view(
+                        bytemuck::bytes_of(&params),
+                        std::mem::size_of::<TestSoftParams>(),
+                    ),
+                ],
+                [1, 1, 1],
+            )
+            .expect("dispatch failed");
+        assert_eq!(out.len(), 1);
+        bytemuck::cast_slice::<_, TestSphere>(&out[0])[0]
+    }
+
+    #[test]
+    fn soft_contact_force_grows_smoothly_with_depth() {
+        let params = TestSoftParams {
+            stiffness: 1.0e4,
+            damping: 0.0,
+            smoothing: 0.01,
+            dt: 0.01,
+        };
+        let sphere = TestSphere {
+            pos: [0.0; 3],
+            _pad1: 0.0,
+            vel: [1.0, 0.0, 0.0],
+            _pad2: 0.0,
+            orientation: [0.0, 0.0, 0.0, 1.0],
+            angular_vel: [0.0; 3],
+            _pad3: 0.0,
+        };
+
+        let speeds: Vec<f32> = [-0.02, -0.01, 0.0, 0.01, 0.02]
+            .iter()
+            .map(|&depth| solve(sphere, depth, params).vel[1])
+            .collect();
+        assert!(speeds[0] > 0.0, "pushes slightly before touching");
+        assert!(
+            speeds.windows(2).all(|pair| pair[1] > pair[0]),
+            "{speeds:?}"
+        );
+        // Deep inside, the impulse approaches that of an implicit spring
+        let deep = solve(sphere, 0.1, params).vel[1];
+        let implicit = 0.01 * 1.0e4 * 0.1 / (1.0 + 0.01 * 0.01 * 1.0e4);
+        assert!(
+            (deep - implicit).abs() < 0.01 * implicit,
+            "{deep} vs {implicit}"
+        );
+
+        let slid = solve(sphere, 0.02, params);
+        assert!(
+            slid.vel[0] < 1.0 && slid.vel[0] > 0.0,
+            "friction slows without reversing"
+        );
+    }
+}
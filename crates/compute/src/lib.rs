@@ -168,7 +168,8 @@ pub enum Kernel {
     // These kernels are specific to the physics simulation.
     /// Integrates the positions and velocities of rigid bodies over a time step.
     IntegrateBodies,
-    /// Detects collisions between spheres.
+    /// Detects collisions between spheres, skipping pairs rejected by the
+    /// collision filters in [`kernels::collision_filter`].
     DetectContactsSphere,
     /// Detects collisions between boxes.
     DetectContactsBox,
@@ -178,10 +179,19 @@ pub enum Kernel {
     DetectContactsCylinderCylinder,
     /// Detects collisions between boxes and cylinders.
     DetectContactsBoxCylinder,
+    /// Detects collisions between pairs of capsules, skipping pairs rejected
+    /// by the collision filters in [`kernels::collision_filter`].
+    DetectContactsCapsule,
     /// Detects collisions using Signed Distance Functions (SDFs).
     DetectContactsSDF,
     /// Solves contact constraints using Position-Based Dynamics (PBD).
     SolveContactsPBD,
+    /// Applies compliant penalty contacts with a smooth force law to body
+    /// velocities.
+    /// - **Binding 0:** In/Out `bodies`
+    /// - **Binding 1:** Input `contacts`
+    /// - **Binding 2:** Uniform `params` (stiffness, damping, smoothing, dt)
+    SolveContactsSoft,
     /// Solves joint constraints using Position-Based Dynamics (PBD).
     SolveJointsPBD,
     /// Solves revolute joint constraints.
@@ -78,8 +78,10 @@ fn kernel_name(kernel: &Kernel) -> &'static str {
         Kernel::DetectContactsSphereCylinder => "detect_contacts_sphere_cylinder",
         Kernel::DetectContactsCylinderCylinder => "detect_contacts_cylinder_cylinder",
         Kernel::DetectContactsBoxCylinder => "detect_contacts_box_cylinder",
+        Kernel::DetectContactsCapsule => "detect_contacts_capsule",
         Kernel::DetectContactsSDF => "detect_contacts_sdf",
         Kernel::SolveContactsPBD => "solve_contacts_pbd",
+        Kernel::SolveContactsSoft => "solve_contacts_soft",
         Kernel::SolveJointsPBD => "solve_joints_pbd",
         Kernel::SolveRevoluteJoints => "solve_revolute_joints",
         Kernel::SolvePrismaticJoints => "solve_prismatic_joints",
@@ -102,7 +104,8 @@ fn is_read_only(kernel: &Kernel, binding: u32) -> bool {
         Kernel::Neg | Kernel::Relu | Kernel::ExpandInstances => binding == 0 || binding == 2,
         Kernel::MatMul => binding == 0 || binding == 1 || binding == 3,
         Kernel::IntegrateBodies => binding == 1 || binding == 2,
-        Kernel::DetectContactsSphere => binding == 0,
+        Kernel::DetectContactsSphere => binding < 2,
+        Kernel::SolveContactsSoft => binding != 0,
         Kernel::Gather => binding == 0 || binding == 1 || binding == 3,
         Kernel::ScatterAdd => binding == 0 || binding == 1 || binding == 3,
         _ => binding < binding_count - 1,
@@ -115,6 +118,7 @@ fn is_uniform(kernel: &Kernel, binding: u32) -> bool {
         Kernel::ExpandInstances => binding == 2,
         Kernel::MatMul => binding == 3,
         Kernel::IntegrateBodies => binding == 1,
+        Kernel::SolveContactsSoft => binding == 2,
         _ => false,
     }
 }
@@ -151,8 +155,10 @@ fn to_shader_source(kernel: &Kernel) -> &'static str {
         Kernel::DetectContactsSphereCylinder => include_str!("../../../shaders/detect_contacts_sphere_cylinder.wgsl"),
         Kernel::DetectContactsCylinderCylinder => include_str!("../../../shaders/detect_contacts_cylinder_cylinder.wgsl"),
         Kernel::DetectContactsBoxCylinder => include_str!("../../../shaders/detect_contacts_box_cylinder.wgsl"),
+        Kernel::DetectContactsCapsule => include_str!("../../../shaders/detect_contacts_capsule.wgsl"),
         Kernel::DetectContactsSDF => include_str!("../../../shaders/detect_contacts_sdf.wgsl"),
         Kernel::SolveContactsPBD => include_str!("../../../shaders/solve_contacts_pbd.wgsl"),
+        Kernel::SolveContactsSoft => include_str!("../../../shaders/solve_contacts_soft.wgsl"),
         Kernel::SolveJointsPBD => include_str!("../../../shaders/solve_joints_pbd.wgsl"),
         Kernel::SolveRevoluteJoints => include_str!("../../../shaders/solve_revolute_joints.wgsl"),
         Kernel::SolvePrismaticJoints => include_str!("../../../shaders/solve_prismatic_joints.wgsl"),
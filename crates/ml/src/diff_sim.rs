@@ -0,0 +1,1317 @@
+//! Differentiable rigid body simulation.
+//!
+//! [`DiffSim`] replays the XPBD step of a [`PhysicsSim`] with scalar tensor
+//! operations. Stepping it with a [`crate::tape::Tape`] as the recorder lets
+//! [`crate::tape::Tape::backward`] carry the gradient of a loss on the final
+//! body states back to the initial positions and velocities, the applied
+//! forces, gravity, and the masses and materials of the bodies.
+//!
+//! Every substep integrates the bodies, projects joints, contacts and planar
+//! constraints, turns the corrections into velocities and then applies
+//! dynamic friction and restitution, in the order of
+//! [`physics::SolverType::Xpbd`]. Branches, such as whether a contact
+//! penetrates, follow the values of the forward pass, so the gradients are
+//! those of the regime the motion is in. Unlike the engine,
+//!
+//! - orientations are integrated to first order in the substep,
+//! - contacts are found at the start of each step against planes and
+//!   between spheres, with boxes touching by their corners and cylinders by
+//!   points on their rims,
+//! - bodies never fall asleep and continuous collision detection is off.
+//!
+//! Convex hulls, compounds, heightfields, meshes, distance joints, joint
+//! limits and motors, and [`physics::ContactModel::Soft`] contacts are not
+//! supported. Of the applied forces only [`physics::PhysParams::forces`] is
+//! copied; forces waiting from [`PhysicsSim::apply_force`] and its siblings
+//! are refused.
+
+use crate::recorder::Recorder;
+use crate::tensor::Tensor;
+use anyhow::{bail, Result};
+use physics::{BodyHandle, ContactModel, Integrator, JointHandle, Material, PhysicsSim, SolverType};
+use std::collections::HashMap;
+
+/// Substeps per step when the simulation does not use the XPBD solver.
+pub const DEFAULT_SUBSTEPS: usize = 8;
+
+/// Points on each cap rim of a cylinder that may touch a plane.
+const RIM_POINTS: usize = 8;
+
+/// A vector made of three tensors of shape `[1]`.
+pub type Vector = [Tensor; 3];
+
+/// A unit quaternion `[x, y, z, w]` made of four tensors of shape `[1]`.
+pub type Quaternion = [Tensor; 4];
+
+/// State and parameters of one body of a [`DiffSim`].
+///
+/// Every tensor has shape `[1]`. The state tensors are replaced by every
+/// step, so keep the initial ones to read their gradients after the
+/// backward pass. The parameters stay the same tensors across steps.
+#[derive(Clone)]
+pub struct DiffBody {
+    /// The body in the [`PhysicsSim`] this was copied from.
+    pub handle: BodyHandle,
+    /// Position of the centre of mass.
+    pub position: Vector,
+    /// Orientation as an `[x, y, z, w]` quaternion.
+    pub orientation: Quaternion,
+    /// Linear velocity.
+    pub linear_velocity: Vector,
+    /// Angular velocity.
+    pub angular_velocity: Vector,
+    /// Force applied at the centre of mass during every step. Replace it
+    /// between steps for a force that changes over time.
+    pub force: Vector,
+    /// Mass, which also scales the inertia. Only used by dynamic bodies.
+    pub mass: Tensor,
+    /// Friction coefficient of the body's material.
+    pub friction: Tensor,
+    /// Restitution coefficient of the body's material.
+    pub restitution: Tensor,
+    /// Contact compliance of the body's material.
+    pub compliance: Tensor,
+    dynamic: bool,
+    /// Inverse inertia divided by inverse mass, which depends only on the
+    /// shape.
+    inertia_scale: [f32; 3],
+    shape: ContactShape,
+}
+
+/// How a body touches planes and other bodies.
+#[derive(Clone)]
+enum ContactShape {
+    Sphere { radius: f32 },
+    Capsule { radius: f32, half_height: f32 },
+    /// Fixed points in the body frame, such as the corners of a box.
+    Points(Vec<[f32; 3]>),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum JointKind {
+    Revolute,
+    Prismatic,
+    Ball,
+    Fixed,
+}
+
+struct DiffJoint {
+    kind: JointKind,
+    /// Index of body A in the solver state, with the static body last.
+    body_a: usize,
+    body_b: usize,
+    anchor_a: [f32; 3],
+    anchor_b: [f32; 3],
+    /// Hinge or slide axis in A's frame.
+    axis_a: [f32; 3],
+    /// The same axis in B's frame.
+    axis_b: [f32; 3],
+    /// Orientation of B relative to A that prismatic and fixed joints hold.
+    reference: [f32; 4],
+    compliance: f32,
+}
+
+struct PlanarRow {
+    body: usize,
+    normal: [f32; 3],
+    distance: f32,
+    reference: [f32; 4],
+    basis: [[f32; 3]; 2],
+}
+
+struct DiffPlane {
+    normal: [f32; 3],
+    d: f32,
+    material: Material,
+}
+
+/// Pose, velocity and inverse mass of a body during a step.
+struct State {
+    x: Vector,
+    q: Quaternion,
+    v: Vector,
+    w: Vector,
+    /// Inverse mass and inverse inertia in the body frame, for dynamic bodies.
+    inverse: Option<(Tensor, Vector)>,
+}
+
+/// A contact point found at the start of a step. The surface points are
+/// fixed in the frames of the two bodies and re-evaluated every substep.
+struct Contact {
+    body_a: usize,
+    body_b: usize,
+    /// Unit normal from A to B.
+    normal: Vector,
+    local_a: Vector,
+    local_b: Vector,
+    friction: Tensor,
+    restitution: Tensor,
+    compliance: Tensor,
+    /// Normal multiplier of the current substep, if the contact pushed.
+    lambda: Option<Tensor>,
+    /// Normal velocity before the positions were projected.
+    approach_speed: Option<Tensor>,
+}
+
+/// A [`PhysicsSim`] whose steps are recorded as tensor operations.
+pub struct DiffSim {
+    /// Spheres, boxes, cylinders and capsules, in [`BodyHandle`] order.
+    pub bodies: Vec<DiffBody>,
+    /// Gravitational acceleration.
+    pub gravity: Vector,
+    joints: Vec<DiffJoint>,
+    planar: Vec<PlanarRow>,
+    planes: Vec<DiffPlane>,
+    /// Body and plane pairs that may touch.
+    plane_pairs: Vec<(usize, usize)>,
+    /// Pairs of spheres that may touch.
+    sphere_pairs: Vec<(usize, usize)>,
+    dt: f32,
+    substeps: usize,
+    restitution_threshold: f32,
+    contact_margin: f32,
+}
+
+impl DiffSim {
+    /// Copy the bodies, joints and parameters of `sim` into new leaf tensors
+    /// in `tensors`, all of which collect gradients.
+    ///
+    /// Fails if `sim` holds something the differentiable step does not
+    /// model.
+    pub fn new(sim: &PhysicsSim, tensors: &mut HashMap<usize, Tensor>) -> Result<Self> {
+        // Removed bodies and joints keep their slots but take no part
+        let live = |handle: BodyHandle| !sim.is_removed(handle);
+        let live_joint = |joint: JointHandle| !sim.is_joint_removed(joint);
+        let hulls = (0..sim.hulls.len()).map(BodyHandle::Hull);
+        if hulls.chain((0..sim.compounds.len()).map(BodyHandle::Compound)).any(live) {
+            bail!("convex hulls and compounds are not differentiable");
+        }
+        let terrain = (0..sim.heightfields.len()).map(BodyHandle::Heightfield);
+        if terrain.chain((0..sim.meshes.len()).map(BodyHandle::Mesh)).any(live) {
+            bail!("contacts with heightfields and meshes are not differentiable");
+        }
+        if (0..sim.joints.len()).map(JointHandle::Distance).any(live_joint) {
+            bail!("distance joints are not differentiable");
+        }
+        let unsupported = sim
+            .revolute_joints
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| live_joint(JointHandle::Revolute(i)))
+            .map(|(_, j)| (j.limits(), j.control()))
+            .chain(
+                sim.prismatic_joints
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| live_joint(JointHandle::Prismatic(i)))
+                    .map(|(_, j)| (j.limits(), j.control())),
+            );
+        for (limits, control) in unsupported {
+            if limits.is_some() || !matches!(control, physics::JointControl::Off) {
+                bail!("joint limits and motors are not differentiable");
+            }
+        }
+        if sim.integrator != Integrator::SemiImplicitEuler {
+            bail!("only the semi-implicit Euler integrator is differentiable");
+        }
+        if sim.adaptive_step.is_some() {
+            bail!("adaptive stepping is not differentiable");
+        }
+        if !sim.articulations.is_empty() {
+            bail!("articulations are not differentiable");
+        }
+        if matches!(sim.contact_model, ContactModel::Soft(_)) {
+            bail!("soft contacts are not differentiable yet; use ContactModel::Hard");
+        }
+        let moving = (0..sim.spheres.len())
+            .map(BodyHandle::Sphere)
+            .chain((0..sim.boxes.len()).map(BodyHandle::Box))
+            .chain((0..sim.cylinders.len()).map(BodyHandle::Cylinder))
+            .chain((0..sim.capsules.len()).map(BodyHandle::Capsule));
+        let unloaded = (physics::Vec3::ZERO, physics::Vec3::ZERO);
+        if moving.filter(|&handle| live(handle)).any(|handle| sim.applied_force(handle) != unloaded) {
+            bail!("forces from apply_force and its siblings are not differentiable; set DiffBody::force instead");
+        }
+
+        let mut leaf = |value: f32| {
+            let mut tensor = Tensor::from_vec(vec![1], vec![value]);
+            tensor.set_requires_grad();
+            tensors.insert(tensor.id, tensor.clone());
+            tensor
+        };
+        let forces = &sim.params.forces;
+        let mut bodies = Vec::new();
+        let mut add = |handle: BodyHandle, pose: Pose, force: [f32; 2], material: &Material, shape: ContactShape| {
+            if !live(handle) {
+                return;
+            }
+            let (inv_mass, inv_inertia) = sim.inverse_mass(handle);
+            let dynamic = inv_mass > 0.0;
+            let inertia_scale = if dynamic {
+                [inv_inertia.x / inv_mass, inv_inertia.y / inv_mass, inv_inertia.z / inv_mass]
+            } else {
+                [0.0; 3]
+            };
+            let force = if dynamic { [force[0], 0.0, force[1]] } else { [0.0; 3] };
+            bodies.push(DiffBody {
+                handle,
+                position: pose.position.map(&mut leaf),
+                orientation: unit_quaternion(pose.orientation).map(&mut leaf),
+                linear_velocity: pose.velocity.map(&mut leaf),
+                angular_velocity: pose.angular_velocity.map(&mut leaf),
+                force: force.map(&mut leaf),
+                mass: leaf(pose.mass),
+                friction: leaf(material.friction),
+                restitution: leaf(material.restitution),
+                compliance: leaf(material.compliance),
+                dynamic,
+                inertia_scale,
+                shape,
+            });
+        };
+        for (i, s) in sim.spheres.iter().enumerate() {
+            let pose = Pose::new(s.pos, s.orientation, s.vel, s.angular_vel, s.mass);
+            let force = forces.get(i).copied().unwrap_or_default();
+            add(BodyHandle::Sphere(i), pose, force, &s.material, ContactShape::Sphere { radius: s.radius });
+        }
+        for (i, b) in sim.boxes.iter().enumerate() {
+            let pose = Pose::new(b.pos, b.orientation, b.vel, b.angular_vel, b.mass);
+            let force = forces.get(i).copied().unwrap_or_default();
+            add(BodyHandle::Box(i), pose, force, &b.material, ContactShape::Points(box_corners(b.half_extents)));
+        }
+        for (i, c) in sim.cylinders.iter().enumerate() {
+            let pose = Pose::new(c.pos, c.orientation, c.vel, c.angular_vel, c.mass);
+            let shape = ContactShape::Points(rim_points(c.radius, c.half_height));
+            add(BodyHandle::Cylinder(i), pose, [0.0; 2], &c.material, shape);
+        }
+        for (i, c) in sim.capsules.iter().enumerate() {
+            let pose = Pose::new(c.pos, c.orientation, c.vel, c.angular_vel, c.mass);
+            let shape = ContactShape::Capsule {
+                radius: c.radius,
+                half_height: c.half_height,
+            };
+            add(BodyHandle::Capsule(i), pose, [0.0; 2], &c.material, shape);
+        }
+        let gravity = vec3(sim.params.gravity).map(&mut leaf);
+
+        let index = |handle: BodyHandle| -> Option<usize> {
+            match handle {
+                BodyHandle::Plane(_) | BodyHandle::Heightfield(_) | BodyHandle::Mesh(_) => Some(bodies.len()),
+                _ => bodies.iter().position(|body| body.handle == handle),
+            }
+        };
+        let mut joints = Vec::new();
+        let mut add_joint = |kind, handles: (u32, u32, u32, u32), anchors: ([f32; 3], [f32; 3]), axis: [f32; 3], reference, compliance: f32| {
+            let (type_a, a, type_b, b) = handles;
+            let Some(handle_a) = BodyHandle::from_type_code(type_a, a as usize) else { return };
+            let Some(handle_b) = BodyHandle::from_type_code(type_b, b as usize) else { return };
+            let (Some(body_a), Some(body_b)) = (index(handle_a), index(handle_b)) else { return };
+            if body_a == body_b {
+                return;
+            }
+            let axis = normalize_or_zero(axis);
+            joints.push(DiffJoint {
+                kind,
+                body_a,
+                body_b,
+                anchor_a: anchors.0,
+                anchor_b: anchors.1,
+                axis_a: axis,
+                axis_b: rotate(conjugate(reference), axis),
+                reference,
+                compliance: compliance + sim.joint_params.compliance,
+            });
+        };
+        for (i, j) in sim.revolute_joints.iter().enumerate() {
+            if !live_joint(JointHandle::Revolute(i)) {
+                continue;
+            }
+            let handles = (j.body_a_type, j.body_a, j.body_b_type, j.body_b);
+            let reference = unit_quaternion(j.reference_rotation);
+            let anchors = (vec3(j.anchor_a), vec3(j.anchor_b));
+            add_joint(JointKind::Revolute, handles, anchors, vec3(j.axis), reference, j.compliance);
+        }
+        for (i, j) in sim.prismatic_joints.iter().enumerate() {
+            if !live_joint(JointHandle::Prismatic(i)) {
+                continue;
+            }
+            let handles = (j.body_a_type, j.body_a, j.body_b_type, j.body_b);
+            let reference = unit_quaternion(j.reference_rotation);
+            let anchors = (vec3(j.anchor_a), vec3(j.anchor_b));
+            add_joint(JointKind::Prismatic, handles, anchors, vec3(j.axis), reference, j.compliance);
+        }
+        for (i, j) in sim.ball_joints.iter().enumerate() {
+            if !live_joint(JointHandle::Ball(i)) {
+                continue;
+            }
+            let handles = (j.body_a_type, j.body_a, j.body_b_type, j.body_b);
+            let anchors = (vec3(j.anchor_a), vec3(j.anchor_b));
+            add_joint(JointKind::Ball, handles, anchors, [0.0; 3], IDENTITY, j.compliance);
+        }
+        for (i, j) in sim.fixed_joints.iter().enumerate() {
+            if !live_joint(JointHandle::Fixed(i)) {
+                continue;
+            }
+            let handles = (j.body_a_type, j.body_a, j.body_b_type, j.body_b);
+            let reference = unit_quaternion(j.relative_rotation);
+            let anchors = (vec3(j.anchor_a), vec3(j.anchor_b));
+            add_joint(JointKind::Fixed, handles, anchors, [0.0; 3], reference, j.compliance);
+        }
+
+        let planar = sim
+            .planar_constraints
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| live_joint(JointHandle::Planar(i)))
+            .filter_map(|(_, constraint)| {
+                let body = bodies.iter().position(|body| body.handle == constraint.body)?;
+                let normal = normalize_or_zero(vec3(constraint.normal));
+                Some(PlanarRow {
+                    body,
+                    normal,
+                    distance: constraint.distance,
+                    reference: unit_quaternion(constraint.reference_orientation),
+                    basis: tangent_basis(normal),
+                })
+            })
+            .collect();
+
+        let planes: Vec<_> = sim
+            .planes
+            .iter()
+            .map(|plane| DiffPlane {
+                normal: normalize_or_zero(vec3(plane.normal)),
+                d: plane.d,
+                material: plane.material,
+            })
+            .collect();
+        let mut plane_pairs = Vec::new();
+        let mut sphere_pairs = Vec::new();
+        for (i, body) in bodies.iter().enumerate() {
+            plane_pairs.extend(
+                (0..planes.len())
+                    .filter(|&plane| body.dynamic && live(BodyHandle::Plane(plane)))
+                    .filter(|&plane| sim.can_collide(body.handle, BodyHandle::Plane(plane)))
+                    .map(|plane| (i, plane)),
+            );
+            for (j, other) in bodies.iter().enumerate().skip(i + 1) {
+                let spheres = matches!(body.shape, ContactShape::Sphere { .. })
+                    && matches!(other.shape, ContactShape::Sphere { .. });
+                if spheres && (body.dynamic || other.dynamic) && sim.can_collide(body.handle, other.handle) {
+                    sphere_pairs.push((i, j));
+                }
+            }
+        }
+
+        Ok(Self {
+            bodies,
+            gravity,
+            joints,
+            planar,
+            planes,
+            plane_pairs,
+            sphere_pairs,
+            dt: sim.params.dt,
+            substeps: match sim.solver {
+                SolverType::Xpbd { substeps } => substeps.max(1),
+                SolverType::SequentialImpulse => DEFAULT_SUBSTEPS,
+            },
+            restitution_threshold: sim.contact_params.restitution_threshold,
+            contact_margin: sim.contact_params.contact_margin,
+        })
+    }
+
+    /// The body copied from `handle`, if it is one this simulation models.
+    pub fn body(&self, handle: BodyHandle) -> Option<&DiffBody> {
+        self.bodies.iter().find(|body| body.handle == handle)
+    }
+
+    /// Mutable access to the body copied from `handle`.
+    pub fn body_mut(&mut self, handle: BodyHandle) -> Option<&mut DiffBody> {
+        self.bodies.iter_mut().find(|body| body.handle == handle)
+    }
+
+    /// Advance the simulation by one time step, recording every operation
+    /// on `recorder`.
+    pub fn step(&mut self, recorder: &mut impl Recorder, tensors: &mut HashMap<usize, Tensor>) {
+        let mut ops = Ops { recorder, tensors };
+        let substep = self.dt / self.substeps as f32;
+
+        let mut states: Vec<State> = self.bodies.iter().map(|body| body.state(&mut ops)).collect();
+        states.push(State {
+            x: ops.vector([0.0; 3]),
+            q: IDENTITY.map(|value| ops.constant(value)),
+            v: ops.vector([0.0; 3]),
+            w: ops.vector([0.0; 3]),
+            inverse: None,
+        });
+        let external: Vec<Option<Vector>> = self
+            .bodies
+            .iter()
+            .zip(&states)
+            .map(|(body, state)| {
+                let (inv_mass, _) = state.inverse.as_ref()?;
+                let push = ops.vscale(&body.force, inv_mass);
+                let acceleration = ops.vadd(&self.gravity, &push);
+                Some(ops.vscale_by(&acceleration, substep))
+            })
+            .collect();
+        let mut contacts = self.find_contacts(&mut ops, &states);
+
+        for _ in 0..self.substeps {
+            self.substep(&mut ops, &mut states, &external, &mut contacts, substep);
+        }
+
+        for (body, state) in self.bodies.iter_mut().zip(states) {
+            body.position = state.x;
+            body.orientation = state.q;
+            body.linear_velocity = state.v;
+            body.angular_velocity = state.w;
+        }
+    }
+
+    /// Contacts of the bodies in `states` within the contact margin.
+    fn find_contacts<R: Recorder>(&self, ops: &mut Ops<'_, R>, states: &[State]) -> Vec<Contact> {
+        let mut contacts = Vec::new();
+        let static_body = states.len() - 1;
+        for &(i, plane_index) in &self.plane_pairs {
+            let (body, state, plane) = (&self.bodies[i], &states[i], &self.planes[plane_index]);
+            let normal = ops.vector(plane.normal);
+            // Surface points of the body in its own frame
+            let locals: Vec<Vector> = match body.shape {
+                ContactShape::Sphere { radius } => {
+                    let reach = ops.vector(scale(plane.normal, -radius));
+                    vec![ops.rotate_inverse(&state.q, &reach)]
+                }
+                ContactShape::Capsule { radius, half_height } => {
+                    let reach = ops.vector(scale(plane.normal, -radius));
+                    let reach = ops.rotate_inverse(&state.q, &reach);
+                    [half_height, -half_height]
+                        .into_iter()
+                        .map(|end| {
+                            let end = ops.vector([0.0, end, 0.0]);
+                            ops.vadd(&end, &reach)
+                        })
+                        .collect()
+                }
+                ContactShape::Points(ref points) => points.iter().map(|&point| ops.vector(point)).collect(),
+            };
+            for local_a in locals {
+                let offset = ops.rotate(&state.q, &local_a);
+                let point = ops.vadd(&state.x, &offset);
+                let height = ops.dot(&normal, &point);
+                let gap = ops.add_constant(&height, plane.d);
+                if value(&gap) >= self.contact_margin {
+                    continue;
+                }
+                let drop = ops.vscale(&normal, &gap);
+                contacts.push(Contact {
+                    body_a: i,
+                    body_b: static_body,
+                    normal: ops.vector(scale(plane.normal, -1.0)),
+                    local_a,
+                    local_b: ops.vsub(&point, &drop),
+                    friction: ops.combine(&body.friction, plane.material.friction),
+                    restitution: ops.combine(&body.restitution, plane.material.restitution),
+                    compliance: ops.add_constant(&body.compliance, plane.material.compliance),
+                    lambda: None,
+                    approach_speed: None,
+                });
+            }
+        }
+        for &(i, j) in &self.sphere_pairs {
+            let (ContactShape::Sphere { radius: radius_a }, ContactShape::Sphere { radius: radius_b }) =
+                (&self.bodies[i].shape, &self.bodies[j].shape)
+            else {
+                continue;
+            };
+            let (a, b) = (&states[i], &states[j]);
+            let between = ops.vsub(&b.x, &a.x);
+            let distance_squared = ops.dot(&between, &between);
+            let reach = radius_a + radius_b + self.contact_margin;
+            if value(&distance_squared) >= reach * reach || value(&distance_squared) <= f32::EPSILON {
+                continue;
+            }
+            let distance = ops.sqrt(&distance_squared);
+            let normal = ops.vdiv(&between, &distance);
+            let reach_a = ops.vscale_by(&normal, *radius_a);
+            let reach_b = ops.vscale_by(&normal, -radius_b);
+            let friction = ops.mul(&self.bodies[i].friction, &self.bodies[j].friction);
+            let restitution = ops.mul(&self.bodies[i].restitution, &self.bodies[j].restitution);
+            contacts.push(Contact {
+                body_a: i,
+                body_b: j,
+                local_a: ops.rotate_inverse(&a.q, &reach_a),
+                local_b: ops.rotate_inverse(&b.q, &reach_b),
+                normal,
+                friction: ops.sqrt_or_zero(&friction),
+                restitution: ops.sqrt_or_zero(&restitution),
+                compliance: ops.add(&self.bodies[i].compliance, &self.bodies[j].compliance),
+                lambda: None,
+                approach_speed: None,
+            });
+        }
+        contacts
+    }
+
+    fn substep<R: Recorder>(
+        &self,
+        ops: &mut Ops<'_, R>,
+        states: &mut [State],
+        external: &[Option<Vector>],
+        contacts: &mut [Contact],
+        substep: f32,
+    ) {
+        let previous: Vec<(Vector, Quaternion)> = states.iter().map(|s| (s.x.clone(), s.q.clone())).collect();
+        for (state, dv) in states.iter_mut().zip(external) {
+            if let Some(dv) = dv {
+                state.v = ops.vadd(&state.v, dv);
+            }
+            let step = ops.vscale_by(&state.v, substep);
+            state.x = ops.vadd(&state.x, &step);
+            let turn = ops.vscale_by(&state.w, 0.5 * substep);
+            state.q = ops.turn(&state.q, &turn);
+        }
+        let predicted: Vec<(Vector, Quaternion)> = states.iter().map(|s| (s.x.clone(), s.q.clone())).collect();
+
+        for contact in contacts.iter_mut() {
+            let (a, b) = (&states[contact.body_a], &states[contact.body_b]);
+            let r_a = ops.rotate(&a.q, &contact.local_a);
+            let r_b = ops.rotate(&b.q, &contact.local_b);
+            let velocity_a = ops.velocity_at(a, &r_a);
+            let velocity_b = ops.velocity_at(b, &r_b);
+            let relative = ops.vsub(&velocity_b, &velocity_a);
+            contact.approach_speed = Some(ops.dot(&contact.normal, &relative));
+            contact.lambda = None;
+        }
+
+        self.solve_joint_positions(ops, states, substep);
+        self.solve_contact_positions(ops, states, contacts, &previous, substep);
+        self.project_planar_positions(ops, states);
+
+        for (state, (position, orientation)) in states.iter_mut().zip(&predicted) {
+            if state.inverse.is_none() {
+                continue;
+            }
+            let moved = ops.vsub(&state.x, position);
+            let dv = ops.vscale_by(&moved, 1.0 / substep);
+            state.v = ops.vadd(&state.v, &dv);
+            let inverse = conjugate_tensors(ops, orientation);
+            let delta = ops.qmul(&state.q, &inverse);
+            // The shorter way round, as a rotation vector of twice the
+            // vector part
+            let factor = (if value(&delta[3]) < 0.0 { -2.0 } else { 2.0 }) / substep;
+            let dw = [0, 1, 2].map(|k| ops.scale(&delta[k], factor));
+            state.w = ops.vadd(&state.w, &dw);
+        }
+
+        self.solve_contact_velocities(ops, states, contacts, substep);
+        self.solve_planar_velocities(ops, states);
+    }
+
+    fn solve_joint_positions<R: Recorder>(&self, ops: &mut Ops<'_, R>, states: &mut [State], substep: f32) {
+        for joint in &self.joints {
+            let (a, b) = pair_mut(states, joint.body_a, joint.body_b);
+            let alpha = ops.constant(joint.compliance.max(0.0) / (substep * substep));
+            match joint.kind {
+                JointKind::Ball => {}
+                JointKind::Revolute => {
+                    let axis_a = ops.vector(joint.axis_a);
+                    let axis_a = ops.rotate(&a.q, &axis_a);
+                    let axis_b = ops.vector(joint.axis_b);
+                    let axis_b = ops.rotate(&b.q, &axis_b);
+                    let error = ops.cross(&axis_a, &axis_b);
+                    ops.correct_rotation(a, b, &error, &alpha);
+                }
+                JointKind::Prismatic | JointKind::Fixed => {
+                    let reference = joint.reference.map(|value| ops.constant(value));
+                    let target = ops.qmul(&a.q, &reference);
+                    let current = conjugate_tensors(ops, &b.q);
+                    let delta = ops.qmul(&target, &current);
+                    // Rotation that takes B to the target, the shorter way
+                    // round, reversed
+                    let factor = if value(&delta[3]) < 0.0 { 2.0 } else { -2.0 };
+                    let error = [0, 1, 2].map(|k| ops.scale(&delta[k], factor));
+                    ops.correct_rotation(a, b, &error, &alpha);
+                }
+            }
+
+            let anchor_a = ops.vector(joint.anchor_a);
+            let r_a = ops.rotate(&a.q, &anchor_a);
+            let anchor_b = ops.vector(joint.anchor_b);
+            let r_b = ops.rotate(&b.q, &anchor_b);
+            let point_a = ops.vadd(&a.x, &r_a);
+            let point_b = ops.vadd(&b.x, &r_b);
+            let separation = ops.vsub(&point_b, &point_a);
+            if joint.kind == JointKind::Prismatic {
+                let axis = ops.vector(joint.axis_a);
+                let axis = ops.rotate(&a.q, &axis);
+                let along = ops.dot(&axis, &separation);
+                let along = ops.vscale(&axis, &along);
+                let off_axis = ops.vsub(&separation, &along);
+                let r_a = ops.vadd(&r_a, &separation);
+                ops.correct_position(a, b, &r_a, &r_b, &off_axis, &alpha);
+            } else {
+                ops.correct_position(a, b, &r_a, &r_b, &separation, &alpha);
+            }
+        }
+    }
+
+    fn solve_contact_positions<R: Recorder>(
+        &self,
+        ops: &mut Ops<'_, R>,
+        states: &mut [State],
+        contacts: &mut [Contact],
+        previous: &[(Vector, Quaternion)],
+        substep: f32,
+    ) {
+        let rigid = ops.constant(0.0);
+        for contact in contacts.iter_mut() {
+            let (a, b) = pair_mut(states, contact.body_a, contact.body_b);
+            let surface_a = ops.surface(a, &contact.local_a);
+            let surface_b = ops.surface(b, &contact.local_b);
+            let gap = ops.vsub(&surface_a, &surface_b);
+            let depth = ops.dot(&gap, &contact.normal);
+            if value(&depth) <= 0.0 {
+                continue;
+            }
+            let r_a = ops.rotate(&a.q, &contact.local_a);
+            let r_b = ops.rotate(&b.q, &contact.local_b);
+            let alpha = ops.scale(&contact.compliance, 1.0 / (substep * substep));
+            let push = ops.vscale(&contact.normal, &depth);
+            let push = push.map(|t| ops.neg(&t));
+            let Some(delta) = ops.correct_position(a, b, &r_a, &r_b, &push, &alpha) else {
+                continue;
+            };
+            let lambda = ops.neg(&delta);
+            contact.lambda = Some(lambda.clone());
+
+            // Static friction: undo the tangential slip of this substep if
+            // the normal force can hold it.
+            let moved_a = ops.moved(a, &previous[contact.body_a], &contact.local_a);
+            let moved_b = ops.moved(b, &previous[contact.body_b], &contact.local_b);
+            let slip = ops.vsub(&moved_a, &moved_b);
+            let normal_slip = ops.dot(&contact.normal, &slip);
+            let normal_slip = ops.vscale(&contact.normal, &normal_slip);
+            let slip = ops.vsub(&slip, &normal_slip);
+            let Some(length) = ops.length(&slip) else {
+                continue;
+            };
+            let r_a = ops.rotate(&a.q, &contact.local_a);
+            let r_b = ops.rotate(&b.q, &contact.local_b);
+            let direction = ops.vdiv(&slip, &length);
+            let inv_mass = ops.pair_inv_mass(a, b, &r_a, &r_b, &direction);
+            let hold = value(&contact.friction) * value(&lambda) * value(&inv_mass);
+            if value(&length) < hold {
+                let back = slip.map(|t| ops.neg(&t));
+                ops.correct_position(a, b, &r_a, &r_b, &back, &rigid);
+            }
+        }
+    }
+
+    fn solve_contact_velocities<R: Recorder>(
+        &self,
+        ops: &mut Ops<'_, R>,
+        states: &mut [State],
+        contacts: &[Contact],
+        substep: f32,
+    ) {
+        for contact in contacts {
+            let Some(lambda) = &contact.lambda else {
+                continue;
+            };
+            let normal_impulse = ops.scale(lambda, 1.0 / substep);
+            let (a, b) = pair_mut(states, contact.body_a, contact.body_b);
+            let normal = &contact.normal;
+            let r_a = ops.rotate(&a.q, &contact.local_a);
+            let r_b = ops.rotate(&b.q, &contact.local_b);
+            let relative = ops.relative_velocity(a, b, &r_a, &r_b);
+            let normal_speed = ops.dot(normal, &relative);
+
+            // Dynamic friction, limited by the normal impulse of this substep.
+            let normal_part = ops.vscale(normal, &normal_speed);
+            let tangential = ops.vsub(&relative, &normal_part);
+            if let Some(speed) = ops.length(&tangential) {
+                let direction = ops.vdiv(&tangential, &speed);
+                let inv_mass = ops.pair_inv_mass(a, b, &r_a, &r_b, &direction);
+                if value(&inv_mass) > f32::EPSILON {
+                    let stopping = ops.div(&speed, &inv_mass);
+                    let limit = ops.mul(&contact.friction, &normal_impulse);
+                    let magnitude = ops.min(&stopping, &limit);
+                    let magnitude = ops.neg(&magnitude);
+                    let impulse = ops.vscale(&direction, &magnitude);
+                    ops.apply_pair_impulse(a, b, &impulse, &r_a, &r_b);
+                }
+            }
+
+            // Restitution replaces the separation speed the projection
+            // produced with the bounce of the incoming speed.
+            let Some(approach) = &contact.approach_speed else {
+                continue;
+            };
+            if value(approach) < 0.0 {
+                let target = if -value(approach) > self.restitution_threshold {
+                    let bounce = ops.mul(&contact.restitution, approach);
+                    ops.neg(&bounce)
+                } else {
+                    ops.constant(0.0)
+                };
+                let relative = ops.relative_velocity(a, b, &r_a, &r_b);
+                let normal_speed = ops.dot(normal, &relative);
+                let inv_mass = ops.pair_inv_mass(a, b, &r_a, &r_b, normal);
+                if value(&inv_mass) > f32::EPSILON {
+                    let change = ops.sub(&target, &normal_speed);
+                    let magnitude = ops.div(&change, &inv_mass);
+                    let impulse = ops.vscale(normal, &magnitude);
+                    ops.apply_pair_impulse(a, b, &impulse, &r_a, &r_b);
+                }
+            }
+        }
+    }
+
+    /// Move bodies with planar constraints back onto their planes and
+    /// remove any rotation out of the plane.
+    fn project_planar_positions<R: Recorder>(&self, ops: &mut Ops<'_, R>, states: &mut [State]) {
+        for row in &self.planar {
+            let state = &mut states[row.body];
+            if state.inverse.is_none() {
+                continue;
+            }
+            let normal = ops.vector(row.normal);
+            let height = ops.dot(&normal, &state.x);
+            let off = ops.add_constant(&height, -row.distance);
+            let back = ops.vscale(&normal, &off);
+            state.x = ops.vsub(&state.x, &back);
+
+            // Keep only the twist about the normal relative to the reference
+            let reference = row.reference.map(|value| ops.constant(value));
+            let inverse = conjugate_tensors(ops, &reference);
+            let relative = ops.qmul(&state.q, &inverse);
+            let [x, y, z, w] = relative;
+            let along = ops.dot(&normal, &[x, y, z]);
+            let [tx, ty, tz] = ops.vscale(&normal, &along);
+            let twist = [tx, ty, tz, w];
+            let twist = if value(&ops.dot4(&twist, &twist)) > f32::EPSILON {
+                ops.normalize(&twist)
+            } else {
+                IDENTITY.map(|value| ops.constant(value))
+            };
+            state.q = ops.qmul(&twist, &reference);
+        }
+    }
+
+    /// Remove the velocity components that planar constraints forbid.
+    fn solve_planar_velocities<R: Recorder>(&self, ops: &mut Ops<'_, R>, states: &mut [State]) {
+        for row in &self.planar {
+            let state = &mut states[row.body];
+            if state.inverse.is_none() {
+                continue;
+            }
+            let normal = ops.vector(row.normal);
+            let out_of_plane = ops.dot(&normal, &state.v);
+            let out_of_plane = ops.vscale(&normal, &out_of_plane);
+            state.v = ops.vsub(&state.v, &out_of_plane);
+
+            let basis = row.basis.map(|axis| ops.vector(axis));
+            let error = [0, 1].map(|i| ops.dot(&basis[i], &state.w));
+            let turned = [0, 1].map(|j| ops.inv_inertia_world(state, &basis[j]));
+            let k = [0, 1].map(|i| [0, 1].map(|j| ops.dot(&basis[i], &turned[j])));
+            let first = ops.mul(&k[0][0], &k[1][1]);
+            let second = ops.mul(&k[0][1], &k[1][0]);
+            let determinant = ops.sub(&first, &second);
+            if value(&determinant).abs() <= f32::EPSILON {
+                continue;
+            }
+            // lambda = -K⁻¹ error
+            let terms = [(&k[1][1], &k[0][1]), (&k[0][0], &k[1][0])];
+            let lambda: [Tensor; 2] = [0, 1].map(|i| {
+                let (own, cross) = terms[i];
+                let direct = ops.mul(own, &error[i]);
+                let coupled = ops.mul(cross, &error[1 - i]);
+                let solved = ops.sub(&coupled, &direct);
+                ops.div(&solved, &determinant)
+            });
+            let first = ops.vscale(&basis[0], &lambda[0]);
+            let second = ops.vscale(&basis[1], &lambda[1]);
+            let impulse = ops.vadd(&first, &second);
+            let dw = ops.inv_inertia_world(state, &impulse);
+            state.w = ops.vadd(&state.w, &dw);
+        }
+    }
+}
+
+impl DiffBody {
+    /// Solver state at the start of a step, with the inverse mass and
+    /// inertia derived from the mass tensor.
+    fn state<R: Recorder>(&self, ops: &mut Ops<'_, R>) -> State {
+        let inverse = self.dynamic.then(|| {
+            let one = ops.constant(1.0);
+            let inv_mass = ops.div(&one, &self.mass);
+            let inv_inertia = self.inertia_scale.map(|scale| ops.scale(&inv_mass, scale));
+            (inv_mass, inv_inertia)
+        });
+        State {
+            x: self.position.clone(),
+            q: self.orientation.clone(),
+            v: self.linear_velocity.clone(),
+            w: self.angular_velocity.clone(),
+            inverse,
+        }
+    }
+}
+
+/// Gradient collected in `tensor` by the last backward pass, or zero if
+/// the loss does not depend on it.
+pub fn gradient(tensor: &Tensor, tensors: &HashMap<usize, Tensor>) -> f32 {
+    tensors
+        .get(&tensor.id)
+        .and_then(|tensor| tensor.grad.as_ref())
+        .map_or(0.0, |grad| grad[0])
+}
+
+/// Scalar operations on tensors of shape `[1]`, recorded on `recorder`.
+struct Ops<'a, R: Recorder> {
+    recorder: &'a mut R,
+    tensors: &'a mut HashMap<usize, Tensor>,
+}
+
+impl<R: Recorder> Ops<'_, R> {
+    fn constant(&mut self, value: f32) -> Tensor {
+        let tensor = Tensor::from_vec(vec![1], vec![value]);
+        self.tensors.insert(tensor.id, tensor.clone());
+        tensor
+    }
+
+    fn add(&mut self, a: &Tensor, b: &Tensor) -> Tensor {
+        a.add(b, self.recorder, self.tensors)
+    }
+
+    fn add_constant(&mut self, a: &Tensor, b: f32) -> Tensor {
+        let b = self.constant(b);
+        self.add(a, &b)
+    }
+
+    fn sub(&mut self, a: &Tensor, b: &Tensor) -> Tensor {
+        a.sub(b, self.recorder, self.tensors)
+    }
+
+    fn mul(&mut self, a: &Tensor, b: &Tensor) -> Tensor {
+        a.mul(b, self.recorder, self.tensors)
+    }
+
+    fn div(&mut self, a: &Tensor, b: &Tensor) -> Tensor {
+        a.div(b, self.recorder, self.tensors)
+    }
+
+    fn scale(&mut self, a: &Tensor, k: f32) -> Tensor {
+        a.mul_scalar(k, self.recorder, self.tensors)
+    }
+
+    fn neg(&mut self, a: &Tensor) -> Tensor {
+        a.neg(self.recorder, self.tensors)
+    }
+
+    fn min(&mut self, a: &Tensor, b: &Tensor) -> Tensor {
+        a.min(b, self.recorder, self.tensors)
+    }
+
+    fn sqrt(&mut self, a: &Tensor) -> Tensor {
+        a.sqrt(self.recorder, self.tensors)
+    }
+
+    /// Square root that is zero, with zero gradient, at zero.
+    fn sqrt_or_zero(&mut self, a: &Tensor) -> Tensor {
+        if value(a) > 0.0 {
+            self.sqrt(a)
+        } else {
+            self.constant(0.0)
+        }
+    }
+
+    /// Geometric mean of a material coefficient and a constant one, as the
+    /// engine combines friction and restitution.
+    fn combine(&mut self, a: &Tensor, b: f32) -> Tensor {
+        let product = self.scale(a, b);
+        self.sqrt_or_zero(&product)
+    }
+
+    fn vector(&mut self, v: [f32; 3]) -> Vector {
+        v.map(|value| self.constant(value))
+    }
+
+    fn vadd(&mut self, a: &Vector, b: &Vector) -> Vector {
+        std::array::from_fn(|i| self.add(&a[i], &b[i]))
+    }
+
+    fn vsub(&mut self, a: &Vector, b: &Vector) -> Vector {
+        std::array::from_fn(|i| self.sub(&a[i], &b[i]))
+    }
+
+    fn vscale(&mut self, v: &Vector, s: &Tensor) -> Vector {
+        std::array::from_fn(|i| self.mul(&v[i], s))
+    }
+
+    fn vscale_by(&mut self, v: &Vector, k: f32) -> Vector {
+        std::array::from_fn(|i| self.scale(&v[i], k))
+    }
+
+    fn vdiv(&mut self, v: &Vector, s: &Tensor) -> Vector {
+        std::array::from_fn(|i| self.div(&v[i], s))
+    }
+
+    fn sum(&mut self, terms: &[Tensor]) -> Tensor {
+        let mut total = terms[0].clone();
+        for term in &terms[1..] {
+            total = self.add(&total, term);
+        }
+        total
+    }
+
+    fn dot(&mut self, a: &Vector, b: &Vector) -> Tensor {
+        let products: Vec<_> = (0..3).map(|i| self.mul(&a[i], &b[i])).collect();
+        self.sum(&products)
+    }
+
+    fn dot4(&mut self, a: &Quaternion, b: &Quaternion) -> Tensor {
+        let products: Vec<_> = (0..4).map(|i| self.mul(&a[i], &b[i])).collect();
+        self.sum(&products)
+    }
+
+    fn cross(&mut self, a: &Vector, b: &Vector) -> Vector {
+        std::array::from_fn(|i| {
+            let (j, k) = ((i + 1) % 3, (i + 2) % 3);
+            let first = self.mul(&a[j], &b[k]);
+            let second = self.mul(&a[k], &b[j]);
+            self.sub(&first, &second)
+        })
+    }
+
+    /// Length of `v`, or `None` if it is too short to give a direction.
+    fn length(&mut self, v: &Vector) -> Option<Tensor> {
+        let squared = self.dot(v, v);
+        if value(&squared).sqrt() <= f32::EPSILON {
+            return None;
+        }
+        Some(self.sqrt(&squared))
+    }
+
+    fn qmul(&mut self, a: &Quaternion, b: &Quaternion) -> Quaternion {
+        // Signs and factor indices of the Hamilton product, per component
+        const TERMS: [[(f32, usize, usize); 4]; 4] = [
+            [(1.0, 3, 0), (1.0, 0, 3), (1.0, 1, 2), (-1.0, 2, 1)],
+            [(1.0, 3, 1), (-1.0, 0, 2), (1.0, 1, 3), (1.0, 2, 0)],
+            [(1.0, 3, 2), (1.0, 0, 1), (-1.0, 1, 0), (1.0, 2, 3)],
+            [(1.0, 3, 3), (-1.0, 0, 0), (-1.0, 1, 1), (-1.0, 2, 2)],
+        ];
+        std::array::from_fn(|i| {
+            let products: Vec<_> = TERMS[i]
+                .iter()
+                .map(|&(sign, j, k)| {
+                    let product = self.mul(&a[j], &b[k]);
+                    if sign < 0.0 {
+                        self.neg(&product)
+                    } else {
+                        product
+                    }
+                })
+                .collect();
+            self.sum(&products)
+        })
+    }
+
+    fn normalize(&mut self, q: &Quaternion) -> Quaternion {
+        let squared = self.dot4(q, q);
+        let length = self.sqrt(&squared);
+        std::array::from_fn(|i| self.div(&q[i], &length))
+    }
+
+    /// `q` turned by the rotation vector `2 · half`, to first order.
+    fn turn(&mut self, q: &Quaternion, half: &Vector) -> Quaternion {
+        let zero = self.constant(0.0);
+        let rotation = [half[0].clone(), half[1].clone(), half[2].clone(), zero];
+        let delta = self.qmul(&rotation, q);
+        let turned = std::array::from_fn(|i| self.add(&q[i], &delta[i]));
+        self.normalize(&turned)
+    }
+
+    fn rotate(&mut self, q: &Quaternion, v: &Vector) -> Vector {
+        let axis = [q[0].clone(), q[1].clone(), q[2].clone()];
+        let t = self.cross(&axis, v);
+        let t = self.vscale_by(&t, 2.0);
+        let along = self.vscale(&t, &q[3]);
+        let around = self.cross(&axis, &t);
+        let turned = self.vadd(v, &along);
+        self.vadd(&turned, &around)
+    }
+
+    fn rotate_inverse(&mut self, q: &Quaternion, v: &Vector) -> Vector {
+        let inverse = conjugate_tensors(self, q);
+        self.rotate(&inverse, v)
+    }
+
+    /// World position of the point at `local` in the frame of `state`.
+    fn surface(&mut self, state: &State, local: &Vector) -> Vector {
+        let offset = self.rotate(&state.q, local);
+        self.vadd(&state.x, &offset)
+    }
+
+    /// How far the point at `local` in the frame of `state` moved since the
+    /// body had the pose `previous`.
+    fn moved(&mut self, state: &State, previous: &(Vector, Quaternion), local: &Vector) -> Vector {
+        let now = self.surface(state, local);
+        let offset = self.rotate(&previous.1, local);
+        let before = self.vadd(&previous.0, &offset);
+        self.vsub(&now, &before)
+    }
+
+    /// `v` multiplied by the world-space inverse inertia of `state`.
+    fn inv_inertia_world(&mut self, state: &State, v: &Vector) -> Vector {
+        let Some((_, inv_inertia)) = &state.inverse else {
+            return self.vector([0.0; 3]);
+        };
+        let local = self.rotate_inverse(&state.q, v);
+        let scaled = std::array::from_fn(|i| self.mul(&local[i], &inv_inertia[i]));
+        self.rotate(&state.q, &scaled)
+    }
+
+    fn velocity_at(&mut self, state: &State, r: &Vector) -> Vector {
+        let spin = self.cross(&state.w, r);
+        self.vadd(&state.v, &spin)
+    }
+
+    fn relative_velocity(&mut self, a: &State, b: &State, r_a: &Vector, r_b: &Vector) -> Vector {
+        let velocity_a = self.velocity_at(a, r_a);
+        let velocity_b = self.velocity_at(b, r_b);
+        self.vsub(&velocity_b, &velocity_a)
+    }
+
+    /// Inverse mass of `state` along `direction` at the point `r`.
+    fn effective_inv_mass(&mut self, state: &State, r: &Vector, direction: &Vector) -> Tensor {
+        let Some((inv_mass, _)) = &state.inverse else {
+            return self.constant(0.0);
+        };
+        let lever = self.cross(r, direction);
+        let turned = self.inv_inertia_world(state, &lever);
+        let angular = self.dot(&lever, &turned);
+        self.add(inv_mass, &angular)
+    }
+
+    fn pair_inv_mass(&mut self, a: &State, b: &State, r_a: &Vector, r_b: &Vector, direction: &Vector) -> Tensor {
+        let inv_mass_a = self.effective_inv_mass(a, r_a, direction);
+        let inv_mass_b = self.effective_inv_mass(b, r_b, direction);
+        self.add(&inv_mass_a, &inv_mass_b)
+    }
+
+    fn apply_impulse(&mut self, state: &mut State, impulse: &Vector, r: &Vector) {
+        let Some((inv_mass, _)) = &state.inverse else {
+            return;
+        };
+        let dv = self.vscale(impulse, inv_mass);
+        state.v = self.vadd(&state.v, &dv);
+        let torque = self.cross(r, impulse);
+        let dw = self.inv_inertia_world(state, &torque);
+        state.w = self.vadd(&state.w, &dw);
+    }
+
+    /// Apply `impulse` to B at `r_b` and its opposite to A at `r_a`.
+    fn apply_pair_impulse(&mut self, a: &mut State, b: &mut State, impulse: &Vector, r_a: &Vector, r_b: &Vector) {
+        let opposite = impulse.clone().map(|t| self.neg(&t));
+        self.apply_impulse(a, &opposite, r_a);
+        self.apply_impulse(b, impulse, r_b);
+    }
+
+    /// Move the body as if `impulse` had acted at `r` for one unit of time.
+    fn apply_position_impulse(&mut self, state: &mut State, impulse: &Vector, r: &Vector) {
+        let Some((inv_mass, _)) = &state.inverse else {
+            return;
+        };
+        let dx = self.vscale(impulse, inv_mass);
+        state.x = self.vadd(&state.x, &dx);
+        let torque = self.cross(r, impulse);
+        self.apply_angular_position_impulse(state, &torque);
+    }
+
+    /// Rotate the body as if the angular `impulse` had acted for one unit
+    /// of time.
+    fn apply_angular_position_impulse(&mut self, state: &mut State, impulse: &Vector) {
+        if state.inverse.is_none() {
+            return;
+        }
+        let rotation = self.inv_inertia_world(state, impulse);
+        let half = self.vscale_by(&rotation, 0.5);
+        state.q = self.turn(&state.q, &half);
+    }
+
+    /// Move B's point at `r_b` and A's point at `r_a` so that `error`, the
+    /// offset of B's point from where it should be, shrinks. Returns the
+    /// multiplier change, or `None` if nothing moved.
+    fn correct_position(
+        &mut self,
+        a: &mut State,
+        b: &mut State,
+        r_a: &Vector,
+        r_b: &Vector,
+        error: &Vector,
+        alpha: &Tensor,
+    ) -> Option<Tensor> {
+        let length = self.length(error)?;
+        let direction = self.vdiv(error, &length);
+        let inv_mass = self.pair_inv_mass(a, b, r_a, r_b, &direction);
+        let delta = self.solve(&length, &inv_mass, alpha)?;
+        let impulse = self.vscale(&direction, &delta);
+        let opposite = impulse.clone().map(|t| self.neg(&t));
+        self.apply_position_impulse(a, &opposite, r_a);
+        self.apply_position_impulse(b, &impulse, r_b);
+        Some(delta)
+    }
+
+    /// Rotate B against A so that `error`, the rotation vector by which B
+    /// is turned too far, shrinks.
+    fn correct_rotation(&mut self, a: &mut State, b: &mut State, error: &Vector, alpha: &Tensor) {
+        let Some(angle) = self.length(error) else {
+            return;
+        };
+        let axis = self.vdiv(error, &angle);
+        let turned_a = self.inv_inertia_world(a, &axis);
+        let turned_b = self.inv_inertia_world(b, &axis);
+        let inv_mass_a = self.dot(&axis, &turned_a);
+        let inv_mass_b = self.dot(&axis, &turned_b);
+        let inv_mass = self.add(&inv_mass_a, &inv_mass_b);
+        let Some(delta) = self.solve(&angle, &inv_mass, alpha) else {
+            return;
+        };
+        let impulse = self.vscale(&axis, &delta);
+        let opposite = impulse.clone().map(|t| self.neg(&t));
+        self.apply_angular_position_impulse(a, &opposite);
+        self.apply_angular_position_impulse(b, &impulse);
+    }
+
+    /// Multiplier that removes an error of length `error` for a generalized
+    /// inverse mass `inv_mass` and compliance `alpha`, starting from zero.
+    fn solve(&mut self, error: &Tensor, inv_mass: &Tensor, alpha: &Tensor) -> Option<Tensor> {
+        let denominator = self.add(inv_mass, alpha);
+        if value(&denominator) <= f32::EPSILON {
+            return None;
+        }
+        let delta = self.div(error, &denominator);
+        Some(self.neg(&delta))
+    }
+}
+
+/// Initial pose, velocity and mass of a body.
+struct Pose {
+    position: [f32; 3],
+    orientation: [f32; 4],
+    velocity: [f32; 3],
+    angular_velocity: [f32; 3],
+    mass: f32,
+}
+
+impl Pose {
+    fn new(position: physics::Vec3, orientation: [f32; 4], velocity: physics::Vec3, angular_velocity: physics::Vec3, mass: f32) -> Self {
+        Self {
+            position: vec3(position),
+            orientation,
+            velocity: vec3(velocity),
+            angular_velocity: vec3(angular_velocity),
+            mass,
+        }
+    }
+}
+
+const IDENTITY: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+fn value(tensor: &Tensor) -> f32 {
+    tensor.data[0]
+}
+
+fn vec3(v: physics::Vec3) -> [f32; 3] {
+    [v.x, v.y, v.z]
+}
+
+fn scale(v: [f32; 3], k: f32) -> [f32; 3] {
+    v.map(|c| c * k)
+}
+
+fn normalize_or_zero(v: [f32; 3]) -> [f32; 3] {
+    let length = v.iter().map(|c| c * c).sum::<f32>().sqrt();
+    if length > 0.0 {
+        scale(v, 1.0 / length)
+    } else {
+        [0.0; 3]
+    }
+}
+
+/// `q` normalised, or the identity if it is zero.
+fn unit_quaternion(q: [f32; 4]) -> [f32; 4] {
+    let length = q.iter().map(|c| c * c).sum::<f32>().sqrt();
+    if length > 0.0 {
+        q.map(|c| c / length)
+    } else {
+        IDENTITY
+    }
+}
+
+fn conjugate(q: [f32; 4]) -> [f32; 4] {
+    [-q[0], -q[1], -q[2], q[3]]
+}
+
+fn conjugate_tensors<R: Recorder>(ops: &mut Ops<'_, R>, q: &Quaternion) -> Quaternion {
+    [ops.neg(&q[0]), ops.neg(&q[1]), ops.neg(&q[2]), q[3].clone()]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+/// `v` rotated by the unit quaternion `q`.
+fn rotate(q: [f32; 4], v: [f32; 3]) -> [f32; 3] {
+    let axis = [q[0], q[1], q[2]];
+    let t = scale(cross(axis, v), 2.0);
+    let around = cross(axis, t);
+    std::array::from_fn(|i| v[i] + q[3] * t[i] + around[i])
+}
+
+/// Deterministic orthonormal pair of vectors perpendicular to `normal`, as
+/// the engine picks them.
+fn tangent_basis(normal: [f32; 3]) -> [[f32; 3]; 2] {
+    let first = if normal[0].abs() >= 0.577_35 {
+        normalize_or_zero([normal[1], -normal[0], 0.0])
+    } else {
+        normalize_or_zero([0.0, normal[2], -normal[1]])
+    };
+    [first, cross(normal, first)]
+}
+
+fn box_corners(half_extents: physics::Vec3) -> Vec<[f32; 3]> {
+    (0..8)
+        .map(|corner| {
+            let sign = |bit: usize| if corner & bit == 0 { -1.0 } else { 1.0 };
+            [sign(1) * half_extents.x, sign(2) * half_extents.y, sign(4) * half_extents.z]
+        })
+        .collect()
+}
+
+fn rim_points(radius: f32, half_height: f32) -> Vec<[f32; 3]> {
+    [half_height, -half_height]
+        .into_iter()
+        .flat_map(|y| {
+            (0..RIM_POINTS).map(move |i| {
+                let angle = std::f32::consts::TAU * i as f32 / RIM_POINTS as f32;
+                [radius * angle.cos(), y, radius * angle.sin()]
+            })
+        })
+        .collect()
+}
+
+/// Mutable access to two distinct states at once.
+fn pair_mut(states: &mut [State], a: usize, b: usize) -> (&mut State, &mut State) {
+    debug_assert_ne!(a, b);
+    if a < b {
+        let (low, high) = states.split_at_mut(b);
+        (&mut low[a], &mut high[0])
+    } else {
+        let (low, high) = states.split_at_mut(a);
+        (&mut high[0], &mut low[b])
+    }
+}
@@ -4,6 +4,7 @@
 //! reinforcement learning helpers. It is **not** a full ML framework but rather
 //! contains only the pieces needed for the included examples and tests.
 
+pub mod diff_sim;
 pub mod graph;
 pub mod nn;
 pub mod optim;
@@ -17,3 +18,4 @@ pub mod tensor;
 pub use tensor::Tensor;
 pub use stick_balance::StickBalanceEnv;
 pub use env::Env;
+pub use diff_sim::DiffSim;
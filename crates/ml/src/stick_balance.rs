@@ -26,7 +26,7 @@ impl StickBalanceEnv {
         // Each sphere needs a force slot.
         sim.params.forces.push([0.0, 0.0]);
         // Constrain them with a distance joint so the stick maintains length.
-        sim.joints.push(Joint { body_a: 0, body_b: 1, rest_length: 1.0, _padding: 0 });
+        sim.joints.push(Joint { body_a: 0, body_b: 1, rest_length: 1.0, disable_collision: 0 });
         Self { sim, base_idx: 0, tip_idx: 1 }
     }
 
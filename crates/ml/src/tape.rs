@@ -35,7 +35,10 @@ impl Tape {
         grads.insert(loss.id, vec![1.0; loss.data.len()]);
 
         for node in self.nodes.iter().rev() {
-            let out_grad = grads.get(&node.out).unwrap().clone();
+            // Nodes whose output does not feed the loss get no gradient
+            let Some(out_grad) = grads.get(&node.out).cloned() else {
+                continue;
+            };
 
             match node.op {
                 EOp::Add => {
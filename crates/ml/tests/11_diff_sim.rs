@@ -0,0 +1,205 @@
+use ml::diff_sim::{gradient, DiffSim};
+use ml::graph::Graph;
+use ml::tape::Tape;
+use ml::tensor::Tensor;
+use physics::{BodyHandle, CartPole, CartPoleConfig, ContactModel, PhysicsSim, SoftContact, SolverType, Vec2, Vec3};
+use std::collections::HashMap;
+
+const EPS: f32 = 1e-2;
+
+/// Name of a leaf, its tensor at the start, and how to reach it in a fresh
+/// simulation.
+type Check<'a> = (&'static str, &'a Tensor, fn(&mut DiffSim) -> &mut Tensor);
+
+/// Runs `steps` steps of `sim` forward only, after `edit` has changed some
+/// of its leaves, and returns `loss` of the final state.
+fn forward(sim: &PhysicsSim, steps: usize, edit: impl FnOnce(&mut DiffSim), loss: impl Fn(&DiffSim) -> f32) -> f32 {
+    let mut tensors = HashMap::new();
+    let mut graph = Graph::new();
+    let mut diff = DiffSim::new(sim, &mut tensors).unwrap();
+    edit(&mut diff);
+    for _ in 0..steps {
+        diff.step(&mut graph, &mut tensors);
+    }
+    loss(&diff)
+}
+
+/// Central difference of `loss` with respect to the leaf `select` picks.
+fn finite_difference(
+    sim: &PhysicsSim,
+    steps: usize,
+    select: impl Fn(&mut DiffSim) -> &mut Tensor,
+    loss: impl Fn(&DiffSim) -> f32,
+) -> f32 {
+    let up = forward(sim, steps, |diff| select(diff).data[0] += EPS, &loss);
+    let down = forward(sim, steps, |diff| select(diff).data[0] -= EPS, &loss);
+    (up - down) / (2.0 * EPS)
+}
+
+fn assert_close(name: &str, analytic: f32, numeric: f32, tolerance: f32) {
+    let scale = analytic.abs().max(numeric.abs()).max(1.0);
+    assert!(
+        (analytic - numeric).abs() <= tolerance * scale,
+        "{name}: analytic {analytic} numeric {numeric}"
+    );
+}
+
+fn falling_sphere() -> PhysicsSim {
+    let mut sim = PhysicsSim::new();
+    sim.solver = SolverType::Xpbd { substeps: 4 };
+    sim.add_sphere(Vec3::new(0.0, 10.0, 0.0), Vec3::new(1.0, 2.0, 0.0), 0.5);
+    sim.set_force(0, [3.0, 0.0]);
+    sim
+}
+
+#[test]
+fn free_fall_follows_engine_and_finite_differences() {
+    let steps = 30;
+    let mut sim = falling_sphere();
+    let mut tensors = HashMap::new();
+    let mut tape = Tape::new();
+    let mut diff = DiffSim::new(&sim, &mut tensors).unwrap();
+    let start = diff.bodies[0].clone();
+    let gravity = diff.gravity.clone();
+    for _ in 0..steps {
+        diff.step(&mut tape, &mut tensors);
+        sim.step_cpu();
+    }
+
+    let body = &diff.bodies[0];
+    let engine = sim.spheres[0];
+    for (tensor, expected) in body.position.iter().zip([engine.pos.x, engine.pos.y, engine.pos.z]) {
+        assert!((tensor.data[0] - expected).abs() < 1e-3, "{} vs {expected}", tensor.data[0]);
+    }
+    assert!((body.linear_velocity[1].data[0] - engine.vel.y).abs() < 1e-3);
+
+    // Loss: height plus sideways travel of the final position
+    let height = &body.position[1];
+    let loss = height.add(&body.position[0], &mut tape, &mut tensors);
+    tape.backward(&loss, &mut tensors).unwrap();
+    let sim = falling_sphere();
+    let loss = |diff: &DiffSim| diff.bodies[0].position[0].data[0] + diff.bodies[0].position[1].data[0];
+    let time = steps as f32 * sim.params.dt;
+
+    assert_close("y0", gradient(&start.position[1], &tensors), 1.0, 1e-3);
+    assert_close("vy0", gradient(&start.linear_velocity[1], &tensors), time, 1e-3);
+    let checks: [Check<'_>; 5] = [
+        ("vx0", &start.linear_velocity[0], |d| &mut d.bodies[0].linear_velocity[0]),
+        ("force x", &start.force[0], |d| &mut d.bodies[0].force[0]),
+        ("mass", &start.mass, |d| &mut d.bodies[0].mass),
+        ("gravity y", &gravity[1], |d| &mut d.gravity[1]),
+        ("y0", &start.position[1], |d| &mut d.bodies[0].position[1]),
+    ];
+    for (name, leaf, select) in checks {
+        let numeric = finite_difference(&sim, steps, select, loss);
+        assert_close(name, gradient(leaf, &tensors), numeric, 1e-2);
+    }
+}
+
+#[test]
+fn sliding_box_friction_gradients_match_finite_differences() {
+    let mut sim = PhysicsSim::new();
+    sim.solver = SolverType::Xpbd { substeps: 4 };
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(0.0, 0.0));
+    let index = sim.add_box(Vec3::new(0.0, 0.25, 0.0), Vec3::new(0.5, 0.25, 0.5), Vec3::new(3.0, 0.0, 0.0));
+    sim.boxes[index].material.friction = 0.4;
+    let steps = 12;
+
+    let mut tensors = HashMap::new();
+    let mut tape = Tape::new();
+    let mut diff = DiffSim::new(&sim, &mut tensors).unwrap();
+    let start = diff.bodies[0].clone();
+    for _ in 0..steps {
+        diff.step(&mut tape, &mut tensors);
+    }
+    let travelled = diff.bodies[0].position[0].clone();
+    assert!(travelled.data[0] > 0.1, "the box slides forward");
+    assert!(diff.bodies[0].linear_velocity[0].data[0] < 3.0 - 0.3, "friction slows the box");
+    assert!((diff.bodies[0].position[1].data[0] - 0.25).abs() < 0.02, "the box rests on the plane");
+    tape.backward(&travelled, &mut tensors).unwrap();
+
+    let loss = |diff: &DiffSim| diff.bodies[0].position[0].data[0];
+    let friction = gradient(&start.friction, &tensors);
+    assert!(friction < 0.0, "more friction means less travel");
+    let checks: [Check<'_>; 3] = [
+        ("friction", &start.friction, |d| &mut d.bodies[0].friction),
+        ("vx0", &start.linear_velocity[0], |d| &mut d.bodies[0].linear_velocity[0]),
+        ("x0", &start.position[0], |d| &mut d.bodies[0].position[0]),
+    ];
+    for (name, leaf, select) in checks {
+        let numeric = finite_difference(&sim, steps, select, loss);
+        assert_close(name, gradient(leaf, &tensors), numeric, 5e-2);
+    }
+}
+
+fn swinging_cartpole() -> (PhysicsSim, CartPole) {
+    let mut sim = PhysicsSim::new();
+    sim.solver = SolverType::Xpbd { substeps: 8 };
+    let config = CartPoleConfig {
+        initial_angle: 0.3,
+        ..CartPoleConfig::default()
+    };
+    let cartpole = CartPole::new(&mut sim, Vec3::new(0.0, 0.0, 0.0), config);
+    sim.boxes[cartpole.cart_idx].vel = Vec3::new(0.5, 0.0, 0.0);
+    (sim, cartpole)
+}
+
+#[test]
+fn cartpole_follows_engine_and_finite_differences() {
+    let steps = 20;
+    let (mut sim, cartpole) = swinging_cartpole();
+    let pole = BodyHandle::Cylinder(cartpole.pole_idx);
+    let cart = BodyHandle::Box(cartpole.cart_idx);
+
+    let mut tensors = HashMap::new();
+    let mut tape = Tape::new();
+    let mut diff = DiffSim::new(&sim, &mut tensors).unwrap();
+    let start_pole = diff.body(pole).unwrap().clone();
+    let start_cart = diff.body(cart).unwrap().clone();
+    let gravity = diff.gravity.clone();
+    for _ in 0..steps {
+        diff.step(&mut tape, &mut tensors);
+        sim.step_cpu();
+    }
+
+    let engine = sim.cylinders[cartpole.pole_idx].pos;
+    let final_pole = diff.body(pole).unwrap();
+    assert!((final_pole.position[0].data[0] - engine.x).abs() < 1e-2, "{} vs {}", final_pole.position[0].data[0], engine.x);
+    assert!((final_pole.position[1].data[0] - engine.y).abs() < 1e-2, "{} vs {}", final_pole.position[1].data[0], engine.y);
+
+    let loss = final_pole.position[0].clone();
+    tape.backward(&loss, &mut tensors).unwrap();
+
+    let (sim, _) = swinging_cartpole();
+    let loss = |diff: &DiffSim| diff.body(pole).unwrap().position[0].data[0];
+    // Bodies come in handle order: the cart box, then the pole cylinder
+    assert_eq!(diff.bodies[0].handle, cart);
+    assert_eq!(diff.bodies[1].handle, pole);
+    let checks: [Check<'_>; 4] = [
+        ("cart vx", &start_cart.linear_velocity[0], |d| &mut d.bodies[0].linear_velocity[0]),
+        ("pole wz", &start_pole.angular_velocity[2], |d| &mut d.bodies[1].angular_velocity[2]),
+        ("gravity y", &gravity[1], |d| &mut d.gravity[1]),
+        ("pole mass", &start_pole.mass, |d| &mut d.bodies[1].mass),
+    ];
+    for (name, leaf, select) in checks {
+        let numeric = finite_difference(&sim, steps, select, loss);
+        assert_close(name, gradient(leaf, &tensors), numeric, 5e-2);
+    }
+    assert!(gradient(&start_cart.linear_velocity[0], &tensors) > 0.0, "a faster cart drags the pole along");
+}
+
+#[test]
+fn unsupported_features_are_rejected() {
+    // Differentiating a soft-contact scene through the hard contact path
+    // would give the gradients of a different system
+    let mut sim = falling_sphere();
+    sim.contact_model = ContactModel::Soft(SoftContact::default());
+    assert!(DiffSim::new(&sim, &mut HashMap::new()).is_err());
+
+    // Forces from the handle API would otherwise be dropped
+    let mut sim = falling_sphere();
+    sim.apply_torque(BodyHandle::Sphere(0), Vec3::new(0.0, 1.0, 0.0));
+    assert!(DiffSim::new(&sim, &mut HashMap::new()).is_err());
+    sim.clear_forces();
+    assert!(DiffSim::new(&sim, &mut HashMap::new()).is_ok());
+}
@@ -6,10 +6,10 @@
 //! crate.  These types are mainly used by the runtime and machine learning
 //! crates to build controllable creatures for simulation or training.
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use physics::{
     types::{Vec2, Vec3},
-    PhysicsSim,
+    ArticulationBase, ArticulationJoint, BodyHandle, PhysicsSim,
 };
 use serde::Deserialize;
 use std::collections::HashMap;
@@ -105,6 +105,20 @@ pub struct JointDef {
     pub rest_length: f32,
 }
 
+/// How [`Phenotype::into_sim_with`] simulates the joints of a creature.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JointModel {
+    /// Every body moves on its own and each joint is a distance constraint
+    /// that the solver enforces between them.
+    #[default]
+    Maximal,
+    /// Bodies connected by joints form a floating articulation rooted at
+    /// the first of them, and each joint becomes a ball joint about the
+    /// centre of the body nearer the root. Joint lengths then hold exactly,
+    /// but the joints must form a tree and cannot stretch.
+    Reduced,
+}
+
 /// Helper used during deserialization to populate missing velocity fields.
 fn zero_vec() -> [f32; 3] {
     [0.0, 0.0, 0.0]
@@ -138,13 +152,28 @@ impl Phenotype {
     /// Returns an error if any joint references a body that does not exist in
     /// this phenotype.
     pub fn into_sim(self) -> Result<PhysicsSim> {
+        self.into_sim_with(JointModel::Maximal)
+    }
+
+    /// Convert the description into a [`PhysicsSim`], simulating its joints
+    /// with `model`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any joint references a body that does not exist in
+    /// this phenotype. With [`JointModel::Reduced`] it is also an error for a
+    /// joint to attach a plane, for the joints to form a loop, or for a
+    /// joint's rest length to differ from the distance between its bodies.
+    pub fn into_sim_with(self, model: JointModel) -> Result<PhysicsSim> {
         let mut sim = PhysicsSim::new();
-        let mut map: HashMap<String, usize> = HashMap::new();
+        // Bodies in declaration order, with their index and handle
+        let mut map: HashMap<String, (usize, BodyHandle)> = HashMap::new();
+        let mut order = Vec::new();
         for body in self.bodies {
-            match body {
+            let (id, handle) = match body {
                 Body::Sphere { id, radius, pos, vel } => {
                     let idx = sim.add_sphere(Vec3::new(pos[0], pos[1], pos[2]), Vec3::new(vel[0], vel[1], vel[2]), radius);
-                    map.insert(id, idx);
+                    (id, BodyHandle::Sphere(idx))
                 }
                 Body::Box { id, half_extents, pos, vel } => {
                     let idx = sim.add_box(
@@ -152,7 +181,7 @@ impl Phenotype {
                         Vec3::new(half_extents[0], half_extents[1], half_extents[2]),
                         Vec3::new(vel[0], vel[1], vel[2]),
                     );
-                    map.insert(id, idx);
+                    (id, BodyHandle::Box(idx))
                 }
                 Body::Cylinder { id, radius, height, pos, vel } => {
                     let idx = sim.add_cylinder(
@@ -161,15 +190,19 @@ impl Phenotype {
                         height,
                         Vec3::new(vel[0], vel[1], vel[2]),
                     );
-                    map.insert(id, idx);
+                    (id, BodyHandle::Cylinder(idx))
                 }
                 Body::Plane { id, normal, d } => {
                     let idx = sim.add_plane(Vec3::new(normal[0], normal[1], normal[2]), d, Vec2::new(0.0, 0.0));
-                    map.insert(id, idx);
+                    (id, BodyHandle::Plane(idx))
                 }
-            }
+            };
+            let index = handle.index();
+            order.push(id.clone());
+            map.insert(id, (index, handle));
         }
 
+        let mut edges = Vec::with_capacity(self.joints.len());
         for joint in self.joints {
             let a = map
                 .get(&joint.body_a)
@@ -177,9 +210,67 @@ impl Phenotype {
             let b = map
                 .get(&joint.body_b)
                 .ok_or_else(|| anyhow::anyhow!("unknown body {}", joint.body_b))?;
-            sim.add_joint(*a as u32, *b as u32, joint.rest_length);
+            match model {
+                JointModel::Maximal => sim.add_joint(a.0 as u32, b.0 as u32, joint.rest_length),
+                JointModel::Reduced => edges.push((joint.body_a, joint.body_b, joint.rest_length)),
+            }
+        }
+        if model == JointModel::Reduced {
+            build_articulations(&mut sim, &order, &map, &edges)?;
         }
 
         Ok(sim)
     }
 }
+
+/// Turn every tree of bodies joined by `edges` into a floating
+/// articulation, rooted at the body declared first.
+fn build_articulations(
+    sim: &mut PhysicsSim,
+    order: &[String],
+    map: &HashMap<String, (usize, BodyHandle)>,
+    edges: &[(String, String, f32)],
+) -> Result<()> {
+    let centre = |sim: &PhysicsSim, id: &str| match map[id].1 {
+        BodyHandle::Sphere(i) => Ok(sim.spheres[i].pos),
+        BodyHandle::Box(i) => Ok(sim.boxes[i].pos),
+        BodyHandle::Cylinder(i) => Ok(sim.cylinders[i].pos),
+        _ => Err(anyhow::anyhow!("a reduced joint cannot attach the plane {id}")),
+    };
+    for (a, b, rest_length) in edges {
+        let distance = (centre(sim, a)? - centre(sim, b)?).length();
+        if (distance - rest_length).abs() > 1e-3 * rest_length.max(1.0) {
+            bail!("joint {a}-{b} has rest length {rest_length} but its bodies are {distance} apart");
+        }
+    }
+
+    // Link of every body placed so far, by articulation
+    let mut placed: HashMap<&str, (usize, usize)> = HashMap::new();
+    let mut used = vec![false; edges.len()];
+    for root in order {
+        let touches = |id: &str, edge: &(String, String, f32)| edge.0 == id || edge.1 == id;
+        if placed.contains_key(root.as_str()) || !edges.iter().any(|edge| touches(root, edge)) {
+            continue;
+        }
+        let articulation = sim.add_articulation(map[root].1, ArticulationBase::Floating);
+        placed.insert(root, (articulation, 0));
+        let mut queue = vec![root.as_str()];
+        while let Some(parent) = queue.pop() {
+            for (e, edge) in edges.iter().enumerate() {
+                if used[e] || !touches(parent, edge) {
+                    continue;
+                }
+                used[e] = true;
+                let child = if edge.0 == parent { edge.1.as_str() } else { edge.0.as_str() };
+                if placed.contains_key(child) {
+                    bail!("the joints through {child} form a loop, which reduced joints cannot describe");
+                }
+                let anchor = centre(sim, parent)?;
+                let link = sim.add_articulation_link(articulation, placed[parent].1, map[child].1, ArticulationJoint::Spherical { anchor });
+                placed.insert(child, (articulation, link));
+                queue.push(child);
+            }
+        }
+    }
+    Ok(())
+}
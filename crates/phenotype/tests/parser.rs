@@ -1,4 +1,4 @@
-use phenotype::Phenotype;
+use phenotype::{JointModel, Phenotype};
 use std::fs;
 
 #[test]
@@ -33,3 +33,43 @@ fn parse_plane_sphere() {
     assert_eq!(p.bodies.len(), 2);
     assert!(p.joints.is_empty());
 }
+
+#[test]
+fn reduced_chain_keeps_its_joint_lengths() {
+    let json = fs::read_to_string("tests/data/chain.json").unwrap();
+    let mut sim = Phenotype::from_str(&json).unwrap().into_sim_with(JointModel::Reduced).unwrap();
+    assert!(sim.joints.is_empty());
+    assert_eq!(sim.articulations.len(), 1);
+    assert_eq!(sim.articulations[0].link_count(), 3);
+    // Swing the end of the chain so the joints have to hold
+    sim.articulations[0].set_joint_velocities(1, &[0.0, 0.0, 2.0]);
+    sim.run_cpu(0.01, 100);
+    for pair in sim.spheres.windows(2) {
+        let length = (pair[0].pos - pair[1].pos).length();
+        assert!((length - 1.0).abs() < 1e-4, "joint length {length}");
+    }
+    assert!(sim.spheres[0].pos.y < 0.0, "the chain did not fall");
+}
+
+#[test]
+fn reduced_joints_reject_loops_and_stretch() {
+    let looped = r#"{
+        "bodies": [
+            {"id": "a", "shape": "sphere", "radius": 0.1, "pos": [0, 1, 0]},
+            {"id": "b", "shape": "sphere", "radius": 0.1, "pos": [1, 1, 0]},
+            {"id": "c", "shape": "sphere", "radius": 0.1, "pos": [0, 2, 0]}
+        ],
+        "joints": [
+            {"body_a": "a", "body_b": "b", "rest_length": 1.0},
+            {"body_a": "a", "body_b": "c", "rest_length": 1.0},
+            {"body_a": "b", "body_b": "c", "rest_length": 1.4142135}
+        ]
+    }"#;
+    let error = Phenotype::from_str(looped).unwrap().into_sim_with(JointModel::Reduced).err().unwrap();
+    assert!(error.to_string().contains("loop"), "{error}");
+    assert!(Phenotype::from_str(looped).unwrap().into_sim().is_ok());
+
+    let json = fs::read_to_string("tests/data/chain.json").unwrap().replace("\"rest_length\": 1.0}\n  ]", "\"rest_length\": 1.5}\n  ]");
+    let error = Phenotype::from_str(&json).unwrap().into_sim_with(JointModel::Reduced).err().unwrap();
+    assert!(error.to_string().contains("rest length"), "{error}");
+}
@@ -16,7 +16,7 @@ fn bench_scene_run(c: &mut Criterion) {
                     body_a: i - 1,
                     body_b: i,
                     rest_length: 1.0,
-                    _padding: 0,
+                    disable_collision: 0,
                 });
             }
             sim.run(0.01, 10).unwrap();
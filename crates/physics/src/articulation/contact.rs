@@ -0,0 +1,245 @@
+//! Contacts of articulation links, solved in joint space.
+//!
+//! A contact row pushes on a link through the joints between it and the
+//! root, so its effective mass comes from the inverse joint-space mass
+//! matrix instead of the link's own mass. Rows between a link and a rigid
+//! body add the body's usual effective mass, and impulses change the joint
+//! velocities of the articulation and the velocity of the body together.
+//! Penetration is removed with a velocity bias, since links have no
+//! separate position pass.
+
+use glam::Vec3;
+
+use super::dynamics::System;
+use super::spatial::Spatial;
+use crate::collision::{ManifoldCache, ManifoldKey};
+use crate::simulation::PhysicsSim;
+use crate::solver::{tangent_basis, SolverBodies};
+use crate::types::ContactParams;
+
+/// Tangential drift beyond which a friction anchor is considered broken.
+const MAX_ANCHOR_DRIFT: f32 = 0.05;
+
+/// One side of a contact row.
+enum Side {
+    /// A rigid body, by its index in [`SolverBodies::bodies`], and the
+    /// offset of the contact point from its centre.
+    Rigid(usize, Vec3),
+    /// A link, by articulation and link index.
+    Link(usize, usize),
+}
+
+/// Constraint along one direction at one contact point.
+struct Row {
+    /// Rigid bodies the row acts on, with the offset of the point and the
+    /// sign of the impulse they receive.
+    rigid: Vec<(usize, Vec3, f32)>,
+    /// Articulations the row acts on, with its joint-space direction and the
+    /// joint velocity change per unit impulse.
+    joints: Vec<(usize, Vec<f64>, Vec<f64>)>,
+    direction: Vec3,
+    mass: f32,
+    impulse: f32,
+}
+
+impl Row {
+    fn new(sides: [&Side; 2], position: Vec3, direction: Vec3, systems: &[System], bodies: &SolverBodies, impulse: f32) -> Self {
+        let mut row = Self {
+            rigid: Vec::new(),
+            joints: Vec::new(),
+            direction,
+            mass: 0.0,
+            impulse,
+        };
+        for (side, sign) in sides.into_iter().zip([-1.0_f32, 1.0]) {
+            match *side {
+                Side::Rigid(index, offset) => row.rigid.push((index, offset, sign)),
+                Side::Link(articulation, link) => {
+                    let system = &systems[articulation];
+                    let point = position.as_dvec3() - system.origin;
+                    let force = Spatial::force_at(point, direction.as_dvec3() * f64::from(sign));
+                    let slot = row.joints.iter().position(|(a, ..)| *a == articulation).unwrap_or_else(|| {
+                        row.joints.push((articulation, vec![0.0; system.dof_count()], Vec::new()));
+                        row.joints.len() - 1
+                    });
+                    system.generalized_force(link, force, &mut row.joints[slot].1);
+                }
+            }
+        }
+        let mut inv_mass: f64 = row
+            .rigid
+            .iter()
+            .map(|&(index, offset, _)| f64::from(bodies.bodies[index].effective_inv_mass(offset, direction)))
+            .sum();
+        for (articulation, jacobian, response) in &mut row.joints {
+            *response = systems[*articulation].response(jacobian);
+            inv_mass += dot(jacobian, response);
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        let mass = if inv_mass > 0.0 { (1.0 / inv_mass) as f32 } else { 0.0 };
+        row.mass = mass;
+        row
+    }
+
+    /// Relative velocity of the two sides along the row.
+    fn speed(&self, bodies: &SolverBodies, velocities: &[Vec<f64>]) -> f32 {
+        let rigid: f32 = self
+            .rigid
+            .iter()
+            .map(|&(index, offset, sign)| sign * bodies.bodies[index].velocity_at(offset).dot(self.direction))
+            .sum();
+        let joints: f64 = self.joints.iter().map(|(articulation, jacobian, _)| dot(jacobian, &velocities[*articulation])).sum();
+        #[allow(clippy::cast_possible_truncation)]
+        let joints = joints as f32;
+        rigid + joints
+    }
+
+    fn apply(&self, impulse: f32, bodies: &mut SolverBodies, velocities: &mut [Vec<f64>]) {
+        for &(index, offset, sign) in &self.rigid {
+            bodies.bodies[index].apply_impulse(self.direction * (sign * impulse), offset);
+        }
+        for (articulation, _, response) in &self.joints {
+            for (velocity, change) in velocities[*articulation].iter_mut().zip(response) {
+                *velocity += change * f64::from(impulse);
+            }
+        }
+    }
+}
+
+struct PointRows {
+    key: ManifoldKey,
+    index: usize,
+    friction: f32,
+    normal: Row,
+    tangents: [Row; 2],
+    /// Separation speed the normal row allows: positive while the surfaces
+    /// are still apart, negative to push overlapping ones out.
+    bias: f32,
+    /// Friction target velocities that pull the anchors back together.
+    anchor_bias: [f32; 2],
+    anchor_broken: bool,
+}
+
+/// Contact rows of every manifold that involves a link, for one step.
+pub(crate) struct LinkContactSolver {
+    points: Vec<PointRows>,
+}
+
+impl LinkContactSolver {
+    pub fn prepare(
+        sim: &PhysicsSim,
+        manifolds: &ManifoldCache,
+        bodies: &SolverBodies,
+        systems: &[System],
+        params: &ContactParams,
+        dt: f32,
+    ) -> Self {
+        let inv_dt = if dt > 0.0 { 1.0 / dt } else { 0.0 };
+        let mut points = Vec::new();
+        for (&key, manifold) in manifolds {
+            let normal: Vec3 = manifold.normal.into();
+            let tangents = tangent_basis(normal);
+            let (frame_a, frame_b) = (sim.body_frame(manifold.body_a), sim.body_frame(manifold.body_b));
+            for (index, point) in manifold.points.iter().enumerate() {
+                let position: Vec3 = point.position.into();
+                let side = |handle| {
+                    if let Some(&(articulation, link)) = sim.articulation_links.get(&handle) {
+                        Side::Link(articulation, link)
+                    } else {
+                        let index = bodies.index(handle);
+                        Side::Rigid(index, position - bodies.bodies[index].position)
+                    }
+                };
+                let sides = [side(manifold.body_a), side(manifold.body_b)];
+                let row = |direction, impulse| Row::new([&sides[0], &sides[1]], position, direction, systems, bodies, impulse);
+
+                let drift = frame_b.to_world(point.anchor_b.into()) - frame_a.to_world(point.anchor_a.into());
+                let tangential_drift = [drift.dot(tangents[0]), drift.dot(tangents[1])];
+                let anchor_broken = tangential_drift[0].hypot(tangential_drift[1]) > MAX_ANCHOR_DRIFT;
+                let anchor_bias = if anchor_broken {
+                    [0.0; 2]
+                } else {
+                    [-params.baumgarte * inv_dt * tangential_drift[0], -params.baumgarte * inv_dt * tangential_drift[1]]
+                };
+                let bias = if point.depth < 0.0 {
+                    -point.depth * inv_dt
+                } else {
+                    -(params.baumgarte * (point.depth - params.linear_slop).max(0.0)).min(params.max_correction) * inv_dt
+                };
+                let warm = |impulse: f32| if params.warm_starting { impulse } else { 0.0 };
+                points.push(PointRows {
+                    key,
+                    index,
+                    friction: manifold.friction,
+                    normal: row(normal, warm(point.normal_impulse)),
+                    tangents: [
+                        row(tangents[0], warm(point.tangent_impulse[0])),
+                        row(tangents[1], warm(point.tangent_impulse[1])),
+                    ],
+                    bias,
+                    anchor_bias,
+                    anchor_broken,
+                });
+            }
+        }
+        Self { points }
+    }
+
+    /// Apply the impulses carried over from the previous step.
+    pub fn warm_start(&self, bodies: &mut SolverBodies, velocities: &mut [Vec<f64>]) {
+        for point in &self.points {
+            for row in point.tangents.iter().chain([&point.normal]) {
+                row.apply(row.impulse, bodies, velocities);
+            }
+        }
+    }
+
+    /// One sequential-impulse sweep over all link contacts.
+    pub fn solve_velocities(&mut self, bodies: &mut SolverBodies, velocities: &mut [Vec<f64>]) {
+        for point in &mut self.points {
+            // Friction first, so the normal row has the final say
+            let max_friction = point.friction * point.normal.impulse;
+            let old = [point.tangents[0].impulse, point.tangents[1].impulse];
+            let mut new = [0.0; 2];
+            for k in 0..2 {
+                let row = &point.tangents[k];
+                new[k] = old[k] - row.mass * (row.speed(bodies, velocities) - point.anchor_bias[k]);
+            }
+            let magnitude = new[0].hypot(new[1]);
+            if magnitude > max_friction {
+                let scale = if magnitude > 0.0 { max_friction / magnitude } else { 0.0 };
+                new = [new[0] * scale, new[1] * scale];
+            }
+            for k in 0..2 {
+                point.tangents[k].impulse = new[k];
+                point.tangents[k].apply(new[k] - old[k], bodies, velocities);
+            }
+
+            let row = &mut point.normal;
+            let lambda = -row.mass * (row.speed(bodies, velocities) + point.bias);
+            let accumulated = (row.impulse + lambda).max(0.0);
+            let applied = accumulated - row.impulse;
+            row.impulse = accumulated;
+            row.apply(applied, bodies, velocities);
+        }
+    }
+
+    /// Copy accumulated impulses back into `manifolds` for the next step's
+    /// warm start, and flag points whose friction saturated.
+    pub fn store_impulses(&self, manifolds: &mut ManifoldCache) {
+        for point in &self.points {
+            let Some(cached) = manifolds.get_mut(&point.key).and_then(|manifold| manifold.points.get_mut(point.index)) else {
+                continue;
+            };
+            let normal = point.normal.impulse;
+            let tangent = [point.tangents[0].impulse, point.tangents[1].impulse];
+            cached.normal_impulse = normal;
+            cached.tangent_impulse = tangent;
+            cached.sliding = point.anchor_broken || normal <= 0.0 || tangent[0].hypot(tangent[1]) >= 0.999 * point.friction * normal;
+        }
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
@@ -0,0 +1,302 @@
+//! Kinematics and the articulated-body algorithm.
+//!
+//! A [`System`] is one articulation at one instant: the pose, motion
+//! subspace, velocity and inertia of every link, and the articulated
+//! inertias that the algorithm propagates from the leaves to the root. The
+//! inertias depend on the pose only, so they are computed once per step and
+//! shared by the forward dynamics and by every contact impulse response.
+
+use glam::{DMat3, DQuat, DVec3};
+
+use super::spatial::{invert, Inertia, Spatial, MAX_DOFS};
+use super::{Articulation, JointKind, Link};
+use crate::simulation::PhysicsSim;
+use crate::solver::SolverBody;
+
+/// One link at the current joint positions.
+pub(crate) struct LinkState {
+    /// Centre of mass, relative to [`System::origin`].
+    pub position: DVec3,
+    pub orientation: DQuat,
+    /// Columns of the motion subspace of the link's joint.
+    pub motion: [Spatial; MAX_DOFS],
+    pub dofs: usize,
+    pub velocity: Spatial,
+    /// Acceleration the joint velocities cause with no joint acceleration,
+    /// from the motion subspace moving along with the link.
+    pub coriolis: Spatial,
+    pub mass: f64,
+    pub inertia: Inertia,
+}
+
+/// Articulated inertia terms of one link, from the leaves-to-root pass.
+struct Factor {
+    /// Articulated inertia times each motion subspace column.
+    u: [Spatial; MAX_DOFS],
+    /// Inverse of the joint-space articulated inertia.
+    d_inv: [[f64; MAX_DOFS]; MAX_DOFS],
+    /// Articulated inertia seen through the joint by the parent.
+    reduced: Inertia,
+}
+
+/// An articulation at one instant, ready for dynamics queries.
+pub(crate) struct System {
+    /// Reference point of every spatial quantity, the world position of the
+    /// root link.
+    pub origin: DVec3,
+    pub links: Vec<LinkState>,
+    parents: Vec<Option<usize>>,
+    offsets: Vec<usize>,
+    factors: Vec<Factor>,
+}
+
+impl System {
+    /// Kinematics and articulated inertias of `articulation` at its current
+    /// joint positions and velocities.
+    pub fn new(sim: &PhysicsSim, articulation: &Articulation) -> Self {
+        let poses = articulation.world_poses();
+        let origin = poses.first().map_or(DVec3::ZERO, |pose| pose.0);
+        let mut links: Vec<LinkState> = Vec::with_capacity(articulation.links.len());
+        for (i, link) in articulation.links.iter().enumerate() {
+            let (position, orientation) = poses[i];
+            let parent = link.parent.map_or((DVec3::ZERO, DQuat::IDENTITY), |p| poses[p]);
+            let (motion, dofs) = motion_subspace(link, parent, (position, orientation), origin);
+            let rates = articulation.velocities[link.velocity_offset..link.velocity_offset + dofs].iter();
+            let relative = motion.iter().zip(rates).fold(Spatial::ZERO, |sum, (&column, &rate)| sum + column * f64::from(rate));
+            let parent_velocity = link.parent.map_or(Spatial::ZERO, |p| links[p].velocity);
+            let velocity = parent_velocity + relative;
+            // Columns fixed in the link turn with it; the floating root's
+            // world axes stay put while its centre of mass moves
+            let coriolis = if link.kind == JointKind::Free {
+                let center_velocity = relative.velocity_at(position - origin);
+                Spatial::new(DVec3::ZERO, center_velocity.cross(relative.angular))
+            } else {
+                velocity.cross_motion(relative)
+            };
+
+            let body = SolverBody::of(sim, link.body);
+            let mass = if body.inv_mass > 0.0 { 1.0 / f64::from(body.inv_mass) } else { 0.0 };
+            let inv_inertia = body.inv_inertia.as_dvec3();
+            let principal = DVec3::select(inv_inertia.cmpgt(DVec3::ZERO), DVec3::ONE / inv_inertia, DVec3::ZERO);
+            let rotation = DMat3::from_quat(orientation);
+            let inertia = rotation * DMat3::from_diagonal(principal) * rotation.transpose();
+            links.push(LinkState {
+                position: position - origin,
+                orientation,
+                motion,
+                dofs,
+                velocity,
+                coriolis,
+                mass,
+                inertia: Inertia::rigid(mass, position - origin, inertia),
+            });
+        }
+
+        let mut system = Self {
+            origin,
+            parents: articulation.links.iter().map(|link| link.parent).collect(),
+            offsets: articulation.links.iter().map(|link| link.velocity_offset).collect(),
+            links,
+            factors: Vec::new(),
+        };
+        system.factorize();
+        system
+    }
+
+    /// Leaves-to-root pass of the articulated inertias.
+    fn factorize(&mut self) {
+        let mut articulated: Vec<Inertia> = self.links.iter().map(|link| link.inertia).collect();
+        let mut factors = Vec::with_capacity(self.links.len());
+        for i in (0..self.links.len()).rev() {
+            let link = &self.links[i];
+            let mut u = [Spatial::ZERO; MAX_DOFS];
+            for (column, motion) in u.iter_mut().zip(&link.motion[..link.dofs]) {
+                *column = articulated[i].apply(*motion);
+            }
+            let mut d = [[0.0; MAX_DOFS]; MAX_DOFS];
+            for (row, motion) in d.iter_mut().zip(&link.motion[..link.dofs]) {
+                for (value, column) in row.iter_mut().zip(&u[..link.dofs]) {
+                    *value = motion.dot(*column);
+                }
+            }
+            let d_inv = invert(&d, link.dofs);
+            let mut reduced = articulated[i];
+            reduced.subtract_outer(&u[..link.dofs], &d_inv);
+            if let Some(parent) = self.parents[i] {
+                articulated[parent] += reduced;
+            }
+            factors.push(Factor { u, d_inv, reduced });
+        }
+        factors.reverse();
+        self.factors = factors;
+    }
+
+    /// Number of joint velocities.
+    pub fn dof_count(&self) -> usize {
+        self.links.iter().zip(&self.offsets).next_back().map_or(0, |(link, offset)| offset + link.dofs)
+    }
+
+    /// Joint accelerations under the joint forces `forces` and the spatial
+    /// forces `external` acting on each link.
+    pub fn accelerations(&self, forces: &[f64], external: &[Spatial]) -> Vec<f64> {
+        let bias: Vec<Spatial> = self
+            .links
+            .iter()
+            .zip(external)
+            .map(|(link, &external)| link.velocity.cross_force(link.inertia.apply(link.velocity)) - external)
+            .collect();
+        let coriolis: Vec<Spatial> = self.links.iter().map(|link| link.coriolis).collect();
+        self.solve(forces, bias, &coriolis)
+    }
+
+    /// Change of the joint velocities under the joint-space impulse
+    /// `impulse`, the inverse joint-space mass matrix applied to it.
+    pub fn response(&self, impulse: &[f64]) -> Vec<f64> {
+        let zero = vec![Spatial::ZERO; self.links.len()];
+        self.solve(impulse, zero.clone(), &zero)
+    }
+
+    /// Joint-space form of the spatial force `force` on `link`: the
+    /// generalized force it exerts on every joint between the link and the
+    /// root.
+    pub fn generalized_force(&self, link: usize, force: Spatial, into: &mut [f64]) {
+        let mut current = Some(link);
+        while let Some(i) = current {
+            let state = &self.links[i];
+            for d in 0..state.dofs {
+                into[self.offsets[i] + d] += state.motion[d].dot(force);
+            }
+            current = self.parents[i];
+        }
+    }
+
+    /// Motion subspace columns of every joint between `link` and the root,
+    /// with the index of their joint velocity.
+    pub fn path(&self, link: usize) -> Vec<(usize, Spatial)> {
+        let mut columns = Vec::new();
+        let mut current = Some(link);
+        while let Some(i) = current {
+            let state = &self.links[i];
+            columns.extend((0..state.dofs).map(|d| (self.offsets[i] + d, state.motion[d])));
+            current = self.parents[i];
+        }
+        columns
+    }
+
+    /// Joint-space mass matrix, the sum over links of the link inertia seen
+    /// through the joints that move it.
+    pub fn mass_matrix(&self) -> Vec<Vec<f64>> {
+        let count = self.dof_count();
+        let mut matrix = vec![vec![0.0; count]; count];
+        for (i, link) in self.links.iter().enumerate() {
+            let path = self.path(i);
+            for &(row, motion) in &path {
+                for &(column, other) in &path {
+                    matrix[row][column] += motion.dot(link.inertia.apply(other));
+                }
+            }
+        }
+        matrix
+    }
+
+    /// Joint forces that give the joint accelerations `accelerations` under
+    /// the spatial forces `external` on each link, by the recursive
+    /// Newton-Euler algorithm.
+    pub fn inverse_dynamics(&self, accelerations: &[f64], external: &[Spatial]) -> Vec<f64> {
+        let count = self.links.len();
+        let mut link_accelerations = vec![Spatial::ZERO; count];
+        let mut forces = vec![Spatial::ZERO; count];
+        for (i, link) in self.links.iter().enumerate() {
+            let base = self.parents[i].map_or(Spatial::ZERO, |parent| link_accelerations[parent]) + link.coriolis;
+            let acceleration = (0..link.dofs).fold(base, |sum, d| sum + link.motion[d] * accelerations[self.offsets[i] + d]);
+            link_accelerations[i] = acceleration;
+            forces[i] = link.inertia.apply(acceleration) + link.velocity.cross_force(link.inertia.apply(link.velocity)) - external[i];
+        }
+        let mut result = vec![0.0; accelerations.len()];
+        for i in (0..count).rev() {
+            let link = &self.links[i];
+            for d in 0..link.dofs {
+                result[self.offsets[i] + d] = link.motion[d].dot(forces[i]);
+            }
+            if let Some(parent) = self.parents[i] {
+                let force = forces[i];
+                forces[parent] += force;
+            }
+        }
+        result
+    }
+
+    /// The articulated-body algorithm proper: bias forces from the leaves to
+    /// the root, then accelerations from the root to the leaves.
+    fn solve(&self, forces: &[f64], mut bias: Vec<Spatial>, coriolis: &[Spatial]) -> Vec<f64> {
+        let count = self.links.len();
+        let mut reduced_forces = vec![[0.0; MAX_DOFS]; count];
+        for i in (0..count).rev() {
+            let (link, factor) = (&self.links[i], &self.factors[i]);
+            let mut u = [0.0; MAX_DOFS];
+            for (d, value) in u.iter_mut().enumerate().take(link.dofs) {
+                *value = forces[self.offsets[i] + d] - link.motion[d].dot(bias[i]);
+            }
+            reduced_forces[i] = u;
+            if let Some(parent) = self.parents[i] {
+                let mut passed = bias[i] + factor.reduced.apply(coriolis[i]);
+                for a in 0..link.dofs {
+                    let weight: f64 = (0..link.dofs).map(|b| factor.d_inv[a][b] * u[b]).sum();
+                    passed += factor.u[a] * weight;
+                }
+                bias[parent] += passed;
+            }
+        }
+
+        let mut accelerations = vec![Spatial::ZERO; count];
+        let mut result = vec![0.0; forces.len()];
+        for i in 0..count {
+            let (link, factor) = (&self.links[i], &self.factors[i]);
+            let base = self.parents[i].map_or(Spatial::ZERO, |parent| accelerations[parent]) + coriolis[i];
+            let mut rhs = [0.0; MAX_DOFS];
+            for (d, value) in rhs.iter_mut().enumerate().take(link.dofs) {
+                *value = reduced_forces[i][d] - base.dot(factor.u[d]);
+            }
+            let mut acceleration = base;
+            for a in 0..link.dofs {
+                let rate: f64 = (0..link.dofs).map(|b| factor.d_inv[a][b] * rhs[b]).sum();
+                result[self.offsets[i] + a] = rate;
+                acceleration += link.motion[a] * rate;
+            }
+            accelerations[i] = acceleration;
+        }
+        result
+    }
+}
+
+/// Motion subspace of the joint of `link`, in world axes about `origin`,
+/// given the world poses of its parent and of the link itself.
+fn motion_subspace(
+    link: &Link,
+    (parent_position, parent_orientation): (DVec3, DQuat),
+    (position, orientation): (DVec3, DQuat),
+    origin: DVec3,
+) -> ([Spatial; MAX_DOFS], usize) {
+    let mut motion = [Spatial::ZERO; MAX_DOFS];
+    let pivot = parent_position + parent_orientation * link.parent_anchor.as_dvec3() - origin;
+    let axis = (parent_orientation * link.axis.as_dvec3()).normalize_or_zero();
+    let dofs = link.kind.dofs();
+    match link.kind {
+        JointKind::Free => {
+            let center = position - origin;
+            for k in 0..3 {
+                motion[k] = Spatial::rotation(center, DVec3::AXES[k]);
+                motion[k + 3] = Spatial::translation(DVec3::AXES[k]);
+            }
+        }
+        JointKind::Fixed => {}
+        JointKind::Revolute => motion[0] = Spatial::rotation(pivot, axis),
+        JointKind::Prismatic => motion[0] = Spatial::translation(axis),
+        JointKind::Spherical => {
+            for (k, column) in motion.iter_mut().take(3).enumerate() {
+                *column = Spatial::rotation(pivot, orientation * DVec3::AXES[k]);
+            }
+        }
+    }
+    (motion, dofs)
+}
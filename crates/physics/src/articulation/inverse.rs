@@ -0,0 +1,95 @@
+//! Joint-space dynamics queries for controllers.
+//!
+//! The equations of motion of an articulation read `M(q) q̈ + c(q, q̇) = τ`,
+//! where `M` is the joint-space mass matrix, `c` the bias forces from
+//! gravity and the joint velocities, and `τ` the joint forces. These
+//! queries expose each term at the current state, in the joint velocity
+//! layout of [`Articulation::joint_velocities`], along with the Jacobians
+//! that map joint velocities to the motion of a point on a link.
+//!
+//! [`Articulation::joint_velocities`]: super::Articulation::joint_velocities
+
+use glam::DVec3;
+
+use super::dynamics::System;
+use crate::body::BodyHandle;
+use crate::simulation::PhysicsSim;
+use crate::types::Vec3;
+
+impl PhysicsSim {
+    /// Joint-space mass matrix of the articulation holding the link body
+    /// `body` at its current pose, one row per joint velocity. The matrix is
+    /// symmetric, and the kinetic energy of the tree is `½ q̇ᵀ M q̇`.
+    ///
+    /// Returns `None` if `body` is not an articulation link.
+    #[must_use]
+    pub fn articulation_mass_matrix(&self, body: BodyHandle) -> Option<Vec<Vec<f32>>> {
+        let system = self.link_system(body)?;
+        Some(system.mass_matrix().into_iter().map(|row| narrow(&row)).collect())
+    }
+
+    /// Joint forces that hold every joint of the articulation holding the
+    /// link body `body` at zero acceleration against gravity and the
+    /// coupling of its current joint velocities. Driving the joints with
+    /// these forces compensates gravity; forces added with
+    /// [`Self::apply_force`] and contacts are not included.
+    ///
+    /// Returns `None` if `body` is not an articulation link.
+    #[must_use]
+    pub fn articulation_bias_forces(&self, body: BodyHandle) -> Option<Vec<f32>> {
+        let system = self.link_system(body)?;
+        let accelerations = vec![0.0; system.dof_count()];
+        Some(narrow(&system.inverse_dynamics(&accelerations, &self.link_weights(&system))))
+    }
+
+    /// Joint forces that give the articulation holding the link body `body`
+    /// the joint accelerations `accelerations` at its current state, under
+    /// gravity and without contacts: the mass matrix times `accelerations`
+    /// plus the bias forces.
+    ///
+    /// Returns `None` if `body` is not an articulation link.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `accelerations` does not hold one value per joint velocity.
+    #[must_use]
+    pub fn articulation_inverse_dynamics(&self, body: BodyHandle, accelerations: &[f32]) -> Option<Vec<f32>> {
+        let system = self.link_system(body)?;
+        assert_eq!(accelerations.len(), system.dof_count(), "one acceleration per joint velocity");
+        let accelerations: Vec<f64> = accelerations.iter().map(|&a| f64::from(a)).collect();
+        Some(narrow(&system.inverse_dynamics(&accelerations, &self.link_weights(&system))))
+    }
+
+    /// Jacobian of the world-space `point`, moving with the link body
+    /// `body`, with respect to the joint velocities of its articulation. Each
+    /// column holds the angular velocity of the link and the velocity of the
+    /// point, `[ωx, ωy, ωz, vx, vy, vz]`, per unit of one joint velocity.
+    /// Joints that do not move the link have zero columns.
+    ///
+    /// Returns `None` if `body` is not an articulation link.
+    #[must_use]
+    pub fn link_jacobian(&self, body: BodyHandle, point: Vec3) -> Option<Vec<[f32; 6]>> {
+        let &(articulation, link) = self.articulation_links.get(&body)?;
+        let system = System::new(self, &self.articulations[articulation]);
+        let point = glam::Vec3::from(point).as_dvec3() - system.origin;
+        let mut columns = vec![[0.0; 6]; system.dof_count()];
+        for (index, motion) in system.path(link) {
+            let (angular, linear): (DVec3, DVec3) = (motion.angular, motion.velocity_at(point));
+            #[allow(clippy::cast_possible_truncation)]
+            let column = [angular.x, angular.y, angular.z, linear.x, linear.y, linear.z].map(|value| value as f32);
+            columns[index] = column;
+        }
+        Some(columns)
+    }
+
+    /// The articulation holding the link body `body`, at its current state.
+    fn link_system(&self, body: BodyHandle) -> Option<System> {
+        let &(articulation, _) = self.articulation_links.get(&body)?;
+        Some(System::new(self, &self.articulations[articulation]))
+    }
+}
+
+fn narrow(values: &[f64]) -> Vec<f32> {
+    #[allow(clippy::cast_possible_truncation)]
+    values.iter().map(|&value| value as f32).collect()
+}
@@ -0,0 +1,534 @@
+//! # Articulations
+//!
+//! Joints between ordinary bodies are solved in maximal coordinates: every
+//! body keeps its own position and velocity and the solver pulls them back
+//! together, so long chains stretch under load and need many iterations.
+//! An [`Articulation`] instead describes a kinematic tree in joint
+//! coordinates. Its state is the position and velocity of every joint,
+//! Featherstone's articulated-body algorithm turns joint forces, gravity and
+//! applied forces into joint accelerations, and the pose of each link
+//! follows from the joint positions, so joints never come apart.
+//!
+//! Every link is an ordinary body of the simulation: it collides, shows up
+//! in scene queries and is drawn like any other body. The rigid-body solver
+//! treats links as kinematic. Contacts that involve a link are solved in
+//! joint space instead, against static geometry, other articulations and
+//! dynamic bodies alike, and bodies pushing on a link push back on the whole
+//! articulation through its joints. Joints from [`PhysicsSim::add_ball_joint`]
+//! and its siblings that attach a link only act on the other body.
+//!
+//! A tree starts with [`PhysicsSim::add_articulation`], whose root either
+//! floats freely or is fixed in place, and grows one link at a time with
+//! [`PhysicsSim::add_articulation_link`]. Joints are given in world space
+//! at the current pose of the bodies, which becomes the zero position of
+//! every joint. Links of one articulation do not collide with each other.
+//!
+//! Joint coordinates of each link, as returned by
+//! [`Articulation::joint_positions`] and [`Articulation::joint_velocities`]:
+//!
+//! | Joint | Positions | Velocities |
+//! |-------|-----------|------------|
+//! | floating root | centre of mass, orientation `[x, y, z, w]` | angular velocity, velocity of the centre of mass |
+//! | fixed | none | none |
+//! | revolute | angle | angular speed |
+//! | prismatic | offset along the axis | speed |
+//! | spherical | rotation from the zero pose, `[x, y, z, w]` | angular velocity in the link frame |
+//!
+//! The floating root uses world axes, every other joint its own axes. Joint
+//! forces use the layout of the velocities. Articulations move with
+//! [`PhysicsSim::step_cpu`] only.
+//!
+//! For controllers, [`PhysicsSim::articulation_mass_matrix`],
+//! [`PhysicsSim::articulation_bias_forces`],
+//! [`PhysicsSim::articulation_inverse_dynamics`] and
+//! [`PhysicsSim::link_jacobian`] give the terms of the equations of motion
+//! at the current state.
+
+mod contact;
+mod dynamics;
+mod inverse;
+mod spatial;
+
+use std::ops::Range;
+
+use glam::{DQuat, DVec3, Quat};
+use serde::{Deserialize, Serialize};
+
+use crate::body::BodyHandle;
+use crate::collision::{BodyFrame, ManifoldCache};
+use crate::simulation::PhysicsSim;
+use crate::solver::SolverBodies;
+use crate::types::Vec3;
+use contact::LinkContactSolver;
+use dynamics::System;
+use spatial::Spatial;
+
+/// How the root link of an articulation is held.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArticulationBase {
+    /// The root moves freely, like the body of a creature.
+    Floating,
+    /// The root stays where it is, like the base of a robot arm.
+    Fixed,
+}
+
+/// Joint between a link and its parent, in world space at the current pose
+/// of both bodies.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ArticulationJoint {
+    /// The link is welded to its parent.
+    Fixed,
+    /// Rotation about the line through `anchor` along `axis`.
+    Revolute { anchor: Vec3, axis: Vec3 },
+    /// Sliding along `axis`, without rotation.
+    Prismatic { axis: Vec3 },
+    /// Free rotation about `anchor`.
+    Spherical { anchor: Vec3 },
+}
+
+/// Joint coordinates of one link.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum JointKind {
+    Free,
+    Fixed,
+    Revolute,
+    Prismatic,
+    Spherical,
+}
+
+impl JointKind {
+    fn positions(self) -> usize {
+        match self {
+            Self::Free => 7,
+            Self::Fixed => 0,
+            Self::Revolute | Self::Prismatic => 1,
+            Self::Spherical => 4,
+        }
+    }
+
+    pub(crate) fn dofs(self) -> usize {
+        match self {
+            Self::Free => 6,
+            Self::Fixed => 0,
+            Self::Revolute | Self::Prismatic => 1,
+            Self::Spherical => 3,
+        }
+    }
+}
+
+/// One body of an articulation and the joint to its parent.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Link {
+    pub body: BodyHandle,
+    pub parent: Option<usize>,
+    pub kind: JointKind,
+    /// Unit joint axis in the parent frame.
+    pub axis: glam::Vec3,
+    /// Joint anchor in the parent frame, or in the world for the root.
+    pub parent_anchor: glam::Vec3,
+    /// Joint anchor in the link frame.
+    pub child_anchor: glam::Vec3,
+    /// Orientation of the link in the parent frame at the zero position.
+    pub rest: Quat,
+    pub position_offset: usize,
+    pub velocity_offset: usize,
+}
+
+/// A tree of bodies connected by joints and simulated in joint
+/// coordinates; see [`crate::articulation`].
+///
+/// Links are numbered in the order they were added, starting with the root
+/// as link 0, so every parent comes before its children.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Articulation {
+    pub(crate) links: Vec<Link>,
+    pub(crate) positions: Vec<f32>,
+    pub(crate) velocities: Vec<f32>,
+    pub(crate) forces: Vec<f32>,
+}
+
+impl Articulation {
+    /// Number of links, the root included.
+    #[must_use]
+    pub fn link_count(&self) -> usize {
+        self.links.len()
+    }
+
+    /// Number of joint velocities of the whole tree.
+    #[must_use]
+    pub fn dof_count(&self) -> usize {
+        self.velocities.len()
+    }
+
+    /// Body of `link`.
+    #[must_use]
+    pub fn body(&self, link: usize) -> BodyHandle {
+        self.links[link].body
+    }
+
+    /// Parent of `link`, or `None` for the root.
+    #[must_use]
+    pub fn parent(&self, link: usize) -> Option<usize> {
+        self.links[link].parent
+    }
+
+    /// Positions of the joint between `link` and its parent.
+    #[must_use]
+    pub fn joint_positions(&self, link: usize) -> &[f32] {
+        &self.positions[self.position_range(link)]
+    }
+
+    /// Velocities of the joint between `link` and its parent.
+    #[must_use]
+    pub fn joint_velocities(&self, link: usize) -> &[f32] {
+        &self.velocities[self.velocity_range(link)]
+    }
+
+    /// Forces or torques driving the joint between `link` and its parent.
+    #[must_use]
+    pub fn joint_forces(&self, link: usize) -> &[f32] {
+        &self.forces[self.velocity_range(link)]
+    }
+
+    /// Drive the joint between `link` and its parent with `forces`, one per
+    /// joint velocity. They keep acting every step until changed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `forces` does not hold one value per joint velocity.
+    pub fn set_joint_forces(&mut self, link: usize, forces: &[f32]) {
+        let range = self.velocity_range(link);
+        self.forces[range].copy_from_slice(forces);
+    }
+
+    /// Set the velocities of the joint between `link` and its parent. The
+    /// link bodies follow at the next step.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `velocities` does not hold one value per joint velocity.
+    pub fn set_joint_velocities(&mut self, link: usize, velocities: &[f32]) {
+        let range = self.velocity_range(link);
+        self.velocities[range].copy_from_slice(velocities);
+    }
+
+    fn position_range(&self, link: usize) -> Range<usize> {
+        let link = &self.links[link];
+        link.position_offset..link.position_offset + link.kind.positions()
+    }
+
+    fn velocity_range(&self, link: usize) -> Range<usize> {
+        let link = &self.links[link];
+        link.velocity_offset..link.velocity_offset + link.kind.dofs()
+    }
+
+    /// World position and orientation of every link at the current joint
+    /// positions.
+    pub(crate) fn world_poses(&self) -> Vec<(DVec3, DQuat)> {
+        let mut poses: Vec<(DVec3, DQuat)> = Vec::with_capacity(self.links.len());
+        for link in &self.links {
+            let q: Vec<f64> = self.positions[link.position_offset..link.position_offset + link.kind.positions()]
+                .iter()
+                .map(|&value| f64::from(value))
+                .collect();
+            if link.kind == JointKind::Free {
+                poses.push((DVec3::new(q[0], q[1], q[2]), DQuat::from_xyzw(q[3], q[4], q[5], q[6]).normalize()));
+                continue;
+            }
+            let (parent_position, parent_orientation) = link.parent.map_or((DVec3::ZERO, DQuat::IDENTITY), |p| poses[p]);
+            let rest = link.rest.as_dquat();
+            let axis = link.axis.as_dvec3();
+            let (orientation, slide) = match link.kind {
+                JointKind::Revolute => (parent_orientation * DQuat::from_axis_angle(axis, q[0]) * rest, DVec3::ZERO),
+                JointKind::Prismatic => (parent_orientation * rest, axis * q[0]),
+                JointKind::Spherical => {
+                    (parent_orientation * rest * DQuat::from_xyzw(q[0], q[1], q[2], q[3]).normalize(), DVec3::ZERO)
+                }
+                JointKind::Free | JointKind::Fixed => (parent_orientation * rest, DVec3::ZERO),
+            };
+            let pivot = parent_position + parent_orientation * (link.parent_anchor.as_dvec3() + slide);
+            poses.push((pivot - orientation * link.child_anchor.as_dvec3(), orientation));
+        }
+        poses
+    }
+
+    /// Advance the joint positions by the joint velocities over `dt`.
+    fn integrate(&mut self, dt: f32) {
+        for link in &self.links {
+            let q = &mut self.positions[link.position_offset..link.position_offset + link.kind.positions()];
+            let v = &self.velocities[link.velocity_offset..link.velocity_offset + link.kind.dofs()];
+            match link.kind {
+                JointKind::Free => {
+                    let position = glam::Vec3::new(q[0], q[1], q[2]) + glam::Vec3::new(v[3], v[4], v[5]) * dt;
+                    let spin = Quat::from_scaled_axis(glam::Vec3::new(v[0], v[1], v[2]) * dt);
+                    let orientation = spin * Quat::from_xyzw(q[3], q[4], q[5], q[6]);
+                    q[..3].copy_from_slice(&position.to_array());
+                    q[3..].copy_from_slice(&orientation.normalize().to_array());
+                }
+                JointKind::Revolute | JointKind::Prismatic => q[0] += v[0] * dt,
+                JointKind::Spherical => {
+                    let spin = Quat::from_scaled_axis(glam::Vec3::new(v[0], v[1], v[2]) * dt);
+                    let rotation = Quat::from_xyzw(q[0], q[1], q[2], q[3]) * spin;
+                    q.copy_from_slice(&rotation.normalize().to_array());
+                }
+                JointKind::Fixed => {}
+            }
+        }
+    }
+}
+
+impl PhysicsSim {
+    /// Start an articulation whose root link is the body `root`, and return
+    /// its index in [`Self::articulations`]. A floating root keeps its
+    /// current velocity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `root` is static geometry, already belongs to an
+    /// articulation, or is not dynamic for a floating base.
+    pub fn add_articulation(&mut self, root: BodyHandle, base: ArticulationBase) -> usize {
+        assert!(
+            !matches!(root, BodyHandle::Plane(_) | BodyHandle::Heightfield(_) | BodyHandle::Mesh(_)),
+            "static geometry cannot be an articulation link"
+        );
+        self.check_link_body(root, base == ArticulationBase::Floating);
+        let frame = self.body_frame(root);
+        let mut articulation = Articulation::default();
+        let link = match base {
+            ArticulationBase::Floating => {
+                let (linear, angular) = self.body_velocity(root);
+                articulation.positions.extend(frame.position.to_array());
+                articulation.positions.extend(frame.orientation.to_array());
+                articulation.velocities.extend(<[f32; 3]>::from(angular));
+                articulation.velocities.extend(<[f32; 3]>::from(linear));
+                Link {
+                    body: root,
+                    parent: None,
+                    kind: JointKind::Free,
+                    axis: glam::Vec3::ZERO,
+                    parent_anchor: glam::Vec3::ZERO,
+                    child_anchor: glam::Vec3::ZERO,
+                    rest: Quat::IDENTITY,
+                    position_offset: 0,
+                    velocity_offset: 0,
+                }
+            }
+            ArticulationBase::Fixed => Link {
+                body: root,
+                parent: None,
+                kind: JointKind::Fixed,
+                axis: glam::Vec3::ZERO,
+                parent_anchor: frame.position,
+                child_anchor: glam::Vec3::ZERO,
+                rest: frame.orientation,
+                position_offset: 0,
+                velocity_offset: 0,
+            },
+        };
+        articulation.forces = vec![0.0; articulation.velocities.len()];
+        articulation.links.push(link);
+        self.articulations.push(articulation);
+        let index = self.articulations.len() - 1;
+        self.claim_link_body(root, index, 0);
+        index
+    }
+
+    /// Attach the body `body` to link `parent` of articulation
+    /// `articulation` with `joint`, and return the new link's index. The
+    /// link starts at rest relative to its parent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the articulation or parent link does not exist, or if
+    /// `body` is not a dynamic body or already belongs to an articulation.
+    pub fn add_articulation_link(
+        &mut self,
+        articulation: usize,
+        parent: usize,
+        body: BodyHandle,
+        joint: ArticulationJoint,
+    ) -> usize {
+        self.check_link_body(body, true);
+        let frame = self.body_frame(body);
+        let tree = &mut self.articulations[articulation];
+        assert!(parent < tree.links.len(), "articulation {articulation} has no link {parent}");
+        let (parent_position, parent_orientation) = tree.world_poses()[parent];
+        #[allow(clippy::cast_possible_truncation)]
+        let (parent_position, parent_orientation) = (parent_position.as_vec3(), parent_orientation.as_quat());
+        let inverse = parent_orientation.inverse();
+        let (kind, anchor, axis) = match joint {
+            ArticulationJoint::Fixed => (JointKind::Fixed, frame.position, glam::Vec3::ZERO),
+            ArticulationJoint::Revolute { anchor, axis } => (JointKind::Revolute, anchor.into(), axis.into()),
+            ArticulationJoint::Prismatic { axis } => (JointKind::Prismatic, frame.position, axis.into()),
+            ArticulationJoint::Spherical { anchor } => (JointKind::Spherical, anchor.into(), glam::Vec3::ZERO),
+        };
+        let link = Link {
+            body,
+            parent: Some(parent),
+            kind,
+            axis: (inverse * axis).normalize_or_zero(),
+            parent_anchor: inverse * (anchor - parent_position),
+            child_anchor: frame.orientation.inverse() * (anchor - frame.position),
+            rest: inverse * frame.orientation,
+            position_offset: tree.positions.len(),
+            velocity_offset: tree.velocities.len(),
+        };
+        if kind == JointKind::Spherical {
+            tree.positions.extend(Quat::IDENTITY.to_array());
+        } else {
+            tree.positions.extend(std::iter::repeat_n(0.0, kind.positions()));
+        }
+        tree.velocities.extend(std::iter::repeat_n(0.0, kind.dofs()));
+        tree.forces.extend(std::iter::repeat_n(0.0, kind.dofs()));
+        tree.links.push(link);
+        let index = tree.links.len() - 1;
+        self.claim_link_body(body, articulation, index);
+        self.sync_articulation(articulation);
+        index
+    }
+
+    /// Whether the body behind `handle` is a link of an articulation.
+    pub(crate) fn is_link(&self, handle: BodyHandle) -> bool {
+        self.articulation_links.contains_key(&handle)
+    }
+
+    fn check_link_body(&self, body: BodyHandle, dynamic: bool) {
+        assert!(self.has_body(body), "{body:?} does not exist");
+        assert!(!self.is_link(body), "{body:?} already belongs to an articulation");
+        assert!(!dynamic || self.is_dynamic(body), "articulation links must be dynamic bodies");
+    }
+
+    /// Record `body` as `link` of `articulation`, and keep it from colliding
+    /// with the other links.
+    fn claim_link_body(&mut self, body: BodyHandle, articulation: usize, link: usize) {
+        let others: Vec<BodyHandle> = self.articulations[articulation].links.iter().map(|link| link.body).collect();
+        for other in others {
+            if other != body {
+                self.set_pair_collision(body, other, false);
+            }
+        }
+        self.articulation_links.insert(body, (articulation, link));
+        self.sleeping.remove(&body);
+        self.rest_time.remove(&body);
+    }
+
+    /// Move the link bodies of `articulation` to the pose and velocity of
+    /// its joint state.
+    fn sync_articulation(&mut self, articulation: usize) {
+        let tree = &self.articulations[articulation];
+        let system = System::new(self, tree);
+        let updates: Vec<(BodyHandle, BodyFrame, Spatial, DVec3)> = tree
+            .links
+            .iter()
+            .zip(&system.links)
+            .map(|(link, state)| {
+                #[allow(clippy::cast_possible_truncation)]
+                let frame = BodyFrame {
+                    position: (state.position + system.origin).as_vec3(),
+                    orientation: state.orientation.as_quat(),
+                };
+                (link.body, frame, state.velocity, state.position)
+            })
+            .collect();
+        for (body, frame, velocity, position) in updates {
+            #[allow(clippy::cast_possible_truncation)]
+            let (linear, angular) = (velocity.velocity_at(position).as_vec3(), velocity.angular.as_vec3());
+            self.set_body_frame(body, frame);
+            self.set_body_velocity(body, linear.into(), angular.into());
+        }
+    }
+
+    /// Change the joint velocities of the articulation holding `link` as if
+    /// `impulse` acted on it at the world-space `point`.
+    pub(crate) fn apply_link_impulse(&mut self, link: BodyHandle, impulse: glam::Vec3, point: glam::Vec3) {
+        let Some(&(articulation, index)) = self.articulation_links.get(&link) else {
+            return;
+        };
+        let system = System::new(self, &self.articulations[articulation]);
+        let mut generalized = vec![0.0; system.dof_count()];
+        let force = Spatial::force_at(point.as_dvec3() - system.origin, impulse.as_dvec3());
+        system.generalized_force(index, force, &mut generalized);
+        let tree = &mut self.articulations[articulation];
+        for (velocity, change) in tree.velocities.iter_mut().zip(system.response(&generalized)) {
+            #[allow(clippy::cast_possible_truncation)]
+            let change = change as f32;
+            *velocity += change;
+        }
+        self.sync_articulation(articulation);
+    }
+
+    /// Weight of every link of `system`, as spatial forces.
+    fn link_weights(&self, system: &System) -> Vec<Spatial> {
+        let gravity = glam::Vec3::from(self.params.gravity).as_dvec3();
+        system.links.iter().map(|state| Spatial::force_at(state.position, gravity * state.mass)).collect()
+    }
+
+    /// Accelerate every articulation over `dt` and solve the contacts of its
+    /// links, before the rigid bodies are solved. Returns the manifolds of
+    /// those contacts, which stay out of the rigid solver until
+    /// [`Self::finish_articulations`].
+    pub(crate) fn solve_articulations(&mut self, dt: f32) -> ManifoldCache {
+        if self.articulations.is_empty() {
+            return ManifoldCache::new();
+        }
+        let mut systems = Vec::with_capacity(self.articulations.len());
+        let mut velocities = Vec::with_capacity(self.articulations.len());
+        for tree in &self.articulations {
+            let system = System::new(self, tree);
+            let external: Vec<Spatial> = tree
+                .links
+                .iter()
+                .zip(&system.links)
+                .zip(self.link_weights(&system))
+                .map(|((link, state), weight)| {
+                    let load = self.applied_loads.get(&link.body).copied().unwrap_or_default();
+                    weight + Spatial::force_at(state.position, load.force.as_dvec3()) + Spatial::new(load.torque.as_dvec3(), DVec3::ZERO)
+                })
+                .collect();
+            let mut qd: Vec<f64> = tree.velocities.iter().map(|&v| f64::from(v)).collect();
+            let forces: Vec<f64> = tree.forces.iter().map(|&f| f64::from(f)).collect();
+            let accelerations = system.accelerations(&forces, &external);
+            for (velocity, acceleration) in qd.iter_mut().zip(accelerations) {
+                *velocity += acceleration * f64::from(dt);
+            }
+            systems.push(system);
+            velocities.push(qd);
+        }
+
+        let (mut linked, rigid): (ManifoldCache, ManifoldCache) = std::mem::take(&mut self.manifolds)
+            .into_iter()
+            .partition(|(_, manifold)| self.is_link(manifold.body_a) || self.is_link(manifold.body_b));
+        self.manifolds = rigid;
+        if !linked.is_empty() {
+            let mut bodies = SolverBodies::gather(self);
+            let mut solver = LinkContactSolver::prepare(self, &linked, &bodies, &systems, &self.contact_params, dt);
+            solver.warm_start(&mut bodies, &mut velocities);
+            for _ in 0..self.contact_params.velocity_iterations {
+                solver.solve_velocities(&mut bodies, &mut velocities);
+            }
+            solver.store_impulses(&mut linked);
+            bodies.scatter_velocities(self);
+        }
+
+        for (index, qd) in velocities.into_iter().enumerate() {
+            for (velocity, value) in self.articulations[index].velocities.iter_mut().zip(qd) {
+                #[allow(clippy::cast_possible_truncation)]
+                let value = value as f32;
+                *velocity = value;
+            }
+            self.sync_articulation(index);
+        }
+        linked
+    }
+
+    /// Advance the joint positions of every articulation over `dt`, move
+    /// the link bodies there and return the link contacts `linked` to the
+    /// manifold cache.
+    pub(crate) fn finish_articulations(&mut self, dt: f32, linked: ManifoldCache) {
+        for index in 0..self.articulations.len() {
+            self.articulations[index].integrate(dt);
+            self.sync_articulation(index);
+        }
+        self.manifolds.extend(linked);
+    }
+}
@@ -0,0 +1,207 @@
+//! Spatial vectors and inertias for the articulated-body algorithm.
+//!
+//! Everything is expressed in world axes about one reference point per
+//! articulation, in double precision so long chains do not lose accuracy
+//! before the result is handed back to the single-precision bodies.
+
+use std::ops::{Add, AddAssign, Mul, Neg, Sub};
+
+use glam::{DMat3, DVec3};
+
+/// Largest number of degrees of freedom of one joint.
+pub(crate) const MAX_DOFS: usize = 6;
+
+/// A spatial motion (angular velocity, velocity of the point at the
+/// reference) or force (moment about the reference, force) vector.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) struct Spatial {
+    pub angular: DVec3,
+    pub linear: DVec3,
+}
+
+impl Spatial {
+    pub const ZERO: Self = Self::new(DVec3::ZERO, DVec3::ZERO);
+
+    pub const fn new(angular: DVec3, linear: DVec3) -> Self {
+        Self { angular, linear }
+    }
+
+    /// Rotation about the line through `point` along the unit `axis`.
+    pub fn rotation(point: DVec3, axis: DVec3) -> Self {
+        Self::new(axis, point.cross(axis))
+    }
+
+    /// Pure translation along `axis`.
+    pub fn translation(axis: DVec3) -> Self {
+        Self::new(DVec3::ZERO, axis)
+    }
+
+    /// Force `force` acting through `point`.
+    pub fn force_at(point: DVec3, force: DVec3) -> Self {
+        Self::new(point.cross(force), force)
+    }
+
+    /// Velocity of the world point `point` when moving with this motion.
+    pub fn velocity_at(self, point: DVec3) -> DVec3 {
+        self.linear + self.angular.cross(point)
+    }
+
+    /// Cross product of two motions, the rate of change of `other` when it
+    /// moves with `self`.
+    pub fn cross_motion(self, other: Self) -> Self {
+        Self::new(
+            self.angular.cross(other.angular),
+            self.angular.cross(other.linear) + self.linear.cross(other.angular),
+        )
+    }
+
+    /// Cross product of this motion with the force `force`.
+    pub fn cross_force(self, force: Self) -> Self {
+        Self::new(
+            self.angular.cross(force.angular) + self.linear.cross(force.linear),
+            self.angular.cross(force.linear),
+        )
+    }
+
+    /// Power of the force `force` on this motion.
+    pub fn dot(self, force: Self) -> f64 {
+        self.angular.dot(force.angular) + self.linear.dot(force.linear)
+    }
+}
+
+impl Add for Spatial {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self::new(self.angular + other.angular, self.linear + other.linear)
+    }
+}
+
+impl AddAssign for Spatial {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Spatial {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.angular - other.angular, self.linear - other.linear)
+    }
+}
+
+impl Neg for Spatial {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::new(-self.angular, -self.linear)
+    }
+}
+
+impl Mul<f64> for Spatial {
+    type Output = Self;
+    fn mul(self, scale: f64) -> Self {
+        Self::new(self.angular * scale, self.linear * scale)
+    }
+}
+
+/// Symmetric spatial inertia `[[a, b], [bᵀ, c]]`, mapping motions to
+/// forces.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Inertia {
+    a: DMat3,
+    b: DMat3,
+    c: DMat3,
+}
+
+impl Inertia {
+    /// Rigid body of `mass` centred on `center`, with the rotational
+    /// inertia `inertia` about its centre in world axes.
+    pub fn rigid(mass: f64, center: DVec3, inertia: DMat3) -> Self {
+        let skew = skew(center);
+        Self {
+            a: inertia - skew * skew * mass,
+            b: skew * mass,
+            c: DMat3::from_diagonal(DVec3::splat(mass)),
+        }
+    }
+
+    pub fn apply(&self, motion: Spatial) -> Spatial {
+        Spatial::new(
+            self.a * motion.angular + self.b * motion.linear,
+            self.b.transpose() * motion.angular + self.c * motion.linear,
+        )
+    }
+
+    /// Subtract the symmetric sum of `weight[i][j] · u[i] u[j]ᵀ`.
+    pub fn subtract_outer(&mut self, u: &[Spatial], weight: &[[f64; MAX_DOFS]; MAX_DOFS]) {
+        for (i, ui) in u.iter().enumerate() {
+            for (j, uj) in u.iter().enumerate() {
+                let w = weight[i][j];
+                self.a -= outer(ui.angular, uj.angular) * w;
+                self.b -= outer(ui.angular, uj.linear) * w;
+                self.c -= outer(ui.linear, uj.linear) * w;
+            }
+        }
+    }
+}
+
+impl AddAssign for Inertia {
+    fn add_assign(&mut self, other: Self) {
+        self.a += other.a;
+        self.b += other.b;
+        self.c += other.c;
+    }
+}
+
+/// Matrix of the cross product with `v`.
+fn skew(v: DVec3) -> DMat3 {
+    DMat3::from_cols(
+        DVec3::new(0.0, v.z, -v.y),
+        DVec3::new(-v.z, 0.0, v.x),
+        DVec3::new(v.y, -v.x, 0.0),
+    )
+}
+
+/// The matrix `a bᵀ`.
+fn outer(a: DVec3, b: DVec3) -> DMat3 {
+    DMat3::from_cols(a * b.x, a * b.y, a * b.z)
+}
+
+/// Inverse of the leading `size` by `size` block of the symmetric positive
+/// semi-definite `matrix`, through its `LDLᵀ` factorization. Directions the
+/// matrix cannot resolve, such as a joint that moves no mass, get a zero
+/// inverse.
+pub(crate) fn invert(matrix: &[[f64; MAX_DOFS]; MAX_DOFS], size: usize) -> [[f64; MAX_DOFS]; MAX_DOFS] {
+    let scale = (0..size).map(|i| matrix[i][i].abs()).fold(0.0, f64::max);
+    let mut lower = [[0.0; MAX_DOFS]; MAX_DOFS];
+    let mut diagonal = [0.0; MAX_DOFS];
+    for j in 0..size {
+        let d = matrix[j][j] - (0..j).map(|k| lower[j][k] * lower[j][k] * diagonal[k]).sum::<f64>();
+        diagonal[j] = if d > 1e-12 * scale { d } else { 0.0 };
+        lower[j][j] = 1.0;
+        for i in j + 1..size {
+            if diagonal[j] > 0.0 {
+                let sum: f64 = (0..j).map(|k| lower[i][k] * lower[j][k] * diagonal[k]).sum();
+                lower[i][j] = (matrix[i][j] - sum) / diagonal[j];
+            }
+        }
+    }
+
+    let mut inverse = [[0.0; MAX_DOFS]; MAX_DOFS];
+    for column in 0..size {
+        let mut x = [0.0; MAX_DOFS];
+        x[column] = 1.0;
+        for i in 0..size {
+            x[i] -= (0..i).map(|k| lower[i][k] * x[k]).sum::<f64>();
+        }
+        for i in 0..size {
+            x[i] = if diagonal[i] > 0.0 { x[i] / diagonal[i] } else { 0.0 };
+        }
+        for i in (0..size).rev() {
+            x[i] -= (i + 1..size).map(|k| lower[k][i] * x[k]).sum::<f64>();
+        }
+        for i in 0..size {
+            inverse[i][column] = x[i];
+        }
+    }
+    inverse
+}
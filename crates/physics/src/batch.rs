@@ -0,0 +1,328 @@
+//! # Batched Worlds
+//!
+//! [`BatchedPhysicsSim`] holds N isolated copies of a template
+//! [`PhysicsSim`] and steps them together, for vectorised reinforcement
+//! learning environments. Unlike [`crate::CartPoleGrid`], the copies never
+//! interact and need no spacing.
+//!
+//! Body state is kept in structure-of-arrays layout in a [`BatchState`]:
+//! one array per quantity, with the entries of one body in every world next
+//! to each other. Each world keeps its own solver caches and parameters, so
+//! worlds can differ in gravity, applied forces, materials or masses, and
+//! can be reset one at a time.
+//!
+//! The worlds are still full [`PhysicsSim`]s, and the arrays are a mirror of
+//! their bodies: every step copies the new state out into them, and edits
+//! made through [`BatchedPhysicsSim::state_mut`] are copied back in before
+//! the next one. The batch saves the bookkeeping of many separate
+//! simulations and spreads the CPU step over threads, but it is not cheaper
+//! per world than stepping the worlds one by one. Only scenes whose moving
+//! bodies are all spheres run on the compute kernels; see
+//! [`BatchedPhysicsSim::step_gpu`].
+
+use std::num::NonZeroUsize;
+use std::thread;
+
+use crate::body::BodyHandle;
+use crate::collision::BodyFrame;
+use crate::gpu_executor::step_spheres_gpu;
+use crate::simulation::{PhysicsError, PhysicsSim};
+use crate::types::{PhysParams, Vec3};
+
+/// Body state of every world in a [`BatchedPhysicsSim`].
+///
+/// Entry `index(body, world)` of each array belongs to body `body`, its
+/// position in [`BatchedPhysicsSim::bodies`], in world `world`. Edits take
+/// effect at the next step.
+#[derive(Clone, Debug, Default)]
+pub struct BatchState {
+    /// Number of worlds.
+    pub worlds: usize,
+    /// Center of mass positions.
+    pub position: Vec<Vec3>,
+    /// Orientations as `[x, y, z, w]` quaternions.
+    pub orientation: Vec<[f32; 4]>,
+    /// Linear velocities.
+    pub linear_velocity: Vec<Vec3>,
+    /// Angular velocities.
+    pub angular_velocity: Vec<Vec3>,
+}
+
+impl BatchState {
+    /// Entry of body `body` in world `world`.
+    #[must_use]
+    pub fn index(&self, body: usize, world: usize) -> usize {
+        body * self.worlds + world
+    }
+}
+
+fn xyz(v: Vec3) -> [f32; 3] {
+    [v.x, v.y, v.z]
+}
+
+/// One quantity of one body, gathered by [`BatchedPhysicsSim::gather`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Observation {
+    /// Position, three values.
+    Position(BodyHandle),
+    /// Orientation quaternion `[x, y, z, w]`, four values.
+    Orientation(BodyHandle),
+    /// Linear velocity, three values.
+    LinearVelocity(BodyHandle),
+    /// Angular velocity, three values.
+    AngularVelocity(BodyHandle),
+}
+
+impl Observation {
+    /// Number of values this observation contributes to a row.
+    #[must_use]
+    pub fn width(self) -> usize {
+        match self {
+            Observation::Orientation(_) => 4,
+            Observation::Position(_) | Observation::LinearVelocity(_) | Observation::AngularVelocity(_) => 3,
+        }
+    }
+
+    fn body(self) -> BodyHandle {
+        match self {
+            Observation::Position(body)
+            | Observation::Orientation(body)
+            | Observation::LinearVelocity(body)
+            | Observation::AngularVelocity(body) => body,
+        }
+    }
+}
+
+/// N isolated copies of a template world, stepped together.
+pub struct BatchedPhysicsSim {
+    template: PhysicsSim,
+    /// Per-world solver state and parameters. Their body state matches
+    /// [`Self::state`] unless `edited` is set.
+    worlds: Vec<PhysicsSim>,
+    bodies: Vec<BodyHandle>,
+    state: BatchState,
+    /// Whether `state` was handed out for editing since the worlds last
+    /// took their body state from it.
+    edited: bool,
+}
+
+impl BatchedPhysicsSim {
+    /// Create `worlds` copies of `template`, each starting from its current
+    /// state.
+    #[must_use]
+    pub fn new(template: PhysicsSim, worlds: usize) -> Self {
+        let bodies: Vec<BodyHandle> = template
+            .body_handles()
+            .into_iter()
+            .filter(|handle| !matches!(handle, BodyHandle::Plane(_) | BodyHandle::Heightfield(_) | BodyHandle::Mesh(_)))
+            .collect();
+        let entries = bodies.len() * worlds;
+        let mut batch = Self {
+            worlds: vec![template.clone(); worlds],
+            template,
+            bodies,
+            state: BatchState {
+                worlds,
+                position: vec![Vec3::ZERO; entries],
+                orientation: vec![[0.0, 0.0, 0.0, 1.0]; entries],
+                linear_velocity: vec![Vec3::ZERO; entries],
+                angular_velocity: vec![Vec3::ZERO; entries],
+            },
+            edited: false,
+        };
+        for world in 0..worlds {
+            batch.store(world);
+        }
+        batch
+    }
+
+    /// Number of worlds.
+    #[must_use]
+    pub fn world_count(&self) -> usize {
+        self.worlds.len()
+    }
+
+    /// Bodies that move, in the order [`BatchState`] stores them: every
+    /// body of the template except planes, heightfields and meshes.
+    #[must_use]
+    pub fn bodies(&self) -> &[BodyHandle] {
+        &self.bodies
+    }
+
+    /// Position of `handle` in [`Self::bodies`].
+    #[must_use]
+    pub fn body_index(&self, handle: BodyHandle) -> Option<usize> {
+        self.bodies.iter().position(|&body| body == handle)
+    }
+
+    /// Body state of every world.
+    #[must_use]
+    pub fn state(&self) -> &BatchState {
+        &self.state
+    }
+
+    /// Mutable body state of every world, for setting initial conditions.
+    pub fn state_mut(&mut self) -> &mut BatchState {
+        self.edited = true;
+        &mut self.state
+    }
+
+    /// Gravity, timestep and applied forces of `world`.
+    #[must_use]
+    pub fn params(&self, world: usize) -> &PhysParams {
+        &self.worlds[world].params
+    }
+
+    /// Mutable gravity, timestep and applied forces of `world`.
+    pub fn params_mut(&mut self, world: usize) -> &mut PhysParams {
+        &mut self.worlds[world].params
+    }
+
+    /// Run `f` on the simulation of `world`, with its current body state.
+    /// Anything `f` changes, bodies included, stays with that world.
+    pub fn configure<R>(&mut self, world: usize, f: impl FnOnce(&mut PhysicsSim) -> R) -> R {
+        self.load_edits();
+        let result = f(&mut self.worlds[world]);
+        self.store(world);
+        result
+    }
+
+    /// Put the bodies of `world` back to the template's state and forget
+    /// its contacts, warm-start impulses and sleep state. Its parameters
+    /// are kept.
+    pub fn reset(&mut self, world: usize) {
+        let sim = &mut self.worlds[world];
+        sim.clear_solver_state();
+        for &handle in &self.bodies {
+            let (position, orientation) = self.template.body_pose(handle);
+            sim.set_body_frame(handle, BodyFrame {
+                position: position.into(),
+                orientation: glam::Quat::from_array(orientation),
+            });
+            let (linear, angular) = self.template.body_velocity(handle);
+            sim.set_body_velocity(handle, linear, angular);
+        }
+        self.store(world);
+    }
+
+    /// Reset every world whose entry in `done` is set.
+    pub fn reset_where(&mut self, done: &[bool]) {
+        for (world, _) in done.iter().enumerate().filter(|(_, &done)| done) {
+            self.reset(world);
+        }
+    }
+
+    /// Step every world once on the CPU, spreading the worlds over the
+    /// available cores.
+    pub fn step_cpu(&mut self) {
+        self.load_edits();
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let chunk = self.worlds.len().div_ceil(threads).max(1);
+        thread::scope(|scope| {
+            for worlds in self.worlds.chunks_mut(chunk) {
+                scope.spawn(move || worlds.iter_mut().for_each(PhysicsSim::step_cpu));
+            }
+        });
+        self.store_all();
+    }
+
+    /// Step every world once through the compute kernels of the template's
+    /// backend. Worlds with the same gravity, timestep and contact model
+    /// share each dispatch.
+    ///
+    /// The kernels only move spheres, like [`PhysicsSim::step_gpu`]. If any
+    /// body in [`Self::bodies`] is not a sphere, the whole batch takes
+    /// [`Self::step_cpu`] instead, so no body is left behind.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PhysicsError::BackendError`] if a kernel dispatch fails.
+    pub fn step_gpu(&mut self) -> Result<(), PhysicsError> {
+        if !self.bodies.iter().all(|body| matches!(body, BodyHandle::Sphere(_))) {
+            self.step_cpu();
+            return Ok(());
+        }
+        self.load_edits();
+        let mut remaining: Vec<&mut PhysicsSim> = self.worlds.iter_mut().collect();
+        while !remaining.is_empty() {
+            let (gravity, dt, model) = (remaining[0].params.gravity, remaining[0].params.dt, remaining[0].contact_model);
+            let (mut group, rest): (Vec<_>, Vec<_>) = remaining.into_iter().partition(|sim| {
+                sim.params.gravity == gravity && sim.params.dt.to_bits() == dt.to_bits() && sim.contact_model == model
+            });
+            step_spheres_gpu(self.template.backend.as_ref(), &mut group)?;
+            remaining = rest;
+        }
+        self.store_all();
+        Ok(())
+    }
+
+    /// Gather `observations` of every world into a row-major matrix with
+    /// one row per world, or `None` if an observation names a body that is
+    /// not in [`Self::bodies`].
+    #[must_use]
+    pub fn gather(&self, observations: &[Observation]) -> Option<Vec<f32>> {
+        let columns = observations
+            .iter()
+            .map(|&observation| Some((observation, self.body_index(observation.body())?)))
+            .collect::<Option<Vec<(Observation, usize)>>>()?;
+        let width: usize = observations.iter().map(|observation| observation.width()).sum();
+        let mut rows = Vec::with_capacity(width * self.worlds.len());
+        for world in 0..self.worlds.len() {
+            for &(observation, body) in &columns {
+                let index = self.state.index(body, world);
+                match observation {
+                    Observation::Position(_) => rows.extend(xyz(self.state.position[index])),
+                    Observation::Orientation(_) => rows.extend(self.state.orientation[index]),
+                    Observation::LinearVelocity(_) => rows.extend(xyz(self.state.linear_velocity[index])),
+                    Observation::AngularVelocity(_) => rows.extend(xyz(self.state.angular_velocity[index])),
+                }
+            }
+        }
+        Some(rows)
+    }
+
+    /// Copy edits made through [`Self::state_mut`] into every world.
+    fn load_edits(&mut self) {
+        if std::mem::take(&mut self.edited) {
+            for world in 0..self.worlds.len() {
+                self.load(world);
+            }
+        }
+    }
+
+    fn store_all(&mut self) {
+        for world in 0..self.worlds.len() {
+            self.store(world);
+        }
+    }
+
+    /// Copy the body state of `world` from the batch arrays into its
+    /// simulation.
+    fn load(&mut self, world: usize) {
+        let sim = &mut self.worlds[world];
+        for (body, &handle) in self.bodies.iter().enumerate() {
+            let index = self.state.index(body, world);
+            let frame = BodyFrame {
+                position: self.state.position[index].into(),
+                orientation: glam::Quat::from_array(self.state.orientation[index]),
+            };
+            sim.set_body_frame(handle, frame);
+            sim.set_body_velocity(handle, self.state.linear_velocity[index], self.state.angular_velocity[index]);
+        }
+    }
+
+    /// Copy the body state of `world` from its simulation into the batch
+    /// arrays.
+    fn store(&mut self, world: usize) {
+        let sim = &self.worlds[world];
+        for (body, &handle) in self.bodies.iter().enumerate() {
+            let index = self.state.index(body, world);
+            let (position, orientation) = sim.body_pose(handle);
+            let (linear, angular) = sim.body_velocity(handle);
+            self.state.position[index] = position;
+            self.state.orientation[index] = orientation;
+            self.state.linear_velocity[index] = linear;
+            self.state.angular_velocity[index] = angular;
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! # Body Handles
+//!
+//! Rigid bodies are stored in one vector per shape on
+//! [`crate::simulation::PhysicsSim`]. A [`BodyHandle`] names a body
+//! independently of its shape so that subsystems such as the contact solver
+//! can treat every body uniformly.
+
+use crate::types::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// Identifies a body by its shape and its index in the matching vector of
+/// [`crate::simulation::PhysicsSim`].
+///
+/// Handles are ordered first by shape and then by index. Collision pairs are
+/// always stored with the smaller handle first, so static planes,
+/// heightfields and meshes end up as body B of any pair they take part in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum BodyHandle {
+    /// Index into `PhysicsSim::spheres`.
+    Sphere(usize),
+    /// Index into `PhysicsSim::boxes`.
+    Box(usize),
+    /// Index into `PhysicsSim::cylinders`.
+    Cylinder(usize),
+    /// Index into `PhysicsSim::capsules`.
+    Capsule(usize),
+    /// Index into `PhysicsSim::hulls`.
+    Hull(usize),
+    /// Index into `PhysicsSim::compounds`.
+    Compound(usize),
+    /// Index into `PhysicsSim::planes`.
+    Plane(usize),
+    /// Index into `PhysicsSim::heightfields`.
+    Heightfield(usize),
+    /// Index into `PhysicsSim::meshes`.
+    Mesh(usize),
+}
+
+impl BodyHandle {
+    /// Shape code for boxes in the joint builder methods of
+    /// [`crate::simulation::PhysicsSim`].
+    pub const BOX_TYPE: u32 = 0;
+    /// Shape code for spheres.
+    pub const SPHERE_TYPE: u32 = 1;
+    /// Shape code for cylinders.
+    pub const CYLINDER_TYPE: u32 = 2;
+    /// Shape code for planes.
+    pub const PLANE_TYPE: u32 = 3;
+    /// Shape code for heightfields.
+    pub const HEIGHTFIELD_TYPE: u32 = 4;
+    /// Shape code for convex hulls.
+    pub const HULL_TYPE: u32 = 5;
+    /// Shape code for triangle meshes.
+    pub const MESH_TYPE: u32 = 6;
+    /// Shape code for capsules.
+    pub const CAPSULE_TYPE: u32 = 7;
+    /// Shape code for compound bodies.
+    pub const COMPOUND_TYPE: u32 = 8;
+
+    /// Builds a handle from a shape code and an index, as stored in the
+    /// GPU-compatible joint structs. Returns `None` for unknown codes.
+    #[must_use]
+    pub const fn from_type_code(code: u32, index: usize) -> Option<Self> {
+        match code {
+            Self::BOX_TYPE => Some(Self::Box(index)),
+            Self::SPHERE_TYPE => Some(Self::Sphere(index)),
+            Self::CYLINDER_TYPE => Some(Self::Cylinder(index)),
+            Self::PLANE_TYPE => Some(Self::Plane(index)),
+            Self::HEIGHTFIELD_TYPE => Some(Self::Heightfield(index)),
+            Self::HULL_TYPE => Some(Self::Hull(index)),
+            Self::MESH_TYPE => Some(Self::Mesh(index)),
+            Self::CAPSULE_TYPE => Some(Self::Capsule(index)),
+            Self::COMPOUND_TYPE => Some(Self::Compound(index)),
+            _ => None,
+        }
+    }
+
+    /// Shape code of this handle, the inverse of [`Self::from_type_code`].
+    #[must_use]
+    pub const fn type_code(self) -> u32 {
+        match self {
+            Self::Box(_) => Self::BOX_TYPE,
+            Self::Sphere(_) => Self::SPHERE_TYPE,
+            Self::Cylinder(_) => Self::CYLINDER_TYPE,
+            Self::Plane(_) => Self::PLANE_TYPE,
+            Self::Heightfield(_) => Self::HEIGHTFIELD_TYPE,
+            Self::Hull(_) => Self::HULL_TYPE,
+            Self::Mesh(_) => Self::MESH_TYPE,
+            Self::Capsule(_) => Self::CAPSULE_TYPE,
+            Self::Compound(_) => Self::COMPOUND_TYPE,
+        }
+    }
+
+    /// Returns the index of the body within its shape vector.
+    #[must_use]
+    pub const fn index(self) -> usize {
+        match self {
+            Self::Sphere(i)
+            | Self::Box(i)
+            | Self::Cylinder(i)
+            | Self::Capsule(i)
+            | Self::Hull(i)
+            | Self::Compound(i)
+            | Self::Plane(i)
+            | Self::Heightfield(i)
+            | Self::Mesh(i) => i,
+        }
+    }
+}
+
+/// Inverse of the diagonal inertia tensor of a solid sphere.
+pub(crate) fn sphere_inverse_inertia(mass: f32, radius: f32) -> Vec3 {
+    let inertia = 0.4 * mass * radius * radius;
+    Vec3::new(1.0 / inertia, 1.0 / inertia, 1.0 / inertia)
+}
+
+/// Inverse of the diagonal inertia tensor of a solid box, in its local frame.
+pub(crate) fn box_inverse_inertia(mass: f32, half_extents: Vec3) -> Vec3 {
+    let x2 = 4.0 * half_extents.x * half_extents.x;
+    let y2 = 4.0 * half_extents.y * half_extents.y;
+    let z2 = 4.0 * half_extents.z * half_extents.z;
+    let scale = mass / 12.0;
+    Vec3::new(
+        1.0 / (scale * (y2 + z2)),
+        1.0 / (scale * (x2 + z2)),
+        1.0 / (scale * (x2 + y2)),
+    )
+}
+
+/// Inverse of the diagonal inertia tensor of a solid cylinder whose axis is
+/// the local Y axis.
+pub(crate) fn cylinder_inverse_inertia(mass: f32, radius: f32, half_height: f32) -> Vec3 {
+    let height = 2.0 * half_height;
+    let axial = 0.5 * mass * radius * radius;
+    let transverse = mass * (3.0 * radius * radius + height * height) / 12.0;
+    Vec3::new(1.0 / transverse, 1.0 / axial, 1.0 / transverse)
+}
+
+/// Inverse of the diagonal inertia tensor of a solid capsule whose axis is
+/// the local Y axis.
+///
+/// The mass is split between the cylinder and the two hemispherical caps by
+/// volume; the caps are moved out to the ends of the cylinder with the
+/// parallel axis theorem.
+pub(crate) fn capsule_inverse_inertia(mass: f32, radius: f32, half_height: f32) -> Vec3 {
+    let r2 = radius * radius;
+    let cylinder_volume = 2.0 * half_height;
+    let caps_volume = 4.0 / 3.0 * radius;
+    let cylinder_mass = mass * cylinder_volume / (cylinder_volume + caps_volume);
+    let caps_mass = mass - cylinder_mass;
+
+    let axial = 0.5 * cylinder_mass * r2 + 0.4 * caps_mass * r2;
+    let cylinder_transverse = cylinder_mass * (3.0 * r2 + 4.0 * half_height * half_height) / 12.0;
+    // Each hemisphere's centre of mass sits 3r/8 beyond the end of the cylinder
+    let caps_transverse =
+        caps_mass * (0.4 * r2 + half_height * half_height + 0.75 * half_height * radius);
+    let transverse = cylinder_transverse + caps_transverse;
+    Vec3::new(1.0 / transverse, 1.0 / axial, 1.0 / transverse)
+}
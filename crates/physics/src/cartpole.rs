@@ -4,7 +4,7 @@
 //! the physics simulation components for creating cartpole systems.
 
 use crate::types::{Vec3, Vec2};
-use crate::PhysicsSim;
+use crate::{BodyHandle, PhysicsSim};
 
 /// Configuration for a CartPole entity
 #[derive(Clone, Debug)]
@@ -90,7 +90,7 @@ impl CartPole {
         let pole_pos = Vec3::new(
             joint_world_pos.x + pole_offset_x,
             joint_world_pos.y + pole_offset_y,
-            0.0  // Always at z=0 for 2D constraint
+            joint_world_pos.z
         );
         
         // Create pole as dynamic body (affected by gravity)
@@ -109,24 +109,21 @@ impl CartPole {
         );
         sim.cylinders[pole_idx].mass = config.pole_mass;
         
-        // CRITICAL FIX: Set initial orientation to match the initial angle
-        let half_angle = config.initial_angle * 0.5;
-        let sin_half = half_angle.sin();
-        let cos_half = half_angle.cos();
-        sim.cylinders[pole_idx].orientation = [
-            0.0,         // x
-            0.0,         // y
-            sin_half,    // z (rotation around z-axis)
-            cos_half,    // w
-        ];
+        sim.cylinders[pole_idx].orientation = pole_orientation(config.initial_angle);
         
         // Create revolute joint
         let joint_idx = sim.add_revolute_joint(
-            0, cart_idx as u32,  // Box type
-            2, pole_idx as u32,  // Cylinder type
+            BodyHandle::BOX_TYPE, cart_idx as u32,
+            BodyHandle::CYLINDER_TYPE, pole_idx as u32,
             joint_world_pos,
             Vec3::new(0.0, 0.0, 1.0) // Rotate around Z axis (perpendicular to X-Y plane)
         );
+        // The pole rests on the cart at the hinge, so keep them from colliding
+        sim.revolute_joints[joint_idx].disable_collision = 1;
+        
+        // Keep the cart and pole in the X-Y plane they start in
+        sim.add_planar_constraint(BodyHandle::Box(cart_idx), Vec3::new(0.0, 0.0, 1.0));
+        sim.add_planar_constraint(BodyHandle::Cylinder(pole_idx), Vec3::new(0.0, 0.0, 1.0));
         
         Self {
             cart_idx,
@@ -212,7 +209,8 @@ impl CartPole {
             cart.pos.x,
             cart.vel.x,
             self.get_pole_angle(sim),
-            pole.angular_vel.z, // Angular velocity around Z axis
+            // The angle grows towards +X, which is a negative rotation about Z
+            -pole.angular_vel.z,
         ]
     }
     
@@ -247,22 +245,20 @@ impl CartPole {
         sim.cylinders[self.pole_idx].vel = Vec3::ZERO;
         sim.cylinders[self.pole_idx].angular_vel = Vec3::ZERO;
         
-        // CRITICAL FIX: Reset orientation to match initial angle
-        let half_angle = self.config.initial_angle * 0.5;
-        let sin_half = half_angle.sin();
-        let cos_half = half_angle.cos();
-        sim.cylinders[self.pole_idx].orientation = [
-            0.0,         // x
-            0.0,         // y
-            sin_half,    // z (rotation around z-axis)
-            cos_half,    // w
-        ];
+        sim.cylinders[self.pole_idx].orientation = pole_orientation(self.config.initial_angle);
         
         // Clear any applied forces
         sim.set_force(self.cart_idx, [0.0, 0.0]);
     }
 }
 
+/// Orientation of a pole tilted by `angle` from vertical towards +X.
+fn pole_orientation(angle: f32) -> [f32; 4] {
+    // Tilting towards +X is a negative rotation about the Z axis
+    let half_angle = -angle * 0.5;
+    [0.0, 0.0, half_angle.sin(), half_angle.cos()]
+}
+
 /// Manages multiple CartPole entities in a grid layout
 pub struct CartPoleGrid {
     /// All cartpoles in the grid
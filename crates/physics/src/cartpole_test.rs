@@ -70,9 +70,9 @@ fn test_inverted_pendulum_falls() {
     assert!(oscillation_range > 0.1, 
             "Pole should oscillate with range > 0.1 rad (got {:.3} rad)", oscillation_range);
     
-    // Pole should move back toward vertical (start at +30°, should go negative)
-    assert!(min_angle < 0.0, 
-            "Inverted pendulum should swing past vertical (min angle = {:.3} rad)", min_angle);
+    // Nothing holds the pole up, so it should fall away from vertical
+    assert!(max_angle > std::f32::consts::FRAC_PI_2, 
+            "Inverted pendulum should fall past horizontal (max angle = {:.3} rad)", max_angle);
     
     // Check that pendulum shows proper dynamics (not stuck at one angle)
     let angle_changes: Vec<f32> = angles.windows(2)
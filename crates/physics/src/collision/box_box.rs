@@ -1,113 +1,322 @@
-//! Box-box collision detection and response
+//! Box-box contact generation
+//!
+//! Uses the separating axis test over the 15 candidate axes of two oriented
+//! boxes. Face contacts clip the incident face of one box against the side
+//! planes of the reference face of the other, giving up to eight points that
+//! the manifold reduces to four. Edge contacts produce the closest point pair
+//! between the two edges.
 
-use crate::types::{Vec3, BoxBody, Material};
-use super::{Contact, combine_friction, combine_restitution};
+use glam::{Quat, Vec3};
 
-/// Detect collision between two boxes
-pub fn detect_box_box_collision(
+use crate::types::BoxBody;
+use super::manifold::{body_rotation, ManifoldPoint, ManifoldPoints};
+
+/// Separation by which an edge axis must beat the best face axis before it is
+/// used. Face contacts give richer manifolds, so they are preferred.
+const EDGE_AXIS_TOLERANCE: f32 = 0.005;
+/// Separation by which a face of box B must beat the best face of box A.
+const FACE_AXIS_TOLERANCE: f32 = 0.001;
+/// Incident vertices overhanging the reference face by less than this are
+/// kept as they are. Without it, boxes of equal size stacked exactly on top
+/// of each other flip between vertex and clipped points from float noise,
+/// and lose their warm-start impulses.
+const CLIP_TOLERANCE: f32 = 0.005;
+
+/// Oriented box in world space.
+#[derive(Copy, Clone)]
+struct Obb {
+    center: Vec3,
+    axes: [Vec3; 3],
+    half: Vec3,
+}
+
+impl Obb {
+    fn new(box_body: &BoxBody) -> Self {
+        let rotation: Quat = body_rotation(box_body.orientation);
+        Self {
+            center: box_body.pos.into(),
+            axes: [rotation * Vec3::X, rotation * Vec3::Y, rotation * Vec3::Z],
+            half: box_body.half_extents.into(),
+        }
+    }
+
+    fn projected_radius(&self, axis: Vec3) -> f32 {
+        self.half.x * self.axes[0].dot(axis).abs()
+            + self.half.y * self.axes[1].dot(axis).abs()
+            + self.half.z * self.axes[2].dot(axis).abs()
+    }
+
+    /// Corners of face `face` (axis `face / 2`, negative side when odd), in
+    /// counter-clockwise order around the outward normal.
+    fn face_vertices(&self, face: usize) -> [Vec3; 4] {
+        let axis = face / 2;
+        let sign = if face.is_multiple_of(2) { 1.0 } else { -1.0 };
+        let u = (axis + 1) % 3;
+        let v = (axis + 2) % 3;
+        let center = self.center + self.axes[axis] * (sign * self.half[axis]);
+        let du = self.axes[u] * self.half[u];
+        let dv = self.axes[v] * (self.half[v] * sign);
+        [center + du + dv, center - du + dv, center - du - dv, center + du - dv]
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Axis {
+    /// Face normal `index` of box A.
+    FaceA(usize),
+    /// Face normal `index` of box B.
+    FaceB(usize),
+    /// Cross product of edge `i` of box A and edge `j` of box B.
+    Edge(usize, usize),
+}
+
+#[derive(Copy, Clone)]
+struct AxisQuery {
+    axis: Axis,
+    /// Axis oriented from A to B.
+    normal: Vec3,
+    separation: f32,
+}
+
+/// Generate a manifold between two oriented boxes.
+pub(crate) fn box_box_manifold(
     box_a: &BoxBody,
     box_b: &BoxBody,
-) -> Option<Contact> {
-    // Calculate separation on each axis
-    let center_diff = box_b.pos - box_a.pos;
-    let combined_half_extents = box_a.half_extents + box_b.half_extents;
-    
-    // Check if boxes overlap on all axes
-    let overlap_x = combined_half_extents.x - center_diff.x.abs();
-    let overlap_y = combined_half_extents.y - center_diff.y.abs();
-    let overlap_z = combined_half_extents.z - center_diff.z.abs();
-    
-    if overlap_x > 0.0 && overlap_y > 0.0 && overlap_z > 0.0 {
-        // Find axis with smallest overlap (separation axis)
-        let min_overlap = overlap_x.min(overlap_y).min(overlap_z);
-        
-        let normal = if overlap_x == min_overlap {
-            Vec3::new(center_diff.x.signum(), 0.0, 0.0)
-        } else if overlap_y == min_overlap {
-            Vec3::new(0.0, center_diff.y.signum(), 0.0)
-        } else {
-            Vec3::new(0.0, 0.0, center_diff.z.signum())
-        };
-        
-        // Contact point is at the midpoint between the boxes along the normal
-        let contact_point = box_a.pos + normal * (box_a.half_extents.dot(normal.abs()));
-        
-        Some(Contact::new(
-            contact_point,
-            normal,
-            min_overlap,
-            &box_a.material,
-            &box_b.material,
-        ))
+    margin: f32,
+) -> Option<ManifoldPoints> {
+    let a = Obb::new(box_a);
+    let b = Obb::new(box_b);
+    let offset = b.center - a.center;
+
+    let test = |axis: Axis, direction: Vec3| -> Option<AxisQuery> {
+        let length = direction.length();
+        if length < 1e-5 {
+            return None;
+        }
+        let mut normal = direction / length;
+        if normal.dot(offset) < 0.0 {
+            normal = -normal;
+        }
+        let separation =
+            normal.dot(offset) - a.projected_radius(normal) - b.projected_radius(normal);
+        Some(AxisQuery { axis, normal, separation })
+    };
+
+    let mut best_face_a: Option<AxisQuery> = None;
+    let mut best_face_b: Option<AxisQuery> = None;
+    let mut best_edge: Option<AxisQuery> = None;
+    let keep = |best: &mut Option<AxisQuery>, query: AxisQuery| {
+        if best.is_none_or(|current| query.separation > current.separation) {
+            *best = Some(query);
+        }
+    };
+
+    for i in 0..3 {
+        let query = test(Axis::FaceA(i), a.axes[i])?;
+        if query.separation > margin {
+            return None;
+        }
+        keep(&mut best_face_a, query);
+    }
+    for i in 0..3 {
+        let query = test(Axis::FaceB(i), b.axes[i])?;
+        if query.separation > margin {
+            return None;
+        }
+        keep(&mut best_face_b, query);
+    }
+    for i in 0..3 {
+        for j in 0..3 {
+            if let Some(query) = test(Axis::Edge(i, j), a.axes[i].cross(b.axes[j])) {
+                if query.separation > margin {
+                    return None;
+                }
+                keep(&mut best_edge, query);
+            }
+        }
+    }
+
+    let face_a = best_face_a?;
+    let face_b = best_face_b?;
+    let mut best = if face_b.separation > face_a.separation + FACE_AXIS_TOLERANCE {
+        face_b
     } else {
-        None
+        face_a
+    };
+    if let Some(edge) = best_edge {
+        if edge.separation > best.separation + EDGE_AXIS_TOLERANCE {
+            best = edge;
+        }
     }
+
+    let points = match best.axis {
+        Axis::FaceA(axis) => face_contact(&a, &b, axis, best.normal, margin, 0),
+        Axis::FaceB(axis) => face_contact(&b, &a, axis, -best.normal, margin, 1 << 12),
+        Axis::Edge(i, j) => edge_contact(&a, &b, i, j, best.normal, best.separation),
+    };
+
+    (!points.is_empty()).then_some(ManifoldPoints {
+        normal: best.normal,
+        points,
+    })
 }
 
-/// Resolve collision between two boxes
-pub fn resolve_box_box_collision(
-    box_a: &mut BoxBody,
-    box_b: &mut BoxBody,
-    contact: &Contact,
-) {
-    // Calculate relative velocity
-    let relative_velocity = box_b.vel - box_a.vel;
-    let velocity_along_normal = relative_velocity.dot(contact.normal);
-    
-    // Don't resolve if velocities are separating
-    if velocity_along_normal > 0.0 {
-        return;
+/// Largest gap between two oriented boxes along any of the 15 candidate
+/// axes. It never exceeds the true distance between the boxes, and is
+/// negative when they overlap.
+pub(crate) fn box_box_separation(box_a: &BoxBody, box_b: &BoxBody) -> f32 {
+    let a = Obb::new(box_a);
+    let b = Obb::new(box_b);
+    let offset = b.center - a.center;
+    let edges = (0..9).map(|k| a.axes[k / 3].cross(b.axes[k % 3]));
+    a.axes
+        .into_iter()
+        .chain(b.axes)
+        .chain(edges)
+        .filter_map(Vec3::try_normalize)
+        .map(|normal| normal.dot(offset).abs() - a.projected_radius(normal) - b.projected_radius(normal))
+        .fold(f32::NEG_INFINITY, f32::max)
+}
+
+/// Clip the incident face of `incident` against the reference face of
+/// `reference` whose outward normal is `normal`.
+fn face_contact(
+    reference: &Obb,
+    incident: &Obb,
+    axis: usize,
+    normal: Vec3,
+    margin: f32,
+    id_base: u32,
+) -> Vec<ManifoldPoint> {
+    let reference_face = if reference.axes[axis].dot(normal) > 0.0 {
+        2 * axis
+    } else {
+        2 * axis + 1
+    };
+
+    // Incident face is the one most anti-parallel to the reference normal.
+    let mut incident_face = 0;
+    let mut most_negative = f32::INFINITY;
+    for i in 0..3 {
+        let alignment = incident.axes[i].dot(normal);
+        if alignment < most_negative {
+            most_negative = alignment;
+            incident_face = 2 * i;
+        }
+        if -alignment < most_negative {
+            most_negative = -alignment;
+            incident_face = 2 * i + 1;
+        }
     }
-    
-    // Calculate impulse
-    let mass_sum = 1.0 / box_a.mass + 1.0 / box_b.mass;
-    let restitution = contact.restitution;
-    let impulse_magnitude = -(1.0 + restitution) * velocity_along_normal / mass_sum;
-    let impulse = contact.normal * impulse_magnitude;
-    
-    // Apply impulse
-    box_a.vel -= impulse / box_a.mass;
-    box_b.vel += impulse / box_b.mass;
-    
-    // Position correction to resolve penetration
-    if contact.depth > 0.001 {
-        let correction = contact.normal * contact.depth * 0.8;
-        let mass_ratio_a = (1.0 / box_a.mass) / mass_sum;
-        let mass_ratio_b = (1.0 / box_b.mass) / mass_sum;
-        
-        box_a.pos -= correction * mass_ratio_a;
-        box_b.pos += correction * mass_ratio_b;
+
+    #[allow(clippy::cast_possible_truncation)]
+    let face_ids = (id_base | (reference_face as u32) << 8) | (incident_face as u32) << 4;
+    let mut polygon: Vec<(Vec3, u32)> = incident
+        .face_vertices(incident_face)
+        .iter()
+        .zip(0u32..)
+        .map(|(&vertex, index)| (vertex, face_ids | index))
+        .collect();
+
+    // Clip against the four side planes of the reference face.
+    let side_axes = [(axis + 1) % 3, (axis + 2) % 3];
+    let mut plane_index = 0u32;
+    for &side in &side_axes {
+        for sign in [1.0f32, -1.0] {
+            let side_normal = reference.axes[side] * sign;
+            let offset = side_normal.dot(reference.center) + reference.half[side] + CLIP_TOLERANCE;
+            polygon = clip_polygon(&polygon, side_normal, offset, face_ids | 0x8 | plane_index);
+            plane_index += 1;
+            if polygon.is_empty() {
+                return Vec::new();
+            }
+        }
     }
-    
-    // Apply friction
-    apply_friction(box_a, box_b, contact, impulse_magnitude);
+
+    let face_center = reference.center + normal * reference.half[axis];
+    polygon
+        .into_iter()
+        .filter_map(|(vertex, feature_id)| {
+            let separation = (vertex - face_center).dot(normal);
+            (separation <= margin).then(|| ManifoldPoint {
+                position: vertex - normal * (0.5 * separation),
+                depth: -separation,
+                feature_id,
+            })
+        })
+        .collect()
 }
 
-fn apply_friction(
-    box_a: &mut BoxBody,
-    box_b: &mut BoxBody,
-    contact: &Contact,
-    normal_impulse: f32,
-) {
-    if contact.friction <= 0.0 || normal_impulse <= 0.0 {
-        return;
+/// Sutherland-Hodgman clip of `polygon` against the half-space
+/// `normal · x <= offset`. Points created on the clip plane get `clip_id`
+/// combined with the index of the edge they came from.
+pub(super) fn clip_polygon(polygon: &[(Vec3, u32)], normal: Vec3, offset: f32, clip_id: u32) -> Vec<(Vec3, u32)> {
+    let mut output = Vec::with_capacity(polygon.len() + 1);
+    for (i, &(start, start_id)) in polygon.iter().enumerate() {
+        let (end, _) = polygon[(i + 1) % polygon.len()];
+        let start_distance = normal.dot(start) - offset;
+        let end_distance = normal.dot(end) - offset;
+
+        if start_distance <= 0.0 {
+            output.push((start, start_id));
+        }
+        if (start_distance <= 0.0) != (end_distance <= 0.0) {
+            let t = start_distance / (start_distance - end_distance);
+            #[allow(clippy::cast_possible_truncation)]
+            let edge_id = (i as u32) << 16;
+            output.push((start + (end - start) * t, clip_id | 0x1000_0000 | edge_id));
+        }
     }
-    
-    let relative_velocity = box_b.vel - box_a.vel;
-    let velocity_along_normal = relative_velocity.dot(contact.normal);
-    let tangent_velocity = relative_velocity - contact.normal * velocity_along_normal;
-    let tangent_speed = tangent_velocity.length();
-    
-    if tangent_speed < 0.001 {
-        return;
+    output
+}
+
+/// Closest points between the supporting edges of an edge-edge contact.
+fn edge_contact(
+    a: &Obb,
+    b: &Obb,
+    edge_a: usize,
+    edge_b: usize,
+    normal: Vec3,
+    separation: f32,
+) -> Vec<ManifoldPoint> {
+    // Supporting edge of A in direction `normal` and of B in `-normal`.
+    let mut point_a = a.center;
+    let mut point_b = b.center;
+    for k in 0..3 {
+        if k != edge_a {
+            point_a += a.axes[k] * (a.half[k] * a.axes[k].dot(normal).signum());
+        }
+        if k != edge_b {
+            point_b -= b.axes[k] * (b.half[k] * b.axes[k].dot(normal).signum());
+        }
     }
-    
-    let tangent_direction = tangent_velocity / tangent_speed;
-    let mass_sum = 1.0 / box_a.mass + 1.0 / box_b.mass;
-    let max_friction = tangent_speed / mass_sum;
-    let friction_impulse = (contact.friction * normal_impulse.abs()).min(max_friction);
-    
-    let friction = tangent_direction * friction_impulse;
-    box_a.vel += friction / box_a.mass;
-    box_b.vel -= friction / box_b.mass;
-}
\ No newline at end of file
+    let dir_a = a.axes[edge_a];
+    let dir_b = b.axes[edge_b];
+
+    // Closest points between the two infinite lines, clamped to the edges.
+    let between = point_a - point_b;
+    let alignment = dir_a.dot(dir_b);
+    let denominator = 1.0 - alignment * alignment;
+    let (along_a, along_b) = if denominator.abs() < 1e-6 {
+        (0.0, dir_b.dot(between))
+    } else {
+        let proj_a = dir_a.dot(between);
+        let proj_b = dir_b.dot(between);
+        (
+            (alignment * proj_b - proj_a) / denominator,
+            (proj_b - alignment * proj_a) / denominator,
+        )
+    };
+    let along_a = along_a.clamp(-a.half[edge_a], a.half[edge_a]);
+    let along_b = along_b.clamp(-b.half[edge_b], b.half[edge_b]);
+
+    let on_a = point_a + dir_a * along_a;
+    let on_b = point_b + dir_b * along_b;
+    #[allow(clippy::cast_possible_truncation)]
+    let feature_id = 0x2000_0000 | ((edge_a * 3 + edge_b) as u32);
+    vec![ManifoldPoint {
+        position: (on_a + on_b) * 0.5,
+        depth: -separation,
+        feature_id,
+    }]
+}
@@ -2,6 +2,7 @@
 
 use crate::types::{Vec3, BoxBody, Plane};
 use super::Contact;
+use super::manifold::{body_rotation, ManifoldPoint, ManifoldPoints};
 
 /// Detect collision between a box and a plane
 pub fn detect_box_plane_collision(
@@ -92,6 +93,42 @@ pub fn resolve_box_plane_collision(
     box_body.angular_vel *= 0.98;
 }
 
+/// Generate a manifold between an oriented box and a plane from the box
+/// corners that lie within `margin` of the plane. The corner index is used as
+/// feature id.
+pub(crate) fn box_plane_manifold(
+    box_body: &BoxBody,
+    plane: &Plane,
+    margin: f32,
+) -> Option<ManifoldPoints> {
+    let rotation = body_rotation(box_body.orientation);
+    let center: glam::Vec3 = box_body.pos.into();
+    let half: glam::Vec3 = box_body.half_extents.into();
+    let plane_normal: glam::Vec3 = plane.normal.into();
+
+    let points: Vec<ManifoldPoint> = (0..8u32)
+        .filter_map(|corner| {
+            let local = glam::Vec3::new(
+                if corner & 1 == 0 { -half.x } else { half.x },
+                if corner & 2 == 0 { -half.y } else { half.y },
+                if corner & 4 == 0 { -half.z } else { half.z },
+            );
+            let world = center + rotation * local;
+            let distance = plane_normal.dot(world) + plane.d;
+            (distance <= margin).then(|| ManifoldPoint {
+                position: world - plane_normal * (0.5 * distance),
+                depth: -distance,
+                feature_id: corner,
+            })
+        })
+        .collect();
+
+    (!points.is_empty()).then(|| ManifoldPoints {
+        normal: -plane_normal,
+        points,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
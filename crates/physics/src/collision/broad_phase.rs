@@ -1,6 +1,14 @@
 //! Broad-phase collision detection using spatial partitioning
+//!
+//! Every body except planes is bounded by an axis-aligned box, grown by the
+//! contact margin; a heightfield is bounded by the box around its terrain. Overlapping boxes are found either with incremental
+//! sweep and prune or with the uniform [`SpatialGrid`]. Planes are infinite
+//! half-spaces, so they are tested directly against every box.
 
-use crate::types::{Vec3, Sphere, SpatialGrid, BoundingBox};
+use crate::body::BodyHandle;
+use crate::types::{Vec3, Sphere, SpatialGrid, BoundingBox, Plane};
+use super::manifold::body_rotation;
+use super::Primitive;
 
 /// Update spatial grid with current sphere positions
 pub fn update_spatial_grid(grid: &mut SpatialGrid, spheres: &[Sphere]) {
@@ -44,6 +52,118 @@ fn sphere_bounding_box(sphere: &Sphere) -> BoundingBox {
     }
 }
 
+/// Axis-aligned bounds of a body grown by `margin` on every side. Planes are
+/// unbounded and return `None`.
+pub(crate) fn primitive_bounding_box(primitive: &Primitive<'_>, margin: f32) -> Option<BoundingBox> {
+    let (center, half) = match *primitive {
+        Primitive::Sphere(sphere) => (glam::Vec3::from(sphere.pos), glam::Vec3::splat(sphere.radius)),
+        Primitive::Box(box_body) => {
+            let rotation = glam::Mat3::from_quat(body_rotation(box_body.orientation));
+            let half = glam::Vec3::from(box_body.half_extents);
+            let extent = glam::Mat3::from_cols(rotation.x_axis.abs(), rotation.y_axis.abs(), rotation.z_axis.abs()) * half;
+            (glam::Vec3::from(box_body.pos), extent)
+        }
+        Primitive::Cylinder(cylinder) => {
+            let rotation = body_rotation(cylinder.orientation);
+            let center = glam::Vec3::from(cylinder.pos) + rotation * glam::Vec3::from(cylinder.shape_offset);
+            // A disc of radius r with unit normal n spans r * sqrt(1 - n_i^2)
+            // along axis i; the cylinder adds its half-height along n.
+            let axis = rotation * glam::Vec3::Y;
+            let disc = (glam::Vec3::ONE - axis * axis).max(glam::Vec3::ZERO);
+            let disc = glam::Vec3::new(disc.x.sqrt(), disc.y.sqrt(), disc.z.sqrt());
+            let extent = axis.abs() * cylinder.half_height + disc * cylinder.radius;
+            (center, extent)
+        }
+        Primitive::Capsule(capsule) => {
+            let axis = body_rotation(capsule.orientation) * glam::Vec3::Y;
+            let extent = axis.abs() * capsule.half_height + glam::Vec3::splat(capsule.radius);
+            (glam::Vec3::from(capsule.pos), extent)
+        }
+        Primitive::Hull(_) => {
+            let (min, max) = super::Convex::of(primitive)?.bounds();
+            ((min + max) * 0.5, (max - min) * 0.5)
+        }
+        Primitive::Compound(compound) => {
+            let (min, max) = compound
+                .child_shapes()
+                .iter()
+                .filter_map(|shape| primitive_bounding_box(&shape.primitive(), 0.0))
+                .fold((glam::Vec3::INFINITY, glam::Vec3::NEG_INFINITY), |(min, max), bounds| {
+                    (min.min(bounds.min.into()), max.max(bounds.max.into()))
+                });
+            if min.x > max.x {
+                let pos = glam::Vec3::from(compound.pos);
+                (pos, glam::Vec3::ZERO)
+            } else {
+                ((min + max) * 0.5, (max - min) * 0.5)
+            }
+        }
+        Primitive::Heightfield(heightfield) => {
+            let bounds = heightfield.bounds();
+            let (min, max) = (glam::Vec3::from(bounds.min), glam::Vec3::from(bounds.max));
+            ((min + max) * 0.5, (max - min) * 0.5)
+        }
+        Primitive::Mesh(mesh) => {
+            let bounds = mesh.bounds();
+            let (min, max) = (glam::Vec3::from(bounds.min), glam::Vec3::from(bounds.max));
+            ((min + max) * 0.5, (max - min) * 0.5)
+        }
+        Primitive::Plane(_) => return None,
+    };
+    let half = half + glam::Vec3::splat(margin);
+    Some(BoundingBox {
+        min: (center - half).into(),
+        max: (center + half).into(),
+    })
+}
+
+/// Whether a bounding box reaches into the half-space behind a plane.
+pub(crate) fn plane_overlaps_box(plane: &Plane, bounds: &BoundingBox) -> bool {
+    let normal = glam::Vec3::from(plane.normal);
+    let min = glam::Vec3::from(bounds.min);
+    let max = glam::Vec3::from(bounds.max);
+    let center = (min + max) * 0.5;
+    let radius = normal.abs().dot((max - min) * 0.5);
+    normal.dot(center) + plane.d <= radius
+}
+
+/// Candidate pairs among `proxies` from a uniform grid. Bodies that are not
+/// fully inside the grid bounds are tested against every other body, so no
+/// pair is lost when bodies leave the grid.
+pub(crate) fn spatial_grid_pairs(
+    grid: &mut SpatialGrid,
+    proxies: &[(BodyHandle, BoundingBox)],
+) -> Vec<(BodyHandle, BodyHandle)> {
+    grid.clear();
+    let mut outside = Vec::new();
+    for (index, (_, bounds)) in proxies.iter().enumerate() {
+        if contains(&grid.bounds, bounds) {
+            grid.insert(index, *bounds);
+        } else {
+            outside.push(index);
+        }
+    }
+
+    let mut pairs: Vec<(usize, usize)> = get_potential_collision_pairs(grid);
+    for (i, &a) in outside.iter().enumerate() {
+        // Every body inside the grid, and each other outside body once
+        let inside = (0..proxies.len()).filter(|b| !outside.contains(b));
+        let later = outside[i + 1..].iter().copied();
+        pairs.extend(inside.chain(later).map(|b| (a.min(b), a.max(b))));
+    }
+    pairs
+        .into_iter()
+        .filter(|&(a, b)| boxes_overlap(&proxies[a].1, &proxies[b].1))
+        .map(|(a, b)| (proxies[a].0, proxies[b].0))
+        .collect()
+}
+
+fn contains(outer: &BoundingBox, inner: &BoundingBox) -> bool {
+    outer.min.x <= inner.min.x && inner.max.x <= outer.max.x &&
+    outer.min.y <= inner.min.y && inner.max.y <= outer.max.y &&
+    outer.min.z <= inner.min.z && inner.max.z <= outer.max.z
+}
+
 /// Check if two bounding boxes overlap
 pub fn boxes_overlap(a: &BoundingBox, b: &BoundingBox) -> bool {
     a.min.x <= b.max.x && a.max.x >= b.min.x &&
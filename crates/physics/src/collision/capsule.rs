@@ -0,0 +1,411 @@
+//! Contacts of capsules
+//!
+//! A capsule is everything within its radius of a core segment, so every
+//! pair reduces to the closest points between that segment and the core of
+//! the other shape: a point for spheres, a segment for capsules, and the
+//! solid shape itself for boxes and cylinders. Where the capsule lies along
+//! a flat face, or parallel to another capsule or to the side of a
+//! cylinder, the segment is clipped to the part that overlaps the other
+//! shape and both ends of that part become contact points, so a capsule
+//! lying on its side rests on two points instead of rocking on one.
+//! Capsules against hulls, heightfields and meshes take the general convex
+//! path, which clips the segment against faces the same way.
+
+use glam::{Vec2, Vec3};
+
+use crate::types::{BoxBody, Capsule, Cylinder, Plane, Sphere};
+use super::convex::{capsule_segment, disc_interval, slab, Convex};
+use super::manifold::{body_rotation, ManifoldPoint, ManifoldPoints};
+
+/// Smallest cosine between the contact normal and a face, or between two
+/// axes, for the capsule to be treated as lying along it.
+const PARALLEL_ALIGNMENT: f32 = 0.99;
+/// Feature id of the point between the closest points of the two shapes.
+const CLOSEST: u32 = 2;
+/// Feature id bit of points on the core of body B.
+const END_OF_B: u32 = 1 << 2;
+
+/// Generate a manifold between a sphere and a capsule.
+pub(crate) fn sphere_capsule_manifold(sphere: &Sphere, capsule: &Capsule, margin: f32) -> Option<ManifoldPoints> {
+    let center = Vec3::from(sphere.pos);
+    let (start, end) = capsule_segment(capsule);
+    let closest = closest_on_segment(center, start, end);
+    let normal = (closest - center).try_normalize().unwrap_or_else(|| (end - start).any_orthonormal_vector());
+    let point = core_point(center, closest, CLOSEST, normal, (sphere.radius, capsule.radius), margin)?;
+    Some(ManifoldPoints {
+        normal,
+        points: vec![point],
+    })
+}
+
+/// Generate a manifold between two capsules.
+pub(crate) fn capsule_capsule_manifold(a: &Capsule, b: &Capsule, margin: f32) -> Option<ManifoldPoints> {
+    let (start_a, end_a) = capsule_segment(a);
+    let (start_b, end_b) = capsule_segment(b);
+    let (s, t) = closest_between_segments((start_a, end_a), (start_b, end_b));
+    let (closest_a, closest_b) = (start_a.lerp(end_a, s), start_b.lerp(end_b, t));
+    let (axis_a, axis_b) = (end_a - start_a, end_b - start_b);
+    let normal = (closest_b - closest_a).try_normalize().unwrap_or_else(|| {
+        // Crossing cores: push apart across both axes, towards B
+        let across = axis_a.cross(axis_b).try_normalize().unwrap_or_else(|| axis_a.any_orthonormal_vector());
+        if across.dot(Vec3::from(b.pos) - Vec3::from(a.pos)) < 0.0 { -across } else { across }
+    });
+    let radii = (a.radius, b.radius);
+
+    // Parallel cores touch along the part of A that lies beside B
+    let parallel = axis_a.normalize_or_zero().dot(axis_b.normalize_or_zero()).abs() >= PARALLEL_ALIGNMENT;
+    let points: Vec<ManifoldPoint> = match overlap_on(axis_a, start_a, start_b, end_b).filter(|_| parallel) {
+        Some((low, high)) => [(low, 0), (high, 1)]
+            .into_iter()
+            .filter_map(|(s, id)| {
+                let on_a = start_a.lerp(end_a, s);
+                core_point(on_a, closest_on_segment(on_a, start_b, end_b), id, normal, radii, margin)
+            })
+            .collect(),
+        None => core_point(closest_a, closest_b, CLOSEST, normal, radii, margin).into_iter().collect(),
+    };
+    (!points.is_empty()).then_some(ManifoldPoints { normal, points })
+}
+
+/// Generate a manifold between a capsule and a plane.
+pub(crate) fn capsule_plane_manifold(capsule: &Capsule, plane: &Plane, margin: f32) -> Option<ManifoldPoints> {
+    let plane_normal = Vec3::from(plane.normal);
+    let (start, end) = capsule_segment(capsule);
+    let points: Vec<ManifoldPoint> = [(start, 0), (end, 1)]
+        .into_iter()
+        .filter_map(|(tip, feature_id)| {
+            let distance = plane_normal.dot(tip) + plane.d - capsule.radius;
+            (distance <= margin).then(|| ManifoldPoint {
+                position: tip - plane_normal * (capsule.radius + 0.5 * distance),
+                depth: -distance,
+                feature_id,
+            })
+        })
+        .collect();
+
+    (!points.is_empty()).then(|| ManifoldPoints {
+        normal: -plane_normal,
+        points,
+    })
+}
+
+/// Generate a manifold between a box and a capsule.
+pub(crate) fn box_capsule_manifold(box_body: &BoxBody, capsule: &Capsule, margin: f32) -> Option<ManifoldPoints> {
+    let rotation = body_rotation(box_body.orientation);
+    let center = Vec3::from(box_body.pos);
+    let half = Vec3::from(box_body.half_extents);
+    let (start, end) = capsule_segment(capsule);
+    let local_start = rotation.inverse() * (start - center);
+    let local_end = rotation.inverse() * (end - center);
+
+    let (normal, closest) = if let Some((on_box, on_segment)) = segment_box_closest(local_start, local_end, half) {
+        let offset = on_segment - on_box;
+        if offset.length() > capsule.radius + margin {
+            return None;
+        }
+        let normal = rotation * offset.try_normalize()?;
+        (normal, Some((center + rotation * on_box, center + rotation * on_segment)))
+    } else {
+        // The core passes through the box: push it out through the face
+        // that takes the shortest move
+        let (axis, sign) = (0..3)
+            .flat_map(|axis| [(axis, 1.0), (axis, -1.0)])
+            .min_by(|&(i, si), &(j, sj)| {
+                let push = |axis: usize, sign: f32| half[axis] - (sign * local_start[axis]).min(sign * local_end[axis]);
+                push(i, si).total_cmp(&push(j, sj))
+            })?;
+        let mut local = Vec3::ZERO;
+        local[axis] = sign;
+        (rotation * local, None)
+    };
+
+    let face = Convex::Box(box_body).face(normal)?;
+    if face.0.dot(normal) >= PARALLEL_ALIGNMENT || closest.is_none() {
+        let points = segment_face_points((start, end), capsule.radius, &face, END_OF_B, margin);
+        if !points.is_empty() {
+            return Some(ManifoldPoints { normal: face.0, points });
+        }
+    }
+    let (on_box, on_segment) = closest?;
+    let gap = (on_segment - on_box).length() - capsule.radius;
+    Some(ManifoldPoints {
+        normal,
+        points: vec![ManifoldPoint {
+            position: on_box + normal * (0.5 * gap),
+            depth: -gap,
+            feature_id: CLOSEST,
+        }],
+    })
+}
+
+/// Generate a manifold between a cylinder and a capsule.
+pub(crate) fn cylinder_capsule_manifold(cylinder: &Cylinder, capsule: &Capsule, margin: f32) -> Option<ManifoldPoints> {
+    let rotation = body_rotation(cylinder.orientation);
+    let center = Vec3::from(cylinder.pos) + rotation * Vec3::from(cylinder.shape_offset);
+    let (start, end) = capsule_segment(capsule);
+    let to_local = |point: Vec3| rotation.inverse() * (point - center);
+    let (local_start, local_end) = (to_local(start), to_local(end));
+    let (half_height, radius) = (cylinder.half_height, cylinder.radius);
+    let direction = local_end - local_start;
+
+    // Closest points between the core and the cylinder's axis say whether
+    // the capsule is beside the cylinder or beyond one of its caps
+    let axis = (Vec3::new(0.0, -half_height, 0.0), Vec3::new(0.0, half_height, 0.0));
+    let (s, t) = closest_between_segments((local_start, local_end), axis);
+    let on_core = local_start.lerp(local_end, s);
+    let radial = Vec2::new(on_core.x, on_core.z);
+    let beside = t > 0.0 && t < 1.0;
+    let outward = |point: Vec3| {
+        Vec2::new(point.x, point.z).try_normalize().unwrap_or_else(|| {
+            let sideways = Vec2::new(direction.z, -direction.x);
+            sideways.try_normalize().unwrap_or(Vec2::X)
+        })
+    };
+
+    // A core inside the cylinder leaves through the side or a cap, whichever
+    // is closer
+    let side_first = radial.length() >= radius || radius - radial.length() <= half_height - on_core.y.abs();
+
+    let mut points = Vec::new();
+    let local_normal = if beside && side_first {
+        // Side: gaps are measured radially, at both ends of the part of a
+        // parallel core that runs beside the cylinder
+        let side = outward(on_core);
+        let normal = Vec3::new(side.x, 0.0, side.y);
+        let samples: Vec<(f32, u32)> = if direction.normalize_or_zero().y.abs() >= PARALLEL_ALIGNMENT {
+            slab(local_start.y, direction.y, half_height)
+                .map(|(near, far)| (near.max(0.0), far.min(1.0)))
+                .filter(|(near, far)| near <= far)
+                .map_or_else(|| vec![(s, CLOSEST)], |(near, far)| vec![(near, 0), (far, 1)])
+        } else {
+            vec![(s, CLOSEST)]
+        };
+        for (s, feature_id) in samples {
+            let on_core = local_start.lerp(local_end, s);
+            let gap = Vec2::new(on_core.x, on_core.z).dot(side) - radius - capsule.radius;
+            if gap <= margin {
+                let position = on_core - normal * (capsule.radius + 0.5 * gap);
+                points.push((position, -gap, feature_id));
+            }
+        }
+        normal
+    } else {
+        let cap_sign = if on_core.y >= 0.0 { 1.0 } else { -1.0 };
+        let cap_normal = Vec3::new(0.0, cap_sign, 0.0);
+        let over_cap = disc_interval(Vec2::new(local_start.x, local_start.z), Vec2::new(direction.x, direction.z), radius)
+            .map(|(enter, exit)| (enter.max(0.0), exit.min(1.0)))
+            .filter(|(enter, exit)| enter <= exit);
+        if let Some((enter, exit)) = over_cap {
+            // Cap: gaps are measured along the axis, over the part of the
+            // core above the cap
+            for (s, feature_id) in [(enter, 0), (exit, 1)] {
+                let on_core = local_start.lerp(local_end, s);
+                let gap = cap_sign * on_core.y - half_height - capsule.radius;
+                if gap <= margin {
+                    let position = on_core - cap_normal * (capsule.radius + 0.5 * gap);
+                    points.push((position, -gap, feature_id));
+                }
+            }
+            cap_normal
+        } else {
+            // Rim: the core passes beyond the edge of the cap
+            let side = outward(on_core);
+            let rim = Vec3::new(side.x * radius, cap_sign * half_height, side.y * radius);
+            let normal = (on_core - rim).try_normalize()?;
+            let gap = (on_core - rim).length() - capsule.radius;
+            if gap <= margin {
+                points.push((rim + normal * (0.5 * gap), -gap, CLOSEST));
+            }
+            normal
+        }
+    };
+
+    (!points.is_empty()).then(|| ManifoldPoints {
+        normal: rotation * local_normal,
+        points: points
+            .into_iter()
+            .map(|(position, depth, feature_id)| ManifoldPoint {
+                position: center + rotation * position,
+                depth,
+                feature_id,
+            })
+            .collect(),
+    })
+}
+
+/// Clip the core segment of a capsule against a flat face of another
+/// shape, given by its outward normal and corners counter-clockwise around
+/// it. Returns the ends of the clipped segment within `margin` of the face,
+/// with `end_flag` added to their feature ids.
+pub(super) fn segment_face_points(
+    (start, end): (Vec3, Vec3),
+    radius: f32,
+    (face_normal, corners): &(Vec3, Vec<(Vec3, u32)>),
+    end_flag: u32,
+    margin: f32,
+) -> Vec<ManifoldPoint> {
+    let (mut enter, mut exit) = (0.0f32, 1.0f32);
+    let direction = end - start;
+    for (&(from, _), &(to, _)) in corners.iter().zip(corners.iter().cycle().skip(1)) {
+        // Keep the part of the segment inside each side plane of the face
+        let side = (to - from).cross(*face_normal).normalize_or_zero();
+        let (distance, rate) = (side.dot(start - from), side.dot(direction));
+        if rate.abs() <= f32::EPSILON {
+            if distance > 0.0 {
+                return Vec::new();
+            }
+        } else if rate > 0.0 {
+            exit = exit.min(-distance / rate);
+        } else {
+            enter = enter.max(-distance / rate);
+        }
+    }
+    if enter > exit {
+        return Vec::new();
+    }
+
+    let origin = corners.first().map_or(Vec3::ZERO, |&(corner, _)| corner);
+    [(enter, 0), (exit, 1)]
+        .into_iter()
+        .filter_map(|(s, id)| {
+            let on_core = start + direction * s;
+            let gap = face_normal.dot(on_core - origin) - radius;
+            (gap <= margin).then(|| ManifoldPoint {
+                position: on_core - *face_normal * (radius + 0.5 * gap),
+                depth: -gap,
+                feature_id: end_flag | id,
+            })
+        })
+        .collect()
+}
+
+/// Contacts of a capsule, either `a` or `b`, lying along a flat face of
+/// the other shape when the contact normal `normal`, from `a` to `b`, is
+/// close to that face's normal. Returns the face normal oriented from `a`
+/// to `b` and the clipped points.
+pub(super) fn capsule_face_contacts(
+    a: &Convex<'_>,
+    b: &Convex<'_>,
+    normal: Vec3,
+    margin: f32,
+) -> Option<(Vec3, Vec<ManifoldPoint>)> {
+    let (segment, radius, face, end_flag, sign) = if let Some(segment) = a.segment() {
+        (segment, a.radius(), b.face(-normal)?, 0, -1.0)
+    } else {
+        (b.segment()?, b.radius(), a.face(normal)?, END_OF_B, 1.0)
+    };
+    if face.0.dot(normal) * sign < PARALLEL_ALIGNMENT {
+        return None;
+    }
+    let points = segment_face_points(segment, radius, &face, end_flag, margin);
+    Some((face.0 * sign, points))
+}
+
+/// Contact point between the surfaces around two core points, when they
+/// are within `margin` of each other along `normal`, from `a` to `b`.
+fn core_point(
+    a: Vec3,
+    b: Vec3,
+    feature_id: u32,
+    normal: Vec3,
+    (radius_a, radius_b): (f32, f32),
+    margin: f32,
+) -> Option<ManifoldPoint> {
+    let gap = normal.dot(b - a) - radius_a - radius_b;
+    (gap <= margin).then(|| ManifoldPoint {
+        position: a + normal * (radius_a + 0.5 * gap),
+        depth: -gap,
+        feature_id,
+    })
+}
+
+/// Closest point to `point` on the segment from `start` to `end`.
+fn closest_on_segment(point: Vec3, start: Vec3, end: Vec3) -> Vec3 {
+    let direction = end - start;
+    let length_squared = direction.length_squared();
+    if length_squared <= f32::EPSILON {
+        return start;
+    }
+    start + direction * ((point - start).dot(direction) / length_squared).clamp(0.0, 1.0)
+}
+
+/// Range of parameters along `start + s * axis`, within `[0, 1]`, that the
+/// segment from `from` to `to` projects onto, or `None` if it misses.
+fn overlap_on(axis: Vec3, start: Vec3, from: Vec3, to: Vec3) -> Option<(f32, f32)> {
+    let length_squared = axis.length_squared();
+    if length_squared <= f32::EPSILON {
+        return None;
+    }
+    let project = |point: Vec3| (point - start).dot(axis) / length_squared;
+    let (first, second) = (project(from), project(to));
+    let (low, high) = (first.min(second).max(0.0), first.max(second).min(1.0));
+    (low <= high).then_some((low, high))
+}
+
+/// Parameters `(s, t)` of the closest points `p0 + s (p1 - p0)` and
+/// `q0 + t (q1 - q0)` between two segments.
+pub(super) fn closest_between_segments((p0, p1): (Vec3, Vec3), (q0, q1): (Vec3, Vec3)) -> (f32, f32) {
+    let (dir_p, dir_q, between) = (p1 - p0, q1 - q0, p0 - q0);
+    let (length_p, length_q) = (dir_p.length_squared(), dir_q.length_squared());
+    let along_q = dir_q.dot(between);
+    if length_p <= f32::EPSILON && length_q <= f32::EPSILON {
+        return (0.0, 0.0);
+    }
+    if length_p <= f32::EPSILON {
+        return (0.0, (along_q / length_q).clamp(0.0, 1.0));
+    }
+    let along_p = dir_p.dot(between);
+    if length_q <= f32::EPSILON {
+        return ((-along_p / length_p).clamp(0.0, 1.0), 0.0);
+    }
+    let cross = dir_p.dot(dir_q);
+    let denominator = length_p * length_q - cross * cross;
+    // Parallel segments have a whole range of closest points; start from s = 0
+    let s = if denominator > f32::EPSILON * length_p * length_q {
+        ((cross * along_q - along_p * length_q) / denominator).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let t = (cross * s + along_q) / length_q;
+    if t < 0.0 {
+        ((-along_p / length_p).clamp(0.0, 1.0), 0.0)
+    } else if t > 1.0 {
+        (((cross - along_p) / length_p).clamp(0.0, 1.0), 1.0)
+    } else {
+        (s, t)
+    }
+}
+
+/// Closest points `(on_box, on_segment)` between a segment and a box of
+/// half extents `half` centred on the origin, or `None` if the segment
+/// passes through the box.
+///
+/// When the two are apart, the closest point of the segment is one of its
+/// ends or lies across from an edge of the box, so only those need testing.
+fn segment_box_closest(start: Vec3, end: Vec3, half: Vec3) -> Option<(Vec3, Vec3)> {
+    let direction = end - start;
+    let inside = (0..3).try_fold((0.0f32, 1.0f32), |(enter, exit), axis| {
+        let (near, far) = slab(start[axis], direction[axis], half[axis])?;
+        Some((enter.max(near), exit.min(far)))
+    });
+    if inside.is_some_and(|(enter, exit)| enter <= exit) {
+        return None;
+    }
+
+    let ends = [start, end].map(|tip| (tip.clamp(-half, half), tip));
+    let edges = (0..3).flat_map(|axis| {
+        (0..4).map(move |corner: u32| {
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+            let mut from = Vec3::ZERO;
+            from[u] = if corner & 1 == 0 { -half[u] } else { half[u] };
+            from[v] = if corner & 2 == 0 { -half[v] } else { half[v] };
+            let mut to = from;
+            (from[axis], to[axis]) = (-half[axis], half[axis]);
+            let (s, t) = closest_between_segments((start, end), (from, to));
+            (from.lerp(to, t), start + direction * s)
+        })
+    });
+    ends.into_iter()
+        .chain(edges)
+        .min_by(|(a, p), (b, q)| a.distance_squared(*p).total_cmp(&b.distance_squared(*q)))
+}
@@ -0,0 +1,223 @@
+//! Continuous collision detection
+//!
+//! Finds the first time of impact between a body and another body over one
+//! step, so that a small fast body cannot pass through a plane or a thin box
+//! between two discrete overlap checks. Spheres against planes and spheres
+//! use exact swept-sphere tests. Every other pair uses conservative
+//! advancement: the bodies are moved forward by the largest fraction of the
+//! step that cannot close their current gap, until the gap is used up.
+
+use glam::Vec3;
+
+use crate::compound::Compound;
+use crate::heightfield::Heightfield;
+use crate::mesh::{ConvexHull, TriangleMesh};
+use crate::types::{BoxBody, Capsule, Cylinder, Plane, Sphere};
+use super::box_box::box_box_separation;
+use super::compound::generate_manifolds;
+use super::manifold::BodyFrame;
+use super::Primitive;
+
+/// Conservative advancement stops once the gap is within this distance of
+/// the contact offset.
+const ADVANCEMENT_TOLERANCE: f32 = 1e-3;
+/// Upper bound on conservative advancement iterations. Grazing motion can
+/// converge slowly; the last safe time is used when the bound is hit.
+const MAX_ADVANCEMENT_ITERATIONS: usize = 32;
+
+/// Copy of a body's shape that can be moved along a sweep. Heightfields
+/// and meshes never move and are borrowed instead; hulls and compound
+/// children share their geometry with the body.
+#[derive(Clone)]
+pub(crate) enum Shape<'a> {
+    Sphere(Sphere),
+    Box(BoxBody),
+    Cylinder(Cylinder),
+    Capsule(Capsule),
+    Hull(ConvexHull),
+    Compound(Compound),
+    Plane(Plane),
+    Heightfield(&'a Heightfield),
+    Mesh(&'a TriangleMesh),
+}
+
+impl<'a> Shape<'a> {
+    pub fn of(primitive: &Primitive<'a>) -> Self {
+        match *primitive {
+            Primitive::Sphere(sphere) => Self::Sphere(*sphere),
+            Primitive::Box(box_body) => Self::Box(*box_body),
+            Primitive::Cylinder(cylinder) => Self::Cylinder(*cylinder),
+            Primitive::Capsule(capsule) => Self::Capsule(*capsule),
+            Primitive::Hull(hull) => Self::Hull(hull.clone()),
+            Primitive::Compound(compound) => Self::Compound(compound.clone()),
+            Primitive::Plane(plane) => Self::Plane(*plane),
+            Primitive::Heightfield(heightfield) => Self::Heightfield(heightfield),
+            Primitive::Mesh(mesh) => Self::Mesh(mesh),
+        }
+    }
+
+    /// The shape moved to `frame`. Planes, heightfields and meshes do not
+    /// move.
+    fn at(&self, frame: BodyFrame) -> Self {
+        let position = frame.position.into();
+        let orientation = frame.orientation.to_array();
+        let mut shape = self.clone();
+        match &mut shape {
+            Self::Sphere(sphere) => (sphere.pos, sphere.orientation) = (position, orientation),
+            Self::Box(box_body) => (box_body.pos, box_body.orientation) = (position, orientation),
+            Self::Cylinder(cylinder) => (cylinder.pos, cylinder.orientation) = (position, orientation),
+            Self::Capsule(capsule) => (capsule.pos, capsule.orientation) = (position, orientation),
+            Self::Hull(hull) => (hull.pos, hull.orientation) = (position, orientation),
+            Self::Compound(compound) => (compound.pos, compound.orientation) = (position, orientation),
+            Self::Plane(_) | Self::Heightfield(_) | Self::Mesh(_) => {}
+        }
+        shape
+    }
+
+    pub fn primitive(&self) -> Primitive<'_> {
+        match self {
+            Self::Sphere(sphere) => Primitive::Sphere(sphere),
+            Self::Box(box_body) => Primitive::Box(box_body),
+            Self::Cylinder(cylinder) => Primitive::Cylinder(cylinder),
+            Self::Capsule(capsule) => Primitive::Capsule(capsule),
+            Self::Hull(hull) => Primitive::Hull(hull),
+            Self::Compound(compound) => Primitive::Compound(compound),
+            Self::Plane(plane) => Primitive::Plane(plane),
+            Self::Heightfield(heightfield) => Primitive::Heightfield(heightfield),
+            Self::Mesh(mesh) => Primitive::Mesh(mesh),
+        }
+    }
+
+    /// Radius of a sphere around the body origin that contains the shape.
+    fn bounding_radius(&self) -> f32 {
+        match self {
+            Self::Sphere(sphere) => sphere.radius,
+            Self::Box(box_body) => Vec3::from(box_body.half_extents).length(),
+            Self::Cylinder(cylinder) => {
+                Vec3::from(cylinder.shape_offset).length() + cylinder.radius.hypot(cylinder.half_height)
+            }
+            Self::Capsule(capsule) => capsule.half_height + capsule.radius,
+            Self::Hull(hull) => Primitive::Hull(hull).as_collider().bounding_radius(),
+            Self::Compound(compound) => Primitive::Compound(compound).as_collider().bounding_radius(),
+            Self::Plane(_) | Self::Heightfield(_) | Self::Mesh(_) => 0.0,
+        }
+    }
+}
+
+/// Motion of a body's frame over one step.
+#[derive(Copy, Clone)]
+pub(crate) struct Sweep {
+    pub start: BodyFrame,
+    pub end: BodyFrame,
+}
+
+impl Sweep {
+    /// Frame at fraction `t` of the step.
+    pub fn at(&self, t: f32) -> BodyFrame {
+        BodyFrame {
+            position: self.start.position.lerp(self.end.position, t),
+            orientation: self.start.orientation.slerp(self.end.orientation, t),
+        }
+    }
+
+    /// Upper bound on how far any point within `radius` of the body origin
+    /// travels over the whole sweep.
+    fn reach(&self, radius: f32) -> f32 {
+        let rotation = self.start.orientation.angle_between(self.end.orientation);
+        self.start.position.distance(self.end.position) + rotation * radius
+    }
+}
+
+/// Fraction of the step at which `a` and `b` first come within `offset` of
+/// each other. Returns `None` if they stay further apart over the whole
+/// sweep, if they already start within `offset`, which the discrete contacts
+/// handle, or if the pair has no narrow phase.
+pub(crate) fn time_of_impact(
+    a: &Shape<'_>,
+    sweep_a: &Sweep,
+    b: &Shape<'_>,
+    sweep_b: &Sweep,
+    offset: f32,
+) -> Option<f32> {
+    match (a, b) {
+        (Shape::Sphere(sphere), Shape::Plane(plane)) => swept_sphere_plane(sphere.radius, sweep_a, plane, offset),
+        (Shape::Sphere(sphere_a), Shape::Sphere(sphere_b)) => {
+            swept_sphere_sphere(sphere_a.radius + sphere_b.radius, sweep_a, sweep_b, offset)
+        }
+        _ => conservative_advancement(a, sweep_a, b, sweep_b, offset),
+    }
+}
+
+fn swept_sphere_plane(radius: f32, sweep: &Sweep, plane: &Plane, offset: f32) -> Option<f32> {
+    let normal = Vec3::from(plane.normal);
+    let gap = |frame: BodyFrame| normal.dot(frame.position) + plane.d - radius;
+    let (start, end) = (gap(sweep.start), gap(sweep.end));
+    (start > offset && end < offset).then(|| (start - offset) / (start - end))
+}
+
+fn swept_sphere_sphere(radius: f32, sweep_a: &Sweep, sweep_b: &Sweep, offset: f32) -> Option<f32> {
+    let start = sweep_b.start.position - sweep_a.start.position;
+    let motion = (sweep_b.end.position - sweep_a.end.position) - start;
+    let reach = radius + offset;
+    // Solve |start + t * motion| = reach for the first root in [0, 1].
+    let a = motion.length_squared();
+    let b = 2.0 * start.dot(motion);
+    let c = start.length_squared() - reach * reach;
+    if c <= 0.0 || a <= f32::EPSILON {
+        return None;
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t = (-b - discriminant.sqrt()) / (2.0 * a);
+    (0.0..=1.0).contains(&t).then_some(t)
+}
+
+fn conservative_advancement(
+    a: &Shape<'_>,
+    sweep_a: &Sweep,
+    b: &Shape<'_>,
+    sweep_b: &Sweep,
+    offset: f32,
+) -> Option<f32> {
+    let reach = sweep_a.reach(a.bounding_radius()) + sweep_b.reach(b.bounding_radius());
+    if reach <= f32::EPSILON {
+        return None;
+    }
+    // Bodies further apart than the reach cannot meet within the step, so
+    // the narrow phase only has to look that far.
+    let gap_at = |t: f32| separation(&a.at(sweep_a.at(t)), &b.at(sweep_b.at(t)), reach + offset);
+
+    let mut gap = gap_at(0.0)?;
+    if gap <= offset {
+        return None;
+    }
+    let mut t = 0.0;
+    for _ in 0..MAX_ADVANCEMENT_ITERATIONS {
+        // No point moves further than `reach` over the whole step, so the
+        // gap cannot close by more than `reach * dt` in `dt`.
+        t += (gap - offset) / reach;
+        if t >= 1.0 {
+            return None;
+        }
+        gap = gap_at(t)?;
+        if gap <= offset + ADVANCEMENT_TOLERANCE {
+            break;
+        }
+    }
+    Some(t)
+}
+
+/// Distance between two shapes, or a lower bound on it. Negative when they
+/// overlap, and `None` if the shapes may be further apart than `limit`.
+fn separation(a: &Shape<'_>, b: &Shape<'_>, limit: f32) -> Option<f32> {
+    if let (Shape::Box(box_a), Shape::Box(box_b)) = (a, b) {
+        return Some(box_box_separation(box_a, box_b));
+    }
+    generate_manifolds(&a.primitive(), &b.primitive(), limit)
+        .iter()
+        .flat_map(|(_, manifold)| &manifold.points)
+        .map(|point| -point.depth)
+        .reduce(f32::min)
+}
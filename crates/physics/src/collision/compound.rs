@@ -0,0 +1,84 @@
+//! Contacts of compound bodies
+//!
+//! A compound body collides through its children. Each child of a compound
+//! is tested against the other body, or against each of its children if it
+//! is a compound too, with the generator of the two shapes involved. Child
+//! pairs whose bounds are apart are skipped.
+
+use super::broad_phase::primitive_bounding_box;
+use super::manifold::{generate_manifold, ManifoldPoints};
+use super::{Primitive, Shape};
+
+/// Run the narrow phase for a pair of bodies, either of which may be a
+/// compound, and return the contacts of every touching child pair together
+/// with the indices of the two children.
+///
+/// `a` must belong to the smaller handle. Bodies that are not compounds
+/// count as their own single child 0.
+pub(crate) fn generate_manifolds(
+    a: &Primitive<'_>,
+    b: &Primitive<'_>,
+    margin: f32,
+) -> Vec<((usize, usize), ManifoldPoints)> {
+    let (shapes_a, shapes_b) = (child_shapes(a), child_shapes(b));
+    let parts_a = parts(a, &shapes_a);
+    let parts_b = parts(b, &shapes_b);
+
+    let mut manifolds = Vec::new();
+    for (i, part_a) in parts_a.iter().enumerate() {
+        for (j, part_b) in parts_b.iter().enumerate() {
+            if (parts_a.len() > 1 || parts_b.len() > 1) && !bounds_overlap(part_a, part_b, margin) {
+                continue;
+            }
+            if let Some(manifold) = generate_in_any_order(part_a, part_b, margin) {
+                manifolds.push(((i, j), manifold));
+            }
+        }
+    }
+    manifolds
+}
+
+/// Children of a compound as standalone shapes; empty for other bodies.
+fn child_shapes(primitive: &Primitive<'_>) -> Vec<Shape<'static>> {
+    match primitive {
+        Primitive::Compound(compound) => compound.child_shapes(),
+        _ => Vec::new(),
+    }
+}
+
+/// The primitives a body collides with: its children if it is a compound,
+/// or else the body itself.
+fn parts<'a>(primitive: &Primitive<'a>, shapes: &'a [Shape<'static>]) -> Vec<Primitive<'a>> {
+    match primitive {
+        Primitive::Compound(_) => shapes.iter().map(Shape::primitive).collect(),
+        _ => vec![*primitive],
+    }
+}
+
+fn bounds_overlap(a: &Primitive<'_>, b: &Primitive<'_>, margin: f32) -> bool {
+    match (primitive_bounding_box(a, margin), primitive_bounding_box(b, margin)) {
+        (Some(a), Some(b)) => {
+            a.min.x <= b.max.x
+                && b.min.x <= a.max.x
+                && a.min.y <= b.max.y
+                && b.min.y <= a.max.y
+                && a.min.z <= b.max.z
+                && b.min.z <= a.max.z
+        }
+        // Planes are unbounded
+        _ => true,
+    }
+}
+
+/// [`generate_manifold`] for two shapes in either order. A child can come
+/// after the other body in shape order even when its compound does not, in
+/// which case the pair is swapped and the normal flipped back.
+fn generate_in_any_order(a: &Primitive<'_>, b: &Primitive<'_>, margin: f32) -> Option<ManifoldPoints> {
+    if b.as_collider().primitive_type() < a.as_collider().primitive_type() {
+        let mut manifold = generate_manifold(b, a, margin)?;
+        manifold.normal = -manifold.normal;
+        Some(manifold)
+    } else {
+        generate_manifold(a, b, margin)
+    }
+}
@@ -0,0 +1,395 @@
+//! Convex shapes for the general narrow phase
+//!
+//! [`Convex`] puts every convex body, and single mesh triangles, behind the
+//! three queries the general contact generators need: the support point for
+//! GJK, the vertices that may touch another surface, and where a line
+//! enters and leaves the shape.
+
+use glam::{Quat, Vec2, Vec3};
+
+use crate::mesh::ConvexHull;
+use crate::types::{BoxBody, Capsule, Cylinder, Sphere};
+use super::cylinder_plane::cylinder_rim_samples;
+use super::manifold::body_rotation;
+use super::Primitive;
+
+/// A convex shape placed in the world.
+pub(crate) enum Convex<'a> {
+    Sphere(&'a Sphere),
+    Box(&'a BoxBody),
+    Cylinder(&'a Cylinder),
+    Capsule(&'a Capsule),
+    Hull(&'a ConvexHull),
+    Triangle([Vec3; 3]),
+}
+
+impl<'a> Convex<'a> {
+    /// The convex shape of a body, or `None` for compounds, planes,
+    /// heightfields and meshes.
+    pub fn of(primitive: &Primitive<'a>) -> Option<Self> {
+        match *primitive {
+            Primitive::Sphere(sphere) => Some(Self::Sphere(sphere)),
+            Primitive::Box(box_body) => Some(Self::Box(box_body)),
+            Primitive::Cylinder(cylinder) => Some(Self::Cylinder(cylinder)),
+            Primitive::Capsule(capsule) => Some(Self::Capsule(capsule)),
+            Primitive::Hull(hull) => Some(Self::Hull(hull)),
+            Primitive::Compound(_) | Primitive::Plane(_) | Primitive::Heightfield(_) | Primitive::Mesh(_) => None,
+        }
+    }
+
+    /// Rotation and position of the shape's own frame. Cylinders use the
+    /// centre of their collision shape.
+    fn frame(&self) -> (Quat, Vec3) {
+        match self {
+            Self::Sphere(sphere) => (Quat::IDENTITY, sphere.pos.into()),
+            Self::Box(box_body) => (body_rotation(box_body.orientation), box_body.pos.into()),
+            Self::Cylinder(cylinder) => {
+                let rotation = body_rotation(cylinder.orientation);
+                (rotation, Vec3::from(cylinder.pos) + rotation * Vec3::from(cylinder.shape_offset))
+            }
+            Self::Capsule(capsule) => (body_rotation(capsule.orientation), capsule.pos.into()),
+            Self::Hull(hull) => (body_rotation(hull.orientation), hull.pos.into()),
+            Self::Triangle(_) => (Quat::IDENTITY, Vec3::ZERO),
+        }
+    }
+
+    /// A point inside the shape.
+    pub fn center(&self) -> Vec3 {
+        match self {
+            Self::Triangle([p0, p1, p2]) => (*p0 + *p1 + *p2) / 3.0,
+            _ => self.frame().1,
+        }
+    }
+
+    /// Point of the shape furthest along `direction`.
+    pub fn support(&self, direction: Vec3) -> Vec3 {
+        let (rotation, center) = self.frame();
+        let local = rotation.inverse() * direction;
+        let sign = |value: f32, half: f32| if value >= 0.0 { half } else { -half };
+        let point = match self {
+            Self::Sphere(sphere) => local.normalize_or_zero() * sphere.radius,
+            Self::Box(box_body) => {
+                let half = Vec3::from(box_body.half_extents);
+                Vec3::new(sign(local.x, half.x), sign(local.y, half.y), sign(local.z, half.z))
+            }
+            Self::Cylinder(cylinder) => {
+                let radial = Vec2::new(local.x, local.z).normalize_or_zero() * cylinder.radius;
+                Vec3::new(radial.x, sign(local.y, cylinder.half_height), radial.y)
+            }
+            Self::Capsule(capsule) => {
+                Vec3::new(0.0, sign(local.y, capsule.half_height), 0.0) + local.normalize_or_zero() * capsule.radius
+            }
+            Self::Hull(hull) => hull.geometry.support(local),
+            Self::Triangle(corners) => {
+                return corners
+                    .iter()
+                    .copied()
+                    .max_by(|a, b| a.dot(direction).total_cmp(&b.dot(direction)))
+                    .unwrap_or(Vec3::ZERO);
+            }
+        };
+        center + rotation * point
+    }
+
+    /// Support point of the shape with spheres shrunk to their centre and
+    /// capsules to their core segment.
+    pub fn core_support(&self, direction: Vec3) -> Vec3 {
+        match self {
+            Self::Sphere(sphere) => sphere.pos.into(),
+            Self::Capsule(capsule) => {
+                let (start, end) = capsule_segment(capsule);
+                if direction.dot(end - start) >= 0.0 { end } else { start }
+            }
+            _ => self.support(direction),
+        }
+    }
+
+    /// Radius by which [`Self::core_support`] falls short of the shape.
+    pub fn radius(&self) -> f32 {
+        match self {
+            Self::Sphere(sphere) => sphere.radius,
+            Self::Capsule(capsule) => capsule.radius,
+            _ => 0.0,
+        }
+    }
+
+    /// End points of the core segment of a capsule.
+    pub fn segment(&self) -> Option<(Vec3, Vec3)> {
+        match self {
+            Self::Capsule(capsule) => Some(capsule_segment(capsule)),
+            _ => None,
+        }
+    }
+
+    /// Points of the shape that can touch a surface facing it along
+    /// `surface_normal`, with a feature id each: the corners of polytopes,
+    /// the rim of cylinders and the lowest point of each capsule cap.
+    /// Spheres have none.
+    pub fn vertices(&self, surface_normal: Vec3) -> Vec<(Vec3, u32)> {
+        let (rotation, center) = self.frame();
+        match self {
+            Self::Sphere(_) => Vec::new(),
+            Self::Box(box_body) => {
+                let half = Vec3::from(box_body.half_extents);
+                (0..8u32)
+                    .map(|corner| {
+                        let local = Vec3::new(
+                            if corner & 1 == 0 { -half.x } else { half.x },
+                            if corner & 2 == 0 { -half.y } else { half.y },
+                            if corner & 4 == 0 { -half.z } else { half.z },
+                        );
+                        (center + rotation * local, corner)
+                    })
+                    .collect()
+            }
+            Self::Cylinder(cylinder) => cylinder_rim_samples(cylinder, surface_normal),
+            Self::Capsule(capsule) => {
+                let (start, end) = capsule_segment(capsule);
+                let reach = surface_normal.normalize_or_zero() * capsule.radius;
+                vec![(start - reach, 0), (end - reach, 1)]
+            }
+            Self::Hull(hull) => hull
+                .geometry
+                .local_vertices()
+                .iter()
+                .zip(0u32..)
+                .map(|(&vertex, index)| (center + rotation * vertex, index))
+                .collect(),
+            Self::Triangle(corners) => corners.iter().copied().zip(0u32..).collect(),
+        }
+    }
+
+    /// Outward normal and corners, counter-clockwise around it, of the
+    /// flat face whose normal is closest to `direction`, with a feature id
+    /// for each corner. Spheres, cylinders and capsules have no such faces.
+    pub fn face(&self, direction: Vec3) -> Option<(Vec3, Vec<(Vec3, u32)>)> {
+        let (rotation, center) = self.frame();
+        let local = rotation.inverse() * direction;
+        match self {
+            Self::Sphere(_) | Self::Cylinder(_) | Self::Capsule(_) => None,
+            Self::Box(box_body) => {
+                let half = Vec3::from(box_body.half_extents);
+                let magnitude = local.abs();
+                let axis = (0..3).fold(0, |best, k| if magnitude[k] > magnitude[best] { k } else { best });
+                let sign = if local[axis] >= 0.0 { 1.0 } else { -1.0 };
+                let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+                // Counter-clockwise around the face normal
+                let corners = [(1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0), (1.0, -1.0)].map(|(du, dv)| {
+                    let mut signs = Vec3::ZERO;
+                    (signs[axis], signs[u], signs[v]) = (sign, du, dv * sign);
+                    let id = (0..3).filter(|&k| signs[k] > 0.0).map(|k| 1u32 << k).sum::<u32>();
+                    (center + rotation * (signs * half), id)
+                });
+                let mut normal = Vec3::ZERO;
+                normal[axis] = sign;
+                Some((rotation * normal, corners.to_vec()))
+            }
+            Self::Hull(hull) => {
+                let face = hull.geometry.face(local);
+                let vertices = hull.geometry.local_vertices();
+                let corners = face
+                    .vertices
+                    .iter()
+                    .map(|&index| (center + rotation * vertices[index as usize], index))
+                    .collect();
+                Some((rotation * face.normal, corners))
+            }
+            Self::Triangle(corners) => {
+                let [p0, p1, p2] = *corners;
+                let normal = (p1 - p0).cross(p2 - p0).try_normalize()?;
+                Some((normal, corners.iter().copied().zip(0u32..).collect()))
+            }
+        }
+    }
+
+    /// Interval of `t` over which `point + t * direction` is inside the
+    /// shape, or `None` if the line misses it. A triangle stands for the
+    /// face of a solid mesh, so the line is inside from where it crosses the
+    /// triangle onwards on the triangle's back side.
+    pub fn line_interval(&self, point: Vec3, direction: Vec3) -> Option<(f32, f32)> {
+        let (rotation, center) = self.frame();
+        let origin = rotation.inverse() * (point - center);
+        let local = rotation.inverse() * direction;
+        let (enter, exit) = match self {
+            Self::Sphere(sphere) => sphere_interval(origin, local, sphere.radius)?,
+            Self::Box(box_body) => {
+                // Inside once the line is within all three slabs
+                let half = Vec3::from(box_body.half_extents);
+                let (mut enter, mut exit) = (f32::NEG_INFINITY, f32::INFINITY);
+                for axis in 0..3 {
+                    let (near, far) = slab(origin[axis], local[axis], half[axis])?;
+                    (enter, exit) = (enter.max(near), exit.min(far));
+                }
+                (enter, exit)
+            }
+            Self::Cylinder(cylinder) => {
+                // Between the caps and within the radius of the axis
+                let (near, far) = slab(origin.y, local.y, cylinder.half_height)?;
+                let (enter, exit) =
+                    disc_interval(Vec2::new(origin.x, origin.z), Vec2::new(local.x, local.z), cylinder.radius)?;
+                (near.max(enter), far.min(exit))
+            }
+            Self::Capsule(capsule) => {
+                // The union of the cylinder around the segment and the two
+                // cap spheres, which is convex, so its pieces overlap
+                let cap = |y: f32| sphere_interval(origin - Vec3::new(0.0, y, 0.0), local, capsule.radius);
+                let body = slab(origin.y, local.y, capsule.half_height)
+                    .and_then(|(near, far)| {
+                        disc_interval(Vec2::new(origin.x, origin.z), Vec2::new(local.x, local.z), capsule.radius)
+                            .map(|(enter, exit)| (near.max(enter), far.min(exit)))
+                    })
+                    .filter(|(enter, exit)| enter <= exit);
+                [cap(-capsule.half_height), cap(capsule.half_height), body]
+                    .into_iter()
+                    .flatten()
+                    .reduce(|(enter, exit), (near, far)| (enter.min(near), exit.max(far)))?
+            }
+            Self::Hull(hull) => {
+                // Inside while behind every face plane
+                let (mut enter, mut exit) = (f32::NEG_INFINITY, f32::INFINITY);
+                for &(normal, offset) in hull.geometry.planes() {
+                    let rate = normal.dot(local);
+                    let gap = offset - normal.dot(origin);
+                    if rate.abs() <= f32::EPSILON {
+                        if gap < 0.0 {
+                            return None;
+                        }
+                    } else if rate > 0.0 {
+                        exit = exit.min(gap / rate);
+                    } else {
+                        enter = enter.max(gap / rate);
+                    }
+                }
+                (enter, exit)
+            }
+            Self::Triangle(corners) => {
+                // Mesh triangles bound the solid behind them
+                let (t, rate) = line_triangle(point, direction, *corners)?;
+                if rate < 0.0 {
+                    (t, f32::INFINITY)
+                } else {
+                    (f32::NEG_INFINITY, t)
+                }
+            }
+        };
+        (enter <= exit).then_some((enter, exit))
+    }
+
+    /// Outward unit normal of the surface nearest to `point`, a point on or
+    /// close to the shape.
+    pub fn surface_normal(&self, point: Vec3) -> Vec3 {
+        let (rotation, center) = self.frame();
+        let local = rotation.inverse() * (point - center);
+        let normal = match self {
+            Self::Sphere(_) => local,
+            Self::Box(box_body) => {
+                // The face the point is closest to relative to the box's size
+                let scaled = local / Vec3::from(box_body.half_extents);
+                let axis = (0..3).fold(0, |best, k| if scaled[k].abs() > scaled[best].abs() { k } else { best });
+                let mut normal = Vec3::ZERO;
+                normal[axis] = scaled[axis].signum();
+                normal
+            }
+            Self::Cylinder(cylinder) => {
+                let radial = Vec2::new(local.x, local.z);
+                if local.y.abs() - cylinder.half_height > radial.length() - cylinder.radius {
+                    Vec3::new(0.0, local.y.signum(), 0.0)
+                } else {
+                    Vec3::new(radial.x, 0.0, radial.y)
+                }
+            }
+            Self::Capsule(capsule) => {
+                local - Vec3::new(0.0, local.y.clamp(-capsule.half_height, capsule.half_height), 0.0)
+            }
+            Self::Hull(hull) => hull
+                .geometry
+                .planes()
+                .iter()
+                .max_by(|(n_a, d_a), (n_b, d_b)| (n_a.dot(local) - d_a).total_cmp(&(n_b.dot(local) - d_b)))
+                .map_or(Vec3::ZERO, |&(normal, _)| normal),
+            Self::Triangle([p0, p1, p2]) => return (*p1 - *p0).cross(*p2 - *p0).normalize_or_zero(),
+        };
+        (rotation * normal).normalize_or_zero()
+    }
+
+    /// Corners of the axis-aligned box around the shape.
+    pub fn bounds(&self) -> (Vec3, Vec3) {
+        let min = Vec3::new(self.support(Vec3::NEG_X).x, self.support(Vec3::NEG_Y).y, self.support(Vec3::NEG_Z).z);
+        let max = Vec3::new(self.support(Vec3::X).x, self.support(Vec3::Y).y, self.support(Vec3::Z).z);
+        (min, max)
+    }
+
+    /// Whether the shape is a sphere, which touches other shapes at a single
+    /// point.
+    pub fn is_round(&self) -> bool {
+        matches!(self, Self::Sphere(_))
+    }
+}
+
+/// World-space end points of the core segment of a capsule.
+pub(super) fn capsule_segment(capsule: &Capsule) -> (Vec3, Vec3) {
+    let (start, end) = capsule.segment();
+    (start.into(), end.into())
+}
+
+/// Interval of `t` in which `origin + t * direction` lies within `radius`
+/// of the origin, or `None` if it never does.
+fn sphere_interval(origin: Vec3, direction: Vec3, radius: f32) -> Option<(f32, f32)> {
+    // Solve |origin + t * direction| = radius
+    let speed = direction.length_squared();
+    if speed <= f32::EPSILON {
+        return None;
+    }
+    let along = origin.dot(direction);
+    let discriminant = along * along - speed * (origin.length_squared() - radius * radius);
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    Some(((-along - root) / speed, (-along + root) / speed))
+}
+
+/// Interval of `t` in which `origin + t * direction` lies within `radius`
+/// of the origin in the plane, or `None` if it never does.
+pub(super) fn disc_interval(origin: Vec2, direction: Vec2, radius: f32) -> Option<(f32, f32)> {
+    let speed = direction.length_squared();
+    let outside = origin.length_squared() - radius * radius;
+    if speed <= f32::EPSILON {
+        return (outside <= 0.0).then_some((f32::NEG_INFINITY, f32::INFINITY));
+    }
+    let along = origin.dot(direction);
+    let discriminant = along * along - speed * outside;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    Some(((-along - root) / speed, (-along + root) / speed))
+}
+
+/// Interval of `t` in which `origin + t * direction` lies within
+/// `[-half, half]`, or `None` if it never does.
+pub(super) fn slab(origin: f32, direction: f32, half: f32) -> Option<(f32, f32)> {
+    if direction.abs() <= f32::EPSILON {
+        return (origin.abs() <= half).then_some((f32::NEG_INFINITY, f32::INFINITY));
+    }
+    let (a, b) = ((-half - origin) / direction, (half - origin) / direction);
+    Some((a.min(b), a.max(b)))
+}
+
+/// Parameter `t` at which `point + t * direction` crosses the triangle and
+/// the rate at which it moves along the triangle's normal, or `None` if the
+/// line passes beside the triangle or runs parallel to it.
+fn line_triangle(point: Vec3, direction: Vec3, [p0, p1, p2]: [Vec3; 3]) -> Option<(f32, f32)> {
+    let normal = (p1 - p0).cross(p2 - p0);
+    let rate = normal.dot(direction);
+    if rate.abs() <= f32::EPSILON * normal.length() {
+        return None;
+    }
+    let t = normal.dot(p0 - point) / rate;
+    let hit = point + direction * t;
+    // Inside when the hit is on the inner side of all three edges
+    let inside = [(p0, p1), (p1, p2), (p2, p0)]
+        .iter()
+        .all(|&(from, to)| (to - from).cross(hit - from).dot(normal) >= 0.0);
+    inside.then_some((t, rate))
+}
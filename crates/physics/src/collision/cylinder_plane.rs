@@ -2,6 +2,7 @@
 
 use crate::types::{Vec3, Cylinder, Plane};
 use super::Contact;
+use super::manifold::{body_rotation, ManifoldPoint, ManifoldPoints};
 
 /// Detect collision between a cylinder and a plane
 pub fn detect_cylinder_plane_collision(
@@ -83,4 +84,67 @@ pub fn resolve_cylinder_plane_collision(
     
     // Damp angular velocity slightly on ground contact
     cylinder.angular_vel *= 0.98;
-}
\ No newline at end of file
+}
+
+/// Number of rim samples taken on each cylinder cap.
+const CAP_RIM_SAMPLES: u32 = 8;
+
+/// Generate a manifold between an oriented cylinder and a plane.
+///
+/// Each cap contributes its exact deepest rim point plus evenly spaced rim
+/// samples, which gives stable support both for a cylinder standing on a cap
+/// and for one lying on its side.
+pub(crate) fn cylinder_plane_manifold(
+    cylinder: &Cylinder,
+    plane: &Plane,
+    margin: f32,
+) -> Option<ManifoldPoints> {
+    let plane_normal: glam::Vec3 = plane.normal.into();
+    let points: Vec<ManifoldPoint> = cylinder_rim_samples(cylinder, plane_normal)
+        .into_iter()
+        .filter_map(|(world, feature_id)| {
+            let distance = plane_normal.dot(world) + plane.d;
+            (distance <= margin).then(|| ManifoldPoint {
+                position: world - plane_normal * (0.5 * distance),
+                depth: -distance,
+                feature_id,
+            })
+        })
+        .collect();
+
+    (!points.is_empty()).then(|| ManifoldPoints {
+        normal: -plane_normal,
+        points,
+    })
+}
+
+/// World positions and feature ids of the rim samples of both caps, for a
+/// surface with outward normal `surface_normal`.
+pub(super) fn cylinder_rim_samples(cylinder: &Cylinder, surface_normal: glam::Vec3) -> Vec<(glam::Vec3, u32)> {
+    let rotation = body_rotation(cylinder.orientation);
+    let center = glam::Vec3::from(cylinder.pos) + rotation * glam::Vec3::from(cylinder.shape_offset);
+    let axis = rotation * glam::Vec3::Y;
+
+    let radial_u = rotation * glam::Vec3::X;
+    let radial_v = rotation * glam::Vec3::Z;
+
+    // Direction within the cap plane that points deepest into the surface.
+    let down = -surface_normal - axis * axis.dot(-surface_normal);
+    let deepest_dir = (down.length() > 0.001).then(|| down.normalize());
+
+    let mut samples = Vec::new();
+    for (cap, sign) in [(0u32, -1.0f32), (1, 1.0)] {
+        let cap_center = center + axis * (sign * cylinder.half_height);
+        let cap_id = cap * 16;
+        for sample in 0..CAP_RIM_SAMPLES {
+            #[allow(clippy::cast_precision_loss)]
+            let angle = sample as f32 * std::f32::consts::TAU / CAP_RIM_SAMPLES as f32;
+            let offset = (radial_u * angle.cos() + radial_v * angle.sin()) * cylinder.radius;
+            samples.push((cap_center + offset, cap_id + sample));
+        }
+        if let Some(dir) = deepest_dir {
+            samples.push((cap_center + dir * cylinder.radius, cap_id + CAP_RIM_SAMPLES));
+        }
+    }
+    samples
+}
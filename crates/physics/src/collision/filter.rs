@@ -0,0 +1,37 @@
+//! Collision filtering
+//!
+//! Two bodies may touch only if their [`CollisionFilter`]s accept each
+//! other, the pair has not been excluded on the simulation, and no joint
+//! between them disables collision. Rejected pairs are dropped right after
+//! the broad phase, so they never reach the narrow phase, the solver or
+//! continuous collision detection.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::body::BodyHandle;
+
+pub use compute::kernels::collision_filter::CollisionFilter;
+
+/// `(a, b)` with the smaller handle first.
+pub(crate) fn ordered_pair(a: BodyHandle, b: BodyHandle) -> (BodyHandle, BodyHandle) {
+    if a <= b { (a, b) } else { (b, a) }
+}
+
+/// Every reason a pair of bodies may not collide, gathered once per step.
+pub(crate) struct PairFilter<'a> {
+    pub(crate) filters: &'a BTreeMap<BodyHandle, CollisionFilter>,
+    /// Excluded pairs, smaller handle first.
+    pub(crate) excluded: BTreeSet<(BodyHandle, BodyHandle)>,
+}
+
+impl PairFilter<'_> {
+    /// Filter of `body`, or the default one if none was set.
+    pub(crate) fn filter(&self, body: BodyHandle) -> CollisionFilter {
+        self.filters.get(&body).copied().unwrap_or(CollisionFilter::DEFAULT)
+    }
+
+    /// Whether bodies `a` and `b` may collide.
+    pub(crate) fn allows(&self, a: BodyHandle, b: BodyHandle) -> bool {
+        self.filter(a).collides_with(self.filter(b)) && !self.excluded.contains(&ordered_pair(a, b))
+    }
+}
@@ -0,0 +1,351 @@
+//! Distance and penetration between convex shapes
+//!
+//! GJK walks a simplex of the Minkowski difference `A - B` towards the
+//! origin to find the closest points of two separated shapes. When the
+//! simplex encloses the origin the shapes overlap, and EPA grows it into a
+//! polytope until the face nearest the origin gives the direction and depth
+//! of least penetration. Each simplex vertex remembers the support points
+//! of A and B it came from, so both searches end in a pair of witness
+//! points. Spheres take part as their centre point, and their radius is
+//! added back at the end, which keeps them exact and out of EPA unless
+//! the centre itself is inside the other shape.
+
+use glam::Vec3;
+
+use super::convex::Convex;
+
+/// Upper bound on GJK iterations; the closest point found so far is used
+/// when it is hit.
+const MAX_GJK_ITERATIONS: usize = 64;
+/// Upper bound on EPA expansions.
+const MAX_EPA_ITERATIONS: usize = 64;
+/// Relative progress below which both searches stop.
+const TOLERANCE: f32 = 1e-5;
+
+/// Closest points, or points of deepest penetration, of two convex shapes.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Separation {
+    /// Unit direction from A towards B along which they are closest, or
+    /// along which B must move the least to stop overlapping A.
+    pub normal: Vec3,
+    /// Distance between the shapes, negative when they overlap.
+    pub distance: f32,
+    pub point_a: Vec3,
+    pub point_b: Vec3,
+}
+
+/// Vertex of the Minkowski difference with the support points behind it.
+#[derive(Copy, Clone, Debug)]
+struct Vertex {
+    point: Vec3,
+    a: Vec3,
+    b: Vec3,
+}
+
+fn support(a: &Convex<'_>, b: &Convex<'_>, direction: Vec3) -> Vertex {
+    let (on_a, on_b) = (a.core_support(direction), b.core_support(-direction));
+    Vertex {
+        point: on_a - on_b,
+        a: on_a,
+        b: on_b,
+    }
+}
+
+/// Distance and witness points of `a` and `b`, or `None` if GJK cannot
+/// make sense of the shapes (for example degenerate ones).
+pub(crate) fn separation(a: &Convex<'_>, b: &Convex<'_>) -> Option<Separation> {
+    let core = core_separation(a, b)?;
+    let (radius_a, radius_b) = (a.radius(), b.radius());
+    Some(Separation {
+        distance: core.distance - radius_a - radius_b,
+        point_a: core.point_a + core.normal * radius_a,
+        point_b: core.point_b - core.normal * radius_b,
+        ..core
+    })
+}
+
+/// Separation of the shapes with spheres shrunk to their centres.
+fn core_separation(a: &Convex<'_>, b: &Convex<'_>) -> Option<Separation> {
+    let start = (a.center() - b.center()).try_normalize().unwrap_or(Vec3::X);
+    let mut simplex = vec![(support(a, b, start), 1.0)];
+    let mut closest = simplex[0].0.point;
+    let scale = closest.length().max(1.0);
+
+    for _ in 0..MAX_GJK_ITERATIONS {
+        if closest.length_squared() <= (TOLERANCE * scale).powi(2) {
+            return penetration(a, b, simplex.into_iter().map(|(vertex, _)| vertex).collect());
+        }
+        let next = support(a, b, -closest);
+        // Stop once the new vertex gets no closer to the origin
+        let progress = closest.length_squared() - closest.dot(next.point);
+        if progress <= TOLERANCE * closest.length_squared() || simplex.iter().any(|(v, _)| v.point == next.point) {
+            break;
+        }
+        let vertices: Vec<Vertex> = simplex.iter().map(|&(vertex, _)| vertex).chain([next]).collect();
+        simplex = closest_on_simplex(&vertices);
+        if simplex.len() == 4 {
+            return penetration(a, b, vertices);
+        }
+        closest = simplex.iter().map(|&(vertex, weight)| vertex.point * weight).sum();
+    }
+
+    let point_a: Vec3 = simplex.iter().map(|&(vertex, weight)| vertex.a * weight).sum();
+    let point_b: Vec3 = simplex.iter().map(|&(vertex, weight)| vertex.b * weight).sum();
+    let distance = closest.length();
+    Some(Separation {
+        normal: (-closest).try_normalize()?,
+        distance,
+        point_a,
+        point_b,
+    })
+}
+
+/// The smallest sub-simplex of `vertices` whose convex hull contains the
+/// point closest to the origin, with the barycentric weight of each of its
+/// vertices. A full tetrahedron is returned when it contains the origin.
+fn closest_on_simplex(vertices: &[Vertex]) -> Vec<(Vertex, f32)> {
+    match *vertices {
+        [first] => vec![(first, 1.0)],
+        [first, second] => segment(first, second),
+        [first, second, third] => triangle(first, second, third),
+        [first, second, third, fourth] => {
+            let faces = [
+                ([first, second, third], fourth),
+                ([first, third, fourth], second),
+                ([first, fourth, second], third),
+                ([second, fourth, third], first),
+            ];
+            // The origin is inside unless it is beyond one of the faces. A
+            // flat tetrahedron has no inside, so all faces are tried.
+            let edges = [second.point - first.point, third.point - first.point, fourth.point - first.point];
+            let volume = edges[0].dot(edges[1].cross(edges[2]));
+            let flat = volume.abs() <= f32::EPSILON * edges.iter().map(|edge| edge.length_squared()).sum::<f32>().powf(1.5);
+            let outside = faces.iter().filter(|([p0, p1, p2], opposite)| {
+                let normal = (p1.point - p0.point).cross(p2.point - p0.point);
+                let origin_side = normal.dot(-p0.point);
+                let opposite_side = normal.dot(opposite.point - p0.point);
+                flat || origin_side * opposite_side <= 0.0
+            });
+            outside
+                .map(|&([p0, p1, p2], _)| triangle(p0, p1, p2))
+                .min_by(|x, y| weighted(x).length_squared().total_cmp(&weighted(y).length_squared()))
+                .unwrap_or_else(|| vertices.iter().map(|&vertex| (vertex, 0.25)).collect())
+        }
+        _ => unreachable!("a simplex has one to four vertices"),
+    }
+}
+
+fn weighted(simplex: &[(Vertex, f32)]) -> Vec3 {
+    simplex.iter().map(|&(vertex, weight)| vertex.point * weight).sum()
+}
+
+fn segment(first: Vertex, second: Vertex) -> Vec<(Vertex, f32)> {
+    let edge = second.point - first.point;
+    let t = (-first.point).dot(edge) / edge.length_squared().max(f32::MIN_POSITIVE);
+    if t <= 0.0 {
+        vec![(first, 1.0)]
+    } else if t >= 1.0 {
+        vec![(second, 1.0)]
+    } else {
+        vec![(first, 1.0 - t), (second, t)]
+    }
+}
+
+/// Closest feature of a triangle to the origin, by Voronoi regions.
+fn triangle(first: Vertex, second: Vertex, third: Vertex) -> Vec<(Vertex, f32)> {
+    let (ab, ac, ap) = (second.point - first.point, third.point - first.point, -first.point);
+    let (d1, d2) = (ab.dot(ap), ac.dot(ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return vec![(first, 1.0)];
+    }
+    let bp = -second.point;
+    let (d3, d4) = (ab.dot(bp), ac.dot(bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return vec![(second, 1.0)];
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        let t = d1 / (d1 - d3);
+        return vec![(first, 1.0 - t), (second, t)];
+    }
+    let cp = -third.point;
+    let (d5, d6) = (ab.dot(cp), ac.dot(cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return vec![(third, 1.0)];
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        let t = d2 / (d2 - d6);
+        return vec![(first, 1.0 - t), (third, t)];
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        let t = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return vec![(second, 1.0 - t), (third, t)];
+    }
+    let total = va + vb + vc;
+    if total.abs() <= f32::MIN_POSITIVE {
+        // Degenerate triangle: fall back to one of its edges
+        return segment(first, second);
+    }
+    vec![(first, va / total), (second, vb / total), (third, vc / total)]
+}
+
+/// Penetration of two overlapping shapes, by EPA from a simplex that
+/// touches or encloses the origin.
+fn penetration(a: &Convex<'_>, b: &Convex<'_>, simplex: Vec<Vertex>) -> Option<Separation> {
+    let vertices = enclosing_tetrahedron(a, b, simplex)?;
+    let centroid = vertices.iter().map(|vertex| vertex.point).sum::<Vec3>() * 0.25;
+    let mut polytope = Polytope { vertices, faces: Vec::new() };
+    for corners in [[0, 1, 2], [0, 1, 3], [0, 2, 3], [1, 2, 3]] {
+        let face = polytope.face(corners);
+        // Wind every face so its normal points away from the interior
+        let face = if face.normal.dot(polytope.vertices[corners[0]].point - centroid) < 0.0 {
+            polytope.face([corners[0], corners[2], corners[1]])
+        } else {
+            face
+        };
+        polytope.faces.push(face);
+    }
+
+    let mut nearest = polytope.nearest()?;
+    for _ in 0..MAX_EPA_ITERATIONS {
+        let next = support(a, b, nearest.normal);
+        let reach = nearest.normal.dot(next.point);
+        if reach - nearest.distance <= TOLERANCE * reach.abs().max(1.0) {
+            break;
+        }
+        polytope.vertices.push(next);
+        if !polytope.expand(polytope.vertices.len() - 1) {
+            break;
+        }
+        nearest = polytope.nearest()?;
+    }
+
+    // Witness points from the projection of the origin onto the face
+    let [p0, p1, p2] = nearest.corners.map(|corner| polytope.vertices[corner]);
+    let projection = nearest.normal * nearest.distance;
+    let weights = barycentric(projection, [p0.point, p1.point, p2.point]);
+    Some(Separation {
+        normal: nearest.normal,
+        distance: -nearest.distance,
+        point_a: p0.a * weights.x + p1.a * weights.y + p2.a * weights.z,
+        point_b: p0.b * weights.x + p1.b * weights.y + p2.b * weights.z,
+    })
+}
+
+/// Extend a simplex that touches the origin to a tetrahedron of positive
+/// volume, using support points in directions it does not span yet.
+fn enclosing_tetrahedron(a: &Convex<'_>, b: &Convex<'_>, mut simplex: Vec<Vertex>) -> Option<Vec<Vertex>> {
+    let axes = [Vec3::X, Vec3::NEG_X, Vec3::Y, Vec3::NEG_Y, Vec3::Z, Vec3::NEG_Z];
+    let scale = simplex.iter().map(|vertex| vertex.point.length()).fold(1e-3, f32::max);
+    let epsilon = TOLERANCE * scale;
+    while simplex.len() < 4 {
+        let directions: Vec<Vec3> = match simplex[..] {
+            [_] => axes.to_vec(),
+            [first, second] => {
+                let edge = second.point - first.point;
+                let side = edge.any_orthonormal_vector();
+                let other = edge.normalize_or_zero().cross(side);
+                vec![side, -side, other, -other]
+            }
+            [first, second, third] => {
+                let normal = (second.point - first.point).cross(third.point - first.point).normalize_or_zero();
+                vec![normal, -normal]
+            }
+            _ => unreachable!(),
+        };
+        let grows = |vertex: &Vertex| match simplex[..] {
+            [first] => vertex.point.distance(first.point) > epsilon,
+            [first, second] => {
+                let edge = (second.point - first.point).normalize_or_zero();
+                (vertex.point - first.point).reject_from_normalized(edge).length() > epsilon
+            }
+            [first, second, third] => {
+                let normal = (second.point - first.point).cross(third.point - first.point).normalize_or_zero();
+                normal.dot(vertex.point - first.point).abs() > epsilon
+            }
+            _ => false,
+        };
+        let next = directions.iter().map(|&direction| support(a, b, direction)).find(grows)?;
+        simplex.push(next);
+    }
+    Some(simplex)
+}
+
+/// Convex polytope grown by EPA. Faces are wound counter-clockwise seen
+/// from outside.
+struct Polytope {
+    vertices: Vec<Vertex>,
+    faces: Vec<Face>,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Face {
+    corners: [usize; 3],
+    normal: Vec3,
+    /// Distance of the face plane from the origin.
+    distance: f32,
+}
+
+impl Polytope {
+    fn face(&self, corners: [usize; 3]) -> Face {
+        let [p0, p1, p2] = corners.map(|corner| self.vertices[corner].point);
+        let normal = (p1 - p0).cross(p2 - p0).normalize_or_zero();
+        Face {
+            corners,
+            normal,
+            distance: normal.dot(p0),
+        }
+    }
+
+    fn nearest(&self) -> Option<Face> {
+        self.faces
+            .iter()
+            .copied()
+            .filter(|face| face.normal != Vec3::ZERO)
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+
+    /// Replace the faces that vertex `apex` sees by a fan around their
+    /// horizon. Returns `false` if it sees none.
+    fn expand(&mut self, apex: usize) -> bool {
+        let point = self.vertices[apex].point;
+        let (visible, kept): (Vec<Face>, Vec<Face>) = self
+            .faces
+            .iter()
+            .partition(|face| face.normal.dot(point) - face.distance > 0.0);
+        if visible.is_empty() {
+            return false;
+        }
+        let edges: Vec<(usize, usize)> = visible
+            .iter()
+            .flat_map(|face| {
+                let [p0, p1, p2] = face.corners;
+                [(p0, p1), (p1, p2), (p2, p0)]
+            })
+            .collect();
+        self.faces = kept;
+        for &(from, to) in &edges {
+            if !edges.contains(&(to, from)) {
+                let face = self.face([from, to, apex]);
+                self.faces.push(face);
+            }
+        }
+        true
+    }
+}
+
+/// Barycentric coordinates of `point` projected onto the triangle's plane.
+fn barycentric(point: Vec3, [p0, p1, p2]: [Vec3; 3]) -> Vec3 {
+    let (v0, v1, v2) = (p1 - p0, p2 - p0, point - p0);
+    let (d00, d01, d11) = (v0.dot(v0), v0.dot(v1), v1.dot(v1));
+    let (d20, d21) = (v2.dot(v0), v2.dot(v1));
+    let denominator = d00 * d11 - d01 * d01;
+    if denominator.abs() <= f32::MIN_POSITIVE {
+        return Vec3::new(1.0, 0.0, 0.0);
+    }
+    let second = (d11 * d20 - d01 * d21) / denominator;
+    let third = (d00 * d21 - d01 * d20) / denominator;
+    Vec3::new(1.0 - second - third, second, third)
+}
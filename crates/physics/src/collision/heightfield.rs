@@ -0,0 +1,161 @@
+//! Contacts between bodies and heightfield terrain
+//!
+//! Spheres use the exact closest point on the terrain triangles around them.
+//! Boxes, cylinders and convex hulls are tested like against a plane, but
+//! with the height and slope of the terrain under each corner or rim sample. Terrain samples
+//! under the body add points of their own, so peaks and ridges narrower
+//! than the body are not missed between its samples.
+
+use glam::Vec3;
+
+use crate::heightfield::Heightfield;
+use crate::types::Sphere;
+use super::convex::Convex;
+use super::manifold::{ManifoldPoint, ManifoldPoints};
+
+/// Feature id bit of points at terrain samples; the low bits hold the
+/// sample index.
+const TERRAIN_SAMPLE: u32 = 1 << 31;
+
+/// Generate a manifold between a sphere and a heightfield.
+pub(crate) fn sphere_heightfield_manifold(
+    sphere: &Sphere,
+    heightfield: &Heightfield,
+    margin: f32,
+) -> Option<ManifoldPoints> {
+    let center = Vec3::from(sphere.pos);
+
+    // A centre below the surface is pushed out along the normal of the
+    // triangle it is under.
+    let (normal, distance) = match heightfield.surface_at(center.x, center.z) {
+        Some((height, normal)) if center.y <= height => (normal, (center.y - height) * normal.y),
+        _ => {
+            let reach = Vec3::splat(sphere.radius + margin);
+            let closest = heightfield
+                .triangles_in(center - reach, center + reach)
+                .map(|triangle| closest_point_on_triangle(center, triangle))
+                .min_by(|a, b| a.distance_squared(center).total_cmp(&b.distance_squared(center)))?;
+            let offset = center - closest;
+            (offset.try_normalize()?, offset.length())
+        }
+    };
+    let depth = sphere.radius - distance;
+    if depth < -margin {
+        return None;
+    }
+
+    Some(ManifoldPoints {
+        normal: -normal,
+        points: vec![ManifoldPoint {
+            position: center - normal * (0.5 * (sphere.radius + distance)),
+            depth,
+            feature_id: 0,
+        }],
+    })
+}
+
+/// Generate a manifold between a box, cylinder or convex hull and a
+/// heightfield.
+pub(crate) fn convex_heightfield_manifold(
+    convex: &Convex<'_>,
+    heightfield: &Heightfield,
+    margin: f32,
+) -> Option<ManifoldPoints> {
+    let center = convex.center();
+    let up = heightfield
+        .surface_at(center.x, center.z)
+        .map_or(Vec3::Y, |(_, normal)| normal);
+    let samples = convex.vertices(up);
+    let entry = |point: Vec3, direction: Vec3| convex.line_interval(point, direction).map(|(enter, _)| enter);
+    sampled_manifold(heightfield, &samples, entry, margin)
+}
+
+/// Contacts of a convex body, given by `samples` of its surface that
+/// include its extreme points, against a heightfield.
+///
+/// Each sample is tested against the plane of the terrain triangle under
+/// it. `entry(point, direction)` is the distance along `direction` at which
+/// a line through `point` enters the body, or `None` if it misses; terrain
+/// samples under the body are tested along the manifold normal with it.
+fn sampled_manifold(
+    heightfield: &Heightfield,
+    samples: &[(Vec3, u32)],
+    entry: impl Fn(Vec3, Vec3) -> Option<f32>,
+    margin: f32,
+) -> Option<ManifoldPoints> {
+    let mut points = Vec::new();
+    let mut normal_sum = Vec3::ZERO;
+    for &(sample, feature_id) in samples {
+        let Some((height, normal)) = heightfield.surface_at(sample.x, sample.z) else {
+            continue;
+        };
+        let distance = (sample.y - height) * normal.y;
+        if distance <= margin {
+            points.push(ManifoldPoint {
+                position: sample - normal * (0.5 * distance),
+                depth: -distance,
+                feature_id,
+            });
+            normal_sum += normal;
+        }
+    }
+
+    // Pushing straight up always separates a body from a heightfield, which
+    // is the safest choice when only terrain samples touch it.
+    let normal = normal_sum.try_normalize().unwrap_or(Vec3::Y);
+    let (min, max) = samples
+        .iter()
+        .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), &(sample, _)| {
+            (min.min(sample), max.max(sample))
+        });
+    for (index, vertex) in heightfield.vertices_in(min, max) {
+        let Some(distance) = entry(vertex, normal).filter(|&distance| distance <= margin) else {
+            continue;
+        };
+        #[allow(clippy::cast_possible_truncation)]
+        let feature_id = TERRAIN_SAMPLE | index as u32;
+        points.push(ManifoldPoint {
+            position: vertex + normal * (0.5 * distance),
+            depth: -distance,
+            feature_id,
+        });
+    }
+
+    (!points.is_empty()).then(|| ManifoldPoints {
+        normal: -normal,
+        points,
+    })
+}
+
+/// Closest point to `point` on the triangle `[a, b, c]`.
+pub(super) fn closest_point_on_triangle(point: Vec3, [a, b, c]: [Vec3; 3]) -> Vec3 {
+    let (ab, ac, ap) = (b - a, c - a, point - a);
+    let (d1, d2) = (ab.dot(ap), ac.dot(ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+    let bp = point - b;
+    let (d3, d4) = (ab.dot(bp), ac.dot(bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = point - c;
+    let (d5, d6) = (ab.dot(cp), ac.dot(cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let denominator = 1.0 / (va + vb + vc);
+    a + ab * (vb * denominator) + ac * (vc * denominator)
+}
@@ -0,0 +1,173 @@
+//! Contacts of convex hulls
+//!
+//! Hulls take the general convex path against the other dynamic shapes:
+//! GJK, or EPA when the shapes overlap, gives the contact normal. When a
+//! flat face of either shape lies along it, the incident face of the other
+//! shape is clipped against that reference face as for two boxes, and the
+//! reference face normal becomes the contact normal; the core segment of a
+//! capsule is clipped the same way. Otherwise every corner
+//! of one shape that a line along the normal carries into the other becomes
+//! a contact point, with its depth measured along that line. When no
+//! corner qualifies, as for crossing edges or curved surfaces, the GJK or
+//! EPA witness points give a single contact. Against planes, hull corners
+//! are tested like box corners.
+
+use glam::Vec3;
+
+use crate::types::Plane;
+use super::box_box::clip_polygon;
+use super::capsule::capsule_face_contacts;
+use super::convex::Convex;
+use super::gjk::{separation, Separation};
+use super::manifold::{ManifoldPoint, ManifoldPoints};
+
+/// Feature id bit of points at corners of body B, or clipped from the
+/// incident face of body A; the low bits hold the corner.
+const CORNER_OF_B: u32 = 1 << 30;
+/// Feature id of a point between the GJK or EPA witness points.
+pub(super) const WITNESS: u32 = u32::MAX;
+/// Smallest cosine between the contact normal and a face for the face to
+/// be used as the reference face.
+const FACE_ALIGNMENT: f32 = 0.99;
+/// Cosine by which a face of B must be better aligned than the face of A
+/// to become the reference face.
+const FACE_ALIGNMENT_TOLERANCE: f32 = 0.001;
+/// Incident corners overhanging the reference face by less than this are
+/// kept as they are, as for boxes.
+const CLIP_TOLERANCE: f32 = 0.005;
+
+/// Generate a manifold between two convex shapes.
+pub(crate) fn convex_manifold(a: &Convex<'_>, b: &Convex<'_>, margin: f32) -> Option<ManifoldPoints> {
+    let separation = separation(a, b)?;
+    if separation.distance > margin {
+        return None;
+    }
+    let (normal, points) = contact_points(a, b, &separation, margin);
+    Some(ManifoldPoints { normal, points })
+}
+
+/// Contact normal and points of two shapes `separation` apart: clipped
+/// faces or capsule segments, corners, or else the witness points.
+pub(super) fn contact_points(
+    a: &Convex<'_>,
+    b: &Convex<'_>,
+    separation: &Separation,
+    margin: f32,
+) -> (Vec3, Vec<ManifoldPoint>) {
+    let clipped = capsule_face_contacts(a, b, separation.normal, margin)
+        .or_else(|| face_contacts(a, b, separation.normal, margin));
+    if let Some((normal, points)) = clipped {
+        if !points.is_empty() {
+            return (normal, points);
+        }
+    }
+    let mut points = corner_contacts(a, b, separation.normal, margin);
+    if points.is_empty() {
+        points.push(ManifoldPoint {
+            position: (separation.point_a + separation.point_b) * 0.5,
+            depth: -separation.distance,
+            feature_id: WITNESS,
+        });
+    }
+    (separation.normal, points)
+}
+
+/// Clip the incident face of one shape against the reference face of the
+/// other, when either has a face along `normal`, the direction from `a` to
+/// `b`. Returns the reference face normal oriented from `a` to `b` and the
+/// clipped points within `margin` of the reference face.
+fn face_contacts(a: &Convex<'_>, b: &Convex<'_>, normal: Vec3, margin: f32) -> Option<(Vec3, Vec<ManifoldPoint>)> {
+    let (normal_a, face_a) = a.face(normal)?;
+    let (normal_b, face_b) = b.face(-normal)?;
+    let (alignment_a, alignment_b) = (normal_a.dot(normal), -normal_b.dot(normal));
+    if alignment_a.max(alignment_b) < FACE_ALIGNMENT {
+        return None;
+    }
+    // The incident face is the face of the other shape most opposed to the
+    // reference face
+    let (reference_normal, reference, (_, incident), incident_flag) =
+        if alignment_b > alignment_a + FACE_ALIGNMENT_TOLERANCE {
+            (normal_b, face_b, a.face(-normal_b)?, 0)
+        } else {
+            (normal_a, face_a, b.face(-normal_a)?, CORNER_OF_B)
+        };
+    let contact_normal = if incident_flag == 0 { -reference_normal } else { reference_normal };
+
+    let mut polygon: Vec<(Vec3, u32)> = incident.into_iter().map(|(corner, id)| (corner, incident_flag | id)).collect();
+    for (edge, (&(start, _), &(end, _))) in (0u32..).zip(reference.iter().zip(reference.iter().cycle().skip(1))) {
+        let side = (end - start).cross(reference_normal).normalize_or_zero();
+        polygon = clip_polygon(&polygon, side, side.dot(start) + CLIP_TOLERANCE, incident_flag | edge);
+        if polygon.is_empty() {
+            return None;
+        }
+    }
+
+    let origin = reference[0].0;
+    let points = polygon
+        .into_iter()
+        .filter_map(|(corner, feature_id)| {
+            let gap = (corner - origin).dot(reference_normal);
+            (gap <= margin).then(|| ManifoldPoint {
+                position: corner - reference_normal * (0.5 * gap),
+                depth: -gap,
+                feature_id,
+            })
+        })
+        .collect();
+    Some((contact_normal, points))
+}
+
+/// Corners of either shape within `margin` of the other along `normal`,
+/// the direction from `a` to `b`. Spheres touch at a single point, so pairs
+/// with a sphere have none.
+fn corner_contacts(a: &Convex<'_>, b: &Convex<'_>, normal: Vec3, margin: f32) -> Vec<ManifoldPoint> {
+    if a.is_round() || b.is_round() {
+        return Vec::new();
+    }
+    let mut points = Vec::new();
+    for (corner, feature_id) in a.vertices(-normal) {
+        if let Some((enter, exit)) = b.line_interval(corner, normal) {
+            if exit >= 0.0 && enter <= margin {
+                points.push(ManifoldPoint {
+                    position: corner + normal * (0.5 * enter),
+                    depth: -enter,
+                    feature_id,
+                });
+            }
+        }
+    }
+    for (corner, feature_id) in b.vertices(normal) {
+        if let Some((enter, exit)) = a.line_interval(corner, -normal) {
+            if exit >= 0.0 && enter <= margin {
+                points.push(ManifoldPoint {
+                    position: corner - normal * (0.5 * enter),
+                    depth: -enter,
+                    feature_id: CORNER_OF_B | feature_id,
+                });
+            }
+        }
+    }
+    points
+}
+
+/// Generate a manifold between the corners of a convex shape and a plane.
+pub(crate) fn convex_plane_manifold(convex: &Convex<'_>, plane: &Plane, margin: f32) -> Option<ManifoldPoints> {
+    let plane_normal = Vec3::from(plane.normal);
+    let points: Vec<ManifoldPoint> = convex
+        .vertices(plane_normal)
+        .into_iter()
+        .filter_map(|(corner, feature_id)| {
+            let distance = plane_normal.dot(corner) + plane.d;
+            (distance <= margin).then(|| ManifoldPoint {
+                position: corner - plane_normal * (0.5 * distance),
+                depth: -distance,
+                feature_id,
+            })
+        })
+        .collect();
+
+    (!points.is_empty()).then(|| ManifoldPoints {
+        normal: -plane_normal,
+        points,
+    })
+}
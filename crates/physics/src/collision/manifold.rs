@@ -0,0 +1,346 @@
+//! Persistent contact manifolds
+//!
+//! Narrow-phase generators produce up to four contact points per body pair.
+//! Every point carries a feature id that names the pair of geometric features
+//! (vertex, edge, face) it came from. When the same pair is seen on the next
+//! step, points with matching ids inherit the accumulated impulses and
+//! friction anchors of their predecessors so the solver can be warm started.
+
+use std::collections::BTreeMap;
+
+use glam::Quat;
+
+use super::primitives::Primitive;
+use super::{
+    box_box_manifold, box_capsule_manifold, box_plane_manifold, capsule_capsule_manifold, capsule_plane_manifold,
+    combine_friction, combine_restitution, convex_heightfield_manifold, convex_manifold, convex_mesh_manifold,
+    convex_plane_manifold, cylinder_capsule_manifold, cylinder_plane_manifold, sphere_box_manifold,
+    sphere_capsule_manifold, sphere_cylinder_manifold, sphere_heightfield_manifold, sphere_plane_manifold,
+    sphere_sphere_manifold, Convex,
+};
+use crate::body::BodyHandle;
+use crate::types::{Material, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of points kept per manifold.
+pub const MAX_MANIFOLD_POINTS: usize = 4;
+
+/// Points without a matching feature id inherit the state of an old point
+/// this close (in body A's frame).
+const MATCH_DISTANCE: f32 = 0.02;
+
+/// A single point of a [`ContactManifold`].
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct ContactPoint {
+    /// World-space contact point, halfway between the two surfaces.
+    pub position: Vec3,
+    /// Penetration depth (negative while the surfaces are still apart).
+    pub depth: f32,
+    /// Identifier of the geometric features that produced this point.
+    pub feature_id: u32,
+    /// Accumulated normal impulse from the last solve.
+    pub normal_impulse: f32,
+    /// Accumulated friction impulse along the two manifold tangents.
+    pub tangent_impulse: [f32; 2],
+    /// Surface point of body A in A's local frame.
+    pub(crate) local_a: Vec3,
+    /// Surface point of body B in B's local frame.
+    pub(crate) local_b: Vec3,
+    /// Static friction anchor on body A in A's local frame.
+    pub(crate) anchor_a: Vec3,
+    /// Static friction anchor on body B in B's local frame.
+    pub(crate) anchor_b: Vec3,
+    /// Set when friction saturated during the last solve, so the anchors are
+    /// re-seeded from the current points instead of being kept.
+    pub(crate) sliding: bool,
+}
+
+/// Up to [`MAX_MANIFOLD_POINTS`] contact points shared by one body pair, or
+/// by one pair of children when compound bodies are involved.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContactManifold {
+    /// First body of the pair (always the smaller handle).
+    pub body_a: BodyHandle,
+    /// Second body of the pair.
+    pub body_b: BodyHandle,
+    /// Child of body A that touches, if body A is a
+    /// [`Compound`](crate::compound::Compound); zero for any other body.
+    pub child_a: usize,
+    /// Child of body B that touches, if body B is a compound.
+    pub child_b: usize,
+    /// Contact normal pointing from body A to body B.
+    pub normal: Vec3,
+    /// Contact points of this manifold.
+    pub points: Vec<ContactPoint>,
+    /// Combined friction coefficient.
+    pub friction: f32,
+    /// Combined restitution coefficient.
+    pub restitution: f32,
+    /// Combined contact compliance, the sum of both materials' compliance.
+    pub compliance: f32,
+}
+
+impl ContactManifold {
+    /// Key of this manifold in a [`ManifoldCache`].
+    pub(crate) fn key(&self) -> ManifoldKey {
+        (self.body_a, self.body_b, self.child_a, self.child_b)
+    }
+}
+
+/// Raw contact point produced by a narrow-phase generator.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct ManifoldPoint {
+    pub position: glam::Vec3,
+    pub depth: f32,
+    pub feature_id: u32,
+}
+
+/// Output of a narrow-phase generator for one body pair.
+#[derive(Clone, Debug)]
+pub(crate) struct ManifoldPoints {
+    /// Normal pointing from body A to body B.
+    pub normal: glam::Vec3,
+    pub points: Vec<ManifoldPoint>,
+}
+
+/// Position and orientation of a body's reference frame.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct BodyFrame {
+    pub position: glam::Vec3,
+    pub orientation: Quat,
+}
+
+impl BodyFrame {
+    pub const IDENTITY: Self = Self {
+        position: glam::Vec3::ZERO,
+        orientation: Quat::IDENTITY,
+    };
+
+    pub fn to_local(self, point: glam::Vec3) -> glam::Vec3 {
+        self.orientation.inverse() * (point - self.position)
+    }
+
+    pub fn to_world(self, point: glam::Vec3) -> glam::Vec3 {
+        self.position + self.orientation * point
+    }
+}
+
+/// Convert an `[x, y, z, w]` body orientation into a unit quaternion.
+pub(crate) fn body_rotation(orientation: [f32; 4]) -> Quat {
+    let rotation = Quat::from_array(orientation);
+    if rotation.length_squared() > 0.0 {
+        rotation.normalize()
+    } else {
+        Quat::IDENTITY
+    }
+}
+
+/// Identifies a manifold by its body pair and the touching child of each
+/// body. Every child pair of two compounds gets its own manifold, since
+/// children can touch along different normals.
+pub(crate) type ManifoldKey = (BodyHandle, BodyHandle, usize, usize);
+
+/// Cache of manifolds keyed by body pair and children. A `BTreeMap` keeps
+/// the solve order deterministic between runs.
+pub(crate) type ManifoldCache = BTreeMap<ManifoldKey, ContactManifold>;
+
+/// Manifolds of every child pair of the bodies `a` and `b`.
+pub(crate) fn pair_manifolds(
+    cache: &ManifoldCache,
+    (a, b): (BodyHandle, BodyHandle),
+) -> impl Iterator<Item = (&ManifoldKey, &ContactManifold)> {
+    cache.range((a, b, 0, 0)..=(a, b, usize::MAX, usize::MAX))
+}
+
+/// Run the narrow phase for a pair of primitives.
+///
+/// `a` must belong to the smaller handle. Returns `None` when the shapes are
+/// further apart than `margin` or when the pair has no generator.
+pub(crate) fn generate_manifold(
+    a: &Primitive<'_>,
+    b: &Primitive<'_>,
+    margin: f32,
+) -> Option<ManifoldPoints> {
+    let manifold = match (a, b) {
+        (Primitive::Sphere(s1), Primitive::Sphere(s2)) => sphere_sphere_manifold(s1, s2, margin),
+        (Primitive::Sphere(s), Primitive::Box(b)) => sphere_box_manifold(s, b, margin),
+        (Primitive::Sphere(s), Primitive::Cylinder(c)) => sphere_cylinder_manifold(s, c, margin),
+        (Primitive::Sphere(s), Primitive::Capsule(c)) => sphere_capsule_manifold(s, c, margin),
+        (Primitive::Sphere(s), Primitive::Plane(p)) => sphere_plane_manifold(s, p, margin),
+        (Primitive::Sphere(s), Primitive::Heightfield(h)) => sphere_heightfield_manifold(s, h, margin),
+        (Primitive::Box(b1), Primitive::Box(b2)) => box_box_manifold(b1, b2, margin),
+        (Primitive::Box(b), Primitive::Capsule(c)) => box_capsule_manifold(b, c, margin),
+        (Primitive::Box(b), Primitive::Plane(p)) => box_plane_manifold(b, p, margin),
+        (Primitive::Cylinder(cy), Primitive::Capsule(c)) => cylinder_capsule_manifold(cy, c, margin),
+        (Primitive::Cylinder(c), Primitive::Plane(p)) => cylinder_plane_manifold(c, p, margin),
+        (Primitive::Capsule(c1), Primitive::Capsule(c2)) => capsule_capsule_manifold(c1, c2, margin),
+        (Primitive::Capsule(c), Primitive::Plane(p)) => capsule_plane_manifold(c, p, margin),
+        (Primitive::Hull(h), Primitive::Plane(p)) => convex_plane_manifold(&Convex::Hull(h), p, margin),
+        (a, Primitive::Hull(_)) => convex_manifold(&Convex::of(a)?, &Convex::of(b)?, margin),
+        (a, Primitive::Heightfield(h)) => convex_heightfield_manifold(&Convex::of(a)?, h, margin),
+        (a, Primitive::Mesh(m)) => convex_mesh_manifold(&Convex::of(a)?, m, margin),
+        _ => None,
+    }?;
+    let points = reduce_to_four(manifold.points, manifold.normal);
+    if points.is_empty() {
+        return None;
+    }
+    Some(ManifoldPoints {
+        normal: manifold.normal,
+        points,
+    })
+}
+
+/// Turn fresh narrow-phase output into a manifold, inheriting impulses and
+/// friction anchors from `previous` for points whose feature ids match.
+pub(crate) fn update_manifold(
+    key: ManifoldKey,
+    frames: (BodyFrame, BodyFrame),
+    fresh: &ManifoldPoints,
+    materials: (&Material, &Material),
+    previous: Option<&ContactManifold>,
+    warm_starting: bool,
+) -> ContactManifold {
+    let (frame_a, frame_b) = frames;
+    let normal = fresh.normal;
+    let old_points = previous.map_or(&[][..], |old| old.points.as_slice());
+    let mut claimed = vec![false; old_points.len()];
+
+    let mut points = Vec::with_capacity(fresh.points.len());
+    for raw in &fresh.points {
+        let half = normal * (0.5 * raw.depth);
+        // Surface of A sits on the B side of the midpoint when penetrating.
+        let local_a: Vec3 = frame_a.to_local(raw.position + half).into();
+        let local_b: Vec3 = frame_b.to_local(raw.position - half).into();
+        let mut point = ContactPoint {
+            position: raw.position.into(),
+            depth: raw.depth,
+            feature_id: raw.feature_id,
+            normal_impulse: 0.0,
+            tangent_impulse: [0.0; 2],
+            local_a,
+            local_b,
+            anchor_a: local_a,
+            anchor_b: local_b,
+            sliding: false,
+        };
+
+        let by_id = old_points.iter().position(|p| p.feature_id == raw.feature_id);
+        let matching = by_id.filter(|&i| !claimed[i]).or_else(|| {
+            old_points.iter().enumerate().position(|(i, p)| {
+                !claimed[i] && glam::Vec3::from(p.local_a).distance(local_a.into()) < MATCH_DISTANCE
+            })
+        });
+        if let Some(index) = matching {
+            claimed[index] = true;
+            let old = &old_points[index];
+            if warm_starting {
+                point.normal_impulse = old.normal_impulse;
+                point.tangent_impulse = old.tangent_impulse;
+            }
+            if !old.sliding {
+                point.anchor_a = old.anchor_a;
+                point.anchor_b = old.anchor_b;
+            }
+        }
+        points.push(point);
+    }
+
+    let (material_a, material_b) = materials;
+    let (body_a, body_b, child_a, child_b) = key;
+    ContactManifold {
+        body_a,
+        body_b,
+        child_a,
+        child_b,
+        normal: normal.into(),
+        points,
+        friction: combine_friction(material_a.friction, material_b.friction),
+        restitution: combine_restitution(material_a.restitution, material_b.restitution),
+        compliance: material_a.compliance + material_b.compliance,
+    }
+}
+
+/// Keep at most four points that preserve the deepest contact and span the
+/// largest area in the contact plane.
+fn reduce_to_four(mut points: Vec<ManifoldPoint>, normal: glam::Vec3) -> Vec<ManifoldPoint> {
+    if points.len() <= MAX_MANIFOLD_POINTS {
+        return points;
+    }
+
+    let mut chosen = Vec::with_capacity(MAX_MANIFOLD_POINTS);
+
+    // 1. Deepest point.
+    let deepest = index_of_max(&points, |p| p.depth);
+    chosen.push(points.swap_remove(deepest));
+
+    // 2. Point furthest from the first.
+    let first = chosen[0].position;
+    let furthest = index_of_max(&points, |p| p.position.distance_squared(first));
+    chosen.push(points.swap_remove(furthest));
+
+    // 3. Point that spans the largest triangle with the first two.
+    let second = chosen[1].position;
+    let area = |p: &ManifoldPoint| (second - first).cross(p.position - first).dot(normal);
+    let widest = index_of_max(&points, |p| area(p).abs());
+    let winding = area(&points[widest]).signum();
+    chosen.push(points.swap_remove(widest));
+
+    // 4. Point that adds the most area outside the triangle.
+    let third = chosen[2].position;
+    let outside = |p: &ManifoldPoint| {
+        let edges = [(first, second), (second, third), (third, first)];
+        edges
+            .iter()
+            .map(|&(from, to)| -winding * (to - from).cross(p.position - from).dot(normal))
+            .fold(0.0_f32, f32::max)
+    };
+    let fourth = index_of_max(&points, outside);
+    if outside(&points[fourth]) > 0.0 {
+        chosen.push(points.swap_remove(fourth));
+    }
+
+    chosen
+}
+
+fn index_of_max(points: &[ManifoldPoint], key: impl Fn(&ManifoldPoint) -> f32) -> usize {
+    let mut best = 0;
+    let mut best_value = f32::NEG_INFINITY;
+    for (i, point) in points.iter().enumerate() {
+        let value = key(point);
+        if value > best_value {
+            best = i;
+            best_value = value;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f32, z: f32, depth: f32, feature_id: u32) -> ManifoldPoint {
+        ManifoldPoint {
+            position: glam::Vec3::new(x, 0.0, z),
+            depth,
+            feature_id,
+        }
+    }
+
+    #[test]
+    fn reduction_keeps_deepest_and_outer_points() {
+        let points = vec![
+            point(-1.0, -1.0, 0.01, 0),
+            point(1.0, -1.0, 0.01, 1),
+            point(1.0, 1.0, 0.01, 2),
+            point(-1.0, 1.0, 0.05, 3),
+            point(0.0, 0.0, 0.02, 4),
+            point(0.5, 0.0, 0.02, 5),
+        ];
+        let reduced = reduce_to_four(points, glam::Vec3::Y);
+        let mut ids: Vec<u32> = reduced.iter().map(|p| p.feature_id).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![0, 1, 2, 3]);
+    }
+}
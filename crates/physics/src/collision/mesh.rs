@@ -0,0 +1,70 @@
+//! Contacts between convex bodies and static triangle meshes
+//!
+//! The mesh's bounding volume hierarchy finds the triangles near the body,
+//! and each of them is treated as the face of a solid: a convex shape for
+//! GJK whose inside lies behind it. Triangles the body's centre is behind
+//! are skipped, so bodies are only ever pushed out of the front of a mesh.
+//! The contacts of all triangles share one manifold, whose normal is the
+//! average of theirs.
+
+use glam::Vec3;
+
+use crate::mesh::TriangleMesh;
+use super::convex::Convex;
+use super::gjk::separation;
+use super::hull::contact_points;
+use super::manifold::{ManifoldPoint, ManifoldPoints};
+
+/// Points closer than this to a point of another triangle are dropped, so
+/// that a body resting across a shared edge is not held twice.
+const DUPLICATE_DISTANCE: f32 = 1e-3;
+
+/// Generate a manifold between a convex shape and a triangle mesh.
+pub(crate) fn convex_mesh_manifold(convex: &Convex<'_>, mesh: &TriangleMesh, margin: f32) -> Option<ManifoldPoints> {
+    let center = convex.center();
+    let (min, max) = convex.bounds();
+    let reach = Vec3::splat(margin);
+
+    let mut points: Vec<ManifoldPoint> = Vec::new();
+    let mut normal_sum = Vec3::ZERO;
+    for (index, corners) in mesh.triangles_in(min - reach, max + reach) {
+        let [p0, p1, p2] = corners;
+        let Some(face_normal) = (p1 - p0).cross(p2 - p0).try_normalize() else {
+            continue;
+        };
+        if face_normal.dot(center - p0) < 0.0 {
+            continue;
+        }
+        let triangle = Convex::Triangle(corners);
+        let Some(mut separation) = separation(convex, &triangle) else {
+            continue;
+        };
+        if separation.distance > margin {
+            continue;
+        }
+        // EPA may find it shorter to push the body out through the back of
+        // the triangle; push it out of the front face instead
+        if separation.distance <= 0.0 && separation.normal.dot(face_normal) >= 0.0 {
+            let deepest = convex.support(-face_normal);
+            let distance = face_normal.dot(deepest - p0);
+            separation.normal = -face_normal;
+            separation.distance = distance;
+            (separation.point_a, separation.point_b) = (deepest, deepest - face_normal * distance);
+        }
+
+        let (normal, found) = contact_points(convex, &triangle, &separation, margin);
+        #[allow(clippy::cast_possible_truncation)]
+        let triangle_id = index as u32;
+        for mut point in found {
+            if points.iter().any(|other| other.position.distance(point.position) < DUPLICATE_DISTANCE) {
+                continue;
+            }
+            point.feature_id = point.feature_id.rotate_left(16) ^ triangle_id;
+            points.push(point);
+        }
+        normal_sum += normal;
+    }
+
+    let normal = normal_sum.try_normalize()?;
+    (!points.is_empty()).then_some(ManifoldPoints { normal, points })
+}
@@ -7,33 +7,67 @@
 mod primitives;
 mod dispatcher;
 mod response;
+mod manifold;
+mod ccd;
+mod raycast;
+mod convex;
+mod filter;
+mod gjk;
 
 // Individual collision algorithms
 mod sphere_sphere;
 mod sphere_plane;
 mod sphere_box;
 mod sphere_cylinder;
+mod box_box;
 mod box_plane;
+mod capsule;
+mod compound;
 mod cylinder_plane;
+mod heightfield;
+mod hull;
+mod mesh;
 mod broad_phase;
+mod sweep_and_prune;
 mod stubs;
 
 // Export new unified API
 pub use primitives::{Collider, PrimitiveType, Primitive, PrimitiveMut};
 pub use dispatcher::CollisionDispatcher;
 pub use response::{CollisionResponder, CollisionSolver};
+pub use manifold::{ContactManifold, ContactPoint};
+pub(crate) use manifold::{
+    body_rotation, pair_manifolds, update_manifold, BodyFrame, ManifoldCache, ManifoldKey,
+};
 
 // Keep exporting individual functions for backward compatibility
 pub use sphere_sphere::*;
 pub use sphere_plane::*;
 pub use sphere_box::*;
 pub use sphere_cylinder::*;
+pub(crate) use box_box::box_box_manifold;
+pub(crate) use capsule::{
+    box_capsule_manifold, capsule_capsule_manifold, capsule_plane_manifold, cylinder_capsule_manifold,
+    sphere_capsule_manifold,
+};
+pub(crate) use heightfield::{convex_heightfield_manifold, sphere_heightfield_manifold};
+pub(crate) use hull::{convex_manifold, convex_plane_manifold};
+pub(crate) use mesh::convex_mesh_manifold;
+pub(crate) use compound::generate_manifolds;
+pub(crate) use convex::Convex;
+pub use filter::CollisionFilter;
+pub(crate) use filter::{ordered_pair, PairFilter};
+pub(crate) use ccd::{time_of_impact, Shape, Sweep};
+pub(crate) use raycast::{cast_ray, cast_sphere, CastHit};
 pub use box_plane::*;
 pub use cylinder_plane::*;
 pub use broad_phase::*;
+pub(crate) use broad_phase::{plane_overlaps_box, primitive_bounding_box, spatial_grid_pairs};
+pub(crate) use sweep_and_prune::SweepAndPrune;
 // pub use stubs::*; // Don't re-export to avoid ambiguity
 
 use crate::types::{Vec3, Material};
+use serde::{Deserialize, Serialize};
 
 /// Contact information for collision response
 #[derive(Debug, Clone, Copy)]
@@ -74,8 +108,12 @@ fn combine_restitution(r1: f32, r2: f32) -> f32 {
 }
 
 /// Collision detection configuration
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CollisionConfig {
-    /// Contact offset for continuous collision detection
+    /// Gap that continuous collision detection leaves between a fast body
+    /// and the first surface it would hit. Keep it below
+    /// `ContactParams::contact_margin` so the next step picks the pair up
+    /// as a speculative contact.
     pub contact_offset: f32,
     /// Maximum penetration before position correction
     pub max_penetration: f32,
@@ -1,14 +1,23 @@
 //! Unified primitive trait and collision detection framework
 
-use crate::types::{Vec3, Material, Sphere, BoxBody, Cylinder, Plane};
+use crate::compound::Compound;
+use crate::heightfield::Heightfield;
+use crate::mesh::{ConvexHull, TriangleMesh};
+use crate::types::{Vec3, Material, Sphere, BoxBody, Cylinder, Capsule, Plane};
 
-/// Primitive shape types for collision detection
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Primitive shape types for collision detection, in the same order as
+/// [`crate::body::BodyHandle`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum PrimitiveType {
     Sphere,
     Box,
     Cylinder,
+    Capsule,
+    Hull,
+    Compound,
     Plane,
+    Heightfield,
+    Mesh,
 }
 
 /// Unified interface for collision primitives
@@ -126,6 +135,32 @@ impl Collider for Cylinder {
     }
 }
 
+impl Collider for Capsule {
+    fn primitive_type(&self) -> PrimitiveType {
+        PrimitiveType::Capsule
+    }
+    
+    fn center(&self) -> Vec3 {
+        self.pos
+    }
+    
+    fn material(&self) -> &Material {
+        &self.material
+    }
+    
+    fn support(&self, direction: Vec3) -> Vec3 {
+        // End of the core segment furthest along the direction, pushed out
+        // by the radius
+        let (start, end) = self.segment();
+        let tip = if direction.dot(end - start) >= 0.0 { end } else { start };
+        tip + direction.normalize() * self.radius
+    }
+    
+    fn bounding_radius(&self) -> f32 {
+        self.half_height + self.radius
+    }
+}
+
 impl Collider for Plane {
     fn primitive_type(&self) -> PrimitiveType {
         PrimitiveType::Plane
@@ -156,20 +191,151 @@ impl Collider for Plane {
     }
 }
 
+impl Collider for Heightfield {
+    fn primitive_type(&self) -> PrimitiveType {
+        PrimitiveType::Heightfield
+    }
+    
+    fn center(&self) -> Vec3 {
+        let bounds = self.bounds();
+        (bounds.min + bounds.max) * 0.5
+    }
+    
+    fn material(&self) -> &Material {
+        &self.material
+    }
+    
+    fn support(&self, direction: Vec3) -> Vec3 {
+        // Corner of the bounding box in the given direction
+        let bounds = self.bounds();
+        Vec3::new(
+            if direction.x > 0.0 { bounds.max.x } else { bounds.min.x },
+            if direction.y > 0.0 { bounds.max.y } else { bounds.min.y },
+            if direction.z > 0.0 { bounds.max.z } else { bounds.min.z },
+        )
+    }
+    
+    fn bounding_radius(&self) -> f32 {
+        let bounds = self.bounds();
+        (bounds.max - bounds.min).length() * 0.5
+    }
+}
+
+impl Collider for ConvexHull {
+    fn primitive_type(&self) -> PrimitiveType {
+        PrimitiveType::Hull
+    }
+    
+    fn center(&self) -> Vec3 {
+        self.pos
+    }
+    
+    fn material(&self) -> &Material {
+        &self.material
+    }
+    
+    fn support(&self, direction: Vec3) -> Vec3 {
+        let rotation = super::body_rotation(self.orientation);
+        let local = self.geometry.support(rotation.inverse() * glam::Vec3::from(direction));
+        self.pos + (rotation * local).into()
+    }
+    
+    fn bounding_radius(&self) -> f32 {
+        self.geometry
+            .local_vertices()
+            .iter()
+            .map(|vertex| vertex.length())
+            .fold(0.0, f32::max)
+    }
+}
+
+impl Collider for Compound {
+    fn primitive_type(&self) -> PrimitiveType {
+        PrimitiveType::Compound
+    }
+    
+    fn center(&self) -> Vec3 {
+        self.pos
+    }
+    
+    fn material(&self) -> &Material {
+        &self.material
+    }
+    
+    fn support(&self, direction: Vec3) -> Vec3 {
+        let direction_glam = glam::Vec3::from(direction);
+        self.child_shapes()
+            .iter()
+            .map(|shape| shape.primitive().as_collider().support(direction))
+            .max_by(|a, b| direction_glam.dot((*a).into()).total_cmp(&direction_glam.dot((*b).into())))
+            .unwrap_or(self.pos)
+    }
+    
+    fn bounding_radius(&self) -> f32 {
+        self.child_shapes()
+            .iter()
+            .map(|shape| {
+                let collider = shape.primitive();
+                let collider = collider.as_collider();
+                (glam::Vec3::from(collider.center()) - glam::Vec3::from(self.pos)).length() + collider.bounding_radius()
+            })
+            .fold(0.0, f32::max)
+    }
+}
+
+impl Collider for TriangleMesh {
+    fn primitive_type(&self) -> PrimitiveType {
+        PrimitiveType::Mesh
+    }
+    
+    fn center(&self) -> Vec3 {
+        let bounds = self.bounds();
+        (bounds.min + bounds.max) * 0.5
+    }
+    
+    fn material(&self) -> &Material {
+        &self.material
+    }
+    
+    fn support(&self, direction: Vec3) -> Vec3 {
+        let direction = glam::Vec3::from(direction);
+        self.vertices()
+            .into_iter()
+            .max_by(|a, b| direction.dot((*a).into()).total_cmp(&direction.dot((*b).into())))
+            .unwrap_or(Vec3::ZERO)
+    }
+    
+    fn bounding_radius(&self) -> f32 {
+        let bounds = self.bounds();
+        (bounds.max - bounds.min).length() * 0.5
+    }
+}
+
 /// Dynamic primitive wrapper for polymorphic collision detection
 /// Note: Using separate enums for immutable (detection) and mutable (response) phases
+#[derive(Clone, Copy)]
 pub enum Primitive<'a> {
     Sphere(&'a Sphere),
     Box(&'a BoxBody),
     Cylinder(&'a Cylinder),
+    Capsule(&'a Capsule),
+    Hull(&'a ConvexHull),
+    Compound(&'a Compound),
     Plane(&'a Plane),
+    Heightfield(&'a Heightfield),
+    Mesh(&'a TriangleMesh),
 }
 
 pub enum PrimitiveMut<'a> {
     Sphere(&'a mut Sphere),
     Box(&'a mut BoxBody),
     Cylinder(&'a mut Cylinder),
+    Capsule(&'a mut Capsule),
+    Hull(&'a mut ConvexHull),
+    Compound(&'a mut Compound),
     Plane(&'a mut Plane),
+    Heightfield(&'a mut Heightfield),
+    Mesh(&'a mut TriangleMesh),
 }
 
 impl<'a> Primitive<'a> {
@@ -178,7 +344,12 @@ impl<'a> Primitive<'a> {
             Primitive::Sphere(s) => *s,
             Primitive::Box(b) => *b,
             Primitive::Cylinder(c) => *c,
+            Primitive::Capsule(c) => *c,
+            Primitive::Hull(h) => *h,
+            Primitive::Compound(c) => *c,
             Primitive::Plane(p) => *p,
+            Primitive::Heightfield(h) => *h,
+            Primitive::Mesh(m) => *m,
         }
     }
 }
\ No newline at end of file
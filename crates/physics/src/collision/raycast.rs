@@ -0,0 +1,178 @@
+//! Ray and sphere casts against single bodies
+//!
+//! Rays enter convex shapes where [`Convex::line_interval`] says the line
+//! does, and planes, heightfield triangles and mesh triangles where they
+//! cross the surface from its front. A ray that starts inside a solid hits
+//! it at once. Sphere casts sweep a sphere along the ray with the time of
+//! impact search of continuous collision detection, then take the normal
+//! from the narrow phase where the sphere stops.
+
+use glam::{Quat, Vec3};
+
+use crate::types::{BoundingBox, Sphere};
+use super::ccd::{time_of_impact, Shape, Sweep};
+use super::compound::generate_manifolds;
+use super::convex::{slab, Convex};
+use super::manifold::BodyFrame;
+use super::{primitive_bounding_box, Primitive};
+
+/// Gap between a cast sphere and a surface that still counts as touching
+/// once the time of impact search has stopped.
+const CAST_TOLERANCE: f32 = 1e-2;
+
+/// Where a cast first touches a body.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct CastHit {
+    /// Distance travelled along the cast direction.
+    pub distance: f32,
+    /// Point on the surface of the body.
+    pub point: Vec3,
+    /// Outward unit normal of the body's surface at `point`.
+    pub normal: Vec3,
+}
+
+/// First hit of the ray from `origin` along the unit vector `direction`
+/// within `max_distance`, or `None` if it misses the body.
+pub(crate) fn cast_ray(primitive: &Primitive<'_>, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<CastHit> {
+    if primitive_bounding_box(primitive, 0.0).is_some_and(|bounds| !ray_reaches_box(&bounds, origin, direction, max_distance)) {
+        return None;
+    }
+    let hit = |distance: f32, normal: Vec3| CastHit {
+        distance,
+        point: origin + direction * distance,
+        normal,
+    };
+    let end = origin + direction * max_distance;
+    let (min, max) = (origin.min(end), origin.max(end));
+    match primitive {
+        Primitive::Plane(plane) => {
+            let normal = Vec3::from(plane.normal);
+            let gap = normal.dot(origin) + plane.d;
+            if gap <= 0.0 {
+                return Some(hit(0.0, -direction));
+            }
+            let rate = normal.dot(direction);
+            let distance = -gap / rate;
+            (rate < 0.0 && distance <= max_distance).then(|| hit(distance, normal))
+        }
+        Primitive::Heightfield(heightfield) => {
+            nearest_front_face(heightfield.triangles_in(min, max), origin, direction, max_distance)
+        }
+        Primitive::Mesh(mesh) => nearest_front_face(
+            mesh.triangles_in(min, max).map(|(_, corners)| corners),
+            origin,
+            direction,
+            max_distance,
+        ),
+        Primitive::Compound(compound) => compound
+            .child_shapes()
+            .iter()
+            .filter_map(|shape| cast_ray(&shape.primitive(), origin, direction, max_distance))
+            .min_by(|a, b| a.distance.total_cmp(&b.distance)),
+        _ => {
+            let convex = Convex::of(primitive)?;
+            let (enter, exit) = convex.line_interval(origin, direction)?;
+            if exit < 0.0 || enter > max_distance {
+                return None;
+            }
+            if enter <= 0.0 {
+                return Some(hit(0.0, -direction));
+            }
+            Some(hit(enter, convex.surface_normal(origin + direction * enter)))
+        }
+    }
+}
+
+/// First contact of a sphere of `radius` moved from `origin` along the unit
+/// vector `direction` for up to `max_distance` with the body, whose frame is
+/// `frame`. A sphere that starts overlapping the body touches it at once.
+pub(crate) fn cast_sphere(
+    primitive: &Primitive<'_>,
+    frame: BodyFrame,
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+    radius: f32,
+) -> Option<CastHit> {
+    if primitive_bounding_box(primitive, radius).is_some_and(|bounds| !ray_reaches_box(&bounds, origin, direction, max_distance)) {
+        return None;
+    }
+    let sphere_at = |center: Vec3| Sphere::new(center.into(), crate::types::Vec3::ZERO, radius);
+    // Normal from the sphere centred at `center` to the body, of its
+    // deepest contact point within `margin`
+    let contact_normal = |center: Vec3, margin: f32| {
+        generate_manifolds(&Primitive::Sphere(&sphere_at(center)), primitive, margin)
+            .into_iter()
+            .filter_map(|(_, manifold)| {
+                let depth = manifold.points.iter().map(|point| point.depth).reduce(f32::max)?;
+                Some((depth, manifold.normal))
+            })
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, normal)| normal)
+    };
+    if contact_normal(origin, 0.0).is_some() {
+        return Some(CastHit {
+            distance: 0.0,
+            point: origin,
+            normal: -direction,
+        });
+    }
+
+    let start = BodyFrame {
+        position: origin,
+        orientation: Quat::IDENTITY,
+    };
+    let sweep = Sweep {
+        start,
+        end: BodyFrame {
+            position: origin + direction * max_distance,
+            ..start
+        },
+    };
+    let still = Sweep { start: frame, end: frame };
+    let t = time_of_impact(&Shape::Sphere(sphere_at(origin)), &sweep, &Shape::of(primitive), &still, 0.0)?;
+    let center = sweep.at(t).position;
+    // The normal of the narrow phase points from the sphere to the body
+    let normal = -contact_normal(center, CAST_TOLERANCE)?;
+    Some(CastHit {
+        distance: max_distance * t,
+        point: center - normal * radius,
+        normal,
+    })
+}
+
+/// Whether the segment from `origin` along the unit vector `direction` for
+/// `max_distance` passes through `bounds`.
+fn ray_reaches_box(bounds: &BoundingBox, origin: Vec3, direction: Vec3, max_distance: f32) -> bool {
+    let (min, max) = (Vec3::from(bounds.min), Vec3::from(bounds.max));
+    let (center, half) = ((min + max) * 0.5, (max - min) * 0.5);
+    let (mut enter, mut exit) = (0.0_f32, max_distance);
+    for axis in 0..3 {
+        let Some((near, far)) = slab(origin[axis] - center[axis], direction[axis], half[axis]) else {
+            return false;
+        };
+        (enter, exit) = (enter.max(near), exit.min(far));
+    }
+    enter <= exit
+}
+
+/// Nearest crossing of the ray into the front of one of `triangles`.
+fn nearest_front_face(
+    triangles: impl Iterator<Item = [Vec3; 3]>,
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+) -> Option<CastHit> {
+    triangles
+        .filter_map(|corners| {
+            let triangle = Convex::Triangle(corners);
+            // Only a crossing from the front has a finite entry
+            let (enter, _) = triangle.line_interval(origin, direction)?;
+            (enter.is_finite() && (0.0..=max_distance).contains(&enter)).then(|| CastHit {
+                distance: enter,
+                point: origin + direction * enter,
+                normal: triangle.surface_normal(origin),
+            })
+        })
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
@@ -2,6 +2,7 @@
 
 use crate::types::{Vec3, Sphere, BoxBody};
 use super::Contact;
+use super::manifold::{body_rotation, ManifoldPoint, ManifoldPoints};
 
 /// Detect collision between a sphere and an axis-aligned box
 pub fn detect_sphere_box_collision(
@@ -113,4 +114,54 @@ pub fn resolve_sphere_box_collision(
     
     sphere.pos += correction / sphere.mass;
     box_body.pos -= correction / box_body.mass;
-}
\ No newline at end of file
+}
+
+/// Generate a single-point manifold between a sphere and an oriented box.
+pub(crate) fn sphere_box_manifold(
+    sphere: &Sphere,
+    box_body: &BoxBody,
+    margin: f32,
+) -> Option<ManifoldPoints> {
+    let rotation = body_rotation(box_body.orientation);
+    let box_center: glam::Vec3 = box_body.pos.into();
+    let half: glam::Vec3 = box_body.half_extents.into();
+    let center = rotation.inverse() * (glam::Vec3::from(sphere.pos) - box_center);
+
+    let closest = center.clamp(-half, half);
+    let delta = closest - center;
+    let distance = delta.length();
+
+    // Local normal from the sphere towards the box, plus the box surface point.
+    let (local_normal, surface, depth) = if distance > 0.0001 {
+        (delta / distance, closest, sphere.radius - distance)
+    } else {
+        // Centre inside the box: push out through the nearest face.
+        let gaps = half - center.abs();
+        let axis = if gaps.x <= gaps.y && gaps.x <= gaps.z {
+            0
+        } else if gaps.y <= gaps.z {
+            1
+        } else {
+            2
+        };
+        let mut face = glam::Vec3::ZERO;
+        face[axis] = if center[axis] >= 0.0 { 1.0 } else { -1.0 };
+        let mut surface = center;
+        surface[axis] = face[axis] * half[axis];
+        (-face, surface, sphere.radius + gaps[axis])
+    };
+    if depth < -margin {
+        return None;
+    }
+
+    let normal = rotation * local_normal;
+    let surface = box_center + rotation * surface;
+    Some(ManifoldPoints {
+        normal,
+        points: vec![ManifoldPoint {
+            position: surface - normal * (0.5 * depth),
+            depth,
+            feature_id: 0,
+        }],
+    })
+}
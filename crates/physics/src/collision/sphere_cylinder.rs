@@ -2,6 +2,7 @@
 
 use crate::types::{Vec3, Sphere, Cylinder};
 use super::Contact;
+use super::manifold::{body_rotation, ManifoldPoint, ManifoldPoints};
 
 /// Detect collision between a sphere and a cylinder
 /// Note: This assumes the cylinder is axis-aligned along Y axis
@@ -119,4 +120,65 @@ pub fn resolve_sphere_cylinder_collision(
     
     sphere.pos += correction / sphere.mass;
     cylinder.pos -= correction / cylinder.mass;
-}
\ No newline at end of file
+}
+
+/// Generate a single-point manifold between a sphere and an oriented
+/// cylinder whose axis is its local Y axis.
+pub(crate) fn sphere_cylinder_manifold(
+    sphere: &Sphere,
+    cylinder: &Cylinder,
+    margin: f32,
+) -> Option<ManifoldPoints> {
+    let rotation = body_rotation(cylinder.orientation);
+    let shape_center = glam::Vec3::from(cylinder.pos) + rotation * glam::Vec3::from(cylinder.shape_offset);
+    let center = rotation.inverse() * (glam::Vec3::from(sphere.pos) - shape_center);
+
+    let radial = glam::Vec3::new(center.x, 0.0, center.z);
+    let radial_distance = radial.length();
+    let radial_dir = if radial_distance > 0.0001 {
+        radial / radial_distance
+    } else {
+        glam::Vec3::X
+    };
+
+    let inside_radius = radial_distance <= cylinder.radius;
+    let inside_height = center.y.abs() <= cylinder.half_height;
+
+    let (local_normal, surface, depth) = if inside_radius && inside_height {
+        // Centre inside: leave through the closest of the side or a cap.
+        let side_gap = cylinder.radius - radial_distance;
+        let cap_gap = cylinder.half_height - center.y.abs();
+        if side_gap < cap_gap {
+            let surface = radial_dir * cylinder.radius + glam::Vec3::Y * center.y;
+            (-radial_dir, surface, sphere.radius + side_gap)
+        } else {
+            let sign = if center.y >= 0.0 { 1.0 } else { -1.0 };
+            let surface = glam::Vec3::new(center.x, sign * cylinder.half_height, center.z);
+            (glam::Vec3::Y * -sign, surface, sphere.radius + cap_gap)
+        }
+    } else {
+        let clamped_radial = radial_dir * radial_distance.min(cylinder.radius);
+        let surface = clamped_radial
+            + glam::Vec3::Y * center.y.clamp(-cylinder.half_height, cylinder.half_height);
+        let delta = surface - center;
+        let distance = delta.length();
+        if distance <= 0.0001 {
+            return None;
+        }
+        (delta / distance, surface, sphere.radius - distance)
+    };
+    if depth < -margin {
+        return None;
+    }
+
+    let normal = rotation * local_normal;
+    let surface = shape_center + rotation * surface;
+    Some(ManifoldPoints {
+        normal,
+        points: vec![ManifoldPoint {
+            position: surface - normal * (0.5 * depth),
+            depth,
+            feature_id: 0,
+        }],
+    })
+}
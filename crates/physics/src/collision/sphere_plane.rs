@@ -2,6 +2,7 @@
 
 use crate::types::{Sphere, Plane};
 use super::Contact;
+use super::manifold::{ManifoldPoint, ManifoldPoints};
 
 /// Detect collision between a sphere and a plane
 pub fn detect_sphere_plane_collision(
@@ -77,4 +78,30 @@ fn apply_friction(sphere: &mut Sphere, contact: &Contact, normal_impulse: f32) {
         
         sphere.vel -= friction_impulse;
     }
-}
\ No newline at end of file
+}
+
+/// Generate a single-point manifold between a sphere and a plane.
+///
+/// The sphere is body A, so the normal points into the plane.
+pub(crate) fn sphere_plane_manifold(
+    sphere: &Sphere,
+    plane: &Plane,
+    margin: f32,
+) -> Option<ManifoldPoints> {
+    let center: glam::Vec3 = sphere.pos.into();
+    let plane_normal: glam::Vec3 = plane.normal.into();
+    let distance = plane_normal.dot(center) + plane.d;
+    let depth = sphere.radius - distance;
+    if depth < -margin {
+        return None;
+    }
+
+    Some(ManifoldPoints {
+        normal: -plane_normal,
+        points: vec![ManifoldPoint {
+            position: center - plane_normal * (0.5 * (sphere.radius + distance)),
+            depth,
+            feature_id: 0,
+        }],
+    })
+}
@@ -2,6 +2,7 @@
 
 use crate::types::{Vec3, Sphere};
 use super::Contact;
+use super::manifold::{ManifoldPoint, ManifoldPoints};
 
 /// Detect collision between two spheres
 pub fn detect_sphere_sphere_collision(
@@ -72,4 +73,35 @@ pub fn resolve_sphere_sphere_collision(
     
     sphere_a.pos -= correction / sphere_a.mass;
     sphere_b.pos += correction / sphere_b.mass;
-}
\ No newline at end of file
+}
+
+/// Generate a single-point manifold between two spheres.
+pub(crate) fn sphere_sphere_manifold(
+    sphere_a: &Sphere,
+    sphere_b: &Sphere,
+    margin: f32,
+) -> Option<ManifoldPoints> {
+    let center_a: glam::Vec3 = sphere_a.pos.into();
+    let center_b: glam::Vec3 = sphere_b.pos.into();
+    let delta = center_b - center_a;
+    let distance = delta.length();
+    let depth = sphere_a.radius + sphere_b.radius - distance;
+    if depth < -margin {
+        return None;
+    }
+
+    let normal = if distance > 0.0001 {
+        delta / distance
+    } else {
+        glam::Vec3::Y
+    };
+
+    Some(ManifoldPoints {
+        normal,
+        points: vec![ManifoldPoint {
+            position: center_a + normal * (sphere_a.radius - 0.5 * depth),
+            depth,
+            feature_id: 0,
+        }],
+    })
+}
@@ -0,0 +1,104 @@
+//! Incremental sweep and prune
+//!
+//! Bounding boxes are kept sorted by their lower bound along one axis. The
+//! order is carried over between steps, so bodies that moved a little only
+//! need a few swaps of insertion sort to sort again. A sweep along the
+//! sorted list then only tests boxes whose intervals overlap on that axis.
+//! Nothing limits where bodies may be, so worlds are unbounded.
+
+use glam::Vec3;
+
+use crate::body::BodyHandle;
+use crate::types::BoundingBox;
+use serde::{Deserialize, Serialize};
+
+/// Persistent sort order of the sweep-and-prune broad phase.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct SweepAndPrune {
+    /// Bodies sorted by the lower bound of their box along `axis`.
+    order: Vec<BodyHandle>,
+    /// Axis the bodies are sorted along.
+    axis: usize,
+}
+
+impl SweepAndPrune {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Re-sort the bodies and return every pair whose boxes overlap, with
+    /// the lower handle first. `proxies` must be sorted by handle.
+    pub fn update(&mut self, proxies: &[(BodyHandle, BoundingBox)]) -> Vec<(BodyHandle, BodyHandle)> {
+        let bounds: Vec<(Vec3, Vec3)> = proxies
+            .iter()
+            .map(|(_, bounds)| (Vec3::from(bounds.min), Vec3::from(bounds.max)))
+            .collect();
+        let index_of = |handle: BodyHandle| proxies.binary_search_by_key(&handle, |&(handle, _)| handle).ok();
+
+        // Forget removed bodies and append new ones at the end
+        let mut known = vec![false; proxies.len()];
+        self.order.retain(|&handle| match index_of(handle) {
+            Some(index) => {
+                known[index] = true;
+                true
+            }
+            None => false,
+        });
+        self.order.extend(proxies.iter().zip(&known).filter(|(_, &known)| !known).map(|(&(handle, _), _)| handle));
+
+        self.axis = spread_axis(&bounds);
+        let axis = self.axis;
+        let mut sorted: Vec<usize> = self.order.iter().filter_map(|&handle| index_of(handle)).collect();
+        insertion_sort_by_key(&mut sorted, |index| bounds[index].0[axis]);
+        self.order = sorted.iter().map(|&index| proxies[index].0).collect();
+
+        let mut pairs = Vec::new();
+        let mut active: Vec<usize> = Vec::new();
+        for &index in &sorted {
+            let (min, max) = bounds[index];
+            active.retain(|&other| bounds[other].1[axis] >= min[axis]);
+            for &other in &active {
+                let (other_min, other_max) = bounds[other];
+                if min.cmple(other_max).all() && other_min.cmple(max).all() {
+                    pairs.push((proxies[index.min(other)].0, proxies[index.max(other)].0));
+                }
+            }
+            active.push(index);
+        }
+        pairs
+    }
+}
+
+/// Axis along which the box centres are most spread out, which prunes the
+/// most pairs.
+fn spread_axis(bounds: &[(Vec3, Vec3)]) -> usize {
+    if bounds.is_empty() {
+        return 0;
+    }
+    let centers = bounds.iter().map(|&(min, max)| (min + max) * 0.5);
+    let (sum, sum_squares) = centers.fold((Vec3::ZERO, Vec3::ZERO), |(sum, squares), center| {
+        (sum + center, squares + center * center)
+    });
+    #[allow(clippy::cast_precision_loss)]
+    let count = bounds.len() as f32;
+    let mean = sum / count;
+    let variance = sum_squares / count - mean * mean;
+    if variance.x >= variance.y && variance.x >= variance.z {
+        0
+    } else if variance.y >= variance.z {
+        1
+    } else {
+        2
+    }
+}
+
+/// Insertion sort, which is linear for lists that are almost sorted.
+fn insertion_sort_by_key(items: &mut [usize], key: impl Fn(usize) -> f32) {
+    for i in 1..items.len() {
+        let mut j = i;
+        while j > 0 && key(items[j - 1]) > key(items[j]) {
+            items.swap(j - 1, j);
+            j -= 1;
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! # Compound Bodies
+//!
+//! A [`Compound`] is a single rigid body built from several child shapes,
+//! each fixed at an offset and orientation in the body frame. Mass and
+//! inertia are summed over the children, every child collides on its own,
+//! and the solver moves the whole body as one unit.
+
+use std::sync::Arc;
+
+use glam::{Mat3, Quat};
+
+use crate::body::{box_inverse_inertia, capsule_inverse_inertia, cylinder_inverse_inertia, sphere_inverse_inertia};
+use crate::collision::{body_rotation, Shape};
+use crate::mesh::{ConvexHull, HullGeometry, MassProperties};
+use crate::simulation::{calculate_box_mass, calculate_capsule_mass, calculate_cylinder_mass, calculate_sphere_mass};
+use crate::types::{BodyType, BoxBody, Capsule, Cylinder, Material, Sphere, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// Shape of one child of a [`Compound`], in the child's own frame.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ChildShape {
+    /// Sphere around the child origin.
+    Sphere { radius: f32 },
+    /// Box centred on the child origin.
+    Box { half_extents: Vec3 },
+    /// Cylinder along the child's Y axis.
+    Cylinder { radius: f32, half_height: f32 },
+    /// Capsule along the child's Y axis.
+    Capsule { radius: f32, half_height: f32 },
+    /// Convex hull, shared between copies of the body.
+    Hull(Arc<HullGeometry>),
+}
+
+/// A child shape placed in the frame of its [`Compound`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CompoundChild {
+    /// Shape of the child.
+    pub shape: ChildShape,
+    /// Position of the child origin in the body frame.
+    pub position: Vec3,
+    /// Orientation of the child in the body frame, represented as a
+    /// quaternion in `[x, y, z, w]` format.
+    pub orientation: [f32; 4],
+}
+
+impl CompoundChild {
+    /// A child at `position` with no rotation.
+    #[must_use]
+    pub const fn new(shape: ChildShape, position: Vec3) -> Self {
+        Self {
+            shape,
+            position,
+            orientation: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+
+    /// The child rotated by `orientation`, given as `[x, y, z, w]`.
+    #[must_use]
+    pub const fn with_orientation(mut self, orientation: [f32; 4]) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Mass, centre of mass and inertia tensor of the child at uniform
+    /// `density`, in the frame the child is placed in.
+    fn mass_properties(&self, density: f32) -> MassProperties {
+        let rotation = Mat3::from_quat(body_rotation(self.orientation));
+        let position = glam::Vec3::from(self.position);
+        let diagonal = |mass: f32, inverse_inertia: Vec3| {
+            (mass, glam::Vec3::ZERO, Mat3::from_diagonal(glam::Vec3::ONE / glam::Vec3::from(inverse_inertia)))
+        };
+        let (mass, center, inertia) = match &self.shape {
+            ChildShape::Sphere { radius } => {
+                let mass = calculate_sphere_mass(*radius, density);
+                diagonal(mass, sphere_inverse_inertia(mass, *radius))
+            }
+            ChildShape::Box { half_extents } => {
+                let mass = calculate_box_mass(*half_extents, density);
+                diagonal(mass, box_inverse_inertia(mass, *half_extents))
+            }
+            ChildShape::Cylinder { radius, half_height } => {
+                let mass = calculate_cylinder_mass(*radius, 2.0 * half_height, density);
+                diagonal(mass, cylinder_inverse_inertia(mass, *radius, *half_height))
+            }
+            ChildShape::Capsule { radius, half_height } => {
+                let mass = calculate_capsule_mass(*radius, *half_height, density);
+                diagonal(mass, capsule_inverse_inertia(mass, *radius, *half_height))
+            }
+            ChildShape::Hull(geometry) => {
+                let properties = geometry.mass_properties(density);
+                (
+                    properties.mass,
+                    properties.center_of_mass.into(),
+                    Mat3::from_cols_array_2d(&properties.inertia),
+                )
+            }
+        };
+        MassProperties {
+            mass,
+            center_of_mass: (position + rotation * center).into(),
+            inertia: (rotation * inertia * rotation.transpose()).to_cols_array_2d(),
+        }
+    }
+}
+
+/// A rigid body made of several child shapes.
+///
+/// As with [`ConvexHull`], the body frame is the principal frame of the
+/// whole body: `pos` is its centre of mass and the children are stored in a
+/// frame where its inertia tensor is diagonal.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Compound {
+    /// The world-space position of the centre of mass.
+    pub pos: Vec3,
+    /// The linear velocity of the body.
+    pub vel: Vec3,
+    /// The orientation of the principal frame, represented as a quaternion
+    /// in `[x, y, z, w]` format.
+    pub orientation: [f32; 4],
+    /// The angular velocity of the body, measured in radians per second.
+    pub angular_vel: Vec3,
+    /// Mass of the body in kilograms.
+    pub mass: f32,
+    /// Principal moments of inertia, about the axes of the body frame.
+    pub inertia: Vec3,
+    /// Material properties for collision response, shared by every child.
+    pub material: Material,
+    /// Body type (Dynamic, Kinematic, Static)
+    pub body_type: BodyType,
+    /// Children in the body frame.
+    pub children: Vec<CompoundChild>,
+}
+
+impl Compound {
+    /// Creates a dynamic body from `children` with uniform `density`, with
+    /// the origin the children are placed around at `position`.
+    ///
+    /// The body starts in the world orientation of that origin; its
+    /// `orientation` is that of its principal axes.
+    #[must_use]
+    pub fn new(children: Vec<CompoundChild>, position: Vec3, density: f32) -> Self {
+        let properties = combined_mass_properties(&children, density);
+        let (moments, rotation) = properties.principal_axes();
+        let rotation = Quat::from_array(rotation);
+        let center = glam::Vec3::from(properties.center_of_mass);
+        let to_body = rotation.inverse();
+        let children = children
+            .into_iter()
+            .map(|child| CompoundChild {
+                position: (to_body * (glam::Vec3::from(child.position) - center)).into(),
+                orientation: (to_body * body_rotation(child.orientation)).normalize().to_array(),
+                shape: child.shape,
+            })
+            .collect();
+        Self {
+            pos: (glam::Vec3::from(position) + center).into(),
+            vel: Vec3::ZERO,
+            orientation: rotation.to_array(),
+            angular_vel: Vec3::ZERO,
+            mass: properties.mass,
+            inertia: moments,
+            material: Material::default(),
+            body_type: BodyType::Dynamic,
+            children,
+        }
+    }
+
+    /// Every child as a standalone shape at its current place in the world.
+    /// The shapes carry the body's material but no velocity or mass.
+    pub(crate) fn child_shapes(&self) -> Vec<Shape<'static>> {
+        let rotation = body_rotation(self.orientation);
+        self.children
+            .iter()
+            .map(|child| {
+                let pos: Vec3 = (glam::Vec3::from(self.pos) + rotation * glam::Vec3::from(child.position)).into();
+                let orientation = (rotation * body_rotation(child.orientation)).to_array();
+                let (vel, angular_vel, mass, material, body_type) =
+                    (Vec3::ZERO, Vec3::ZERO, 0.0, self.material, self.body_type);
+                match &child.shape {
+                    ChildShape::Sphere { radius } => {
+                        let mut sphere = Sphere::with_material(pos, vel, *radius, material);
+                        (sphere.mass, sphere.orientation) = (mass, orientation);
+                        Shape::Sphere(sphere)
+                    }
+                    ChildShape::Box { half_extents } => Shape::Box(BoxBody {
+                        pos,
+                        half_extents: *half_extents,
+                        vel,
+                        mass,
+                        orientation,
+                        angular_vel,
+                        material,
+                        body_type,
+                    }),
+                    ChildShape::Cylinder { radius, half_height } => Shape::Cylinder(Cylinder {
+                        pos,
+                        vel,
+                        radius: *radius,
+                        half_height: *half_height,
+                        mass,
+                        orientation,
+                        angular_vel,
+                        material,
+                        body_type,
+                        shape_offset: Vec3::ZERO,
+                        mesh_offset: Vec3::ZERO,
+                    }),
+                    ChildShape::Capsule { radius, half_height } => Shape::Capsule(Capsule {
+                        pos,
+                        vel,
+                        radius: *radius,
+                        half_height: *half_height,
+                        mass,
+                        orientation,
+                        angular_vel,
+                        material,
+                        body_type,
+                    }),
+                    ChildShape::Hull(geometry) => Shape::Hull(ConvexHull {
+                        pos,
+                        vel,
+                        orientation,
+                        angular_vel,
+                        mass,
+                        inertia: Vec3::ZERO,
+                        material,
+                        body_type,
+                        geometry: Arc::clone(geometry),
+                    }),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Mass properties of all `children` together, about their common centre
+/// of mass, in the frame they are placed in.
+fn combined_mass_properties(children: &[CompoundChild], density: f32) -> MassProperties {
+    let parts: Vec<MassProperties> = children.iter().map(|child| child.mass_properties(density)).collect();
+    let mass: f32 = parts.iter().map(|part| part.mass).sum();
+    if mass <= 0.0 {
+        return MassProperties {
+            mass: 0.0,
+            center_of_mass: Vec3::ZERO,
+            inertia: [[0.0; 3]; 3],
+        };
+    }
+    let center = parts
+        .iter()
+        .map(|part| glam::Vec3::from(part.center_of_mass) * part.mass)
+        .sum::<glam::Vec3>()
+        / mass;
+    // Parallel axis theorem: m (|d|² 1 - d dᵀ) about the common centre
+    let inertia = parts.iter().fold(Mat3::ZERO, |sum, part| {
+        let offset = glam::Vec3::from(part.center_of_mass) - center;
+        let shift = Mat3::from_diagonal(glam::Vec3::splat(offset.length_squared()))
+            - Mat3::from_cols(offset * offset.x, offset * offset.y, offset * offset.z);
+        sum + Mat3::from_cols_array_2d(&part.inertia) + shift * part.mass
+    });
+    MassProperties {
+        mass,
+        center_of_mass: center.into(),
+        inertia: inertia.to_cols_array_2d(),
+    }
+}
@@ -0,0 +1,140 @@
+//! # Contact Reporting
+//!
+//! After every CPU step [`crate::simulation::PhysicsSim`] records which
+//! bodies touched, as a list of [`ContactDebugInfo`] points with the impulse
+//! each one applied, as [`ContactEvent`]s for pairs that started, kept or
+//! stopped touching, and as the total contact force on every body.
+//!
+//! A pair touches when one of its contact points overlaps or pushed the
+//! bodies apart during the step. Speculative points, which only keep bodies
+//! from closing in within the contact margin, do not count until they do.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::body::BodyHandle;
+use crate::collision::{ContactManifold, ContactPoint};
+use crate::solver::tangent_basis;
+use crate::types::{ContactDebugInfo, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// How the contact between two bodies changed during a step.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ContactEventKind {
+    /// The bodies touched for the first time.
+    Begin,
+    /// The bodies already touched at the end of the previous step.
+    Persist,
+    /// The bodies touched at the end of the previous step but no longer do.
+    End,
+}
+
+/// A change in contact between two bodies, reported once per body pair
+/// whatever the number of contact points or compound children involved.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ContactEvent {
+    /// What happened to the contact.
+    pub kind: ContactEventKind,
+    /// First body of the pair (always the smaller handle).
+    pub body_a: BodyHandle,
+    /// Second body of the pair.
+    pub body_b: BodyHandle,
+}
+
+impl ContactEvent {
+    /// The other body of the pair if `body` is one of the two, for example
+    /// to find what a foot landed on.
+    #[must_use]
+    pub fn other(&self, body: BodyHandle) -> Option<BodyHandle> {
+        if body == self.body_a {
+            Some(self.body_b)
+        } else if body == self.body_b {
+            Some(self.body_a)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether a contact point overlaps or carried an impulse.
+fn touches(point: &ContactPoint) -> bool {
+    point.depth >= 0.0 || point.normal_impulse > 0.0
+}
+
+/// Impulse a contact point applied to body B of its manifold.
+fn point_impulse(manifold: &ContactManifold, point: &ContactPoint) -> glam::Vec3 {
+    let normal = glam::Vec3::from(manifold.normal);
+    let tangents = tangent_basis(normal);
+    normal * point.normal_impulse + tangents[0] * point.tangent_impulse[0] + tangents[1] * point.tangent_impulse[1]
+}
+
+/// Body pairs with at least one touching point.
+pub(crate) fn touching_pairs<'a>(
+    manifolds: impl Iterator<Item = &'a ContactManifold>,
+) -> BTreeSet<(BodyHandle, BodyHandle)> {
+    manifolds
+        .filter(|manifold| manifold.points.iter().any(touches))
+        .map(|manifold| (manifold.body_a, manifold.body_b))
+        .collect()
+}
+
+/// Events for the change from the `previous` touching pairs to `current`,
+/// in pair order.
+pub(crate) fn contact_events(
+    previous: &BTreeSet<(BodyHandle, BodyHandle)>,
+    current: &BTreeSet<(BodyHandle, BodyHandle)>,
+) -> Vec<ContactEvent> {
+    let mut events: Vec<ContactEvent> = current
+        .iter()
+        .map(|&(body_a, body_b)| ContactEvent {
+            kind: if previous.contains(&(body_a, body_b)) {
+                ContactEventKind::Persist
+            } else {
+                ContactEventKind::Begin
+            },
+            body_a,
+            body_b,
+        })
+        .chain(previous.difference(current).map(|&(body_a, body_b)| ContactEvent {
+            kind: ContactEventKind::End,
+            body_a,
+            body_b,
+        }))
+        .collect();
+    events.sort_by_key(|event| (event.body_a, event.body_b));
+    events
+}
+
+/// Every touching contact point of `manifolds`.
+pub(crate) fn contact_reports<'a>(manifolds: impl Iterator<Item = &'a ContactManifold>) -> Vec<ContactDebugInfo> {
+    manifolds
+        .flat_map(|manifold| {
+            manifold.points.iter().filter(|point| touches(point)).map(move |point| ContactDebugInfo {
+                position: point.position,
+                normal: manifold.normal,
+                depth: point.depth,
+                bodies: (manifold.body_a, manifold.body_b),
+                children: (manifold.child_a, manifold.child_b),
+                impulse: point_impulse(manifold, point).into(),
+            })
+        })
+        .collect()
+}
+
+/// Total contact force on every touching body over a step of `timestep`.
+pub(crate) fn contact_forces<'a>(
+    manifolds: impl Iterator<Item = &'a ContactManifold>,
+    timestep: f32,
+) -> BTreeMap<BodyHandle, Vec3> {
+    let mut forces = BTreeMap::new();
+    if timestep <= 0.0 {
+        return forces;
+    }
+    for manifold in manifolds {
+        for point in manifold.points.iter().filter(|point| touches(point)) {
+            let force = point_impulse(manifold, point) / timestep;
+            *forces.entry(manifold.body_a).or_insert(Vec3::ZERO) -= force.into();
+            *forces.entry(manifold.body_b).or_insert(Vec3::ZERO) += force.into();
+        }
+    }
+    forces
+}
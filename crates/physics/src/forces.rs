@@ -0,0 +1,148 @@
+//! # Applied Forces
+//!
+//! Forces, torques and impulses on any dynamic body, in world space.
+//!
+//! [`PhysicsSim::apply_force`], [`PhysicsSim::apply_force_at_point`] and
+//! [`PhysicsSim::apply_torque`] add to a per-body accumulator that the next
+//! CPU step integrates alongside gravity. With the default
+//! [`ForceMode::ClearEachStep`] the accumulators are emptied after every
+//! step, so a controller applies its forces again before each one; with
+//! [`ForceMode::Persistent`] they keep acting until
+//! [`PhysicsSim::clear_forces`] is called. Impulses change the velocity
+//! straight away.
+//!
+//! Static and kinematic bodies ignore all of these. The older
+//! [`PhysicsSim::set_force`] and [`crate::types::PhysParams::forces`] still
+//! push spheres and boxes in the horizontal plane, and remain the only
+//! forces the GPU step sees.
+
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::body::BodyHandle;
+use crate::simulation::PhysicsSim;
+use crate::solver::SolverBody;
+use crate::types;
+
+/// How long forces and torques added with [`PhysicsSim::apply_force`] and
+/// [`PhysicsSim::apply_torque`] keep acting.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ForceMode {
+    /// Forces act for the next step only.
+    #[default]
+    ClearEachStep,
+    /// Forces act every step until [`PhysicsSim::clear_forces`].
+    Persistent,
+}
+
+/// Force and torque accumulated on one body, in world space about its
+/// center of mass.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct AppliedLoad {
+    pub force: Vec3,
+    pub torque: Vec3,
+}
+
+impl AppliedLoad {
+    pub fn is_zero(&self) -> bool {
+        self.force == Vec3::ZERO && self.torque == Vec3::ZERO
+    }
+}
+
+impl PhysicsSim {
+    /// Push the body behind `handle` through its center of mass with
+    /// `force` during the next step.
+    pub fn apply_force(&mut self, handle: BodyHandle, force: types::Vec3) {
+        if let Some(load) = self.load_mut(handle) {
+            load.force += Vec3::from(force);
+        }
+    }
+
+    /// Push the body behind `handle` at the world-space `point` with
+    /// `force` during the next step. Off-center forces also turn the body.
+    pub fn apply_force_at_point(&mut self, handle: BodyHandle, force: types::Vec3, point: types::Vec3) {
+        if !self.takes_loads(handle) {
+            return;
+        }
+        let offset = Vec3::from(point) - self.body_frame(handle).position;
+        let load = self.applied_loads.entry(handle).or_default();
+        load.force += Vec3::from(force);
+        load.torque += offset.cross(force.into());
+    }
+
+    /// Turn the body behind `handle` with the world-space `torque` during
+    /// the next step.
+    pub fn apply_torque(&mut self, handle: BodyHandle, torque: types::Vec3) {
+        if let Some(load) = self.load_mut(handle) {
+            load.torque += Vec3::from(torque);
+        }
+    }
+
+    /// Change the momentum of the body behind `handle` by `impulse` at its
+    /// center of mass, right away.
+    pub fn apply_impulse(&mut self, handle: BodyHandle, impulse: types::Vec3) {
+        if !self.has_body(handle) {
+            return;
+        }
+        let center = self.body_frame(handle).position;
+        self.apply_impulse_at_point(handle, impulse, center.into());
+    }
+
+    /// Change the momentum of the body behind `handle` by `impulse` at the
+    /// world-space `point`, right away.
+    pub fn apply_impulse_at_point(&mut self, handle: BodyHandle, impulse: types::Vec3, point: types::Vec3) {
+        if self.is_link(handle) {
+            self.apply_link_impulse(handle, impulse.into(), point.into());
+            return;
+        }
+        if !self.has_body(handle) || !self.is_dynamic(handle) {
+            return;
+        }
+        let mut body = SolverBody::of(self, handle);
+        body.apply_impulse(impulse.into(), Vec3::from(point) - body.position);
+        self.set_body_velocity(handle, body.linear_velocity.into(), body.angular_velocity.into());
+    }
+
+    /// Force and torque waiting to act on the body behind `handle`.
+    #[must_use]
+    pub fn applied_force(&self, handle: BodyHandle) -> (types::Vec3, types::Vec3) {
+        self.applied_loads
+            .get(&handle)
+            .map_or((types::Vec3::ZERO, types::Vec3::ZERO), |load| (load.force.into(), load.torque.into()))
+    }
+
+    /// Remove every force and torque added with [`Self::apply_force`] and
+    /// its siblings.
+    pub fn clear_forces(&mut self) {
+        self.applied_loads.clear();
+    }
+
+    /// Whether the body behind `handle` exists and is a dynamic body or an
+    /// articulation link.
+    fn takes_loads(&self, handle: BodyHandle) -> bool {
+        self.has_body(handle) && (self.is_dynamic(handle) || self.is_link(handle))
+    }
+
+    /// Accumulator of the body behind `handle`, if it takes loads.
+    fn load_mut(&mut self, handle: BodyHandle) -> Option<&mut AppliedLoad> {
+        self.takes_loads(handle).then(|| self.applied_loads.entry(handle).or_default())
+    }
+
+    /// Add the velocity change of the accumulated forces over `dt`.
+    pub(crate) fn apply_loads(&mut self, dt: f32) {
+        let loads: Vec<(BodyHandle, AppliedLoad)> = self.applied_loads.iter().map(|(&handle, &load)| (handle, load)).collect();
+        for (handle, load) in loads {
+            let mut body = SolverBody::of(self, handle);
+            body.apply_impulse(load.force * dt, Vec3::ZERO);
+            body.apply_angular_impulse(load.torque * dt);
+            self.set_body_velocity(handle, body.linear_velocity.into(), body.angular_velocity.into());
+        }
+    }
+
+    /// Empty the accumulators at the end of a step, unless forces persist.
+    pub(crate) fn finish_loads(&mut self) {
+        if self.force_mode == ForceMode::ClearEachStep {
+            self.applied_loads.clear();
+        }
+    }
+}
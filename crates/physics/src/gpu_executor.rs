@@ -1,43 +1,93 @@
 //! # GPU Execution Pipeline
-//! 
+//!
 //! This module manages the GPU compute pipeline for physics simulation,
 //! including buffer management, kernel dispatch, and data synchronization.
+//!
+//! The spheres of several worlds can be stepped together: their bodies are
+//! concatenated into one buffer, so each kernel is dispatched once for all of
+//! them. Worlds stepped together share the gravity, timestep and contact
+//! model of the first one.
 
-use crate::PhysicsSim;
-use compute::{BufferView, ComputeError, Kernel};
+use crate::types::{ContactModel, SoftContact, Sphere};
+use crate::{BodyHandle, PhysicsSim};
+use compute::{BufferView, ComputeBackend, ComputeError, Kernel};
 use std::sync::Arc;
 
 /// Execute one physics step on the GPU
 pub fn execute_gpu_step(sim: &mut PhysicsSim) -> Result<(), ComputeError> {
+    let backend = Arc::clone(&sim.backend);
+    step_spheres_gpu(backend.as_ref(), &mut [sim])
+}
+
+/// Execute one physics step on the GPU for every world in `worlds`, with one
+/// dispatch per kernel.
+pub(crate) fn step_spheres_gpu(backend: &dyn ComputeBackend, worlds: &mut [&mut PhysicsSim]) -> Result<(), ComputeError> {
+    let Some(first) = worlds.first() else {
+        return Ok(());
+    };
+    let contact_model = first.contact_model;
+
     // For now, just integrate bodies as a simple example
-    if !sim.spheres.is_empty() {
-        integrate_spheres_gpu(sim)?;
+    if let ContactModel::Soft(model) = contact_model {
+        solve_soft_sphere_plane_contacts_gpu(backend, worlds, model)?;
     }
-    
+    if worlds.iter().any(|sim| live_spheres(sim).next().is_some()) {
+        integrate_spheres_gpu(backend, worlds)?;
+    }
+
     // TODO: Implement other GPU kernels when shaders are ready
-    
+
     Ok(())
 }
 
-/// Integrate sphere positions on GPU
-fn integrate_spheres_gpu(sim: &mut PhysicsSim) -> Result<(), ComputeError> {
-    let backend = &sim.backend;
-    
-    // Define GPU-compatible sphere struct that matches kernel expectations
-    #[repr(C)]
-    #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-    struct GpuSphere {
-        pos: [f32; 3],
-        _pad1: f32,
-        vel: [f32; 3],
-        _pad2: f32,
-        orientation: [f32; 4],
-        angular_vel: [f32; 3],
-        _pad3: f32,
-    }
-    
-    // Convert our spheres to GPU format
-    let gpu_spheres: Vec<GpuSphere> = sim.spheres.iter().map(|s| {
+/// GPU-compatible sphere struct that matches kernel expectations
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuSphere {
+    pos: [f32; 3],
+    _pad1: f32,
+    vel: [f32; 3],
+    _pad2: f32,
+    orientation: [f32; 4],
+    angular_vel: [f32; 3],
+    _pad3: f32,
+}
+
+/// Sphere-plane contact in the layout of [`Kernel::SolveContactsSoft`].
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuContact {
+    body_index: u32,
+    normal: [f32; 3],
+    depth: f32,
+    inv_mass: f32,
+    friction: f32,
+    _pad: f32,
+}
+
+/// Spheres of `sim` that were not removed, with their indices. Only these
+/// go into the GPU buffers.
+fn live_spheres(sim: &PhysicsSim) -> impl Iterator<Item = (usize, &Sphere)> {
+    sim.spheres
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| !sim.is_removed(BodyHandle::Sphere(i)))
+}
+
+/// Mutable spheres of `sim` that were not removed, in the order of
+/// [`live_spheres`].
+fn live_spheres_mut(sim: &mut PhysicsSim) -> impl Iterator<Item = &mut Sphere> {
+    let removed = &sim.removed_bodies;
+    sim.spheres
+        .iter_mut()
+        .enumerate()
+        .filter(|(i, _)| !removed.contains(&BodyHandle::Sphere(*i)))
+        .map(|(_, sphere)| sphere)
+}
+
+/// Spheres of every world, one after another.
+fn gather_spheres(worlds: &[&mut PhysicsSim]) -> Vec<GpuSphere> {
+    worlds.iter().flat_map(|sim| live_spheres(sim)).map(|(_, s)| {
         GpuSphere {
             pos: [s.pos.x, s.pos.y, s.pos.z],
             _pad1: 0.0,
@@ -47,15 +97,135 @@ fn integrate_spheres_gpu(sim: &mut PhysicsSim) -> Result<(), ComputeError> {
             angular_vel: [s.angular_vel.x, s.angular_vel.y, s.angular_vel.z],
             _pad3: 0.0,
         }
-    }).collect();
-    
+    }).collect()
+}
+
+/// Forces on the spheres of every world, one after another.
+fn gather_forces(worlds: &[&mut PhysicsSim]) -> Vec<[f32; 2]> {
+    worlds.iter().flat_map(|sim| {
+        live_spheres(sim).map(|(i, _)| sim.params.forces.get(i).copied().unwrap_or([0.0, 0.0]))
+    }).collect()
+}
+
+/// Sphere-plane contacts within the contact margin, with the same depth and
+/// combined friction as the CPU manifolds. Body indices start at `offset`.
+fn sphere_plane_contacts(sim: &PhysicsSim, offset: usize, contacts: &mut Vec<GpuContact>) {
+    let margin = sim.contact_params.contact_margin;
+    for (slot, (i, sphere)) in live_spheres(sim).enumerate() {
+        let Ok(body_index) = u32::try_from(offset + slot) else {
+            break;
+        };
+        let (inv_mass, _) = sim.inverse_mass(BodyHandle::Sphere(i));
+        for (p, plane) in sim.planes.iter().enumerate() {
+            if sim.is_removed(BodyHandle::Plane(p)) {
+                continue;
+            }
+            let depth = sphere.radius - (sphere.pos.dot(plane.normal) + plane.d);
+            if depth < -margin || !sim.can_collide(BodyHandle::Sphere(i), BodyHandle::Plane(p)) {
+                continue;
+            }
+            contacts.push(GpuContact {
+                body_index,
+                normal: [plane.normal.x, plane.normal.y, plane.normal.z],
+                depth,
+                inv_mass,
+                friction: (sphere.material.friction * plane.material.friction).sqrt(),
+                _pad: 0.0,
+            });
+        }
+    }
+}
+
+/// Apply soft contact impulses between spheres and planes on GPU, before
+/// integration.
+fn solve_soft_sphere_plane_contacts_gpu(
+    backend: &dyn ComputeBackend,
+    worlds: &mut [&mut PhysicsSim],
+    model: SoftContact,
+) -> Result<(), ComputeError> {
+    #[repr(C)]
+    #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+    struct GpuSoftParams {
+        stiffness: f32,
+        damping: f32,
+        smoothing: f32,
+        dt: f32,
+    }
+
+    let mut contacts = Vec::new();
+    let mut offset = 0;
+    for sim in worlds.iter() {
+        sphere_plane_contacts(sim, offset, &mut contacts);
+        offset += live_spheres(sim).count();
+    }
+    if contacts.is_empty() {
+        return Ok(());
+    }
+
+    // Contacts see the velocity that gravity and the applied forces are
+    // about to give, as on the CPU; only their own change is kept.
+    let dt = worlds[0].params.dt;
+    let gravity = worlds[0].params.gravity;
+    let mut gpu_spheres = gather_spheres(worlds);
+    for (sphere, f) in gpu_spheres.iter_mut().zip(gather_forces(worlds)) {
+        sphere.vel[0] += (gravity.x + f[0]) * dt;
+        sphere.vel[1] += (gravity.y + f[1]) * dt;
+        sphere.vel[2] += gravity.z * dt;
+    }
+    let params = GpuSoftParams {
+        stiffness: model.stiffness,
+        damping: model.damping,
+        smoothing: model.smoothing,
+        dt,
+    };
+
+    let results = backend.dispatch(
+        &Kernel::SolveContactsSoft,
+        &[
+            BufferView::new(
+                Arc::from(bytemuck::cast_slice(&gpu_spheres)),
+                vec![gpu_spheres.len()],
+                std::mem::size_of::<GpuSphere>(),
+            ),
+            BufferView::new(
+                Arc::from(bytemuck::cast_slice(&contacts)),
+                vec![contacts.len()],
+                std::mem::size_of::<GpuContact>(),
+            ),
+            BufferView::new(
+                Arc::from(bytemuck::bytes_of(&params)),
+                vec![1],
+                std::mem::size_of::<GpuSoftParams>(),
+            ),
+        ],
+        [1, 1, 1],
+    )?;
+
+    if let Some(result_bytes) = results.first() {
+        let new_gpu_spheres: &[GpuSphere] = bytemuck::cast_slice(result_bytes);
+        let spheres = worlds.iter_mut().flat_map(|sim| live_spheres_mut(sim));
+        for ((sphere, predicted), gpu_sphere) in spheres.zip(&gpu_spheres).zip(new_gpu_spheres) {
+            sphere.vel.x += gpu_sphere.vel[0] - predicted.vel[0];
+            sphere.vel.y += gpu_sphere.vel[1] - predicted.vel[1];
+            sphere.vel.z += gpu_sphere.vel[2] - predicted.vel[2];
+        }
+    }
+
+    Ok(())
+}
+
+/// Integrate sphere positions on GPU
+fn integrate_spheres_gpu(backend: &dyn ComputeBackend, worlds: &mut [&mut PhysicsSim]) -> Result<(), ComputeError> {
+    // Convert our spheres to GPU format
+    let gpu_spheres = gather_spheres(worlds);
+
     let sphere_bytes = bytemuck::cast_slice(&gpu_spheres);
     let spheres_buffer = BufferView::new(
         Arc::from(sphere_bytes),
         vec![gpu_spheres.len()],
         std::mem::size_of::<GpuSphere>(),
     );
-    
+
     // Create a simple params struct that can be Pod
     #[repr(C)]
     #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
@@ -65,43 +235,44 @@ fn integrate_spheres_gpu(sim: &mut PhysicsSim) -> Result<(), ComputeError> {
         _padding1: f32,
         _padding2: f32,
     }
-    
+
+    let params = &worlds[0].params;
     let gpu_params = GpuParams {
-        gravity: [sim.params.gravity.x, sim.params.gravity.y, sim.params.gravity.z],
-        dt: sim.params.dt,
+        gravity: [params.gravity.x, params.gravity.y, params.gravity.z],
+        dt: params.dt,
         _padding1: 0.0,
         _padding2: 0.0,
     };
-    
+
     let params_bytes = bytemuck::bytes_of(&gpu_params);
     let params_buffer = BufferView::new(
         Arc::from(params_bytes),
         vec![1],
         std::mem::size_of::<GpuParams>(),
     );
-    
+
     // Create forces buffer - ensure we have the right number of forces
-    let mut forces = sim.params.forces.clone();
-    forces.resize(sim.spheres.len(), [0.0, 0.0]);
+    let forces = gather_forces(worlds);
     let forces_bytes = bytemuck::cast_slice(&forces);
     let forces_buffer = BufferView::new(
         Arc::from(forces_bytes),
-        vec![sim.spheres.len()],
+        vec![gpu_spheres.len()],
         std::mem::size_of::<[f32; 2]>(),
     );
-    
+
     // Dispatch integration kernel
-    let workgroups = calculate_workgroups(sim.spheres.len());
+    let workgroups = calculate_workgroups(gpu_spheres.len());
     let results = backend.dispatch(
         &Kernel::IntegrateBodies,
         &[spheres_buffer, params_buffer, forces_buffer],
         [workgroups, 1, 1],
     )?;
-    
+
     // Update spheres with results
     if let Some(result_bytes) = results.first() {
         let new_gpu_spheres: &[GpuSphere] = bytemuck::cast_slice(result_bytes);
-        for (sphere, gpu_sphere) in sim.spheres.iter_mut().zip(new_gpu_spheres) {
+        let spheres = worlds.iter_mut().flat_map(|sim| live_spheres_mut(sim));
+        for (sphere, gpu_sphere) in spheres.zip(new_gpu_spheres) {
             sphere.pos.x = gpu_sphere.pos[0];
             sphere.pos.y = gpu_sphere.pos[1];
             sphere.pos.z = gpu_sphere.pos[2];
@@ -114,7 +285,7 @@ fn integrate_spheres_gpu(sim: &mut PhysicsSim) -> Result<(), ComputeError> {
             sphere.angular_vel.z = gpu_sphere.angular_vel[2];
         }
     }
-    
+
     Ok(())
 }
 
@@ -123,4 +294,4 @@ fn integrate_spheres_gpu(sim: &mut PhysicsSim) -> Result<(), ComputeError> {
 fn calculate_workgroups(num_elements: usize) -> u32 {
     const WORKGROUP_SIZE: u32 = 256;
     ((num_elements as u32 + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE).max(1)
-}
\ No newline at end of file
+}
@@ -0,0 +1,242 @@
+//! # Heightfield Terrain
+//!
+//! A [`Heightfield`] is static terrain described by a regular grid of
+//! heights in the XZ plane. Every grid cell is split into two triangles
+//! along the diagonal from its smallest-x, smallest-z corner, so the surface
+//! is continuous and planar within each triangle. Everything below the
+//! surface is solid. Heights can be generated from a function or loaded
+//! from a grayscale PNG image.
+
+use std::path::Path;
+
+use crate::simulation::PhysicsError;
+use crate::types::{BoundingBox, Material, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// Static terrain given by heights sampled on a regular grid.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Heightfield {
+    /// World position of the first sample, the grid corner with the smallest
+    /// x and z.
+    pub origin: Vec3,
+    /// Distance between neighbouring samples along x and z.
+    pub spacing: f32,
+    /// Number of samples along x.
+    pub columns: usize,
+    /// Number of samples along z.
+    pub rows: usize,
+    /// Height of every sample above `origin.y`, row by row. The sample in
+    /// column `c` of row `r` is `heights[r * columns + c]` and lies at
+    /// `origin + (c * spacing, height, r * spacing)`.
+    pub heights: Vec<f32>,
+    /// Material properties for the terrain surface.
+    pub material: Material,
+}
+
+impl Heightfield {
+    /// Creates a heightfield from row-major sample heights.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer than two samples along either axis, if
+    /// `heights` does not hold `columns * rows` samples, or if `spacing` is
+    /// not positive.
+    #[must_use]
+    pub fn new(origin: Vec3, spacing: f32, columns: usize, rows: usize, heights: Vec<f32>) -> Self {
+        assert!(columns >= 2 && rows >= 2, "a heightfield needs at least 2 x 2 samples");
+        assert_eq!(heights.len(), columns * rows, "expected {columns} x {rows} heights");
+        assert!(spacing > 0.0, "heightfield spacing must be positive");
+        Self {
+            origin,
+            spacing,
+            columns,
+            rows,
+            heights,
+            material: Material::default(),
+        }
+    }
+
+    /// Creates a heightfield by sampling `height(x, z)`, the height above
+    /// `origin.y` at world position `(x, z)`, at every grid point.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`Self::new`].
+    #[must_use]
+    pub fn from_fn(
+        origin: Vec3,
+        spacing: f32,
+        columns: usize,
+        rows: usize,
+        height: impl Fn(f32, f32) -> f32,
+    ) -> Self {
+        let heights = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let (x, z) = grid_offset(spacing, column, row);
+                height(origin.x + x, origin.z + z)
+            })
+            .collect();
+        Self::new(origin, spacing, columns, rows, heights)
+    }
+
+    /// Loads a heightfield from a grayscale PNG file, one sample per pixel.
+    ///
+    /// Pixel `(x, y)` becomes the sample in column `x` of row `y`. Black maps
+    /// to `origin.y` and white to `max_height` above it; colour images are
+    /// converted to luminance first.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PhysicsError::Image`] if the file cannot be read or decoded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is smaller than 2 x 2 pixels or if `spacing` is
+    /// not positive.
+    pub fn from_png(
+        path: impl AsRef<Path>,
+        origin: Vec3,
+        spacing: f32,
+        max_height: f32,
+    ) -> Result<Self, PhysicsError> {
+        let image = image::open(path)?;
+        Ok(Self::from_image(&image, origin, spacing, max_height))
+    }
+
+    /// Decodes a heightfield from PNG data in memory. See [`Self::from_png`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PhysicsError::Image`] if the data is not a valid PNG.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`Self::from_png`].
+    pub fn from_png_bytes(bytes: &[u8], origin: Vec3, spacing: f32, max_height: f32) -> Result<Self, PhysicsError> {
+        let image = image::load_from_memory_with_format(bytes, image::ImageFormat::Png)?;
+        Ok(Self::from_image(&image, origin, spacing, max_height))
+    }
+
+    fn from_image(image: &image::DynamicImage, origin: Vec3, spacing: f32, max_height: f32) -> Self {
+        let luma = image.to_luma16();
+        let heights = luma
+            .pixels()
+            .map(|pixel| f32::from(pixel.0[0]) / f32::from(u16::MAX) * max_height)
+            .collect();
+        Self::new(origin, spacing, luma.width() as usize, luma.height() as usize, heights)
+    }
+
+    /// World height of the surface at `(x, z)`, or `None` outside the grid.
+    #[must_use]
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        self.surface_at(x, z).map(|(height, _)| height)
+    }
+
+    /// Upward unit normal of the surface at `(x, z)`, or `None` outside the
+    /// grid.
+    #[must_use]
+    pub fn normal_at(&self, x: f32, z: f32) -> Option<Vec3> {
+        self.surface_at(x, z).map(|(_, normal)| normal.into())
+    }
+
+    /// Axis-aligned bounds of the terrain surface.
+    #[must_use]
+    pub fn bounds(&self) -> BoundingBox {
+        let (low, high) = self
+            .heights
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), &h| (low.min(h), high.max(h)));
+        let (x, z) = grid_offset(self.spacing, self.columns - 1, self.rows - 1);
+        BoundingBox {
+            min: self.origin + Vec3::new(0.0, low, 0.0),
+            max: self.origin + Vec3::new(x, high, z),
+        }
+    }
+
+    /// World position of the sample in `column` of `row`.
+    pub(crate) fn vertex(&self, column: usize, row: usize) -> glam::Vec3 {
+        let (x, z) = grid_offset(self.spacing, column, row);
+        glam::Vec3::from(self.origin) + glam::Vec3::new(x, self.heights[row * self.columns + column], z)
+    }
+
+    /// Height and upward normal of the triangle under `(x, z)`.
+    pub(crate) fn surface_at(&self, x: f32, z: f32) -> Option<(f32, glam::Vec3)> {
+        let u = (x - self.origin.x) / self.spacing;
+        let v = (z - self.origin.z) / self.spacing;
+        let (last_column, last_row) = (self.columns - 1, self.rows - 1);
+        #[allow(clippy::cast_precision_loss)]
+        if !(0.0..=last_column as f32).contains(&u) || !(0.0..=last_row as f32).contains(&v) {
+            return None;
+        }
+        let column = grid_index(u, last_column - 1);
+        let row = grid_index(v, last_row - 1);
+        #[allow(clippy::cast_precision_loss)]
+        let upper = u - column as f32 >= v - row as f32;
+        let [p0, p1, p2] = self.cell_triangles(column, row)[usize::from(!upper)];
+        let normal = (p1 - p0).cross(p2 - p0).normalize();
+        let height = p0.y - (normal.x * (x - p0.x) + normal.z * (z - p0.z)) / normal.y;
+        Some((height, normal))
+    }
+
+    /// Triangles of every cell that overlaps `[min, max]` in x and z.
+    pub(crate) fn triangles_in(&self, min: glam::Vec3, max: glam::Vec3) -> impl Iterator<Item = [glam::Vec3; 3]> + '_ {
+        let (columns, rows) = self.index_range(min, max, 1);
+        rows.flat_map(move |row| columns.clone().flat_map(move |column| self.cell_triangles(column, row)))
+    }
+
+    /// Flat index and position of every sample within `[min, max]` in x
+    /// and z.
+    pub(crate) fn vertices_in(&self, min: glam::Vec3, max: glam::Vec3) -> impl Iterator<Item = (usize, glam::Vec3)> + '_ {
+        let (columns, rows) = self.index_range(min, max, 0);
+        rows.flat_map(move |row| {
+            columns
+                .clone()
+                .map(move |column| (row * self.columns + column, self.vertex(column, row)))
+        })
+    }
+
+    /// The two triangles of the cell whose smallest corner is the sample in
+    /// `column` of `row`, wound so that their normals point up.
+    fn cell_triangles(&self, column: usize, row: usize) -> [[glam::Vec3; 3]; 2] {
+        let corner = |dc: usize, dr: usize| self.vertex(column + dc, row + dr);
+        let (p00, p10, p01, p11) = (corner(0, 0), corner(1, 0), corner(0, 1), corner(1, 1));
+        [[p00, p11, p10], [p00, p01, p11]]
+    }
+
+    /// Column and row ranges of the samples (`cells == 0`) or cells
+    /// (`cells == 1`) that overlap `[min, max]` in x and z.
+    fn index_range(
+        &self,
+        min: glam::Vec3,
+        max: glam::Vec3,
+        cells: usize,
+    ) -> (std::ops::Range<usize>, std::ops::Range<usize>) {
+        let origin = glam::Vec3::from(self.origin);
+        let range = |low: f32, high: f32, count: usize| {
+            let last = count - 1 - cells;
+            // Cells start at their smallest sample, samples must be inside
+            let (low, high) = if cells == 0 { (low.ceil(), high.floor()) } else { (low.floor(), high.floor()) };
+            #[allow(clippy::cast_precision_loss)]
+            if high < 0.0 || low > last as f32 {
+                return 0..0;
+            }
+            grid_index(low, last)..grid_index(high, last) + 1
+        };
+        let scale = 1.0 / self.spacing;
+        let (low, high) = ((min - origin) * scale, (max - origin) * scale);
+        (range(low.x, high.x, self.columns), range(low.z, high.z, self.rows))
+    }
+}
+
+/// Offset of the sample in `column` of `row` from the grid origin.
+#[allow(clippy::cast_precision_loss)]
+fn grid_offset(spacing: f32, column: usize, row: usize) -> (f32, f32) {
+    (column as f32 * spacing, row as f32 * spacing)
+}
+
+/// Grid coordinate `value` rounded down and clamped to `0..=last`.
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn grid_index(value: f32, last: usize) -> usize {
+    value.floor().clamp(0.0, last as f32) as usize
+}
@@ -2,51 +2,110 @@
 //! 
 //! This module handles the numerical integration of physics bodies,
 //! including position updates, velocity calculations, and force application.
+//!
+//! The per-shape functions below implement [`Integrator::SemiImplicitEuler`].
+//! The other schemes work on [`SolverBody`] values: both solvers first give
+//! every body its velocity change over the step (or substep) and solve
+//! constraints, then [`advance_body`] moves each dynamic body from its
+//! state at the start of the step.
 
-use crate::types::{Vec3, Sphere, BoxBody, Cylinder};
+use crate::compound::Compound;
+use crate::mesh::ConvexHull;
+use crate::solver::SolverBody;
+use crate::types::{Integrator, Vec3, Sphere, BoxBody, Capsule, Cylinder};
 
 /// Integration constants
 const DAMPING_FACTOR: f32 = 1.0; // No damping for now (was 0.999)
 
-/// Integrate sphere positions and velocities using Verlet integration
-pub fn integrate_spheres(spheres: &mut [Sphere], gravity: Vec3, dt: f32) {
+/// Apply gravity to sphere velocities
+pub fn apply_gravity_to_spheres(spheres: &mut [Sphere], gravity: Vec3, dt: f32) {
     for sphere in spheres.iter_mut() {
-        // Apply gravity force
-        let acceleration = gravity;
-        
-        // Simple Euler integration
-        sphere.vel += acceleration * dt;
-        sphere.pos += sphere.vel * dt;
-        
-        // Apply damping (disabled for now)
-        // sphere.vel *= DAMPING_FACTOR;
+        sphere.vel += gravity * dt;
     }
 }
 
-/// Integrate box positions and velocities
-pub fn integrate_boxes(boxes: &mut [BoxBody], gravity: Vec3, dt: f32) {
+/// Apply gravity to box velocities
+pub fn apply_gravity_to_boxes(boxes: &mut [BoxBody], gravity: Vec3, dt: f32) {
     use crate::types::BodyType;
     
     for box_body in boxes.iter_mut() {
         // Only apply gravity to dynamic bodies
         if box_body.body_type == BodyType::Dynamic {
-            let acceleration = gravity;
-            box_body.vel += acceleration * dt;
+            box_body.vel += gravity * dt;
+        }
+    }
+}
+
+/// Apply gravity to cylinder velocities
+pub fn apply_gravity_to_cylinders(cylinders: &mut [Cylinder], gravity: Vec3, dt: f32) {
+    use crate::types::BodyType;
+    
+    for cylinder in cylinders.iter_mut() {
+        // Only apply gravity to dynamic bodies
+        if cylinder.body_type == BodyType::Dynamic {
+            cylinder.vel += gravity * dt;
+        }
+    }
+}
+
+/// Apply gravity to capsule velocities
+pub fn apply_gravity_to_capsules(capsules: &mut [Capsule], gravity: Vec3, dt: f32) {
+    use crate::types::BodyType;
+    
+    for capsule in capsules.iter_mut() {
+        // Only apply gravity to dynamic bodies
+        if capsule.body_type == BodyType::Dynamic {
+            capsule.vel += gravity * dt;
         }
+    }
+}
+
+/// Apply gravity to convex hull velocities
+pub fn apply_gravity_to_hulls(hulls: &mut [ConvexHull], gravity: Vec3, dt: f32) {
+    use crate::types::BodyType;
+    
+    for hull in hulls.iter_mut() {
+        // Only apply gravity to dynamic bodies
+        if hull.body_type == BodyType::Dynamic {
+            hull.vel += gravity * dt;
+        }
+    }
+}
+
+/// Apply gravity to compound body velocities
+pub fn apply_gravity_to_compounds(compounds: &mut [Compound], gravity: Vec3, dt: f32) {
+    use crate::types::BodyType;
+    
+    for compound in compounds.iter_mut() {
+        // Only apply gravity to dynamic bodies
+        if compound.body_type == BodyType::Dynamic {
+            compound.vel += gravity * dt;
+        }
+    }
+}
+
+/// Integrate sphere positions and orientations from their velocities
+pub fn integrate_sphere_positions(spheres: &mut [Sphere], dt: f32) {
+    for sphere in spheres.iter_mut() {
+        sphere.pos += sphere.vel * dt;
+        integrate_orientation(&mut sphere.orientation, sphere.angular_vel, dt);
         
+        // Apply damping (disabled for now)
+        // sphere.vel *= DAMPING_FACTOR;
+    }
+}
+
+/// Integrate box positions and orientations from their velocities
+pub fn integrate_box_positions(boxes: &mut [BoxBody], dt: f32) {
+    use crate::types::BodyType;
+    
+    for box_body in boxes.iter_mut() {
         // Update position for dynamic and kinematic bodies (static bodies don't move)
         if box_body.body_type != BodyType::Static {
             box_body.pos += box_body.vel * dt;
         }
         
-        // Integrate angular velocity
-        if box_body.angular_vel.length() > 0.0 {
-            let angle = box_body.angular_vel.length() * dt;
-            let axis = box_body.angular_vel.normalize();
-            let delta_quat = quaternion_from_axis_angle(axis, angle);
-            box_body.orientation = quaternion_multiply(delta_quat, box_body.orientation);
-            normalize_quaternion(&mut box_body.orientation);
-        }
+        integrate_orientation(&mut box_body.orientation, box_body.angular_vel, dt);
         
         // Apply damping (disabled for now)
         // box_body.vel *= DAMPING_FACTOR;
@@ -54,28 +113,17 @@ pub fn integrate_boxes(boxes: &mut [BoxBody], gravity: Vec3, dt: f32) {
     }
 }
 
-/// Integrate cylinder positions and velocities
-pub fn integrate_cylinders(cylinders: &mut [Cylinder], gravity: Vec3, dt: f32) {
+/// Integrate cylinder positions and orientations from their velocities
+pub fn integrate_cylinder_positions(cylinders: &mut [Cylinder], dt: f32) {
     use crate::types::BodyType;
     
     for cylinder in cylinders.iter_mut() {
-        // SKIP gravity for dynamic cylinders - let the constraint solver handle forces
-        // This prevents joint drift in CartPole systems
-        // (Direct gravity application conflicts with rigid joint constraints)
-        
         // Update position for dynamic and kinematic bodies (static bodies don't move)
         if cylinder.body_type != BodyType::Static {
             cylinder.pos += cylinder.vel * dt;
         }
         
-        // Integrate angular velocity and update orientation
-        if cylinder.angular_vel.length() > 0.0 {
-            let angle = cylinder.angular_vel.length() * dt;
-            let axis = cylinder.angular_vel.normalize();
-            let delta_quat = quaternion_from_axis_angle(axis, angle);
-            cylinder.orientation = quaternion_multiply(delta_quat, cylinder.orientation);
-            normalize_quaternion(&mut cylinder.orientation);
-        }
+        integrate_orientation(&mut cylinder.orientation, cylinder.angular_vel, dt);
         
         // Apply damping (disabled for now)  
         // cylinder.vel *= DAMPING_FACTOR;
@@ -83,6 +131,59 @@ pub fn integrate_cylinders(cylinders: &mut [Cylinder], gravity: Vec3, dt: f32) {
     }
 }
 
+/// Integrate capsule positions and orientations from their velocities
+pub fn integrate_capsule_positions(capsules: &mut [Capsule], dt: f32) {
+    use crate::types::BodyType;
+    
+    for capsule in capsules.iter_mut() {
+        // Update position for dynamic and kinematic bodies (static bodies don't move)
+        if capsule.body_type != BodyType::Static {
+            capsule.pos += capsule.vel * dt;
+        }
+        
+        integrate_orientation(&mut capsule.orientation, capsule.angular_vel, dt);
+    }
+}
+
+/// Integrate convex hull positions and orientations from their velocities
+pub fn integrate_hull_positions(hulls: &mut [ConvexHull], dt: f32) {
+    use crate::types::BodyType;
+    
+    for hull in hulls.iter_mut() {
+        // Update position for dynamic and kinematic bodies (static bodies don't move)
+        if hull.body_type != BodyType::Static {
+            hull.pos += hull.vel * dt;
+        }
+        
+        integrate_orientation(&mut hull.orientation, hull.angular_vel, dt);
+    }
+}
+
+/// Integrate compound body positions and orientations from their velocities
+pub fn integrate_compound_positions(compounds: &mut [Compound], dt: f32) {
+    use crate::types::BodyType;
+    
+    for compound in compounds.iter_mut() {
+        // Update position for dynamic and kinematic bodies (static bodies don't move)
+        if compound.body_type != BodyType::Static {
+            compound.pos += compound.vel * dt;
+        }
+        
+        integrate_orientation(&mut compound.orientation, compound.angular_vel, dt);
+    }
+}
+
+/// Rotate `orientation` by `angular_vel * dt`
+fn integrate_orientation(orientation: &mut [f32; 4], angular_vel: Vec3, dt: f32) {
+    if angular_vel.length() > 0.0 {
+        let angle = angular_vel.length() * dt;
+        let axis = angular_vel.normalize();
+        let delta_quat = quaternion_from_axis_angle(axis, angle);
+        *orientation = quaternion_multiply(delta_quat, *orientation);
+        normalize_quaternion(orientation);
+    }
+}
+
 /// Apply external forces to spheres
 pub fn apply_forces_to_spheres(spheres: &mut [Sphere], forces: &[[f32; 2]], dt: f32) {
     for (i, sphere) in spheres.iter_mut().enumerate() {
@@ -138,4 +239,26 @@ fn normalize_quaternion(q: &mut [f32; 4]) {
         q[2] /= mag;
         q[3] /= mag;
     }
-}
\ No newline at end of file
+}
+
+/// Move the dynamic `body` over `dt` with `integrator`.
+///
+/// `start` is the body at the start of the step. `body` is still at that
+/// pose, but its velocity already holds the whole change over the step:
+/// gravity, applied forces, and contact and joint impulses.
+pub(crate) fn advance_body(integrator: Integrator, body: &mut SolverBody, start: &SolverBody, dt: f32) {
+    let mean_angular = match integrator {
+        Integrator::SemiImplicitEuler => {
+            body.position += body.linear_velocity * dt;
+            body.angular_velocity
+        }
+        // Gravity, applied forces and the solver impulses are constant
+        // over the step, so all four RK4 stages see the same acceleration
+        // and both schemes come down to the exact update
+        Integrator::VelocityVerlet | Integrator::Rk4 => {
+            body.position += (start.linear_velocity + body.linear_velocity) * (0.5 * dt);
+            (start.angular_velocity + body.angular_velocity) * 0.5
+        }
+    };
+    body.orientation = (glam::Quat::from_scaled_axis(mean_angular * dt) * body.orientation).normalize();
+}
@@ -13,11 +13,19 @@
 //! ## Key Components
 //!
 //! -   **Rigid Bodies:** The engine supports several types of rigid bodies,
-//!     including [`Sphere`], [`BoxBody`], [`Cylinder`], and [`Plane`]. These
-//!     are defined in the [`types`] module.
+//!     including [`Sphere`], [`BoxBody`], [`Cylinder`], [`Capsule`], and [`Plane`]. These
+//!     are defined in the [`types`] module. Static terrain is described by a
+//!     [`Heightfield`]. The [`mesh`] module adds [`ConvexHull`] bodies and
+//!     static [`TriangleMesh`] geometry, both loadable from OBJ files. A
+//!     [`Compound`] joins several shapes into one rigid body, and an
+//!     [`Articulation`] simulates a tree of bodies in joint coordinates.
 //! -   **Simulation:** The [`PhysicsSim`] struct in the [`simulation`] module
 //!     is the main entry point for running the physics simulation. It manages
 //!     the state of all rigid bodies and steps the simulation forward in time.
+//!     After each CPU step it reports the contacts of the step, with
+//!     [`ContactEvent`]s for pairs that began, kept or stopped touching.
+//!     Ray casts, sphere casts and box overlaps query the world between
+//!     steps (see the [`query`] module).
 //! -   **Differentiability:** A key feature of the JAXS physics engine is its
 //!     support for differentiability, which allows for gradient-based
 //!     optimization of physical parameters. This is crucial for the ML
@@ -43,7 +51,19 @@
 //! ```
 
 // Public API modules
+pub mod articulation;
+pub mod batch;
+pub mod body;
 pub mod cartpole;
+pub mod compound;
+pub mod contacts;
+pub mod forces;
+pub mod heightfield;
+pub mod mesh;
+pub mod query;
+pub mod removal;
+pub mod snapshot;
+pub mod stepping;
 pub mod types;
 pub mod simulation;
 
@@ -54,16 +74,31 @@ mod cartpole_test;
 mod collision;
 mod gpu_executor;
 mod integrator;
+mod solver;
 mod spatial_grid_ext;
 pub mod transform;
 
 // Re-export main types for convenient access
+pub use articulation::{Articulation, ArticulationBase, ArticulationJoint};
+pub use batch::{BatchState, BatchedPhysicsSim, Observation};
+pub use body::BodyHandle;
 pub use cartpole::{CartPole, CartPoleConfig, CartPoleGrid};
+pub use collision::{CollisionConfig, CollisionFilter, ContactManifold, ContactPoint};
+pub use compound::{ChildShape, Compound, CompoundChild};
+pub use contacts::{ContactEvent, ContactEventKind};
+pub use forces::ForceMode;
+pub use heightfield::Heightfield;
+pub use mesh::{ConvexHull, HullGeometry, MassProperties, ObjMesh, TriangleMesh};
+pub use query::{QueryFilter, Ray, RayHit};
+pub use removal::JointHandle;
 pub use simulation::{PhysicsError, PhysicsSim, SphereState};
+pub use snapshot::Snapshot;
+pub use stepping::AdaptiveStep;
 pub use types::{
-    BoxBody, BoundingBox, ContactDebugInfo, Cylinder, ForceDebugInfo, Joint, JointParams, 
-    Material, PhysicsDebugInfo, PhysParams, Plane, Sphere, SpatialGrid, SpatialGridDebugInfo, 
+    BoxBody, BoundingBox, BroadPhaseType, Capsule, ContactDebugInfo, ContactModel, ContactParams, Cylinder, ForceDebugInfo, Integrator, Joint, JointParams, 
+    Material, PhysicsDebugInfo, PhysParams, Plane, SleepParams, SoftContact, Sphere, SolverType, SpatialGrid, SpatialGridDebugInfo, 
     Vec3, Vec2, VelocityDebugInfo,
     // Joint types
-    RevoluteJoint, PrismaticJoint, BallJoint, FixedJoint,
+    RevoluteJoint, PrismaticJoint, BallJoint, FixedJoint, PlanarConstraint,
+    JointControl, JointState, MOTOR_DISABLED, MOTOR_VELOCITY, MOTOR_POSITION,
 };
@@ -0,0 +1,107 @@
+//! Bounding volume hierarchy over triangles
+
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// Most triangles kept in one leaf.
+const LEAF_SIZE: usize = 4;
+
+/// Tree of axis-aligned boxes over a set of triangles, built once by
+/// splitting at the median centroid along the longest axis.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Bvh {
+    nodes: Vec<Node>,
+    /// Triangle indices, ordered so that every node covers a contiguous run.
+    order: Vec<usize>,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+struct Node {
+    min: Vec3,
+    max: Vec3,
+    /// Leaves cover `order[first..first + count]`; inner nodes have
+    /// `count == 0` and their children at `first` and `first + 1`.
+    first: usize,
+    count: usize,
+}
+
+impl Bvh {
+    pub fn build(triangles: &[[Vec3; 3]]) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(2 * triangles.len() / LEAF_SIZE + 1),
+            order: (0..triangles.len()).collect(),
+        };
+        if !triangles.is_empty() {
+            bvh.nodes.push(Node {
+                min: Vec3::ZERO,
+                max: Vec3::ZERO,
+                first: 0,
+                count: triangles.len(),
+            });
+            bvh.split(0, triangles);
+        }
+        bvh
+    }
+
+    /// Fit node `index` around its triangles and split it while it holds
+    /// more than a leaf's worth.
+    fn split(&mut self, index: usize, triangles: &[[Vec3; 3]]) {
+        let Node { first, count, .. } = self.nodes[index];
+        let run = &mut self.order[first..first + count];
+        let (min, max) = run.iter().flat_map(|&triangle| triangles[triangle]).fold(
+            (Vec3::INFINITY, Vec3::NEG_INFINITY),
+            |(min, max), vertex| (min.min(vertex), max.max(vertex)),
+        );
+        (self.nodes[index].min, self.nodes[index].max) = (min, max);
+        if count <= LEAF_SIZE {
+            return;
+        }
+
+        let extent = max - min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        let centroid = |triangle: usize| (triangles[triangle][0] + triangles[triangle][1] + triangles[triangle][2])[axis];
+        let half = count / 2;
+        run.select_nth_unstable_by(half, |&a, &b| centroid(a).total_cmp(&centroid(b)));
+
+        let children = self.nodes.len();
+        for (first, count) in [(first, half), (first + half, count - half)] {
+            self.nodes.push(Node {
+                min,
+                max,
+                first,
+                count,
+            });
+        }
+        self.nodes[index].first = children;
+        self.nodes[index].count = 0;
+        self.split(children, triangles);
+        self.split(children + 1, triangles);
+    }
+
+    /// Indices of the triangles whose bounds overlap `[min, max]`.
+    pub fn query(&self, min: Vec3, max: Vec3) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.min.cmpgt(max).any() || node.max.cmplt(min).any() {
+                continue;
+            }
+            if node.count == 0 {
+                stack.extend([node.first, node.first + 1]);
+            } else {
+                found.extend_from_slice(&self.order[node.first..node.first + node.count]);
+            }
+        }
+        found
+    }
+}
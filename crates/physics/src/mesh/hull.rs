@@ -0,0 +1,350 @@
+//! Convex hulls of point clouds
+
+use std::path::Path;
+use std::sync::Arc;
+
+use glam::{Quat, Vec3};
+
+use super::{MassProperties, ObjMesh};
+use crate::simulation::PhysicsError;
+use crate::types::{BodyType, Material};
+use serde::{Deserialize, Serialize};
+
+/// A convex polyhedron: the hull of a point cloud, with outward-wound
+/// triangle faces.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HullGeometry {
+    vertices: Vec<Vec3>,
+    triangles: Vec<[u32; 3]>,
+    /// Outward unit normal and offset of the plane of every triangle.
+    planes: Vec<(Vec3, f32)>,
+    /// Faces with coplanar triangles merged.
+    faces: Vec<HullFace>,
+}
+
+/// A planar face of a hull.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct HullFace {
+    /// Outward unit normal.
+    pub normal: Vec3,
+    /// Vertex indices, counter-clockwise seen from outside.
+    pub vertices: Vec<u32>,
+}
+
+impl HullGeometry {
+    /// Computes the convex hull of `points`.
+    ///
+    /// Points inside the hull are dropped. Returns `None` if the points are
+    /// all coplanar, so that they enclose no volume.
+    #[must_use]
+    pub fn from_points(points: &[crate::types::Vec3]) -> Option<Self> {
+        let points: Vec<Vec3> = points.iter().map(|&point| point.into()).collect();
+        let (vertices, triangles) = quickhull(&points)?;
+        Some(Self::from_parts(vertices, triangles))
+    }
+
+    /// Computes the convex hull of every vertex of an OBJ file; faces in the
+    /// file are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`ObjMesh::load`], or [`PhysicsError::Obj`] if
+    /// the vertices enclose no volume.
+    pub fn from_obj(path: impl AsRef<Path>) -> Result<Self, PhysicsError> {
+        let mesh = ObjMesh::load(path)?;
+        Self::from_points(&mesh.vertices).ok_or_else(|| PhysicsError::Obj {
+            line: 0,
+            message: "the vertices of a convex hull must not all lie in one plane".into(),
+        })
+    }
+
+    fn from_parts(vertices: Vec<Vec3>, triangles: Vec<[u32; 3]>) -> Self {
+        let planes: Vec<(Vec3, f32)> = triangles
+            .iter()
+            .map(|triangle| {
+                let [p0, p1, p2] = triangle.map(|index| vertices[index as usize]);
+                let normal = (p1 - p0).cross(p2 - p0).normalize_or_zero();
+                (normal, normal.dot(p0))
+            })
+            .collect();
+        let faces = merge_faces(&vertices, &planes);
+        Self {
+            vertices,
+            triangles,
+            planes,
+            faces,
+        }
+    }
+
+    /// Hull vertices.
+    #[must_use]
+    pub fn vertices(&self) -> Vec<crate::types::Vec3> {
+        self.vertices.iter().map(|&vertex| vertex.into()).collect()
+    }
+
+    /// Hull faces as triangles over [`Self::vertices`], wound
+    /// counter-clockwise seen from outside.
+    #[must_use]
+    pub fn triangles(&self) -> &[[u32; 3]] {
+        &self.triangles
+    }
+
+    /// Mass properties of the solid hull at uniform `density`.
+    #[must_use]
+    pub fn mass_properties(&self, density: f32) -> MassProperties {
+        MassProperties::from_triangles(&self.vertices(), &self.triangles, density)
+    }
+
+    /// The hull rotated by `rotation` and then moved by `offset`.
+    #[must_use]
+    fn transformed(&self, rotation: Quat, offset: Vec3) -> Self {
+        let vertices = self.vertices.iter().map(|&vertex| rotation * vertex + offset).collect();
+        Self::from_parts(vertices, self.triangles.clone())
+    }
+
+    pub(crate) fn local_vertices(&self) -> &[Vec3] {
+        &self.vertices
+    }
+
+    pub(crate) fn planes(&self) -> &[(Vec3, f32)] {
+        &self.planes
+    }
+
+    /// The face whose normal is closest to `direction`.
+    pub(crate) fn face(&self, direction: Vec3) -> &HullFace {
+        self.faces
+            .iter()
+            .max_by(|a, b| a.normal.dot(direction).total_cmp(&b.normal.dot(direction)))
+            .expect("a hull has faces")
+    }
+
+    /// Hull vertex furthest along `direction`.
+    pub(crate) fn support(&self, direction: Vec3) -> Vec3 {
+        self.vertices
+            .iter()
+            .copied()
+            .max_by(|a, b| a.dot(direction).total_cmp(&b.dot(direction)))
+            .unwrap_or(Vec3::ZERO)
+    }
+}
+
+/// Faces of a hull from the planes of its triangles: every vertex on a
+/// plane, ordered around it.
+fn merge_faces(vertices: &[Vec3], planes: &[(Vec3, f32)]) -> Vec<HullFace> {
+    let (min, max) = vertices
+        .iter()
+        .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), &vertex| (min.min(vertex), max.max(vertex)));
+    let epsilon = FACE_TOLERANCE * (max - min).length();
+    let mut faces: Vec<HullFace> = Vec::new();
+    for &(normal, offset) in planes {
+        if normal == Vec3::ZERO || faces.iter().any(|face| face.normal.dot(normal) >= 1.0 - FACE_TOLERANCE) {
+            continue;
+        }
+        let mut on_plane: Vec<u32> = (0u32..)
+            .zip(vertices)
+            .filter(|&(_, vertex)| (normal.dot(*vertex) - offset).abs() <= epsilon)
+            .map(|(index, _)| index)
+            .collect();
+        #[allow(clippy::cast_precision_loss)]
+        let center = on_plane.iter().map(|&index| vertices[index as usize]).sum::<Vec3>() / on_plane.len() as f32;
+        let u = (vertices[on_plane[0] as usize] - center).normalize_or_zero();
+        let w = normal.cross(u);
+        let angle = |index: &u32| {
+            let offset = vertices[*index as usize] - center;
+            w.dot(offset).atan2(u.dot(offset))
+        };
+        on_plane.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+        faces.push(HullFace {
+            normal,
+            vertices: on_plane,
+        });
+    }
+    faces
+}
+
+/// A rigid body shaped like a convex hull.
+///
+/// The body frame is the principal frame of the hull: `pos` is its centre of
+/// mass and the hull geometry is stored rotated so that its inertia tensor
+/// is diagonal, which is what the solver works with. The geometry is shared
+/// between copies of the body.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConvexHull {
+    /// The world-space position of the centre of mass.
+    pub pos: crate::types::Vec3,
+    /// The linear velocity of the body.
+    pub vel: crate::types::Vec3,
+    /// The orientation of the principal frame, represented as a quaternion
+    /// in `[x, y, z, w]` format.
+    pub orientation: [f32; 4],
+    /// The angular velocity of the body, measured in radians per second.
+    pub angular_vel: crate::types::Vec3,
+    /// Mass of the body in kilograms.
+    pub mass: f32,
+    /// Principal moments of inertia, about the axes of the body frame.
+    pub inertia: crate::types::Vec3,
+    /// Material properties for collision response.
+    pub material: Material,
+    /// Body type (Dynamic, Kinematic, Static)
+    pub body_type: BodyType,
+    /// Hull in the body frame.
+    pub geometry: Arc<HullGeometry>,
+}
+
+impl ConvexHull {
+    /// Creates a dynamic body from `geometry` with uniform `density`, with
+    /// the origin of the geometry placed at `position`.
+    ///
+    /// The body starts in the world orientation of the geometry; its
+    /// `orientation` is that of its principal axes.
+    #[must_use]
+    pub fn new(geometry: &HullGeometry, position: crate::types::Vec3, density: f32) -> Self {
+        let properties = geometry.mass_properties(density);
+        let (moments, rotation) = properties.principal_axes();
+        let rotation = Quat::from_array(rotation);
+        let center = Vec3::from(properties.center_of_mass);
+        Self {
+            pos: (Vec3::from(position) + center).into(),
+            vel: crate::types::Vec3::ZERO,
+            orientation: rotation.to_array(),
+            angular_vel: crate::types::Vec3::ZERO,
+            mass: properties.mass,
+            inertia: moments,
+            material: Material::default(),
+            body_type: BodyType::Dynamic,
+            geometry: Arc::new(geometry.transformed(rotation.inverse(), -(rotation.inverse() * center))),
+        }
+    }
+}
+
+/// Relative tolerance, scaled by the size of the point cloud, below which
+/// points count as coplanar.
+const HULL_TOLERANCE: f32 = 1e-5;
+/// Relative tolerance within which triangle planes are merged into one
+/// face.
+const FACE_TOLERANCE: f32 = 1e-4;
+
+/// Incremental convex hull: start from a tetrahedron of extreme points,
+/// then repeatedly take the point furthest outside a face and replace the
+/// faces it sees by a fan of new faces around the horizon of those faces.
+/// Every face keeps the points outside it that are still to be added.
+fn quickhull(points: &[Vec3]) -> Option<(Vec<Vec3>, Vec<[u32; 3]>)> {
+    let (min, max) = points
+        .iter()
+        .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), &point| (min.min(point), max.max(point)));
+    let epsilon = HULL_TOLERANCE * (max - min).length();
+    let mut faces = initial_tetrahedron(points, epsilon)?;
+    assign_outside(points, &mut faces, 0..points.len(), epsilon);
+
+    while let Some(apex) = faces.iter().find_map(|face| {
+        face.outside
+            .iter()
+            .copied()
+            .max_by(|&a, &b| face.distance(points[a]).total_cmp(&face.distance(points[b])))
+    }) {
+        let (visible, kept): (Vec<Face>, Vec<Face>) =
+            faces.into_iter().partition(|face| face.distance(points[apex]) > epsilon);
+        faces = kept;
+        let edges: Vec<(usize, usize)> = visible
+            .iter()
+            .flat_map(|face| {
+                let [p0, p1, p2] = face.corners;
+                [(p0, p1), (p1, p2), (p2, p0)]
+            })
+            .collect();
+        // Horizon edges belong to exactly one visible face
+        let first_new = faces.len();
+        for &(from, to) in &edges {
+            if !edges.contains(&(to, from)) {
+                faces.push(Face::new(points, [from, to, apex]));
+            }
+        }
+        let orphans = visible.into_iter().flat_map(|face| face.outside).filter(|&point| point != apex);
+        assign_outside(points, &mut faces[first_new..], orphans, epsilon);
+    }
+
+    // Keep only the points that are hull vertices
+    let mut remap = vec![u32::MAX; points.len()];
+    let mut vertices = Vec::new();
+    let triangles = faces
+        .iter()
+        .map(|face| {
+            face.corners.map(|corner| {
+                if remap[corner] == u32::MAX {
+                    remap[corner] = u32::try_from(vertices.len()).expect("too many hull vertices");
+                    vertices.push(points[corner]);
+                }
+                remap[corner]
+            })
+        })
+        .collect();
+    Some((vertices, triangles))
+}
+
+/// Give each point to the first face it lies more than `epsilon` in front
+/// of. Points behind all of them are inside the hull and dropped.
+fn assign_outside(points: &[Vec3], faces: &mut [Face], candidates: impl IntoIterator<Item = usize>, epsilon: f32) {
+    for point in candidates {
+        if let Some(face) = faces.iter_mut().find(|face| face.distance(points[point]) > epsilon) {
+            face.outside.push(point);
+        }
+    }
+}
+
+/// Hull face under construction, over indices into the point cloud.
+struct Face {
+    corners: [usize; 3],
+    normal: Vec3,
+    offset: f32,
+    /// Points in front of this face that are not yet part of the hull.
+    outside: Vec<usize>,
+}
+
+impl Face {
+    fn new(points: &[Vec3], corners: [usize; 3]) -> Self {
+        let [p0, p1, p2] = corners.map(|corner| points[corner]);
+        let normal = (p1 - p0).cross(p2 - p0).normalize_or_zero();
+        Self {
+            corners,
+            normal,
+            offset: normal.dot(p0),
+            outside: Vec::new(),
+        }
+    }
+
+    /// Signed distance of `point` in front of the face.
+    fn distance(&self, point: Vec3) -> f32 {
+        self.normal.dot(point) - self.offset
+    }
+}
+
+/// Outward-wound faces of a tetrahedron spanned by extreme points, or
+/// `None` if every point lies within `epsilon` of one plane.
+fn initial_tetrahedron(points: &[Vec3], epsilon: f32) -> Option<Vec<Face>> {
+    let furthest_by = |score: &dyn Fn(Vec3) -> f32| {
+        (0..points.len()).max_by(|&a, &b| score(points[a]).total_cmp(&score(points[b])))
+    };
+    let first = furthest_by(&|point| -point.x)?;
+    let second = furthest_by(&|point| point.distance(points[first]))?;
+    let axis = (points[second] - points[first]).normalize_or_zero();
+    let off_line = |point: Vec3| (point - points[first]).reject_from_normalized(axis).length();
+    let third = furthest_by(&off_line)?;
+    let normal = (points[second] - points[first]).cross(points[third] - points[first]).normalize_or_zero();
+    let off_plane = |point: Vec3| normal.dot(point - points[first]).abs();
+    let fourth = furthest_by(&off_plane)?;
+    if off_line(points[third]) <= epsilon || off_plane(points[fourth]) <= epsilon {
+        return None;
+    }
+
+    let centroid = (points[first] + points[second] + points[third] + points[fourth]) * 0.25;
+    let faces = [[first, second, third], [first, second, fourth], [first, third, fourth], [second, third, fourth]]
+        .map(|corners| {
+            let face = Face::new(points, corners);
+            if face.distance(centroid) > 0.0 {
+                Face::new(points, [corners[0], corners[2], corners[1]])
+            } else {
+                face
+            }
+        });
+    Some(faces.into())
+}
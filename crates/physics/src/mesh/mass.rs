@@ -0,0 +1,115 @@
+//! Mass properties of closed triangle surfaces
+
+use glam::{Mat3, Quat, Vec3};
+
+/// Mass, centre of mass and inertia of a solid bounded by a closed triangle
+/// surface.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MassProperties {
+    /// Total mass in kilograms.
+    pub mass: f32,
+    /// Centre of mass, in the frame of the surface vertices.
+    pub center_of_mass: crate::types::Vec3,
+    /// Symmetric inertia tensor about the centre of mass, in the frame of the
+    /// surface vertices.
+    pub inertia: [[f32; 3]; 3],
+}
+
+impl MassProperties {
+    /// Mass properties of the solid enclosed by `triangles` at uniform
+    /// `density`.
+    ///
+    /// The surface is split into tetrahedra that share the origin, whose
+    /// signed volumes and second moments add up to those of the solid. The
+    /// triangles may be wound either way as long as they are wound
+    /// consistently.
+    #[must_use]
+    pub fn from_triangles(vertices: &[crate::types::Vec3], triangles: &[[u32; 3]], density: f32) -> Self {
+        // Second moment of the unit tetrahedron (0, e1, e2, e3)
+        let canonical = Mat3::from_cols_array(&[2.0, 1.0, 1.0, 1.0, 2.0, 1.0, 1.0, 1.0, 2.0]) * (1.0 / 120.0);
+        let mut volume = 0.0;
+        let mut first = Vec3::ZERO;
+        let mut second = Mat3::ZERO;
+        for triangle in triangles {
+            let [p0, p1, p2] = triangle.map(|index| Vec3::from(vertices[index as usize]));
+            let corners = Mat3::from_cols(p0, p1, p2);
+            let det = corners.determinant();
+            volume += det / 6.0;
+            first += (p0 + p1 + p2) * (det / 24.0);
+            second += corners * canonical * corners.transpose() * det;
+        }
+        // Inward winding flips every signed quantity
+        let sign = if volume < 0.0 { -1.0 } else { 1.0 };
+        let (volume, first, second) = (volume * sign, first * sign, second * sign);
+        if volume <= f32::EPSILON {
+            return Self {
+                mass: 0.0,
+                center_of_mass: crate::types::Vec3::ZERO,
+                inertia: [[0.0; 3]; 3],
+            };
+        }
+
+        let center = first / volume;
+        // Second moment about the centre of mass, then I = tr(C) 1 - C
+        let covariance = (second - outer(center, center) * volume) * density;
+        let trace = covariance.x_axis.x + covariance.y_axis.y + covariance.z_axis.z;
+        let inertia = Mat3::from_diagonal(Vec3::splat(trace)) - covariance;
+        Self {
+            mass: volume * density,
+            center_of_mass: center.into(),
+            inertia: inertia.to_cols_array_2d(),
+        }
+    }
+
+    /// Principal moments of inertia and the rotation whose columns are the
+    /// matching principal axes, so that the inertia tensor equals
+    /// `R * diag(moments) * Rᵀ`.
+    #[must_use]
+    pub fn principal_axes(&self) -> (crate::types::Vec3, [f32; 4]) {
+        let (moments, axes) = symmetric_eigen(Mat3::from_cols_array_2d(&self.inertia));
+        let rotation = Quat::from_mat3(&axes).normalize();
+        (moments.into(), rotation.to_array())
+    }
+}
+
+fn outer(a: Vec3, b: Vec3) -> Mat3 {
+    Mat3::from_cols(a * b.x, a * b.y, a * b.z)
+}
+
+/// Eigenvalues and eigenvectors (as the columns of a proper rotation) of a
+/// symmetric matrix, by cyclic Jacobi rotations.
+fn symmetric_eigen(matrix: Mat3) -> (Vec3, Mat3) {
+    let mut tensor = matrix.to_cols_array_2d();
+    // Row-major, so the eigenvectors end up in its columns
+    let mut vectors = Mat3::IDENTITY.to_cols_array_2d();
+    for _ in 0..32 {
+        let off_diagonal = tensor[0][1].powi(2) + tensor[0][2].powi(2) + tensor[1][2].powi(2);
+        let diagonal = tensor[0][0].powi(2) + tensor[1][1].powi(2) + tensor[2][2].powi(2);
+        if off_diagonal <= 1e-12 * diagonal.max(f32::MIN_POSITIVE) {
+            break;
+        }
+        for (i, j) in [(0, 1), (0, 2), (1, 2)] {
+            if tensor[i][j].abs() <= f32::MIN_POSITIVE {
+                continue;
+            }
+            // Rotation in the (i, j) plane that zeroes tensor[i][j]
+            let theta = (tensor[j][j] - tensor[i][i]) / (2.0 * tensor[i][j]);
+            let tan = theta.signum() / (theta.abs() + theta.hypot(1.0));
+            let cos = 1.0 / tan.hypot(1.0);
+            let sin = tan * cos;
+            let rotate = |first: f32, second: f32| (cos * first - sin * second, sin * first + cos * second);
+            for row in tensor.iter_mut().chain(vectors.iter_mut()) {
+                (row[i], row[j]) = rotate(row[i], row[j]);
+            }
+            let (upper, lower) = tensor.split_at_mut(j);
+            for (first, second) in upper[i].iter_mut().zip(lower[0].iter_mut()) {
+                (*first, *second) = rotate(*first, *second);
+            }
+        }
+    }
+    let mut axes = Mat3::from_cols_array_2d(&vectors).transpose();
+    if axes.determinant() < 0.0 {
+        axes.z_axis = -axes.z_axis;
+    }
+    (Vec3::new(tensor[0][0], tensor[1][1], tensor[2][2]), axes)
+}
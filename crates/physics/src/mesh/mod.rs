@@ -0,0 +1,18 @@
+//! # Convex Hulls and Triangle Meshes
+//!
+//! Shapes built from polygon geometry. A [`ConvexHull`] is a dynamic rigid
+//! body shaped like the convex hull of a point cloud, with its mass and
+//! inertia computed from the hull. A [`TriangleMesh`] is static collision
+//! geometry of arbitrary shape. Both can be loaded from Wavefront OBJ files
+//! through [`ObjMesh`].
+
+mod bvh;
+mod hull;
+mod mass;
+mod obj;
+mod trimesh;
+
+pub use hull::{ConvexHull, HullGeometry};
+pub use mass::MassProperties;
+pub use obj::ObjMesh;
+pub use trimesh::TriangleMesh;
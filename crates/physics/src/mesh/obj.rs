@@ -0,0 +1,86 @@
+//! Wavefront OBJ geometry
+
+use std::path::Path;
+
+use crate::simulation::PhysicsError;
+use crate::types::Vec3;
+
+/// Vertices and triangles read from a Wavefront OBJ file.
+///
+/// Only geometry is read: `v` and `f` statements. Polygons are split into
+/// triangle fans, and texture and normal indices (`f 1/2/3 ...`) are
+/// ignored, as are all other statements.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjMesh {
+    /// Vertex positions in file order.
+    pub vertices: Vec<Vec3>,
+    /// Triangles as zero-based indices into `vertices`, wound as in the file.
+    pub triangles: Vec<[u32; 3]>,
+}
+
+impl ObjMesh {
+    /// Reads an OBJ file from disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PhysicsError::Io`] if the file cannot be read and
+    /// [`PhysicsError::Obj`] if it is malformed.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PhysicsError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses the text of an OBJ file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PhysicsError::Obj`] for a vertex without three coordinates
+    /// or a face with fewer than three or out-of-range indices.
+    pub fn parse(source: &str) -> Result<Self, PhysicsError> {
+        let mut mesh = Self::default();
+        for (number, line) in source.lines().enumerate() {
+            let error = |message: String| PhysicsError::Obj { line: number + 1, message };
+            let mut words = line.split('#').next().unwrap_or_default().split_whitespace();
+            match words.next() {
+                Some("v") => {
+                    let coordinates: Vec<f32> = words
+                        .take(3)
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()
+                        .map_err(|err| error(format!("invalid vertex coordinate: {err}")))?;
+                    let [x, y, z] = coordinates[..] else {
+                        return Err(error("a vertex needs three coordinates".into()));
+                    };
+                    mesh.vertices.push(Vec3::new(x, y, z));
+                }
+                Some("f") => {
+                    let corners = words
+                        .map(|word| mesh.vertex_index(word).map_err(&error))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if corners.len() < 3 {
+                        return Err(error("a face needs at least three vertices".into()));
+                    }
+                    mesh.triangles
+                        .extend(corners.windows(2).skip(1).map(|pair| [corners[0], pair[0], pair[1]]));
+                }
+                _ => {}
+            }
+        }
+        Ok(mesh)
+    }
+
+    /// Zero-based vertex index of a face corner such as `7`, `7/1/2` or
+    /// `-1`, where negative indices count back from the last vertex read.
+    fn vertex_index(&self, word: &str) -> Result<u32, String> {
+        let position = word.split('/').next().unwrap_or_default();
+        let index: i64 = position
+            .parse()
+            .map_err(|err| format!("invalid vertex index {position:?}: {err}"))?;
+        let count = i64::try_from(self.vertices.len()).unwrap_or(i64::MAX);
+        let resolved = if index < 0 { count + index } else { index - 1 };
+        if (0..count).contains(&resolved) {
+            u32::try_from(resolved).map_err(|err| err.to_string())
+        } else {
+            Err(format!("vertex index {index} is out of range"))
+        }
+    }
+}
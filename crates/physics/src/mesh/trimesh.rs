@@ -0,0 +1,116 @@
+//! Static triangle meshes
+
+use std::path::Path;
+
+use glam::{Quat, Vec3};
+
+use super::bvh::Bvh;
+use super::{MassProperties, ObjMesh};
+use crate::simulation::PhysicsError;
+use crate::types::{BoundingBox, Material};
+use serde::{Deserialize, Serialize};
+
+/// Static collision geometry made of triangles in world space.
+///
+/// Triangles are one-sided: bodies are pushed out along the normal of the
+/// side from which the triangle is wound counter-clockwise. A closed mesh
+/// wound outward therefore behaves as a solid, while an open one, such as
+/// a ramp or a floor, only collides from its front. Triangles are kept in a
+/// bounding volume hierarchy so the narrow phase only visits those near a
+/// body.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TriangleMesh {
+    vertices: Vec<Vec3>,
+    triangles: Vec<[u32; 3]>,
+    bvh: Bvh,
+    /// Material properties for the mesh surface.
+    pub material: Material,
+}
+
+impl TriangleMesh {
+    /// Creates a mesh from vertices and triangles indexing them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a triangle refers to a vertex that does not exist.
+    #[must_use]
+    pub fn new(vertices: &[crate::types::Vec3], triangles: Vec<[u32; 3]>) -> Self {
+        let vertices: Vec<Vec3> = vertices.iter().map(|&vertex| vertex.into()).collect();
+        assert!(
+            triangles.iter().flatten().all(|&index| (index as usize) < vertices.len()),
+            "triangle vertex index out of range"
+        );
+        let corners: Vec<[Vec3; 3]> = triangles
+            .iter()
+            .map(|triangle| triangle.map(|index| vertices[index as usize]))
+            .collect();
+        Self {
+            bvh: Bvh::build(&corners),
+            vertices,
+            triangles,
+            material: Material::default(),
+        }
+    }
+
+    /// Loads a mesh from an OBJ file, rotated by `orientation` (`[x, y, z,
+    /// w]`) and then moved by `position`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`ObjMesh::load`].
+    pub fn from_obj(
+        path: impl AsRef<Path>,
+        position: crate::types::Vec3,
+        orientation: [f32; 4],
+    ) -> Result<Self, PhysicsError> {
+        let mesh = ObjMesh::load(path)?;
+        let rotation = Quat::from_array(orientation).normalize();
+        let vertices: Vec<crate::types::Vec3> = mesh
+            .vertices
+            .iter()
+            .map(|&vertex| (rotation * Vec3::from(vertex) + Vec3::from(position)).into())
+            .collect();
+        Ok(Self::new(&vertices, mesh.triangles))
+    }
+
+    /// Mesh vertices.
+    #[must_use]
+    pub fn vertices(&self) -> Vec<crate::types::Vec3> {
+        self.vertices.iter().map(|&vertex| vertex.into()).collect()
+    }
+
+    /// Mesh triangles as indices into [`Self::vertices`].
+    #[must_use]
+    pub fn triangles(&self) -> &[[u32; 3]] {
+        &self.triangles
+    }
+
+    /// Mass properties of the solid a closed mesh encloses, at uniform
+    /// `density`. Meaningless for open meshes.
+    #[must_use]
+    pub fn mass_properties(&self, density: f32) -> MassProperties {
+        MassProperties::from_triangles(&self.vertices(), &self.triangles, density)
+    }
+
+    /// Axis-aligned bounds of the mesh.
+    #[must_use]
+    pub fn bounds(&self) -> BoundingBox {
+        let (min, max) = self
+            .vertices
+            .iter()
+            .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), &vertex| (min.min(vertex), max.max(vertex)));
+        BoundingBox {
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+
+    /// Index and corners of every triangle whose bounds overlap
+    /// `[min, max]`.
+    pub(crate) fn triangles_in(&self, min: Vec3, max: Vec3) -> impl Iterator<Item = (usize, [Vec3; 3])> + '_ {
+        self.bvh
+            .query(min, max)
+            .into_iter()
+            .map(|index| (index, self.triangles[index].map(|corner| self.vertices[corner as usize])))
+    }
+}
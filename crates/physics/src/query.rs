@@ -0,0 +1,109 @@
+//! # Scene Queries
+//!
+//! [`crate::simulation::PhysicsSim`] answers questions about the current
+//! state of the world without stepping it: which body a [`Ray`] hits first,
+//! every body along it, where a sphere moved along a ray first touches, and
+//! which bodies lie in an axis-aligned box. A [`QueryFilter`] picks the
+//! bodies a query may report, by their collision groups or by handle.
+//!
+//! Rays that start inside a body hit it at distance zero with a normal that
+//! faces back along the ray. Heightfields and triangle meshes are one-sided,
+//! so rays only hit them from the front.
+
+use crate::body::BodyHandle;
+use crate::collision::CollisionFilter;
+use crate::types::Vec3;
+
+/// A half-line from `origin` along a unit `direction`, cut off after
+/// `max_distance`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
+    /// Start of the ray.
+    pub origin: Vec3,
+    /// Unit direction of the ray.
+    pub direction: Vec3,
+    /// Length of the ray. Bodies further along are not hit.
+    pub max_distance: f32,
+}
+
+impl Ray {
+    /// A ray from `origin` along `direction`, which is normalised. A zero
+    /// direction gives a ray that hits nothing.
+    #[must_use]
+    pub fn new(origin: Vec3, direction: Vec3, max_distance: f32) -> Self {
+        Self {
+            origin,
+            direction: glam::Vec3::from(direction).normalize_or_zero().into(),
+            max_distance,
+        }
+    }
+
+    /// The point `distance` along the ray.
+    #[must_use]
+    pub fn at(&self, distance: f32) -> Vec3 {
+        (glam::Vec3::from(self.origin) + glam::Vec3::from(self.direction) * distance).into()
+    }
+}
+
+/// Where a ray or a cast sphere first touches a body.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit {
+    /// The body that was hit.
+    pub body: BodyHandle,
+    /// Point on the body's surface.
+    pub point: Vec3,
+    /// Outward unit normal of the body's surface at `point`.
+    pub normal: Vec3,
+    /// Distance along the ray to the hit. For sphere casts this is how far
+    /// the sphere's centre travelled.
+    pub distance: f32,
+}
+
+/// Which bodies a query may report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryFilter {
+    /// Only bodies whose collision group shares a bit with this mask are
+    /// reported.
+    pub mask: u32,
+    /// Bodies that are never reported, such as the body a sensor is
+    /// mounted on.
+    pub exclude: Vec<BodyHandle>,
+}
+
+impl QueryFilter {
+    /// A filter that reports every body.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            mask: u32::MAX,
+            exclude: Vec::new(),
+        }
+    }
+
+    /// Only report bodies whose collision group shares a bit with `mask`.
+    #[must_use]
+    pub const fn with_mask(mut self, mask: u32) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    /// Never report `body`.
+    #[must_use]
+    pub fn excluding(mut self, body: BodyHandle) -> Self {
+        self.exclude.push(body);
+        self
+    }
+
+    /// Whether a body with handle `body` and collision filter `filter` may
+    /// be reported.
+    #[must_use]
+    pub fn accepts(&self, body: BodyHandle, filter: CollisionFilter) -> bool {
+        filter.group & self.mask != 0 && !self.exclude.contains(&body)
+    }
+}
+
+impl Default for QueryFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,187 @@
+//! # Removing Bodies and Joints
+//!
+//! Bodies and joints are addressed by their index in the shape and joint
+//! vectors of [`PhysicsSim`], and joints, [`crate::CartPole`] and user code
+//! hold on to those indices. Removal therefore leaves the vectors as they
+//! are and marks the entry as removed: every other handle keeps pointing at
+//! the same body or joint. The next body of the same kind takes the lowest
+//! removed slot before the vector grows, so a handle kept past the removal
+//! of its body may later name a new body. Removed joints are never reused.
+//!
+//! The shape vectors still hold the removed bodies until their slot is
+//! taken; [`PhysicsSim::live`] skips them, for renderers and other code
+//! that walks the vectors directly.
+//!
+//! A removed body no longer moves, collides, shows up in scene queries or
+//! islands, or joins the GPU sphere buffers. Removing a body also removes
+//! every joint and planar constraint attached to it, and forgets its
+//! contacts, applied forces, sleep state and collision settings.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::body::BodyHandle;
+use crate::simulation::PhysicsSim;
+use crate::solver::links;
+use crate::types::Vec3;
+
+/// Names a joint or constraint by its kind and its index in the matching
+/// joint vector of [`PhysicsSim`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum JointHandle {
+    /// Index into `PhysicsSim::joints`.
+    Distance(usize),
+    /// Index into `PhysicsSim::revolute_joints`.
+    Revolute(usize),
+    /// Index into `PhysicsSim::prismatic_joints`.
+    Prismatic(usize),
+    /// Index into `PhysicsSim::ball_joints`.
+    Ball(usize),
+    /// Index into `PhysicsSim::fixed_joints`.
+    Fixed(usize),
+    /// Index into `PhysicsSim::planar_constraints`.
+    Planar(usize),
+}
+
+impl PhysicsSim {
+    /// Remove the body behind `handle`, with every joint and planar
+    /// constraint attached to it. Bodies that rested on it or were jointed
+    /// to it wake up. Other handles stay valid. Removing a body twice, one
+    /// that never existed, or a link of an articulation, does nothing.
+    pub fn remove_body(&mut self, handle: BodyHandle) {
+        if !self.has_body(handle) || self.is_link(handle) {
+            return;
+        }
+        let mut neighbours: Vec<BodyHandle> = self.touching(handle).collect();
+        neighbours.extend(links(self).into_iter().filter_map(|(a, b, _)| {
+            if a == handle {
+                Some(b)
+            } else if b == handle {
+                Some(a)
+            } else {
+                None
+            }
+        }));
+        for neighbour in neighbours {
+            self.wake(neighbour);
+        }
+        for joint in self.attached_joints(handle) {
+            self.remove_joint(joint);
+        }
+        self.set_body_velocity(handle, Vec3::ZERO, Vec3::ZERO);
+        self.removed_bodies.insert(handle);
+
+        self.manifolds.retain(|&(a, b, _, _), _| a != handle && b != handle);
+        self.touching.retain(|&(a, b)| a != handle && b != handle);
+        self.excluded_pairs.retain(|&(a, b)| a != handle && b != handle);
+        self.collision_filters.remove(&handle);
+        self.ccd_bodies.remove(&handle);
+        self.applied_loads.remove(&handle);
+        self.sleeping.remove(&handle);
+        self.rest_time.remove(&handle);
+    }
+
+    /// Remove the joint or planar constraint behind `joint`. Its bodies stay
+    /// and move freely from the next step on.
+    pub fn remove_joint(&mut self, joint: JointHandle) {
+        if self.has_joint(joint) {
+            self.removed_joints.insert(joint);
+        }
+    }
+
+    /// Whether `handle` names a body that was removed with
+    /// [`Self::remove_body`].
+    #[must_use]
+    pub fn is_removed(&self, handle: BodyHandle) -> bool {
+        self.removed_bodies.contains(&handle)
+    }
+
+    /// The entries of `bodies`, one of the shape vectors of `self`, that
+    /// were not removed. `kind` builds the handle of an index, such as
+    /// `BodyHandle::Sphere`.
+    ///
+    /// ```
+    /// # use physics::{BodyHandle, PhysicsSim, Vec3};
+    /// let mut sim = PhysicsSim::new();
+    /// sim.add_sphere(Vec3::ZERO, Vec3::ZERO, 0.5);
+    /// let gone = sim.add_sphere(Vec3::new(2.0, 0.0, 0.0), Vec3::ZERO, 0.5);
+    /// sim.remove_body(BodyHandle::Sphere(gone));
+    /// assert_eq!(sim.live(&sim.spheres, BodyHandle::Sphere).count(), 1);
+    /// ```
+    pub fn live<'a, T>(&'a self, bodies: &'a [T], kind: fn(usize) -> BodyHandle) -> impl Iterator<Item = &'a T> + 'a {
+        bodies.iter().enumerate().filter(move |&(i, _)| !self.is_removed(kind(i))).map(|(_, body)| body)
+    }
+
+    /// Whether `joint` names a joint that was removed, directly or with one
+    /// of its bodies.
+    #[must_use]
+    pub fn is_joint_removed(&self, joint: JointHandle) -> bool {
+        self.removed_joints.contains(&joint)
+    }
+
+    /// Whether `joint` refers to a joint that exists.
+    #[must_use]
+    pub fn has_joint(&self, joint: JointHandle) -> bool {
+        let (index, len) = match joint {
+            JointHandle::Distance(i) => (i, self.joints.len()),
+            JointHandle::Revolute(i) => (i, self.revolute_joints.len()),
+            JointHandle::Prismatic(i) => (i, self.prismatic_joints.len()),
+            JointHandle::Ball(i) => (i, self.ball_joints.len()),
+            JointHandle::Fixed(i) => (i, self.fixed_joints.len()),
+            JointHandle::Planar(i) => (i, self.planar_constraints.len()),
+        };
+        index < len && !self.is_joint_removed(joint)
+    }
+
+    /// Joints and planar constraints still attached to `body`.
+    fn attached_joints(&self, body: BodyHandle) -> Vec<JointHandle> {
+        let joins = |type_a: u32, a: u32, type_b: u32, b: u32| {
+            BodyHandle::from_type_code(type_a, a as usize) == Some(body)
+                || BodyHandle::from_type_code(type_b, b as usize) == Some(body)
+        };
+        let distance = self.joints.iter().enumerate().filter_map(|(i, j)| {
+            joins(BodyHandle::SPHERE_TYPE, j.body_a, BodyHandle::SPHERE_TYPE, j.body_b).then_some(JointHandle::Distance(i))
+        });
+        let revolute = self.revolute_joints.iter().enumerate().filter_map(|(i, j)| {
+            joins(j.body_a_type, j.body_a, j.body_b_type, j.body_b).then_some(JointHandle::Revolute(i))
+        });
+        let prismatic = self.prismatic_joints.iter().enumerate().filter_map(|(i, j)| {
+            joins(j.body_a_type, j.body_a, j.body_b_type, j.body_b).then_some(JointHandle::Prismatic(i))
+        });
+        let ball = self.ball_joints.iter().enumerate().filter_map(|(i, j)| {
+            joins(j.body_a_type, j.body_a, j.body_b_type, j.body_b).then_some(JointHandle::Ball(i))
+        });
+        let fixed = self.fixed_joints.iter().enumerate().filter_map(|(i, j)| {
+            joins(j.body_a_type, j.body_a, j.body_b_type, j.body_b).then_some(JointHandle::Fixed(i))
+        });
+        let planar = self
+            .planar_constraints
+            .iter()
+            .enumerate()
+            .filter_map(|(i, constraint)| (constraint.body == body).then_some(JointHandle::Planar(i)));
+        distance
+            .chain(revolute)
+            .chain(prismatic)
+            .chain(ball)
+            .chain(fixed)
+            .chain(planar)
+            .filter(|&joint| !self.is_joint_removed(joint))
+            .collect()
+    }
+}
+
+/// Store `body` in the lowest removed slot of `kind` in `bodies`, or append
+/// it, and return its index.
+pub(crate) fn insert_body<T>(removed: &mut BTreeSet<BodyHandle>, bodies: &mut Vec<T>, kind: fn(usize) -> BodyHandle, body: T) -> usize {
+    let slot = removed.range(kind(0)..=kind(usize::MAX)).next().copied();
+    if let Some(handle) = slot {
+        removed.remove(&handle);
+        let index = handle.index();
+        bodies[index] = body;
+        index
+    } else {
+        bodies.push(body);
+        bodies.len() - 1
+    }
+}
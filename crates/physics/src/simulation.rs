@@ -4,27 +4,44 @@
 //! execution methods. It coordinates between different subsystems like
 //! integration, collision detection, and constraint solving.
 
+use crate::articulation::Articulation;
+use crate::body::BodyHandle;
+use crate::compound::{Compound, CompoundChild};
+use crate::forces::{AppliedLoad, ForceMode};
+use crate::contacts::{contact_events, contact_forces, contact_reports, touching_pairs, ContactEvent};
+use crate::heightfield::Heightfield;
+use crate::mesh::{ConvexHull, HullGeometry, TriangleMesh};
+use crate::query::{QueryFilter, Ray, RayHit};
+use crate::removal::{insert_body, JointHandle};
+use crate::stepping::{AdaptiveStep, StepCount};
 use crate::types::{
-    BoundingBox, BoxBody, Cylinder, Joint, JointParams, RevoluteJoint,
-    PrismaticJoint, BallJoint, FixedJoint, PhysParams, Plane,
+    BoundingBox, BoxBody, BroadPhaseType, Capsule, Cylinder, Joint, JointParams, RevoluteJoint,
+    PrismaticJoint, BallJoint, FixedJoint, PlanarConstraint, PhysParams, Plane,
+    JointControl, JointState, SleepParams, SolverType, MOTOR_DISABLED, Integrator,
     Sphere, SpatialGrid, Vec3, Vec2, Material, PhysicsDebugInfo, SpatialGridDebugInfo,
-    ForceDebugInfo, VelocityDebugInfo, BodyType,
+    ForceDebugInfo, VelocityDebugInfo, BodyType, ContactModel, ContactParams, ContactDebugInfo,
 };
 use crate::collision::{
-    detect_sphere_sphere_collision, resolve_sphere_sphere_collision,
-    detect_sphere_plane_collision, resolve_sphere_plane_collision,
-    detect_sphere_box_collision, resolve_sphere_box_collision,
-    detect_sphere_cylinder_collision, resolve_sphere_cylinder_collision,
-    detect_box_plane_collision, resolve_box_plane_collision,
-    detect_cylinder_plane_collision, resolve_cylinder_plane_collision,
+    boxes_overlap, cast_ray, cast_sphere, generate_manifolds, pair_manifolds, plane_overlaps_box, primitive_bounding_box, spatial_grid_pairs,
+    time_of_impact, update_manifold, ordered_pair, BodyFrame, CollisionConfig, CollisionFilter, ContactManifold,
+    CastHit, ManifoldCache, PairFilter, Primitive, Shape, Sweep, SweepAndPrune,
 };
 use crate::integrator::{
-    integrate_spheres, integrate_boxes, integrate_cylinders,
-    apply_forces_to_spheres, apply_forces_to_boxes,
+    apply_gravity_to_spheres, apply_gravity_to_boxes, apply_gravity_to_cylinders, apply_gravity_to_capsules,
+    apply_gravity_to_hulls, apply_gravity_to_compounds,
+    integrate_sphere_positions, integrate_box_positions, integrate_cylinder_positions,
+    integrate_capsule_positions, integrate_hull_positions, integrate_compound_positions,
+    apply_forces_to_spheres, apply_forces_to_boxes, advance_body,
+};
+use crate::solver::{
+    build_islands, collision_free_pairs, links, prismatic_state, revolute_state, solve_positions, ContactSolver, Island, JointImpulses,
+    JointSolver, SoftContactSolver, SolverBodies, SolverBody, XpbdSolver,
 };
 use crate::gpu_executor::execute_gpu_step;
 use compute::ComputeBackend;
 use glam::Quat;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
 
 /// Physics simulation error types.
@@ -34,6 +51,19 @@ pub enum PhysicsError {
     BackendError(compute::ComputeError),
     /// Attempted to run simulation without any spheres
     NoSpheres,
+    /// A heightfield image could not be read or decoded
+    Image(image::ImageError),
+    /// A mesh file could not be read
+    Io(std::io::Error),
+    /// A Wavefront OBJ file is malformed
+    Obj {
+        /// One-based line number of the offending line.
+        line: usize,
+        /// What is wrong with it.
+        message: String,
+    },
+    /// A snapshot could not be encoded or decoded
+    Snapshot(String),
 }
 
 impl From<compute::ComputeError> for PhysicsError {
@@ -42,6 +72,18 @@ impl From<compute::ComputeError> for PhysicsError {
     }
 }
 
+impl From<image::ImageError> for PhysicsError {
+    fn from(err: image::ImageError) -> Self {
+        PhysicsError::Image(err)
+    }
+}
+
+impl From<std::io::Error> for PhysicsError {
+    fn from(err: std::io::Error) -> Self {
+        PhysicsError::Io(err)
+    }
+}
+
 /// Snapshot of sphere position after simulation run.
 #[derive(Clone, Copy, Debug)]
 pub struct SphereState {
@@ -49,30 +91,95 @@ pub struct SphereState {
 }
 
 /// Physics simulation orchestrator managing bodies, constraints, and spatial data.
+///
+/// Serializing it covers everything but the compute backend; see
+/// [`crate::snapshot`].
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PhysicsSim {
     // Dynamic rigid bodies
     pub spheres: Vec<Sphere>,
     pub boxes: Vec<BoxBody>,
     pub cylinders: Vec<Cylinder>,
-    
+    pub capsules: Vec<Capsule>,
+    pub hulls: Vec<ConvexHull>,
+    pub compounds: Vec<Compound>,
+
     // Static collision geometry
     pub planes: Vec<Plane>,
-    
+    pub heightfields: Vec<Heightfield>,
+    pub meshes: Vec<TriangleMesh>,
+
     // Simulation configuration
     pub params: PhysParams,
-    
+    pub integrator: Integrator,
+    pub adaptive_step: Option<AdaptiveStep>,
+    /// Length the next adaptive internal step starts from, zero before the
+    /// first one.
+    pub(crate) next_substep: f32,
+    pub(crate) step_count: StepCount,
+
+    // Forces and torques applied through the handle API
+    pub force_mode: ForceMode,
+    #[serde(with = "crate::snapshot::pairs")]
+    pub(crate) applied_loads: BTreeMap<BodyHandle, AppliedLoad>,
+
     // Physical constraints
     pub joints: Vec<Joint>,
     pub revolute_joints: Vec<RevoluteJoint>,
     pub prismatic_joints: Vec<PrismaticJoint>,
     pub ball_joints: Vec<BallJoint>,
     pub fixed_joints: Vec<FixedJoint>,
+    pub planar_constraints: Vec<PlanarConstraint>,
     pub joint_params: JointParams,
-    
-    // Spatial acceleration structure
+    pub(crate) joint_impulses: JointImpulses,
+
+    // Trees of bodies simulated in joint coordinates
+    pub articulations: Vec<Articulation>,
+    /// Articulation and link index of every link body.
+    #[serde(with = "crate::snapshot::pairs")]
+    pub(crate) articulation_links: BTreeMap<BodyHandle, (usize, usize)>,
+
+    // Bodies and joints taken out with `remove_body` and `remove_joint`
+    pub(crate) removed_bodies: BTreeSet<BodyHandle>,
+    pub(crate) removed_joints: BTreeSet<JointHandle>,
+
+    // Contact solver configuration and persistent contacts
+    pub solver: SolverType,
+    pub contact_params: ContactParams,
+    pub contact_model: ContactModel,
+    #[serde(with = "crate::snapshot::pairs")]
+    pub(crate) manifolds: ManifoldCache,
+    /// Body pairs touching at the end of the last step.
+    pub(crate) touching: BTreeSet<(BodyHandle, BodyHandle)>,
+    pub(crate) contact_events: Vec<ContactEvent>,
+
+    // Continuous collision detection
+    pub collision_config: CollisionConfig,
+    pub(crate) ccd_bodies: BTreeSet<BodyHandle>,
+
+    // Collision filtering
+    #[serde(with = "crate::snapshot::pairs")]
+    pub(crate) collision_filters: BTreeMap<BodyHandle, CollisionFilter>,
+    /// Pairs that never collide, smaller handle first.
+    pub(crate) excluded_pairs: BTreeSet<(BodyHandle, BodyHandle)>,
+
+    // Islands and sleeping
+    pub sleep_params: SleepParams,
+    /// Sleeping bodies and the frame each fell asleep in.
+    #[serde(with = "crate::snapshot::pairs")]
+    pub(crate) sleeping: BTreeMap<BodyHandle, BodyFrame>,
+    /// How long each awake dynamic body has been resting, and its frame at
+    /// the end of the last step.
+    #[serde(with = "crate::snapshot::pairs")]
+    pub(crate) rest_time: BTreeMap<BodyHandle, (f32, BodyFrame)>,
+
+    // Broad phase
+    pub broad_phase: BroadPhaseType,
     pub spatial_grid: SpatialGrid,
-    
+    pub(crate) sweep_and_prune: SweepAndPrune,
+
     // GPU computation backend
+    #[serde(skip, default = "compute::default_backend")]
     pub(crate) backend: Arc<dyn ComputeBackend>,
 }
 
@@ -86,22 +193,54 @@ impl PhysicsSim {
             spheres: Vec::new(),
             boxes: Vec::new(),
             cylinders: Vec::new(),
+            capsules: Vec::new(),
+            hulls: Vec::new(),
+            compounds: Vec::new(),
             planes: Vec::new(),
+            heightfields: Vec::new(),
+            meshes: Vec::new(),
             params: PhysParams {
                 gravity: Vec3::new(0.0, -9.81, 0.0),
                 dt: 0.01,
                 forces: Vec::new(),
             },
+            integrator: Integrator::default(),
+            adaptive_step: None,
+            next_substep: 0.0,
+            step_count: StepCount::default(),
+            force_mode: ForceMode::default(),
+            applied_loads: BTreeMap::new(),
             joints: Vec::new(),
             revolute_joints: Vec::new(),
             prismatic_joints: Vec::new(),
             ball_joints: Vec::new(),
             fixed_joints: Vec::new(),
+            planar_constraints: Vec::new(),
             joint_params: JointParams {
                 compliance: 0.0,
                 _pad: [0.0; 3],
             },
+            joint_impulses: JointImpulses::default(),
+            articulations: Vec::new(),
+            articulation_links: BTreeMap::new(),
+            removed_bodies: BTreeSet::new(),
+            removed_joints: BTreeSet::new(),
+            solver: SolverType::default(),
+            contact_params: ContactParams::default(),
+            contact_model: ContactModel::default(),
+            manifolds: ManifoldCache::new(),
+            touching: BTreeSet::new(),
+            contact_events: Vec::new(),
+            collision_config: CollisionConfig::default(),
+            ccd_bodies: BTreeSet::new(),
+            collision_filters: BTreeMap::new(),
+            excluded_pairs: BTreeSet::new(),
+            sleep_params: SleepParams::default(),
+            sleeping: BTreeMap::new(),
+            rest_time: BTreeMap::new(),
+            broad_phase: BroadPhaseType::default(),
             spatial_grid,
+            sweep_and_prune: SweepAndPrune::new(),
             backend: compute::default_backend(),
         }
     }
@@ -117,6 +256,10 @@ impl PhysicsSim {
     }
 
     /// Apply external force to specific body.
+    ///
+    /// `force` pushes along x and z, on both the sphere and the box with
+    /// index `body_index`. See [`Self::apply_force`] for forces in any
+    /// direction on any one body.
     pub fn set_force(&mut self, body_index: usize, force: [f32; 2]) {
         if self.is_valid_body_index(body_index) {
             self.params.forces[body_index] = force;
@@ -132,9 +275,117 @@ impl PhysicsSim {
         self.backend = backend;
     }
 
-    /// Configure spatial acceleration structure.
+    /// Opt `body` in or out of continuous collision detection. A CCD body
+    /// that would hit another body it is not yet in contact with during a
+    /// step stops at the first time of impact, `collision_config.contact_offset`
+    /// short of the surface, instead of passing through it. Only dynamic
+    /// bodies are swept.
+    pub fn set_ccd(&mut self, body: BodyHandle, enabled: bool) {
+        if enabled {
+            self.ccd_bodies.insert(body);
+        } else {
+            self.ccd_bodies.remove(&body);
+        }
+    }
+
+    /// Whether `body` uses continuous collision detection.
+    #[must_use]
+    pub fn ccd_enabled(&self, body: BodyHandle) -> bool {
+        self.ccd_bodies.contains(&body)
+    }
+
+    /// Set the collision group and mask of `body`. Two bodies collide only
+    /// if each one's group shares a bit with the other's mask. Bodies start
+    /// with [`CollisionFilter::DEFAULT`].
+    pub fn set_collision_filter(&mut self, body: BodyHandle, filter: CollisionFilter) {
+        if filter == CollisionFilter::DEFAULT {
+            self.collision_filters.remove(&body);
+        } else {
+            self.collision_filters.insert(body, filter);
+        }
+    }
+
+    /// Collision group and mask of `body`.
+    #[must_use]
+    pub fn collision_filter(&self, body: BodyHandle) -> CollisionFilter {
+        self.collision_filters.get(&body).copied().unwrap_or(CollisionFilter::DEFAULT)
+    }
+
+    /// Allow or forbid contacts between bodies `a` and `b`, whatever their
+    /// collision filters say. Contacts between bodies joined by a joint can
+    /// also be turned off with the joint's `disable_collision` flag.
+    pub fn set_pair_collision(&mut self, a: BodyHandle, b: BodyHandle, enabled: bool) {
+        if enabled {
+            self.excluded_pairs.remove(&ordered_pair(a, b));
+        } else {
+            self.excluded_pairs.insert(ordered_pair(a, b));
+        }
+    }
+
+    /// Whether bodies `a` and `b` may collide, taking collision filters,
+    /// excluded pairs and joints into account.
+    #[must_use]
+    pub fn can_collide(&self, a: BodyHandle, b: BodyHandle) -> bool {
+        self.pair_filter().allows(a, b)
+    }
+
+    /// Filter binding for the pairwise contact kernels, such as
+    /// [`compute::Kernel::DetectContactsSphere`], for a body buffer holding
+    /// `bodies` in order. Pairs of these bodies that may not collide are
+    /// listed as excluded index pairs.
+    #[must_use]
+    pub fn gpu_collision_filters(&self, bodies: &[BodyHandle]) -> Vec<[u32; 2]> {
+        let filter = self.pair_filter();
+        let index = |handle: BodyHandle| {
+            let position = bodies.iter().position(|&body| body == handle)?;
+            u32::try_from(position).ok()
+        };
+        let excluded: Vec<[u32; 2]> = filter
+            .excluded
+            .iter()
+            .filter_map(|&(a, b)| Some([index(a)?, index(b)?]))
+            .collect();
+        let filters: Vec<CollisionFilter> = bodies.iter().map(|&body| filter.filter(body)).collect();
+        compute::kernels::collision_filter::pack_filters(&filters, &excluded)
+    }
+
+    /// Collision filters, excluded pairs and collision-free joints, gathered
+    /// for one pass over candidate pairs.
+    fn pair_filter(&self) -> PairFilter<'_> {
+        let mut excluded = self.excluded_pairs.clone();
+        excluded.extend(collision_free_pairs(self).into_iter().map(|(a, b)| ordered_pair(a, b)));
+        PairFilter {
+            filters: &self.collision_filters,
+            excluded,
+        }
+    }
+
+    /// Whether `body` is asleep. Sleeping bodies hold still and skip
+    /// integration and contact generation until they are disturbed.
+    #[must_use]
+    pub fn is_sleeping(&self, body: BodyHandle) -> bool {
+        self.sleeping.contains_key(&body)
+    }
+
+    /// Wake `body`. The rest of its island wakes with it on the next step.
+    pub fn wake(&mut self, body: BodyHandle) {
+        self.sleeping.remove(&body);
+        self.rest_time.remove(&body);
+    }
+
+    /// Groups of dynamic bodies linked by the contacts of the last step or
+    /// by joints. Static and kinematic bodies belong to no island.
+    #[must_use]
+    pub fn islands(&self) -> Vec<Vec<BodyHandle>> {
+        build_islands(self).into_iter().map(|island| island.bodies).collect()
+    }
+
+    /// Switch the broad phase to a uniform grid with `cell_size` cells
+    /// covering `bounds`. Set [`Self::broad_phase`] back to
+    /// [`BroadPhaseType::SweepAndPrune`] to leave the grid.
     pub fn configure_spatial_grid(&mut self, cell_size: f32, bounds: BoundingBox) {
         self.spatial_grid = SpatialGrid::new(cell_size, bounds);
+        self.broad_phase = BroadPhaseType::SpatialGrid;
     }
 
     /// Get spatial grid statistics
@@ -146,6 +397,8 @@ impl PhysicsSim {
     /// Get debug information
     pub fn get_debug_info(&self) -> PhysicsDebugInfo {
         let grid_stats = self.spatial_grid.get_stats();
+        let num_sleeping = self.sleeping.len();
+        let num_dynamic = self.body_handles().into_iter().filter(|&handle| self.is_dynamic(handle)).count();
         
         PhysicsDebugInfo {
             num_spheres: self.spheres.len(),
@@ -155,6 +408,11 @@ impl PhysicsSim {
             num_joints: self.joints.len(),
             gravity: self.params.gravity,
             dt: self.params.dt,
+            substeps: self.step_count.accepted,
+            rejected_substeps: self.step_count.rejected,
+            num_awake: num_dynamic - num_sleeping,
+            num_sleeping,
+            contacts: self.contacts(),
             spatial_grid: SpatialGridDebugInfo {
                 cell_size: self.spatial_grid.cell_size,
                 bounds: self.spatial_grid.bounds,
@@ -189,19 +447,190 @@ impl PhysicsSim {
         Ok(())
     }
 
-    /// Execute single physics timestep on CPU.
+    /// Execute single physics timestep on CPU with the configured
+    /// [`SolverType`].
+    ///
+    /// With the impulse solver, velocities are integrated first, then joints
+    /// and contacts are solved on the new velocities, warm started from the
+    /// previous step. Positions are then advanced, and any joint drift or
+    /// remaining overlap is removed.
+    ///
+    /// Bodies opted into continuous collision detection with
+    /// [`Self::set_ccd`] are stopped at their first time of impact.
+    ///
+    /// With [`Self::adaptive_step`] set, the step is split into internal
+    /// steps of varying length that still add up to `params.dt`; see
+    /// [`crate::stepping`].
     pub fn step_cpu(&mut self) {
+        if let Some(adaptive) = self.adaptive_step {
+            self.step_adaptive(&adaptive);
+        } else {
+            self.step_fixed();
+            self.step_count = StepCount::default();
+        }
+    }
+
+    /// One step of `params.dt` with the configured solver.
+    pub(crate) fn step_fixed(&mut self) {
+        if let SolverType::Xpbd { substeps } = self.solver {
+            self.step_xpbd(substeps);
+            return;
+        }
         let timestep = self.params.dt;
         
-        // Apply forces and integrate velocities/positions
-        self.apply_forces_and_integrate(timestep);
+        self.wake_disturbed_bodies();
+        let initial = (self.integrator != Integrator::SemiImplicitEuler).then(|| SolverBodies::gather(self));
+        self.apply_forces_and_gravity(timestep);
+        self.hold_sleeping_bodies();
+        
+        self.update_contact_manifolds();
+        let islands = self.wake_touched_islands();
+        let linked = self.solve_articulations(timestep);
+        self.solve_velocity_constraints(timestep);
+        
+        let start = self.ccd_start_frames();
+        self.integrate_positions(timestep, initial.as_ref());
+        self.clamp_to_time_of_impact(&start);
         
         // CRITICAL: Enforce constraints AFTER integration to fix any drift
         self.solve_physical_constraints();
-        
-        self.update_spatial_acceleration_structure();
-        self.detect_and_resolve_all_collisions();
-        self.apply_2d_constraints();
+        self.solve_position_constraints();
+        self.finish_articulations(timestep, linked);
+        self.update_sleep(&islands, timestep);
+        self.update_contact_events();
+        self.finish_loads();
+    }
+
+    /// Persistent contact manifolds from the last CPU step, keyed by body pair.
+    pub fn contact_manifolds(&self) -> impl Iterator<Item = &ContactManifold> {
+        self.manifolds.values()
+    }
+
+    /// Touching contact points of the last CPU step, with the impulse each
+    /// one applied.
+    #[must_use]
+    pub fn contacts(&self) -> Vec<ContactDebugInfo> {
+        contact_reports(self.manifolds.values())
+    }
+
+    /// Pairs of bodies that began, kept or stopped touching during the last
+    /// CPU step, in pair order.
+    #[must_use]
+    pub fn contact_events(&self) -> &[ContactEvent] {
+        &self.contact_events
+    }
+
+    /// Bodies touching `body` at the end of the last CPU step.
+    pub fn touching(&self, body: BodyHandle) -> impl Iterator<Item = BodyHandle> + '_ {
+        self.touching.iter().filter_map(move |&(a, b)| {
+            if a == body {
+                Some(b)
+            } else if b == body {
+                Some(a)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Total contact force, friction included, that pushed on `body` during
+    /// the last CPU step.
+    #[must_use]
+    pub fn contact_force(&self, body: BodyHandle) -> Vec3 {
+        let touching = self.manifolds.values().filter(|m| m.body_a == body || m.body_b == body);
+        contact_forces(touching, self.params.dt).get(&body).copied().unwrap_or(Vec3::ZERO)
+    }
+
+    /// Total contact force on every body touched during the last CPU step.
+    #[must_use]
+    pub fn contact_forces(&self) -> BTreeMap<BodyHandle, Vec3> {
+        contact_forces(self.manifolds.values(), self.params.dt)
+    }
+
+    /// Record which pairs touch after a step and how that changed.
+    fn update_contact_events(&mut self) {
+        let touching = touching_pairs(self.manifolds.values());
+        self.contact_events = contact_events(&self.touching, &touching);
+        self.touching = touching;
+    }
+
+    /// Nearest body hit by `ray` among those `filter` accepts.
+    #[must_use]
+    pub fn raycast(&self, ray: &Ray, filter: &QueryFilter) -> Option<RayHit> {
+        self.ray_hits(&self.query_bodies(filter), ray)
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+
+    /// Every body hit by `ray` among those `filter` accepts, nearest first,
+    /// with the point where the ray first hits each one.
+    #[must_use]
+    pub fn raycast_all(&self, ray: &Ray, filter: &QueryFilter) -> Vec<RayHit> {
+        let mut hits: Vec<RayHit> = self.ray_hits(&self.query_bodies(filter), ray).collect();
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        hits
+    }
+
+    /// Nearest hit of each of `rays`, as [`Self::raycast`] would find it,
+    /// for sensors that cast many rays at once such as a lidar.
+    #[must_use]
+    pub fn raycast_batch(&self, rays: &[Ray], filter: &QueryFilter) -> Vec<Option<RayHit>> {
+        let bodies = self.query_bodies(filter);
+        rays.iter()
+            .map(|ray| self.ray_hits(&bodies, ray).min_by(|a, b| a.distance.total_cmp(&b.distance)))
+            .collect()
+    }
+
+    /// First body touched by a sphere of `radius` whose centre moves along
+    /// `ray`, among those `filter` accepts. The hit distance is how far the
+    /// centre travelled; a sphere that starts overlapping a body hits it at
+    /// distance zero.
+    #[must_use]
+    pub fn sphere_cast(&self, ray: &Ray, radius: f32, filter: &QueryFilter) -> Option<RayHit> {
+        let (origin, direction) = (glam::Vec3::from(ray.origin), glam::Vec3::from(ray.direction));
+        if direction.length_squared() <= f32::EPSILON {
+            return None;
+        }
+        self.query_bodies(filter)
+            .into_iter()
+            .filter_map(|body| {
+                let frame = self.body_frame(body);
+                let hit = cast_sphere(&self.primitive(body), frame, origin, direction, ray.max_distance, radius)?;
+                Some(ray_hit(body, hit))
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+
+    /// Bodies among those `filter` accepts whose bounding boxes overlap
+    /// `bounds`, in handle order. Planes count when part of `bounds` lies
+    /// behind them.
+    #[must_use]
+    pub fn overlap_aabb(&self, bounds: &BoundingBox, filter: &QueryFilter) -> Vec<BodyHandle> {
+        self.query_bodies(filter)
+            .into_iter()
+            .filter(|&body| match self.primitive(body) {
+                Primitive::Plane(plane) => plane_overlaps_box(plane, bounds),
+                primitive => primitive_bounding_box(&primitive, 0.0).is_some_and(|own| boxes_overlap(&own, bounds)),
+            })
+            .collect()
+    }
+
+    /// Bodies that `filter` lets a scene query report.
+    fn query_bodies(&self, filter: &QueryFilter) -> Vec<BodyHandle> {
+        self.body_handles()
+            .into_iter()
+            .filter(|&body| filter.accepts(body, self.collision_filter(body)))
+            .collect()
+    }
+
+    /// First hit of `ray` on each of `bodies` that it hits.
+    fn ray_hits<'a>(&'a self, bodies: &'a [BodyHandle], ray: &Ray) -> impl Iterator<Item = RayHit> + 'a {
+        let (origin, direction) = (glam::Vec3::from(ray.origin), glam::Vec3::from(ray.direction));
+        let max_distance = ray.max_distance;
+        let bodies = if direction.length_squared() <= f32::EPSILON { &[] } else { bodies };
+        bodies.iter().filter_map(move |&body| {
+            let hit = cast_ray(&self.primitive(body), origin, direction, max_distance)?;
+            Some(ray_hit(body, hit))
+        })
     }
 
     /// Run simulation for multiple steps (GPU)
@@ -231,125 +660,572 @@ impl PhysicsSim {
 
 // CPU simulation implementation
 impl PhysicsSim {
-    fn apply_forces_and_integrate(&mut self, timestep: f32) {
+    fn apply_forces_and_gravity(&mut self, timestep: f32) {
         apply_forces_to_spheres(&mut self.spheres, &self.params.forces, timestep);
         apply_forces_to_boxes(&mut self.boxes, &self.params.forces, timestep);
+        self.apply_loads(timestep);
         
-        integrate_spheres(&mut self.spheres, self.params.gravity, timestep);
-        integrate_boxes(&mut self.boxes, self.params.gravity, timestep);
-        integrate_cylinders(&mut self.cylinders, self.params.gravity, timestep);
+        apply_gravity_to_spheres(&mut self.spheres, self.params.gravity, timestep);
+        apply_gravity_to_boxes(&mut self.boxes, self.params.gravity, timestep);
+        apply_gravity_to_cylinders(&mut self.cylinders, self.params.gravity, timestep);
+        apply_gravity_to_capsules(&mut self.capsules, self.params.gravity, timestep);
+        apply_gravity_to_hulls(&mut self.hulls, self.params.gravity, timestep);
+        apply_gravity_to_compounds(&mut self.compounds, self.params.gravity, timestep);
     }
 
-    fn update_spatial_acceleration_structure(&mut self) {
-        self.spatial_grid.update(&self.spheres);
+    /// Move every body by its velocity over `timestep`. Dynamic bodies
+    /// follow [`Self::integrator`] from their state in `initial`, gathered
+    /// before the velocity update; without it they use semi-implicit Euler.
+    fn integrate_positions(&mut self, timestep: f32, initial: Option<&SolverBodies>) {
+        let advanced = initial.map(|initial| {
+            let mut bodies = SolverBodies::gather(self);
+            for (body, start) in bodies.bodies.iter_mut().zip(&initial.bodies) {
+                if body.is_dynamic() {
+                    advance_body(self.integrator, body, start, timestep);
+                }
+            }
+            bodies
+        });
+        integrate_sphere_positions(&mut self.spheres, timestep);
+        integrate_box_positions(&mut self.boxes, timestep);
+        integrate_cylinder_positions(&mut self.cylinders, timestep);
+        integrate_capsule_positions(&mut self.capsules, timestep);
+        integrate_hull_positions(&mut self.hulls, timestep);
+        integrate_compound_positions(&mut self.compounds, timestep);
+        if let Some(bodies) = advanced {
+            bodies.scatter_positions(self);
+            bodies.scatter_velocities(self);
+        }
     }
 
-    fn detect_and_resolve_all_collisions(&mut self) {
-        self.resolve_sphere_sphere_collisions();
-        self.resolve_sphere_static_collisions();
-        self.resolve_sphere_dynamic_collisions();
-    }
-    
-    fn resolve_sphere_sphere_collisions(&mut self) {
-        let sphere_count = self.spheres.len();
-        
-        for i in 0..sphere_count {
-            for j in (i + 1)..sphere_count {
-                self.check_and_resolve_sphere_pair(i, j);
+    /// Body pairs that may touch within the contact margin, with the lower
+    /// handle first.
+    fn find_candidate_pairs(&mut self) -> Vec<(BodyHandle, BodyHandle)> {
+        let margin = self.contact_params.contact_margin;
+        let proxies: Vec<_> = self
+            .body_handles()
+            .into_iter()
+            .filter_map(|handle| Some((handle, primitive_bounding_box(&self.primitive(handle), margin)?)))
+            .collect();
+
+        let mut pairs = match self.broad_phase {
+            BroadPhaseType::SweepAndPrune => self.sweep_and_prune.update(&proxies),
+            BroadPhaseType::SpatialGrid => spatial_grid_pairs(&mut self.spatial_grid, &proxies),
+        };
+        for (i, plane) in self.planes.iter().enumerate() {
+            if self.is_removed(BodyHandle::Plane(i)) {
+                continue;
             }
+            pairs.extend(
+                proxies
+                    .iter()
+                    .filter(|(handle, _)| !matches!(handle, BodyHandle::Heightfield(_) | BodyHandle::Mesh(_)))
+                    .filter(|(_, bounds)| plane_overlaps_box(plane, bounds))
+                    .map(|&(handle, _)| (handle, BodyHandle::Plane(i))),
+            );
         }
+        let filter = self.pair_filter();
+        pairs.retain(|&(a, b)| filter.allows(a, b));
+        pairs
     }
-    
-    fn check_and_resolve_sphere_pair(&mut self, index_a: usize, index_b: usize) {
-        let (first_part, second_part) = self.spheres.split_at_mut(index_b);
-        let sphere_a = &mut first_part[index_a];
-        let sphere_b = &mut second_part[0];
-        
-        if let Some(contact) = detect_sphere_sphere_collision(sphere_a, sphere_b) {
-            resolve_sphere_sphere_collision(sphere_a, sphere_b, &contact);
+
+    /// Every body in the simulation that was not removed, in handle order.
+    pub(crate) fn body_handles(&self) -> Vec<BodyHandle> {
+        (0..self.spheres.len()).map(BodyHandle::Sphere)
+            .chain((0..self.boxes.len()).map(BodyHandle::Box))
+            .chain((0..self.cylinders.len()).map(BodyHandle::Cylinder))
+            .chain((0..self.capsules.len()).map(BodyHandle::Capsule))
+            .chain((0..self.hulls.len()).map(BodyHandle::Hull))
+            .chain((0..self.compounds.len()).map(BodyHandle::Compound))
+            .chain((0..self.planes.len()).map(BodyHandle::Plane))
+            .chain((0..self.heightfields.len()).map(BodyHandle::Heightfield))
+            .chain((0..self.meshes.len()).map(BodyHandle::Mesh))
+            .filter(|handle| !self.is_removed(*handle))
+            .collect()
+    }
+
+    /// Shape of the body behind `handle`.
+    pub(crate) fn primitive(&self, handle: BodyHandle) -> Primitive<'_> {
+        match handle {
+            BodyHandle::Sphere(i) => Primitive::Sphere(&self.spheres[i]),
+            BodyHandle::Box(i) => Primitive::Box(&self.boxes[i]),
+            BodyHandle::Cylinder(i) => Primitive::Cylinder(&self.cylinders[i]),
+            BodyHandle::Capsule(i) => Primitive::Capsule(&self.capsules[i]),
+            BodyHandle::Hull(i) => Primitive::Hull(&self.hulls[i]),
+            BodyHandle::Compound(i) => Primitive::Compound(&self.compounds[i]),
+            BodyHandle::Plane(i) => Primitive::Plane(&self.planes[i]),
+            BodyHandle::Heightfield(i) => Primitive::Heightfield(&self.heightfields[i]),
+            BodyHandle::Mesh(i) => Primitive::Mesh(&self.meshes[i]),
         }
     }
-    
-    fn resolve_sphere_static_collisions(&mut self) {
-        // Sphere-plane collisions
-        for sphere in &mut self.spheres {
-            for plane in &self.planes {
-                if let Some(contact) = detect_sphere_plane_collision(sphere, plane) {
-                    resolve_sphere_plane_collision(sphere, plane, &contact);
-                }
-            }
+
+    /// Whether `handle` refers to a body that exists and was not removed.
+    pub(crate) fn has_body(&self, handle: BodyHandle) -> bool {
+        let exists = match handle {
+            BodyHandle::Sphere(i) => i < self.spheres.len(),
+            BodyHandle::Box(i) => i < self.boxes.len(),
+            BodyHandle::Cylinder(i) => i < self.cylinders.len(),
+            BodyHandle::Plane(i) => i < self.planes.len(),
+            BodyHandle::Capsule(i) => i < self.capsules.len(),
+            BodyHandle::Hull(i) => i < self.hulls.len(),
+            BodyHandle::Compound(i) => i < self.compounds.len(),
+            BodyHandle::Heightfield(i) => i < self.heightfields.len(),
+            BodyHandle::Mesh(i) => i < self.meshes.len(),
+        };
+        exists && !self.is_removed(handle)
+    }
+
+    /// Whether the body behind `handle` responds to contact impulses.
+    /// Articulation links do, but through their joints, so the rigid-body
+    /// solver treats them as kinematic.
+    pub(crate) fn is_dynamic(&self, handle: BodyHandle) -> bool {
+        if self.is_link(handle) {
+            return false;
+        }
+        match handle {
+            BodyHandle::Sphere(_) => true,
+            BodyHandle::Box(i) => self.boxes[i].body_type == BodyType::Dynamic,
+            BodyHandle::Cylinder(i) => self.cylinders[i].body_type == BodyType::Dynamic,
+            BodyHandle::Capsule(i) => self.capsules[i].body_type == BodyType::Dynamic,
+            BodyHandle::Hull(i) => self.hulls[i].body_type == BodyType::Dynamic,
+            BodyHandle::Compound(i) => self.compounds[i].body_type == BodyType::Dynamic,
+            BodyHandle::Plane(_) | BodyHandle::Heightfield(_) | BodyHandle::Mesh(_) => false,
+        }
+    }
+
+    /// Reference frame of the body behind `handle`. Planes, heightfields and
+    /// meshes use the world frame.
+    pub(crate) fn body_frame(&self, handle: BodyHandle) -> BodyFrame {
+        let (position, orientation) = self.body_pose(handle);
+        BodyFrame {
+            position: position.into(),
+            orientation: crate::collision::body_rotation(orientation),
+        }
+    }
+
+    /// Position and raw orientation quaternion of the body behind `handle`.
+    /// Planes, heightfields and meshes sit at the origin.
+    pub(crate) fn body_pose(&self, handle: BodyHandle) -> (Vec3, [f32; 4]) {
+        match handle {
+            BodyHandle::Sphere(i) => (self.spheres[i].pos, self.spheres[i].orientation),
+            BodyHandle::Box(i) => (self.boxes[i].pos, self.boxes[i].orientation),
+            BodyHandle::Cylinder(i) => (self.cylinders[i].pos, self.cylinders[i].orientation),
+            BodyHandle::Capsule(i) => (self.capsules[i].pos, self.capsules[i].orientation),
+            BodyHandle::Hull(i) => (self.hulls[i].pos, self.hulls[i].orientation),
+            BodyHandle::Compound(i) => (self.compounds[i].pos, self.compounds[i].orientation),
+            BodyHandle::Plane(_) | BodyHandle::Heightfield(_) | BodyHandle::Mesh(_) => (Vec3::ZERO, [0.0, 0.0, 0.0, 1.0]),
         }
+    }
+
+    /// Regenerate contact manifolds for every touching pair found by the
+    /// broad phase, carrying impulses and friction anchors over from the
+    /// previous step.
+    fn update_contact_manifolds(&mut self) {
+        let margin = self.contact_params.contact_margin;
+        let warm_starting = self.contact_params.warm_starting;
+        let mut manifolds = ManifoldCache::new();
         
-        // Box-plane collisions
-        for box_body in &mut self.boxes {
-            for plane in &self.planes {
-                if let Some(contact) = detect_box_plane_collision(box_body, plane) {
-                    resolve_box_plane_collision(box_body, plane, &contact, self.params.dt);
+        for (handle_a, handle_b) in self.find_candidate_pairs() {
+            let linked = self.is_link(handle_a) || self.is_link(handle_b);
+            if !linked && !self.is_dynamic(handle_a) && !self.is_dynamic(handle_b) {
+                continue;
+            }
+            if !linked && !self.is_awake(handle_a) && !self.is_awake(handle_b) {
+                // Neither body has moved, so last step's contacts still hold
+                for (&key, manifold) in pair_manifolds(&self.manifolds, (handle_a, handle_b)) {
+                    manifolds.insert(key, manifold.clone());
                 }
+                continue;
+            }
+            let primitive_a = self.primitive(handle_a);
+            let primitive_b = self.primitive(handle_b);
+            for ((child_a, child_b), fresh) in generate_manifolds(&primitive_a, &primitive_b, margin) {
+                let key = (handle_a, handle_b, child_a, child_b);
+                let manifold = update_manifold(
+                    key,
+                    (self.body_frame(handle_a), self.body_frame(handle_b)),
+                    &fresh,
+                    (primitive_a.as_collider().material(), primitive_b.as_collider().material()),
+                    self.manifolds.get(&key),
+                    warm_starting,
+                );
+                manifolds.insert(key, manifold);
             }
         }
         
-        // Cylinder-plane collisions
-        for cylinder in &mut self.cylinders {
-            for plane in &self.planes {
-                if let Some(contact) = detect_cylinder_plane_collision(cylinder, plane) {
-                    resolve_cylinder_plane_collision(cylinder, plane, &contact, self.params.dt);
+        self.manifolds = manifolds;
+    }
+
+    /// One step of the XPBD solver, split into `substeps` substeps.
+    fn step_xpbd(&mut self, substeps: usize) {
+        let timestep = self.params.dt;
+        let substep = timestep / substeps.max(1) as f32;
+
+        self.wake_disturbed_bodies();
+        self.update_contact_manifolds();
+        let islands = self.wake_touched_islands();
+        let linked = self.solve_articulations(timestep);
+
+        // Reuse the force and gravity rules of the impulse path: the velocity
+        // change they produce over one substep is added in every substep.
+        let mut bodies = SolverBodies::gather(self);
+        self.apply_forces_and_gravity(substep);
+        self.hold_sleeping_bodies();
+        let external: Vec<_> = SolverBodies::gather(self)
+            .bodies
+            .iter()
+            .zip(&bodies.bodies)
+            .map(|(pushed, body)| (pushed.linear_velocity - body.linear_velocity, pushed.angular_velocity - body.angular_velocity))
+            .collect();
+
+        let start = self.ccd_start_frames();
+        let mut solver = XpbdSolver::prepare(self, &bodies);
+        for _ in 0..substeps.max(1) {
+            solver.substep(&mut bodies, &external, substep);
+        }
+        solver.store_impulses(&mut self.manifolds);
+
+        // Kinematic bodies follow their velocity; dynamic ones are
+        // overwritten with the solver result.
+        self.integrate_positions(timestep, None);
+        bodies.scatter_positions(self);
+        bodies.scatter_velocities(self);
+        self.clamp_to_time_of_impact(&start);
+        self.solve_physical_constraints();
+        self.finish_articulations(timestep, linked);
+        self.update_sleep(&islands, timestep);
+        self.update_contact_events();
+        self.finish_loads();
+    }
+
+    /// Whether the body behind `handle` is dynamic and not asleep.
+    pub(crate) fn is_awake(&self, handle: BodyHandle) -> bool {
+        self.is_dynamic(handle) && !self.sleeping.contains_key(&handle)
+    }
+
+    /// Inverse mass and the diagonal of the inverse inertia tensor, in the
+    /// body frame, of the body behind `handle`. Both are zero for bodies
+    /// that are not dynamic.
+    #[must_use]
+    pub fn inverse_mass(&self, handle: BodyHandle) -> (f32, Vec3) {
+        let body = SolverBody::of(self, handle);
+        (body.inv_mass, body.inv_inertia.into())
+    }
+
+    /// Linear and angular velocity of the body behind `handle`.
+    pub(crate) fn body_velocity(&self, handle: BodyHandle) -> (Vec3, Vec3) {
+        match handle {
+            BodyHandle::Sphere(i) => (self.spheres[i].vel, self.spheres[i].angular_vel),
+            BodyHandle::Box(i) => (self.boxes[i].vel, self.boxes[i].angular_vel),
+            BodyHandle::Cylinder(i) => (self.cylinders[i].vel, self.cylinders[i].angular_vel),
+            BodyHandle::Capsule(i) => (self.capsules[i].vel, self.capsules[i].angular_vel),
+            BodyHandle::Hull(i) => (self.hulls[i].vel, self.hulls[i].angular_vel),
+            BodyHandle::Compound(i) => (self.compounds[i].vel, self.compounds[i].angular_vel),
+            BodyHandle::Plane(_) | BodyHandle::Heightfield(_) | BodyHandle::Mesh(_) => (Vec3::ZERO, Vec3::ZERO),
+        }
+    }
+
+    /// Wake sleeping bodies that were moved, given a velocity or pushed by
+    /// an applied force since they fell asleep. Everything wakes when
+    /// sleeping is disabled.
+    fn wake_disturbed_bodies(&mut self) {
+        if !self.sleep_params.enabled {
+            self.sleeping.clear();
+            self.rest_time.clear();
+            return;
+        }
+        let disturbed: Vec<BodyHandle> = self
+            .sleeping
+            .iter()
+            .filter(|&(&handle, frame)| {
+                let current = self.body_frame(handle);
+                let (velocity, angular_velocity) = self.body_velocity(handle);
+                let force = match handle {
+                    BodyHandle::Sphere(i) | BodyHandle::Box(i) => self.params.forces.get(i).copied(),
+                    _ => None,
+                };
+                current.position != frame.position
+                    || current.orientation != frame.orientation
+                    || velocity != Vec3::ZERO
+                    || angular_velocity != Vec3::ZERO
+                    || force.is_some_and(|[x, z]| x != 0.0 || z != 0.0)
+                    || self.applied_loads.get(&handle).is_some_and(|load| !load.is_zero())
+            })
+            .map(|(&handle, _)| handle)
+            .collect();
+        for handle in disturbed {
+            self.wake(handle);
+        }
+    }
+
+    /// Undo the gravity and forces just applied to sleeping and removed
+    /// bodies.
+    fn hold_sleeping_bodies(&mut self) {
+        for &handle in self.sleeping.keys().chain(&self.removed_bodies) {
+            match handle {
+                BodyHandle::Sphere(i) => (self.spheres[i].vel, self.spheres[i].angular_vel) = (Vec3::ZERO, Vec3::ZERO),
+                BodyHandle::Box(i) => (self.boxes[i].vel, self.boxes[i].angular_vel) = (Vec3::ZERO, Vec3::ZERO),
+                BodyHandle::Cylinder(i) => {
+                    (self.cylinders[i].vel, self.cylinders[i].angular_vel) = (Vec3::ZERO, Vec3::ZERO);
+                }
+                BodyHandle::Capsule(i) => {
+                    (self.capsules[i].vel, self.capsules[i].angular_vel) = (Vec3::ZERO, Vec3::ZERO);
                 }
+                BodyHandle::Hull(i) => (self.hulls[i].vel, self.hulls[i].angular_vel) = (Vec3::ZERO, Vec3::ZERO),
+                BodyHandle::Compound(i) => {
+                    (self.compounds[i].vel, self.compounds[i].angular_vel) = (Vec3::ZERO, Vec3::ZERO);
+                }
+                BodyHandle::Plane(_) | BodyHandle::Heightfield(_) | BodyHandle::Mesh(_) => {}
             }
         }
     }
-    
-    fn resolve_sphere_dynamic_collisions(&mut self) {
-        for sphere in &mut self.spheres {
-            for box_body in &mut self.boxes {
-                if let Some(contact) = detect_sphere_box_collision(sphere, box_body) {
-                    resolve_sphere_box_collision(sphere, box_body, &contact);
+
+    /// Wake every island that an awake body or a moving kinematic body
+    /// touches, and return the islands of this step.
+    fn wake_touched_islands(&mut self) -> Vec<Island> {
+        if self.sleeping.is_empty() {
+            return build_islands(self);
+        }
+        let moving_kinematic = |sim: &Self, handle: BodyHandle| {
+            let (velocity, angular_velocity) = sim.body_velocity(handle);
+            !sim.is_dynamic(handle) && (velocity != Vec3::ZERO || angular_velocity != Vec3::ZERO)
+        };
+        let pushed: Vec<BodyHandle> = links(self)
+            .into_iter()
+            .filter_map(|(a, b, _)| {
+                if moving_kinematic(self, a) {
+                    Some(b)
+                } else if moving_kinematic(self, b) {
+                    Some(a)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        for handle in pushed {
+            self.wake(handle);
+        }
+
+        let islands = build_islands(self);
+        for island in &islands {
+            if island.bodies.iter().any(|handle| !self.sleeping.contains_key(handle)) {
+                for handle in &island.bodies {
+                    self.sleeping.remove(handle);
                 }
             }
-            
-            for cylinder in &mut self.cylinders {
-                if let Some(contact) = detect_sphere_cylinder_collision(sphere, cylinder) {
-                    resolve_sphere_cylinder_collision(sphere, cylinder, &contact);
+        }
+        islands
+    }
+
+    /// Advance the rest timers of awake bodies and put every island whose
+    /// bodies have all rested for `sleep_params.time_to_sleep` to sleep.
+    fn update_sleep(&mut self, islands: &[Island], timestep: f32) {
+        if !self.sleep_params.enabled {
+            return;
+        }
+        let linear = self.sleep_params.linear_threshold;
+        let angular = self.sleep_params.angular_threshold;
+        for island in islands {
+            if island.bodies.iter().all(|handle| self.sleeping.contains_key(handle)) {
+                continue;
+            }
+            let mut resting = !island.driven;
+            for &handle in &island.bodies {
+                // Position corrections can move a body whose velocity the
+                // solver has zeroed, so the motion over the step counts too
+                let frame = self.body_frame(handle);
+                let (velocity, angular_velocity) = self.body_velocity(handle);
+                let (time, previous) = self.rest_time.get(&handle).copied().unwrap_or((0.0, frame));
+                let speed = velocity.length().max(frame.position.distance(previous.position) / timestep);
+                let spin = angular_velocity.length().max(frame.orientation.angle_between(previous.orientation) / timestep);
+                let time = if speed < linear && spin < angular { time + timestep } else { 0.0 };
+                self.rest_time.insert(handle, (time, frame));
+                resting &= time >= self.sleep_params.time_to_sleep;
+            }
+            if resting {
+                for &handle in &island.bodies {
+                    self.rest_time.remove(&handle);
+                    self.sleeping.insert(handle, self.body_frame(handle));
                 }
             }
         }
+        self.hold_sleeping_bodies();
     }
 
-    fn solve_physical_constraints(&mut self) {
-        self.solve_distance_joint_constraints();
-        self.solve_revolute_joint_constraints();
-        // Future: Add prismatic and other constraint types
+    /// Frames of every body at the start of the motion that continuous
+    /// collision detection sweeps, or nothing if no body uses it.
+    fn ccd_start_frames(&self) -> Vec<BodyFrame> {
+        if self.ccd_bodies.is_empty() {
+            return Vec::new();
+        }
+        self.body_handles().into_iter().map(|handle| self.body_frame(handle)).collect()
     }
-    
-    fn apply_2d_constraints(&mut self) {
-        // Constrain boxes that are part of revolute joints to X-Y plane
-        for joint in &self.revolute_joints {
-            let box_idx = joint.body_a as usize;
-            let cylinder_idx = joint.body_b as usize;
-            
-            // Constrain box to 2D plane if it's part of a revolute joint
-            if box_idx < self.boxes.len() {
-                self.boxes[box_idx].pos.z = 0.0;
-                self.boxes[box_idx].vel.z = 0.0;
-                // Constrain rotation to Z-axis only
-                self.boxes[box_idx].angular_vel.x = 0.0;
-                self.boxes[box_idx].angular_vel.y = 0.0;
+
+    /// Move every CCD body back along its motion since `start` to the first
+    /// time it comes within the contact offset of a body it had no contact
+    /// with. Velocities are kept, so the next step meets the pair as a
+    /// speculative contact.
+    fn clamp_to_time_of_impact(&mut self, start: &[BodyFrame]) {
+        if start.is_empty() {
+            return;
+        }
+        let handles = self.body_handles();
+        let sweeps: Vec<Sweep> = handles
+            .iter()
+            .zip(start)
+            .map(|(&handle, &start)| Sweep {
+                start,
+                end: self.body_frame(handle),
+            })
+            .collect();
+        let shapes: Vec<Shape<'_>> = handles.iter().map(|&handle| Shape::of(&self.primitive(handle))).collect();
+        let offset = self.collision_config.contact_offset;
+        let filter = self.pair_filter();
+
+        let mut impacts = Vec::new();
+        for (i, &handle) in handles.iter().enumerate() {
+            if !self.ccd_bodies.contains(&handle) || !self.is_dynamic(handle) {
+                continue;
             }
-            
-            // Constrain cylinder to 2D plane
-            if cylinder_idx < self.cylinders.len() {
-                self.cylinders[cylinder_idx].pos.z = 0.0;
-                self.cylinders[cylinder_idx].vel.z = 0.0;
-                // Keep only Z-axis rotation
-                self.cylinders[cylinder_idx].angular_vel.x = 0.0;
-                self.cylinders[cylinder_idx].angular_vel.y = 0.0;
+            let first = (0..handles.len())
+                .filter(|&j| j != i && filter.allows(handle, handles[j]))
+                .filter_map(|j| {
+                    // Keep the narrow-phase order of the pair
+                    let (a, b) = if i < j { (i, j) } else { (j, i) };
+                    if pair_manifolds(&self.manifolds, (handles[a], handles[b])).next().is_some() {
+                        return None;
+                    }
+                    time_of_impact(&shapes[a], &sweeps[a], &shapes[b], &sweeps[b], offset)
+                })
+                .fold(1.0_f32, f32::min);
+            if first < 1.0 {
+                impacts.push((handle, sweeps[i].at(first)));
+            }
+        }
+        for (handle, frame) in impacts {
+            self.set_body_frame(handle, frame);
+        }
+    }
+
+    /// Move the body behind `handle` to `frame`. Planes, heightfields and
+    /// meshes do not move.
+    pub(crate) fn set_body_frame(&mut self, handle: BodyHandle, frame: BodyFrame) {
+        let position = frame.position.into();
+        let orientation = frame.orientation.to_array();
+        match handle {
+            BodyHandle::Sphere(i) => (self.spheres[i].pos, self.spheres[i].orientation) = (position, orientation),
+            BodyHandle::Box(i) => (self.boxes[i].pos, self.boxes[i].orientation) = (position, orientation),
+            BodyHandle::Cylinder(i) => {
+                (self.cylinders[i].pos, self.cylinders[i].orientation) = (position, orientation);
+            }
+            BodyHandle::Capsule(i) => {
+                (self.capsules[i].pos, self.capsules[i].orientation) = (position, orientation);
+            }
+            BodyHandle::Hull(i) => (self.hulls[i].pos, self.hulls[i].orientation) = (position, orientation),
+            BodyHandle::Compound(i) => {
+                (self.compounds[i].pos, self.compounds[i].orientation) = (position, orientation);
+            }
+            BodyHandle::Plane(_) | BodyHandle::Heightfield(_) | BodyHandle::Mesh(_) => {}
+        }
+    }
+
+    /// Set the linear and angular velocity of the body behind `handle`.
+    /// Planes, heightfields and meshes do not move.
+    pub(crate) fn set_body_velocity(&mut self, handle: BodyHandle, linear: Vec3, angular: Vec3) {
+        match handle {
+            BodyHandle::Sphere(i) => (self.spheres[i].vel, self.spheres[i].angular_vel) = (linear, angular),
+            BodyHandle::Box(i) => (self.boxes[i].vel, self.boxes[i].angular_vel) = (linear, angular),
+            BodyHandle::Cylinder(i) => (self.cylinders[i].vel, self.cylinders[i].angular_vel) = (linear, angular),
+            BodyHandle::Capsule(i) => (self.capsules[i].vel, self.capsules[i].angular_vel) = (linear, angular),
+            BodyHandle::Hull(i) => (self.hulls[i].vel, self.hulls[i].angular_vel) = (linear, angular),
+            BodyHandle::Compound(i) => (self.compounds[i].vel, self.compounds[i].angular_vel) = (linear, angular),
+            BodyHandle::Plane(_) | BodyHandle::Heightfield(_) | BodyHandle::Mesh(_) => {}
+        }
+    }
+
+    /// Forget everything carried between steps: contact manifolds and
+    /// events, warm-start impulses and sleep state.
+    pub(crate) fn clear_solver_state(&mut self) {
+        self.manifolds.clear();
+        self.touching.clear();
+        self.contact_events.clear();
+        self.joint_impulses = JointImpulses::default();
+        self.sleeping.clear();
+        self.rest_time.clear();
+    }
+
+    fn solve_velocity_constraints(&mut self, timestep: f32) {
+        if self.manifolds.is_empty() && !self.has_solver_joints() {
+            return;
+        }
+        let warm_starting = self.contact_params.warm_starting;
+        let mut bodies = SolverBodies::gather(self);
+        let mut joints = JointSolver::prepare(self, &bodies, &self.joint_impulses, warm_starting, timestep);
+        // Soft contacts replace the rigid contact rows with one implicit
+        // penalty impulse per point
+        let no_manifolds = ManifoldCache::new();
+        let rigid = match self.contact_model {
+            ContactModel::Hard => &self.manifolds,
+            ContactModel::Soft(model) => {
+                let mut soft = SoftContactSolver::prepare(self, &bodies, model);
+                soft.apply(&mut bodies, timestep);
+                soft.store_impulses(&mut self.manifolds);
+                &no_manifolds
+            }
+        };
+        let mut contacts = ContactSolver::prepare(rigid, &bodies, &self.contact_params, timestep);
+        if warm_starting {
+            joints.warm_start(&mut bodies);
+            contacts.warm_start(&mut bodies);
+        }
+        for iteration in 0..self.contact_params.velocity_iterations {
+            joints.solve_velocities(&mut bodies);
+            contacts.solve_velocities(&mut bodies, iteration % 2 == 1);
+            joints.solve_planar_velocities(&mut bodies);
+        }
+        contacts.apply_restitution(&mut bodies, self.contact_params.restitution_threshold);
+        joints.solve_planar_velocities(&mut bodies);
+        joints.store_impulses(&mut self.joint_impulses);
+        contacts.store_impulses(&mut self.manifolds);
+        bodies.scatter_velocities(self);
+    }
+
+    /// Whether any joint or planar constraint is handled by the impulse
+    /// solver rather than by [`Self::solve_physical_constraints`].
+    fn has_solver_joints(&self) -> bool {
+        !(self.revolute_joints.is_empty()
+            && self.prismatic_joints.is_empty()
+            && self.ball_joints.is_empty()
+            && self.fixed_joints.is_empty()
+            && self.planar_constraints.is_empty())
+    }
+
+    fn solve_position_constraints(&mut self) {
+        if self.manifolds.is_empty() && !self.has_solver_joints() {
+            return;
+        }
+        let mut bodies = SolverBodies::gather(self);
+        let joints = JointSolver::prepare(self, &bodies, &self.joint_impulses, false, self.params.dt);
+        for _ in 0..self.contact_params.position_iterations {
+            joints.solve_positions(&mut bodies);
+            if self.contact_model == ContactModel::Hard {
+                solve_positions(&self.manifolds, &mut bodies, &self.contact_params);
             }
         }
+        joints.project_planar_positions(&mut bodies);
+        bodies.scatter_positions(self);
+    }
+
+    fn solve_physical_constraints(&mut self) {
+        self.solve_distance_joint_constraints();
     }
     
     fn solve_distance_joint_constraints(&mut self) {
         let joints = self.joints.clone();
-        for joint in &joints {
+        for (index, joint) in joints.iter().enumerate() {
+            if self.is_joint_removed(JointHandle::Distance(index)) {
+                continue;
+            }
             let body_a_index = joint.body_a as usize;
             let body_b_index = joint.body_b as usize;
             
@@ -360,7 +1236,7 @@ impl PhysicsSim {
     }
     
     fn are_valid_sphere_indices(&self, index_a: usize, index_b: usize) -> bool {
-        index_a < self.spheres.len() && index_b < self.spheres.len()
+        self.has_body(BodyHandle::Sphere(index_a)) && self.has_body(BodyHandle::Sphere(index_b))
     }
     
     fn apply_distance_constraint(&mut self, body_a_index: usize, body_b_index: usize, rest_length: f32) {
@@ -371,107 +1247,15 @@ impl PhysicsSim {
             solve_distance_constraint_one_sided(sphere_a, sphere_b_position, sphere_b_mass, rest_length);
         }
     }
-    
-    fn solve_revolute_joint_constraints(&mut self) {
-        // For now, implement a simple version that maintains the anchor points together
-        // Full angular constraints will be added next
-        let joints = self.revolute_joints.clone();
-        
-        for joint in &joints {
-            let body_a_idx = joint.body_a as usize;
-            let body_b_idx = joint.body_b as usize;
-            
-            // For now, only support box-cylinder connections
-            if body_a_idx < self.boxes.len() && body_b_idx < self.cylinders.len() {
-                self.solve_revolute_constraint_box_cylinder(body_a_idx, body_b_idx, joint);
-            }
-        }
-    }
-    
-    fn solve_revolute_constraint_box_cylinder(
-        &mut self, 
-        box_idx: usize, 
-        cylinder_idx: usize, 
-        joint: &RevoluteJoint
-    ) {
-        // Get cart state
-        let cart_pos = self.boxes[box_idx].pos;
-        let cart_vel = self.boxes[box_idx].vel;
-        
-        // Get the joint position in world space (top of cart)
-        let joint_world_pos = cart_pos + joint.anchor_a;
-        
-        // Physics parameters
-        let gravity_magnitude = self.params.gravity.length();
-        let mass = self.cylinders[cylinder_idx].mass;
-        let pole_length = self.cylinders[cylinder_idx].half_height * 2.0;
-        let lever_arm = pole_length / 2.0; // Distance from joint to center of mass
-        
-        // Get current pole position and calculate angle from it
-        let pole_pos = self.cylinders[cylinder_idx].pos;
-        let pole_offset = pole_pos - joint_world_pos;
-        
-        // Calculate current angle from actual pole position
-        let current_angle = pole_offset.x.atan2(pole_offset.y);
-        
-        // 1. Gravity torque for INVERTED pendulum (pole standing UP)
-        let gravity_torque = mass * gravity_magnitude * lever_arm * current_angle.sin();
-        
-        // 2. Cart acceleration effect
-        let cart_acceleration = if self.boxes[box_idx].body_type == BodyType::Kinematic {
-            cart_vel.x * 5.0
-        } else {
-            0.0
-        };
-        let acceleration_torque = -mass * cart_acceleration * lever_arm * current_angle.cos();
-        
-        // 3. Total torque
-        let total_torque = gravity_torque + acceleration_torque;
-        
-        // 4. Moment of inertia for rod about end
-        let moment_of_inertia = (1.0 / 3.0) * mass * pole_length * pole_length;
-        
-        // 5. Angular acceleration
-        let angular_acceleration = total_torque / moment_of_inertia;
-        
-        // 6. Update angular velocity
-        self.cylinders[cylinder_idx].angular_vel.z += angular_acceleration * self.params.dt;
-        
-        // 7. Apply damping
-        self.cylinders[cylinder_idx].angular_vel.z *= 0.998;
-        
-        // 8. Update angle
-        let new_angle = current_angle + self.cylinders[cylinder_idx].angular_vel.z * self.params.dt;
-        
-        // 9. Update orientation quaternion for rendering
-        let half_angle = new_angle * 0.5;
-        self.cylinders[cylinder_idx].orientation = [
-            0.0,
-            0.0,
-            half_angle.sin(),
-            half_angle.cos(),
-        ];
-        
-        // 10. ENFORCE POSITION CONSTRAINT: pole must be attached at joint
-        // Calculate where pole center MUST be based on joint position and angle
-        let direction = Vec3::new(new_angle.sin(), new_angle.cos(), 0.0);
-        let constrained_pole_center = joint_world_pos + direction * lever_arm;
-        
-        // 11. Hard constraint: directly set pole position
-        self.cylinders[cylinder_idx].pos = constrained_pole_center;
-        
-        // 12. Calculate pole velocity consistently with constraint
-        // Velocity at pole center = cart velocity + rotational component
-        let angular_vel_z = self.cylinders[cylinder_idx].angular_vel.z;
-        let tangent = Vec3::new(new_angle.cos(), -new_angle.sin(), 0.0);
-        let rotation_velocity = tangent * (lever_arm * angular_vel_z);
-        self.cylinders[cylinder_idx].vel = cart_vel + rotation_velocity;
-        
-        // 13. Constrain to 2D plane
-        self.cylinders[cylinder_idx].pos.z = 0.0;
-        self.cylinders[cylinder_idx].vel.z = 0.0;
-        self.cylinders[cylinder_idx].angular_vel.x = 0.0;
-        self.cylinders[cylinder_idx].angular_vel.y = 0.0;
+}
+
+/// Scene query hit on `body` from a cast against it.
+fn ray_hit(body: BodyHandle, hit: CastHit) -> RayHit {
+    RayHit {
+        body,
+        point: hit.point.into(),
+        normal: hit.normal.into(),
+        distance: hit.distance,
     }
 }
 
@@ -531,9 +1315,12 @@ impl PhysicsSim {
         material: Material,
     ) -> usize {
         let sphere = Sphere::with_mass_and_material(pos, vel, radius, mass, material);
-        self.spheres.push(sphere);
-        self.params.forces.push([0.0, 0.0]);
-        self.spheres.len() - 1
+        let count = self.spheres.len();
+        let index = insert_body(&mut self.removed_bodies, &mut self.spheres, BodyHandle::Sphere, sphere);
+        if index == count {
+            self.params.forces.push([0.0, 0.0]);
+        }
+        index
     }
 
     /// Add a box-shaped rigid body
@@ -554,9 +1341,12 @@ impl PhysicsSim {
             material: Material::default(),
             body_type,
         };
-        self.boxes.push(box_body);
-        self.params.forces.push([0.0, 0.0]);
-        self.boxes.len() - 1
+        let count = self.boxes.len();
+        let index = insert_body(&mut self.removed_bodies, &mut self.boxes, BodyHandle::Box, box_body);
+        if index == count {
+            self.params.forces.push([0.0, 0.0]);
+        }
+        index
     }
 
     /// Add a cylindrical rigid body
@@ -593,8 +1383,7 @@ impl PhysicsSim {
             shape_offset: Vec3::ZERO, // Default: shape at center of mass
             mesh_offset: Vec3::ZERO,  // Default: mesh origin at center of mass
         };
-        self.cylinders.push(cylinder);
-        self.cylinders.len() - 1
+        insert_body(&mut self.removed_bodies, &mut self.cylinders, BodyHandle::Cylinder, cylinder)
     }
     
     /// Add a cylinder with custom shape and mesh offsets
@@ -622,8 +1411,37 @@ impl PhysicsSim {
             shape_offset,
             mesh_offset,
         };
-        self.cylinders.push(cylinder);
-        self.cylinders.len() - 1
+        insert_body(&mut self.removed_bodies, &mut self.cylinders, BodyHandle::Cylinder, cylinder)
+    }
+
+    /// Add a capsule-shaped rigid body whose core segment runs along the
+    /// local Y axis
+    pub fn add_capsule(&mut self, pos: Vec3, radius: f32, half_height: f32, vel: Vec3) -> usize {
+        self.add_capsule_with_type(pos, radius, half_height, vel, BodyType::Dynamic)
+    }
+
+    /// Add a capsule-shaped rigid body with specific body type
+    pub fn add_capsule_with_type(
+        &mut self,
+        pos: Vec3,
+        radius: f32,
+        half_height: f32,
+        vel: Vec3,
+        body_type: BodyType,
+    ) -> usize {
+        let mass = calculate_capsule_mass(radius, half_height, 1.0); // Default density
+        let capsule = Capsule {
+            pos,
+            vel,
+            radius,
+            half_height,
+            mass,
+            orientation: [0.0, 0.0, 0.0, 1.0], // Identity quaternion
+            angular_vel: Vec3::ZERO,
+            material: Material::default(),
+            body_type,
+        };
+        insert_body(&mut self.removed_bodies, &mut self.capsules, BodyHandle::Capsule, capsule)
     }
 
     /// Add a static plane for collision
@@ -634,8 +1452,43 @@ impl PhysicsSim {
             extents,
             material: Material::default(),
         };
-        self.planes.push(plane);
-        self.planes.len() - 1
+        insert_body(&mut self.removed_bodies, &mut self.planes, BodyHandle::Plane, plane)
+    }
+
+    /// Add static heightfield terrain for collision.
+    pub fn add_heightfield(&mut self, heightfield: Heightfield) -> usize {
+        insert_body(&mut self.removed_bodies, &mut self.heightfields, BodyHandle::Heightfield, heightfield)
+    }
+
+    /// Add a dynamic convex hull body with the origin of `geometry` at
+    /// `pos`. Mass and inertia follow from the hull at the default density.
+    pub fn add_convex_hull(&mut self, geometry: &HullGeometry, pos: Vec3, vel: Vec3) -> usize {
+        let mut hull = ConvexHull::new(geometry, pos, 1.0); // Default density
+        hull.vel = vel;
+        self.add_hull_body(hull)
+    }
+
+    /// Add a convex hull body built with [`ConvexHull::new`].
+    pub fn add_hull_body(&mut self, hull: ConvexHull) -> usize {
+        insert_body(&mut self.removed_bodies, &mut self.hulls, BodyHandle::Hull, hull)
+    }
+
+    /// Add a dynamic body made of `children`, placed around `pos`, at the
+    /// default density. See [`Compound::new`].
+    pub fn add_compound(&mut self, children: Vec<CompoundChild>, pos: Vec3, vel: Vec3) -> usize {
+        let mut compound = Compound::new(children, pos, 1.0); // Default density
+        compound.vel = vel;
+        self.add_compound_body(compound)
+    }
+
+    /// Add a compound body built with [`Compound::new`].
+    pub fn add_compound_body(&mut self, compound: Compound) -> usize {
+        insert_body(&mut self.removed_bodies, &mut self.compounds, BodyHandle::Compound, compound)
+    }
+
+    /// Add a static triangle mesh for collision.
+    pub fn add_mesh(&mut self, mesh: TriangleMesh) -> usize {
+        insert_body(&mut self.removed_bodies, &mut self.meshes, BodyHandle::Mesh, mesh)
     }
 }
 
@@ -649,121 +1502,236 @@ impl PhysicsSim {
     }
 
     /// Add revolute (hinge) joint between two bodies.
+    ///
+    /// Bodies are named by a shape code (see [`BodyHandle::from_type_code`])
+    /// and an index. `anchor` and `axis` are given in world space at the
+    /// bodies' current poses; the joint keeps the anchor shared and lets the
+    /// bodies rotate relative to each other only about the axis.
     pub fn add_revolute_joint(
         &mut self,
-        _body_a_type: u32,
+        body_a_type: u32,
         body_a_index: u32,
-        _body_b_type: u32,
+        body_b_type: u32,
         body_b_index: u32,
         anchor: Vec3,
         axis: Vec3,
     ) -> usize {
-        // Calculate anchor points in local coordinates for each body
-        let body_a_idx = body_a_index as usize;
-        let body_b_idx = body_b_index as usize;
-        
-        // Calculate proper anchor points for box-cylinder connection (CartPole)
-        let anchor_a = if body_a_idx < self.boxes.len() {
-            // For the cart (box): anchor at TOP of cart (joint connection point)
-            // Cart half_extents.y is half-height, so top is at +half_extents.y
-            Vec3::new(0.0, self.boxes[body_a_idx].half_extents.y, 0.0)
-        } else {
-            Vec3::ZERO
-        };
-        
-        let anchor_b = if body_b_idx < self.cylinders.len() {
-            // For the pole (cylinder): anchor at the BOTTOM of the pole
-            // Cylinder position is at center, so bottom is at -half_height in Y
-            let pole_bottom_offset = Vec3::new(0.0, -self.cylinders[body_b_idx].half_height, 0.0);
-            pole_bottom_offset
-        } else {
-            Vec3::ZERO
-        };
+        let frame_a = self.joint_body_frame(body_a_type, body_a_index);
+        let frame_b = self.joint_body_frame(body_b_type, body_b_index);
+        let world_axis = glam::Vec3::from(axis).normalize_or_zero();
         
         let joint = RevoluteJoint {
             body_a: body_a_index,
             body_b: body_b_index,
-            anchor_a,
-            anchor_b,
-            axis,
+            body_a_type,
+            body_b_type,
+            anchor_a: frame_a.to_local(anchor.into()).into(),
+            anchor_b: frame_b.to_local(anchor.into()).into(),
+            axis: (frame_a.orientation.inverse() * world_axis).into(),
+            reference_rotation: (frame_a.orientation.inverse() * frame_b.orientation).to_array(),
             lower_limit: -std::f32::consts::PI,
             upper_limit: std::f32::consts::PI,
             motor_speed: 0.0,
             motor_max_force: 0.0,
-            enable_motor: 0,
+            enable_motor: MOTOR_DISABLED,
             enable_limit: 0,
-            _pad: 0.0,
+            target_position: 0.0,
+            stiffness: 0.0,
+            damping: 0.0,
+            compliance: 0.0,
+            disable_collision: 0,
         };
         self.revolute_joints.push(joint);
         self.revolute_joints.len() - 1
     }
 
+    /// Set the motor of revolute joint `joint`. Controls are read every
+    /// step, so this can be called between steps to drive the joint.
+    pub fn set_revolute_control(&mut self, joint: usize, control: JointControl) {
+        if let Some(joint) = self.revolute_joints.get_mut(joint) {
+            joint.set_control(control);
+        }
+    }
+
+    /// Limit revolute joint `joint` to angles in `[lower, upper]`, or remove
+    /// its limits with `None`.
+    pub fn set_revolute_limits(&mut self, joint: usize, limits: Option<(f32, f32)>) {
+        if let Some(joint) = self.revolute_joints.get_mut(joint) {
+            joint.set_limits(limits);
+        }
+    }
+
+    /// Angle and angular speed of revolute joint `joint` about its axis,
+    /// measured from the pose at which it was created.
+    #[must_use]
+    pub fn revolute_joint_state(&self, joint: usize) -> Option<JointState> {
+        revolute_state(self, joint)
+    }
+
+    /// Frame of a joint body, or the world frame if it does not exist.
+    fn joint_body_frame(&self, body_type: u32, index: u32) -> BodyFrame {
+        BodyHandle::from_type_code(body_type, index as usize)
+            .filter(|&handle| self.has_body(handle))
+            .map_or(BodyFrame::IDENTITY, |handle| self.body_frame(handle))
+    }
+
+    /// Restrict a body to the plane through its current position with the
+    /// given normal. The body may still rotate about the normal.
+    pub fn add_planar_constraint(&mut self, body: BodyHandle, normal: Vec3) -> usize {
+        let frame = if self.has_body(body) { self.body_frame(body) } else { BodyFrame::IDENTITY };
+        let normal = normal.normalize();
+        let constraint = PlanarConstraint {
+            body,
+            normal,
+            distance: normal.dot(frame.position.into()),
+            reference_orientation: frame.orientation.to_array(),
+        };
+        self.planar_constraints.push(constraint);
+        self.planar_constraints.len() - 1
+    }
+
     /// Add prismatic (sliding) joint between two bodies.
+    ///
+    /// `anchor` and `axis` are given in world space at the bodies' current
+    /// poses. Body B may slide along the axis through the anchor but keeps
+    /// its orientation relative to body A.
     pub fn add_prismatic_joint(
         &mut self,
-        _body_a_type: u32,
+        body_a_type: u32,
         body_a_index: u32,
-        _body_b_type: u32,
+        body_b_type: u32,
         body_b_index: u32,
         anchor: Vec3,
         axis: Vec3,
     ) -> usize {
-        let joint = create_prismatic_joint(body_a_index, body_b_index, anchor, axis);
+        let frame_a = self.joint_body_frame(body_a_type, body_a_index);
+        let frame_b = self.joint_body_frame(body_b_type, body_b_index);
+        let world_axis = glam::Vec3::from(axis).normalize_or_zero();
+
+        let joint = PrismaticJoint {
+            body_a: body_a_index,
+            body_b: body_b_index,
+            body_a_type,
+            body_b_type,
+            anchor_a: frame_a.to_local(anchor.into()).into(),
+            anchor_b: frame_b.to_local(anchor.into()).into(),
+            axis: (frame_a.orientation.inverse() * world_axis).into(),
+            reference_rotation: (frame_a.orientation.inverse() * frame_b.orientation).to_array(),
+            lower_limit: -1.0,
+            upper_limit: 1.0,
+            motor_speed: 0.0,
+            motor_max_force: 0.0,
+            enable_motor: MOTOR_DISABLED,
+            enable_limit: 0,
+            target_position: 0.0,
+            stiffness: 0.0,
+            damping: 0.0,
+            compliance: 0.0,
+            disable_collision: 0,
+        };
         self.prismatic_joints.push(joint);
         self.prismatic_joints.len() - 1
     }
 
+    /// Set the motor of prismatic joint `joint`. Controls are read every
+    /// step, so this can be called between steps to drive the joint.
+    pub fn set_prismatic_control(&mut self, joint: usize, control: JointControl) {
+        if let Some(joint) = self.prismatic_joints.get_mut(joint) {
+            joint.set_control(control);
+        }
+    }
+
+    /// Limit prismatic joint `joint` to translations in `[lower, upper]`, or
+    /// remove its limits with `None`.
+    pub fn set_prismatic_limits(&mut self, joint: usize, limits: Option<(f32, f32)>) {
+        if let Some(joint) = self.prismatic_joints.get_mut(joint) {
+            joint.set_limits(limits);
+        }
+    }
+
+    /// Translation and speed of prismatic joint `joint` along its axis,
+    /// measured from the pose at which it was created.
+    #[must_use]
+    pub fn prismatic_joint_state(&self, joint: usize) -> Option<JointState> {
+        prismatic_state(self, joint)
+    }
+
     /// Add ball joint (3DOF rotation) between two bodies.
+    ///
+    /// `anchor` is given in world space at the bodies' current poses.
     pub fn add_ball_joint(
         &mut self,
-        _body_a_type: u32,
+        body_a_type: u32,
         body_a_index: u32,
-        _body_b_type: u32,
+        body_b_type: u32,
         body_b_index: u32,
         anchor: Vec3,
     ) -> usize {
-        let joint = create_ball_joint(body_a_index, body_b_index, anchor);
+        let frame_a = self.joint_body_frame(body_a_type, body_a_index);
+        let frame_b = self.joint_body_frame(body_b_type, body_b_index);
+
+        let joint = BallJoint {
+            body_a: body_a_index,
+            body_b: body_b_index,
+            body_a_type,
+            body_b_type,
+            anchor_a: frame_a.to_local(anchor.into()).into(),
+            anchor_b: frame_b.to_local(anchor.into()).into(),
+            compliance: 0.0,
+            disable_collision: 0,
+        };
         self.ball_joints.push(joint);
         self.ball_joints.len() - 1
     }
 
     /// Add fixed joint (no relative motion) between two bodies.
+    ///
+    /// `relative_position` and `relative_orientation` give the pose of body B
+    /// in body A's local frame that the joint holds.
     pub fn add_fixed_joint(
         &mut self,
-        _body_a_type: u32,
+        body_a_type: u32,
         body_a_index: u32,
-        _body_b_type: u32,
+        body_b_type: u32,
         body_b_index: u32,
         relative_position: Vec3,
         relative_orientation: [f32; 4],
     ) -> usize {
-        let joint = create_fixed_joint(
+        let mut joint = create_fixed_joint(
             body_a_index,
             body_b_index,
             relative_position,
             relative_orientation
         );
+        joint.body_a_type = body_a_type;
+        joint.body_b_type = body_b_type;
         self.fixed_joints.push(joint);
         self.fixed_joints.len() - 1
     }
 }
 
 // Helper functions for mass calculations
-fn calculate_sphere_mass(radius: f32, density: f32) -> f32 {
+pub(crate) fn calculate_sphere_mass(radius: f32, density: f32) -> f32 {
     let volume = (4.0 / 3.0) * std::f32::consts::PI * radius.powi(3);
     volume * density
 }
 
-fn calculate_box_mass(half_extents: Vec3, density: f32) -> f32 {
+pub(crate) fn calculate_box_mass(half_extents: Vec3, density: f32) -> f32 {
     let volume = 8.0 * half_extents.x * half_extents.y * half_extents.z;
     volume * density
 }
 
-fn calculate_cylinder_mass(radius: f32, height: f32, density: f32) -> f32 {
+pub(crate) fn calculate_cylinder_mass(radius: f32, height: f32, density: f32) -> f32 {
     let volume = std::f32::consts::PI * radius.powi(2) * height;
     volume * density
 }
 
+pub(crate) fn calculate_capsule_mass(radius: f32, half_height: f32, density: f32) -> f32 {
+    let cylinder = std::f32::consts::PI * radius.powi(2) * 2.0 * half_height;
+    let caps = (4.0 / 3.0) * std::f32::consts::PI * radius.powi(3);
+    (cylinder + caps) * density
+}
+
 // Simulation configuration helpers
 fn create_default_simulation_bounds() -> BoundingBox {
     BoundingBox {
@@ -791,42 +1759,10 @@ fn create_distance_joint(body_a: u32, body_b: u32, rest_length: f32) -> Joint {
         body_a,
         body_b,
         rest_length,
-        _padding: 0,
-    }
-}
-
-
-fn create_prismatic_joint(
-    body_a: u32,
-    body_b: u32,
-    anchor: Vec3,
-    axis: Vec3,
-) -> PrismaticJoint {
-    PrismaticJoint {
-        body_a,
-        body_b,
-        anchor_a: anchor,
-        anchor_b: anchor,
-        axis,
-        lower_limit: -1.0,
-        upper_limit: 1.0,
-        motor_speed: 0.0,
-        motor_max_force: 0.0,
-        enable_motor: 0,
-        enable_limit: 0,
-        _pad: 0.0,
+        disable_collision: 0,
     }
 }
 
-fn create_ball_joint(body_a: u32, body_b: u32, anchor: Vec3) -> BallJoint {
-    BallJoint {
-        body_a,
-        body_b,
-        anchor_a: anchor,
-        anchor_b: anchor,
-        _pad: [0.0; 2],
-    }
-}
 
 fn create_fixed_joint(
     body_a: u32,
@@ -837,8 +1773,12 @@ fn create_fixed_joint(
     FixedJoint {
         body_a,
         body_b,
+        body_a_type: BodyHandle::BOX_TYPE,
+        body_b_type: BodyHandle::BOX_TYPE,
         anchor_a: relative_position,
         anchor_b: Vec3::ZERO,
         relative_rotation: relative_orientation,
+        compliance: 0.0,
+        disable_collision: 0,
     }
 }
\ No newline at end of file
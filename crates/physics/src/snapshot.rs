@@ -0,0 +1,191 @@
+//! # Snapshots
+//!
+//! A [`Snapshot`] captures the complete state of a [`PhysicsSim`]: bodies,
+//! static geometry, joints, parameters, and the caches the solver carries
+//! from one step to the next (contact manifolds with their warm-start
+//! impulses, joint impulses, sleep state and broad phase order). Restoring
+//! it and stepping gives bit-for-bit the same result as stepping the
+//! original, which makes snapshots suitable for checkpoints, rollbacks
+//! during planning and bug reports.
+//!
+//! Snapshots are stored in one of two versioned formats:
+//!
+//! - **Binary** ([`Snapshot::to_bytes`]): the magic bytes `JAXSSNAP`, the
+//!   format version as a little-endian `u32`, then the state encoded with
+//!   `bincode`. Floats are stored as their raw bits.
+//! - **JSON** ([`Snapshot::to_json`]): an object with `format`, `version`
+//!   and `sim` fields, for reading and diffing by hand. Floats are written
+//!   with enough digits to read back exactly, but JSON has no NaN or
+//!   infinity, so a snapshot holding one can only be stored as binary.
+//!
+//! The compute backend is not part of a snapshot. [`PhysicsSim::restore`]
+//! keeps the backend of the simulation it restores into, and
+//! [`Snapshot::into_sim`] uses the default one.
+
+use std::collections::BTreeMap;
+
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::Serializer;
+use serde::Serialize;
+
+use crate::simulation::{PhysicsError, PhysicsSim};
+
+/// Version of the snapshot formats written by this build.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+const MAGIC: &[u8; 8] = b"JAXSSNAP";
+const FORMAT: &str = "jaxs-physics-snapshot";
+
+/// Complete state of a [`PhysicsSim`], taken by [`PhysicsSim::snapshot`].
+#[derive(Clone)]
+pub struct Snapshot {
+    sim: PhysicsSim,
+}
+
+#[derive(Serialize)]
+struct JsonSnapshotRef<'a> {
+    format: &'a str,
+    version: u32,
+    sim: &'a PhysicsSim,
+}
+
+#[derive(serde::Deserialize)]
+struct JsonHeader {
+    format: String,
+    version: u32,
+}
+
+#[derive(serde::Deserialize)]
+struct JsonSnapshot {
+    sim: PhysicsSim,
+}
+
+impl Snapshot {
+    /// A simulation in the captured state, using the default compute
+    /// backend.
+    #[must_use]
+    pub fn into_sim(self) -> PhysicsSim {
+        self.sim
+    }
+
+    /// Encode the snapshot in the binary format.
+    ///
+    /// # Panics
+    ///
+    /// Never in practice: every part of the state can be encoded.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::from(*MAGIC);
+        bytes.extend(SNAPSHOT_VERSION.to_le_bytes());
+        bincode::serialize_into(&mut bytes, &self.sim).expect("simulation state is always encodable");
+        bytes
+    }
+
+    /// Decode a snapshot written by [`Self::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PhysicsError::Snapshot`] if `bytes` is not a binary
+    /// snapshot, was written by another format version, or is truncated or
+    /// corrupt.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PhysicsError> {
+        let payload = bytes
+            .strip_prefix(MAGIC.as_slice())
+            .ok_or_else(|| PhysicsError::Snapshot("not a binary physics snapshot".into()))?;
+        let (version, payload) = payload
+            .split_first_chunk::<4>()
+            .ok_or_else(|| PhysicsError::Snapshot("snapshot ends before its version".into()))?;
+        check_version(u32::from_le_bytes(*version))?;
+        let sim = bincode::deserialize(payload).map_err(|err| PhysicsError::Snapshot(err.to_string()))?;
+        Ok(Self { sim })
+    }
+
+    /// Encode the snapshot as pretty-printed JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PhysicsError::Snapshot`] if the state holds a NaN or
+    /// infinite value, which JSON cannot represent.
+    pub fn to_json(&self) -> Result<String, PhysicsError> {
+        let json = JsonSnapshotRef {
+            format: FORMAT,
+            version: SNAPSHOT_VERSION,
+            sim: &self.sim,
+        };
+        let text = serde_json::to_string_pretty(&json).map_err(|err| PhysicsError::Snapshot(err.to_string()))?;
+        // `serde_json` writes non-finite floats as `null`; reading the text
+        // back is the reliable way to find them
+        let exact = serde_json::from_str::<JsonSnapshot>(&text)
+            .is_ok_and(|decoded| bincode::serialize(&decoded.sim).ok() == bincode::serialize(&self.sim).ok());
+        if !exact {
+            return Err(PhysicsError::Snapshot("JSON cannot hold NaN or infinite values".into()));
+        }
+        Ok(text)
+    }
+
+    /// Decode a snapshot written by [`Self::to_json`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PhysicsError::Snapshot`] if `json` is not a JSON snapshot,
+    /// was written by another format version, or does not describe a valid
+    /// simulation.
+    pub fn from_json(json: &str) -> Result<Self, PhysicsError> {
+        let header: JsonHeader = serde_json::from_str(json).map_err(|err| PhysicsError::Snapshot(err.to_string()))?;
+        if header.format != FORMAT {
+            return Err(PhysicsError::Snapshot(format!("unknown snapshot format `{}`", header.format)));
+        }
+        check_version(header.version)?;
+        let snapshot: JsonSnapshot = serde_json::from_str(json).map_err(|err| PhysicsError::Snapshot(err.to_string()))?;
+        Ok(Self { sim: snapshot.sim })
+    }
+}
+
+impl PhysicsSim {
+    /// Capture the complete state of the simulation. This copies the
+    /// bodies, joints and caches but shares the compute backend.
+    #[must_use]
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot { sim: self.clone() }
+    }
+
+    /// Return the simulation to the state captured in `snapshot`, keeping
+    /// its own compute backend.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        let backend = std::sync::Arc::clone(&self.backend);
+        self.clone_from(&snapshot.sim);
+        self.backend = backend;
+    }
+}
+
+fn check_version(version: u32) -> Result<(), PhysicsError> {
+    if version == SNAPSHOT_VERSION {
+        Ok(())
+    } else {
+        Err(PhysicsError::Snapshot(format!(
+            "snapshot format version {version} is not supported, expected {SNAPSHOT_VERSION}"
+        )))
+    }
+}
+
+/// Serialize a map as a list of `(key, value)` pairs, for maps whose keys
+/// are not strings and so cannot be JSON object keys.
+pub(crate) mod pairs {
+    use super::{BTreeMap, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<K: Serialize, V: Serialize, S: Serializer>(
+        map: &BTreeMap<K, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(map)
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<BTreeMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Ord,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Ok(Vec::<(K, V)>::deserialize(deserializer)?.into_iter().collect())
+    }
+}
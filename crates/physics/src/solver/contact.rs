@@ -0,0 +1,271 @@
+//! Sequential-impulse contact solver with warm starting.
+//!
+//! Each manifold point gets a non-penetration row and two friction rows. The
+//! friction rows are clamped to a circular cone and are biased towards the
+//! point's static friction anchors so resting bodies do not creep. Overlap
+//! left after the velocity solve is removed by a separate position pass that
+//! does not add energy to the system.
+
+use glam::Vec3;
+
+use super::{tangent_basis, SolverBodies};
+use crate::collision::{ManifoldCache, ManifoldKey};
+use crate::types::ContactParams;
+
+/// Tangential drift beyond which a friction anchor is considered broken.
+const MAX_ANCHOR_DRIFT: f32 = 0.05;
+
+struct PointConstraint {
+    r_a: Vec3,
+    r_b: Vec3,
+    normal_mass: f32,
+    tangent_mass: [f32; 2],
+    normal_impulse: f32,
+    tangent_impulse: [f32; 2],
+    max_normal_impulse: f32,
+    /// Separation speed the normal row allows (positive for speculative
+    /// contacts that are still apart).
+    speculative_bias: f32,
+    /// Friction target velocities that pull the anchors back together.
+    anchor_bias: [f32; 2],
+    /// Normal velocity before the solve, used for restitution.
+    approach_velocity: f32,
+    anchor_broken: bool,
+}
+
+struct ManifoldConstraint {
+    key: ManifoldKey,
+    body_a: usize,
+    body_b: usize,
+    normal: Vec3,
+    tangents: [Vec3; 2],
+    friction: f32,
+    restitution: f32,
+    points: Vec<PointConstraint>,
+}
+
+/// Contact constraints for one step.
+pub(crate) struct ContactSolver {
+    constraints: Vec<ManifoldConstraint>,
+}
+
+fn inverse_or_zero(value: f32) -> f32 {
+    if value > 0.0 {
+        1.0 / value
+    } else {
+        0.0
+    }
+}
+
+impl ContactSolver {
+    /// Build constraints from the cached manifolds and the bodies' current
+    /// state. `dt` is the step the velocities will be integrated over.
+    pub fn prepare(manifolds: &ManifoldCache, bodies: &SolverBodies, params: &ContactParams, dt: f32) -> Self {
+        let inv_dt = if dt > 0.0 { 1.0 / dt } else { 0.0 };
+        let constraints = manifolds
+            .values()
+            .map(|manifold| {
+                let body_a = bodies.index(manifold.body_a);
+                let body_b = bodies.index(manifold.body_b);
+                let a = &bodies.bodies[body_a];
+                let b = &bodies.bodies[body_b];
+                let normal: Vec3 = manifold.normal.into();
+                let tangents = tangent_basis(normal);
+                let (frame_a, frame_b) = (a.frame(), b.frame());
+
+                let points = manifold
+                    .points
+                    .iter()
+                    .map(|point| {
+                        let position: Vec3 = point.position.into();
+                        let r_a = position - a.position;
+                        let r_b = position - b.position;
+                        let mass_along = |direction: Vec3| {
+                            inverse_or_zero(
+                                a.effective_inv_mass(r_a, direction) + b.effective_inv_mass(r_b, direction),
+                            )
+                        };
+
+                        let drift = frame_b.to_world(point.anchor_b.into()) - frame_a.to_world(point.anchor_a.into());
+                        let tangential_drift = [drift.dot(tangents[0]), drift.dot(tangents[1])];
+                        let anchor_broken = tangential_drift[0].hypot(tangential_drift[1]) > MAX_ANCHOR_DRIFT;
+                        let anchor_bias = if anchor_broken {
+                            [0.0; 2]
+                        } else {
+                            [
+                                -params.baumgarte * inv_dt * tangential_drift[0],
+                                -params.baumgarte * inv_dt * tangential_drift[1],
+                            ]
+                        };
+
+                        let relative = b.velocity_at(r_b) - a.velocity_at(r_a);
+                        PointConstraint {
+                            r_a,
+                            r_b,
+                            normal_mass: mass_along(normal),
+                            tangent_mass: [mass_along(tangents[0]), mass_along(tangents[1])],
+                            normal_impulse: point.normal_impulse,
+                            tangent_impulse: point.tangent_impulse,
+                            max_normal_impulse: 0.0,
+                            speculative_bias: (-point.depth).max(0.0) * inv_dt,
+                            anchor_bias,
+                            approach_velocity: relative.dot(normal),
+                            anchor_broken,
+                        }
+                    })
+                    .collect();
+
+                ManifoldConstraint {
+                    key: manifold.key(),
+                    body_a,
+                    body_b,
+                    normal,
+                    tangents,
+                    friction: manifold.friction,
+                    restitution: manifold.restitution,
+                    points,
+                }
+            })
+            .collect();
+
+        Self { constraints }
+    }
+
+    /// Apply the impulses carried over from the previous step.
+    pub fn warm_start(&self, bodies: &mut SolverBodies) {
+        for constraint in &self.constraints {
+            let (a, b) = bodies.pair_mut(constraint.body_a, constraint.body_b);
+            for point in &constraint.points {
+                let impulse = constraint.normal * point.normal_impulse
+                    + constraint.tangents[0] * point.tangent_impulse[0]
+                    + constraint.tangents[1] * point.tangent_impulse[1];
+                a.apply_impulse(-impulse, point.r_a);
+                b.apply_impulse(impulse, point.r_b);
+            }
+        }
+    }
+
+    /// One sequential-impulse sweep over all contacts. Alternating the sweep
+    /// direction between iterations keeps the fixed solve order from biasing
+    /// stacks towards one side.
+    pub fn solve_velocities(&mut self, bodies: &mut SolverBodies, reverse: bool) {
+        let count = self.constraints.len();
+        for c in 0..count {
+            let constraint = &mut self.constraints[if reverse { count - 1 - c } else { c }];
+            let (a, b) = bodies.pair_mut(constraint.body_a, constraint.body_b);
+            let point_count = constraint.points.len();
+            for p in 0..point_count {
+                let point = &mut constraint.points[if reverse { point_count - 1 - p } else { p }];
+                // Friction first, so the normal row has the final say.
+                let relative = b.velocity_at(point.r_b) - a.velocity_at(point.r_a);
+                let max_friction = constraint.friction * point.normal_impulse;
+                let old = point.tangent_impulse;
+                let mut new = [0.0; 2];
+                for k in 0..2 {
+                    let speed = relative.dot(constraint.tangents[k]);
+                    new[k] = old[k] - point.tangent_mass[k] * (speed - point.anchor_bias[k]);
+                }
+                let magnitude = new[0].hypot(new[1]);
+                if magnitude > max_friction {
+                    let scale = if magnitude > 0.0 { max_friction / magnitude } else { 0.0 };
+                    new = [new[0] * scale, new[1] * scale];
+                }
+                point.tangent_impulse = new;
+                let impulse = constraint.tangents[0] * (new[0] - old[0])
+                    + constraint.tangents[1] * (new[1] - old[1]);
+                a.apply_impulse(-impulse, point.r_a);
+                b.apply_impulse(impulse, point.r_b);
+
+                let relative = b.velocity_at(point.r_b) - a.velocity_at(point.r_a);
+                let normal_speed = relative.dot(constraint.normal);
+                let lambda = -point.normal_mass * (normal_speed + point.speculative_bias);
+                let accumulated = (point.normal_impulse + lambda).max(0.0);
+                let applied = accumulated - point.normal_impulse;
+                point.normal_impulse = accumulated;
+                point.max_normal_impulse = point.max_normal_impulse.max(accumulated);
+                let impulse = constraint.normal * applied;
+                a.apply_impulse(-impulse, point.r_a);
+                b.apply_impulse(impulse, point.r_b);
+            }
+        }
+    }
+
+    /// Add the bounce for points that were approaching faster than
+    /// `threshold` and actually took load during the solve.
+    pub fn apply_restitution(&mut self, bodies: &mut SolverBodies, threshold: f32) {
+        for constraint in &mut self.constraints {
+            if constraint.restitution == 0.0 {
+                continue;
+            }
+            let (a, b) = bodies.pair_mut(constraint.body_a, constraint.body_b);
+            for point in &mut constraint.points {
+                if point.approach_velocity > -threshold || point.max_normal_impulse == 0.0 {
+                    continue;
+                }
+                let relative = b.velocity_at(point.r_b) - a.velocity_at(point.r_a);
+                let normal_speed = relative.dot(constraint.normal);
+                let target = -constraint.restitution * point.approach_velocity;
+                let lambda = -point.normal_mass * (normal_speed - target);
+                let accumulated = (point.normal_impulse + lambda).max(0.0);
+                let applied = accumulated - point.normal_impulse;
+                point.normal_impulse = accumulated;
+                let impulse = constraint.normal * applied;
+                a.apply_impulse(-impulse, point.r_a);
+                b.apply_impulse(impulse, point.r_b);
+            }
+        }
+    }
+
+    /// Copy accumulated impulses back into the manifold cache for the next
+    /// step's warm start, and flag points whose friction saturated.
+    pub fn store_impulses(&self, manifolds: &mut ManifoldCache) {
+        for constraint in &self.constraints {
+            let Some(manifold) = manifolds.get_mut(&constraint.key) else {
+                continue;
+            };
+            for (cached, point) in manifold.points.iter_mut().zip(&constraint.points) {
+                cached.normal_impulse = point.normal_impulse;
+                cached.tangent_impulse = point.tangent_impulse;
+                let max_friction = constraint.friction * point.normal_impulse;
+                let friction = point.tangent_impulse[0].hypot(point.tangent_impulse[1]);
+                cached.sliding = point.anchor_broken
+                    || point.normal_impulse <= 0.0
+                    || friction >= 0.999 * max_friction;
+            }
+        }
+    }
+}
+
+/// One iteration of pushing overlapping bodies apart after positions have
+/// been integrated.
+///
+/// Works on positions only, using the contact points stored in each body's
+/// frame, so it corrects penetration without changing velocities.
+pub(crate) fn solve_positions(manifolds: &ManifoldCache, bodies: &mut SolverBodies, params: &ContactParams) {
+    for manifold in manifolds.values() {
+        let index_a = bodies.index(manifold.body_a);
+        let index_b = bodies.index(manifold.body_b);
+        let normal: Vec3 = manifold.normal.into();
+        let (a, b) = bodies.pair_mut(index_a, index_b);
+        for point in &manifold.points {
+            let on_a = a.frame().to_world(point.local_a.into());
+            let on_b = b.frame().to_world(point.local_b.into());
+            let separation = (on_b - on_a).dot(normal);
+            let correction = (params.baumgarte * (separation + params.linear_slop))
+                .clamp(-params.max_correction, 0.0);
+            if correction >= 0.0 {
+                continue;
+            }
+
+            let r_a = on_a - a.position;
+            let r_b = on_b - b.position;
+            let inv_mass = a.effective_inv_mass(r_a, normal) + b.effective_inv_mass(r_b, normal);
+            if inv_mass <= 0.0 {
+                continue;
+            }
+            let impulse = normal * (-correction / inv_mass);
+            a.apply_position_impulse(-impulse, r_a);
+            b.apply_position_impulse(impulse, r_b);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Simulation islands
+//!
+//! Dynamic bodies that touch or share a joint form an island. Static and
+//! kinematic bodies do not join islands, so a floor does not tie everything
+//! resting on it together. Bodies in different islands cannot push each
+//! other within a step, which lets a whole island fall asleep, or wake up,
+//! at once.
+
+use super::joint::joint_frames;
+use crate::body::BodyHandle;
+use crate::removal::JointHandle;
+use crate::simulation::PhysicsSim;
+use crate::types::JointControl;
+
+/// Dynamic bodies that constrain each other, directly or through others.
+pub(crate) struct Island {
+    pub bodies: Vec<BodyHandle>,
+    /// Whether a joint motor drives the island, which keeps it awake.
+    pub driven: bool,
+}
+
+/// Body pairs linked by a contact manifold or a joint, and whether the link
+/// is a motorised joint.
+pub(crate) fn links(sim: &PhysicsSim) -> Vec<(BodyHandle, BodyHandle, bool)> {
+    let contacts = sim.manifolds.keys().map(|&(a, b, _, _)| (a, b, false));
+    let distance = sim
+        .joints
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| !sim.is_joint_removed(JointHandle::Distance(index)))
+        .map(|(_, joint)| (BodyHandle::Sphere(joint.body_a as usize), BodyHandle::Sphere(joint.body_b as usize), false));
+    let joints = joint_frames(sim).map(|frame| {
+        let driven = !matches!(frame.control, JointControl::Off);
+        (frame.handle_a, frame.handle_b, driven)
+    });
+    contacts
+        .chain(distance)
+        .chain(joints)
+        .filter(|&(a, b, _)| sim.has_body(a) && sim.has_body(b))
+        .collect()
+}
+
+/// Group the dynamic bodies of `sim` into islands, in handle order.
+pub(crate) fn build_islands(sim: &PhysicsSim) -> Vec<Island> {
+    let handles: Vec<BodyHandle> = sim
+        .body_handles()
+        .into_iter()
+        .filter(|&handle| sim.is_dynamic(handle))
+        .collect();
+    let index_of = |handle: BodyHandle| handles.binary_search(&handle).ok();
+
+    let mut parent: Vec<usize> = (0..handles.len()).collect();
+    let mut driven = vec![false; handles.len()];
+    for (a, b, motor) in links(sim) {
+        match (index_of(a), index_of(b)) {
+            (Some(a), Some(b)) => {
+                let (root_a, root_b) = (find(&mut parent, a), find(&mut parent, b));
+                parent[root_a.max(root_b)] = root_a.min(root_b);
+                driven[root_a.min(root_b)] |= motor || driven[root_a.max(root_b)];
+            }
+            (Some(body), None) | (None, Some(body)) => {
+                let root = find(&mut parent, body);
+                driven[root] |= motor;
+            }
+            (None, None) => {}
+        }
+    }
+
+    let mut islands: Vec<Island> = Vec::new();
+    let mut island_of_root = vec![usize::MAX; handles.len()];
+    for (index, &handle) in handles.iter().enumerate() {
+        let root = find(&mut parent, index);
+        if island_of_root[root] == usize::MAX {
+            island_of_root[root] = islands.len();
+            islands.push(Island {
+                bodies: Vec::new(),
+                driven: driven[root],
+            });
+        }
+        islands[island_of_root[root]].bodies.push(handle);
+    }
+    islands
+}
+
+/// Root of `index` in the union-find forest, halving paths on the way.
+fn find(parent: &mut [usize], mut index: usize) -> usize {
+    while parent[index] != index {
+        parent[index] = parent[parent[index]];
+        index = parent[index];
+    }
+    index
+}
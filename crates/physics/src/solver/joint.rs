@@ -0,0 +1,880 @@
+//! Joint constraints for the sequential-impulse solver.
+//!
+//! Every joint is built from one linear and one angular part:
+//!
+//! | Joint     | Linear rows                  | Angular rows               |
+//! |-----------|------------------------------|----------------------------|
+//! | Ball      | anchors coincide (3)         | none                       |
+//! | Revolute  | anchors coincide (3)         | hinge axes parallel (2)    |
+//! | Fixed     | anchors coincide (3)         | relative rotation held (3) |
+//! | Prismatic | anchor stays on the axis (2) | relative rotation held (3) |
+//!
+//! Revolute and prismatic joints have one free axis, which carries up to
+//! three more rows: a motor (velocity target or PD servo, capped at the
+//! motor force) and one row for each limit. Limit rows are speculative: the
+//! joint may close the remaining gap to a limit within a step, but not
+//! cross it.
+//!
+//! Velocities are solved without a bias term; drift is removed by the
+//! position pass, like contact penetration.
+//!
+//! Planar constraints tie a single body to the world: they remove the
+//! velocity along the plane normal and the angular velocity about the two
+//! in-plane axes.
+
+use std::f32::consts::{PI, TAU};
+
+use glam::{Mat2, Mat3, Quat, Vec2, Vec3};
+
+use super::{tangent_basis, SolverBodies, SolverBody};
+use crate::body::BodyHandle;
+use crate::collision::body_rotation;
+use crate::removal::JointHandle;
+use crate::simulation::PhysicsSim;
+use crate::types::{JointControl, JointState};
+use serde::{Deserialize, Serialize};
+
+/// Accumulated impulses of one joint, kept between steps for warm starting.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct JointImpulse {
+    pub linear: Vec3,
+    pub angular: Vec3,
+    /// Motor impulse along the free axis.
+    pub motor: f32,
+    /// Impulse of the lower limit, never negative.
+    pub lower: f32,
+    /// Impulse of the upper limit, never negative.
+    pub upper: f32,
+}
+
+/// Warm-start state for every joint, indexed like the joint vectors of
+/// [`PhysicsSim`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct JointImpulses {
+    pub revolute: Vec<JointImpulse>,
+    pub prismatic: Vec<JointImpulse>,
+    pub ball: Vec<JointImpulse>,
+    pub fixed: Vec<JointImpulse>,
+}
+
+impl JointImpulses {
+    fn get(&self, kind: JointKind, index: usize) -> JointImpulse {
+        let stored = match kind {
+            JointKind::Revolute => &self.revolute,
+            JointKind::Prismatic => &self.prismatic,
+            JointKind::Ball => &self.ball,
+            JointKind::Fixed => &self.fixed,
+        };
+        stored.get(index).copied().unwrap_or_default()
+    }
+
+    fn get_mut(&mut self, kind: JointKind) -> &mut Vec<JointImpulse> {
+        match kind {
+            JointKind::Revolute => &mut self.revolute,
+            JointKind::Prismatic => &mut self.prismatic,
+            JointKind::Ball => &mut self.ball,
+            JointKind::Fixed => &mut self.fixed,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) enum JointKind {
+    Revolute,
+    Prismatic,
+    Ball,
+    Fixed,
+}
+
+impl JointKind {
+    /// The anchor of B may slide along the axis instead of staying on A's.
+    pub(super) fn slides(self) -> bool {
+        self == Self::Prismatic
+    }
+
+    pub(super) fn locks_rotation(self) -> bool {
+        matches!(self, Self::Prismatic | Self::Fixed)
+    }
+
+    /// Handle of joint `index` of this kind.
+    fn handle(self, index: usize) -> JointHandle {
+        match self {
+            Self::Revolute => JointHandle::Revolute(index),
+            Self::Prismatic => JointHandle::Prismatic(index),
+            Self::Ball => JointHandle::Ball(index),
+            Self::Fixed => JointHandle::Fixed(index),
+        }
+    }
+}
+
+/// Joint description shared by all joint kinds, in the bodies' frames.
+pub(super) struct JointFrame {
+    pub kind: JointKind,
+    pub joint: usize,
+    pub handle_a: BodyHandle,
+    pub handle_b: BodyHandle,
+    pub anchor_a: Vec3,
+    pub anchor_b: Vec3,
+    /// Hinge or slide axis in A's frame.
+    pub axis_a: Vec3,
+    /// The same axis in B's frame.
+    pub axis_b: Vec3,
+    /// Orientation of B relative to A.
+    pub reference: Quat,
+    pub limits: Option<(f32, f32)>,
+    pub control: JointControl,
+    /// The joint's own compliance, without [`crate::types::JointParams`].
+    pub compliance: f32,
+}
+
+/// Direction and lever arms of the free axis of a revolute or prismatic
+/// joint at the bodies' current poses.
+#[derive(Copy, Clone)]
+pub(super) struct Axial {
+    pub axis: Vec3,
+    pub r_a: Vec3,
+    pub r_b: Vec3,
+    pub angular: bool,
+}
+
+enum Motor {
+    Off,
+    Velocity {
+        speed: f32,
+        max_impulse: f32,
+    },
+    /// Implicit spring-damper towards `target`, solved as a soft row.
+    Servo {
+        target: f32,
+        bias_rate: f32,
+        softness: f32,
+        max_impulse: f32,
+    },
+}
+
+struct JointConstraint {
+    frame: JointFrame,
+    body_a: usize,
+    body_b: usize,
+    /// Lever arm of the linear rows on A. For sliders this reaches all the
+    /// way to B's anchor.
+    r_a: Vec3,
+    r_b: Vec3,
+    /// Directions perpendicular to the hinge or slide axis.
+    basis: [Vec3; 2],
+    linear_mass: Mat3,
+    slider_mass: Mat2,
+    hinge_mass: Mat2,
+    angular_mass: Mat3,
+    /// Free axis with its effective mass and the joint position along it.
+    axial: Option<(Axial, f32, f32)>,
+    motor: Motor,
+    impulse: JointImpulse,
+}
+
+struct PlanarRow {
+    body: usize,
+    normal: Vec3,
+    distance: f32,
+    reference: Quat,
+    basis: [Vec3; 2],
+}
+
+/// Joint constraints for one step.
+pub(crate) struct JointSolver {
+    joints: Vec<JointConstraint>,
+    planar: PlanarSolver,
+    inv_timestep: f32,
+}
+
+/// Planar constraints for one step.
+pub(crate) struct PlanarSolver {
+    rows: Vec<PlanarRow>,
+}
+
+fn skew(v: Vec3) -> Mat3 {
+    Mat3::from_cols(
+        Vec3::new(0.0, v.z, -v.y),
+        Vec3::new(-v.z, 0.0, v.x),
+        Vec3::new(v.y, -v.x, 0.0),
+    )
+}
+
+fn inverse_or_zero3(m: Mat3) -> Mat3 {
+    if m.determinant().abs() > f32::EPSILON {
+        m.inverse()
+    } else {
+        Mat3::ZERO
+    }
+}
+
+fn inverse_or_zero2(m: Mat2) -> Mat2 {
+    if m.determinant().abs() > f32::EPSILON {
+        m.inverse()
+    } else {
+        Mat2::ZERO
+    }
+}
+
+pub(super) fn inverse_or_zero(k: f32) -> f32 {
+    if k > f32::EPSILON {
+        1.0 / k
+    } else {
+        0.0
+    }
+}
+
+/// Effective mass of a point-to-point constraint between `a` and `b`.
+fn point_mass(a: &SolverBody, b: &SolverBody, r_a: Vec3, r_b: Vec3) -> Mat3 {
+    let skew_a = skew(r_a);
+    let skew_b = skew(r_b);
+    let k = Mat3::from_diagonal(Vec3::splat(a.inv_mass + b.inv_mass))
+        + skew_a * a.inv_inertia_matrix() * skew_a.transpose()
+        + skew_b * b.inv_inertia_matrix() * skew_b.transpose();
+    inverse_or_zero3(k)
+}
+
+/// Effective mass of the two linear rows along `basis`.
+fn slider_mass(a: &SolverBody, b: &SolverBody, r_a: Vec3, r_b: Vec3, basis: [Vec3; 2]) -> Mat2 {
+    let arms_a = basis.map(|direction| r_a.cross(direction));
+    let arms_b = basis.map(|direction| r_b.cross(direction));
+    let (inertia_a, inertia_b) = (a.inv_inertia_matrix(), b.inv_inertia_matrix());
+    let k = |i: usize, j: usize| {
+        (a.inv_mass + b.inv_mass) * basis[i].dot(basis[j])
+            + arms_a[i].dot(inertia_a * arms_a[j])
+            + arms_b[i].dot(inertia_b * arms_b[j])
+    };
+    inverse_or_zero2(Mat2::from_cols(Vec2::new(k(0, 0), k(1, 0)), Vec2::new(k(0, 1), k(1, 1))))
+}
+
+/// Effective mass of two angular rows about `basis` for the combined world
+/// inverse inertia `inv_inertia`.
+fn angular_mass(inv_inertia: Mat3, basis: [Vec3; 2]) -> Mat2 {
+    let k = |i: usize, j: usize| basis[i].dot(inv_inertia * basis[j]);
+    inverse_or_zero2(Mat2::from_cols(Vec2::new(k(0, 0), k(1, 0)), Vec2::new(k(0, 1), k(1, 1))))
+}
+
+/// Small rotation that takes `current` to `target`, as a rotation vector.
+pub(super) fn rotation_error(current: Quat, target: Quat) -> Vec3 {
+    let delta = target * current.inverse();
+    let delta = if delta.w < 0.0 { -delta } else { delta };
+    2.0 * Vec3::new(delta.x, delta.y, delta.z)
+}
+
+/// Angle in `[-PI, PI]` by which B has turned about `axis` (in A's frame)
+/// since it had orientation `reference` relative to A.
+pub(super) fn hinge_angle(orientation_a: Quat, orientation_b: Quat, axis: Vec3, reference: Quat) -> f32 {
+    let relative = orientation_a.inverse() * orientation_b * reference.inverse();
+    let relative = if relative.w < 0.0 { -relative } else { relative };
+    2.0 * axis.dot(Vec3::new(relative.x, relative.y, relative.z)).atan2(relative.w)
+}
+
+/// Add `lambda` to an accumulated impulse kept within `[min, max]` and
+/// return the change actually applied.
+pub(super) fn accumulate(total: &mut f32, lambda: f32, min: f32, max: f32) -> f32 {
+    let previous = *total;
+    *total = (previous + lambda).clamp(min, max);
+    *total - previous
+}
+
+impl Motor {
+    fn new(control: JointControl, timestep: f32) -> Self {
+        match control {
+            JointControl::Off => Self::Off,
+            JointControl::Velocity { speed, max_force } => Self::Velocity {
+                speed,
+                max_impulse: max_force * timestep,
+            },
+            JointControl::Position {
+                target,
+                stiffness,
+                damping,
+                max_force,
+            } => {
+                let denominator = damping + timestep * stiffness;
+                if denominator <= 0.0 {
+                    return Self::Off;
+                }
+                Self::Servo {
+                    target,
+                    bias_rate: stiffness / denominator,
+                    softness: 1.0 / (timestep * denominator),
+                    max_impulse: max_force * timestep,
+                }
+            }
+        }
+    }
+}
+
+impl Axial {
+    /// Rate of change of the joint position.
+    pub fn speed(&self, a: &SolverBody, b: &SolverBody) -> f32 {
+        if self.angular {
+            self.axis.dot(b.angular_velocity - a.angular_velocity)
+        } else {
+            self.axis.dot(b.velocity_at(self.r_b) - a.velocity_at(self.r_a))
+        }
+    }
+
+    pub fn mass(&self, a: &SolverBody, b: &SolverBody) -> f32 {
+        if self.angular {
+            inverse_or_zero(self.axis.dot((a.inv_inertia_matrix() + b.inv_inertia_matrix()) * self.axis))
+        } else {
+            inverse_or_zero(a.effective_inv_mass(self.r_a, self.axis) + b.effective_inv_mass(self.r_b, self.axis))
+        }
+    }
+
+    /// Apply an impulse that increases the joint speed.
+    pub fn apply(&self, a: &mut SolverBody, b: &mut SolverBody, impulse: f32) {
+        if self.angular {
+            a.apply_angular_impulse(-self.axis * impulse);
+            b.apply_angular_impulse(self.axis * impulse);
+        } else {
+            a.apply_impulse(-self.axis * impulse, self.r_a);
+            b.apply_impulse(self.axis * impulse, self.r_b);
+        }
+    }
+
+    /// Apply a position impulse that increases the joint position.
+    fn apply_position(&self, a: &mut SolverBody, b: &mut SolverBody, impulse: f32) {
+        if self.angular {
+            a.apply_angular_position_impulse(-self.axis * impulse);
+            b.apply_angular_position_impulse(self.axis * impulse);
+        } else {
+            a.apply_position_impulse(-self.axis * impulse, self.r_a);
+            b.apply_position_impulse(self.axis * impulse, self.r_b);
+        }
+    }
+}
+
+impl JointFrame {
+    /// Free axis and joint position of a revolute or prismatic joint for
+    /// the given body poses.
+    pub fn axial(&self, a: &SolverBody, b: &SolverBody) -> Option<(Axial, f32)> {
+        let axis = a.orientation * self.axis_a;
+        match self.kind {
+            JointKind::Revolute => {
+                let axial = Axial {
+                    axis,
+                    r_a: Vec3::ZERO,
+                    r_b: Vec3::ZERO,
+                    angular: true,
+                };
+                Some((axial, hinge_angle(a.orientation, b.orientation, self.axis_a, self.reference)))
+            }
+            JointKind::Prismatic => {
+                let r_b = b.orientation * self.anchor_b;
+                let separation = (b.position + r_b) - (a.position + a.orientation * self.anchor_a);
+                let axial = Axial {
+                    axis,
+                    r_a: (b.position + r_b) - a.position,
+                    r_b,
+                    angular: false,
+                };
+                Some((axial, axis.dot(separation)))
+            }
+            JointKind::Ball | JointKind::Fixed => None,
+        }
+    }
+}
+
+impl JointSolver {
+    /// Build constraints for every joint whose bodies exist, seeding them
+    /// with the impulses from the previous step when `warm_starting` is set.
+    pub fn prepare(
+        sim: &PhysicsSim,
+        bodies: &SolverBodies,
+        impulses: &JointImpulses,
+        warm_starting: bool,
+        timestep: f32,
+    ) -> Self {
+        let joints = joint_frames(sim)
+            .filter(|frame| sim.has_body(frame.handle_a) && sim.has_body(frame.handle_b))
+            .filter_map(|frame| {
+                let body_a = bodies.index(frame.handle_a);
+                let body_b = bodies.index(frame.handle_b);
+                if body_a == body_b {
+                    return None;
+                }
+                let impulse = if warm_starting {
+                    impulses.get(frame.kind, frame.joint)
+                } else {
+                    JointImpulse::default()
+                };
+                let mut constraint = JointConstraint {
+                    motor: Motor::new(frame.control, timestep),
+                    frame,
+                    body_a,
+                    body_b,
+                    r_a: Vec3::ZERO,
+                    r_b: Vec3::ZERO,
+                    basis: [Vec3::ZERO; 2],
+                    linear_mass: Mat3::ZERO,
+                    slider_mass: Mat2::ZERO,
+                    hinge_mass: Mat2::ZERO,
+                    angular_mass: Mat3::ZERO,
+                    axial: None,
+                    impulse,
+                };
+                constraint.update(&bodies.bodies[body_a], &bodies.bodies[body_b]);
+                constraint.project_impulse();
+                Some(constraint)
+            })
+            .collect();
+
+        let planar = PlanarSolver::prepare(sim, bodies);
+
+        Self {
+            joints,
+            planar,
+            inv_timestep: inverse_or_zero(timestep),
+        }
+    }
+
+    /// Apply the impulses carried over from the previous step.
+    pub fn warm_start(&self, bodies: &mut SolverBodies) {
+        for constraint in &self.joints {
+            let (a, b) = bodies.pair_mut(constraint.body_a, constraint.body_b);
+            let impulse = constraint.impulse;
+            a.apply_impulse(-impulse.linear, constraint.r_a);
+            b.apply_impulse(impulse.linear, constraint.r_b);
+            a.apply_angular_impulse(-impulse.angular);
+            b.apply_angular_impulse(impulse.angular);
+            if let Some((axial, _, _)) = constraint.axial {
+                axial.apply(a, b, impulse.motor + impulse.lower - impulse.upper);
+            }
+        }
+    }
+
+    /// One sweep over all joints.
+    pub fn solve_velocities(&mut self, bodies: &mut SolverBodies) {
+        for constraint in &mut self.joints {
+            let (a, b) = bodies.pair_mut(constraint.body_a, constraint.body_b);
+            constraint.solve_axial(a, b, self.inv_timestep);
+
+            let relative_spin = b.angular_velocity - a.angular_velocity;
+            let angular = match constraint.frame.kind {
+                JointKind::Ball => Vec3::ZERO,
+                JointKind::Revolute => {
+                    let [first, second] = constraint.basis;
+                    let error = Vec2::new(first.dot(relative_spin), second.dot(relative_spin));
+                    let lambda = -(constraint.hinge_mass * error);
+                    first * lambda.x + second * lambda.y
+                }
+                JointKind::Prismatic | JointKind::Fixed => -(constraint.angular_mass * relative_spin),
+            };
+            constraint.impulse.angular += angular;
+            a.apply_angular_impulse(-angular);
+            b.apply_angular_impulse(angular);
+
+            let relative = b.velocity_at(constraint.r_b) - a.velocity_at(constraint.r_a);
+            let linear = if constraint.frame.kind.slides() {
+                let [first, second] = constraint.basis;
+                let lambda = -(constraint.slider_mass * Vec2::new(first.dot(relative), second.dot(relative)));
+                first * lambda.x + second * lambda.y
+            } else {
+                -(constraint.linear_mass * relative)
+            };
+            constraint.impulse.linear += linear;
+            a.apply_impulse(-linear, constraint.r_a);
+            b.apply_impulse(linear, constraint.r_b);
+        }
+    }
+
+    /// Remove the velocity components that planar constraints forbid.
+    pub fn solve_planar_velocities(&self, bodies: &mut SolverBodies) {
+        self.planar.solve_velocities(bodies);
+    }
+
+    /// Save accumulated impulses for the next step's warm start.
+    pub fn store_impulses(&self, impulses: &mut JointImpulses) {
+        *impulses = JointImpulses::default();
+        for constraint in &self.joints {
+            let stored = impulses.get_mut(constraint.frame.kind);
+            if stored.len() <= constraint.frame.joint {
+                stored.resize(constraint.frame.joint + 1, JointImpulse::default());
+            }
+            stored[constraint.frame.joint] = constraint.impulse;
+        }
+    }
+
+    /// One position iteration: push joints back within their limits, pull
+    /// anchors back together and undo any rotation the joints do not allow.
+    pub fn solve_positions(&self, bodies: &mut SolverBodies) {
+        for constraint in &self.joints {
+            let frame = &constraint.frame;
+            let (a, b) = bodies.pair_mut(constraint.body_a, constraint.body_b);
+
+            if let (Some((lower, upper)), Some((axial, position))) = (frame.limits, frame.axial(a, b)) {
+                if position < lower {
+                    axial.apply_position(a, b, (lower - position) * axial.mass(a, b));
+                } else if position > upper {
+                    axial.apply_position(a, b, (upper - position) * axial.mass(a, b));
+                }
+            }
+
+            let inv_inertia = a.inv_inertia_matrix() + b.inv_inertia_matrix();
+            let angular = match frame.kind {
+                JointKind::Ball => Vec3::ZERO,
+                JointKind::Revolute => {
+                    let axis_a = a.orientation * frame.axis_a;
+                    let axis_b = b.orientation * frame.axis_b;
+                    let basis = tangent_basis(axis_a);
+                    // Rotating B by `misalignment` (and A by its opposite)
+                    // brings the axes back together.
+                    let misalignment = axis_b.cross(axis_a);
+                    let error = Vec2::new(basis[0].dot(misalignment), basis[1].dot(misalignment));
+                    let lambda = angular_mass(inv_inertia, basis) * error;
+                    basis[0] * lambda.x + basis[1] * lambda.y
+                }
+                JointKind::Prismatic | JointKind::Fixed => {
+                    let error = rotation_error(b.orientation, a.orientation * frame.reference);
+                    inverse_or_zero3(inv_inertia) * error
+                }
+            };
+            a.apply_angular_position_impulse(-angular);
+            b.apply_angular_position_impulse(angular);
+
+            let r_a = a.orientation * frame.anchor_a;
+            let r_b = b.orientation * frame.anchor_b;
+            let separation = (b.position + r_b) - (a.position + r_a);
+            let (linear, r_a) = if frame.kind.slides() {
+                let basis = tangent_basis(a.orientation * frame.axis_a);
+                let r_a = r_a + separation;
+                let error = Vec2::new(basis[0].dot(separation), basis[1].dot(separation));
+                let lambda = -(slider_mass(a, b, r_a, r_b, basis) * error);
+                (basis[0] * lambda.x + basis[1] * lambda.y, r_a)
+            } else {
+                (-(point_mass(a, b, r_a, r_b) * separation), r_a)
+            };
+            a.apply_position_impulse(-linear, r_a);
+            b.apply_position_impulse(linear, r_b);
+        }
+    }
+
+    /// Move bodies with planar constraints back onto their planes and
+    /// remove any rotation out of the plane.
+    pub fn project_planar_positions(&self, bodies: &mut SolverBodies) {
+        self.planar.project_positions(bodies);
+    }
+}
+
+impl PlanarSolver {
+    pub fn prepare(sim: &PhysicsSim, bodies: &SolverBodies) -> Self {
+        let rows = sim
+            .planar_constraints
+            .iter()
+            .enumerate()
+            .filter(|&(index, constraint)| sim.has_body(constraint.body) && sim.has_joint(JointHandle::Planar(index)))
+            .map(|(_, constraint)| {
+                let normal = Vec3::from(constraint.normal).normalize_or_zero();
+                PlanarRow {
+                    body: bodies.index(constraint.body),
+                    normal,
+                    distance: constraint.distance,
+                    reference: body_rotation(constraint.reference_orientation),
+                    basis: tangent_basis(normal),
+                }
+            })
+            .collect();
+        Self { rows }
+    }
+
+    /// Remove the velocity components that planar constraints forbid.
+    pub fn solve_velocities(&self, bodies: &mut SolverBodies) {
+        for row in &self.rows {
+            let body = &mut bodies.bodies[row.body];
+            if !body.is_dynamic() {
+                continue;
+            }
+            body.linear_velocity -= row.normal * row.normal.dot(body.linear_velocity);
+
+            let error = Vec2::new(row.basis[0].dot(body.angular_velocity), row.basis[1].dot(body.angular_velocity));
+            let lambda = -(angular_mass(body.inv_inertia_matrix(), row.basis) * error);
+            body.apply_angular_impulse(row.basis[0] * lambda.x + row.basis[1] * lambda.y);
+        }
+    }
+
+    /// Move bodies with planar constraints back onto their planes and
+    /// remove any rotation out of the plane.
+    pub fn project_positions(&self, bodies: &mut SolverBodies) {
+        for row in &self.rows {
+            let body = &mut bodies.bodies[row.body];
+            if !body.is_dynamic() {
+                continue;
+            }
+            body.position -= row.normal * (row.normal.dot(body.position) - row.distance);
+
+            // Keep only the twist about the normal relative to the reference.
+            let relative = body.orientation * row.reference.inverse();
+            let twist_axis = row.normal * row.normal.dot(Vec3::new(relative.x, relative.y, relative.z));
+            let twist = Quat::from_xyzw(twist_axis.x, twist_axis.y, twist_axis.z, relative.w);
+            let twist = if twist.length_squared() > f32::EPSILON {
+                twist.normalize()
+            } else {
+                Quat::IDENTITY
+            };
+            body.orientation = twist * row.reference;
+        }
+    }
+}
+
+/// Angle or translation and speed of revolute joint `joint`, or `None` if
+/// the joint or one of its bodies does not exist.
+pub(crate) fn revolute_state(sim: &PhysicsSim, joint: usize) -> Option<JointState> {
+    if sim.is_joint_removed(JointHandle::Revolute(joint)) {
+        return None;
+    }
+    axial_state(sim, &revolute_frame(joint, sim.revolute_joints.get(joint)?)?)
+}
+
+/// Translation and speed of prismatic joint `joint`.
+pub(crate) fn prismatic_state(sim: &PhysicsSim, joint: usize) -> Option<JointState> {
+    if sim.is_joint_removed(JointHandle::Prismatic(joint)) {
+        return None;
+    }
+    axial_state(sim, &prismatic_frame(joint, sim.prismatic_joints.get(joint)?)?)
+}
+
+fn axial_state(sim: &PhysicsSim, frame: &JointFrame) -> Option<JointState> {
+    if !(sim.has_body(frame.handle_a) && sim.has_body(frame.handle_b)) {
+        return None;
+    }
+    let a = SolverBody::of(sim, frame.handle_a);
+    let b = SolverBody::of(sim, frame.handle_b);
+    let (axial, position) = frame.axial(&a, &b)?;
+    Some(JointState {
+        position,
+        speed: axial.speed(&a, &b),
+    })
+}
+
+/// Pairs of bodies joined by a joint that disables collision between them.
+pub(crate) fn collision_free_pairs(sim: &PhysicsSim) -> Vec<(BodyHandle, BodyHandle)> {
+    let live = |handle: JointHandle| !sim.is_joint_removed(handle);
+    let distance = sim
+        .joints
+        .iter()
+        .enumerate()
+        .filter(|&(i, j)| j.disable_collision != 0 && live(JointHandle::Distance(i)))
+        .map(|(_, j)| Some((BodyHandle::Sphere(j.body_a as usize), BodyHandle::Sphere(j.body_b as usize))));
+    let revolute = sim
+        .revolute_joints
+        .iter()
+        .enumerate()
+        .filter(|&(i, j)| j.disable_collision != 0 && live(JointHandle::Revolute(i)))
+        .map(|(_, j)| handles(j.body_a_type, j.body_a, j.body_b_type, j.body_b));
+    let prismatic = sim
+        .prismatic_joints
+        .iter()
+        .enumerate()
+        .filter(|&(i, j)| j.disable_collision != 0 && live(JointHandle::Prismatic(i)))
+        .map(|(_, j)| handles(j.body_a_type, j.body_a, j.body_b_type, j.body_b));
+    let ball = sim
+        .ball_joints
+        .iter()
+        .enumerate()
+        .filter(|&(i, j)| j.disable_collision != 0 && live(JointHandle::Ball(i)))
+        .map(|(_, j)| handles(j.body_a_type, j.body_a, j.body_b_type, j.body_b));
+    let fixed = sim
+        .fixed_joints
+        .iter()
+        .enumerate()
+        .filter(|&(i, j)| j.disable_collision != 0 && live(JointHandle::Fixed(i)))
+        .map(|(_, j)| handles(j.body_a_type, j.body_a, j.body_b_type, j.body_b));
+    distance.chain(revolute).chain(prismatic).chain(ball).chain(fixed).flatten().collect()
+}
+
+fn handles(type_a: u32, a: u32, type_b: u32, b: u32) -> Option<(BodyHandle, BodyHandle)> {
+    Some((
+        BodyHandle::from_type_code(type_a, a as usize)?,
+        BodyHandle::from_type_code(type_b, b as usize)?,
+    ))
+}
+
+fn revolute_frame(joint: usize, j: &crate::types::RevoluteJoint) -> Option<JointFrame> {
+    let (handle_a, handle_b) = handles(j.body_a_type, j.body_a, j.body_b_type, j.body_b)?;
+    let axis = Vec3::from(j.axis).normalize_or_zero();
+    let reference = body_rotation(j.reference_rotation);
+    Some(JointFrame {
+        kind: JointKind::Revolute,
+        joint,
+        handle_a,
+        handle_b,
+        anchor_a: j.anchor_a.into(),
+        anchor_b: j.anchor_b.into(),
+        axis_a: axis,
+        axis_b: reference.inverse() * axis,
+        reference,
+        limits: j.limits(),
+        control: j.control(),
+        compliance: j.compliance,
+    })
+}
+
+fn prismatic_frame(joint: usize, j: &crate::types::PrismaticJoint) -> Option<JointFrame> {
+    let (handle_a, handle_b) = handles(j.body_a_type, j.body_a, j.body_b_type, j.body_b)?;
+    let axis = Vec3::from(j.axis).normalize_or_zero();
+    let reference = body_rotation(j.reference_rotation);
+    Some(JointFrame {
+        kind: JointKind::Prismatic,
+        joint,
+        handle_a,
+        handle_b,
+        anchor_a: j.anchor_a.into(),
+        anchor_b: j.anchor_b.into(),
+        axis_a: axis,
+        axis_b: reference.inverse() * axis,
+        reference,
+        limits: j.limits(),
+        control: j.control(),
+        compliance: j.compliance,
+    })
+}
+
+/// Every joint of `sim`, whatever its kind. Removed joints and joints naming
+/// an unknown shape code are skipped.
+pub(super) fn joint_frames(sim: &PhysicsSim) -> impl Iterator<Item = JointFrame> + '_ {
+    let revolute = sim
+        .revolute_joints
+        .iter()
+        .enumerate()
+        .filter_map(|(joint, j)| revolute_frame(joint, j));
+    let prismatic = sim
+        .prismatic_joints
+        .iter()
+        .enumerate()
+        .filter_map(|(joint, j)| prismatic_frame(joint, j));
+    let ball = sim.ball_joints.iter().enumerate().filter_map(|(joint, j)| {
+        let (handle_a, handle_b) = handles(j.body_a_type, j.body_a, j.body_b_type, j.body_b)?;
+        Some(JointFrame {
+            kind: JointKind::Ball,
+            joint,
+            handle_a,
+            handle_b,
+            anchor_a: j.anchor_a.into(),
+            anchor_b: j.anchor_b.into(),
+            axis_a: Vec3::ZERO,
+            axis_b: Vec3::ZERO,
+            reference: Quat::IDENTITY,
+            limits: None,
+            control: JointControl::Off,
+            compliance: j.compliance,
+        })
+    });
+    let fixed = sim.fixed_joints.iter().enumerate().filter_map(|(joint, j)| {
+        let (handle_a, handle_b) = handles(j.body_a_type, j.body_a, j.body_b_type, j.body_b)?;
+        Some(JointFrame {
+            kind: JointKind::Fixed,
+            joint,
+            handle_a,
+            handle_b,
+            anchor_a: j.anchor_a.into(),
+            anchor_b: j.anchor_b.into(),
+            axis_a: Vec3::ZERO,
+            axis_b: Vec3::ZERO,
+            reference: body_rotation(j.relative_rotation),
+            limits: None,
+            control: JointControl::Off,
+            compliance: j.compliance,
+        })
+    });
+    revolute
+        .chain(prismatic)
+        .chain(ball)
+        .chain(fixed)
+        .filter(|frame| !sim.is_joint_removed(frame.kind.handle(frame.joint)))
+}
+
+impl JointConstraint {
+    /// Recompute lever arms, axis basis and effective masses from the
+    /// bodies' current state.
+    fn update(&mut self, a: &SolverBody, b: &SolverBody) {
+        let frame = &self.frame;
+        self.r_a = a.orientation * frame.anchor_a;
+        self.r_b = b.orientation * frame.anchor_b;
+        self.basis = tangent_basis(a.orientation * frame.axis_a);
+        let inv_inertia = a.inv_inertia_matrix() + b.inv_inertia_matrix();
+
+        if frame.kind.slides() {
+            self.r_a = (b.position + self.r_b) - a.position;
+            self.slider_mass = slider_mass(a, b, self.r_a, self.r_b, self.basis);
+        } else {
+            self.linear_mass = point_mass(a, b, self.r_a, self.r_b);
+        }
+        if frame.kind.locks_rotation() {
+            self.angular_mass = inverse_or_zero3(inv_inertia);
+        } else if frame.kind == JointKind::Revolute {
+            self.hinge_mass = angular_mass(inv_inertia, self.basis);
+        }
+        self.axial = frame
+            .axial(a, b)
+            .map(|(axial, position)| (axial, axial.mass(a, b), position));
+    }
+
+    /// Drop the parts of a warm-start impulse that act along free
+    /// directions, since the axis may have turned since it was stored, and
+    /// the impulses of motors and limits that are switched off.
+    fn project_impulse(&mut self) {
+        let [first, second] = self.basis;
+        let onto_basis = |v: Vec3| first * first.dot(v) + second * second.dot(v);
+        if self.frame.kind.slides() {
+            self.impulse.linear = onto_basis(self.impulse.linear);
+        }
+        match self.frame.kind {
+            JointKind::Ball => self.impulse.angular = Vec3::ZERO,
+            JointKind::Revolute => self.impulse.angular = onto_basis(self.impulse.angular),
+            JointKind::Prismatic | JointKind::Fixed => {}
+        }
+        if matches!(self.motor, Motor::Off) {
+            self.impulse.motor = 0.0;
+        }
+        if self.frame.limits.is_none() {
+            self.impulse.lower = 0.0;
+            self.impulse.upper = 0.0;
+        }
+    }
+
+    /// Motor and limit rows along the free axis.
+    fn solve_axial(&mut self, a: &mut SolverBody, b: &mut SolverBody, inv_timestep: f32) {
+        let Some((axial, mass, position)) = self.axial else {
+            return;
+        };
+        let wrap = |error: f32| if axial.angular { (error + PI).rem_euclid(TAU) - PI } else { error };
+
+        match self.motor {
+            Motor::Off => {}
+            Motor::Velocity { speed, max_impulse } => {
+                let lambda = -mass * (axial.speed(a, b) - speed);
+                let applied = accumulate(&mut self.impulse.motor, lambda, -max_impulse, max_impulse);
+                axial.apply(a, b, applied);
+            }
+            Motor::Servo {
+                target,
+                bias_rate,
+                softness,
+                max_impulse,
+            } => {
+                let soft_mass = inverse_or_zero(inverse_or_zero(mass) + softness);
+                let bias = bias_rate * wrap(position - target);
+                let lambda = -soft_mass * (axial.speed(a, b) + bias + softness * self.impulse.motor);
+                let applied = accumulate(&mut self.impulse.motor, lambda, -max_impulse, max_impulse);
+                axial.apply(a, b, applied);
+            }
+        }
+
+        if let Some((lower, upper)) = self.frame.limits {
+            let gap = position - lower;
+            let bias = if gap > 0.0 { gap * inv_timestep } else { 0.0 };
+            let lambda = -mass * (axial.speed(a, b) + bias);
+            let applied = accumulate(&mut self.impulse.lower, lambda, 0.0, f32::INFINITY);
+            axial.apply(a, b, applied);
+
+            let gap = upper - position;
+            let bias = if gap > 0.0 { gap * inv_timestep } else { 0.0 };
+            let lambda = -mass * (bias - axial.speed(a, b));
+            let applied = accumulate(&mut self.impulse.upper, lambda, 0.0, f32::INFINITY);
+            axial.apply(a, b, -applied);
+        }
+    }
+}
@@ -0,0 +1,408 @@
+//! # Constraint Solver
+//!
+//! The solver works on a flat array of [`SolverBody`] values gathered from the
+//! per-shape body vectors of [`PhysicsSim`]. Constraints address bodies by
+//! their position in that array, which lets one code path handle every pair
+//! of shapes. Results are scattered back into the simulation afterwards.
+
+mod contact;
+mod island;
+mod joint;
+mod soft_contact;
+mod xpbd;
+
+pub(crate) use contact::{solve_positions, ContactSolver};
+pub(crate) use island::{build_islands, links, Island};
+pub(crate) use joint::{collision_free_pairs, prismatic_state, revolute_state, JointImpulses, JointSolver, PlanarSolver};
+pub(crate) use soft_contact::SoftContactSolver;
+pub(crate) use xpbd::XpbdSolver;
+
+use glam::{Mat3, Quat, Vec3};
+
+use crate::body::{
+    box_inverse_inertia, capsule_inverse_inertia, cylinder_inverse_inertia, sphere_inverse_inertia, BodyHandle,
+};
+use crate::collision::{body_rotation, BodyFrame};
+use crate::compound::Compound;
+use crate::mesh::ConvexHull;
+use crate::simulation::PhysicsSim;
+use crate::types::{BodyType, BoxBody, Capsule, Cylinder, Sphere};
+
+/// Rigid body state used while solving constraints.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct SolverBody {
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub linear_velocity: Vec3,
+    pub angular_velocity: Vec3,
+    pub inv_mass: f32,
+    /// Diagonal of the inverse inertia tensor in the body frame.
+    pub inv_inertia: Vec3,
+}
+
+impl SolverBody {
+    /// Body with infinite mass that never moves.
+    const STATIC: Self = Self {
+        position: Vec3::ZERO,
+        orientation: Quat::IDENTITY,
+        linear_velocity: Vec3::ZERO,
+        angular_velocity: Vec3::ZERO,
+        inv_mass: 0.0,
+        inv_inertia: Vec3::ZERO,
+    };
+
+    fn new(
+        position: Vec3,
+        orientation: [f32; 4],
+        linear_velocity: Vec3,
+        angular_velocity: Vec3,
+        body_type: BodyType,
+        mass: f32,
+        inv_inertia: Vec3,
+    ) -> Self {
+        let dynamic = body_type == BodyType::Dynamic && mass > 0.0;
+        Self {
+            position,
+            orientation: body_rotation(orientation),
+            linear_velocity,
+            angular_velocity,
+            inv_mass: if dynamic { 1.0 / mass } else { 0.0 },
+            inv_inertia: if dynamic { inv_inertia } else { Vec3::ZERO },
+        }
+    }
+
+    fn sphere(s: &Sphere) -> Self {
+        Self::new(
+            s.pos.into(),
+            s.orientation,
+            s.vel.into(),
+            s.angular_vel.into(),
+            BodyType::Dynamic,
+            s.mass,
+            sphere_inverse_inertia(s.mass, s.radius).into(),
+        )
+    }
+
+    fn box_body(b: &BoxBody) -> Self {
+        Self::new(
+            b.pos.into(),
+            b.orientation,
+            b.vel.into(),
+            b.angular_vel.into(),
+            b.body_type,
+            b.mass,
+            box_inverse_inertia(b.mass, b.half_extents).into(),
+        )
+    }
+
+    fn cylinder(c: &Cylinder) -> Self {
+        Self::new(
+            c.pos.into(),
+            c.orientation,
+            c.vel.into(),
+            c.angular_vel.into(),
+            c.body_type,
+            c.mass,
+            cylinder_inverse_inertia(c.mass, c.radius, c.half_height).into(),
+        )
+    }
+
+    fn capsule(c: &Capsule) -> Self {
+        Self::new(
+            c.pos.into(),
+            c.orientation,
+            c.vel.into(),
+            c.angular_vel.into(),
+            c.body_type,
+            c.mass,
+            capsule_inverse_inertia(c.mass, c.radius, c.half_height).into(),
+        )
+    }
+
+    fn hull(h: &ConvexHull) -> Self {
+        let inertia = Vec3::from(h.inertia);
+        Self::new(
+            h.pos.into(),
+            h.orientation,
+            h.vel.into(),
+            h.angular_vel.into(),
+            h.body_type,
+            h.mass,
+            Vec3::ONE / inertia.max(Vec3::splat(f32::MIN_POSITIVE)),
+        )
+    }
+
+    fn compound(c: &Compound) -> Self {
+        let inertia = Vec3::from(c.inertia);
+        Self::new(
+            c.pos.into(),
+            c.orientation,
+            c.vel.into(),
+            c.angular_vel.into(),
+            c.body_type,
+            c.mass,
+            Vec3::ONE / inertia.max(Vec3::splat(f32::MIN_POSITIVE)),
+        )
+    }
+
+    /// Solver state of a single existing body. Planes, heightfields and
+    /// meshes are static.
+    pub fn of(sim: &PhysicsSim, handle: BodyHandle) -> Self {
+        match handle {
+            BodyHandle::Sphere(i) => Self::sphere(&sim.spheres[i]),
+            BodyHandle::Box(i) => Self::box_body(&sim.boxes[i]),
+            BodyHandle::Cylinder(i) => Self::cylinder(&sim.cylinders[i]),
+            BodyHandle::Capsule(i) => Self::capsule(&sim.capsules[i]),
+            BodyHandle::Hull(i) => Self::hull(&sim.hulls[i]),
+            BodyHandle::Compound(i) => Self::compound(&sim.compounds[i]),
+            BodyHandle::Plane(_) | BodyHandle::Heightfield(_) | BodyHandle::Mesh(_) => Self::STATIC,
+        }
+    }
+
+    pub fn is_dynamic(&self) -> bool {
+        self.inv_mass > 0.0
+    }
+
+    pub fn frame(&self) -> BodyFrame {
+        BodyFrame {
+            position: self.position,
+            orientation: self.orientation,
+        }
+    }
+
+    /// Multiply `v` by the world-space inverse inertia tensor.
+    pub fn inv_inertia_world(&self, v: Vec3) -> Vec3 {
+        self.orientation * (self.inv_inertia * (self.orientation.inverse() * v))
+    }
+
+    /// World-space inverse inertia tensor.
+    pub fn inv_inertia_matrix(&self) -> Mat3 {
+        let rotation = Mat3::from_quat(self.orientation);
+        rotation * Mat3::from_diagonal(self.inv_inertia) * rotation.transpose()
+    }
+
+    /// Velocity of the material point at offset `r` from the body origin.
+    pub fn velocity_at(&self, r: Vec3) -> Vec3 {
+        self.linear_velocity + self.angular_velocity.cross(r)
+    }
+
+    /// Effective inverse mass along `direction` for a point at offset `r`.
+    pub fn effective_inv_mass(&self, r: Vec3, direction: Vec3) -> f32 {
+        let rn = r.cross(direction);
+        self.inv_mass + rn.dot(self.inv_inertia_world(rn))
+    }
+
+    pub fn apply_impulse(&mut self, impulse: Vec3, r: Vec3) {
+        self.linear_velocity += impulse * self.inv_mass;
+        self.angular_velocity += self.inv_inertia_world(r.cross(impulse));
+    }
+
+    pub fn apply_angular_impulse(&mut self, impulse: Vec3) {
+        self.angular_velocity += self.inv_inertia_world(impulse);
+    }
+
+    /// Move the body as if `impulse` had acted for one unit of time.
+    pub fn apply_position_impulse(&mut self, impulse: Vec3, r: Vec3) {
+        if !self.is_dynamic() {
+            return;
+        }
+        self.position += impulse * self.inv_mass;
+        self.apply_angular_position_impulse(r.cross(impulse));
+    }
+
+    /// Rotate the body as if the angular `impulse` had acted for one unit of
+    /// time.
+    pub fn apply_angular_position_impulse(&mut self, impulse: Vec3) {
+        if !self.is_dynamic() {
+            return;
+        }
+        let rotation = self.inv_inertia_world(impulse);
+        let delta = Quat::from_xyzw(rotation.x, rotation.y, rotation.z, 0.0) * self.orientation;
+        self.orientation = Quat::from_xyzw(
+            self.orientation.x + 0.5 * delta.x,
+            self.orientation.y + 0.5 * delta.y,
+            self.orientation.z + 0.5 * delta.z,
+            self.orientation.w + 0.5 * delta.w,
+        )
+        .normalize();
+    }
+}
+
+/// Deterministic orthonormal pair of vectors perpendicular to `normal`.
+pub(crate) fn tangent_basis(normal: Vec3) -> [Vec3; 2] {
+    let first = if normal.x.abs() >= 0.577_35 {
+        Vec3::new(normal.y, -normal.x, 0.0).normalize()
+    } else {
+        Vec3::new(0.0, normal.z, -normal.y).normalize()
+    };
+    [first, normal.cross(first)]
+}
+
+/// Solver bodies for every body of a [`PhysicsSim`].
+///
+/// Layout: spheres, then boxes, then cylinders, then capsules, then hulls,
+/// then compounds, then a single static body shared by all static geometry. Sleeping bodies
+/// are gathered as static, and articulation links as kinematic.
+pub(crate) struct SolverBodies {
+    pub bodies: Vec<SolverBody>,
+    box_offset: usize,
+    cylinder_offset: usize,
+    capsule_offset: usize,
+    hull_offset: usize,
+    compound_offset: usize,
+    static_index: usize,
+}
+
+impl SolverBodies {
+    pub fn gather(sim: &PhysicsSim) -> Self {
+        let count = sim.spheres.len()
+            + sim.boxes.len()
+            + sim.cylinders.len()
+            + sim.capsules.len()
+            + sim.hulls.len()
+            + sim.compounds.len();
+        let mut bodies = Vec::with_capacity(count + 1);
+        bodies.extend(sim.spheres.iter().map(SolverBody::sphere));
+        let box_offset = bodies.len();
+        bodies.extend(sim.boxes.iter().map(SolverBody::box_body));
+        let cylinder_offset = bodies.len();
+        bodies.extend(sim.cylinders.iter().map(SolverBody::cylinder));
+        let capsule_offset = bodies.len();
+        bodies.extend(sim.capsules.iter().map(SolverBody::capsule));
+        let hull_offset = bodies.len();
+        bodies.extend(sim.hulls.iter().map(SolverBody::hull));
+        let compound_offset = bodies.len();
+        bodies.extend(sim.compounds.iter().map(SolverBody::compound));
+        let static_index = bodies.len();
+        bodies.push(SolverBody::STATIC);
+
+        let mut bodies = Self {
+            bodies,
+            box_offset,
+            cylinder_offset,
+            capsule_offset,
+            hull_offset,
+            compound_offset,
+            static_index,
+        };
+        // Sleeping bodies hold still, like static ones, until they wake;
+        // removed bodies hold still for good
+        for &handle in sim.sleeping.keys().chain(&sim.removed_bodies) {
+            let index = bodies.index(handle);
+            bodies.bodies[index] = SolverBody {
+                position: bodies.bodies[index].position,
+                orientation: bodies.bodies[index].orientation,
+                ..SolverBody::STATIC
+            };
+        }
+        // Links move with their articulation, which takes their contacts
+        for &handle in sim.articulation_links.keys() {
+            let index = bodies.index(handle);
+            bodies.bodies[index].inv_mass = 0.0;
+            bodies.bodies[index].inv_inertia = Vec3::ZERO;
+        }
+        bodies
+    }
+
+    /// Position of `handle` in [`Self::bodies`].
+    pub fn index(&self, handle: BodyHandle) -> usize {
+        match handle {
+            BodyHandle::Sphere(i) => i,
+            BodyHandle::Box(i) => self.box_offset + i,
+            BodyHandle::Cylinder(i) => self.cylinder_offset + i,
+            BodyHandle::Capsule(i) => self.capsule_offset + i,
+            BodyHandle::Hull(i) => self.hull_offset + i,
+            BodyHandle::Compound(i) => self.compound_offset + i,
+            BodyHandle::Plane(_) | BodyHandle::Heightfield(_) | BodyHandle::Mesh(_) => self.static_index,
+        }
+    }
+
+    /// Mutable access to two distinct bodies at once.
+    pub fn pair_mut(&mut self, a: usize, b: usize) -> (&mut SolverBody, &mut SolverBody) {
+        debug_assert_ne!(a, b);
+        if a < b {
+            let (low, high) = self.bodies.split_at_mut(b);
+            (&mut low[a], &mut high[0])
+        } else {
+            let (low, high) = self.bodies.split_at_mut(a);
+            (&mut high[0], &mut low[b])
+        }
+    }
+
+    /// Write velocities of dynamic bodies back into the simulation.
+    pub fn scatter_velocities(&self, sim: &mut PhysicsSim) {
+        for (sphere, body) in sim.spheres.iter_mut().zip(&self.bodies) {
+            sphere.vel = body.linear_velocity.into();
+            sphere.angular_vel = body.angular_velocity.into();
+        }
+        for (box_body, body) in sim.boxes.iter_mut().zip(&self.bodies[self.box_offset..]) {
+            if body.is_dynamic() {
+                box_body.vel = body.linear_velocity.into();
+                box_body.angular_vel = body.angular_velocity.into();
+            }
+        }
+        for (cylinder, body) in sim.cylinders.iter_mut().zip(&self.bodies[self.cylinder_offset..]) {
+            if body.is_dynamic() {
+                cylinder.vel = body.linear_velocity.into();
+                cylinder.angular_vel = body.angular_velocity.into();
+            }
+        }
+        for (capsule, body) in sim.capsules.iter_mut().zip(&self.bodies[self.capsule_offset..]) {
+            if body.is_dynamic() {
+                capsule.vel = body.linear_velocity.into();
+                capsule.angular_vel = body.angular_velocity.into();
+            }
+        }
+        for (hull, body) in sim.hulls.iter_mut().zip(&self.bodies[self.hull_offset..]) {
+            if body.is_dynamic() {
+                hull.vel = body.linear_velocity.into();
+                hull.angular_vel = body.angular_velocity.into();
+            }
+        }
+        for (compound, body) in sim.compounds.iter_mut().zip(&self.bodies[self.compound_offset..]) {
+            if body.is_dynamic() {
+                compound.vel = body.linear_velocity.into();
+                compound.angular_vel = body.angular_velocity.into();
+            }
+        }
+    }
+
+    /// Write positions and orientations of dynamic bodies back into the
+    /// simulation.
+    pub fn scatter_positions(&self, sim: &mut PhysicsSim) {
+        for (sphere, body) in sim.spheres.iter_mut().zip(&self.bodies) {
+            sphere.pos = body.position.into();
+            sphere.orientation = body.orientation.to_array();
+        }
+        for (box_body, body) in sim.boxes.iter_mut().zip(&self.bodies[self.box_offset..]) {
+            if body.is_dynamic() {
+                box_body.pos = body.position.into();
+                box_body.orientation = body.orientation.to_array();
+            }
+        }
+        for (cylinder, body) in sim.cylinders.iter_mut().zip(&self.bodies[self.cylinder_offset..]) {
+            if body.is_dynamic() {
+                cylinder.pos = body.position.into();
+                cylinder.orientation = body.orientation.to_array();
+            }
+        }
+        for (capsule, body) in sim.capsules.iter_mut().zip(&self.bodies[self.capsule_offset..]) {
+            if body.is_dynamic() {
+                capsule.pos = body.position.into();
+                capsule.orientation = body.orientation.to_array();
+            }
+        }
+        for (hull, body) in sim.hulls.iter_mut().zip(&self.bodies[self.hull_offset..]) {
+            if body.is_dynamic() {
+                hull.pos = body.position.into();
+                hull.orientation = body.orientation.to_array();
+            }
+        }
+        for (compound, body) in sim.compounds.iter_mut().zip(&self.bodies[self.compound_offset..]) {
+            if body.is_dynamic() {
+                compound.pos = body.position.into();
+                compound.orientation = body.orientation.to_array();
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Compliant penalty contacts for [`ContactModel::Soft`].
+//!
+//! Every manifold point pushes with a force that is a smooth function of its
+//! current depth and normal speed, so small changes to the bodies' state
+//! change the response smoothly. The depth is measured again from the
+//! point's surface anchors every time the impulses are applied, which lets
+//! the XPBD substeps reuse the same points.
+//!
+//! [`ContactModel::Soft`]: crate::types::ContactModel::Soft
+
+use glam::Vec3;
+
+use super::xpbd::pair_inv_mass;
+use super::SolverBodies;
+use crate::collision::ManifoldCache;
+use crate::simulation::PhysicsSim;
+use crate::types::SoftContact;
+
+struct SoftPoint {
+    manifold: usize,
+    point: usize,
+    body_a: usize,
+    body_b: usize,
+    normal: Vec3,
+    local_a: Vec3,
+    local_b: Vec3,
+    friction: f32,
+    /// Normal impulse summed over every application this step.
+    impulse: f32,
+}
+
+/// Penalty contacts of one step.
+pub(crate) struct SoftContactSolver {
+    model: SoftContact,
+    points: Vec<SoftPoint>,
+}
+
+impl SoftContactSolver {
+    pub fn prepare(sim: &PhysicsSim, bodies: &SolverBodies, model: SoftContact) -> Self {
+        // Contacts of sleeping pairs keep the impulses they fell asleep with
+        let points = sim
+            .manifolds
+            .values()
+            .enumerate()
+            .filter(|(_, manifold)| sim.is_awake(manifold.body_a) || sim.is_awake(manifold.body_b))
+            .flat_map(|(manifold_index, manifold)| {
+                let body_a = bodies.index(manifold.body_a);
+                let body_b = bodies.index(manifold.body_b);
+                manifold.points.iter().enumerate().map(move |(point_index, point)| SoftPoint {
+                    manifold: manifold_index,
+                    point: point_index,
+                    body_a,
+                    body_b,
+                    normal: manifold.normal.into(),
+                    local_a: point.local_a.into(),
+                    local_b: point.local_b.into(),
+                    friction: manifold.friction,
+                    impulse: 0.0,
+                })
+            })
+            .collect();
+        Self { model, points }
+    }
+
+    /// Apply the contact impulses of an interval of length `dt` to the
+    /// bodies' velocities.
+    pub fn apply(&mut self, bodies: &mut SolverBodies, dt: f32) {
+        let model = self.model;
+        for point in &mut self.points {
+            let (a, b) = bodies.pair_mut(point.body_a, point.body_b);
+            let normal = point.normal;
+            let (r_a, r_b) = (a.orientation * point.local_a, b.orientation * point.local_b);
+            let depth = ((a.position + r_a) - (b.position + r_b)).dot(normal);
+
+            let normal_speed = normal.dot(b.velocity_at(r_b) - a.velocity_at(r_a));
+            let inv_mass = pair_inv_mass(a, b, r_a, r_b, normal);
+            let normal_impulse = model.normal_impulse(depth, normal_speed, inv_mass, dt);
+            a.apply_impulse(-normal * normal_impulse, r_a);
+            b.apply_impulse(normal * normal_impulse, r_b);
+            point.impulse += normal_impulse;
+
+            let relative = b.velocity_at(r_b) - a.velocity_at(r_a);
+            let slip = relative - normal * normal.dot(relative);
+            let direction = slip.normalize_or_zero();
+            let inv_mass = pair_inv_mass(a, b, r_a, r_b, direction);
+            let impulse: Vec3 = model
+                .friction_impulse(slip.into(), normal_impulse, point.friction, inv_mass, dt)
+                .into();
+            a.apply_impulse(-impulse, r_a);
+            b.apply_impulse(impulse, r_b);
+        }
+    }
+
+    /// Report the normal impulse each contact point applied over the step.
+    pub fn store_impulses(&self, manifolds: &mut ManifoldCache) {
+        let mut manifolds: Vec<_> = manifolds.values_mut().collect();
+        for point in &self.points {
+            let contact = &mut manifolds[point.manifold].points[point.point];
+            contact.normal_impulse = point.impulse;
+            contact.tangent_impulse = [0.0; 2];
+        }
+    }
+}
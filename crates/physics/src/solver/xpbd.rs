@@ -0,0 +1,442 @@
+//! Extended position-based dynamics (XPBD).
+//!
+//! Follows Müller et al., "Detailed Rigid Body Simulation with Extended
+//! Position Based Dynamics" (2020). A step is split into substeps of length
+//! `h`, and every substep
+//!
+//! 1. integrates velocities and poses,
+//! 2. projects joints and contacts once, softening each correction by its
+//!    compliance `α / h²`,
+//! 3. turns the positional corrections into velocity changes, and
+//! 4. applies restitution, dynamic friction and joint motors to those
+//!    velocities.
+//!
+//! Contacts come from the manifolds found at the start of the step; their
+//! surface points are re-evaluated at the bodies' poses in every substep.
+//! Small substeps keep the solve accurate for stiff joints and large mass
+//! ratios without extra iterations. With [`ContactModel::Soft`] the contacts
+//! are not projected; their penalty impulses are added to the velocities
+//! before the poses are integrated.
+//!
+//! [`ContactModel::Soft`]: crate::types::ContactModel::Soft
+
+use std::f32::consts::{PI, TAU};
+
+use glam::{Quat, Vec3};
+
+use super::joint::{inverse_or_zero, joint_frames, rotation_error, JointFrame, JointKind};
+use super::{PlanarSolver, SoftContactSolver, SolverBodies, SolverBody};
+use crate::collision::ManifoldCache;
+use crate::integrator::advance_body;
+use crate::simulation::PhysicsSim;
+use crate::types::{ContactModel, Integrator, JointControl};
+
+/// Compliance and Lagrange multiplier of one scalar constraint during a
+/// substep.
+#[derive(Copy, Clone)]
+struct Row {
+    /// Compliance divided by the squared substep.
+    alpha: f32,
+    lambda: f32,
+    /// Largest magnitude the multiplier may reach.
+    max_lambda: f32,
+}
+
+impl Row {
+    const RIGID: Self = Self {
+        alpha: 0.0,
+        lambda: 0.0,
+        max_lambda: f32::INFINITY,
+    };
+
+    fn new(compliance: f32, substep: f32) -> Self {
+        Self {
+            alpha: compliance.max(0.0) / (substep * substep),
+            ..Self::RIGID
+        }
+    }
+
+    /// Multiplier change that removes an error of length `error` for a
+    /// generalized inverse mass `inv_mass`.
+    fn solve(&mut self, error: f32, inv_mass: f32) -> f32 {
+        let denominator = inv_mass + self.alpha;
+        if denominator <= f32::EPSILON {
+            return 0.0;
+        }
+        let delta = (-error - self.alpha * self.lambda) / denominator;
+        let total = (self.lambda + delta).clamp(-self.max_lambda, self.max_lambda);
+        let applied = total - self.lambda;
+        self.lambda = total;
+        applied
+    }
+}
+
+/// Move B's point at `r_b` and A's point at `r_a` so that `error`, the
+/// offset of B's point from where it should be, shrinks.
+fn correct_position(a: &mut SolverBody, b: &mut SolverBody, r_a: Vec3, r_b: Vec3, error: Vec3, row: &mut Row) {
+    let length = error.length();
+    if length <= f32::EPSILON {
+        return;
+    }
+    let direction = error / length;
+    let inv_mass = a.effective_inv_mass(r_a, direction) + b.effective_inv_mass(r_b, direction);
+    let impulse = direction * row.solve(length, inv_mass);
+    a.apply_position_impulse(-impulse, r_a);
+    b.apply_position_impulse(impulse, r_b);
+}
+
+/// Rotate B against A so that `error`, the rotation vector by which B is
+/// turned too far, shrinks.
+fn correct_rotation(a: &mut SolverBody, b: &mut SolverBody, error: Vec3, row: &mut Row) {
+    let angle = error.length();
+    if angle <= f32::EPSILON {
+        return;
+    }
+    let axis = error / angle;
+    let inv_mass = axis.dot(a.inv_inertia_world(axis)) + axis.dot(b.inv_inertia_world(axis));
+    let impulse = axis * row.solve(angle, inv_mass);
+    a.apply_angular_position_impulse(-impulse);
+    b.apply_angular_position_impulse(impulse);
+}
+
+/// Inverse mass of `a` and `b` for an impulse along `direction` at their
+/// points `r_a` and `r_b`.
+pub(super) fn pair_inv_mass(a: &SolverBody, b: &SolverBody, r_a: Vec3, r_b: Vec3, direction: Vec3) -> f32 {
+    a.effective_inv_mass(r_a, direction) + b.effective_inv_mass(r_b, direction)
+}
+
+struct XpbdJoint {
+    frame: JointFrame,
+    body_a: usize,
+    body_b: usize,
+    /// Joint compliance including the shared [`crate::types::JointParams`] value.
+    compliance: f32,
+}
+
+struct XpbdContact {
+    manifold: usize,
+    point: usize,
+    body_a: usize,
+    body_b: usize,
+    normal: Vec3,
+    local_a: Vec3,
+    local_b: Vec3,
+    friction: f32,
+    restitution: f32,
+    compliance: f32,
+    /// Normal multiplier of the current substep.
+    lambda: f32,
+    /// Normal velocity before the positions were projected, negative when
+    /// the surfaces approach.
+    approach_speed: f32,
+    /// Normal impulse summed over the substeps of this step.
+    impulse: f32,
+}
+
+/// Joints and contacts of one step, solved with XPBD substeps.
+pub(crate) struct XpbdSolver {
+    joints: Vec<XpbdJoint>,
+    contacts: Vec<XpbdContact>,
+    /// Penalty contacts that replace `contacts` under [`ContactModel::Soft`].
+    soft: Option<SoftContactSolver>,
+    planar: PlanarSolver,
+    restitution_threshold: f32,
+    integrator: Integrator,
+}
+
+impl XpbdSolver {
+    pub fn prepare(sim: &PhysicsSim, bodies: &SolverBodies) -> Self {
+        let joints = joint_frames(sim)
+            .filter(|frame| sim.has_body(frame.handle_a) && sim.has_body(frame.handle_b))
+            .filter_map(|frame| {
+                let body_a = bodies.index(frame.handle_a);
+                let body_b = bodies.index(frame.handle_b);
+                (body_a != body_b).then(|| XpbdJoint {
+                    compliance: frame.compliance + sim.joint_params.compliance,
+                    frame,
+                    body_a,
+                    body_b,
+                })
+            })
+            .collect();
+
+        let soft = match sim.contact_model {
+            ContactModel::Hard => None,
+            ContactModel::Soft(model) => Some(SoftContactSolver::prepare(sim, bodies, model)),
+        };
+
+        // Contacts of sleeping pairs keep the impulses they fell asleep with
+        let contacts = sim
+            .manifolds
+            .values()
+            .enumerate()
+            .filter(|_| soft.is_none())
+            .filter(|(_, manifold)| sim.is_awake(manifold.body_a) || sim.is_awake(manifold.body_b))
+            .flat_map(|(manifold_index, manifold)| {
+                let body_a = bodies.index(manifold.body_a);
+                let body_b = bodies.index(manifold.body_b);
+                manifold.points.iter().enumerate().map(move |(point_index, point)| XpbdContact {
+                    manifold: manifold_index,
+                    point: point_index,
+                    body_a,
+                    body_b,
+                    normal: manifold.normal.into(),
+                    local_a: point.local_a.into(),
+                    local_b: point.local_b.into(),
+                    friction: manifold.friction,
+                    restitution: manifold.restitution,
+                    compliance: manifold.compliance,
+                    lambda: 0.0,
+                    approach_speed: 0.0,
+                    impulse: 0.0,
+                })
+            })
+            .collect();
+
+        Self {
+            joints,
+            contacts,
+            soft,
+            planar: PlanarSolver::prepare(sim, bodies),
+            restitution_threshold: sim.contact_params.restitution_threshold,
+            integrator: sim.integrator,
+        }
+    }
+
+    /// Advance `bodies` by one substep of length `substep`. `external` is the
+    /// linear and angular velocity change from gravity and applied forces
+    /// over one substep.
+    pub fn substep(&mut self, bodies: &mut SolverBodies, external: &[(Vec3, Vec3)], substep: f32) {
+        let previous: Vec<(Vec3, Quat)> = bodies.bodies.iter().map(|body| (body.position, body.orientation)).collect();
+        let initial = (self.integrator != Integrator::SemiImplicitEuler).then(|| bodies.bodies.clone());
+        for (body, &(dv, dw)) in bodies.bodies.iter_mut().zip(external) {
+            body.linear_velocity += dv;
+            body.angular_velocity += dw;
+        }
+        if let Some(soft) = &mut self.soft {
+            soft.apply(bodies, substep);
+        }
+        for (index, body) in bodies.bodies.iter_mut().enumerate() {
+            match &initial {
+                Some(initial) if body.is_dynamic() => advance_body(self.integrator, body, &initial[index], substep),
+                _ => {
+                    body.position += body.linear_velocity * substep;
+                    body.orientation =
+                        (Quat::from_scaled_axis(body.angular_velocity * substep) * body.orientation).normalize();
+                }
+            }
+        }
+        let predicted: Vec<(Vec3, Quat)> = bodies.bodies.iter().map(|body| (body.position, body.orientation)).collect();
+
+        for contact in &mut self.contacts {
+            let (a, b) = bodies.pair_mut(contact.body_a, contact.body_b);
+            let (r_a, r_b) = (a.orientation * contact.local_a, b.orientation * contact.local_b);
+            contact.approach_speed = contact.normal.dot(b.velocity_at(r_b) - a.velocity_at(r_a));
+            contact.lambda = 0.0;
+        }
+
+        self.solve_joint_positions(bodies, substep);
+        self.solve_contact_positions(bodies, &previous, substep);
+        self.planar.project_positions(bodies);
+
+        // The velocity change equals the projection's displacement over the
+        // substep. Adding it to the integrated velocity, rather than taking
+        // the whole displacement, keeps f32 rounding of large coordinates
+        // out of free motion.
+        for (body, &(position, orientation)) in bodies.bodies.iter_mut().zip(&predicted) {
+            if body.is_dynamic() {
+                body.linear_velocity += (body.position - position) / substep;
+                let delta = body.orientation * orientation.inverse();
+                let delta = if delta.w < 0.0 { -delta } else { delta };
+                body.angular_velocity += delta.to_scaled_axis() / substep;
+            }
+        }
+
+        self.solve_contact_velocities(bodies, substep);
+        self.solve_joint_velocities(bodies, substep);
+        self.planar.solve_velocities(bodies);
+    }
+
+    fn solve_joint_positions(&self, bodies: &mut SolverBodies, substep: f32) {
+        for joint in &self.joints {
+            let frame = &joint.frame;
+            let (a, b) = bodies.pair_mut(joint.body_a, joint.body_b);
+
+            if let (Some((lower, upper)), Some((axial, position))) = (frame.limits, frame.axial(a, b)) {
+                let excess = if position < lower {
+                    position - lower
+                } else if position > upper {
+                    position - upper
+                } else {
+                    0.0
+                };
+                let error = axial.axis * excess;
+                let mut row = Row::RIGID;
+                if axial.angular {
+                    correct_rotation(a, b, error, &mut row);
+                } else {
+                    correct_position(a, b, axial.r_a, axial.r_b, error, &mut row);
+                }
+            }
+
+            if let JointControl::Position {
+                target,
+                stiffness,
+                max_force,
+                ..
+            } = frame.control
+            {
+                if let (true, Some((axial, position))) = (stiffness > 0.0, frame.axial(a, b)) {
+                    let mut row = Row::new(1.0 / stiffness, substep);
+                    row.max_lambda = max_force * substep * substep;
+                    let offset = position - target;
+                    if axial.angular {
+                        let offset = (offset + PI).rem_euclid(TAU) - PI;
+                        correct_rotation(a, b, axial.axis * offset, &mut row);
+                    } else {
+                        correct_position(a, b, axial.r_a, axial.r_b, axial.axis * offset, &mut row);
+                    }
+                }
+            }
+
+            let mut row = Row::new(joint.compliance, substep);
+            match frame.kind {
+                JointKind::Ball => {}
+                JointKind::Revolute => {
+                    let axis_a = a.orientation * frame.axis_a;
+                    let axis_b = b.orientation * frame.axis_b;
+                    correct_rotation(a, b, axis_a.cross(axis_b), &mut row);
+                }
+                JointKind::Prismatic | JointKind::Fixed => {
+                    let error = -rotation_error(b.orientation, a.orientation * frame.reference);
+                    correct_rotation(a, b, error, &mut row);
+                }
+            }
+
+            let mut row = Row::new(joint.compliance, substep);
+            let r_a = a.orientation * frame.anchor_a;
+            let r_b = b.orientation * frame.anchor_b;
+            let separation = (b.position + r_b) - (a.position + r_a);
+            if frame.kind.slides() {
+                let axis = a.orientation * frame.axis_a;
+                let off_axis = separation - axis * axis.dot(separation);
+                correct_position(a, b, r_a + separation, r_b, off_axis, &mut row);
+            } else {
+                correct_position(a, b, r_a, r_b, separation, &mut row);
+            }
+        }
+    }
+
+    fn solve_contact_positions(&mut self, bodies: &mut SolverBodies, previous: &[(Vec3, Quat)], substep: f32) {
+        for contact in &mut self.contacts {
+            let (a, b) = bodies.pair_mut(contact.body_a, contact.body_b);
+            let normal = contact.normal;
+            let surface = |body: &SolverBody, local: Vec3| body.position + body.orientation * local;
+
+            let depth = (surface(a, contact.local_a) - surface(b, contact.local_b)).dot(normal);
+            if depth <= 0.0 {
+                continue;
+            }
+            let (r_a, r_b) = (a.orientation * contact.local_a, b.orientation * contact.local_b);
+            let mut row = Row::new(contact.compliance, substep);
+            correct_position(a, b, r_a, r_b, -normal * depth, &mut row);
+            contact.lambda = row.lambda.abs();
+
+            // Static friction: undo the tangential slip of this substep if
+            // the normal force can hold it.
+            let (position_a, orientation_a) = previous[contact.body_a];
+            let (position_b, orientation_b) = previous[contact.body_b];
+            let moved_a = surface(a, contact.local_a) - (position_a + orientation_a * contact.local_a);
+            let moved_b = surface(b, contact.local_b) - (position_b + orientation_b * contact.local_b);
+            let slip = moved_a - moved_b;
+            let slip = slip - normal * normal.dot(slip);
+            let length = slip.length();
+            if length <= f32::EPSILON {
+                continue;
+            }
+            let (r_a, r_b) = (a.orientation * contact.local_a, b.orientation * contact.local_b);
+            let inv_mass = pair_inv_mass(a, b, r_a, r_b, slip / length);
+            if length < contact.friction * contact.lambda * inv_mass {
+                let mut row = Row::RIGID;
+                correct_position(a, b, r_a, r_b, -slip, &mut row);
+            }
+        }
+    }
+
+    fn solve_contact_velocities(&mut self, bodies: &mut SolverBodies, substep: f32) {
+        for contact in &mut self.contacts {
+            if contact.lambda <= 0.0 {
+                continue;
+            }
+            let normal_impulse = contact.lambda / substep;
+            contact.impulse += normal_impulse;
+
+            let (a, b) = bodies.pair_mut(contact.body_a, contact.body_b);
+            let normal = contact.normal;
+            let (r_a, r_b) = (a.orientation * contact.local_a, b.orientation * contact.local_b);
+            let relative = b.velocity_at(r_b) - a.velocity_at(r_a);
+            let normal_speed = normal.dot(relative);
+
+            // Dynamic friction, limited by the normal impulse of this substep.
+            let tangential = relative - normal * normal_speed;
+            let speed = tangential.length();
+            if speed > f32::EPSILON {
+                let direction = tangential / speed;
+                let stopping = speed * inverse_or_zero(pair_inv_mass(a, b, r_a, r_b, direction));
+                let impulse = -direction * stopping.min(contact.friction * normal_impulse);
+                a.apply_impulse(-impulse, r_a);
+                b.apply_impulse(impulse, r_b);
+            }
+
+            // Restitution replaces the separation speed the projection
+            // produced with the bounce of the incoming speed.
+            if contact.approach_speed < 0.0 {
+                let target = if -contact.approach_speed > self.restitution_threshold {
+                    -contact.restitution * contact.approach_speed
+                } else {
+                    0.0
+                };
+                let normal_speed = normal.dot(b.velocity_at(r_b) - a.velocity_at(r_a));
+                let impulse = normal * ((target - normal_speed) * inverse_or_zero(pair_inv_mass(a, b, r_a, r_b, normal)));
+                a.apply_impulse(-impulse, r_a);
+                b.apply_impulse(impulse, r_b);
+            }
+        }
+    }
+
+    fn solve_joint_velocities(&self, bodies: &mut SolverBodies, substep: f32) {
+        for joint in &self.joints {
+            let (a, b) = bodies.pair_mut(joint.body_a, joint.body_b);
+            let Some((axial, _)) = joint.frame.axial(a, b) else {
+                continue;
+            };
+            let speed = axial.speed(a, b);
+            let mass = axial.mass(a, b);
+            let impulse = match joint.frame.control {
+                JointControl::Off => continue,
+                JointControl::Velocity { speed: target, max_force } => {
+                    let limit = max_force * substep;
+                    (mass * (target - speed)).clamp(-limit, limit)
+                }
+                JointControl::Position { damping, max_force, .. } => {
+                    let limit = max_force * substep;
+                    (-speed * (damping * substep).min(mass)).clamp(-limit, limit)
+                }
+            };
+            axial.apply(a, b, impulse);
+        }
+    }
+
+    /// Report the normal impulse each contact point carried over the step.
+    pub fn store_impulses(&self, manifolds: &mut ManifoldCache) {
+        if let Some(soft) = &self.soft {
+            soft.store_impulses(manifolds);
+        }
+        let mut manifolds: Vec<_> = manifolds.values_mut().collect();
+        for contact in &self.contacts {
+            let point = &mut manifolds[contact.manifold].points[contact.point];
+            point.normal_impulse = contact.impulse;
+            point.tangent_impulse = [0.0; 2];
+        }
+    }
+}
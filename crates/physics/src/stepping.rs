@@ -0,0 +1,141 @@
+//! # Adaptive Stepping
+//!
+//! By default every [`PhysicsSim::step_cpu`] advances the simulation by
+//! exactly `params.dt` in one go, and stiff joints or fast contacts can blow
+//! up when that step is too large. Setting [`PhysicsSim::adaptive_step`]
+//! keeps the user-visible step but splits it into internal steps whose
+//! length follows the local error.
+//!
+//! The error of an internal step of length `h` is estimated by step
+//! doubling: the step is taken once as a whole and once as two halves, and
+//! the largest difference in any body's position or orientation is compared
+//! with [`AdaptiveStep::tolerance`]. Steps above the tolerance are rejected
+//! and retried shorter; accepted ones keep the more accurate two-half
+//! result and let the next step grow. Internal steps never leave
+//! [`AdaptiveStep::min_dt`]..=[`AdaptiveStep::max_dt`], except that a step
+//! already at `min_dt` is accepted whatever its error, and the last steps
+//! are shortened so they end exactly at `params.dt`.
+//!
+//! Forces added with [`PhysicsSim::apply_force`] act for the whole
+//! user-visible step, and contact events compare the contacts at its start
+//! and end. [`PhysicsSim::get_debug_info`] reports how many internal steps
+//! the last step took and how many were rejected.
+
+use serde::{Deserialize, Serialize};
+
+use crate::contacts::contact_events;
+use crate::forces::ForceMode;
+use crate::simulation::PhysicsSim;
+use crate::solver::SolverBodies;
+
+/// Growth of the step after an accepted step with no measurable error.
+const MAX_GROWTH: f32 = 2.0;
+/// Strongest shrink after a rejected step.
+const MIN_SHRINK: f32 = 0.2;
+/// Margin that keeps the next step a little under the estimated best one.
+const SAFETY: f32 = 0.9;
+
+/// Bounds and tolerance for adaptive stepping; see [`crate::stepping`].
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AdaptiveStep {
+    /// Shortest internal step, in seconds.
+    pub min_dt: f32,
+    /// Longest internal step, in seconds.
+    pub max_dt: f32,
+    /// Largest accepted error of one internal step, as a distance in
+    /// meters or an angle in radians.
+    pub tolerance: f32,
+}
+
+impl Default for AdaptiveStep {
+    fn default() -> Self {
+        Self {
+            min_dt: 1e-4,
+            max_dt: 0.01,
+            tolerance: 1e-3,
+        }
+    }
+}
+
+impl AdaptiveStep {
+    /// `h` kept within the bounds.
+    fn clamp(&self, h: f32) -> f32 {
+        h.min(self.max_dt).max(self.min_dt)
+    }
+}
+
+/// Internal steps taken by the last CPU step.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct StepCount {
+    pub accepted: usize,
+    pub rejected: usize,
+}
+
+impl Default for StepCount {
+    fn default() -> Self {
+        Self { accepted: 1, rejected: 0 }
+    }
+}
+
+impl PhysicsSim {
+    /// Advance by `params.dt` in internal steps chosen by `adaptive`.
+    pub(crate) fn step_adaptive(&mut self, adaptive: &AdaptiveStep) {
+        let dt = self.params.dt;
+        let touching = self.touching.clone();
+        let force_mode = std::mem::replace(&mut self.force_mode, ForceMode::Persistent);
+        let mut count = StepCount { accepted: 0, rejected: 0 };
+        let mut guess = if self.next_substep > 0.0 { adaptive.clamp(self.next_substep) } else { adaptive.max_dt };
+        let mut remaining = dt;
+
+        while remaining > 0.0 {
+            // Spread what is left evenly rather than leave a sliver at the end
+            let h = remaining / (remaining / guess).ceil().max(1.0);
+            let start = self.clone();
+            let mut whole = self.clone();
+            whole.step_internal(h);
+            self.step_internal(0.5 * h);
+            self.step_internal(0.5 * h);
+
+            let ratio = step_difference(&whole, self) / adaptive.tolerance;
+            if ratio > 1.0 && h > adaptive.min_dt {
+                *self = start;
+                count.rejected += 1;
+                guess = adaptive.clamp(h * (SAFETY / ratio.sqrt()).max(MIN_SHRINK));
+                continue;
+            }
+            count.accepted += 1;
+            remaining = if h >= remaining { 0.0 } else { remaining - h };
+            let growth = if ratio > 0.0 { (SAFETY / ratio.sqrt()).min(MAX_GROWTH) } else { MAX_GROWTH };
+            guess = adaptive.clamp(h * growth);
+        }
+
+        self.params.dt = dt;
+        self.force_mode = force_mode;
+        self.next_substep = guess;
+        self.step_count = count;
+        self.contact_events = contact_events(&touching, &self.touching);
+        self.finish_loads();
+    }
+
+    /// One fixed step of length `h`.
+    fn step_internal(&mut self, h: f32) {
+        self.params.dt = h;
+        self.step_fixed();
+    }
+}
+
+/// Largest difference in position or orientation between the same body in
+/// `a` and `b`. A body that left the finite range counts as an infinite
+/// difference.
+fn step_difference(a: &PhysicsSim, b: &PhysicsSim) -> f32 {
+    let (a, b) = (SolverBodies::gather(a), SolverBodies::gather(b));
+    let mut worst: f32 = 0.0;
+    for (a, b) in a.bodies.iter().zip(&b.bodies) {
+        let difference = a.position.distance(b.position).max(a.orientation.angle_between(b.orientation));
+        if !difference.is_finite() || !a.position.is_finite() || !b.position.is_finite() {
+            return f32::INFINITY;
+        }
+        worst = worst.max(difference);
+    }
+    worst
+}
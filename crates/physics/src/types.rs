@@ -11,9 +11,11 @@
 //! -   **Geometric Primitives:** These are the basic building blocks for rigid
 //!     bodies, such as [`Vec3`] for positions and velocities.
 //! -   **Rigid Bodies:** These represent the dynamic objects in the simulation,
-//!     including [`Sphere`], [`BoxBody`], and [`Cylinder`].
+//!     including [`Sphere`], [`BoxBody`], [`Cylinder`] and [`Capsule`].
 //! -   **Constraints:** These are used to connect rigid bodies, such as the
-//!     [`Joint`] struct.
+//!     [`Joint`] and [`RevoluteJoint`] structs, or to restrict a single body,
+//!     such as [`PlanarConstraint`]. Joint motors are driven through
+//!     [`JointControl`].
 //! -   **Simulation Parameters:** These control the global behavior of the
 //!     physics simulation, such as [`PhysParams`] and [`JointParams`].
 //!
@@ -26,8 +28,10 @@
 //! feature of the JAXS physics engine, as it enables the use of GPU
 //! acceleration for the simulation loop.
 
+use serde::{Deserialize, Serialize};
+
 #[repr(C)]
-#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable, Serialize, Deserialize)]
 /// Three dimensional vector used by the physics engine.
 ///
 /// This simple data structure is shared by all bodies to represent
@@ -203,7 +207,7 @@ impl std::ops::Neg for Vec3 {
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Serialize, Deserialize)]
 /// Material properties for physical interactions.
 ///
 /// Defines surface properties that control how objects interact during collisions.
@@ -214,8 +218,10 @@ pub struct Material {
     pub restitution: f32,
     /// Density in kg/m^3. Default is 1000 (water density).
     pub density: f32,
-    /// Padding for alignment.
-    _pad: f32,
+    /// Contact compliance in meters per newton, used by
+    /// [`SolverType::Xpbd`]. The compliances of two touching materials add
+    /// up. Default is 0 (rigid).
+    pub compliance: f32,
 }
 
 impl Material {
@@ -225,7 +231,7 @@ impl Material {
             friction,
             restitution,
             density: 1000.0, // Default water density
-            _pad: 0.0,
+            compliance: 0.0,
         }
     }
 
@@ -246,7 +252,7 @@ impl Material {
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Serialize, Deserialize)]
 /// A dynamic spherical rigid body.
 ///
 /// A `Sphere` is one of the fundamental rigid body types in the JAXS physics
@@ -341,7 +347,7 @@ pub struct BoxShape {
     _pad2: f32,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 /// Global parameters that control the physics simulation.
 ///
 /// These values influence the behavior of all rigid bodies in the simulation.
@@ -357,7 +363,7 @@ pub struct PhysParams {
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Serialize, Deserialize)]
 /// Constraint linking two bodies together at a fixed distance.
 pub struct Joint {
     /// Index of the first body in [`crate::simulation::PhysicsSim::spheres`].
@@ -366,91 +372,557 @@ pub struct Joint {
     pub body_b: u32,
     /// The target distance that the joint tries to maintain between the two bodies.
     pub rest_length: f32,
-    pub _padding: u32,
+    /// Skip contacts between the two bodies when non-zero.
+    pub disable_collision: u32,
+}
+
+/// `enable_motor` value of a joint without a motor.
+pub const MOTOR_DISABLED: u32 = 0;
+/// `enable_motor` value of a joint driven towards `motor_speed`.
+pub const MOTOR_VELOCITY: u32 = 1;
+/// `enable_motor` value of a joint servoed towards `target_position`.
+pub const MOTOR_POSITION: u32 = 2;
+
+/// Control input for the motor of a revolute or prismatic joint.
+///
+/// Angles and angular speeds apply to revolute joints, distances and linear
+/// speeds to prismatic joints. Controls are read every step, so they can be
+/// changed between steps to drive joints directly.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum JointControl {
+    /// No motor; the joint moves freely within its limits.
+    Off,
+    /// Drive the joint speed towards `speed`, using at most `max_force`.
+    Velocity { speed: f32, max_force: f32 },
+    /// PD servo towards `target`: the motor applies
+    /// `stiffness * (target - position) - damping * speed`, capped at
+    /// `max_force`.
+    Position {
+        target: f32,
+        stiffness: f32,
+        damping: f32,
+        max_force: f32,
+    },
+}
+
+impl JointControl {
+    fn decode(mode: u32, speed: f32, max_force: f32, target: f32, stiffness: f32, damping: f32) -> Self {
+        match mode {
+            MOTOR_VELOCITY => Self::Velocity { speed, max_force },
+            MOTOR_POSITION => Self::Position {
+                target,
+                stiffness,
+                damping,
+                max_force,
+            },
+            _ => Self::Off,
+        }
+    }
+}
+
+/// Position and speed of a revolute or prismatic joint along its free axis.
+///
+/// Revolute joints report an angle in radians, measured from the pose at
+/// which the joint was created; prismatic joints report a translation.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct JointState {
+    /// Joint angle or translation.
+    pub position: f32,
+    /// Rate of change of [`Self::position`].
+    pub speed: f32,
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Serialize, Deserialize)]
 /// A hinge joint allowing rotation around a single axis.
+///
+/// The joint keeps the two anchors together and the hinge axes of both
+/// bodies aligned, leaving one rotational degree of freedom.
 pub struct RevoluteJoint {
     /// Index of the first body.
     pub body_a: u32,
     /// Index of the second body.
     pub body_b: u32,
+    /// Shape of the first body, see [`crate::body::BodyHandle::from_type_code`].
+    pub body_a_type: u32,
+    /// Shape of the second body.
+    pub body_b_type: u32,
     /// Anchor point on body A in local coordinates.
     pub anchor_a: Vec3,
     /// Anchor point on body B in local coordinates.
     pub anchor_b: Vec3,
-    /// Rotation axis in world coordinates.
+    /// Rotation axis in body A's local coordinates.
     pub axis: Vec3,
+    /// Orientation of body B relative to body A when the joint was created,
+    /// as an `[x, y, z, w]` quaternion. Used to find the hinge axis in body
+    /// B's frame.
+    pub reference_rotation: [f32; 4],
     /// Lower angular limit in radians.
     pub lower_limit: f32,
     /// Upper angular limit in radians.
     pub upper_limit: f32,
     /// Target motor speed in radians per second.
     pub motor_speed: f32,
-    /// Maximum motor force.
+    /// Maximum motor torque.
     pub motor_max_force: f32,
-    /// Enable motor when non-zero.
+    /// Motor mode, one of the `MOTOR_*` constants.
     pub enable_motor: u32,
     /// Enable limits when non-zero.
     pub enable_limit: u32,
-    pub _pad: f32,
+    /// Target angle in radians for [`MOTOR_POSITION`].
+    pub target_position: f32,
+    /// Servo torque per radian of angle error.
+    pub stiffness: f32,
+    /// Servo torque per radian per second of joint speed.
+    pub damping: f32,
+    /// Compliance of the joint in [`SolverType::Xpbd`], added to
+    /// [`JointParams::compliance`].
+    pub compliance: f32,
+    /// Skip contacts between the two bodies when non-zero.
+    pub disable_collision: u32,
+}
+
+impl RevoluteJoint {
+    /// Motor control decoded from the motor fields.
+    #[must_use]
+    pub fn control(&self) -> JointControl {
+        JointControl::decode(
+            self.enable_motor,
+            self.motor_speed,
+            self.motor_max_force,
+            self.target_position,
+            self.stiffness,
+            self.damping,
+        )
+    }
+
+    /// Write `control` into the motor fields.
+    pub fn set_control(&mut self, control: JointControl) {
+        match control {
+            JointControl::Off => self.enable_motor = MOTOR_DISABLED,
+            JointControl::Velocity { speed, max_force } => {
+                self.enable_motor = MOTOR_VELOCITY;
+                self.motor_speed = speed;
+                self.motor_max_force = max_force;
+            }
+            JointControl::Position {
+                target,
+                stiffness,
+                damping,
+                max_force,
+            } => {
+                self.enable_motor = MOTOR_POSITION;
+                self.target_position = target;
+                self.stiffness = stiffness;
+                self.damping = damping;
+                self.motor_max_force = max_force;
+            }
+        }
+    }
+
+    /// `(lower, upper)` limits if they are enabled.
+    #[must_use]
+    pub fn limits(&self) -> Option<(f32, f32)> {
+        (self.enable_limit != 0).then_some((self.lower_limit, self.upper_limit))
+    }
+
+    /// Enable the given limits, or disable limits with `None`.
+    pub fn set_limits(&mut self, limits: Option<(f32, f32)>) {
+        self.enable_limit = u32::from(limits.is_some());
+        if let Some((lower, upper)) = limits {
+            self.lower_limit = lower;
+            self.upper_limit = upper;
+        }
+    }
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Serialize, Deserialize)]
 /// A sliding joint constraining motion along an axis.
+///
+/// The bodies keep their relative orientation and may only translate
+/// relative to each other along the axis.
 pub struct PrismaticJoint {
     pub body_a: u32,
     pub body_b: u32,
+    /// Shape of the first body, see [`crate::body::BodyHandle::from_type_code`].
+    pub body_a_type: u32,
+    /// Shape of the second body.
+    pub body_b_type: u32,
+    /// Anchor point on body A in local coordinates.
     pub anchor_a: Vec3,
+    /// Anchor point on body B in local coordinates.
     pub anchor_b: Vec3,
+    /// Slide axis in body A's local coordinates.
     pub axis: Vec3,
+    /// Orientation of body B relative to body A that the joint holds, as an
+    /// `[x, y, z, w]` quaternion.
+    pub reference_rotation: [f32; 4],
+    /// Lower translation limit along the axis.
     pub lower_limit: f32,
+    /// Upper translation limit along the axis.
     pub upper_limit: f32,
+    /// Target motor speed in meters per second.
     pub motor_speed: f32,
+    /// Maximum motor force.
     pub motor_max_force: f32,
+    /// Motor mode, one of the `MOTOR_*` constants.
     pub enable_motor: u32,
+    /// Enable limits when non-zero.
     pub enable_limit: u32,
-    pub _pad: f32,
+    /// Target translation for [`MOTOR_POSITION`].
+    pub target_position: f32,
+    /// Servo force per meter of translation error.
+    pub stiffness: f32,
+    /// Servo force per meter per second of joint speed.
+    pub damping: f32,
+    /// Compliance of the joint in [`SolverType::Xpbd`], added to
+    /// [`JointParams::compliance`].
+    pub compliance: f32,
+    /// Skip contacts between the two bodies when non-zero.
+    pub disable_collision: u32,
+}
+
+impl PrismaticJoint {
+    /// Motor control decoded from the motor fields.
+    #[must_use]
+    pub fn control(&self) -> JointControl {
+        JointControl::decode(
+            self.enable_motor,
+            self.motor_speed,
+            self.motor_max_force,
+            self.target_position,
+            self.stiffness,
+            self.damping,
+        )
+    }
+
+    /// Write `control` into the motor fields.
+    pub fn set_control(&mut self, control: JointControl) {
+        match control {
+            JointControl::Off => self.enable_motor = MOTOR_DISABLED,
+            JointControl::Velocity { speed, max_force } => {
+                self.enable_motor = MOTOR_VELOCITY;
+                self.motor_speed = speed;
+                self.motor_max_force = max_force;
+            }
+            JointControl::Position {
+                target,
+                stiffness,
+                damping,
+                max_force,
+            } => {
+                self.enable_motor = MOTOR_POSITION;
+                self.target_position = target;
+                self.stiffness = stiffness;
+                self.damping = damping;
+                self.motor_max_force = max_force;
+            }
+        }
+    }
+
+    /// `(lower, upper)` limits if they are enabled.
+    #[must_use]
+    pub fn limits(&self) -> Option<(f32, f32)> {
+        (self.enable_limit != 0).then_some((self.lower_limit, self.upper_limit))
+    }
+
+    /// Enable the given limits, or disable limits with `None`.
+    pub fn set_limits(&mut self, limits: Option<(f32, f32)>) {
+        self.enable_limit = u32::from(limits.is_some());
+        if let Some((lower, upper)) = limits {
+            self.lower_limit = lower;
+            self.upper_limit = upper;
+        }
+    }
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Serialize, Deserialize)]
 /// A ball-and-socket joint allowing 3 DoF rotation.
 pub struct BallJoint {
     pub body_a: u32,
     pub body_b: u32,
+    /// Shape of the first body, see [`crate::body::BodyHandle::from_type_code`].
+    pub body_a_type: u32,
+    /// Shape of the second body.
+    pub body_b_type: u32,
+    /// Anchor point on body A in local coordinates.
     pub anchor_a: Vec3,
+    /// Anchor point on body B in local coordinates.
     pub anchor_b: Vec3,
-    pub _pad: [f32; 2],
+    /// Compliance of the joint in [`SolverType::Xpbd`], added to
+    /// [`JointParams::compliance`].
+    pub compliance: f32,
+    /// Skip contacts between the two bodies when non-zero.
+    pub disable_collision: u32,
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Serialize, Deserialize)]
 /// A rigid joint locking two bodies together.
 pub struct FixedJoint {
     pub body_a: u32,
     pub body_b: u32,
+    /// Shape of the first body, see [`crate::body::BodyHandle::from_type_code`].
+    pub body_a_type: u32,
+    /// Shape of the second body.
+    pub body_b_type: u32,
+    /// Anchor point on body A in local coordinates.
     pub anchor_a: Vec3,
+    /// Anchor point on body B in local coordinates.
     pub anchor_b: Vec3,
-    /// Relative rotation stored as a quaternion.
+    /// Orientation of body B relative to body A, stored as an `[x, y, z, w]`
+    /// quaternion.
     pub relative_rotation: [f32; 4],
+    /// Compliance of the joint in [`SolverType::Xpbd`], added to
+    /// [`JointParams::compliance`].
+    pub compliance: f32,
+    /// Skip contacts between the two bodies when non-zero.
+    pub disable_collision: u32,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+/// Restricts a body to translate within a plane and to rotate only about the
+/// plane normal, turning a 3D body into a 2D one.
+pub struct PlanarConstraint {
+    /// The constrained body.
+    pub body: crate::body::BodyHandle,
+    /// Unit normal of the plane of motion.
+    pub normal: Vec3,
+    /// Value of `normal · position` the body's center is held at.
+    pub distance: f32,
+    /// Orientation of the body when the constraint was created, as an
+    /// `[x, y, z, w]` quaternion. The body may only twist away from it about
+    /// `normal`.
+    pub reference_orientation: [f32; 4],
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Serialize, Deserialize)]
 /// Global parameters that control the behavior of the joint solver.
 pub struct JointParams {
-    /// The compliance of the position-based dynamics (PBD) joints. A higher
-    /// value results in more "stretchy" joints.
+    /// Compliance (inverse stiffness) shared by every joint, added to each
+    /// joint's own compliance. A higher value results in more "stretchy"
+    /// joints. Only [`SolverType::Xpbd`] honours compliance; the impulse
+    /// solver treats joints as rigid.
     pub compliance: f32,
     pub _pad: [f32; 3],
 }
 
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// Constraint solver used by [`crate::simulation::PhysicsSim::step_cpu`].
+pub enum SolverType {
+    /// Sequential impulses on velocities, warm started from the previous
+    /// step, followed by a position pass. Constraints are rigid.
+    #[default]
+    SequentialImpulse,
+    /// Extended position-based dynamics: each step is split into
+    /// `substeps` substeps that integrate, project joints and contacts with
+    /// their compliance, derive velocities from the corrections and then
+    /// apply restitution, friction and joint motors to the velocities.
+    Xpbd {
+        /// Number of substeps per step; more substeps make stiff and
+        /// high-mass-ratio systems more accurate.
+        substeps: usize,
+    },
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// Scheme that advances dynamic bodies through gravity and applied forces
+/// in [`crate::simulation::PhysicsSim::step_cpu`], in every substep under
+/// [`SolverType::Xpbd`].
+///
+/// Every scheme rotates bodies by the exponential map of their mean angular
+/// velocity over the step. The GPU step always uses semi-implicit Euler.
+pub enum Integrator {
+    /// Update the velocity first, then move with the new velocity. Cheap
+    /// and symplectic, but first order.
+    #[default]
+    SemiImplicitEuler,
+    /// Move with the mean of the old and new velocities. Second order and
+    /// exact under constant forces.
+    VelocityVerlet,
+    /// Classical fourth-order Runge-Kutta. The engine holds every force
+    /// constant over a step, so this moves bodies exactly like
+    /// [`Self::VelocityVerlet`].
+    Rk4,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// Broad phase used by [`crate::simulation::PhysicsSim::step_cpu`] to find
+/// the body pairs whose bounding boxes overlap.
+pub enum BroadPhaseType {
+    /// Incremental sweep and prune over every body. It needs no bounds and
+    /// stays cheap while bodies move a little per step.
+    #[default]
+    SweepAndPrune,
+    /// Uniform grid in [`crate::simulation::PhysicsSim::spatial_grid`].
+    /// Bodies that leave the grid bounds are tested against every other
+    /// body, so the grid should cover where bodies are expected to be.
+    SpatialGrid,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Parameters that control the contact solver used by
+/// [`crate::simulation::PhysicsSim::step_cpu`].
+pub struct ContactParams {
+    /// Number of sequential-impulse iterations on velocities per step.
+    pub velocity_iterations: usize,
+    /// Number of iterations used to push overlapping bodies apart per step.
+    pub position_iterations: usize,
+    /// Reuse accumulated impulses from the previous step as the initial guess.
+    pub warm_starting: bool,
+    /// Fraction of the penetration (and friction anchor drift) corrected per step.
+    pub baumgarte: f32,
+    /// Penetration that is tolerated without correction, which keeps resting
+    /// contacts from flickering.
+    pub linear_slop: f32,
+    /// Largest positional correction applied to a contact in one iteration.
+    pub max_correction: f32,
+    /// Closing speed below which restitution is ignored, so that resting
+    /// bodies do not keep bouncing.
+    pub restitution_threshold: f32,
+    /// Distance at which contacts are created before the shapes touch.
+    pub contact_margin: f32,
+}
+
+impl Default for ContactParams {
+    fn default() -> Self {
+        Self {
+            velocity_iterations: 10,
+            position_iterations: 4,
+            warm_starting: true,
+            baumgarte: 0.2,
+            linear_slop: 0.003,
+            max_correction: 0.2,
+            restitution_threshold: 0.5,
+            contact_margin: 0.02,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+/// How contacts push touching bodies apart, in
+/// [`crate::simulation::PhysicsSim::step_cpu`] and in
+/// [`crate::simulation::PhysicsSim::step_gpu`].
+pub enum ContactModel {
+    /// Rigid non-penetration constraints solved by the configured
+    /// [`SolverType`]. The response jumps from nothing to a full stop at
+    /// first touch, so its gradients are zero or undefined.
+    #[default]
+    Hard,
+    /// Compliant penalty contacts whose force is a smooth function of the
+    /// depth and relative velocity of every contact point.
+    Soft(SoftContact),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// Parameters of the [`ContactModel::Soft`] contact model.
+///
+/// A point at penetration depth `d` pushes with force
+/// `stiffness * smoothing * softplus(d / smoothing)` along the normal, plus
+/// `damping` times the closing speed weighted by `sigmoid(d / smoothing)`.
+/// The force is integrated implicitly over the step, so it stays stable at
+/// high stiffness. Friction is Coulomb friction regularized below a slip of
+/// `smoothing` per step. Bodies rest slightly sunk into each other, by about
+/// their weight over `stiffness`.
+///
+/// Contacts only exist within [`ContactParams::contact_margin`] of touching,
+/// so the margin should span several smoothing widths.
+pub struct SoftContact {
+    /// Normal force per unit of penetration, in N/m.
+    pub stiffness: f32,
+    /// Normal force per unit of closing speed, in N·s/m.
+    pub damping: f32,
+    /// Width over which the force fades in around first touch, in m.
+    pub smoothing: f32,
+}
+
+impl Default for SoftContact {
+    fn default() -> Self {
+        Self {
+            stiffness: 1.0e4,
+            damping: 100.0,
+            smoothing: 0.001,
+        }
+    }
+}
+
+impl SoftContact {
+    /// Smoothed penetration `smoothing * softplus(depth / smoothing)`: zero
+    /// well apart, `depth` well inside, and smooth in between.
+    #[must_use]
+    pub fn penetration(&self, depth: f32) -> f32 {
+        let x = depth / self.smoothing;
+        self.smoothing * (x.max(0.0) + (-x.abs()).exp().ln_1p())
+    }
+
+    /// Derivative of [`Self::penetration`] with respect to depth, which
+    /// also weights the damping.
+    #[must_use]
+    pub fn activation(&self, depth: f32) -> f32 {
+        1.0 / (1.0 + (-depth / self.smoothing).exp())
+    }
+
+    /// Normal impulse over `dt` at a point with penetration `depth` whose
+    /// surfaces separate at `normal_speed`, for a pair with inverse
+    /// effective mass `inv_mass` along the normal.
+    ///
+    /// The spring and damper are integrated implicitly, using the normal
+    /// speed after the impulse. The impulse never pulls.
+    #[must_use]
+    pub fn normal_impulse(&self, depth: f32, normal_speed: f32, inv_mass: f32, dt: f32) -> f32 {
+        let gain = self.activation(depth) * (self.stiffness * dt + self.damping);
+        let impulse = dt * (self.stiffness * self.penetration(depth) - gain * normal_speed);
+        (impulse / (1.0 + inv_mass * dt * gain)).max(0.0)
+    }
+
+    /// Friction impulse against the tangential slip velocity `slip`, for a
+    /// normal impulse `normal_impulse` and friction coefficient `friction`.
+    /// `inv_mass` is the pair's inverse effective mass along the slip.
+    ///
+    /// The impulse approaches `friction * normal_impulse` while sliding fast
+    /// and fades smoothly to zero with the slip, never reversing it.
+    #[must_use]
+    pub fn friction_impulse(&self, slip: Vec3, normal_impulse: f32, friction: f32, inv_mass: f32, dt: f32) -> Vec3 {
+        let limit = friction * normal_impulse;
+        let regularization = self.smoothing / dt;
+        let speed = (slip.dot(slip) + regularization * regularization).sqrt();
+        -slip * (limit / (speed + limit * inv_mass))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Parameters that decide when resting islands of bodies fall asleep.
+///
+/// A sleeping body keeps its pose and is left out of integration and
+/// contact generation until something disturbs it: a contact with an awake
+/// body, an applied force, or a change to its pose or velocity.
+pub struct SleepParams {
+    /// Let islands fall asleep at all.
+    pub enabled: bool,
+    /// Speed below which a body counts as resting, in m/s.
+    pub linear_threshold: f32,
+    /// Angular speed below which a body counts as resting, in rad/s.
+    pub angular_threshold: f32,
+    /// Time every body of an island must rest before the island sleeps.
+    pub time_to_sleep: f32,
+}
+
+impl Default for SleepParams {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            linear_threshold: 0.01,
+            angular_threshold: 0.035,
+            time_to_sleep: 0.5,
+        }
+    }
+}
+
 /// Body type determines how physics affects the body
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum BodyType {
     /// Dynamic body affected by gravity and forces
     Dynamic,
@@ -460,7 +932,7 @@ pub enum BodyType {
     Static,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 /// A dynamic, axis-aligned bounding box (AABB) used for simplified
 /// collision detection.
 pub struct BoxBody {
@@ -482,7 +954,7 @@ pub struct BoxBody {
     pub body_type: BodyType,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 /// A dynamic cylinder primitive.
 pub struct Cylinder {
     /// The position of the center of mass.
@@ -509,8 +981,44 @@ pub struct Cylinder {
     pub mesh_offset: Vec3,
 }
 
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+/// A dynamic capsule primitive: a cylinder capped by two hemispheres.
+///
+/// The capsule is the set of points within `radius` of the segment that
+/// runs along the local Y axis from `-half_height` to `half_height`.
+pub struct Capsule {
+    /// The position of the center of mass.
+    pub pos: Vec3,
+    /// The linear velocity of the capsule.
+    pub vel: Vec3,
+    /// The radius of the capsule around its core segment.
+    pub radius: f32,
+    /// Half the length of the core segment, excluding the hemispherical caps.
+    pub half_height: f32,
+    /// Mass of the capsule in kilograms.
+    pub mass: f32,
+    /// The orientation of the capsule, represented as a quaternion in `[x, y, z, w]` format.
+    pub orientation: [f32; 4],
+    /// The angular velocity of the capsule, measured in radians per second.
+    pub angular_vel: Vec3,
+    /// Material properties for collision response.
+    pub material: Material,
+    /// Body type (Dynamic, Kinematic, Static)
+    pub body_type: BodyType,
+}
+
+impl Capsule {
+    /// World-space end points of the core segment.
+    #[must_use]
+    pub fn segment(&self) -> (Vec3, Vec3) {
+        let rotation = crate::collision::body_rotation(self.orientation);
+        let axis = Vec3::from(rotation * glam::Vec3::Y * self.half_height);
+        (self.pos - axis, self.pos + axis)
+    }
+}
+
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Serialize, Deserialize)]
 pub struct Vec2 {
     pub x: f32,
     pub y: f32,
@@ -523,7 +1031,7 @@ impl Vec2 {
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Serialize, Deserialize)]
 /// An infinite plane used as a static collision primitive.
 ///
 /// A plane is defined by its normal vector and its distance from the origin.
@@ -547,6 +1055,7 @@ pub struct Plane {
 /// This grid subdivides 3D space into cubic cells and maintains lists of
 /// objects in each cell. This allows for efficient pruning of collision
 /// pairs by only checking objects in the same or neighboring cells.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SpatialGrid {
     /// Size of each grid cell (same in all dimensions).
     pub cell_size: f32,
@@ -554,12 +1063,12 @@ pub struct SpatialGrid {
     pub bounds: BoundingBox,
     /// Dimensions of the grid (number of cells in each direction).
     pub dimensions: [usize; 3],
-    /// Flattened array of cells, each containing a list of sphere indices.
+    /// Flattened array of cells, each containing a list of object indices.
     pub cells: Vec<Vec<usize>>,
 }
 
 /// Axis-aligned bounding box used for spatial grid bounds.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct BoundingBox {
     /// Minimum corner of the bounding box.
     pub min: Vec3,
@@ -584,6 +1093,18 @@ pub struct PhysicsDebugInfo {
     pub gravity: Vec3,
     /// Time step.
     pub dt: f32,
+    /// Internal steps the last CPU step was split into; one unless
+    /// adaptive stepping is on.
+    pub substeps: usize,
+    /// Internal steps of the last CPU step that adaptive stepping rejected
+    /// and retried shorter.
+    pub rejected_substeps: usize,
+    /// Number of dynamic bodies that are awake.
+    pub num_awake: usize,
+    /// Number of dynamic bodies that are asleep.
+    pub num_sleeping: usize,
+    /// Contact points of the last step.
+    pub contacts: Vec<ContactDebugInfo>,
     /// Spatial grid info.
     pub spatial_grid: SpatialGridDebugInfo,
     /// Force information.
@@ -592,17 +1113,24 @@ pub struct PhysicsDebugInfo {
     pub velocities: Vec<VelocityDebugInfo>,
 }
 
-/// Debug information for a contact point.
+/// A contact point between two bodies that touched during the last step.
 #[derive(Copy, Clone, Debug)]
 pub struct ContactDebugInfo {
     /// Position of the contact point.
     pub position: Vec3,
-    /// Normal vector at the contact point.
+    /// Normal vector at the contact point, pointing from the first body to
+    /// the second.
     pub normal: Vec3,
     /// Penetration depth.
     pub depth: f32,
-    /// Indices of the objects in contact.
-    pub object_indices: (usize, usize),
+    /// The bodies in contact, smaller handle first.
+    pub bodies: (crate::body::BodyHandle, crate::body::BodyHandle),
+    /// Touching children of the two bodies if they are compounds, zero
+    /// otherwise.
+    pub children: (usize, usize),
+    /// Impulse the contact applied to the second body during the step,
+    /// friction included. The first body received the opposite impulse.
+    pub impulse: Vec3,
 }
 
 /// Debug information for visualizing velocity.
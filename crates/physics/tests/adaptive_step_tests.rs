@@ -0,0 +1,144 @@
+//! Tests for adaptive stepping: error-controlled internal steps that add up
+//! to the user step, stay within their bounds, and keep stiff joint chains
+//! together where a fixed step of the same length does not
+
+use physics::{
+    AdaptiveStep, BodyHandle, ContactEventKind, Integrator, PhysicsSim, SolverType,
+    types::{BodyType, Vec2, Vec3},
+};
+
+/// Six spheres hanging in a horizontal chain of ball joints from a static
+/// support, the last one a hundred times heavier than the rest.
+fn heavy_chain(solver: SolverType, adaptive: Option<AdaptiveStep>) -> PhysicsSim {
+    let mut sim = PhysicsSim::new();
+    sim.solver = solver;
+    sim.params.dt = 0.05;
+    sim.adaptive_step = adaptive;
+    let support = sim.add_box_with_type(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.1, 0.1, 0.1), Vec3::ZERO, BodyType::Static);
+    let mut parent = (BodyHandle::BOX_TYPE, support as u32);
+    for i in 0..6 {
+        let link = sim.add_sphere(Vec3::new(i as f32 + 1.0, 10.0, 0.0), Vec3::ZERO, 0.1);
+        sim.add_ball_joint(parent.0, parent.1, BodyHandle::SPHERE_TYPE, link as u32, Vec3::new(i as f32, 10.0, 0.0));
+        parent = (BodyHandle::SPHERE_TYPE, link as u32);
+    }
+    sim.spheres[5].mass *= 100.0;
+    sim
+}
+
+/// Largest error in the length of a link of the chain over one second.
+fn worst_stretch(sim: &mut PhysicsSim) -> f32 {
+    let mut worst: f32 = 0.0;
+    for _ in 0..20 {
+        sim.step_cpu();
+        for pair in sim.spheres.windows(2) {
+            let stretch = ((pair[1].pos - pair[0].pos).length() - 1.0).abs();
+            worst = worst.max(if stretch.is_finite() { stretch } else { f32::INFINITY });
+        }
+    }
+    worst
+}
+
+#[test]
+fn test_adaptive_step_keeps_a_stiff_chain_together() {
+    let adaptive = AdaptiveStep { min_dt: 1e-3, max_dt: 0.05, tolerance: 1e-2 };
+    for solver in [SolverType::SequentialImpulse, SolverType::Xpbd { substeps: 4 }] {
+        let fixed = worst_stretch(&mut heavy_chain(solver, None));
+        let mut sim = heavy_chain(solver, Some(adaptive));
+        let stretch = worst_stretch(&mut sim);
+        assert!(stretch * 5.0 < fixed, "{solver:?}: adaptive {stretch} vs fixed {fixed}");
+    }
+    let stretch = worst_stretch(&mut heavy_chain(SolverType::SequentialImpulse, Some(adaptive)));
+    assert!(stretch < 0.05, "stretch = {stretch}");
+}
+
+#[test]
+fn test_internal_steps_stay_within_bounds() {
+    let adaptive = AdaptiveStep { min_dt: 2e-3, max_dt: 0.02, tolerance: 1e-3 };
+    let mut sim = heavy_chain(SolverType::SequentialImpulse, Some(adaptive));
+    let (mut most, mut rejected) = (0, 0);
+    for _ in 0..20 {
+        sim.step_cpu();
+        let info = sim.get_debug_info();
+        assert!((3..=25).contains(&info.substeps), "{} internal steps", info.substeps);
+        assert!((sim.params.dt - 0.05).abs() < f32::EPSILON, "the user step is restored");
+        most = most.max(info.substeps);
+        rejected += info.rejected_substeps;
+    }
+    assert!(most > 3, "the chain needs shorter steps than max_dt at some point");
+    assert!(rejected > 0);
+}
+
+#[test]
+fn test_internal_steps_add_up_to_the_user_step() {
+    let mut sim = PhysicsSim::new();
+    sim.integrator = Integrator::VelocityVerlet;
+    sim.params.dt = 0.05;
+    let start = Vec3::new(0.0, 10.0, 0.0);
+    let launch = Vec3::new(2.0, 5.0, -1.0);
+    sim.add_sphere(start, launch, 0.1);
+    sim.step_cpu();
+    assert_eq!((sim.get_debug_info().substeps, sim.get_debug_info().rejected_substeps), (1, 0));
+
+    sim.adaptive_step = Some(AdaptiveStep { min_dt: 1e-4, max_dt: 0.01, tolerance: 1e-3 });
+    for _ in 0..19 {
+        sim.step_cpu();
+        assert_eq!((sim.get_debug_info().substeps, sim.get_debug_info().rejected_substeps), (5, 0));
+    }
+    // Velocity Verlet flies parabolas exactly, so only the elapsed time matters
+    let t = 20.0 * sim.params.dt;
+    let expected = start + launch * t + sim.params.gravity * (0.5 * t * t);
+    let error = (sim.spheres[0].pos - expected).length();
+    assert!(error < 1e-3, "error = {error}");
+}
+
+#[test]
+fn test_forces_act_over_the_whole_user_step() {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    sim.params.dt = 0.05;
+    sim.adaptive_step = Some(AdaptiveStep { min_dt: 1e-4, max_dt: 0.01, tolerance: 1e-3 });
+    let cube = BodyHandle::Box(sim.add_box(Vec3::ZERO, Vec3::new(0.5, 0.5, 0.5), Vec3::ZERO));
+    sim.apply_force(cube, Vec3::new(3.0, 0.0, 0.0));
+    let inv_mass = sim.inverse_mass(cube).0;
+    sim.step_cpu();
+
+    let expected = 3.0 * inv_mass * sim.params.dt;
+    assert!((sim.boxes[0].vel.x - expected).abs() < 1e-5, "vx = {}, expected {expected}", sim.boxes[0].vel.x);
+    assert_eq!(sim.applied_force(cube).0, Vec3::ZERO);
+    sim.step_cpu();
+    assert!((sim.boxes[0].vel.x - expected).abs() < 1e-5, "the force acted for one step only");
+}
+
+#[test]
+fn test_contact_events_span_the_user_step() {
+    let mut sim = PhysicsSim::new();
+    sim.params.dt = 0.05;
+    sim.adaptive_step = Some(AdaptiveStep::default());
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+    sim.add_sphere(Vec3::new(0.0, 1.0, 0.0), Vec3::ZERO, 0.5);
+    let mut begins = 0;
+    for _ in 0..40 {
+        sim.step_cpu();
+        begins += sim.contact_events().iter().filter(|event| event.kind == ContactEventKind::Begin).count();
+    }
+    assert_eq!(begins, 1);
+    assert!((sim.spheres[0].pos.y - 0.5).abs() < 0.02, "y = {}", sim.spheres[0].pos.y);
+}
+
+#[test]
+fn test_restore_replays_adaptive_steps() {
+    let adaptive = AdaptiveStep { min_dt: 1e-3, max_dt: 0.05, tolerance: 1e-2 };
+    let mut sim = heavy_chain(SolverType::SequentialImpulse, Some(adaptive));
+    for _ in 0..5 {
+        sim.step_cpu();
+    }
+    let checkpoint = sim.snapshot();
+    for _ in 0..5 {
+        sim.step_cpu();
+    }
+    let mut replay = checkpoint.into_sim();
+    for _ in 0..5 {
+        replay.step_cpu();
+    }
+    assert_eq!(sim.snapshot().to_bytes(), replay.snapshot().to_bytes());
+}
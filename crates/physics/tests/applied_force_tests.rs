@@ -0,0 +1,223 @@
+//! Tests for the handle-based force API: forces, torques and impulses on
+//! every dynamic body type, clearing after each step or persisting, and
+//! both CPU solvers
+
+use physics::{
+    BodyHandle, ChildShape, CompoundChild, ForceMode, HullGeometry, PhysicsSim, SolverType,
+    types::{BodyType, Vec2, Vec3},
+};
+
+/// One body of each dynamic type, far apart, without gravity.
+fn bodies(solver: SolverType) -> (PhysicsSim, Vec<BodyHandle>) {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    sim.solver = solver;
+    let sphere = sim.add_sphere(Vec3::new(0.0, 0.0, 0.0), Vec3::ZERO, 0.5);
+    let cube = sim.add_box(Vec3::new(5.0, 0.0, 0.0), Vec3::new(0.5, 0.3, 0.2), Vec3::ZERO);
+    let cylinder = sim.add_cylinder(Vec3::new(10.0, 0.0, 0.0), 0.3, 0.5, Vec3::ZERO);
+    let capsule = sim.add_capsule(Vec3::new(15.0, 0.0, 0.0), 0.3, 0.5, Vec3::ZERO);
+    let corners = [
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0),
+    ];
+    let hull = sim.add_convex_hull(&HullGeometry::from_points(&corners).unwrap(), Vec3::new(20.0, 0.0, 0.0), Vec3::ZERO);
+    let compound = sim.add_compound(
+        vec![
+            CompoundChild::new(ChildShape::Sphere { radius: 0.3 }, Vec3::new(-0.5, 0.0, 0.0)),
+            CompoundChild::new(ChildShape::Box { half_extents: Vec3::new(0.2, 0.2, 0.2) }, Vec3::new(0.5, 0.0, 0.0)),
+        ],
+        Vec3::new(25.0, 0.0, 0.0),
+        Vec3::ZERO,
+    );
+    let handles = vec![
+        BodyHandle::Sphere(sphere),
+        BodyHandle::Box(cube),
+        BodyHandle::Cylinder(cylinder),
+        BodyHandle::Capsule(capsule),
+        BodyHandle::Hull(hull),
+        BodyHandle::Compound(compound),
+    ];
+    (sim, handles)
+}
+
+fn velocity(sim: &PhysicsSim, handle: BodyHandle) -> (glam::Vec3, glam::Vec3) {
+    let (linear, angular) = match handle {
+        BodyHandle::Sphere(i) => (sim.spheres[i].vel, sim.spheres[i].angular_vel),
+        BodyHandle::Box(i) => (sim.boxes[i].vel, sim.boxes[i].angular_vel),
+        BodyHandle::Cylinder(i) => (sim.cylinders[i].vel, sim.cylinders[i].angular_vel),
+        BodyHandle::Capsule(i) => (sim.capsules[i].vel, sim.capsules[i].angular_vel),
+        BodyHandle::Hull(i) => (sim.hulls[i].vel, sim.hulls[i].angular_vel),
+        BodyHandle::Compound(i) => (sim.compounds[i].vel, sim.compounds[i].angular_vel),
+        _ => unreachable!(),
+    };
+    (linear.into(), angular.into())
+}
+
+/// Center of mass and orientation. Hulls and compounds are stored in the
+/// frame of their principal axes, so even unrotated ones may carry an
+/// orientation.
+fn pose(sim: &PhysicsSim, handle: BodyHandle) -> (glam::Vec3, glam::Quat) {
+    let (position, orientation) = match handle {
+        BodyHandle::Sphere(i) => (sim.spheres[i].pos, sim.spheres[i].orientation),
+        BodyHandle::Box(i) => (sim.boxes[i].pos, sim.boxes[i].orientation),
+        BodyHandle::Cylinder(i) => (sim.cylinders[i].pos, sim.cylinders[i].orientation),
+        BodyHandle::Capsule(i) => (sim.capsules[i].pos, sim.capsules[i].orientation),
+        BodyHandle::Hull(i) => (sim.hulls[i].pos, sim.hulls[i].orientation),
+        BodyHandle::Compound(i) => (sim.compounds[i].pos, sim.compounds[i].orientation),
+        _ => unreachable!(),
+    };
+    (position.into(), glam::Quat::from_array(orientation))
+}
+
+fn center(sim: &PhysicsSim, handle: BodyHandle) -> glam::Vec3 {
+    pose(sim, handle).0
+}
+
+/// Angular velocity change from the angular impulse `impulse`.
+fn turn(sim: &PhysicsSim, handle: BodyHandle, impulse: glam::Vec3) -> glam::Vec3 {
+    let orientation = pose(sim, handle).1;
+    let inv_inertia = glam::Vec3::from(sim.inverse_mass(handle).1);
+    orientation * (inv_inertia * (orientation.inverse() * impulse))
+}
+
+fn close(a: glam::Vec3, b: glam::Vec3) -> bool {
+    (a - b).length() <= 1e-4 * (1.0 + b.length())
+}
+
+#[test]
+fn test_force_and_torque_accelerate_every_body_type() {
+    for solver in [SolverType::SequentialImpulse, SolverType::Xpbd { substeps: 4 }] {
+        let (mut sim, handles) = bodies(solver);
+        let force = Vec3::new(1.0, 2.0, -3.0);
+        let torque = Vec3::new(0.0, 0.5, 0.2);
+        for &handle in &handles {
+            sim.apply_force(handle, force);
+            sim.apply_torque(handle, torque);
+        }
+        let dt = sim.params.dt;
+        let expected: Vec<_> = handles
+            .iter()
+            .map(|&handle| (sim.inverse_mass(handle).0, turn(&sim, handle, glam::Vec3::from(torque) * dt)))
+            .collect();
+        sim.step_cpu();
+
+        for (&handle, (inv_mass, expected_angular)) in handles.iter().zip(expected) {
+            let (linear, angular) = velocity(&sim, handle);
+            assert!(close(linear, glam::Vec3::from(force) * inv_mass * dt), "{solver:?} {handle:?}: {linear}");
+            assert!(close(angular, expected_angular), "{solver:?} {handle:?}: {angular} vs {expected_angular}");
+        }
+    }
+}
+
+#[test]
+fn test_forces_clear_after_each_step_unless_persistent() {
+    let (mut sim, handles) = bodies(SolverType::SequentialImpulse);
+    let cube = handles[1];
+    sim.apply_force(cube, Vec3::new(2.0, 0.0, 0.0));
+    sim.step_cpu();
+    let after_one = velocity(&sim, cube).0;
+    assert_eq!(sim.applied_force(cube).0, Vec3::ZERO);
+    sim.step_cpu();
+    assert_eq!(velocity(&sim, cube).0, after_one, "the force acted for one step only");
+
+    sim.force_mode = ForceMode::Persistent;
+    sim.apply_force(cube, Vec3::new(2.0, 0.0, 0.0));
+    sim.step_cpu();
+    sim.step_cpu();
+    assert!(close(velocity(&sim, cube).0, after_one * 3.0), "{}", velocity(&sim, cube).0);
+    assert_eq!(sim.applied_force(cube).0, Vec3::new(2.0, 0.0, 0.0));
+
+    sim.clear_forces();
+    sim.step_cpu();
+    assert!(close(velocity(&sim, cube).0, after_one * 3.0));
+}
+
+#[test]
+fn test_off_center_force_matches_force_plus_torque() {
+    let (mut at_point, handles) = bodies(SolverType::SequentialImpulse);
+    let (mut split, _) = bodies(SolverType::SequentialImpulse);
+    let force = Vec3::new(0.0, 0.0, 4.0);
+    for &handle in &handles {
+        let offset = glam::Vec3::new(0.3, 0.1, 0.0);
+        let point = center(&at_point, handle) + offset;
+        at_point.apply_force_at_point(handle, force, point.into());
+        split.apply_force(handle, force);
+        split.apply_torque(handle, offset.cross(force.into()).into());
+    }
+    at_point.step_cpu();
+    split.step_cpu();
+    for &handle in &handles {
+        let (a, b) = (velocity(&at_point, handle), velocity(&split, handle));
+        assert!(close(a.0, b.0) && close(a.1, b.1), "{handle:?}: {a:?} vs {b:?}");
+        assert!(a.1.length() > 0.0, "{handle:?} turns");
+    }
+}
+
+#[test]
+fn test_impulse_changes_velocity_immediately() {
+    let (mut sim, handles) = bodies(SolverType::SequentialImpulse);
+    for &handle in &handles {
+        let inv_mass = sim.inverse_mass(handle).0;
+        let expected = turn(&sim, handle, glam::Vec3::new(0.0, 0.0, -1.0));
+        let above = center(&sim, handle) + glam::Vec3::Y;
+        sim.apply_impulse(handle, Vec3::new(0.0, 1.0, 0.0));
+        sim.apply_impulse_at_point(handle, Vec3::new(1.0, 0.0, 0.0), above.into());
+
+        let (linear, angular) = velocity(&sim, handle);
+        assert!(close(linear, glam::Vec3::new(1.0, 1.0, 0.0) * inv_mass), "{handle:?}: {linear}");
+        // The push above the center turns the body about -z
+        assert!(close(angular, expected), "{handle:?}: {angular} vs {expected}");
+    }
+}
+
+#[test]
+fn test_static_and_kinematic_bodies_ignore_forces() {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    let fixed = sim.add_box_with_type(Vec3::ZERO, Vec3::new(0.5, 0.5, 0.5), Vec3::ZERO, BodyType::Static);
+    let driven = sim.add_box_with_type(Vec3::new(3.0, 0.0, 0.0), Vec3::new(0.5, 0.5, 0.5), Vec3::new(1.0, 0.0, 0.0), BodyType::Kinematic);
+    let plane = sim.add_plane(Vec3::new(0.0, 1.0, 0.0), -5.0, Vec2::new(10.0, 10.0));
+    for handle in [BodyHandle::Box(fixed), BodyHandle::Box(driven), BodyHandle::Plane(plane)] {
+        sim.apply_force(handle, Vec3::new(0.0, 10.0, 0.0));
+        sim.apply_torque(handle, Vec3::new(0.0, 10.0, 0.0));
+        sim.apply_impulse(handle, Vec3::new(0.0, 10.0, 0.0));
+        assert_eq!(sim.applied_force(handle), (Vec3::ZERO, Vec3::ZERO));
+    }
+    sim.step_cpu();
+    assert_eq!(sim.boxes[fixed].vel, Vec3::ZERO);
+    assert_eq!(sim.boxes[driven].vel, Vec3::new(1.0, 0.0, 0.0));
+}
+
+#[test]
+fn test_missing_and_removed_bodies_ignore_forces() {
+    let (mut sim, handles) = bodies(SolverType::SequentialImpulse);
+    let removed = handles[1];
+    sim.remove_body(removed);
+    for handle in [removed, BodyHandle::Sphere(99)] {
+        let push = Vec3::new(0.0, 10.0, 0.0);
+        sim.apply_force(handle, push);
+        sim.apply_force_at_point(handle, push, Vec3::new(1.0, 0.0, 0.0));
+        sim.apply_torque(handle, push);
+        sim.apply_impulse(handle, push);
+        sim.apply_impulse_at_point(handle, push, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(sim.applied_force(handle), (Vec3::ZERO, Vec3::ZERO), "{handle:?}");
+    }
+}
+
+#[test]
+fn test_force_wakes_sleeping_body() {
+    let mut sim = PhysicsSim::new();
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+    let cube = BodyHandle::Box(sim.add_box(Vec3::new(0.0, 0.25, 0.0), Vec3::new(0.25, 0.25, 0.25), Vec3::ZERO));
+    for _ in 0..300 {
+        sim.step_cpu();
+    }
+    assert!(sim.is_sleeping(cube));
+
+    sim.apply_force(cube, Vec3::new(0.0, 100.0, 0.0));
+    sim.step_cpu();
+    assert!(!sim.is_sleeping(cube));
+    assert!(sim.boxes[0].vel.y > 0.0, "vy = {}", sim.boxes[0].vel.y);
+}
@@ -0,0 +1,202 @@
+//! Tests for the joint-space dynamics queries of articulations, checked
+//! against finite differences of the forward simulation
+
+use physics::{
+    ArticulationBase, ArticulationJoint, BodyHandle, PhysicsSim,
+    types::Vec3,
+};
+
+const BASES: [ArticulationBase; 2] = [ArticulationBase::Fixed, ArticulationBase::Floating];
+
+/// Root body of the articulation built by [`arm`].
+const ROOT: BodyHandle = BodyHandle::Box(0);
+
+/// A box root carrying a revolute arm, a ball joint and a sliding tip, every
+/// joint already moving.
+fn arm(base: ArticulationBase) -> PhysicsSim {
+    let mut sim = PhysicsSim::new();
+    let root = BodyHandle::Box(sim.add_box(Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.2, 0.2, 0.2), Vec3::ZERO));
+    let upper = BodyHandle::Box(sim.add_box(Vec3::new(0.6, 2.0, 0.0), Vec3::new(0.2, 0.1, 0.1), Vec3::ZERO));
+    let wrist = BodyHandle::Sphere(sim.add_sphere(Vec3::new(1.1, 2.0, 0.0), Vec3::ZERO, 0.15));
+    let tip = BodyHandle::Box(sim.add_box(Vec3::new(1.1, 1.5, 0.0), Vec3::new(0.05, 0.2, 0.05), Vec3::ZERO));
+    let chain = sim.add_articulation(root, base);
+    let revolute = ArticulationJoint::Revolute {
+        anchor: Vec3::new(0.3, 2.0, 0.0),
+        axis: Vec3::new(0.0, 0.0, 1.0),
+    };
+    let upper = sim.add_articulation_link(chain, 0, upper, revolute);
+    let wrist = sim.add_articulation_link(chain, upper, wrist, ArticulationJoint::Spherical { anchor: Vec3::new(0.85, 2.0, 0.0) });
+    let tip = sim.add_articulation_link(chain, wrist, tip, ArticulationJoint::Prismatic { axis: Vec3::new(0.0, 1.0, 0.0) });
+
+    let tree = &mut sim.articulations[chain];
+    if base == ArticulationBase::Floating {
+        tree.set_joint_velocities(0, &[0.1, 0.2, -0.3, 0.5, 0.0, 0.1]);
+    }
+    tree.set_joint_velocities(upper, &[0.7]);
+    tree.set_joint_velocities(wrist, &[0.3, -0.5, 0.4]);
+    tree.set_joint_velocities(tip, &[0.2]);
+    sim
+}
+
+fn velocities(sim: &PhysicsSim) -> Vec<f32> {
+    let tree = &sim.articulations[0];
+    (0..tree.link_count()).flat_map(|link| tree.joint_velocities(link).to_vec()).collect()
+}
+
+fn set_forces(sim: &mut PhysicsSim, forces: &[f32]) {
+    let tree = &mut sim.articulations[0];
+    let mut start = 0;
+    for link in 0..tree.link_count() {
+        let end = start + tree.joint_velocities(link).len();
+        tree.set_joint_forces(link, &forces[start..end]);
+        start = end;
+    }
+}
+
+/// Joint accelerations over one step, from the change of the joint
+/// velocities.
+fn measured_accelerations(sim: &mut PhysicsSim) -> Vec<f32> {
+    let before = velocities(sim);
+    sim.step_cpu();
+    let dt = sim.params.dt;
+    velocities(sim).iter().zip(before).map(|(after, before)| (after - before) / dt).collect()
+}
+
+fn multiply(matrix: &[Vec<f32>], vector: &[f32]) -> Vec<f32> {
+    matrix.iter().map(|row| row.iter().zip(vector).map(|(a, b)| a * b).sum()).collect()
+}
+
+fn assert_close(actual: &[f32], expected: &[f32], tolerance: f32, what: &str) {
+    assert_eq!(actual.len(), expected.len(), "{what}");
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < tolerance * (1.0 + e.abs()), "{what}: {actual:?} against {expected:?}");
+    }
+}
+
+#[test]
+fn test_mass_matrix_matches_the_response_to_joint_forces() {
+    for base in BASES {
+        let mut sim = arm(base);
+        sim.params.gravity = Vec3::ZERO;
+        let dofs = sim.articulations[0].dof_count();
+        set_forces(&mut sim, &vec![0.0; dofs]);
+        let stopped = vec![0.0; dofs];
+        for link in 0..sim.articulations[0].link_count() {
+            let count = sim.articulations[0].joint_velocities(link).len();
+            sim.articulations[0].set_joint_velocities(link, &stopped[..count]);
+        }
+        let mass = sim.articulation_mass_matrix(ROOT).unwrap();
+        assert_eq!(mass.len(), dofs);
+        for (i, row) in mass.iter().enumerate() {
+            assert!(row[i] > 0.0, "{base:?}: diagonal {i} is {}", row[i]);
+            for (j, value) in row.iter().enumerate() {
+                assert!((value - mass[j][i]).abs() < 1e-5 * (1.0 + value.abs()), "{base:?}: not symmetric at {i}, {j}");
+            }
+        }
+
+        // From rest and without gravity, a unit force on one joint gives
+        // accelerations that the mass matrix maps back onto it
+        for k in 0..dofs {
+            let mut probe = sim.clone();
+            let mut force = vec![0.0; dofs];
+            force[k] = 1.0;
+            set_forces(&mut probe, &force);
+            let accelerations = measured_accelerations(&mut probe);
+            assert_close(&multiply(&mass, &accelerations), &force, 2e-3, &format!("{base:?}: column {k}"));
+        }
+    }
+}
+
+#[test]
+fn test_bias_forces_balance_gravity_and_joint_velocities() {
+    for base in BASES {
+        let mut sim = arm(base);
+        sim.params.gravity = Vec3::new(0.0, -9.81, 0.0);
+        let mass = sim.articulation_mass_matrix(ROOT).unwrap();
+        let bias = sim.articulation_bias_forces(ROOT).unwrap();
+        // With no joint forces, M q̈ + c = 0
+        let accelerations = measured_accelerations(&mut sim.clone());
+        let residual: Vec<f32> = multiply(&mass, &accelerations).iter().zip(&bias).map(|(a, b)| a + b).collect();
+        assert_close(&residual, &vec![0.0; bias.len()], 2e-3, &format!("{base:?}: residual"));
+    }
+}
+
+#[test]
+fn test_inverse_dynamics_gives_the_requested_accelerations() {
+    for base in BASES {
+        let mut sim = arm(base);
+        let dofs = sim.articulations[0].dof_count();
+        let wanted: Vec<f32> = (0..dofs).map(|i| (i as f32 * 0.7).sin() * 2.0).collect();
+        // Any link names the whole tree
+        let forces = sim.articulation_inverse_dynamics(BodyHandle::Box(2), &wanted).unwrap();
+        let mass = sim.articulation_mass_matrix(ROOT).unwrap();
+        let bias = sim.articulation_bias_forces(ROOT).unwrap();
+        let expected: Vec<f32> = multiply(&mass, &wanted).iter().zip(&bias).map(|(a, b)| a + b).collect();
+        assert_close(&forces, &expected, 1e-4, &format!("{base:?}: M q̈ + c"));
+
+        set_forces(&mut sim, &forces);
+        assert_close(&measured_accelerations(&mut sim), &wanted, 2e-3, &format!("{base:?}: accelerations"));
+    }
+}
+
+#[test]
+fn test_gravity_compensation_holds_a_fixed_arm_still() {
+    let mut sim = arm(ArticulationBase::Fixed);
+    let dofs = sim.articulations[0].dof_count();
+    let stopped = vec![0.0; dofs];
+    for link in 0..sim.articulations[0].link_count() {
+        let count = sim.articulations[0].joint_velocities(link).len();
+        sim.articulations[0].set_joint_velocities(link, &stopped[..count]);
+    }
+    let start = sim.spheres[0].pos;
+    for _ in 0..120 {
+        let bias = sim.articulation_bias_forces(ROOT).unwrap();
+        set_forces(&mut sim, &bias);
+        sim.step_cpu();
+    }
+    assert!((sim.spheres[0].pos - start).length() < 1e-3, "the wrist sagged to {:?}", sim.spheres[0].pos);
+    assert!(velocities(&sim).iter().all(|v| v.abs() < 1e-3), "{:?}", velocities(&sim));
+}
+
+#[test]
+fn test_link_jacobian_matches_the_motion_of_a_point() {
+    for base in BASES {
+        let mut sim = arm(base);
+        sim.params.gravity = Vec3::ZERO;
+        sim.params.dt = 1e-3;
+        let tip = BodyHandle::Box(2);
+        let local = glam::Vec3::new(0.05, -0.2, 0.0);
+        let point = |sim: &PhysicsSim| glam::Vec3::from(sim.boxes[2].pos) + glam::Quat::from_array(sim.boxes[2].orientation) * local;
+        let jacobian = sim.link_jacobian(tip, point(&sim).into()).unwrap();
+        let qd = velocities(&sim);
+        assert_eq!(jacobian.len(), qd.len());
+        let mut predicted = [0.0_f32; 6];
+        for (column, rate) in jacobian.iter().zip(&qd) {
+            for (value, entry) in predicted.iter_mut().zip(column) {
+                *value += entry * rate;
+            }
+        }
+
+        let (p0, q0) = (point(&sim), glam::Quat::from_array(sim.boxes[2].orientation));
+        sim.step_cpu();
+        let dt = sim.params.dt;
+        let (p1, q1) = (point(&sim), glam::Quat::from_array(sim.boxes[2].orientation));
+        let velocity = (p1 - p0) / dt;
+        let turn = q1 * q0.inverse();
+        let angular = glam::Vec3::new(turn.x, turn.y, turn.z) * (2.0 * turn.w.signum() / dt);
+        let measured = [angular.x, angular.y, angular.z, velocity.x, velocity.y, velocity.z];
+        assert_close(&predicted, &measured, 1e-2, &format!("{base:?}: point motion"));
+    }
+
+    // The root of a fixed arm does not move
+    let mut sim = arm(ArticulationBase::Fixed);
+    let root = sim.link_jacobian(ROOT, Vec3::new(0.0, 2.0, 0.0)).unwrap();
+    assert!(root.iter().flatten().all(|&value| value == 0.0));
+
+    // Bodies outside any articulation have no dynamics to query
+    let loose = BodyHandle::Sphere(sim.add_sphere(Vec3::new(5.0, 0.0, 0.0), Vec3::ZERO, 0.1));
+    assert!(sim.link_jacobian(loose, Vec3::ZERO).is_none());
+    assert!(sim.articulation_mass_matrix(loose).is_none());
+    assert!(sim.articulation_bias_forces(loose).is_none());
+    assert!(sim.articulation_inverse_dynamics(loose, &[]).is_none());
+}
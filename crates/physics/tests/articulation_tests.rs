@@ -0,0 +1,202 @@
+//! Tests for articulations: exact joints, free fall and joint torques, and
+//! contacts with the ground and with rigid bodies under both CPU solvers
+
+use physics::{
+    ArticulationBase, ArticulationJoint, BodyHandle, PhysicsSim, SolverType,
+    types::{BodyType, Vec2, Vec3},
+};
+
+const SOLVERS: [SolverType; 2] = [SolverType::SequentialImpulse, SolverType::Xpbd { substeps: 4 }];
+
+/// A sphere on a one-unit arm about the Z axis through a fixed support,
+/// released level with the pivot.
+fn pendulum(solver: SolverType) -> PhysicsSim {
+    let mut sim = PhysicsSim::new();
+    sim.solver = solver;
+    let support = BodyHandle::Box(sim.add_box_with_type(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.1, 0.1, 0.1), Vec3::ZERO, BodyType::Static));
+    let bob = BodyHandle::Sphere(sim.add_sphere(Vec3::new(1.0, 5.0, 0.0), Vec3::ZERO, 0.1));
+    let arm = sim.add_articulation(support, ArticulationBase::Fixed);
+    let joint = ArticulationJoint::Revolute {
+        anchor: Vec3::new(0.0, 5.0, 0.0),
+        axis: Vec3::new(0.0, 0.0, 1.0),
+    };
+    sim.add_articulation_link(arm, 0, bob, joint);
+    sim
+}
+
+#[test]
+fn test_revolute_pendulum_keeps_its_arm_and_energy() {
+    for solver in SOLVERS {
+        let mut sim = pendulum(solver);
+        let pivot = Vec3::new(0.0, 5.0, 0.0);
+        let g = -sim.params.gravity.y;
+        // The bob spins with the arm, so its own rotation counts too
+        let inertia = 1.0 / sim.inverse_mass(BodyHandle::Sphere(0)).1.z;
+        let mass = sim.spheres[0].mass;
+        let energy = |sim: &PhysicsSim| {
+            let sphere = &sim.spheres[0];
+            0.5 * mass * sphere.vel.dot(sphere.vel) + 0.5 * inertia * sphere.angular_vel.z * sphere.angular_vel.z + mass * g * sphere.pos.y
+        };
+        let e0 = energy(&sim);
+        let mut lowest = f32::MAX;
+        for _ in 0..500 {
+            sim.step_cpu();
+            let arm = (sim.spheres[0].pos - pivot).length();
+            assert!((arm - 1.0).abs() < 1e-4, "{solver:?}: arm {arm}");
+            let drift = (energy(&sim) - e0).abs() / (mass * g);
+            assert!(drift < 0.02, "{solver:?}: energy drift {drift}");
+            lowest = lowest.min(sim.spheres[0].pos.y);
+        }
+        assert!((lowest - 4.0).abs() < 1e-3, "{solver:?}: lowest point {lowest}");
+        let angle = sim.articulations[0].joint_positions(1)[0];
+        assert!(angle.is_finite() && angle.abs() < std::f32::consts::PI, "{solver:?}: angle {angle}");
+    }
+}
+
+#[test]
+fn test_floating_chain_falls_freely() {
+    let mut sim = PhysicsSim::new();
+    let root = BodyHandle::Box(sim.add_box(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.2, 0.2, 0.2), Vec3::ZERO));
+    let a = BodyHandle::Sphere(sim.add_sphere(Vec3::new(0.6, 10.0, 0.0), Vec3::ZERO, 0.2));
+    let b = BodyHandle::Sphere(sim.add_sphere(Vec3::new(0.6, 10.6, 0.3), Vec3::ZERO, 0.2));
+    let chain = sim.add_articulation(root, ArticulationBase::Floating);
+    let first = sim.add_articulation_link(chain, 0, a, ArticulationJoint::Spherical { anchor: Vec3::new(0.3, 10.0, 0.0) });
+    sim.add_articulation_link(chain, first, b, ArticulationJoint::Spherical { anchor: Vec3::new(0.6, 10.3, 0.15) });
+    assert_eq!(sim.articulations[chain].dof_count(), 12);
+
+    // Spin the root and bend a joint so the joints carry load while it falls
+    sim.articulations[chain].set_joint_velocities(0, &[0.0, 1.0, 0.5, 0.0, 0.0, 0.0]);
+    sim.articulations[chain].set_joint_velocities(first, &[1.0, 0.0, 0.0]);
+    let masses = [sim.boxes[0].mass, sim.spheres[0].mass, sim.spheres[1].mass];
+    let total: f32 = masses.iter().sum();
+    let momentum = |sim: &PhysicsSim| (sim.boxes[0].vel * masses[0] + sim.spheres[0].vel * masses[1] + sim.spheres[1].vel * masses[2]) * (1.0 / total);
+    // Each joint anchor, seen from either body, stays in one place
+    let anchor = |position: Vec3, orientation: [f32; 4], local: glam::Vec3| glam::Vec3::from(position) + glam::Quat::from_array(orientation) * local;
+
+    sim.step_cpu();
+    let start = momentum(&sim);
+    let steps = 100;
+    for _ in 0..steps {
+        sim.step_cpu();
+        let (root, a, b) = (&sim.boxes[0], &sim.spheres[0], &sim.spheres[1]);
+        let gaps = [
+            anchor(root.pos, root.orientation, glam::Vec3::new(0.3, 0.0, 0.0)).distance(anchor(a.pos, a.orientation, glam::Vec3::new(-0.3, 0.0, 0.0))),
+            anchor(a.pos, a.orientation, glam::Vec3::new(0.0, 0.3, 0.15)).distance(anchor(b.pos, b.orientation, glam::Vec3::new(0.0, -0.3, -0.15))),
+        ];
+        assert!(gaps.iter().all(|&gap| gap < 1e-5), "joints opened by {gaps:?}");
+    }
+    assert!(sim.boxes[0].angular_vel.length() > 0.5, "the chain stopped spinning");
+
+    // Gravity is the only outside force, so it alone changes the velocity
+    // of the centre of mass
+    let expected = start + sim.params.gravity * (steps as f32 * sim.params.dt);
+    let error = (momentum(&sim) - expected).length();
+    assert!(error < 2e-2, "centre of mass velocity off by {error}");
+}
+
+#[test]
+fn test_joint_torque_spins_a_link() {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    let base = BodyHandle::Box(sim.add_box(Vec3::ZERO, Vec3::new(0.5, 0.1, 0.5), Vec3::ZERO));
+    let wheel = BodyHandle::Box(sim.add_box(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.4, 0.1, 0.2), Vec3::ZERO));
+    let arm = sim.add_articulation(base, ArticulationBase::Fixed);
+    let joint = ArticulationJoint::Revolute {
+        anchor: Vec3::new(0.0, 1.0, 0.0),
+        axis: Vec3::new(0.0, 1.0, 0.0),
+    };
+    let link = sim.add_articulation_link(arm, 0, wheel, joint);
+    let torque = 0.5;
+    sim.articulations[arm].set_joint_forces(link, &[torque]);
+    assert_eq!(sim.articulations[arm].joint_forces(link), &[torque]);
+
+    let inertia = 1.0 / sim.inverse_mass(wheel).1.y;
+    for _ in 0..100 {
+        sim.step_cpu();
+    }
+    let t = 100.0 * sim.params.dt;
+    let speed = sim.articulations[arm].joint_velocities(link)[0];
+    assert!((speed - torque / inertia * t).abs() < 1e-3 * speed.abs(), "speed {speed}");
+    assert!((sim.boxes[1].angular_vel.y - speed).abs() < 1e-4);
+    assert!((sim.boxes[1].pos - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-5);
+    // The fixed base does not move, whatever reaction the joint takes
+    assert_eq!(sim.boxes[0].pos, Vec3::ZERO);
+}
+
+#[test]
+fn test_articulation_rests_on_the_ground() {
+    for solver in SOLVERS {
+        let mut sim = PhysicsSim::new();
+        sim.solver = solver;
+        sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+        let half = Vec3::new(0.25, 0.1, 0.1);
+        let boxes: Vec<BodyHandle> = (0..3)
+            .map(|i| BodyHandle::Box(sim.add_box(Vec3::new(i as f32 * 0.5, 0.5, 0.0), half, Vec3::ZERO)))
+            .collect();
+        let chain = sim.add_articulation(boxes[0], ArticulationBase::Floating);
+        for i in 1..3 {
+            let joint = ArticulationJoint::Revolute {
+                anchor: Vec3::new(i as f32 * 0.5 - 0.25, 0.5, 0.0),
+                axis: Vec3::new(0.0, 0.0, 1.0),
+            };
+            sim.add_articulation_link(chain, i - 1, boxes[i], joint);
+        }
+        for _ in 0..300 {
+            sim.step_cpu();
+        }
+        for body in &sim.boxes {
+            assert!((body.pos.y - half.y).abs() < 0.02, "{solver:?}: y = {}", body.pos.y);
+            assert!(body.vel.length() < 0.05, "{solver:?}: still moving at {:?}", body.vel);
+        }
+        assert!(sim.contact_manifolds().count() >= 3, "{solver:?}");
+    }
+}
+
+#[test]
+fn test_box_on_a_link_tips_the_articulation() {
+    for solver in SOLVERS {
+        let mut sim = PhysicsSim::new();
+        sim.solver = solver;
+        sim.params.gravity = Vec3::new(0.0, -9.81, 0.0);
+        // A seesaw: a beam turning about Z on a fixed post
+        let post = BodyHandle::Box(sim.add_box_with_type(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.1, 0.1, 0.1), Vec3::ZERO, BodyType::Static));
+        let beam = BodyHandle::Box(sim.add_box(Vec3::new(0.0, 1.3, 0.0), Vec3::new(2.0, 0.1, 0.3), Vec3::ZERO));
+        let seesaw = sim.add_articulation(post, ArticulationBase::Fixed);
+        let joint = ArticulationJoint::Revolute {
+            anchor: Vec3::new(0.0, 1.3, 0.0),
+            axis: Vec3::new(0.0, 0.0, 1.0),
+        };
+        let link = sim.add_articulation_link(seesaw, 0, beam, joint);
+        let weight = BodyHandle::Box(sim.add_box(Vec3::new(1.5, 1.65, 0.0), Vec3::new(0.2, 0.2, 0.2), Vec3::ZERO));
+        let BodyHandle::Box(weight_index) = weight else { unreachable!() };
+
+        for _ in 0..40 {
+            sim.step_cpu();
+        }
+        let angle = sim.articulations[seesaw].joint_positions(link)[0];
+        assert!(angle < -0.05, "{solver:?}: the beam did not tip, angle {angle}");
+        // The weight rides the beam down instead of falling through it
+        let up = glam::Quat::from_array(sim.boxes[1].orientation) * glam::Vec3::Y;
+        let height = (sim.boxes[weight_index].pos - sim.boxes[1].pos).dot(up.into());
+        assert!(height > 0.25, "{solver:?}: weight {height} above the beam");
+        assert!(sim.touching(beam).any(|other| other == weight), "{solver:?}");
+    }
+}
+
+#[test]
+fn test_articulations_replay_from_a_snapshot() {
+    let mut sim = pendulum(SolverType::SequentialImpulse);
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 4.05, Vec2::new(50.0, 50.0));
+    for _ in 0..30 {
+        sim.step_cpu();
+    }
+    let snapshot = sim.snapshot();
+    let mut copy = physics::Snapshot::from_bytes(&snapshot.to_bytes()).unwrap().into_sim();
+    for _ in 0..100 {
+        sim.step_cpu();
+        copy.step_cpu();
+    }
+    assert_eq!(sim.spheres[0].pos, copy.spheres[0].pos);
+    assert_eq!(sim.articulations[0].joint_positions(1), copy.articulations[0].joint_positions(1));
+    assert_eq!(sim.articulations[0].joint_velocities(1), copy.articulations[0].joint_velocities(1));
+}
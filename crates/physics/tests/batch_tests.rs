@@ -0,0 +1,164 @@
+//! Tests for batched worlds: isolation, agreement with standalone
+//! simulations, per-world parameters and reset, and the observation layout
+
+use physics::{
+    BatchedPhysicsSim, BodyHandle, Observation, PhysicsSim,
+    types::{Vec2, Vec3},
+};
+
+fn template() -> PhysicsSim {
+    let mut sim = PhysicsSim::new();
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+    sim.add_sphere(Vec3::new(0.0, 2.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.5);
+    sim.add_box(Vec3::new(3.0, 1.0, 0.0), Vec3::new(0.5, 0.5, 0.5), Vec3::ZERO);
+    sim
+}
+
+#[test]
+fn test_batch_matches_standalone_worlds() {
+    let mut batch = BatchedPhysicsSim::new(template(), 4);
+    let mut reference: Vec<PhysicsSim> = (0..4).map(|_| template()).collect();
+    for (world, sim) in reference.iter_mut().enumerate() {
+        let push = world as f32;
+        batch.params_mut(world).forces = vec![[push, 0.0]];
+        batch.params_mut(world).gravity.y = -9.81 + push;
+        sim.params.forces = vec![[push, 0.0]];
+        sim.params.gravity.y = -9.81 + push;
+    }
+
+    for _ in 0..120 {
+        batch.step_cpu();
+        reference.iter_mut().for_each(PhysicsSim::step_cpu);
+    }
+
+    let state = batch.state();
+    for (world, sim) in reference.iter().enumerate() {
+        let sphere = state.index(batch.body_index(BodyHandle::Sphere(0)).unwrap(), world);
+        let cube = state.index(batch.body_index(BodyHandle::Box(0)).unwrap(), world);
+        assert_eq!(state.position[sphere], sim.spheres[0].pos, "world {world}");
+        assert_eq!(state.linear_velocity[sphere], sim.spheres[0].vel, "world {world}");
+        assert_eq!(state.position[cube], sim.boxes[0].pos, "world {world}");
+        assert_eq!(state.orientation[cube], sim.boxes[0].orientation, "world {world}");
+    }
+    let first = state.index(0, 0);
+    let last = state.index(0, 3);
+    assert!(state.position[first] != state.position[last], "worlds with different forces diverge");
+}
+
+#[test]
+fn test_reset_only_touches_one_world() {
+    let mut batch = BatchedPhysicsSim::new(template(), 3);
+    let start = batch.state().clone();
+    for _ in 0..50 {
+        batch.step_cpu();
+    }
+    let stepped = batch.state().clone();
+
+    batch.reset_where(&[false, true, false]);
+    let state = batch.state();
+    for body in 0..batch.bodies().len() {
+        for world in 0..3 {
+            let index = state.index(body, world);
+            let expected = if world == 1 { &start } else { &stepped };
+            assert_eq!(state.position[index], expected.position[index], "body {body}, world {world}");
+            assert_eq!(state.linear_velocity[index], expected.linear_velocity[index]);
+        }
+    }
+
+    // After the reset, world 1 replays the same trajectory as before
+    for _ in 0..50 {
+        batch.step_cpu();
+    }
+    let sphere = batch.state().index(0, 1);
+    let mut fresh = template();
+    fresh.run_cpu(fresh.params.dt, 50);
+    assert_eq!(batch.state().position[sphere], fresh.spheres[0].pos);
+}
+
+#[test]
+fn test_state_edits_and_configure_stay_in_their_world() {
+    let mut batch = BatchedPhysicsSim::new(template(), 2);
+    let sphere = batch.state().index(0, 1);
+    batch.state_mut().linear_velocity[sphere] = Vec3::new(0.0, 5.0, 0.0);
+    let original = batch.configure(1, |sim| sim.spheres[0].mass);
+    let mass = batch.configure(0, |sim| {
+        sim.spheres[0].mass = 4.0;
+        sim.spheres[0].mass
+    });
+    assert_eq!(mass, 4.0);
+
+    batch.step_cpu();
+    let state = batch.state();
+    assert!(state.linear_velocity[sphere].y > 4.0, "world 1 keeps its edited velocity");
+    assert!(state.linear_velocity[state.index(0, 0)].y < 0.0, "world 0 falls");
+    assert_eq!(batch.configure(1, |sim| sim.spheres[0].mass), original);
+}
+
+#[test]
+fn test_gather_is_row_major_per_world() {
+    let mut batch = BatchedPhysicsSim::new(template(), 3);
+    for world in 0..3 {
+        let cube = batch.state().index(1, world);
+        batch.state_mut().position[cube] = Vec3::new(world as f32, 1.0, 2.0);
+    }
+    let observations = [Observation::Position(BodyHandle::Box(0)), Observation::Orientation(BodyHandle::Box(0))];
+    let width: usize = observations.iter().map(|observation| observation.width()).sum();
+    let rows = batch.gather(&observations).unwrap();
+
+    assert_eq!(rows.len(), 3 * width);
+    for (world, row) in rows.chunks(width).enumerate() {
+        assert_eq!(row, &[world as f32, 1.0, 2.0, 0.0, 0.0, 0.0, 1.0]);
+    }
+    // Bodies outside the batch have nothing to gather
+    assert!(batch.gather(&[Observation::Position(BodyHandle::Sphere(7))]).is_none());
+}
+
+#[test]
+fn test_batched_gpu_step_matches_single_world_gpu_step() {
+    let mut template = PhysicsSim::new();
+    template.add_sphere(Vec3::new(0.0, 5.0, 0.0), Vec3::ZERO, 0.5);
+    let mut batch = BatchedPhysicsSim::new(template, 3);
+    let mut reference: Vec<PhysicsSim> = (0..3)
+        .map(|_| {
+            let mut sim = PhysicsSim::new();
+            sim.add_sphere(Vec3::new(0.0, 5.0, 0.0), Vec3::ZERO, 0.5);
+            sim
+        })
+        .collect();
+    // Worlds 0 and 1 share a dispatch; world 2 has its own gravity
+    for (world, sim) in reference.iter_mut().enumerate() {
+        batch.params_mut(world).forces = vec![[world as f32, 0.0]];
+        sim.params.forces = vec![[world as f32, 0.0]];
+    }
+    batch.params_mut(2).gravity.y = -1.0;
+    reference[2].params.gravity.y = -1.0;
+
+    for _ in 0..20 {
+        batch.step_gpu().unwrap();
+        for sim in &mut reference {
+            sim.step_gpu().unwrap();
+        }
+    }
+    for (world, sim) in reference.iter().enumerate() {
+        let sphere = batch.state().index(0, world);
+        assert_eq!(batch.state().position[sphere], sim.spheres[0].pos, "world {world}");
+        assert_eq!(batch.state().linear_velocity[sphere], sim.spheres[0].vel, "world {world}");
+    }
+}
+
+#[test]
+fn test_gpu_step_of_a_mixed_scene_falls_back_to_the_cpu() {
+    let mut gpu = BatchedPhysicsSim::new(template(), 2);
+    let mut cpu = BatchedPhysicsSim::new(template(), 2);
+    for batch in [&mut gpu, &mut cpu] {
+        let cube = batch.state().index(1, 0);
+        batch.state_mut().position[cube].y = 3.0;
+    }
+    for _ in 0..20 {
+        gpu.step_gpu().unwrap();
+        cpu.step_cpu();
+    }
+    // The box moves too, which the sphere kernels alone would not do
+    assert_eq!(gpu.state().position, cpu.state().position);
+    assert!(gpu.state().position[gpu.state().index(1, 0)].y < 2.9, "{:?}", gpu.state().position);
+}
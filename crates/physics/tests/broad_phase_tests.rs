@@ -0,0 +1,191 @@
+//! Tests for the broad phase: sweep and prune over every shape, the grid
+//! strategy, unbounded worlds and incremental updates
+
+use physics::{
+    BodyHandle, BoundingBox, BroadPhaseType, PhysicsSim,
+    types::{BodyType, Vec2, Vec3},
+};
+
+/// Small deterministic generator so the scenes are the same on every run.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> f32 {
+        self.0 = self.0.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, low: f32, high: f32) -> f32 {
+        low + (high - low) * self.next()
+    }
+}
+
+/// A cluttered scene of every shape around the origin, with a ground plane
+/// cutting through the bottom of it.
+fn cluttered_scene() -> PhysicsSim {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 2.5, Vec2::new(100.0, 100.0));
+    let mut rng = Lcg(7);
+    let point = |rng: &mut Lcg| Vec3::new(rng.range(-3.0, 3.0), rng.range(-3.0, 3.0), rng.range(-3.0, 3.0));
+    for _ in 0..30 {
+        let pos = point(&mut rng);
+        sim.add_sphere(pos, Vec3::ZERO, rng.range(0.2, 0.6));
+    }
+    for _ in 0..15 {
+        let pos = point(&mut rng);
+        let half = Vec3::new(rng.range(0.1, 0.5), rng.range(0.1, 0.5), rng.range(0.1, 0.5));
+        let body = sim.add_box(pos, half, Vec3::ZERO);
+        let rotation = glam::Quat::from_euler(glam::EulerRot::XYZ, rng.next() * 3.0, rng.next() * 3.0, 0.0);
+        sim.boxes[body].orientation = rotation.to_array();
+    }
+    for _ in 0..10 {
+        let pos = point(&mut rng);
+        let radius = rng.range(0.1, 0.4);
+        let half_height = rng.range(0.2, 0.6);
+        let body = sim.add_cylinder(pos, radius, half_height, Vec3::ZERO);
+        let rotation = glam::Quat::from_rotation_z(rng.next() * 3.0);
+        sim.cylinders[body].orientation = rotation.to_array();
+    }
+    sim
+}
+
+fn contact_pairs(sim: &PhysicsSim) -> Vec<(BodyHandle, BodyHandle)> {
+    sim.contact_manifolds().map(|manifold| (manifold.body_a, manifold.body_b)).collect()
+}
+
+/// Every body pair within the contact margin, found by testing all pairs of
+/// spheres directly.
+fn touching_sphere_pairs(sim: &PhysicsSim) -> Vec<(BodyHandle, BodyHandle)> {
+    let margin = sim.contact_params.contact_margin;
+    let mut pairs = Vec::new();
+    for (i, a) in sim.spheres.iter().enumerate() {
+        for (j, b) in sim.spheres.iter().enumerate().skip(i + 1) {
+            if (b.pos - a.pos).length() < a.radius + b.radius + margin {
+                pairs.push((BodyHandle::Sphere(i), BodyHandle::Sphere(j)));
+            }
+        }
+    }
+    pairs
+}
+
+fn grid_bounds(min: f32, max: f32) -> BoundingBox {
+    BoundingBox {
+        min: Vec3::new(min, min, min),
+        max: Vec3::new(max, max, max),
+    }
+}
+
+#[test]
+fn test_sweep_and_prune_is_default() {
+    let mut sim = PhysicsSim::new();
+    assert_eq!(sim.broad_phase, BroadPhaseType::SweepAndPrune);
+    sim.configure_spatial_grid(2.0, grid_bounds(-10.0, 10.0));
+    assert_eq!(sim.broad_phase, BroadPhaseType::SpatialGrid);
+}
+
+/// Sweep and prune, the grid, and a grid that most bodies lie outside of
+/// all find the same contacts between every kind of shape
+#[test]
+fn test_broad_phases_agree_on_all_shapes() {
+    let mut sweep = cluttered_scene();
+    let mut grid = cluttered_scene();
+    grid.configure_spatial_grid(1.0, grid_bounds(-10.0, 10.0));
+    let mut small_grid = cluttered_scene();
+    small_grid.configure_spatial_grid(1.0, grid_bounds(-1.0, 1.0));
+
+    sweep.step_cpu();
+    grid.step_cpu();
+    small_grid.step_cpu();
+
+    let pairs = contact_pairs(&sweep);
+    let kinds = |pairs: &[(BodyHandle, BodyHandle)]| {
+        pairs
+            .iter()
+            .filter(|(a, b)| !matches!((a, b), (BodyHandle::Sphere(_), BodyHandle::Sphere(_))))
+            .count()
+    };
+    assert!(kinds(&pairs) > 5, "the scene should have contacts between other shapes too");
+    assert_eq!(pairs, contact_pairs(&grid));
+    assert_eq!(pairs, contact_pairs(&small_grid));
+}
+
+#[test]
+fn test_sweep_and_prune_finds_every_touching_sphere_pair() {
+    let mut sim = cluttered_scene();
+    let expected = touching_sphere_pairs(&sim);
+    assert!(expected.len() > 5);
+
+    sim.params.dt = 0.0;
+    sim.step_cpu();
+    let found: Vec<_> = contact_pairs(&sim)
+        .into_iter()
+        .filter(|(a, b)| matches!((a, b), (BodyHandle::Sphere(_), BodyHandle::Sphere(_))))
+        .collect();
+    assert_eq!(found, expected);
+}
+
+/// Bodies far outside the old fixed grid bounds still collide
+#[test]
+fn test_collisions_in_unbounded_world() {
+    let far = Vec3::new(1000.0, 500.0, -2000.0);
+    let mut sim = PhysicsSim::new();
+    let floor = sim.add_box_with_type(far, Vec3::new(2.0, 0.1, 2.0), Vec3::ZERO, BodyType::Static);
+    let body = sim.add_box(far + Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.2, 0.2, 0.2), Vec3::ZERO);
+    let sphere = sim.add_sphere(far + Vec3::new(1.0, 1.0, 0.0), Vec3::ZERO, 0.2);
+
+    sim.run_cpu(0.01, 200);
+    let top = sim.boxes[floor].pos.y + 0.1;
+    assert!((sim.boxes[body].pos.y - (top + 0.2)).abs() < 0.02, "box at y = {}", sim.boxes[body].pos.y);
+    assert!((sim.spheres[sphere].pos.y - (top + 0.2)).abs() < 0.02, "sphere at y = {}", sim.spheres[sphere].pos.y);
+}
+
+/// Sweep and prune keeps its sort order between steps; the pairs it finds
+/// while bodies stream past each other must match the grid every step
+#[test]
+fn test_incremental_updates_track_moving_bodies() {
+    let build = |grid: bool| {
+        let mut sim = PhysicsSim::new();
+        sim.params.gravity = Vec3::ZERO;
+        if grid {
+            sim.configure_spatial_grid(1.0, grid_bounds(-20.0, 20.0));
+        }
+        for i in 0..10 {
+            let offset = i as f32 * 1.5 - 7.0;
+            sim.add_sphere(Vec3::new(offset, 0.0, 0.0), Vec3::new(3.0, 0.0, 0.0), 0.3);
+            sim.add_box(Vec3::new(-offset, 0.1, 0.0), Vec3::new(0.3, 0.3, 0.3), Vec3::new(-3.0, 0.0, 0.0));
+        }
+        sim
+    };
+    let mut sweep = build(false);
+    let mut grid = build(true);
+
+    let mut contacts = 0;
+    for step in 0..150 {
+        sweep.step_cpu();
+        grid.step_cpu();
+        let pairs = contact_pairs(&sweep);
+        assert_eq!(pairs, contact_pairs(&grid), "broad phases disagree at step {step}");
+        contacts += pairs.len();
+
+        // Bodies added between steps join the broad phase
+        if step == 50 {
+            for sim in [&mut sweep, &mut grid] {
+                sim.add_sphere(Vec3::new(0.0, 0.1, 0.0), Vec3::new(0.0, 0.0, 0.0), 0.5);
+            }
+        }
+    }
+    assert!(contacts > 0, "the bodies should meet");
+}
+
+/// A plane is an infinite half-space, so bodies far from its extents still
+/// rest on it
+#[test]
+fn test_plane_pairs_with_distant_bodies() {
+    let mut sim = PhysicsSim::new();
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(1.0, 1.0));
+    let sphere = sim.add_sphere(Vec3::new(300.0, 0.5, -400.0), Vec3::ZERO, 0.25);
+
+    sim.run_cpu(0.01, 100);
+    assert!((sim.spheres[sphere].pos.y - 0.25).abs() < 0.01, "y = {}", sim.spheres[sphere].pos.y);
+}
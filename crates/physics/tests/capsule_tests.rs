@@ -0,0 +1,159 @@
+//! Tests for capsule bodies: mass properties and contacts with every other
+//! shape
+
+use physics::{
+    BodyHandle, Heightfield, HullGeometry, PhysicsSim, TriangleMesh,
+    types::{BodyType, Vec2, Vec3},
+};
+
+/// Orientation that lays a capsule's axis along the world X axis.
+const LYING: [f32; 4] = [0.0, 0.0, -std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2];
+
+fn ground(sim: &mut PhysicsSim) {
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+}
+
+fn add_lying_capsule(sim: &mut PhysicsSim, pos: Vec3, radius: f32, half_height: f32) -> usize {
+    let capsule = sim.add_capsule(pos, radius, half_height, Vec3::ZERO);
+    sim.capsules[capsule].orientation = LYING;
+    capsule
+}
+
+/// Height between the two ends of a capsule's core segment; zero while it
+/// lies level.
+fn tilt(sim: &PhysicsSim, capsule: usize) -> f32 {
+    let (start, end) = sim.capsules[capsule].segment();
+    (end.y - start.y).abs()
+}
+
+fn assert_close(actual: f32, expected: f32, tolerance: f32, what: &str) {
+    assert!((actual - expected).abs() < tolerance, "{what}: {actual} != {expected}");
+}
+
+#[test]
+fn test_capsule_mass() {
+    let mut sim = PhysicsSim::new();
+    let capsule = sim.add_capsule(Vec3::ZERO, 0.5, 1.0, Vec3::ZERO);
+    // A cylinder of height 2 and a sphere, both of radius 0.5
+    let expected = std::f32::consts::PI * 0.25 * 2.0 + 4.0 / 3.0 * std::f32::consts::PI * 0.125;
+    assert_close(sim.capsules[capsule].mass, expected, 1e-5, "mass");
+    assert_eq!(BodyHandle::from_type_code(BodyHandle::CAPSULE_TYPE, capsule), Some(BodyHandle::Capsule(capsule)));
+}
+
+#[test]
+fn test_capsule_rests_on_plane() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let upright = sim.add_capsule(Vec3::new(-2.0, 1.0, 0.0), 0.2, 0.3, Vec3::ZERO);
+    let lying = add_lying_capsule(&mut sim, Vec3::new(2.0, 1.0, 0.0), 0.2, 0.3);
+
+    sim.run_cpu(0.01, 200);
+    assert_close(sim.capsules[upright].pos.y, 0.5, 0.01, "upright capsule");
+    assert_close(sim.capsules[lying].pos.y, 0.2, 0.01, "lying capsule");
+    assert!(tilt(&sim, lying) < 0.01, "the lying capsule should stay level");
+    let manifold = sim
+        .contact_manifolds()
+        .find(|m| m.body_a == BodyHandle::Capsule(lying))
+        .expect("lying capsule should touch the ground");
+    assert_eq!(manifold.points.len(), 2, "a lying capsule rests on both ends");
+}
+
+#[test]
+fn test_parallel_capsules_touch_along_their_length() {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    let left = add_lying_capsule(&mut sim, Vec3::new(0.0, 1.0, 0.0), 0.2, 0.5);
+    let right = add_lying_capsule(&mut sim, Vec3::new(0.3, 1.0, 0.39), 0.2, 0.5);
+    sim.step_cpu();
+
+    let manifold = sim.contact_manifolds().next().expect("the capsules overlap");
+    assert_eq!((manifold.body_a, manifold.body_b), (BodyHandle::Capsule(left), BodyHandle::Capsule(right)));
+    assert_eq!(manifold.points.len(), 2);
+    assert!(manifold.normal.z > 0.99, "normal should point from left to right: {:?}", manifold.normal);
+
+    sim.run_cpu(0.01, 50);
+    let gap = sim.capsules[right].pos.z - sim.capsules[left].pos.z;
+    assert!(gap >= 0.39, "the capsules should be pushed apart, gap = {gap}");
+    assert!(tilt(&sim, left) < 1e-3 && tilt(&sim, right) < 1e-3);
+}
+
+#[test]
+fn test_capsule_rests_across_box_edge() {
+    let mut sim = PhysicsSim::new();
+    sim.add_box_with_type(Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.5, 0.5, 0.5), Vec3::ZERO, BodyType::Static);
+    // Overhangs the edge of the box, with its centre of mass still above it
+    let capsule = add_lying_capsule(&mut sim, Vec3::new(0.3, 1.5, 0.0), 0.2, 0.4);
+
+    sim.run_cpu(0.01, 200);
+    assert_close(sim.capsules[capsule].pos.y, 1.2, 0.01, "capsule on the box");
+    assert!(tilt(&sim, capsule) < 0.01, "the capsule should not tip over the edge");
+}
+
+#[test]
+fn test_sphere_stops_at_capsule() {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    sim.add_capsule_with_type(Vec3::new(1.0, 0.0, 0.0), 0.3, 0.5, Vec3::ZERO, BodyType::Static);
+    let ball = sim.add_sphere(Vec3::new(-1.0, 0.2, 0.0), Vec3::new(2.0, 0.0, 0.0), 0.2);
+    sim.spheres[ball].material.restitution = 0.0;
+
+    sim.run_cpu(0.01, 150);
+    assert_close(sim.spheres[ball].pos.x, 0.5, 0.01, "sphere beside the capsule");
+}
+
+#[test]
+fn test_capsule_against_cylinder_cap_and_side() {
+    let mut sim = PhysicsSim::new();
+    sim.add_cylinder_with_type(Vec3::new(0.0, 0.5, 0.0), 0.4, 0.5, Vec3::ZERO, BodyType::Static);
+    let on_cap = add_lying_capsule(&mut sim, Vec3::new(0.0, 1.5, 0.0), 0.15, 0.2);
+    sim.run_cpu(0.01, 200);
+    assert_close(sim.capsules[on_cap].pos.y, 1.15, 0.01, "capsule on the cap");
+    assert!(tilt(&sim, on_cap) < 0.01, "the capsule should lie flat on the cap");
+
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    sim.add_cylinder_with_type(Vec3::ZERO, 0.4, 0.5, Vec3::ZERO, BodyType::Static);
+    let beside = sim.add_capsule(Vec3::new(0.0, 0.0, 2.0), 0.2, 0.3, Vec3::new(0.0, 0.0, -2.0));
+    sim.capsules[beside].material.restitution = 0.0;
+    sim.run_cpu(0.01, 150);
+    assert_close(sim.capsules[beside].pos.z, 0.6, 0.01, "capsule beside the cylinder");
+    let manifold = sim.contact_manifolds().next().expect("the capsule should touch the cylinder side");
+    assert_eq!(manifold.points.len(), 2, "parallel axes touch along the side");
+}
+
+#[test]
+fn test_capsule_on_hull_heightfield_and_mesh() {
+    let mut sim = PhysicsSim::new();
+    let slab: Vec<Vec3> = (0..8)
+        .map(|corner| {
+            Vec3::new(
+                if corner & 1 == 0 { -1.0 } else { 1.0 },
+                if corner & 2 == 0 { -0.25 } else { 0.25 },
+                if corner & 4 == 0 { -1.0 } else { 1.0 },
+            )
+        })
+        .collect();
+    let geometry = HullGeometry::from_points(&slab).unwrap();
+    let hull = sim.add_convex_hull(&geometry, Vec3::new(0.0, 0.25, 0.0), Vec3::ZERO);
+    sim.hulls[hull].body_type = BodyType::Static;
+    let on_hull = add_lying_capsule(&mut sim, Vec3::new(0.2, 1.0, 0.0), 0.2, 0.4);
+
+    sim.add_heightfield(Heightfield::from_fn(Vec3::new(5.0, 0.0, -5.0), 0.5, 21, 21, |_, _| 0.3));
+    let on_terrain = add_lying_capsule(&mut sim, Vec3::new(10.1, 1.0, 0.2), 0.2, 0.4);
+
+    let vertices = [
+        Vec3::new(-30.0, 0.5, -10.0),
+        Vec3::new(-10.0, 0.5, -10.0),
+        Vec3::new(-10.0, 0.5, 10.0),
+        Vec3::new(-30.0, 0.5, 10.0),
+    ];
+    sim.add_mesh(TriangleMesh::new(&vertices, vec![[0, 2, 1], [0, 3, 2]]));
+    // Placed across the diagonal the two floor triangles share
+    let on_mesh = add_lying_capsule(&mut sim, Vec3::new(-20.0, 1.5, 0.0), 0.2, 0.4);
+
+    sim.run_cpu(0.01, 200);
+    for (capsule, height, what) in [(on_hull, 0.7, "hull"), (on_terrain, 0.5, "terrain"), (on_mesh, 0.7, "mesh")] {
+        assert_close(sim.capsules[capsule].pos.y, height, 0.02, what);
+        assert!(tilt(&sim, capsule) < 0.01, "the capsule on the {what} should lie level");
+    }
+}
@@ -303,7 +303,7 @@ fn test_2d_motion_constraints() {
         sim.step_cpu();
     }
     
-    // Check that motion is constrained to X-Y plane
+    // Check that motion is constrained to the X-Y plane it started in
     let cart_z = sim.boxes[cartpole.cart_idx].pos.z;
     let pole_z = sim.cylinders[cartpole.pole_idx].pos.z;
     let cart_rot_xy = (sim.boxes[cartpole.cart_idx].angular_vel.x.powi(2) +
@@ -317,8 +317,8 @@ fn test_2d_motion_constraints() {
     println!("  Cart rotation (X,Y): {:.6}", cart_rot_xy);
     println!("  Pole rotation (X,Y): {:.6}", pole_rot_xy);
     
-    assert!(cart_z.abs() < 0.001, "Cart not constrained to Z=0: {}", cart_z);
-    assert!(pole_z.abs() < 0.001, "Pole not constrained to Z=0: {}", pole_z);
+    assert!((cart_z - 1.0).abs() < 0.001, "Cart not constrained to Z=1: {}", cart_z);
+    assert!((pole_z - 1.0).abs() < 0.001, "Pole not constrained to Z=1: {}", pole_z);
     assert!(cart_rot_xy < 0.001, "Cart has non-Z rotation: {}", cart_rot_xy);
     assert!(pole_rot_xy < 0.001, "Pole has non-Z rotation: {}", pole_rot_xy);
 }
@@ -0,0 +1,152 @@
+//! Tests for continuous collision detection of fast, small bodies
+
+use physics::{
+    BodyHandle, PhysicsSim, SolverType,
+    types::{BodyType, Vec2, Vec3},
+};
+
+/// A small sphere falling onto the ground plane at 200 m/s, far more than
+/// its diameter per step.
+fn bullet_over_ground(ccd: bool) -> (PhysicsSim, usize) {
+    let mut sim = PhysicsSim::new();
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(10.0, 10.0));
+    let bullet = sim.add_sphere(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -200.0, 0.0), 0.05);
+    sim.set_ccd(BodyHandle::Sphere(bullet), ccd);
+    (sim, bullet)
+}
+
+/// A static wall 2 cm thick in the YZ plane at x = 1.
+fn add_thin_wall(sim: &mut PhysicsSim) -> usize {
+    sim.add_box_with_type(
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(0.01, 1.0, 1.0),
+        Vec3::ZERO,
+        BodyType::Static,
+    )
+}
+
+#[test]
+fn test_fast_sphere_passes_plane_without_ccd() {
+    let (mut sim, bullet) = bullet_over_ground(false);
+    sim.step_cpu();
+    // Overlap is only checked at the end of the step, by when the sphere is
+    // far below the surface
+    assert!(sim.spheres[bullet].pos.y < -0.5, "y = {}", sim.spheres[bullet].pos.y);
+}
+
+#[test]
+fn test_swept_sphere_stops_at_plane() {
+    let (mut sim, bullet) = bullet_over_ground(true);
+    assert!(sim.ccd_enabled(BodyHandle::Sphere(bullet)));
+
+    sim.step_cpu();
+    // The step ends at the time of impact, contact_offset above the plane
+    let gap = sim.spheres[bullet].pos.y - 0.05;
+    let offset = sim.collision_config.contact_offset;
+    assert!((gap - offset).abs() < 1e-4, "gap = {gap}");
+    assert!(sim.spheres[bullet].vel.y < -100.0, "the velocity is kept for the contact solver");
+
+    for step in 0..50 {
+        sim.step_cpu();
+        let y = sim.spheres[bullet].pos.y;
+        assert!(y > 0.0, "sphere tunnelled to y = {y} at step {step}");
+    }
+}
+
+#[test]
+fn test_contact_offset_sets_impact_gap() {
+    let (mut sim, bullet) = bullet_over_ground(true);
+    sim.collision_config.contact_offset = 0.005;
+    sim.step_cpu();
+    let gap = sim.spheres[bullet].pos.y - 0.05;
+    assert!((gap - 0.005).abs() < 1e-4, "gap = {gap}");
+}
+
+#[test]
+fn test_swept_sphere_hits_thin_wall() {
+    for ccd in [false, true] {
+        let mut sim = PhysicsSim::new();
+        sim.params.gravity = Vec3::ZERO;
+        add_thin_wall(&mut sim);
+        let bullet = sim.add_sphere(Vec3::new(0.0, 0.0, 0.0), Vec3::new(150.0, 0.0, 0.0), 0.05);
+        sim.set_ccd(BodyHandle::Sphere(bullet), ccd);
+
+        sim.run_cpu(0.01, 20);
+        let x = sim.spheres[bullet].pos.x;
+        if ccd {
+            assert!(x < 0.99, "sphere passed the wall, x = {x}");
+            assert!(sim.spheres[bullet].vel.x <= 0.0, "sphere should bounce off the wall");
+        } else {
+            assert!(x > 1.0, "without CCD the sphere tunnels, x = {x}");
+        }
+    }
+}
+
+/// Box against box uses conservative advancement
+#[test]
+fn test_fast_box_stops_at_thin_wall() {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    add_thin_wall(&mut sim);
+    let body = sim.add_box(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.05, 0.05, 0.05), Vec3::new(120.0, 0.0, 0.0));
+    sim.set_ccd(BodyHandle::Box(body), true);
+
+    sim.step_cpu();
+    let gap = 0.99 - (sim.boxes[body].pos.x + 0.05);
+    let offset = sim.collision_config.contact_offset;
+    assert!(gap >= offset - 1e-4 && gap < offset + 2e-3, "gap = {gap}");
+
+    sim.run_cpu(0.01, 20);
+    let x = sim.boxes[body].pos.x;
+    assert!(x < 0.99, "box passed the wall, x = {x}");
+}
+
+/// A tumbling box falling onto a plane uses conservative advancement with
+/// rotation
+#[test]
+fn test_spinning_box_stops_above_plane() {
+    let mut sim = PhysicsSim::new();
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(10.0, 10.0));
+    let body = sim.add_box(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.05, 0.02, 0.05), Vec3::new(0.0, -150.0, 0.0));
+    sim.boxes[body].angular_vel = Vec3::new(10.0, 0.0, 6.0);
+    sim.set_ccd(BodyHandle::Box(body), true);
+
+    for step in 0..50 {
+        sim.step_cpu();
+        let y = sim.boxes[body].pos.y;
+        assert!(y > 0.0, "box tunnelled to y = {y} at step {step}");
+    }
+}
+
+#[test]
+fn test_fast_sphere_hits_sphere() {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    let target = sim.add_sphere(Vec3::new(1.0, 0.0, 0.0), Vec3::ZERO, 0.05);
+    let bullet = sim.add_sphere(Vec3::new(0.0, 0.0, 0.0), Vec3::new(150.0, 0.0, 0.0), 0.05);
+    sim.set_ccd(BodyHandle::Sphere(bullet), true);
+
+    sim.run_cpu(0.01, 5);
+    assert!(sim.spheres[target].vel.x > 50.0, "the target should be hit, vx = {}", sim.spheres[target].vel.x);
+    assert!(sim.spheres[bullet].pos.x < sim.spheres[target].pos.x);
+}
+
+#[test]
+fn test_ccd_with_xpbd_solver() {
+    let (mut sim, bullet) = bullet_over_ground(true);
+    sim.solver = SolverType::Xpbd { substeps: 4 };
+    for step in 0..50 {
+        sim.step_cpu();
+        let y = sim.spheres[bullet].pos.y;
+        assert!(y > 0.0, "sphere tunnelled to y = {y} at step {step}");
+    }
+}
+
+#[test]
+fn test_ccd_can_be_disabled() {
+    let (mut sim, bullet) = bullet_over_ground(true);
+    sim.set_ccd(BodyHandle::Sphere(bullet), false);
+    assert!(!sim.ccd_enabled(BodyHandle::Sphere(bullet)));
+    sim.step_cpu();
+    assert!(sim.spheres[bullet].pos.y < -0.5);
+}
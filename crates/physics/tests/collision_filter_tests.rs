@@ -0,0 +1,164 @@
+//! Tests for collision groups and masks, excluded pairs and joints that
+//! disable collision between the bodies they connect
+
+use std::sync::Arc;
+
+use compute::kernels::detect_contacts_sphere::{TestBody, TestContact, TestVec3};
+use compute::{BufferView, ComputeBackend, CpuBackend, Kernel};
+use physics::{
+    BodyHandle, CollisionFilter, PhysicsSim,
+    types::{BodyType, Vec2, Vec3},
+};
+
+fn ground(sim: &mut PhysicsSim) {
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+}
+
+/// Two unit spheres dropped one above the other onto the ground.
+fn stacked_spheres() -> (PhysicsSim, usize, usize) {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let lower = sim.add_sphere(Vec3::new(0.0, 1.0, 0.0), Vec3::ZERO, 1.0);
+    let upper = sim.add_sphere(Vec3::new(0.0, 3.5, 0.0), Vec3::ZERO, 1.0);
+    (sim, lower, upper)
+}
+
+#[test]
+fn test_groups_and_masks() {
+    let (mut sim, _, upper) = stacked_spheres();
+    sim.run_cpu(0.01, 200);
+    assert!(sim.spheres[upper].pos.y > 2.9, "spheres in the default group stack");
+
+    // Creatures in group 2 ignore each other but still stand on the ground,
+    // which stays in the default group 1
+    let creature = CollisionFilter { group: 2, mask: !2 };
+    let (mut sim, lower, upper) = stacked_spheres();
+    sim.set_collision_filter(BodyHandle::Sphere(lower), creature);
+    sim.set_collision_filter(BodyHandle::Sphere(upper), creature);
+    assert!(!sim.can_collide(BodyHandle::Sphere(lower), BodyHandle::Sphere(upper)));
+    assert!(sim.can_collide(BodyHandle::Sphere(lower), BodyHandle::Plane(0)));
+    sim.run_cpu(0.01, 200);
+    assert!((sim.spheres[lower].pos.y - 1.0).abs() < 0.02, "lower y = {}", sim.spheres[lower].pos.y);
+    assert!((sim.spheres[upper].pos.y - 1.0).abs() < 0.02, "upper y = {}", sim.spheres[upper].pos.y);
+
+    // Both sides must accept each other
+    let (mut sim, _, upper) = stacked_spheres();
+    sim.set_collision_filter(BodyHandle::Sphere(upper), CollisionFilter { group: 1, mask: 2 });
+    assert_eq!(sim.collision_filter(BodyHandle::Sphere(upper)).mask, 2);
+    sim.run_cpu(0.01, 200);
+    assert!(sim.spheres[upper].pos.y < 0.5, "the upper sphere also ignores the ground");
+}
+
+#[test]
+fn test_excluded_pair() {
+    let (mut sim, lower, upper) = stacked_spheres();
+    let (a, b) = (BodyHandle::Sphere(upper), BodyHandle::Sphere(lower));
+    sim.set_pair_collision(a, b, false);
+    assert!(!sim.can_collide(b, a));
+    sim.run_cpu(0.01, 200);
+    assert!((sim.spheres[upper].pos.y - 1.0).abs() < 0.02, "the upper sphere falls through the lower one");
+    assert!(sim.contact_manifolds().all(|m| m.body_b == BodyHandle::Plane(0)));
+
+    sim.set_pair_collision(a, b, true);
+    sim.spheres[upper].pos.y = 3.5;
+    sim.run_cpu(0.01, 200);
+    assert!(sim.spheres[upper].pos.y > 2.9, "the pair collides again");
+}
+
+#[test]
+fn test_excluded_pair_is_ignored_by_ccd() {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    let wall = sim.add_box_with_type(Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.01, 1.0, 1.0), Vec3::ZERO, BodyType::Static);
+    let bullet = sim.add_sphere(Vec3::ZERO, Vec3::new(200.0, 0.0, 0.0), 0.05);
+    sim.set_ccd(BodyHandle::Sphere(bullet), true);
+    sim.set_pair_collision(BodyHandle::Sphere(bullet), BodyHandle::Box(wall), false);
+    sim.step_cpu();
+    assert!(sim.spheres[bullet].pos.x > 1.5, "x = {}", sim.spheres[bullet].pos.x);
+}
+
+/// Two overlapping boxes held together by a ball joint in zero gravity.
+fn jointed_boxes(disable_collision: bool) -> PhysicsSim {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    let a = sim.add_box(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.5, 0.5, 0.5), Vec3::ZERO);
+    let b = sim.add_box(Vec3::new(0.8, 0.0, 0.0), Vec3::new(0.5, 0.5, 0.5), Vec3::ZERO);
+    let joint = sim.add_ball_joint(
+        BodyHandle::BOX_TYPE,
+        a as u32,
+        BodyHandle::BOX_TYPE,
+        b as u32,
+        Vec3::new(0.4, 0.0, 0.0),
+    );
+    sim.ball_joints[joint].disable_collision = u32::from(disable_collision);
+    sim
+}
+
+#[test]
+fn test_joint_disables_collision_between_its_bodies() {
+    let mut colliding = jointed_boxes(false);
+    colliding.step_cpu();
+    assert_eq!(colliding.contact_manifolds().count(), 1);
+
+    let mut sim = jointed_boxes(true);
+    assert!(!sim.can_collide(BodyHandle::Box(0), BodyHandle::Box(1)));
+    sim.run_cpu(0.01, 50);
+    assert_eq!(sim.contact_manifolds().count(), 0);
+    // Nothing pushes the boxes apart
+    assert!((sim.boxes[1].pos.x - sim.boxes[0].pos.x - 0.8).abs() < 1e-4);
+    assert!(Vec3::from(sim.boxes[0].vel).length() < 1e-4);
+}
+
+#[test]
+fn test_cartpole_joint_disables_collision() {
+    let mut sim = PhysicsSim::new();
+    let cartpole = physics::CartPole::new(&mut sim, Vec3::ZERO, physics::CartPoleConfig::default());
+    let (cart, pole) = (BodyHandle::Box(cartpole.cart_idx), BodyHandle::Cylinder(cartpole.pole_idx));
+    assert!(!sim.can_collide(cart, pole));
+}
+
+/// Bodies that receive a contact from the sphere contact kernel, run on the
+/// spheres of `sim` with the simulation's collision filters.
+fn sphere_kernel_contacts(sim: &PhysicsSim) -> Vec<u32> {
+    let handles: Vec<BodyHandle> = (0..sim.spheres.len()).map(BodyHandle::Sphere).collect();
+    let bodies: Vec<TestBody> = sim
+        .spheres
+        .iter()
+        .map(|s| TestBody { pos: TestVec3 { x: s.pos.x, y: s.pos.y, z: s.pos.z } })
+        .collect();
+    let filters = sim.gpu_collision_filters(&handles);
+    let view = |bytes: &[u8], len: usize, size: usize| BufferView::new(Arc::from(bytes), vec![len], size);
+    let result = CpuBackend::new()
+        .dispatch(
+            &Kernel::DetectContactsSphere,
+            &[
+                view(bytemuck::cast_slice(&bodies), bodies.len(), std::mem::size_of::<TestBody>()),
+                view(bytemuck::cast_slice(&filters), filters.len(), std::mem::size_of::<[u32; 2]>()),
+                view(&[0; std::mem::size_of::<TestContact>()], 1, std::mem::size_of::<TestContact>()),
+            ],
+            [1, 1, 1],
+        )
+        .expect("dispatch failed");
+    if result[0].is_empty() {
+        return Vec::new();
+    }
+    let contacts: &[TestContact] = bytemuck::cast_slice(&result[0]);
+    contacts.iter().map(|contact| contact.body_index).collect()
+}
+
+#[test]
+fn test_gpu_contact_kernel_respects_filters() {
+    // Three overlapping unit spheres in a row
+    let mut sim = PhysicsSim::new();
+    let spheres: Vec<BodyHandle> = (0..3u8)
+        .map(|i| BodyHandle::Sphere(sim.add_sphere(Vec3::new(f32::from(i), 0.0, 0.0), Vec3::ZERO, 1.0)))
+        .collect();
+    assert_eq!(sphere_kernel_contacts(&sim), vec![0, 1, 1, 2]);
+
+    sim.set_collision_filter(spheres[2], CollisionFilter { group: 4, mask: 4 });
+    sim.set_pair_collision(spheres[0], spheres[1], false);
+    assert!(sphere_kernel_contacts(&sim).is_empty(), "every overlapping pair is filtered out");
+
+    sim.set_pair_collision(spheres[0], spheres[1], true);
+    assert_eq!(sphere_kernel_contacts(&sim), vec![0, 1]);
+}
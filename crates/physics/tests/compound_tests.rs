@@ -0,0 +1,160 @@
+//! Tests for compound bodies: aggregated mass properties and collisions of
+//! every child
+
+use physics::{
+    BodyHandle, ChildShape, Compound, CompoundChild, PhysicsSim,
+    types::{BodyType, Vec2, Vec3},
+};
+
+fn ground(sim: &mut PhysicsSim) {
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+}
+
+fn sphere(radius: f32, x: f32, y: f32, z: f32) -> CompoundChild {
+    CompoundChild::new(ChildShape::Sphere { radius }, Vec3::new(x, y, z))
+}
+
+/// A box torso standing on four sphere feet, origin at the torso centre.
+fn walker() -> Vec<CompoundChild> {
+    let mut children = vec![CompoundChild::new(
+        ChildShape::Box { half_extents: Vec3::new(0.4, 0.2, 0.3) },
+        Vec3::ZERO,
+    )];
+    for (x, z) in [(-0.3, -0.2), (0.3, -0.2), (-0.3, 0.2), (0.3, 0.2)] {
+        children.push(sphere(0.1, x, -0.4, z));
+    }
+    children
+}
+
+/// Height between the centres of the first two children.
+fn child_height_difference(compound: &Compound) -> f32 {
+    let rotation = glam::Quat::from_array(compound.orientation);
+    let height = |child: &CompoundChild| (rotation * glam::Vec3::from(child.position)).y;
+    (height(&compound.children[0]) - height(&compound.children[1])).abs()
+}
+
+fn assert_close(actual: f32, expected: f32, tolerance: f32, what: &str) {
+    assert!((actual - expected).abs() < tolerance, "{what}: {actual} != {expected}");
+}
+
+#[test]
+fn test_compound_mass_properties() {
+    // Dumbbell of two unit spheres two apart along X
+    let dumbbell = Compound::new(vec![sphere(1.0, -1.0, 0.0, 0.0), sphere(1.0, 1.0, 0.0, 0.0)], Vec3::ZERO, 1.0);
+    let sphere_mass = 4.0 / 3.0 * std::f32::consts::PI;
+    assert_close(dumbbell.mass, 2.0 * sphere_mass, 1e-4, "mass");
+    assert_close(Vec3::from(dumbbell.pos).length(), 0.0, 1e-6, "centre of mass");
+    let mut moments = [dumbbell.inertia.x, dumbbell.inertia.y, dumbbell.inertia.z];
+    moments.sort_by(f32::total_cmp);
+    let about_axis = 2.0 * 0.4 * sphere_mass;
+    let across_axis = about_axis + 2.0 * sphere_mass;
+    assert_close(moments[0], about_axis, 1e-3, "moment about the bar");
+    assert_close(moments[1], across_axis, 1e-3, "moment across the bar");
+    assert_close(moments[2], across_axis, 1e-3, "moment across the bar");
+
+    // A heavy head pulls the centre of mass away from the handle
+    let hammer = Compound::new(
+        vec![
+            CompoundChild::new(ChildShape::Box { half_extents: Vec3::new(0.5, 0.5, 0.5) }, Vec3::new(0.0, 2.0, 0.0)),
+            CompoundChild::new(ChildShape::Cylinder { radius: 0.1, half_height: 1.5 }, Vec3::ZERO),
+        ],
+        Vec3::new(3.0, 0.0, 0.0),
+        1.0,
+    );
+    let handle_mass = std::f32::consts::PI * 0.01 * 3.0;
+    assert_close(hammer.mass, 1.0 + handle_mass, 1e-4, "hammer mass");
+    assert_close(hammer.pos.y, 2.0 / (1.0 + handle_mass), 1e-4, "hammer centre of mass");
+    assert_close(hammer.pos.x, 3.0, 1e-6, "hammer placement");
+}
+
+#[test]
+fn test_dumbbell_rests_on_both_ends() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let bar = CompoundChild::new(ChildShape::Capsule { radius: 0.05, half_height: 0.4 }, Vec3::ZERO)
+        .with_orientation([0.0, 0.0, -std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2]);
+    let dumbbell = sim.add_compound(
+        vec![sphere(0.2, -0.5, 0.0, 0.0), sphere(0.2, 0.5, 0.0, 0.0), bar],
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::ZERO,
+    );
+
+    sim.run_cpu(0.01, 200);
+    let body = &sim.compounds[dumbbell];
+    assert_close(body.pos.y, 0.2, 0.01, "dumbbell height");
+    assert!(child_height_difference(body) < 0.01, "the dumbbell should lie level");
+    let mut touching: Vec<usize> = sim
+        .contact_manifolds()
+        .filter(|m| m.body_a == BodyHandle::Compound(dumbbell))
+        .map(|m| m.child_a)
+        .collect();
+    touching.sort_unstable();
+    assert_eq!(touching, vec![0, 1], "each weight touches the ground on its own");
+}
+
+#[test]
+fn test_walker_stands_on_its_feet() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let walker = sim.add_compound(walker(), Vec3::new(0.0, 1.0, 0.0), Vec3::ZERO);
+
+    sim.run_cpu(0.01, 300);
+    // The feet reach 0.5 below the torso centre, which sits above the
+    // centre of mass
+    let rotation = glam::Quat::from_array(sim.compounds[walker].orientation);
+    let torso = glam::Vec3::from(sim.compounds[walker].pos)
+        + rotation * glam::Vec3::from(sim.compounds[walker].children[0].position);
+    assert_close(torso.y, 0.5, 0.01, "torso height");
+    assert!((rotation * glam::Vec3::Y).y > 0.999, "the walker should stay upright");
+    assert!(
+        sim.contact_manifolds().all(|m| m.child_a != 0),
+        "only the feet should touch the ground"
+    );
+}
+
+#[test]
+fn test_compound_lands_on_box_and_other_compound() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    // The box comes before the compound in handle order, so the sphere feet
+    // are body B's children of the pair
+    sim.add_box_with_type(Vec3::new(0.0, 0.5, 0.0), Vec3::new(1.0, 0.5, 1.0), Vec3::ZERO, BodyType::Static);
+    let on_box = sim.add_compound(walker(), Vec3::new(0.0, 2.0, 0.0), Vec3::ZERO);
+
+    let table = sim.add_compound(walker(), Vec3::new(5.0, 0.6, 0.0), Vec3::ZERO);
+    let stacked = sim.add_compound(walker(), Vec3::new(5.0, 2.0, 0.0), Vec3::ZERO);
+
+    sim.run_cpu(0.01, 300);
+    let torso_height = |sim: &PhysicsSim, index: usize| {
+        let body = &sim.compounds[index];
+        let rotation = glam::Quat::from_array(body.orientation);
+        (glam::Vec3::from(body.pos) + rotation * glam::Vec3::from(body.children[0].position)).y
+    };
+    assert_close(torso_height(&sim, on_box), 1.5, 0.01, "walker on the box");
+    assert_close(torso_height(&sim, table), 0.5, 0.01, "lower walker");
+    // Feet of the upper walker stand on the torso top of the lower one
+    assert_close(torso_height(&sim, stacked), 1.2, 0.02, "stacked walker");
+    assert!(sim
+        .contact_manifolds()
+        .any(|m| m.body_a == BodyHandle::Compound(table) && m.body_b == BodyHandle::Compound(stacked)));
+}
+
+#[test]
+fn test_sphere_hits_any_child() {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    let mut compound = Compound::new(walker(), Vec3::ZERO, 1.0);
+    compound.body_type = BodyType::Static;
+    sim.add_compound_body(compound);
+    // Aimed below the torso, between the two front feet
+    let ball = sim.add_sphere(Vec3::new(0.0, -0.4, 2.0), Vec3::new(0.0, 0.0, -2.0), 0.1);
+    sim.spheres[ball].material.restitution = 0.0;
+
+    sim.run_cpu(0.01, 150);
+    assert!(sim.spheres[ball].pos.z < -0.5, "the ball should pass between the feet");
+
+    let ball = sim.add_sphere(Vec3::new(0.3, -0.4, 2.0), Vec3::new(0.0, 0.0, -2.0), 0.1);
+    sim.spheres[ball].material.restitution = 0.0;
+    sim.run_cpu(0.01, 150);
+    assert_close(sim.spheres[ball].pos.z, 0.4, 0.01, "ball stopped by a foot");
+}
@@ -1,4 +1,4 @@
-use physics::{Material, PhysicsSim, Vec3};
+use physics::{BoundingBox, Material, PhysicsSim, Vec3};
 
 /// Test momentum conservation in elastic collisions
 #[test]
@@ -252,6 +252,10 @@ fn test_multiple_sphere_collisions() {
 #[test]
 fn test_spatial_grid_performance() {
     let mut sim = PhysicsSim::new();
+    sim.configure_spatial_grid(4.0, BoundingBox {
+        min: Vec3::new(-50.0, -10.0, -50.0),
+        max: Vec3::new(50.0, 90.0, 50.0),
+    });
     
     let material = Material::default();
     
@@ -0,0 +1,133 @@
+//! Tests for contact reports: touching points with impulses, begin, persist
+//! and end events, and per-body contact forces
+
+use physics::{
+    BodyHandle, ChildShape, CompoundChild, ContactEvent, ContactEventKind, PhysicsSim, SolverType,
+    types::{Vec2, Vec3},
+};
+
+fn ground(sim: &mut PhysicsSim) {
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+}
+
+/// A unit sphere dropped from just above the ground, without bouncing.
+fn dropped_sphere() -> PhysicsSim {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let sphere = sim.add_sphere(Vec3::new(0.0, 1.5, 0.0), Vec3::ZERO, 1.0);
+    sim.spheres[sphere].material.restitution = 0.0;
+    sim.params.dt = 0.01;
+    sim
+}
+
+fn event(kind: ContactEventKind) -> ContactEvent {
+    ContactEvent {
+        kind,
+        body_a: BodyHandle::Sphere(0),
+        body_b: BodyHandle::Plane(0),
+    }
+}
+
+#[test]
+fn test_begin_persist_and_end_events() {
+    let mut sim = dropped_sphere();
+    let mut steps = 0;
+    while sim.contact_events().is_empty() {
+        assert!(sim.contacts().is_empty(), "no contact before landing");
+        sim.step_cpu();
+        steps += 1;
+        assert!(steps < 100, "the sphere should land");
+    }
+    assert_eq!(sim.contact_events(), &[event(ContactEventKind::Begin)]);
+    assert_eq!(sim.touching(BodyHandle::Plane(0)).collect::<Vec<_>>(), vec![BodyHandle::Sphere(0)]);
+
+    sim.step_cpu();
+    assert_eq!(sim.contact_events(), &[event(ContactEventKind::Persist)]);
+
+    // Throw the sphere back up. The contact found at the start of the
+    // throwing step still overlaps, so the pair ends on the step after.
+    sim.spheres[0].vel = Vec3::new(0.0, 5.0, 0.0);
+    sim.step_cpu();
+    assert!(sim.contacts().iter().all(|contact| contact.impulse == Vec3::ZERO));
+    sim.step_cpu();
+    assert_eq!(sim.contact_events(), &[event(ContactEventKind::End)]);
+    assert_eq!(sim.touching(BodyHandle::Sphere(0)).count(), 0);
+    sim.step_cpu();
+    assert!(sim.contact_events().is_empty());
+}
+
+#[test]
+fn test_resting_contact_force_balances_weight() {
+    let mut sim = dropped_sphere();
+    sim.run_cpu(0.01, 200);
+    let weight = sim.spheres[0].mass * 9.81;
+
+    let force = sim.contact_force(BodyHandle::Sphere(0));
+    assert!((force.y - weight).abs() < 0.01 * weight, "force {force:?}, weight {weight}");
+    assert!(force.x.abs() < 1e-3 && force.z.abs() < 1e-3);
+    // The ground is pushed down just as hard
+    let forces = sim.contact_forces();
+    assert!((forces[&BodyHandle::Plane(0)].y + force.y).abs() < 1e-3);
+    assert_eq!(sim.contact_force(BodyHandle::Sphere(1)), Vec3::ZERO);
+
+    let contacts = sim.contacts();
+    assert_eq!(contacts.len(), 1);
+    let contact = contacts[0];
+    assert_eq!(contact.bodies, (BodyHandle::Sphere(0), BodyHandle::Plane(0)));
+    assert!(contact.position.y.abs() < 0.02);
+    assert!((contact.normal.y + 1.0).abs() < 1e-6, "the normal points from the sphere to the plane");
+    // The plane, body B, receives the downward impulse
+    assert!((contact.impulse.y + weight * 0.01).abs() < 0.01 * weight * 0.01);
+    assert_eq!(sim.get_debug_info().contacts.len(), 1);
+}
+
+#[test]
+fn test_friction_is_part_of_the_contact_force() {
+    let mut sim = dropped_sphere();
+    sim.run_cpu(0.01, 100);
+    sim.spheres[0].vel = Vec3::new(2.0, 0.0, 0.0);
+    sim.step_cpu();
+    let force = sim.contact_force(BodyHandle::Sphere(0));
+    assert!(force.x < 0.0, "friction opposes the sliding, force {force:?}");
+}
+
+#[test]
+fn test_foot_contacts_of_a_compound() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let mut children = vec![CompoundChild::new(
+        ChildShape::Box { half_extents: Vec3::new(0.4, 0.2, 0.3) },
+        Vec3::ZERO,
+    )];
+    for (x, z) in [(-0.3, -0.2), (0.3, -0.2), (-0.3, 0.2), (0.3, 0.2)] {
+        children.push(CompoundChild::new(ChildShape::Sphere { radius: 0.1 }, Vec3::new(x, -0.4, z)));
+    }
+    let walker = sim.add_compound(children, Vec3::new(0.0, 0.6, 0.0), Vec3::ZERO);
+    sim.run_cpu(0.01, 200);
+
+    let mut feet: Vec<usize> = sim
+        .contacts()
+        .iter()
+        .filter(|contact| contact.bodies.0 == BodyHandle::Compound(walker))
+        .map(|contact| contact.children.0)
+        .collect();
+    feet.sort_unstable();
+    assert_eq!(feet, vec![1, 2, 3, 4], "every foot touches the ground, the torso does not");
+    // One event for the pair, however many feet touch
+    assert_eq!(sim.contact_events().len(), 1);
+    let weight = sim.compounds[walker].mass * 9.81;
+    assert!((sim.contact_force(BodyHandle::Compound(walker)).y - weight).abs() < 0.02 * weight);
+}
+
+#[test]
+fn test_xpbd_reports_contacts() {
+    let mut sim = dropped_sphere();
+    sim.solver = SolverType::Xpbd { substeps: 8 };
+    sim.run_cpu(0.01, 200);
+    // Asleep by now, still resting its weight on the ground
+    assert!(sim.is_sleeping(BodyHandle::Sphere(0)));
+    assert_eq!(sim.contact_events(), &[event(ContactEventKind::Persist)]);
+    let weight = sim.spheres[0].mass * 9.81;
+    let force = sim.contact_force(BodyHandle::Sphere(0));
+    assert!((force.y - weight).abs() < 0.05 * weight, "force {force:?}, weight {weight}");
+}
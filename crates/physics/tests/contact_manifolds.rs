@@ -0,0 +1,140 @@
+use physics::types::Vec2;
+use physics::{BodyHandle, Material, PhysicsSim, Vec3};
+
+fn ground(sim: &mut PhysicsSim) {
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(25.0, 25.0));
+}
+
+#[test]
+fn box_on_plane_gets_four_point_manifold() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    sim.add_box(Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.5, 0.5, 0.5), Vec3::ZERO);
+
+    sim.step_cpu();
+
+    let manifold = sim
+        .contact_manifolds()
+        .find(|m| m.body_a == BodyHandle::Box(0) && m.body_b == BodyHandle::Plane(0))
+        .expect("box should touch the ground");
+    assert_eq!(manifold.points.len(), 4);
+    assert!((manifold.normal.y + 1.0).abs() < 1e-5, "normal should point from box into plane");
+}
+
+#[test]
+fn box_stack_stays_at_rest() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let half = Vec3::new(0.5, 0.5, 0.5);
+    for level in 0..5 {
+        #[allow(clippy::cast_precision_loss)]
+        let y = 0.5 + level as f32;
+        sim.add_box(Vec3::new(0.0, y, 0.0), half, Vec3::ZERO);
+    }
+
+    sim.run_cpu(0.01, 100);
+    let settled: Vec<Vec3> = sim.boxes.iter().map(|b| b.pos).collect();
+    sim.run_cpu(0.01, 200);
+
+    for (level, box_body) in sim.boxes.iter().enumerate() {
+        #[allow(clippy::cast_precision_loss)]
+        let expected_y = 0.5 + level as f32;
+        assert!(box_body.pos.x.abs() < 0.01 && box_body.pos.z.abs() < 0.01, "box {level} drifted sideways to {:?}", box_body.pos);
+        assert!((box_body.pos.y - expected_y).abs() < 0.05, "box {level} sank or jumped to y = {}", box_body.pos.y);
+        assert!((box_body.pos - settled[level]).length() < 0.005, "box {level} kept moving after settling");
+        assert!(box_body.vel.length() < 0.02, "box {level} is still jittering at {:?}", box_body.vel);
+        assert!(box_body.angular_vel.length() < 0.02, "box {level} is still rotating at {:?}", box_body.angular_vel);
+    }
+}
+
+#[test]
+fn impulses_persist_between_steps() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    sim.add_box(Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.5, 0.5, 0.5), Vec3::ZERO);
+    sim.run_cpu(0.01, 20);
+
+    let before: Vec<(u32, f32)> = sim
+        .contact_manifolds()
+        .flat_map(|m| m.points.iter().map(|p| (p.feature_id, p.normal_impulse)))
+        .collect();
+    sim.step_cpu();
+    let after: Vec<(u32, f32)> = sim
+        .contact_manifolds()
+        .flat_map(|m| m.points.iter().map(|p| (p.feature_id, p.normal_impulse)))
+        .collect();
+
+    assert_eq!(before.len(), 4);
+    let ids_before: Vec<u32> = before.iter().map(|&(id, _)| id).collect();
+    let ids_after: Vec<u32> = after.iter().map(|&(id, _)| id).collect();
+    assert_eq!(ids_before, ids_after, "feature ids should be stable for a resting box");
+
+    // The box's weight is carried by the warm-started impulses.
+    let box_body = &sim.boxes[0];
+    let weight_impulse = box_body.mass * 9.81 * sim.params.dt;
+    let total: f32 = after.iter().map(|&(_, impulse)| impulse).sum();
+    assert!((total - weight_impulse).abs() < 0.05 * weight_impulse, "total impulse {total} vs weight {weight_impulse}");
+}
+
+#[test]
+fn friction_anchors_stop_creep_on_slope() {
+    let mut sim = PhysicsSim::new();
+    let angle = 15.0_f32.to_radians();
+    let normal = Vec3::new(-angle.sin(), angle.cos(), 0.0);
+    sim.add_plane(normal, 0.0, Vec2::new(25.0, 25.0));
+    sim.planes[0].material = Material::new(1.0, 0.0);
+
+    let half = Vec3::new(0.5, 0.25, 0.5);
+    let box_idx = sim.add_box(normal * 0.25, half, Vec3::ZERO);
+    sim.boxes[box_idx].material = Material::new(1.0, 0.0);
+    let half_angle = 0.5 * angle;
+    sim.boxes[box_idx].orientation = [0.0, 0.0, half_angle.sin(), half_angle.cos()];
+
+    sim.run_cpu(0.01, 50);
+    let settled = sim.boxes[box_idx].pos;
+    sim.run_cpu(0.01, 500);
+    let drift = (sim.boxes[box_idx].pos - settled).length();
+
+    assert!(drift < 0.005, "box crept {drift} down a slope it should stick to");
+}
+
+#[test]
+fn low_friction_box_slides_down_slope() {
+    let mut sim = PhysicsSim::new();
+    let angle = 15.0_f32.to_radians();
+    let normal = Vec3::new(-angle.sin(), angle.cos(), 0.0);
+    sim.add_plane(normal, 0.0, Vec2::new(25.0, 25.0));
+    sim.planes[0].material = Material::new(0.05, 0.0);
+
+    let half = Vec3::new(0.5, 0.25, 0.5);
+    let box_idx = sim.add_box(normal * 0.25, half, Vec3::ZERO);
+    sim.boxes[box_idx].material = Material::new(0.05, 0.0);
+    let half_angle = 0.5 * angle;
+    sim.boxes[box_idx].orientation = [0.0, 0.0, half_angle.sin(), half_angle.cos()];
+
+    let start = sim.boxes[box_idx].pos;
+    sim.run_cpu(0.01, 100);
+
+    // The slope descends towards negative x.
+    assert!(sim.boxes[box_idx].pos.x < start.x - 0.1, "box should slide when friction is low");
+}
+
+#[test]
+fn rotated_box_lands_flat_on_box() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let half = Vec3::new(0.5, 0.5, 0.5);
+    sim.add_box(Vec3::new(0.0, 0.5, 0.0), half, Vec3::ZERO);
+    let top = sim.add_box(Vec3::new(0.0, 1.6, 0.0), half, Vec3::ZERO);
+    let yaw = 0.5 * 0.3_f32;
+    sim.boxes[top].orientation = [0.0, yaw.sin(), 0.0, yaw.cos()];
+
+    sim.run_cpu(0.01, 200);
+
+    let manifold = sim
+        .contact_manifolds()
+        .find(|m| m.body_a == BodyHandle::Box(0) && m.body_b == BodyHandle::Box(1))
+        .expect("boxes should be in contact");
+    assert_eq!(manifold.points.len(), 4, "face-face contact should keep four points");
+    assert!((sim.boxes[top].pos.y - 1.5).abs() < 0.02);
+}
@@ -0,0 +1,317 @@
+//! Tests for convex hull bodies and static triangle meshes: OBJ loading,
+//! hull construction, mass properties and contacts with the other shapes
+
+use physics::{
+    BodyHandle, ConvexHull, Heightfield, HullGeometry, MassProperties, ObjMesh, PhysicsError, PhysicsSim,
+    TriangleMesh,
+    types::{BodyType, Vec2, Vec3},
+};
+
+const CUBE_OBJ: &str = "\
+# unit cube centred on the origin
+v -0.5 -0.5 -0.5
+v  0.5 -0.5 -0.5
+v  0.5  0.5 -0.5
+v -0.5  0.5 -0.5
+v -0.5 -0.5  0.5
+v  0.5 -0.5  0.5
+v  0.5  0.5  0.5
+v -0.5  0.5  0.5
+vn 0 0 1
+f 1 4 3 2
+f 5 6 7 8
+f 1 2 6 5
+f 2 3 7 6
+f 3 4 8 7
+f 4 1 5 8
+";
+
+fn box_points(half: Vec3) -> Vec<Vec3> {
+    (0..8)
+        .map(|corner| {
+            Vec3::new(
+                if corner & 1 == 0 { -half.x } else { half.x },
+                if corner & 2 == 0 { -half.y } else { half.y },
+                if corner & 4 == 0 { -half.z } else { half.z },
+            )
+        })
+        .collect()
+}
+
+fn ground(sim: &mut PhysicsSim) {
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+}
+
+/// A 20 m x 20 m floor at `height` made of two triangles facing up.
+fn floor_mesh(height: f32) -> TriangleMesh {
+    let vertices = [
+        Vec3::new(-10.0, height, -10.0),
+        Vec3::new(10.0, height, -10.0),
+        Vec3::new(10.0, height, 10.0),
+        Vec3::new(-10.0, height, 10.0),
+    ];
+    TriangleMesh::new(&vertices, vec![[0, 2, 1], [0, 3, 2]])
+}
+
+fn assert_close(actual: f32, expected: f32, tolerance: f32, what: &str) {
+    assert!((actual - expected).abs() < tolerance, "{what}: {actual} != {expected}");
+}
+
+#[test]
+fn test_obj_parsing() {
+    let mesh = ObjMesh::parse(CUBE_OBJ).unwrap();
+    assert_eq!(mesh.vertices.len(), 8);
+    assert_eq!(mesh.triangles.len(), 12);
+    assert_eq!(mesh.triangles[0..2], [[0, 3, 2], [0, 2, 1]]);
+
+    // Texture and normal indices are skipped, negative indices count back
+    let mesh = ObjMesh::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1/1/1 2//1 -1\n").unwrap();
+    assert_eq!(mesh.triangles, vec![[0, 1, 2]]);
+
+    let error = |source: &str| match ObjMesh::parse(source) {
+        Err(PhysicsError::Obj { line, .. }) => line,
+        other => panic!("expected a parse error, got {other:?}"),
+    };
+    assert_eq!(error("v 0 0 0\nv 1 0\n"), 2);
+    assert_eq!(error("v 0 0 0\nv 1 0 x\n"), 2);
+    assert_eq!(error("v 0 0 0\nv 1 0 0\nv 0 1 0\n\nf 1 2 4\n"), 5);
+    assert_eq!(error("v 0 0 0\nv 1 0 0\nf 1 2\n"), 3);
+}
+
+#[test]
+fn test_load_obj_files() {
+    let path = std::env::temp_dir().join(format!("convex_mesh_test_{}.obj", std::process::id()));
+    std::fs::write(&path, CUBE_OBJ).unwrap();
+    let hull = HullGeometry::from_obj(&path);
+    let mesh = TriangleMesh::from_obj(&path, Vec3::new(0.0, 2.0, 0.0), [0.0, 0.0, 0.0, 1.0]);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(hull.unwrap().vertices().len(), 8);
+    let bounds = mesh.unwrap().bounds();
+    assert_eq!((bounds.min, bounds.max), (Vec3::new(-0.5, 1.5, -0.5), Vec3::new(0.5, 2.5, 0.5)));
+    assert!(matches!(ObjMesh::load(&path), Err(PhysicsError::Io(_))));
+}
+
+#[test]
+fn test_hull_drops_interior_points() {
+    let mut points = box_points(Vec3::new(1.0, 0.5, 0.25));
+    points.extend([Vec3::ZERO, Vec3::new(0.3, -0.2, 0.1), Vec3::new(0.99, 0.49, 0.0)]);
+    let hull = HullGeometry::from_points(&points).unwrap();
+    assert_eq!(hull.vertices().len(), 8);
+    assert_eq!(hull.triangles().len(), 12);
+
+    // Every face is wound outward, so every vertex is behind every face
+    let vertices: Vec<glam::Vec3> = hull.vertices().into_iter().map(Into::into).collect();
+    for triangle in hull.triangles() {
+        let [p0, p1, p2] = triangle.map(|index| vertices[index as usize]);
+        let normal = (p1 - p0).cross(p2 - p0);
+        assert!(vertices.iter().all(|&vertex| normal.dot(vertex - p0) <= 1e-5));
+    }
+
+    let flat = [Vec3::ZERO, Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 1.0)];
+    assert!(HullGeometry::from_points(&flat).is_none());
+}
+
+#[test]
+fn test_mass_properties_of_box() {
+    let mesh = ObjMesh::parse(CUBE_OBJ).unwrap();
+    // Shift the cube so that the centre of mass is off the origin
+    let shifted: Vec<Vec3> = mesh.vertices.iter().map(|&v| v + Vec3::new(1.0, 2.0, 3.0)).collect();
+    let properties = MassProperties::from_triangles(&shifted, &mesh.triangles, 2.0);
+    assert_close(properties.mass, 2.0, 1e-5, "mass");
+    assert!(glam::Vec3::from(properties.center_of_mass).distance(glam::Vec3::new(1.0, 2.0, 3.0)) < 1e-5);
+    for row in 0..3 {
+        for column in 0..3 {
+            let expected = if row == column { 2.0 / 6.0 } else { 0.0 };
+            assert_close(properties.inertia[row][column], expected, 1e-4, "inertia");
+        }
+    }
+
+    // Inward winding gives the same result
+    let flipped: Vec<[u32; 3]> = mesh.triangles.iter().map(|&[a, b, c]| [a, c, b]).collect();
+    assert_eq!(MassProperties::from_triangles(&shifted, &flipped, 2.0).mass, properties.mass);
+}
+
+/// A hull body's frame is its principal frame, so a box hull rotated away
+/// from the world axes still gets the box's moments of inertia
+#[test]
+fn test_hull_body_uses_principal_frame() {
+    let half = Vec3::new(0.4, 0.2, 0.1);
+    let rotation = glam::Quat::from_euler(glam::EulerRot::XYZ, 0.3, -0.7, 1.1);
+    let offset = glam::Vec3::new(0.5, -0.2, 0.1);
+    let points: Vec<Vec3> = box_points(half)
+        .into_iter()
+        .map(|p| (rotation * glam::Vec3::from(p) + offset).into())
+        .collect();
+    let hull = ConvexHull::new(&HullGeometry::from_points(&points).unwrap(), Vec3::new(0.0, 1.0, 0.0), 1.0);
+
+    assert_close(hull.mass, 8.0 * half.x * half.y * half.z, 1e-5, "mass");
+    assert!(glam::Vec3::from(hull.pos).distance(offset + glam::Vec3::Y) < 1e-5, "centre at {:?}", hull.pos);
+    let mut moments = [hull.inertia.x, hull.inertia.y, hull.inertia.z];
+    moments.sort_by(f32::total_cmp);
+    let (x2, y2, z2) = (4.0 * half.x * half.x, 4.0 * half.y * half.y, 4.0 * half.z * half.z);
+    let mut expected = [(y2 + z2) * hull.mass / 12.0, (x2 + z2) * hull.mass / 12.0, (x2 + y2) * hull.mass / 12.0];
+    expected.sort_by(f32::total_cmp);
+    for (moment, expected) in moments.iter().zip(expected) {
+        assert_close(*moment, expected, 1e-5, "principal moment");
+    }
+
+    // The geometry in world space is unchanged
+    let orientation = glam::Quat::from_array(hull.orientation);
+    let world: Vec<glam::Vec3> = hull
+        .geometry
+        .vertices()
+        .into_iter()
+        .map(|v| orientation * glam::Vec3::from(v) + glam::Vec3::from(hull.pos))
+        .collect();
+    for point in &points {
+        let target = glam::Vec3::from(*point) + glam::Vec3::Y;
+        let nearest = world.iter().map(|v| v.distance(target)).fold(f32::INFINITY, f32::min);
+        assert!(nearest < 1e-4, "vertex {target} moved by {nearest}");
+    }
+}
+
+#[test]
+fn test_hull_rests_on_plane_and_box() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let cube = HullGeometry::from_points(&box_points(Vec3::new(0.25, 0.25, 0.25))).unwrap();
+    let on_ground = sim.add_convex_hull(&cube, Vec3::new(0.0, 0.6, 0.0), Vec3::ZERO);
+    let base = sim.add_box_with_type(Vec3::new(2.0, 0.5, 0.0), Vec3::new(0.5, 0.5, 0.5), Vec3::ZERO, BodyType::Static);
+    let on_box = sim.add_convex_hull(&cube, Vec3::new(2.1, 1.6, 0.1), Vec3::ZERO);
+
+    sim.run_cpu(0.01, 200);
+    assert_close(sim.hulls[on_ground].pos.y, 0.25, 0.02, "hull on the ground");
+    assert_close(sim.hulls[on_box].pos.y, 1.25, 0.02, "hull on the box");
+    let tilt = glam::Quat::from_array(sim.hulls[on_ground].orientation) * glam::Vec3::Y;
+    assert!(tilt.abs().max_element() > 0.999, "the cube should rest on a face, up = {tilt}");
+
+    let pairs: Vec<_> = sim.contact_manifolds().map(|m| (m.body_a, m.body_b)).collect();
+    assert!(pairs.contains(&(BodyHandle::Box(base), BodyHandle::Hull(on_box))));
+    assert!(pairs.contains(&(BodyHandle::Hull(on_ground), BodyHandle::Plane(0))));
+    let on_box_points = sim
+        .contact_manifolds()
+        .find(|m| m.body_b == BodyHandle::Hull(on_box))
+        .map(|m| m.points.len());
+    assert_eq!(on_box_points, Some(4), "a resting face should get a full manifold");
+}
+
+#[test]
+fn test_hull_stack_stays_at_rest() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let cube = HullGeometry::from_points(&box_points(Vec3::new(0.25, 0.25, 0.25))).unwrap();
+    let stack: Vec<usize> = (0..3)
+        .map(|level| sim.add_convex_hull(&cube, Vec3::new(0.0, 0.25 + 0.5 * level as f32, 0.0), Vec3::ZERO))
+        .collect();
+    let pedestal = sim.add_convex_hull(&cube, Vec3::new(2.0, 0.25, 0.0), Vec3::ZERO);
+    let ball = sim.add_sphere(Vec3::new(2.05, 0.6, 0.03), Vec3::ZERO, 0.1);
+
+    sim.run_cpu(0.01, 100);
+    let settled: Vec<Vec3> = stack.iter().map(|&i| sim.hulls[i].pos).collect();
+    sim.run_cpu(0.01, 200);
+    for (level, &i) in stack.iter().enumerate() {
+        let hull = &sim.hulls[i];
+        assert!(hull.pos.x.abs() < 0.01 && hull.pos.z.abs() < 0.01, "hull {level} drifted sideways");
+        assert_close(hull.pos.y, 0.25 + 0.5 * level as f32, 0.05, "stacked hull");
+        assert!((hull.pos - settled[level]).length() < 0.005, "hull {level} kept moving after settling");
+    }
+    assert_close(sim.hulls[pedestal].pos.y, 0.25, 0.02, "pedestal");
+    assert_close(sim.spheres[ball].pos.y, 0.6, 0.02, "sphere on the pedestal");
+}
+
+#[test]
+fn test_wedge_settles_on_its_base() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let wedge = HullGeometry::from_points(&[
+        Vec3::new(-0.4, 0.0, -0.3),
+        Vec3::new(0.4, 0.0, -0.3),
+        Vec3::new(-0.4, 0.0, 0.3),
+        Vec3::new(0.4, 0.0, 0.3),
+        Vec3::new(-0.4, 0.3, -0.3),
+        Vec3::new(-0.4, 0.3, 0.3),
+    ])
+    .unwrap();
+    let ramp = sim.add_convex_hull(&wedge, Vec3::new(0.0, 0.2, 0.0), Vec3::ZERO);
+    // A box slides down the slope onto the ground
+    let block = sim.add_box(Vec3::new(-0.1, 0.6, 0.0), Vec3::new(0.05, 0.05, 0.05), Vec3::ZERO);
+    sim.boxes[block].material.friction = 0.0;
+
+    sim.run_cpu(0.01, 300);
+    // The centre of mass of the wedge is a third of its height up
+    assert_close(sim.hulls[ramp].pos.y, 0.1, 0.02, "wedge");
+    assert!(sim.boxes[block].pos.x > 0.4, "the box should slide off the wedge, x = {}", sim.boxes[block].pos.x);
+    assert_close(sim.boxes[block].pos.y, 0.05, 0.02, "box on the ground");
+}
+
+#[test]
+fn test_bodies_rest_on_triangle_mesh() {
+    let mut sim = PhysicsSim::new();
+    let floor = sim.add_mesh(floor_mesh(0.5));
+    let sphere = sim.add_sphere(Vec3::new(0.3, 1.5, 0.2), Vec3::ZERO, 0.25);
+    let body = sim.add_box(Vec3::new(3.0, 1.5, 0.0), Vec3::new(0.3, 0.2, 0.4), Vec3::ZERO);
+    let cylinder = sim.add_cylinder(Vec3::new(-3.0, 1.5, 0.0), 0.3, 0.4, Vec3::ZERO);
+    let cube = HullGeometry::from_points(&box_points(Vec3::new(0.25, 0.25, 0.25))).unwrap();
+    // Placed across the diagonal the two floor triangles share
+    let hull = sim.add_convex_hull(&cube, Vec3::new(-2.0, 1.5, -2.0), Vec3::ZERO);
+
+    sim.run_cpu(0.01, 300);
+    assert_close(sim.spheres[sphere].pos.y, 0.75, 0.02, "sphere");
+    assert_close(sim.boxes[body].pos.y, 0.7, 0.02, "box");
+    assert_close(sim.cylinders[cylinder].pos.y, 0.9, 0.02, "cylinder");
+    assert_close(sim.hulls[hull].pos.y, 0.75, 0.02, "hull");
+
+    // Meshes are always body B of their contacts
+    assert!(sim.contact_manifolds().all(|m| m.body_b == BodyHandle::Mesh(floor)));
+    assert_eq!(sim.contact_manifolds().count(), 4);
+}
+
+/// A closed mesh is a solid obstacle; an open one only collides from the
+/// front of its triangles
+#[test]
+fn test_mesh_obstacle_and_one_sided_triangles() {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    let obj = ObjMesh::parse(CUBE_OBJ).unwrap();
+    let wall: Vec<Vec3> = obj.vertices.iter().map(|&v| v + Vec3::new(2.0, 0.0, 0.0)).collect();
+    sim.add_mesh(TriangleMesh::new(&wall, obj.triangles));
+    let ball = sim.add_sphere(Vec3::new(0.0, 0.0, 0.0), Vec3::new(3.0, 0.0, 0.0), 0.2);
+    sim.spheres[ball].material.restitution = 0.0;
+
+    // A floor facing down lets a body fall through from above
+    sim.add_mesh(TriangleMesh::new(
+        &[Vec3::new(-10.0, -1.0, -10.0), Vec3::new(10.0, -1.0, -10.0), Vec3::new(0.0, -1.0, 10.0)],
+        vec![[0, 1, 2]],
+    ));
+    let faller = sim.add_sphere(Vec3::new(0.0, -0.5, 3.0), Vec3::new(0.0, -3.0, 0.0), 0.2);
+
+    sim.run_cpu(0.01, 100);
+    let x = sim.spheres[ball].pos.x;
+    assert!(x < 1.31 && x > 1.2, "the sphere should stop at the mesh, x = {x}");
+    assert!(sim.spheres[faller].pos.y < -2.0, "back faces should not collide");
+}
+
+#[test]
+fn test_hull_on_heightfield() {
+    let mut sim = PhysicsSim::new();
+    sim.add_heightfield(Heightfield::from_fn(Vec3::new(-5.0, 0.0, -5.0), 0.5, 21, 21, |_, _| 0.3));
+    let cube = HullGeometry::from_points(&box_points(Vec3::new(0.25, 0.25, 0.25))).unwrap();
+    let hull = sim.add_convex_hull(&cube, Vec3::new(0.1, 1.0, 0.2), Vec3::ZERO);
+    sim.run_cpu(0.01, 200);
+    assert_close(sim.hulls[hull].pos.y, 0.55, 0.02, "hull on the terrain");
+}
+
+#[test]
+fn test_hull_with_ccd_stops_at_mesh() {
+    let mut sim = PhysicsSim::new();
+    sim.add_mesh(floor_mesh(0.0));
+    let cube = HullGeometry::from_points(&box_points(Vec3::new(0.05, 0.05, 0.05))).unwrap();
+    let bullet = sim.add_convex_hull(&cube, Vec3::new(0.5, 2.0, 0.3), Vec3::new(0.0, -150.0, 0.0));
+    sim.set_ccd(BodyHandle::Hull(bullet), true);
+    for step in 0..50 {
+        sim.step_cpu();
+        let y = sim.hulls[bullet].pos.y;
+        assert!(y > 0.0, "hull tunnelled to y = {y} at step {step}");
+    }
+}
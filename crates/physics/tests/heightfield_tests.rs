@@ -0,0 +1,198 @@
+//! Tests for heightfield terrain: construction from functions and PNG
+//! images, surface queries, and contacts with spheres, boxes and cylinders
+
+use physics::{
+    BodyHandle, Heightfield, PhysicsSim,
+    types::{Vec2, Vec3},
+};
+
+/// A 20 m x 20 m flat heightfield at `height`, centred on the origin.
+fn flat(height: f32) -> Heightfield {
+    Heightfield::from_fn(Vec3::new(-10.0, 0.0, -10.0), 0.5, 41, 41, |_, _| height)
+}
+
+/// A slope rising along x with the given gradient, centred on the origin.
+fn slope(gradient: f32) -> Heightfield {
+    Heightfield::from_fn(Vec3::new(-10.0, 0.0, -10.0), 0.5, 41, 41, move |x, _| 5.0 + gradient * x)
+}
+
+fn encode_png(width: u32, height: u32, pixels: Vec<u16>) -> Vec<u8> {
+    let image = image::ImageBuffer::<image::Luma<u16>, _>::from_raw(width, height, pixels).unwrap();
+    let mut bytes = Vec::new();
+    image::DynamicImage::ImageLuma16(image)
+        .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+        .unwrap();
+    bytes
+}
+
+#[test]
+fn test_from_fn_samples_grid() {
+    let origin = Vec3::new(1.0, 2.0, 3.0);
+    let field = Heightfield::from_fn(origin, 0.5, 3, 2, |x, z| x + 10.0 * z);
+    assert_eq!((field.columns, field.rows), (3, 2));
+    assert_eq!(field.heights, vec![31.0, 31.5, 32.0, 36.0, 36.5, 37.0]);
+
+    let bounds = field.bounds();
+    assert_eq!(bounds.min, Vec3::new(1.0, 33.0, 3.0));
+    assert_eq!(bounds.max, Vec3::new(2.0, 39.0, 3.5));
+}
+
+/// Heights are interpolated over the triangles, which reproduce a plane
+/// exactly
+#[test]
+fn test_surface_queries_interpolate_triangles() {
+    let field = slope(0.5);
+    for (x, z) in [(0.0, 0.0), (0.3, -1.7), (-4.2, 6.9), (9.9, 9.9)] {
+        let height = field.height_at(x, z).unwrap();
+        assert!((height - (5.0 + 0.5 * x)).abs() < 1e-4, "height at ({x}, {z}) = {height}");
+        let normal: glam::Vec3 = field.normal_at(x, z).unwrap().into();
+        assert!(normal.distance(glam::Vec3::new(-0.5, 1.0, 0.0).normalize()) < 1e-5);
+    }
+    assert_eq!(field.height_at(-10.5, 0.0), None);
+    assert_eq!(field.height_at(0.0, 10.5), None);
+
+    // A single raised sample forms a pyramid over its neighbouring cells
+    let mut field = Heightfield::new(Vec3::ZERO, 1.0, 3, 3, vec![0.0; 9]);
+    field.heights[4] = 1.0;
+    assert_eq!(field.height_at(1.0, 1.0), Some(1.0));
+    assert!((field.height_at(0.5, 1.0).unwrap() - 0.5).abs() < 1e-6);
+    assert!((field.height_at(1.5, 1.5).unwrap() - 0.5).abs() < 1e-6);
+}
+
+#[test]
+fn test_from_png_bytes_maps_gray_to_height() {
+    let bytes = encode_png(3, 2, vec![0, u16::MAX / 2, u16::MAX, u16::MAX, 0, 0]);
+    let field = Heightfield::from_png_bytes(&bytes, Vec3::new(0.0, -1.0, 0.0), 0.25, 4.0).unwrap();
+    assert_eq!((field.columns, field.rows), (3, 2));
+    let expected = [0.0, 2.0, 4.0, 4.0, 0.0, 0.0];
+    for (height, expected) in field.heights.iter().zip(expected) {
+        assert!((height - expected).abs() < 1e-3, "{:?}", field.heights);
+    }
+    assert_eq!(field.height_at(0.5, 0.0), Some(3.0));
+
+    assert!(Heightfield::from_png_bytes(b"not a png", Vec3::ZERO, 1.0, 1.0).is_err());
+}
+
+#[test]
+fn test_from_png_file() {
+    let path = std::env::temp_dir().join(format!("heightfield_test_{}.png", std::process::id()));
+    std::fs::write(&path, encode_png(2, 2, vec![0, u16::MAX, u16::MAX, 0])).unwrap();
+    let field = Heightfield::from_png(&path, Vec3::ZERO, 1.0, 2.0);
+    std::fs::remove_file(&path).unwrap();
+    let field = field.unwrap();
+    assert!((field.heights[1] - 2.0).abs() < 1e-3);
+    assert!(Heightfield::from_png(&path, Vec3::ZERO, 1.0, 2.0).is_err());
+}
+
+#[test]
+fn test_bodies_rest_on_flat_heightfield() {
+    let mut sim = PhysicsSim::new();
+    let terrain = sim.add_heightfield(flat(1.0));
+    let sphere = sim.add_sphere(Vec3::new(0.1, 2.0, 0.2), Vec3::ZERO, 0.25);
+    let body = sim.add_box(Vec3::new(3.1, 2.0, 0.3), Vec3::new(0.3, 0.2, 0.4), Vec3::ZERO);
+    let cylinder = sim.add_cylinder(Vec3::new(-3.2, 2.0, 0.1), 0.3, 0.4, Vec3::ZERO);
+
+    sim.run_cpu(0.01, 300);
+    let tolerance = 0.02;
+    assert!((sim.spheres[sphere].pos.y - 1.25).abs() < tolerance, "sphere at {:?}", sim.spheres[sphere].pos);
+    assert!((sim.boxes[body].pos.y - 1.2).abs() < tolerance, "box at {:?}", sim.boxes[body].pos);
+    assert!((sim.cylinders[cylinder].pos.y - 1.4).abs() < tolerance, "cylinder at {:?}", sim.cylinders[cylinder].pos);
+
+    // Terrain is always body B of its contacts
+    let pairs: Vec<_> = sim.contact_manifolds().map(|m| (m.body_a, m.body_b)).collect();
+    assert_eq!(
+        pairs,
+        vec![
+            (BodyHandle::Sphere(sphere), BodyHandle::Heightfield(terrain)),
+            (BodyHandle::Box(body), BodyHandle::Heightfield(terrain)),
+            (BodyHandle::Cylinder(cylinder), BodyHandle::Heightfield(terrain)),
+        ]
+    );
+}
+
+/// Bodies settle on a bumpy surface instead of sinking into it
+#[test]
+fn test_bodies_rest_on_uneven_terrain() {
+    let bumps = |x: f32, z: f32| 0.3 * (1.3 * x).sin() * (0.9 * z).cos();
+    let mut sim = PhysicsSim::new();
+    sim.add_heightfield(Heightfield::from_fn(Vec3::new(-10.0, 0.0, -10.0), 0.25, 81, 81, bumps));
+    let spheres: Vec<_> = (0..5)
+        .map(|i| sim.add_sphere(Vec3::new(i as f32 * 1.7 - 4.0, 2.0, 0.6 * i as f32), Vec3::ZERO, 0.3))
+        .collect();
+    let boxes: Vec<_> = (0..5)
+        .map(|i| sim.add_box(Vec3::new(i as f32 * 1.7 - 4.0, 2.0, -3.0), Vec3::new(0.3, 0.3, 0.3), Vec3::ZERO))
+        .collect();
+
+    sim.run_cpu(0.01, 300);
+    for &i in &spheres {
+        let pos = sim.spheres[i].pos;
+        let ground = sim.heightfields[0].height_at(pos.x, pos.z).unwrap();
+        assert!(pos.y > ground + 0.2 && pos.y < ground + 0.5, "sphere {i} at {pos:?}, ground {ground}");
+    }
+    for &i in &boxes {
+        let pos = sim.boxes[i].pos;
+        let ground = sim.heightfields[0].height_at(pos.x, pos.z).unwrap();
+        assert!(pos.y > ground && pos.y < ground + 0.6, "box {i} at {pos:?}, ground {ground}");
+    }
+}
+
+#[test]
+fn test_sphere_rolls_down_slope() {
+    let mut sim = PhysicsSim::new();
+    sim.add_heightfield(slope(0.3));
+    let sphere = sim.add_sphere(Vec3::new(0.0, 5.3, 0.0), Vec3::ZERO, 0.25);
+    sim.run_cpu(0.01, 150);
+    let pos = sim.spheres[sphere].pos;
+    assert!(pos.x < -0.5, "the sphere should roll downhill, x = {}", pos.x);
+    assert!(pos.z.abs() < 0.01, "z = {}", pos.z);
+    let ground = sim.heightfields[0].height_at(pos.x, pos.z).unwrap();
+    assert!(pos.y > ground, "the sphere sank to {pos:?}");
+}
+
+/// A spike between the corners of a wide box still holds it up
+#[test]
+fn test_box_rests_on_narrow_spike() {
+    let mut heights = vec![0.0; 25];
+    heights[12] = 1.0;
+    let mut sim = PhysicsSim::new();
+    sim.add_heightfield(Heightfield::new(Vec3::new(-2.0, 0.0, -2.0), 1.0, 5, 5, heights));
+    let body = sim.add_box(Vec3::new(0.0, 1.6, 0.0), Vec3::new(0.6, 0.1, 0.6), Vec3::ZERO);
+    sim.run_cpu(0.01, 100);
+    let y = sim.boxes[body].pos.y;
+    assert!(y > 1.0, "the box should balance on the spike, y = {y}");
+}
+
+#[test]
+fn test_bodies_fall_off_terrain_edge() {
+    let mut sim = PhysicsSim::new();
+    sim.add_heightfield(flat(0.0));
+    let sphere = sim.add_sphere(Vec3::new(12.0, 0.5, 0.0), Vec3::ZERO, 0.25);
+    sim.run_cpu(0.01, 100);
+    assert!(sim.spheres[sphere].pos.y < -1.0, "nothing lies outside the grid");
+}
+
+/// Heightfields are static, so they never pair with planes or each other
+#[test]
+fn test_heightfield_with_plane() {
+    let mut sim = PhysicsSim::new();
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+    sim.add_heightfield(flat(0.5));
+    sim.add_heightfield(flat(0.6));
+    let sphere = sim.add_sphere(Vec3::new(0.0, 1.5, 0.0), Vec3::ZERO, 0.25);
+    sim.run_cpu(0.01, 200);
+    assert!((sim.spheres[sphere].pos.y - 0.85).abs() < 0.02, "y = {}", sim.spheres[sphere].pos.y);
+    assert!(sim.contact_manifolds().all(|m| m.body_a == BodyHandle::Sphere(sphere)));
+}
+
+#[test]
+fn test_fast_sphere_stops_at_heightfield_with_ccd() {
+    let mut sim = PhysicsSim::new();
+    sim.add_heightfield(flat(0.0));
+    let bullet = sim.add_sphere(Vec3::new(1.0, 2.0, 0.3), Vec3::new(0.0, -200.0, 0.0), 0.05);
+    sim.set_ccd(BodyHandle::Sphere(bullet), true);
+    for step in 0..50 {
+        sim.step_cpu();
+        let y = sim.spheres[bullet].pos.y;
+        assert!(y > 0.0, "sphere tunnelled to y = {y} at step {step}");
+    }
+}
@@ -0,0 +1,144 @@
+//! Tests for the selectable integrators: energy drift on an orbit and a
+//! free pendulum, exact ballistic flight, and resting bodies still settling
+//! under every scheme and both CPU solvers
+
+use physics::{
+    BodyHandle, Integrator, PhysicsSim, SolverType,
+    types::{BodyType, Vec2, Vec3},
+};
+
+const SCHEMES: [Integrator; 3] = [Integrator::SemiImplicitEuler, Integrator::VelocityVerlet, Integrator::Rk4];
+const SOLVERS: [SolverType; 2] = [SolverType::SequentialImpulse, SolverType::Xpbd { substeps: 4 }];
+
+/// Largest relative energy error of a sphere on an eccentric orbit about a
+/// unit point mass at the origin, over about four revolutions. The engine
+/// has no inverse-square field, so the pull is applied as a force at the
+/// start of every step.
+fn orbit_drift(integrator: Integrator, solver: SolverType, dt: f32) -> f32 {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    sim.params.dt = dt;
+    sim.integrator = integrator;
+    sim.solver = solver;
+    let planet = BodyHandle::Sphere(sim.add_sphere(Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.2), 0.1));
+    let mass = sim.spheres[0].mass;
+    let energy = |sim: &PhysicsSim| 0.5 * sim.spheres[0].vel.dot(sim.spheres[0].vel) - 1.0 / sim.spheres[0].pos.length();
+    let e0 = energy(&sim);
+    let mut worst: f32 = 0.0;
+    for _ in 0..(30.0 / dt).round() as usize {
+        let position = sim.spheres[0].pos;
+        let distance = position.length();
+        sim.apply_force(planet, position * (-mass / (distance * distance * distance)));
+        sim.step_cpu();
+        worst = worst.max((energy(&sim) - e0).abs() / e0.abs());
+    }
+    worst
+}
+
+/// Energy of a sphere swinging on a one-unit ball joint for ten seconds,
+/// released level with the pivot. Returns the largest gain and the largest
+/// loss, as heights.
+fn pendulum_drift(integrator: Integrator, solver: SolverType, dt: f32) -> (f32, f32) {
+    let mut sim = PhysicsSim::new();
+    sim.params.dt = dt;
+    sim.integrator = integrator;
+    sim.solver = solver;
+    let support = sim.add_box_with_type(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.1, 0.1, 0.1), Vec3::ZERO, BodyType::Static);
+    let bob = sim.add_sphere(Vec3::new(1.0, 5.0, 0.0), Vec3::ZERO, 0.1);
+    sim.add_ball_joint(BodyHandle::BOX_TYPE, support as u32, BodyHandle::SPHERE_TYPE, bob as u32, Vec3::new(0.0, 5.0, 0.0));
+    let g = -sim.params.gravity.y;
+    let height = |sim: &PhysicsSim| (0.5 * sim.spheres[0].vel.dot(sim.spheres[0].vel) + g * sim.spheres[0].pos.y) / g;
+    let h0 = height(&sim);
+    let (mut gain, mut loss): (f32, f32) = (0.0, 0.0);
+    for _ in 0..(10.0 / dt).round() as usize {
+        sim.step_cpu();
+        gain = gain.max(height(&sim) - h0);
+        loss = loss.max(h0 - height(&sim));
+    }
+    (gain, loss)
+}
+
+#[test]
+fn test_orbit_energy_drift_shrinks_with_the_step() {
+    for solver in SOLVERS {
+        for scheme in SCHEMES {
+            let coarse = orbit_drift(scheme, solver, 0.01);
+            let fine = orbit_drift(scheme, solver, 0.0025);
+            assert!(coarse < 0.1, "{solver:?} {scheme:?}: drift {coarse}");
+            assert!(fine < coarse * 0.5, "{solver:?} {scheme:?}: {fine} vs {coarse}");
+        }
+        // The pull is constant over each step, so every RK4 stage sees the
+        // same acceleration
+        let verlet = orbit_drift(Integrator::VelocityVerlet, solver, 0.01);
+        let rk4 = orbit_drift(Integrator::Rk4, solver, 0.01);
+        assert!((rk4 - verlet).abs() <= 1e-6, "{solver:?}: rk4 {rk4} vs verlet {verlet}");
+    }
+}
+
+#[test]
+fn test_pendulum_never_gains_energy() {
+    for solver in SOLVERS {
+        for scheme in SCHEMES {
+            let (gain, loss) = pendulum_drift(scheme, solver, 0.01);
+            assert!(gain < 1e-2, "{solver:?} {scheme:?}: gained {gain}");
+            // The joint takes out the velocity along the arm each step, so
+            // some energy is lost whatever the scheme
+            assert!(loss < 0.5, "{solver:?} {scheme:?}: lost {loss}");
+        }
+    }
+}
+
+#[test]
+fn test_pendulum_drift_shrinks_with_the_step() {
+    for scheme in SCHEMES {
+        let coarse = pendulum_drift(scheme, SolverType::SequentialImpulse, 0.01).1;
+        let fine = pendulum_drift(scheme, SolverType::SequentialImpulse, 0.0025).1;
+        assert!(fine < coarse * 0.5, "{scheme:?}: {fine} vs {coarse}");
+    }
+    let euler = pendulum_drift(Integrator::SemiImplicitEuler, SolverType::SequentialImpulse, 0.01).1;
+    let verlet = pendulum_drift(Integrator::VelocityVerlet, SolverType::SequentialImpulse, 0.01).1;
+    assert!(verlet < euler * 0.75, "verlet {verlet} vs euler {euler}");
+}
+
+#[test]
+fn test_higher_order_schemes_fly_exact_parabolas() {
+    for solver in SOLVERS {
+        for scheme in SCHEMES {
+            let mut sim = PhysicsSim::new();
+            sim.integrator = scheme;
+            sim.solver = solver;
+            let start = Vec3::new(0.0, 10.0, 0.0);
+            let launch = Vec3::new(2.0, 5.0, -1.0);
+            sim.add_sphere(start, launch, 0.1);
+            for _ in 0..100 {
+                sim.step_cpu();
+            }
+            let t = 100.0 * sim.params.dt;
+            let expected = start + launch * t + sim.params.gravity * (0.5 * t * t);
+            let error = (sim.spheres[0].pos - expected).length();
+            if scheme == Integrator::SemiImplicitEuler {
+                assert!(error > 1e-2, "{solver:?}: euler error {error}");
+            } else {
+                assert!(error < 1e-3, "{solver:?} {scheme:?}: error {error}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_resting_bodies_still_fall_asleep() {
+    for solver in SOLVERS {
+        for scheme in SCHEMES {
+            let mut sim = PhysicsSim::new();
+            sim.integrator = scheme;
+            sim.solver = solver;
+            sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+            let cube = BodyHandle::Box(sim.add_box(Vec3::new(0.0, 0.3, 0.0), Vec3::new(0.25, 0.25, 0.25), Vec3::ZERO));
+            for _ in 0..300 {
+                sim.step_cpu();
+            }
+            assert!(sim.is_sleeping(cube), "{solver:?} {scheme:?}");
+            assert!((sim.boxes[0].pos.y - 0.25).abs() < 0.02, "{solver:?} {scheme:?}: y = {}", sim.boxes[0].pos.y);
+        }
+    }
+}
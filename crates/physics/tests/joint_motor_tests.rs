@@ -0,0 +1,199 @@
+//! Tests for joint limits, velocity motors and PD servos on revolute and
+//! prismatic joints
+
+use physics::{
+    BodyHandle, JointControl, PhysicsSim,
+    types::{BodyType, Vec3},
+};
+
+/// A bar hinged about Z at its left end to a static support. Returns the
+/// simulation and the joint index.
+fn hinged_bar() -> (PhysicsSim, usize) {
+    let mut sim = PhysicsSim::new();
+    let support = sim.add_box_with_type(
+        Vec3::new(0.0, 5.0, 0.0),
+        Vec3::new(0.1, 0.1, 0.1),
+        Vec3::ZERO,
+        BodyType::Static,
+    );
+    let bar = sim.add_box(Vec3::new(1.0, 5.0, 0.0), Vec3::new(0.5, 0.05, 0.05), Vec3::ZERO);
+    let joint = sim.add_revolute_joint(
+        BodyHandle::BOX_TYPE,
+        support as u32,
+        BodyHandle::BOX_TYPE,
+        bar as u32,
+        Vec3::new(0.5, 5.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0),
+    );
+    (sim, joint)
+}
+
+/// A box on a vertical prismatic joint below a static support.
+fn vertical_slider() -> (PhysicsSim, usize) {
+    let mut sim = PhysicsSim::new();
+    let support = sim.add_box_with_type(
+        Vec3::new(0.0, 5.0, 0.0),
+        Vec3::new(0.1, 0.1, 0.1),
+        Vec3::ZERO,
+        BodyType::Static,
+    );
+    let slider = sim.add_box(Vec3::new(0.0, 4.0, 0.0), Vec3::new(0.2, 0.2, 0.2), Vec3::ZERO);
+    let joint = sim.add_prismatic_joint(
+        BodyHandle::BOX_TYPE,
+        support as u32,
+        BodyHandle::BOX_TYPE,
+        slider as u32,
+        Vec3::new(0.0, 4.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    );
+    (sim, joint)
+}
+
+#[test]
+fn test_revolute_joint_state_tracks_bar() {
+    let (mut sim, joint) = hinged_bar();
+    let state = sim.revolute_joint_state(joint).unwrap();
+    assert!(state.position.abs() < 1e-5 && state.speed.abs() < 1e-5);
+
+    sim.run_cpu(0.01, 20);
+    let state = sim.revolute_joint_state(joint).unwrap();
+    // The bar swings down, which is a negative rotation about Z
+    assert!(state.position < -0.05, "angle = {}", state.position);
+    assert!((state.speed - sim.boxes[1].angular_vel.z).abs() < 1e-4);
+    assert!(sim.revolute_joint_state(joint + 1).is_none());
+}
+
+#[test]
+fn test_revolute_limit_stops_swing() {
+    let (mut sim, joint) = hinged_bar();
+    sim.set_revolute_limits(joint, Some((-0.3, 0.3)));
+
+    for step in 0..200 {
+        sim.step_cpu();
+        let angle = sim.revolute_joint_state(joint).unwrap().position;
+        assert!(angle > -0.32, "limit crossed: angle {angle} at step {step}");
+    }
+    let state = sim.revolute_joint_state(joint).unwrap();
+    assert!((state.position + 0.3).abs() < 0.02, "bar should rest on the limit, angle = {}", state.position);
+    assert!(state.speed.abs() < 0.05, "bar should come to rest, speed = {}", state.speed);
+}
+
+#[test]
+fn test_revolute_velocity_motor_reaches_speed() {
+    let (mut sim, joint) = hinged_bar();
+    sim.params.gravity = Vec3::ZERO;
+    sim.set_revolute_control(joint, JointControl::Velocity { speed: 2.0, max_force: 100.0 });
+
+    sim.run_cpu(0.01, 20);
+    let state = sim.revolute_joint_state(joint).unwrap();
+    assert!((state.speed - 2.0).abs() < 0.01, "speed = {}", state.speed);
+    assert!(state.position > 0.3, "bar should turn, angle = {}", state.position);
+}
+
+#[test]
+fn test_revolute_motor_torque_is_capped() {
+    let (mut sim, joint) = hinged_bar();
+    sim.params.gravity = Vec3::ZERO;
+    let max_torque = 0.1;
+    sim.set_revolute_control(joint, JointControl::Velocity { speed: 10.0, max_force: max_torque });
+
+    sim.step_cpu();
+    let speed = sim.revolute_joint_state(joint).unwrap().speed;
+    // Inertia of the bar about its end: m * (4 h^2 + t^2) / 12 + m h^2
+    let bar = &sim.boxes[1];
+    let inertia = bar.mass * (4.0 * 0.25 + 4.0 * 0.0025) / 12.0 + bar.mass * 0.25;
+    let expected = max_torque * 0.01 / inertia;
+    assert!(speed > 0.0 && speed < 1.05 * expected, "speed {speed}, capped speed {expected}");
+}
+
+#[test]
+fn test_revolute_servo_reaches_target() {
+    let (mut sim, joint) = hinged_bar();
+    sim.set_revolute_control(
+        joint,
+        JointControl::Position {
+            target: 0.8,
+            stiffness: 200.0,
+            damping: 20.0,
+            max_force: 1000.0,
+        },
+    );
+
+    sim.run_cpu(0.01, 300);
+    let state = sim.revolute_joint_state(joint).unwrap();
+    // Gravity leaves a small steady-state error below the target
+    assert!((state.position - 0.8).abs() < 0.05, "angle = {}", state.position);
+    assert!(state.speed.abs() < 0.05, "speed = {}", state.speed);
+}
+
+#[test]
+fn test_controls_can_change_every_step() {
+    let (mut sim, joint) = hinged_bar();
+    sim.params.gravity = Vec3::ZERO;
+
+    let mut peak = 0.0_f32;
+    for step in 0..100 {
+        let speed = if step < 50 { 1.0 } else { -1.0 };
+        sim.set_revolute_control(joint, JointControl::Velocity { speed, max_force: 100.0 });
+        sim.step_cpu();
+        peak = peak.max(sim.revolute_joint_state(joint).unwrap().position);
+    }
+    assert!(peak > 0.45, "the bar should turn forward first, peak = {peak}");
+    let angle = sim.revolute_joint_state(joint).unwrap().position;
+    assert!(angle.abs() < 0.05, "the bar should turn back, angle = {angle}");
+
+    sim.set_revolute_control(joint, JointControl::Off);
+    assert_eq!(sim.revolute_joints[joint].control(), JointControl::Off);
+}
+
+#[test]
+fn test_prismatic_limit_stops_fall() {
+    let (mut sim, joint) = vertical_slider();
+    sim.set_prismatic_limits(joint, Some((-0.5, 0.5)));
+
+    for step in 0..150 {
+        sim.step_cpu();
+        let translation = sim.prismatic_joint_state(joint).unwrap().position;
+        assert!(translation > -0.52, "limit crossed: {translation} at step {step}");
+    }
+    let state = sim.prismatic_joint_state(joint).unwrap();
+    assert!((state.position + 0.5).abs() < 0.01, "translation = {}", state.position);
+    assert!((sim.boxes[1].pos.y - 3.5).abs() < 0.01);
+}
+
+#[test]
+fn test_prismatic_motor_lifts_against_gravity() {
+    let (mut sim, joint) = vertical_slider();
+    sim.set_prismatic_control(joint, JointControl::Velocity { speed: 0.5, max_force: 1000.0 });
+
+    sim.run_cpu(0.01, 50);
+    let state = sim.prismatic_joint_state(joint).unwrap();
+    assert!((state.speed - 0.5).abs() < 0.01, "speed = {}", state.speed);
+    assert!(state.position > 0.2, "slider should rise, translation = {}", state.position);
+
+    // A motor too weak to hold the weight lets the slider fall
+    let weight = sim.boxes[1].mass * 9.81;
+    sim.set_prismatic_control(joint, JointControl::Velocity { speed: 0.0, max_force: 0.5 * weight });
+    sim.run_cpu(0.01, 50);
+    assert!(sim.prismatic_joint_state(joint).unwrap().speed < -0.5);
+}
+
+#[test]
+fn test_prismatic_servo_holds_target_within_limits() {
+    let (mut sim, joint) = vertical_slider();
+    sim.set_prismatic_limits(joint, Some((-1.0, 0.3)));
+    sim.set_prismatic_control(
+        joint,
+        JointControl::Position {
+            target: 1.0,
+            stiffness: 500.0,
+            damping: 50.0,
+            max_force: 1000.0,
+        },
+    );
+
+    sim.run_cpu(0.01, 200);
+    let state = sim.prismatic_joint_state(joint).unwrap();
+    // The target lies beyond the upper limit, so the slider rests on it
+    assert!((state.position - 0.3).abs() < 0.01, "translation = {}", state.position);
+}
@@ -0,0 +1,172 @@
+//! Tests for the ball, prismatic and fixed joints solved on the CPU,
+//! including chains and closed loops
+
+use physics::{
+    BodyHandle, PhysicsSim,
+    types::{BodyType, Vec3},
+};
+
+fn rotate(orientation: [f32; 4], local: glam::Vec3) -> glam::Vec3 {
+    glam::Quat::from_array(orientation).normalize() * local
+}
+
+fn box_point(sim: &PhysicsSim, index: usize, local: glam::Vec3) -> glam::Vec3 {
+    let body = &sim.boxes[index];
+    glam::Vec3::from(body.pos) + rotate(body.orientation, local)
+}
+
+/// A chain of spheres hanging from a static box keeps every link length
+#[test]
+fn test_ball_joint_chain_keeps_link_lengths() {
+    let mut sim = PhysicsSim::new();
+    let support = sim.add_box_with_type(
+        Vec3::new(0.0, 5.0, 0.0),
+        Vec3::new(0.1, 0.1, 0.1),
+        Vec3::ZERO,
+        BodyType::Static,
+    );
+
+    // The chain starts out horizontal so it swings down under gravity
+    let link = 0.5;
+    let mut previous = (BodyHandle::BOX_TYPE, support);
+    let mut links = Vec::new();
+    for i in 1..=4 {
+        let x = i as f32 * link;
+        let sphere = sim.add_sphere(Vec3::new(x, 5.0, 0.0), Vec3::ZERO, 0.1);
+        let anchor = Vec3::new(x - 0.5 * link, 5.0, 0.0);
+        sim.add_ball_joint(previous.0, previous.1 as u32, BodyHandle::SPHERE_TYPE, sphere as u32, anchor);
+        links.push((previous, sphere));
+        previous = (BodyHandle::SPHERE_TYPE, sphere);
+    }
+
+    let mut lowest = f32::INFINITY;
+    for step in 0..200 {
+        sim.step_cpu();
+        for &((kind, a), b) in &links {
+            let a_pos = if kind == BodyHandle::BOX_TYPE {
+                glam::Vec3::from(sim.boxes[a].pos)
+            } else {
+                glam::Vec3::from(sim.spheres[a].pos)
+            };
+            let length = a_pos.distance(sim.spheres[b].pos.into());
+            assert!(length < link + 0.02, "link {a}-{b} stretched to {length} at step {step}");
+        }
+        lowest = lowest.min(sim.spheres[3].pos.y);
+    }
+    assert!(lowest < 4.0, "chain should swing down, lowest y = {lowest}");
+}
+
+/// A parallelogram four-bar linkage is a closed loop: every hinge must stay
+/// together and the coupler must stay parallel to the ground link
+#[test]
+fn test_closed_loop_four_bar_linkage() {
+    let mut sim = PhysicsSim::new();
+    let ground = sim.add_box_with_type(
+        Vec3::new(0.0, 5.0, 0.0),
+        Vec3::new(1.2, 0.05, 0.05),
+        Vec3::ZERO,
+        BodyType::Static,
+    );
+    // The cranks sit in front of the bars so the bodies never touch
+    let left = sim.add_box(Vec3::new(-1.0, 4.5, 0.2), Vec3::new(0.05, 0.5, 0.05), Vec3::ZERO);
+    let right = sim.add_box(Vec3::new(1.0, 4.5, 0.2), Vec3::new(0.05, 0.5, 0.05), Vec3::ZERO);
+    let coupler = sim.add_box(Vec3::new(0.0, 4.0, 0.0), Vec3::new(1.2, 0.05, 0.05), Vec3::new(3.0, 0.0, 0.0));
+
+    let hinges = [
+        (ground, left, Vec3::new(-1.0, 5.0, 0.0)),
+        (ground, right, Vec3::new(1.0, 5.0, 0.0)),
+        (coupler, left, Vec3::new(-1.0, 4.0, 0.0)),
+        (coupler, right, Vec3::new(1.0, 4.0, 0.0)),
+    ];
+    let mut local_anchors = Vec::new();
+    for &(a, b, anchor) in &hinges {
+        sim.add_revolute_joint(BodyHandle::BOX_TYPE, a as u32, BodyHandle::BOX_TYPE, b as u32, anchor, Vec3::new(0.0, 0.0, 1.0));
+        let local = |index: usize| {
+            let body = &sim.boxes[index];
+            glam::Vec3::from(anchor) - glam::Vec3::from(body.pos)
+        };
+        local_anchors.push((local(a), local(b)));
+    }
+
+    let mut furthest = 0.0_f32;
+    for step in 0..200 {
+        sim.step_cpu();
+        for (&(a, b, _), &(anchor_a, anchor_b)) in hinges.iter().zip(&local_anchors) {
+            let separation = box_point(&sim, a, anchor_a).distance(box_point(&sim, b, anchor_b));
+            assert!(separation < 0.02, "hinge {a}-{b} separated by {separation} at step {step}");
+        }
+        let along = rotate(sim.boxes[coupler].orientation, glam::Vec3::X);
+        assert!(along.dot(glam::Vec3::X) > 0.999, "coupler rotated at step {step}");
+        furthest = furthest.max(sim.boxes[coupler].pos.x.abs());
+    }
+    assert!(furthest > 0.3, "the linkage should swing, furthest x = {furthest}");
+}
+
+/// A box on an inclined prismatic joint slides down the axis without
+/// leaving it or turning
+#[test]
+fn test_prismatic_joint_slides_along_axis() {
+    let mut sim = PhysicsSim::new();
+    let base = sim.add_box_with_type(
+        Vec3::new(0.0, 5.0, 0.0),
+        Vec3::new(0.2, 0.2, 0.2),
+        Vec3::ZERO,
+        BodyType::Static,
+    );
+    let start = Vec3::new(0.0, 7.0, 0.0);
+    let slider = sim.add_box(start, Vec3::new(0.2, 0.2, 0.2), Vec3::new(0.0, 0.0, 1.0));
+    let axis = glam::Vec3::new(1.0, -1.0, 0.0).normalize();
+    sim.add_prismatic_joint(
+        BodyHandle::BOX_TYPE,
+        base as u32,
+        BodyHandle::BOX_TYPE,
+        slider as u32,
+        start,
+        axis.into(),
+    );
+
+    for step in 0..100 {
+        sim.step_cpu();
+        let body = &sim.boxes[slider];
+        let offset = glam::Vec3::from(body.pos) - glam::Vec3::from(start);
+        let off_axis = (offset - axis * offset.dot(axis)).length();
+        assert!(off_axis < 0.01, "slider left the axis by {off_axis} at step {step}");
+        let alignment = glam::Quat::from_array(body.orientation).normalize().dot(glam::Quat::IDENTITY).abs();
+        assert!(alignment > 0.9999, "slider rotated at step {step}");
+    }
+    let travelled = (glam::Vec3::from(sim.boxes[slider].pos) - glam::Vec3::from(start)).dot(axis);
+    // Free sliding along a 45 degree incline covers g * sin(45°) / 2 in 1 s
+    assert!((travelled - 0.5 * 9.81 * std::f32::consts::FRAC_1_SQRT_2).abs() < 0.3, "slider travelled {travelled}");
+}
+
+/// Two boxes held by a fixed joint move as one rigid body
+#[test]
+fn test_fixed_joint_moves_bodies_together() {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    let a = sim.add_box(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.2, 0.2, 0.2), Vec3::new(1.0, 0.0, 0.0));
+    let b = sim.add_box(Vec3::new(0.6, 5.0, 0.0), Vec3::new(0.2, 0.2, 0.2), Vec3::ZERO);
+    sim.boxes[a].angular_vel = Vec3::new(0.0, 1.0, 2.0);
+    let relative_position = glam::Vec3::new(0.6, 0.0, 0.0);
+    sim.add_fixed_joint(
+        BodyHandle::BOX_TYPE,
+        a as u32,
+        BodyHandle::BOX_TYPE,
+        b as u32,
+        relative_position.into(),
+        [0.0, 0.0, 0.0, 1.0],
+    );
+
+    for step in 0..200 {
+        sim.step_cpu();
+        let expected = box_point(&sim, a, relative_position);
+        let error = expected.distance(sim.boxes[b].pos.into());
+        assert!(error < 0.01, "fixed joint drifted by {error} at step {step}");
+        let qa = glam::Quat::from_array(sim.boxes[a].orientation).normalize();
+        let qb = glam::Quat::from_array(sim.boxes[b].orientation).normalize();
+        assert!(qa.dot(qb).abs() > 0.9999, "bodies rotated apart at step {step}");
+    }
+    let qa = glam::Quat::from_array(sim.boxes[a].orientation).normalize();
+    assert!(qa.angle_between(glam::Quat::IDENTITY) > 0.5, "the pair should spin");
+    assert!(sim.boxes[a].pos.x > 0.5, "the pair should keep drifting");
+}
@@ -0,0 +1,232 @@
+//! Tests for scene queries: ray casts against every body type, sphere
+//! casts, box overlaps and query filters
+
+use physics::{
+    BodyHandle, BoundingBox, ChildShape, CollisionFilter, CompoundChild, Heightfield, HullGeometry, PhysicsSim,
+    QueryFilter, Ray, RayHit, TriangleMesh,
+    types::{BodyType, Vec2, Vec3},
+};
+
+fn ground(sim: &mut PhysicsSim) {
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+}
+
+/// A ray from 10 m along +x towards the origin.
+fn ray_from_x() -> Ray {
+    Ray::new(Vec3::new(10.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0), 100.0)
+}
+
+fn assert_hit(hit: Option<RayHit>, body: BodyHandle, distance: f32, normal: Vec3) {
+    let hit = hit.unwrap_or_else(|| panic!("expected a hit on {body:?}"));
+    assert_eq!(hit.body, body);
+    assert!((hit.distance - distance).abs() < 1e-3, "distance {} instead of {distance}", hit.distance);
+    let error = glam::Vec3::from(hit.normal) - glam::Vec3::from(normal);
+    assert!(error.length() < 1e-3, "normal {:?} instead of {normal:?}", hit.normal);
+}
+
+#[test]
+fn test_raycast_finds_the_nearest_body() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let sphere = sim.add_sphere(Vec3::new(0.0, 2.0, 0.0), Vec3::ZERO, 0.5);
+    let down = Ray::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -3.0, 0.0), 100.0);
+
+    let hit = sim.raycast(&down, &QueryFilter::default());
+    assert_hit(hit, BodyHandle::Sphere(sphere), 7.5, Vec3::new(0.0, 1.0, 0.0));
+    let point = hit.unwrap().point;
+    assert!((point.y - 2.5).abs() < 1e-4 && point.x.abs() < 1e-4);
+
+    let all = sim.raycast_all(&down, &QueryFilter::default());
+    let bodies: Vec<BodyHandle> = all.iter().map(|hit| hit.body).collect();
+    assert_eq!(bodies, vec![BodyHandle::Sphere(sphere), BodyHandle::Plane(0)]);
+    assert!((all[1].distance - 10.0).abs() < 1e-4);
+
+    // Too short to reach, or pointing away
+    assert!(sim.raycast(&Ray::new(down.origin, down.direction, 7.0), &QueryFilter::default()).is_none());
+    assert!(sim.raycast(&Ray::new(down.origin, Vec3::new(0.0, 1.0, 0.0), 100.0), &QueryFilter::default()).is_none());
+    assert!(sim.raycast(&Ray::new(down.origin, Vec3::ZERO, 100.0), &QueryFilter::default()).is_none());
+}
+
+#[test]
+fn test_raycast_against_convex_shapes() {
+    let x = Vec3::new(1.0, 0.0, 0.0);
+
+    let mut sim = PhysicsSim::new();
+    let rotated = sim.add_box(Vec3::ZERO, Vec3::new(1.0, 2.0, 3.0), Vec3::ZERO);
+    // A quarter turn about y brings the 3 m half extent onto the x axis
+    let half = std::f32::consts::FRAC_PI_4;
+    sim.boxes[rotated].orientation = [0.0, half.sin(), 0.0, half.cos()];
+    assert_hit(sim.raycast(&ray_from_x(), &QueryFilter::default()), BodyHandle::Box(rotated), 7.0, x);
+
+    let mut sim = PhysicsSim::new();
+    let cylinder = sim.add_cylinder(Vec3::ZERO, 0.5, 1.0, Vec3::ZERO);
+    assert_hit(sim.raycast(&ray_from_x(), &QueryFilter::default()), BodyHandle::Cylinder(cylinder), 9.5, x);
+    let down = Ray::new(Vec3::new(0.2, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 10.0);
+    assert_hit(sim.raycast(&down, &QueryFilter::default()), BodyHandle::Cylinder(cylinder), 4.0, Vec3::new(0.0, 1.0, 0.0));
+
+    let mut sim = PhysicsSim::new();
+    let capsule = sim.add_capsule(Vec3::ZERO, 0.5, 1.0, Vec3::ZERO);
+    let down = Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 10.0);
+    assert_hit(sim.raycast(&down, &QueryFilter::default()), BodyHandle::Capsule(capsule), 3.5, Vec3::new(0.0, 1.0, 0.0));
+    assert_hit(sim.raycast(&ray_from_x(), &QueryFilter::default()), BodyHandle::Capsule(capsule), 9.5, x);
+
+    let mut sim = PhysicsSim::new();
+    let corners: Vec<Vec3> = (0..8)
+        .map(|i| {
+            let sign = |bit: i32| if i & bit == 0 { -1.0 } else { 1.0 };
+            Vec3::new(sign(1), sign(2), sign(4))
+        })
+        .collect();
+    let hull = sim.add_convex_hull(&HullGeometry::from_points(&corners).unwrap(), Vec3::ZERO, Vec3::ZERO);
+    assert_hit(sim.raycast(&ray_from_x(), &QueryFilter::default()), BodyHandle::Hull(hull), 9.0, x);
+}
+
+#[test]
+fn test_raycast_against_compound_children() {
+    let mut sim = PhysicsSim::new();
+    let children = vec![
+        CompoundChild::new(ChildShape::Box { half_extents: Vec3::new(0.5, 0.5, 0.5) }, Vec3::ZERO),
+        CompoundChild::new(ChildShape::Sphere { radius: 0.5 }, Vec3::new(2.0, 0.0, 0.0)),
+    ];
+    let compound = sim.add_compound(children, Vec3::ZERO, Vec3::ZERO);
+    // The sphere child is met first, the box child through the gap above it
+    assert_hit(sim.raycast(&ray_from_x(), &QueryFilter::default()), BodyHandle::Compound(compound), 7.5, Vec3::new(1.0, 0.0, 0.0));
+    let high = Ray::new(Vec3::new(10.0, 0.4, 0.0), Vec3::new(-1.0, 0.0, 0.0), 100.0);
+    assert!(sim.raycast(&high, &QueryFilter::default()).unwrap().distance < 8.0);
+    let above = Ray::new(Vec3::new(10.0, 0.55, 0.0), Vec3::new(-1.0, 0.0, 0.0), 100.0);
+    assert!(sim.raycast(&above, &QueryFilter::default()).is_none());
+    let gap = Ray::new(Vec3::new(1.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 100.0);
+    assert!(sim.raycast(&gap, &QueryFilter::default()).is_none(), "the ray passes between the children");
+}
+
+#[test]
+fn test_raycast_against_terrain_and_meshes_is_one_sided() {
+    let mut sim = PhysicsSim::new();
+    let terrain = sim.add_heightfield(Heightfield::from_fn(Vec3::new(-10.0, 0.0, -10.0), 0.5, 41, 41, |_, _| 2.0));
+    let down = Ray::new(Vec3::new(0.3, 10.0, 0.7), Vec3::new(0.0, -1.0, 0.0), 100.0);
+    assert_hit(sim.raycast(&down, &QueryFilter::default()), BodyHandle::Heightfield(terrain), 8.0, Vec3::new(0.0, 1.0, 0.0));
+    let up = Ray::new(Vec3::new(0.3, -10.0, 0.7), Vec3::new(0.0, 1.0, 0.0), 100.0);
+    assert!(sim.raycast(&up, &QueryFilter::default()).is_none(), "terrain is only hit from above");
+
+    let mut sim = PhysicsSim::new();
+    let vertices = [
+        Vec3::new(-10.0, 1.0, -10.0),
+        Vec3::new(10.0, 1.0, -10.0),
+        Vec3::new(10.0, 1.0, 10.0),
+        Vec3::new(-10.0, 1.0, 10.0),
+    ];
+    let mesh = sim.add_mesh(TriangleMesh::new(&vertices, vec![[0, 2, 1], [0, 3, 2]]));
+    // A slanted ray, so it crosses the floor 9 m along
+    let slanted = Ray::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 1.0), 100.0);
+    let hit = sim.raycast(&slanted, &QueryFilter::default());
+    assert_hit(hit, BodyHandle::Mesh(mesh), 9.0 * std::f32::consts::SQRT_2, Vec3::new(0.0, 1.0, 0.0));
+    assert!((hit.unwrap().point.z - 9.0).abs() < 1e-3);
+    let up = Ray::new(Vec3::new(0.0, -10.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 100.0);
+    assert!(sim.raycast(&up, &QueryFilter::default()).is_none());
+}
+
+#[test]
+fn test_ray_starting_inside_a_body() {
+    let mut sim = PhysicsSim::new();
+    let sphere = sim.add_sphere(Vec3::ZERO, Vec3::ZERO, 1.0);
+    let ray = Ray::new(Vec3::new(0.2, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 5.0);
+    assert_hit(sim.raycast(&ray, &QueryFilter::default()), BodyHandle::Sphere(sphere), 0.0, Vec3::new(-1.0, 0.0, 0.0));
+}
+
+#[test]
+fn test_queries_respect_filters() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let agent = sim.add_sphere(Vec3::new(0.0, 1.0, 0.0), Vec3::ZERO, 0.5);
+    let obstacle = sim.add_box(Vec3::new(0.0, 3.0, 0.0), Vec3::new(0.5, 0.5, 0.5), Vec3::ZERO);
+    sim.set_collision_filter(BodyHandle::Box(obstacle), CollisionFilter { group: 2, mask: u32::MAX });
+
+    // A sensor inside the agent looking up, then down
+    let up = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 10.0);
+    let down = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 10.0);
+    let sensor = QueryFilter::new().excluding(BodyHandle::Sphere(agent));
+    assert_hit(sim.raycast(&up, &sensor), BodyHandle::Box(obstacle), 1.5, Vec3::new(0.0, -1.0, 0.0));
+    assert_hit(sim.raycast(&down, &sensor), BodyHandle::Plane(0), 1.0, Vec3::new(0.0, 1.0, 0.0));
+    assert!(sim.raycast(&up, &sensor.clone().with_mask(1)).is_none(), "group 2 is masked out");
+    assert_eq!(sim.raycast(&up, &QueryFilter::default()).unwrap().body, BodyHandle::Sphere(agent));
+
+    let everything = BoundingBox { min: Vec3::new(-1.0, -1.0, -1.0), max: Vec3::new(1.0, 5.0, 1.0) };
+    assert_eq!(sim.overlap_aabb(&everything, &sensor.with_mask(2)), vec![BodyHandle::Box(obstacle)]);
+}
+
+#[test]
+fn test_raycast_batch_matches_single_rays() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    for i in 0..4u8 {
+        let angle = f32::from(i) * std::f32::consts::FRAC_PI_2;
+        sim.add_box_with_type(
+            Vec3::new(5.0 * angle.cos(), 1.0, 5.0 * angle.sin()),
+            Vec3::new(0.5, 1.0, 0.5),
+            Vec3::ZERO,
+            BodyType::Static,
+        );
+    }
+    // A lidar sweep of 64 horizontal rays from the centre
+    let rays: Vec<Ray> = (0..64u8)
+        .map(|i| {
+            let angle = f32::from(i) * std::f32::consts::TAU / 64.0;
+            Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(angle.cos(), 0.0, angle.sin()), 20.0)
+        })
+        .collect();
+    let filter = QueryFilter::default();
+    let hits = sim.raycast_batch(&rays, &filter);
+    assert_eq!(hits.len(), rays.len());
+    for (ray, hit) in rays.iter().zip(&hits) {
+        assert_eq!(*hit, sim.raycast(ray, &filter));
+    }
+    assert_eq!(hits[0].unwrap().body, BodyHandle::Box(0));
+    assert!((hits[0].unwrap().distance - 4.5).abs() < 1e-4);
+    assert!(hits[8].is_none(), "the diagonal passes between the boxes");
+    assert_eq!(hits.iter().flatten().count() % 4, 0);
+}
+
+#[test]
+fn test_sphere_cast() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let down = Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 10.0);
+    let hit = sim.sphere_cast(&down, 0.5, &QueryFilter::default());
+    assert_hit(hit, BodyHandle::Plane(0), 4.5, Vec3::new(0.0, 1.0, 0.0));
+    assert!(hit.unwrap().point.y.abs() < 1e-3);
+
+    // A sphere too wide to pass beside a box
+    let wall = sim.add_box_with_type(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.5, 1.0, 2.0), Vec3::ZERO, BodyType::Static);
+    let sideways = Ray::new(Vec3::new(5.0, 1.0, 2.3), Vec3::new(-1.0, 0.0, 0.0), 10.0);
+    let filter = QueryFilter::new().excluding(BodyHandle::Plane(0));
+    assert!(sim.raycast(&sideways, &filter).is_none());
+    let hit = sim.sphere_cast(&sideways, 0.5, &filter).expect("the sphere clips the wall");
+    assert_eq!(hit.body, BodyHandle::Box(wall));
+    assert!((hit.point.z - 2.0).abs() < 0.02 && (hit.point.x - 0.5).abs() < 0.02, "point {:?}", hit.point);
+    assert!(hit.distance > 4.0 && hit.distance < 4.5, "distance {}", hit.distance);
+    assert!(sim.sphere_cast(&sideways, 0.2, &filter).is_none());
+
+    // Already touching
+    let resting = Ray::new(Vec3::new(0.0, 0.4, 0.0), Vec3::new(1.0, 0.0, 0.0), 1.0);
+    assert_eq!(sim.sphere_cast(&resting, 0.5, &QueryFilter::default()).unwrap().distance, 0.0);
+}
+
+#[test]
+fn test_overlap_aabb() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let near = sim.add_sphere(Vec3::new(0.0, 1.0, 0.0), Vec3::ZERO, 0.5);
+    let far = sim.add_sphere(Vec3::new(10.0, 1.0, 0.0), Vec3::ZERO, 0.5);
+    let crate_box = sim.add_box(Vec3::new(1.4, 1.0, 0.0), Vec3::new(0.5, 0.5, 0.5), Vec3::ZERO);
+
+    let above_ground = BoundingBox { min: Vec3::new(-1.0, 0.5, -1.0), max: Vec3::new(1.0, 2.0, 1.0) };
+    let found = sim.overlap_aabb(&above_ground, &QueryFilter::default());
+    assert_eq!(found, vec![BodyHandle::Sphere(near), BodyHandle::Box(crate_box)]);
+    assert!(!found.contains(&BodyHandle::Sphere(far)));
+
+    let touching_ground = BoundingBox { min: Vec3::new(5.0, -0.5, -1.0), max: Vec3::new(11.0, 0.6, 1.0) };
+    assert_eq!(
+        sim.overlap_aabb(&touching_ground, &QueryFilter::default()),
+        vec![BodyHandle::Sphere(far), BodyHandle::Plane(0)]
+    );
+}
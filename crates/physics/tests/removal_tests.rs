@@ -0,0 +1,219 @@
+//! Tests for removing bodies and joints: stable handles, cascading joint
+//! removal, and removed bodies dropping out of contacts, queries, the GPU
+//! step and snapshots
+
+use physics::{
+    BodyHandle, JointHandle, PhysicsSim, QueryFilter, Ray, Snapshot, SolverType,
+    types::{BodyType, Vec2, Vec3},
+};
+
+fn run(sim: &mut PhysicsSim, steps: usize) {
+    for _ in 0..steps {
+        sim.step_cpu();
+    }
+}
+
+/// A static support at `pivot` with a sphere hanging from it on a ball
+/// joint, one unit away.
+fn pendulum(sim: &mut PhysicsSim, pivot: Vec3) -> (BodyHandle, BodyHandle, JointHandle) {
+    let support = sim.add_box_with_type(pivot, Vec3::new(0.1, 0.1, 0.1), Vec3::ZERO, BodyType::Static);
+    let bob = sim.add_sphere(pivot + Vec3::new(1.0, 0.0, 0.0), Vec3::ZERO, 0.1);
+    let joint = sim.add_ball_joint(BodyHandle::BOX_TYPE, support as u32, BodyHandle::SPHERE_TYPE, bob as u32, pivot);
+    (BodyHandle::Box(support), BodyHandle::Sphere(bob), JointHandle::Ball(joint))
+}
+
+#[test]
+fn test_other_handles_stay_valid_after_removal() {
+    for solver in [SolverType::SequentialImpulse, SolverType::Xpbd { substeps: 4 }] {
+        let mut sim = PhysicsSim::new();
+        sim.solver = solver;
+        sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+        for i in 0..3 {
+            sim.add_sphere(Vec3::new(i as f32 * 2.0, 3.0, 0.0), Vec3::ZERO, 0.5);
+        }
+        let cube = sim.add_box(Vec3::new(8.0, 3.0, 0.0), Vec3::new(0.5, 0.5, 0.5), Vec3::ZERO);
+
+        sim.remove_body(BodyHandle::Sphere(1));
+        let removed_at = sim.spheres[1].pos;
+        run(&mut sim, 200);
+
+        assert!(sim.is_removed(BodyHandle::Sphere(1)));
+        assert_eq!(sim.spheres[1].pos, removed_at, "{solver:?}: the removed sphere stays put");
+        for resting in [sim.spheres[0].pos.y, sim.spheres[2].pos.y, sim.boxes[cube].pos.y] {
+            assert!((resting - 0.5).abs() < 0.05, "{solver:?}: y = {resting}");
+        }
+        // The next sphere takes the removed slot
+        assert_eq!(sim.add_sphere(Vec3::new(0.0, 9.0, 0.0), Vec3::ZERO, 0.5), 1);
+        assert!(!sim.is_removed(BodyHandle::Sphere(1)));
+    }
+}
+
+#[test]
+fn test_removing_a_body_removes_its_joints() {
+    let mut sim = PhysicsSim::new();
+    let (support, bob, joint) = pendulum(&mut sim, Vec3::new(0.0, 5.0, 0.0));
+    let (_, other_bob, other_joint) = pendulum(&mut sim, Vec3::new(0.0, 5.0, 3.0));
+    let planar = sim.add_planar_constraint(bob, Vec3::new(0.0, 0.0, 1.0));
+    run(&mut sim, 10);
+
+    sim.remove_body(support);
+    assert!(sim.is_joint_removed(joint));
+    assert!(!sim.is_joint_removed(JointHandle::Planar(planar)), "the constraint holds the bob, not the support");
+    assert!(!sim.is_joint_removed(other_joint));
+    assert!(!sim.has_joint(joint) && sim.has_joint(other_joint));
+
+    sim.remove_body(bob);
+    assert!(sim.is_joint_removed(JointHandle::Planar(planar)));
+
+    // The other pendulum still swings on its own joint
+    run(&mut sim, 50);
+    let BodyHandle::Sphere(other) = other_bob else { unreachable!() };
+    let arm = sim.spheres[other].pos - Vec3::new(0.0, 5.0, 3.0);
+    assert!((arm.length() - 1.0).abs() < 0.02, "arm = {}", arm.length());
+}
+
+#[test]
+fn test_remove_joint_frees_its_bodies() {
+    let mut sim = PhysicsSim::new();
+    let support = sim.add_box_with_type(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.1, 0.1, 0.1), Vec3::ZERO, BodyType::Static);
+    let bob = sim.add_sphere(Vec3::new(1.0, 5.0, 0.0), Vec3::ZERO, 0.1);
+    let revolute = sim.add_revolute_joint(
+        BodyHandle::BOX_TYPE,
+        support as u32,
+        BodyHandle::SPHERE_TYPE,
+        bob as u32,
+        Vec3::new(0.0, 5.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0),
+    );
+    run(&mut sim, 20);
+    assert!(sim.revolute_joint_state(revolute).is_some());
+
+    sim.remove_joint(JointHandle::Revolute(revolute));
+    assert!(sim.revolute_joint_state(revolute).is_none());
+    let before = sim.spheres[0].vel;
+    let dt = sim.params.dt;
+    sim.step_cpu();
+    let expected = before.y + sim.params.gravity.y * dt;
+    assert!((sim.spheres[0].vel.y - expected).abs() < 1e-5, "vy = {}, expected {expected}", sim.spheres[0].vel.y);
+    assert!(sim.islands().iter().all(|island| island.len() == 1));
+}
+
+#[test]
+fn test_removed_body_no_longer_collides_or_answers_queries() {
+    let mut sim = PhysicsSim::new();
+    let floor = BodyHandle::Box(sim.add_box_with_type(
+        Vec3::ZERO,
+        Vec3::new(5.0, 0.5, 5.0),
+        Vec3::ZERO,
+        BodyType::Static,
+    ));
+    let ball = BodyHandle::Sphere(sim.add_sphere(Vec3::new(0.0, 1.0, 0.0), Vec3::ZERO, 0.5));
+    run(&mut sim, 60);
+    assert!(sim.touching(ball).any(|other| other == floor));
+    let down = Ray::new(Vec3::new(3.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 10.0);
+    assert_eq!(sim.raycast(&down, &QueryFilter::new()).map(|hit| hit.body), Some(floor));
+
+    sim.remove_body(floor);
+    assert_eq!(sim.touching(ball).count(), 0);
+    assert!(sim.raycast(&down, &QueryFilter::new()).is_none());
+    run(&mut sim, 60);
+    assert!(sim.spheres[0].pos.y < 0.0, "the ball fell through, y = {}", sim.spheres[0].pos.y);
+    assert!(sim.contacts().is_empty());
+}
+
+#[test]
+fn test_removed_body_drops_its_forces_and_sleep_state() {
+    let mut sim = PhysicsSim::new();
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+    let cube = BodyHandle::Box(sim.add_box(Vec3::new(0.0, 0.25, 0.0), Vec3::new(0.25, 0.25, 0.25), Vec3::ZERO));
+    run(&mut sim, 300);
+    assert!(sim.is_sleeping(cube));
+
+    sim.apply_force(cube, Vec3::new(0.0, 100.0, 0.0));
+    sim.remove_body(cube);
+    assert!(!sim.is_sleeping(cube));
+    assert_eq!(sim.applied_force(cube), (Vec3::ZERO, Vec3::ZERO));
+    sim.apply_impulse(cube, Vec3::new(0.0, 100.0, 0.0));
+    assert_eq!(sim.boxes[0].vel, Vec3::ZERO);
+    assert_eq!(sim.get_debug_info().num_awake, 0);
+}
+
+#[test]
+fn test_gpu_step_skips_removed_spheres() {
+    let mut sim = PhysicsSim::new();
+    let mut reference = PhysicsSim::new();
+    for i in 0..3 {
+        sim.add_sphere(Vec3::new(i as f32, 5.0, 0.0), Vec3::new(0.5, 0.0, 0.0), 0.5);
+    }
+    reference.add_sphere(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.5, 0.0, 0.0), 0.5);
+    reference.add_sphere(Vec3::new(2.0, 5.0, 0.0), Vec3::new(0.5, 0.0, 0.0), 0.5);
+    sim.params.forces = vec![[1.0, 0.0], [2.0, 0.0], [3.0, 0.0]];
+    reference.params.forces = vec![[1.0, 0.0], [3.0, 0.0]];
+
+    sim.remove_body(BodyHandle::Sphere(1));
+    for _ in 0..20 {
+        sim.step_gpu().unwrap();
+        reference.step_gpu().unwrap();
+    }
+    assert_eq!(sim.spheres[0].pos, reference.spheres[0].pos);
+    assert_eq!(sim.spheres[2].pos, reference.spheres[1].pos);
+    assert_eq!(sim.spheres[2].vel, reference.spheres[1].vel);
+    assert_eq!(sim.spheres[1].pos, Vec3::new(1.0, 5.0, 0.0));
+}
+
+#[test]
+fn test_snapshots_keep_removals() {
+    let mut sim = PhysicsSim::new();
+    let (support, bob, joint) = pendulum(&mut sim, Vec3::new(0.0, 5.0, 0.0));
+    sim.remove_body(support);
+    run(&mut sim, 10);
+
+    let mut loaded = Snapshot::from_bytes(&sim.snapshot().to_bytes()).unwrap().into_sim();
+    assert!(loaded.is_removed(support) && !loaded.is_removed(bob));
+    assert!(loaded.is_joint_removed(joint));
+    run(&mut sim, 20);
+    run(&mut loaded, 20);
+    assert_eq!(sim.snapshot().to_bytes(), loaded.snapshot().to_bytes());
+}
+
+#[test]
+fn test_removed_slots_are_reused_lowest_first() {
+    let mut sim = PhysicsSim::new();
+    for i in 0..4 {
+        sim.add_box(Vec3::new(i as f32 * 2.0, 3.0, 0.0), Vec3::new(0.5, 0.5, 0.5), Vec3::ZERO);
+    }
+    sim.add_sphere(Vec3::new(0.0, 6.0, 0.0), Vec3::ZERO, 0.5);
+    sim.remove_body(BodyHandle::Box(2));
+    sim.remove_body(BodyHandle::Box(0));
+    sim.remove_body(BodyHandle::Sphere(0));
+    let spheres = sim.spheres.len();
+
+    // Each kind reuses its own slots and the vectors stop growing
+    assert_eq!(sim.add_box(Vec3::new(0.0, 9.0, 0.0), Vec3::new(0.2, 0.2, 0.2), Vec3::ZERO), 0);
+    assert_eq!(sim.add_box(Vec3::new(4.0, 9.0, 0.0), Vec3::new(0.2, 0.2, 0.2), Vec3::ZERO), 2);
+    assert_eq!(sim.add_box(Vec3::new(8.0, 9.0, 0.0), Vec3::new(0.2, 0.2, 0.2), Vec3::ZERO), 4);
+    assert_eq!(sim.add_sphere(Vec3::new(0.0, 12.0, 0.0), Vec3::ZERO, 0.3), 0);
+    assert_eq!(sim.spheres.len(), spheres);
+    assert_eq!(sim.boxes.len(), 5);
+
+    // The new body in a reused slot moves like any other
+    assert_eq!(sim.boxes[0].half_extents, Vec3::new(0.2, 0.2, 0.2));
+    run(&mut sim, 10);
+    assert!(sim.boxes[0].pos.y < 9.0);
+    assert!(sim.spheres[0].pos.y < 12.0);
+}
+
+#[test]
+fn test_live_bodies_skip_removed_ones() {
+    let mut sim = PhysicsSim::new();
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+    for i in 0..3 {
+        sim.add_sphere(Vec3::new(i as f32 * 2.0, 3.0, 0.0), Vec3::ZERO, 0.5);
+    }
+    sim.remove_body(BodyHandle::Sphere(1));
+    sim.remove_body(BodyHandle::Plane(0));
+
+    let xs: Vec<f32> = sim.live(&sim.spheres, BodyHandle::Sphere).map(|sphere| sphere.pos.x).collect();
+    assert_eq!(xs, [0.0, 4.0]);
+    assert_eq!(sim.live(&sim.planes, BodyHandle::Plane).count(), 0);
+}
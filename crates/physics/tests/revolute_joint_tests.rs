@@ -2,8 +2,8 @@
 //! Following TDD principles - write tests first, then implement
 
 use physics::{
-    PhysicsSim,
-    types::{Vec3, Vec2},
+    BodyHandle, PhysicsSim,
+    types::{BodyType, Vec3, Vec2},
 };
 
 /// Test that a revolute joint keeps two bodies connected at their anchor points
@@ -248,4 +248,116 @@ fn test_cartpole_falls_over() {
     assert!(final_angle > 0.5, "Pole should have fallen significantly, but angle is only {:.3} rad", final_angle);
     
     println!("✓ Cartpole fell over as expected!");
-}
\ No newline at end of file
+}
+/// Local axis of a body expressed in world space
+fn world_axis(orientation: [f32; 4], local: glam::Vec3) -> glam::Vec3 {
+    glam::Quat::from_array(orientation).normalize() * local
+}
+
+/// A box hinged to a kinematic box about the X axis swings in the Y-Z plane
+#[test]
+fn test_revolute_joint_hinges_about_arbitrary_axis() {
+    let mut sim = PhysicsSim::new();
+    // The support sits clear of the pendulum so the two never touch
+    let support = sim.add_box_with_type(
+        Vec3::new(0.0, 2.5, 0.0),
+        Vec3::new(0.1, 0.1, 0.1),
+        Vec3::ZERO,
+        BodyType::Kinematic,
+    );
+    // Pendulum starts horizontal along +Z
+    let bob = sim.add_box(Vec3::new(0.0, 2.0, 0.5), Vec3::new(0.05, 0.05, 0.5), Vec3::ZERO);
+    let hinge = Vec3::new(0.0, 2.0, 0.0);
+    sim.add_revolute_joint(
+        BodyHandle::BOX_TYPE, support as u32,
+        BodyHandle::BOX_TYPE, bob as u32,
+        hinge,
+        Vec3::new(1.0, 0.0, 0.0),
+    );
+
+    let anchor_on_bob = glam::Vec3::new(0.0, 0.0, -0.5);
+    let mut lowest = f32::INFINITY;
+    for step in 0..150 {
+        sim.step_cpu();
+        let body = &sim.boxes[bob];
+        let anchor = glam::Vec3::from(body.pos) + world_axis(body.orientation, anchor_on_bob);
+        let separation = anchor.distance(hinge.into());
+        assert!(separation < 0.01, "anchor separated by {separation} at step {step}");
+        assert!(body.pos.x.abs() < 1e-3, "bob left the swing plane at step {step}");
+        let alignment = world_axis(body.orientation, glam::Vec3::X).dot(glam::Vec3::X);
+        assert!(alignment > 0.9999, "hinge axes diverged at step {step}");
+        lowest = lowest.min(body.pos.y);
+    }
+    assert!(lowest < 1.6, "bob should swing down, lowest y = {lowest}");
+}
+
+/// Any pair of shapes can be hinged, including a sphere hanging from a plane
+#[test]
+fn test_revolute_joint_between_sphere_and_static_plane() {
+    let mut sim = PhysicsSim::new();
+    let ceiling = sim.add_plane(Vec3::new(0.0, -1.0, 0.0), 5.0, Vec2::new(10.0, 10.0));
+    let ball = sim.add_sphere(Vec3::new(1.0, 4.0, 0.0), Vec3::ZERO, 0.1);
+    sim.add_revolute_joint(
+        BodyHandle::PLANE_TYPE, ceiling as u32,
+        BodyHandle::SPHERE_TYPE, ball as u32,
+        Vec3::new(0.0, 4.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0),
+    );
+
+    let mut lowest = f32::INFINITY;
+    for _ in 0..100 {
+        sim.step_cpu();
+        let pos = sim.spheres[ball].pos;
+        let radius = (pos - Vec3::new(0.0, 4.0, 0.0)).length();
+        assert!((radius - 1.0).abs() < 0.01, "pendulum length changed to {radius}");
+        assert!(pos.z.abs() < 1e-3, "pendulum left its swing plane");
+        lowest = lowest.min(pos.y);
+    }
+    assert!(lowest < 3.1, "sphere should swing through the bottom, lowest y = {lowest}");
+}
+
+/// Without a planar constraint a hinged body moves freely in 3D
+#[test]
+fn test_revolute_joint_is_not_planar_by_default() {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    let cart = sim.add_box_with_type(Vec3::ZERO, Vec3::new(0.5, 0.25, 0.25), Vec3::ZERO, BodyType::Kinematic);
+    let pole = sim.add_cylinder(Vec3::new(0.0, 1.25, 0.0), 0.05, 1.0, Vec3::ZERO);
+    sim.add_revolute_joint(
+        BodyHandle::BOX_TYPE, cart as u32,
+        BodyHandle::CYLINDER_TYPE, pole as u32,
+        Vec3::new(0.0, 0.25, 0.0),
+        Vec3::new(0.0, 0.0, 1.0),
+    );
+    sim.boxes[cart].vel = Vec3::new(0.0, 0.0, 1.0);
+
+    sim.run_cpu(0.01, 50);
+
+    assert!(sim.boxes[cart].pos.z > 0.4);
+    assert!(
+        (sim.cylinders[pole].pos.z - sim.boxes[cart].pos.z).abs() < 0.01,
+        "pole should follow the cart out of the X-Y plane"
+    );
+}
+
+/// A planar constraint keeps a body in its plane and only lets it spin about
+/// the plane normal
+#[test]
+fn test_planar_constraint_keeps_body_in_plane() {
+    let mut sim = PhysicsSim::new();
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(10.0, 10.0));
+    let body = sim.add_box(Vec3::new(0.0, 2.0, 0.3), Vec3::new(0.2, 0.2, 0.2), Vec3::new(1.0, 0.0, 2.0));
+    sim.boxes[body].angular_vel = Vec3::new(3.0, 1.0, 2.0);
+    sim.add_planar_constraint(BodyHandle::Box(body), Vec3::new(0.0, 0.0, 1.0));
+
+    for _ in 0..100 {
+        sim.step_cpu();
+        let b = &sim.boxes[body];
+        assert!((b.pos.z - 0.3).abs() < 1e-4, "box left its plane: z = {}", b.pos.z);
+        assert!(b.vel.z.abs() < 1e-4);
+        assert!(b.angular_vel.x.abs() < 1e-4 && b.angular_vel.y.abs() < 1e-4);
+        let local_z = world_axis(b.orientation, glam::Vec3::Z);
+        assert!(local_z.dot(glam::Vec3::Z) > 0.9999, "box tilted out of its plane");
+    }
+    assert!(sim.boxes[body].pos.x > 0.5, "box should still move within the plane");
+}
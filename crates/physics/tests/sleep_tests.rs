@@ -0,0 +1,194 @@
+//! Tests for island building and sleeping: falling asleep, waking on
+//! contact, force and pose changes, and the debug counts
+
+use physics::{
+    BodyHandle, JointControl, PhysicsSim, SolverType,
+    types::{BodyType, Material, Vec2, Vec3},
+};
+
+fn ground(sim: &mut PhysicsSim) {
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+}
+
+/// A stack of `height` boxes of half size 0.25 resting on the ground at `x`.
+fn box_stack(sim: &mut PhysicsSim, x: f32, height: usize) -> Vec<usize> {
+    (0..height)
+        .map(|level| {
+            let y = 0.25 + 0.5 * level as f32;
+            sim.add_box(Vec3::new(x, y, 0.0), Vec3::new(0.25, 0.25, 0.25), Vec3::ZERO)
+        })
+        .collect()
+}
+
+/// Step until every dynamic body sleeps, returning the number of steps.
+fn settle(sim: &mut PhysicsSim, max_steps: usize) -> usize {
+    for step in 1..=max_steps {
+        sim.step_cpu();
+        if sim.get_debug_info().num_awake == 0 {
+            return step;
+        }
+    }
+    panic!("bodies still awake after {max_steps} steps");
+}
+
+#[test]
+fn test_resting_stack_falls_asleep() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let stack = box_stack(&mut sim, 0.0, 3);
+    let sphere = sim.add_sphere(Vec3::new(3.0, 0.5, 0.0), Vec3::ZERO, 0.25);
+
+    settle(&mut sim, 300);
+    let info = sim.get_debug_info();
+    assert_eq!((info.num_awake, info.num_sleeping), (0, 4));
+    assert!(sim.is_sleeping(BodyHandle::Sphere(sphere)));
+
+    // Sleeping bodies hold their pose exactly
+    let before: Vec<_> = stack.iter().map(|&i| sim.boxes[i].pos).collect();
+    sim.run_cpu(0.01, 50);
+    let after: Vec<_> = stack.iter().map(|&i| sim.boxes[i].pos).collect();
+    assert_eq!(before, after);
+    assert!((sim.boxes[stack[2]].pos.y - 1.25).abs() < 0.02);
+}
+
+#[test]
+fn test_moving_bodies_stay_awake() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let sphere = sim.add_sphere(Vec3::new(0.0, 0.25, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.25);
+    sim.spheres[sphere].material.friction = 0.0;
+    for _ in 0..200 {
+        sim.step_cpu();
+        assert!(!sim.is_sleeping(BodyHandle::Sphere(sphere)));
+    }
+}
+
+#[test]
+fn test_islands_group_touching_and_jointed_bodies() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let left = box_stack(&mut sim, -2.0, 2);
+    let right = box_stack(&mut sim, 2.0, 2);
+    let a = sim.add_sphere(Vec3::new(0.0, 3.0, 0.0), Vec3::ZERO, 0.1);
+    let b = sim.add_sphere(Vec3::new(0.0, 2.0, 0.0), Vec3::ZERO, 0.1);
+    sim.add_joint(a as u32, b as u32, 1.0);
+    sim.step_cpu();
+
+    // The ground is static, so it does not join the two stacks
+    let mut islands = sim.islands();
+    islands.sort();
+    assert_eq!(
+        islands,
+        vec![
+            vec![BodyHandle::Sphere(a), BodyHandle::Sphere(b)],
+            left.iter().map(|&i| BodyHandle::Box(i)).collect(),
+            right.iter().map(|&i| BodyHandle::Box(i)).collect(),
+        ]
+    );
+}
+
+#[test]
+fn test_falling_body_wakes_island_on_contact() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let stack = box_stack(&mut sim, 0.0, 2);
+    let other = box_stack(&mut sim, 3.0, 1);
+    settle(&mut sim, 300);
+
+    let sphere = sim.add_sphere_with_mass_and_material(Vec3::new(0.0, 3.0, 0.0), Vec3::ZERO, 0.2, 0.1, Material::default());
+    let mut woke = false;
+    for _ in 0..100 {
+        sim.step_cpu();
+        woke |= !sim.is_sleeping(BodyHandle::Box(stack[0]));
+        assert!(sim.is_sleeping(BodyHandle::Box(other[0])), "an untouched island should sleep on");
+    }
+    assert!(woke, "the whole stack should wake when the sphere lands on top");
+    assert!(sim.spheres[sphere].pos.y > 1.0, "the sphere should land on the stack");
+}
+
+#[test]
+fn test_force_velocity_and_pose_changes_wake_bodies() {
+    let mut sim = PhysicsSim::new();
+    ground(&mut sim);
+    let body = box_stack(&mut sim, 0.0, 1)[0];
+    sim.params.forces = vec![[0.0, 0.0]];
+    let handle = BodyHandle::Box(body);
+
+    settle(&mut sim, 300);
+    sim.set_force(body, [2.0, 0.0]);
+    sim.step_cpu();
+    assert!(!sim.is_sleeping(handle), "an applied force should wake the box");
+    sim.run_cpu(0.01, 20);
+    assert!(sim.boxes[body].pos.x > 0.01, "the box should be pushed along");
+    sim.set_force(body, [0.0, 0.0]);
+
+    settle(&mut sim, 300);
+    sim.boxes[body].vel = Vec3::new(0.0, 2.0, 0.0);
+    sim.step_cpu();
+    assert!(!sim.is_sleeping(handle), "a new velocity should wake the box");
+
+    settle(&mut sim, 300);
+    sim.boxes[body].pos.y = 2.0;
+    sim.step_cpu();
+    assert!(!sim.is_sleeping(handle), "moving the box should wake it");
+    assert!(sim.boxes[body].pos.y < 2.0, "the box should fall again");
+
+    settle(&mut sim, 300);
+    sim.wake(handle);
+    assert!(!sim.is_sleeping(handle));
+}
+
+#[test]
+fn test_motor_keeps_island_awake() {
+    let mut sim = PhysicsSim::new();
+    sim.params.gravity = Vec3::ZERO;
+    let base = sim.add_box_with_type(Vec3::ZERO, Vec3::new(0.1, 0.1, 0.1), Vec3::ZERO, BodyType::Static);
+    let arm = sim.add_box(Vec3::new(0.5, 0.0, 0.0), Vec3::new(0.3, 0.05, 0.05), Vec3::ZERO);
+    let joint = sim.add_revolute_joint(
+        BodyHandle::BOX_TYPE,
+        base as u32,
+        BodyHandle::BOX_TYPE,
+        arm as u32,
+        Vec3::ZERO,
+        Vec3::new(0.0, 0.0, 1.0),
+    );
+    sim.set_revolute_control(
+        joint,
+        JointControl::Position {
+            target: 0.0,
+            stiffness: 10.0,
+            damping: 1.0,
+            max_force: 10.0,
+        },
+    );
+    sim.run_cpu(0.01, 200);
+    assert!(!sim.is_sleeping(BodyHandle::Box(arm)), "a motorised joint keeps its island awake");
+}
+
+#[test]
+fn test_sleeping_can_be_disabled() {
+    let mut sim = PhysicsSim::new();
+    sim.sleep_params.enabled = false;
+    ground(&mut sim);
+    box_stack(&mut sim, 0.0, 2);
+    sim.run_cpu(0.01, 300);
+    let info = sim.get_debug_info();
+    assert_eq!((info.num_awake, info.num_sleeping), (2, 0));
+}
+
+#[test]
+fn test_xpbd_bodies_fall_asleep_and_wake() {
+    let mut sim = PhysicsSim::new();
+    sim.solver = SolverType::Xpbd { substeps: 4 };
+    ground(&mut sim);
+    let stack = box_stack(&mut sim, 0.0, 2);
+    settle(&mut sim, 300);
+    let rest = sim.boxes[stack[1]].pos;
+
+    sim.run_cpu(0.01, 20);
+    assert_eq!(sim.boxes[stack[1]].pos, rest);
+
+    sim.boxes[stack[0]].vel = Vec3::new(1.0, 0.0, 0.0);
+    sim.step_cpu();
+    assert!(!sim.is_sleeping(BodyHandle::Box(stack[1])), "the island should wake as a whole");
+}
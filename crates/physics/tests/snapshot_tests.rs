@@ -0,0 +1,115 @@
+//! Tests for snapshots: in-memory restore, and binary and JSON encodings
+//! that continue the simulation bit for bit
+
+use physics::{
+    BodyHandle, HullGeometry, PhysicsError, PhysicsSim, Snapshot, SolverType,
+    types::{BodyType, Vec2, Vec3},
+};
+
+/// A scene with resting contacts, a joint chain and a hull, so the
+/// warm-start caches are all in use.
+fn scene() -> PhysicsSim {
+    let mut sim = PhysicsSim::new();
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+    for i in 0..3 {
+        sim.add_box(Vec3::new(0.0, 0.5 + i as f32 * 1.01, 0.0), Vec3::new(0.5, 0.5, 0.5), Vec3::ZERO);
+    }
+    sim.add_sphere(Vec3::new(2.0, 0.6, 0.0), Vec3::new(1.5, 0.0, 0.3), 0.5);
+
+    let support = sim.add_box_with_type(Vec3::new(5.0, 4.0, 0.0), Vec3::new(0.1, 0.1, 0.1), Vec3::ZERO, BodyType::Static);
+    let link = sim.add_sphere(Vec3::new(5.5, 4.0, 0.0), Vec3::ZERO, 0.1);
+    sim.add_ball_joint(BodyHandle::BOX_TYPE, support as u32, BodyHandle::SPHERE_TYPE, link as u32, Vec3::new(5.25, 4.0, 0.0));
+
+    let tetrahedron = [
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0),
+    ];
+    let geometry = HullGeometry::from_points(&tetrahedron).unwrap();
+    sim.add_convex_hull(&geometry, Vec3::new(-3.0, 1.0, 0.0), Vec3::ZERO);
+    sim
+}
+
+fn run(sim: &mut PhysicsSim, steps: usize) {
+    for _ in 0..steps {
+        sim.step_cpu();
+    }
+}
+
+/// Whether two simulations are in exactly the same state.
+fn same_state(a: &PhysicsSim, b: &PhysicsSim) -> bool {
+    a.snapshot().to_bytes() == b.snapshot().to_bytes()
+}
+
+#[test]
+fn test_restore_replays_the_same_trajectory() {
+    for solver in [SolverType::SequentialImpulse, SolverType::Xpbd { substeps: 4 }] {
+        let mut sim = scene();
+        sim.solver = solver;
+        run(&mut sim, 60);
+        let checkpoint = sim.snapshot();
+        run(&mut sim, 60);
+        let first = sim.snapshot();
+
+        sim.restore(&checkpoint);
+        assert!(same_state(&sim, &checkpoint.clone().into_sim()), "{solver:?}");
+        run(&mut sim, 60);
+        assert!(same_state(&sim, &first.into_sim()), "{solver:?}: replay diverged");
+    }
+}
+
+#[test]
+fn test_binary_round_trip_is_bit_exact() {
+    let mut sim = scene();
+    run(&mut sim, 40);
+    let bytes = sim.snapshot().to_bytes();
+    assert_eq!(&bytes[..8], b"JAXSSNAP");
+
+    let mut loaded = Snapshot::from_bytes(&bytes).unwrap().into_sim();
+    assert_eq!(loaded.snapshot().to_bytes(), bytes);
+    run(&mut sim, 40);
+    run(&mut loaded, 40);
+    assert!(same_state(&sim, &loaded), "stepping after loading diverged");
+    assert_eq!(loaded.spheres[0].pos, sim.spheres[0].pos);
+}
+
+#[test]
+fn test_json_round_trip_is_bit_exact() {
+    let mut sim = scene();
+    run(&mut sim, 40);
+    let json = sim.snapshot().to_json().unwrap();
+    assert!(json.contains("\"format\": \"jaxs-physics-snapshot\""));
+    assert!(json.contains("\"version\": 1"));
+
+    let mut loaded = Snapshot::from_json(&json).unwrap().into_sim();
+    assert!(same_state(&sim, &loaded));
+    run(&mut sim, 40);
+    run(&mut loaded, 40);
+    assert!(same_state(&sim, &loaded), "stepping after loading diverged");
+}
+
+#[test]
+fn test_rejects_foreign_and_newer_snapshots() {
+    let bytes = scene().snapshot().to_bytes();
+    assert!(matches!(Snapshot::from_bytes(b"not a snapshot"), Err(PhysicsError::Snapshot(_))));
+    assert!(matches!(Snapshot::from_bytes(&bytes[..bytes.len() / 2]), Err(PhysicsError::Snapshot(_))));
+
+    let mut newer = bytes.clone();
+    newer[8..12].copy_from_slice(&2u32.to_le_bytes());
+    assert!(matches!(Snapshot::from_bytes(&newer), Err(PhysicsError::Snapshot(message)) if message.contains("version 2")));
+
+    let json = scene().snapshot().to_json().unwrap().replacen("\"version\": 1", "\"version\": 2", 1);
+    assert!(matches!(Snapshot::from_json(&json), Err(PhysicsError::Snapshot(message)) if message.contains("version 2")));
+}
+
+#[test]
+fn test_non_finite_state_only_encodes_as_binary() {
+    let mut sim = scene();
+    sim.spheres[0].vel.x = f32::NAN;
+    let snapshot = sim.snapshot();
+    assert!(matches!(snapshot.to_json(), Err(PhysicsError::Snapshot(_))));
+
+    let loaded = Snapshot::from_bytes(&snapshot.to_bytes()).unwrap().into_sim();
+    assert_eq!(loaded.spheres[0].vel.x.to_bits(), f32::NAN.to_bits());
+}
@@ -0,0 +1,134 @@
+//! Tests for the soft contact model: resting depth set by the stiffness,
+//! both CPU solvers and the GPU contact kernel, and a response that changes
+//! smoothly with the initial state
+
+use physics::{
+    BodyHandle, ContactModel, PhysicsSim, SoftContact, SolverType,
+    types::{Material, Vec2, Vec3},
+};
+
+const MASS: f32 = 1.0;
+const RADIUS: f32 = 0.5;
+
+fn soft_sim(solver: SolverType, stiffness: f32) -> PhysicsSim {
+    let mut sim = PhysicsSim::new();
+    sim.solver = solver;
+    sim.contact_model = ContactModel::Soft(SoftContact {
+        stiffness,
+        ..SoftContact::default()
+    });
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+    sim
+}
+
+fn add_ball(sim: &mut PhysicsSim, height: f32, velocity: Vec3) -> usize {
+    sim.add_sphere_with_mass_and_material(
+        Vec3::new(0.0, height, 0.0),
+        velocity,
+        RADIUS,
+        MASS,
+        Material::new(0.5, 0.0),
+    )
+}
+
+/// Depth at which the smoothed spring carries the weight of a sphere of
+/// [`MASS`]: the inverse softplus of the weight, close to `m g / k` once the
+/// smoothing width is well below it.
+fn resting_depth(stiffness: f32) -> f32 {
+    let smoothing = SoftContact::default().smoothing;
+    let load = MASS * 9.81 / (stiffness * smoothing);
+    smoothing * load.exp_m1().ln()
+}
+
+#[test]
+fn test_soft_sphere_rests_at_weight_over_stiffness() {
+    for solver in [SolverType::SequentialImpulse, SolverType::Xpbd { substeps: 4 }] {
+        let mut depths = Vec::new();
+        for stiffness in [2.0e3, 1.0e4] {
+            let mut sim = soft_sim(solver, stiffness);
+            let ball = add_ball(&mut sim, RADIUS + 0.05, Vec3::ZERO);
+            sim.run_cpu(0.01, 300);
+
+            let depth = RADIUS - sim.spheres[ball].pos.y;
+            let expected = resting_depth(stiffness);
+            assert!(
+                (depth - expected).abs() < 0.2 * expected.abs(),
+                "{solver:?} k = {stiffness}: depth {depth}, expected {expected}"
+            );
+            assert!(sim.spheres[ball].vel.y.abs() < 1e-2, "{solver:?}: vy = {}", sim.spheres[ball].vel.y);
+            depths.push(depth);
+        }
+        assert!(depths[1] < depths[0], "{solver:?}: stiffer contacts sink less, {depths:?}");
+    }
+}
+
+#[test]
+fn test_soft_contact_reports_weight() {
+    let mut sim = soft_sim(SolverType::SequentialImpulse, 1.0e4);
+    let ball = add_ball(&mut sim, RADIUS, Vec3::ZERO);
+    sim.run_cpu(0.01, 200);
+
+    let force = sim.contact_force(BodyHandle::Sphere(ball));
+    assert!((force.y - MASS * 9.81).abs() < 0.05 * MASS * 9.81, "force {force:?}");
+}
+
+#[test]
+fn test_soft_friction_stops_sliding_sphere_without_reversing() {
+    let mut sim = soft_sim(SolverType::SequentialImpulse, 1.0e4);
+    let ball = add_ball(&mut sim, RADIUS - resting_depth(1.0e4), Vec3::new(2.0, 0.0, 0.0));
+
+    let mut previous = sim.spheres[ball].vel.x;
+    for _ in 0..200 {
+        sim.step_cpu();
+        let vx = sim.spheres[ball].vel.x;
+        assert!(vx <= previous + 1e-4 && vx > -1e-3, "vx went from {previous} to {vx}");
+        previous = vx;
+    }
+    assert!(previous < 2.0 - 0.5, "friction slowed the sphere, vx = {previous}");
+}
+
+/// Vertical velocity after one step from rest, for a sphere starting `gap`
+/// above touching the plane.
+fn speed_after_step(solver: SolverType, model: ContactModel, gap: f32) -> f32 {
+    let mut sim = soft_sim(solver, 1.0e4);
+    sim.contact_model = model;
+    let ball = add_ball(&mut sim, RADIUS + gap, Vec3::ZERO);
+    sim.params.dt = 0.01;
+    sim.step_cpu();
+    sim.spheres[ball].vel.y
+}
+
+#[test]
+fn test_soft_response_is_smooth_in_initial_height() {
+    // Across first touch, the hard response has a kink where its slope
+    // switches abruptly; the soft one bends gradually
+    let gaps: Vec<f32> = (0..24).map(|i| -0.002 + i as f32 * 2.5e-4).collect();
+    let response = |solver, model| {
+        let speeds: Vec<f32> = gaps.iter().map(|&gap| speed_after_step(solver, model, gap)).collect();
+        let jumps: Vec<f32> = speeds.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        let kink = jumps.windows(2).map(|pair| (pair[1] - pair[0]).abs()).fold(0.0, f32::max);
+        (kink, jumps)
+    };
+    for solver in [SolverType::SequentialImpulse, SolverType::Xpbd { substeps: 4 }] {
+        let (hard, _) = response(solver, ContactModel::Hard);
+        let (soft, jumps) = response(solver, ContactModel::Soft(SoftContact::default()));
+        assert!(soft < 0.25 * hard, "{solver:?}: soft {soft}, hard {hard}");
+        assert!(jumps.iter().all(|&jump| jump < 0.0), "{solver:?}: deeper starts push harder, {jumps:?}");
+    }
+}
+
+#[test]
+fn test_gpu_step_applies_soft_contacts() {
+    let stiffness = 1.0e4;
+    let mut gpu = soft_sim(SolverType::SequentialImpulse, stiffness);
+    let ball = add_ball(&mut gpu, RADIUS + 0.05, Vec3::ZERO);
+    gpu.params.dt = 0.01;
+    for _ in 0..300 {
+        gpu.step_gpu().unwrap();
+    }
+
+    let depth = RADIUS - gpu.spheres[ball].pos.y;
+    let expected = resting_depth(stiffness);
+    assert!((depth - expected).abs() < 0.2 * expected.abs(), "depth {depth}, expected {expected}");
+    assert!(gpu.spheres[ball].vel.y.abs() < 1e-2, "vy = {}", gpu.spheres[ball].vel.y);
+}
@@ -35,6 +35,10 @@ fn test_spatial_grid_basic() {
 #[test]
 fn test_spatial_grid_performance() {
     let mut sim = PhysicsSim::new();
+    sim.configure_spatial_grid(4.0, BoundingBox {
+        min: Vec3::new(-50.0, -10.0, -50.0),
+        max: Vec3::new(50.0, 90.0, 50.0),
+    });
     
     // Add many spheres in a grid pattern
     for x in 0..10 {
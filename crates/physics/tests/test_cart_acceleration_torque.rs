@@ -5,8 +5,10 @@ use physics::types::{Vec3, Vec2};
 fn test_cart_acceleration_affects_pole() {
     let mut sim = PhysicsSim::new();
     
-    // Add ground plane
-    sim.add_plane(Vec3::new(0.0, -1.0, 0.0), 1.0, Vec2::new(100.0, 100.0));
+    // Add ground plane through the origin, facing up. Planes keep the side
+    // their normal points to free, so a downward normal would make this a
+    // ceiling cutting through the pole.
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(100.0, 100.0));
     
     let config = CartPoleConfig {
         initial_angle: 0.0, // Start perfectly vertical
@@ -46,8 +48,10 @@ fn test_cart_acceleration_affects_pole() {
 fn test_balancing_torque() {
     let mut sim = PhysicsSim::new();
     
-    // Add ground plane
-    sim.add_plane(Vec3::new(0.0, -1.0, 0.0), 1.0, Vec2::new(100.0, 100.0));
+    // Add ground plane through the origin, facing up. Planes keep the side
+    // their normal points to free, so a downward normal would make this a
+    // ceiling cutting through the pole.
+    sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(100.0, 100.0));
     
     let config = CartPoleConfig {
         initial_angle: 0.1, // Start with small tilt to the right
@@ -0,0 +1,222 @@
+//! Tests for the XPBD substepping solver: contacts, friction, restitution,
+//! joint and contact compliance, and large mass ratios
+
+use physics::{
+    BodyHandle, PhysicsSim, SolverType,
+    cartpole::{CartPole, CartPoleConfig},
+    types::{BodyType, Material, Vec2, Vec3},
+};
+
+fn xpbd_sim(substeps: usize) -> PhysicsSim {
+    let mut sim = PhysicsSim::new();
+    sim.solver = SolverType::Xpbd { substeps };
+    sim
+}
+
+fn ground(sim: &mut PhysicsSim, material: Material) {
+    let plane = sim.add_plane(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec2::new(50.0, 50.0));
+    sim.planes[plane].material = material;
+}
+
+#[test]
+fn test_xpbd_free_fall_matches_gravity() {
+    let mut sim = xpbd_sim(8);
+    let sphere = sim.add_sphere(Vec3::new(0.0, 10.0, 0.0), Vec3::ZERO, 0.1);
+
+    sim.run_cpu(0.01, 50);
+    let t = 0.5;
+    let expected = 10.0 - 0.5 * 9.81 * t * t;
+    assert!((sim.spheres[sphere].pos.y - expected).abs() < 0.01, "y = {}", sim.spheres[sphere].pos.y);
+    assert!((sim.spheres[sphere].vel.y + 9.81 * t).abs() < 0.01, "vy = {}", sim.spheres[sphere].vel.y);
+}
+
+#[test]
+fn test_xpbd_sphere_rests_on_plane() {
+    for substeps in [1, 4] {
+        let mut sim = xpbd_sim(substeps);
+        ground(&mut sim, Material::new(0.5, 0.0));
+        let sphere = sim.add_sphere(Vec3::new(0.0, 0.5, 0.0), Vec3::ZERO, 0.25);
+
+        sim.run_cpu(0.01, 200);
+        let body = &sim.spheres[sphere];
+        assert!((body.pos.y - 0.25).abs() < 0.01, "{substeps} substeps: y = {}", body.pos.y);
+        assert!(body.vel.y.abs() < 0.05, "{substeps} substeps: vy = {}", body.vel.y);
+    }
+}
+
+#[test]
+fn test_xpbd_restitution_in_velocity_pass() {
+    let mut sim = xpbd_sim(8);
+    ground(&mut sim, Material::new(0.0, 0.8));
+    let sphere = sim.add_sphere_with_material(Vec3::new(0.0, 0.3, 0.0), Vec3::new(0.0, -4.0, 0.0), 0.25, Material::new(0.0, 0.8));
+
+    let mut rebound = 0.0_f32;
+    for _ in 0..10 {
+        sim.step_cpu();
+        rebound = rebound.max(sim.spheres[sphere].vel.y);
+    }
+    // Both materials have a restitution of 0.8
+    assert!((rebound - 0.8 * 4.0).abs() < 0.4, "rebound speed = {rebound}");
+}
+
+#[test]
+fn test_xpbd_sliding_friction_decelerates_box() {
+    let mut sim = xpbd_sim(8);
+    let friction = 0.5;
+    ground(&mut sim, Material::new(friction, 0.0));
+    let body = sim.add_box(Vec3::new(0.0, 0.25, 0.0), Vec3::new(0.25, 0.25, 0.25), Vec3::new(3.0, 0.0, 0.0));
+    sim.boxes[body].material = Material::new(friction, 0.0);
+
+    sim.run_cpu(0.01, 20);
+    // Kinetic friction decelerates the box at mu * g
+    let expected = 3.0 - friction * 9.81 * 0.2;
+    let speed = sim.boxes[body].vel.x;
+    assert!((speed - expected).abs() < 0.15, "speed {speed}, expected {expected}");
+
+    sim.run_cpu(0.01, 100);
+    assert!(sim.boxes[body].vel.x.abs() < 0.01, "the box should stop");
+    assert!((sim.boxes[body].pos.y - 0.25).abs() < 0.01);
+}
+
+/// Hang a 2 kg sphere from a static box by a ball joint. Returns the joint
+/// and the sphere.
+fn hanging_bob(sim: &mut PhysicsSim) -> (usize, usize) {
+    let support = sim.add_box_with_type(
+        Vec3::new(0.0, 5.0, 0.0),
+        Vec3::new(0.1, 0.1, 0.1),
+        Vec3::ZERO,
+        BodyType::Static,
+    );
+    let bob = sim.add_sphere_with_mass_and_material(Vec3::new(0.0, 4.0, 0.0), Vec3::ZERO, 0.1, 2.0, Material::default());
+    let joint = sim.add_ball_joint(
+        BodyHandle::BOX_TYPE,
+        support as u32,
+        BodyHandle::SPHERE_TYPE,
+        bob as u32,
+        Vec3::new(0.0, 4.0, 0.0),
+    );
+    (joint, bob)
+}
+
+/// Centre of the vertical oscillation of `sphere` over `steps` steps. A
+/// compliant constraint is an undamped spring, so the bob keeps bouncing
+/// around its rest position.
+fn rest_height(sim: &mut PhysicsSim, sphere: usize, steps: usize) -> f32 {
+    let (mut low, mut high) = (f32::INFINITY, f32::NEG_INFINITY);
+    for _ in 0..steps {
+        sim.step_cpu();
+        low = low.min(sim.spheres[sphere].pos.y);
+        high = high.max(sim.spheres[sphere].pos.y);
+    }
+    0.5 * (low + high)
+}
+
+/// A compliant ball joint stretches by compliance * weight
+#[test]
+fn test_xpbd_joint_compliance_stretches_under_load() {
+    let mut rigid = xpbd_sim(8);
+    let (_, bob) = hanging_bob(&mut rigid);
+    rigid.run_cpu(0.01, 100);
+    let stretch = 4.0 - rigid.spheres[bob].pos.y;
+    assert!(stretch.abs() < 1e-3, "rigid joint stretched by {stretch}");
+
+    let compliance = 1e-3;
+    let expected = compliance * 2.0 * 9.81;
+    let mut soft = xpbd_sim(8);
+    let (joint, bob) = hanging_bob(&mut soft);
+    soft.ball_joints[joint].compliance = compliance;
+    let stretch = 4.0 - rest_height(&mut soft, bob, 100);
+    assert!((stretch - expected).abs() < 0.1 * expected, "stretch {stretch}, expected {expected}");
+
+    // The shared compliance in the joint parameters adds to every joint
+    let mut shared = xpbd_sim(8);
+    shared.joint_params.compliance = compliance;
+    let (_, bob) = hanging_bob(&mut shared);
+    let stretch = 4.0 - rest_height(&mut shared, bob, 100);
+    assert!((stretch - expected).abs() < 0.1 * expected, "stretch {stretch}, expected {expected}");
+}
+
+/// A compliant contact material lets a resting sphere sink by compliance * weight
+#[test]
+fn test_xpbd_contact_compliance() {
+    let mut sim = xpbd_sim(8);
+    // The sphere creeps towards its rest depth slower than the sleep
+    // threshold, so sleeping would freeze it part way
+    sim.sleep_params.enabled = false;
+    ground(&mut sim, Material::new(0.5, 0.0));
+    let compliance = 1e-4;
+    let material = Material {
+        compliance,
+        ..Material::new(0.5, 0.0)
+    };
+    let sphere = sim.add_sphere_with_mass_and_material(Vec3::new(0.0, 0.25, 0.0), Vec3::ZERO, 0.25, 10.0, material);
+
+    sim.run_cpu(0.01, 300);
+    let sink = 0.25 - sim.spheres[sphere].pos.y;
+    let expected = compliance * 10.0 * 9.81;
+    assert!((sink - expected).abs() < 0.15 * expected, "sink {sink}, expected {expected}");
+}
+
+/// A heavy bob hanging from a chain of links a hundred times lighter keeps
+/// the chain together while it swings
+#[test]
+fn test_xpbd_large_mass_ratio_chain() {
+    let mut sim = xpbd_sim(100);
+    let support = sim.add_box_with_type(
+        Vec3::new(0.0, 5.0, 0.0),
+        Vec3::new(0.1, 0.1, 0.1),
+        Vec3::ZERO,
+        BodyType::Static,
+    );
+    let link = 0.4;
+    let mut previous = (BodyHandle::BOX_TYPE, support);
+    for i in 1..=5 {
+        let mass = if i == 5 { 10.0 } else { 0.1 };
+        let y = 5.0 - i as f32 * link;
+        let sphere = sim.add_sphere_with_mass_and_material(Vec3::new(0.0, y, 0.0), Vec3::ZERO, 0.1, mass, Material::default());
+        let anchor = Vec3::new(0.0, y + 0.5 * link, 0.0);
+        sim.add_ball_joint(previous.0, previous.1 as u32, BodyHandle::SPHERE_TYPE, sphere as u32, anchor);
+        previous = (BodyHandle::SPHERE_TYPE, sphere);
+    }
+    sim.spheres[4].vel = Vec3::new(3.0, 0.0, 1.0);
+
+    let mut furthest = 0.0_f32;
+    for step in 0..300 {
+        sim.step_cpu();
+        let mut a = glam::Vec3::from(sim.boxes[support].pos);
+        for sphere in &sim.spheres {
+            let b = glam::Vec3::from(sphere.pos);
+            let length = a.distance(b);
+            assert!(length < link + 0.02, "link stretched to {length} at step {step}");
+            a = b;
+        }
+        furthest = furthest.max(sim.spheres[4].pos.x);
+    }
+    assert!(furthest > 0.5, "the bob should swing out, furthest x = {furthest}");
+}
+
+/// The light pole of a cart-pole stays on its hinge while the cart is driven
+#[test]
+fn test_xpbd_cartpole_light_pole() {
+    let mut sim = xpbd_sim(8);
+    let config = CartPoleConfig::default();
+    let mut cartpole = CartPole::new(&mut sim, Vec3::new(0.0, 0.0, 0.0), config);
+
+    let joint = cartpole.joint_idx;
+    for step in 0..200 {
+        let action = if (step / 40) % 2 == 0 { 1.0 } else { -1.0 };
+        cartpole.apply_force(&mut sim, action);
+        sim.step_cpu();
+
+        let revolute = &sim.revolute_joints[joint];
+        let cart = &sim.boxes[cartpole.cart_idx];
+        let pole = &sim.cylinders[cartpole.pole_idx];
+        let on_cart = glam::Vec3::from(cart.pos)
+            + glam::Quat::from_array(cart.orientation).normalize() * glam::Vec3::from(revolute.anchor_a);
+        let on_pole = glam::Vec3::from(pole.pos)
+            + glam::Quat::from_array(pole.orientation).normalize() * glam::Vec3::from(revolute.anchor_b);
+        let separation = on_cart.distance(on_pole);
+        assert!(separation < 0.01, "hinge separated by {separation} at step {step}");
+        assert!(pole.pos.z.abs() < 1e-3, "pole left the plane at step {step}");
+    }
+}
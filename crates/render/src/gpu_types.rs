@@ -4,7 +4,7 @@
 //! pass data to the WGSL shaders. All types must be Pod and properly aligned.
 
 use bytemuck::{Pod, Zeroable};
-use physics::{BoxBody, Cylinder, Plane, Sphere};
+use physics::{BoxBody, Capsule, Cylinder, Plane, Sphere};
 
 /// Uniform buffer that stores camera matrices for the SDF renderer
 ///
@@ -106,6 +106,31 @@ impl From<&Cylinder> for CylinderGpu {
     }
 }
 
+/// GPU representation of a capsule primitive
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct CapsuleGpu {
+    /// Transform matrix (includes position and rotation)
+    pub transform: [[f32; 4]; 4],
+    /// Capsule radius
+    pub radius: f32,
+    /// Half the length of the core segment along the local Y axis
+    pub half_height: f32,
+    pub _pad: [f32; 2],
+}
+
+impl From<&Capsule> for CapsuleGpu {
+    fn from(capsule: &Capsule) -> Self {
+        let transform = physics::transform::to_transform_matrix(capsule.pos, capsule.orientation);
+        
+        Self {
+            transform,
+            radius: capsule.radius,
+            half_height: capsule.half_height,
+            _pad: [0.0; 2],
+        }
+    }
+}
 
 /// GPU representation of a plane primitive
 #[repr(C)]
@@ -139,4 +164,7 @@ pub struct SceneCounts {
     pub boxes: u32,
     pub cylinders: u32,
     pub planes: u32,
+    pub capsules: u32,
+    /// Pads the uniform to a multiple of 16 bytes
+    pub _pad: [u32; 3],
 }
\ No newline at end of file
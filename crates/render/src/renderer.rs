@@ -125,7 +125,7 @@ impl Renderer {
         
         // Create scene buffers
         let buffer_config = BufferConfig::default();
-        let counts = SceneCounts { spheres: 0, boxes: 0, cylinders: 0, planes: 0 };
+        let counts = SceneCounts { spheres: 0, boxes: 0, cylinders: 0, planes: 0, capsules: 0, _pad: [0; 3] };
         let counts_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Scene Counts"),
             contents: bytemuck::bytes_of(&counts),
@@ -137,6 +137,7 @@ impl Renderer {
             boxes_buffer: create_storage_buffer(&device, "Boxes Buffer", &buffer_config),
             cylinders_buffer: create_storage_buffer(&device, "Cylinders Buffer", &buffer_config),
             planes_buffer: create_storage_buffer(&device, "Planes Buffer", &buffer_config),
+            capsules_buffer: create_storage_buffer(&device, "Capsules Buffer", &buffer_config),
             counts_buffer,
         };
         
@@ -173,6 +174,10 @@ impl Renderer {
                     binding: 5,
                     resource: scene_manager.planes_buffer.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: scene_manager.capsules_buffer.as_entire_binding(),
+                },
             ],
         });
         
@@ -371,8 +376,9 @@ impl Renderer {
         boxes: &[physics::BoxBody],
         cylinders: &[physics::Cylinder],
         planes: &[physics::Plane],
+        capsules: &[physics::Capsule],
     ) {
-        self.scene_manager.update(&self.queue, spheres, boxes, cylinders, planes);
+        self.scene_manager.update(&self.queue, spheres, boxes, cylinders, planes, capsules);
     }
     
     fn take_screenshot(&mut self) {
@@ -10,6 +10,10 @@ struct SceneCounts {
     boxes: u32,
     cylinders: u32,
     planes: u32,
+    capsules: u32,
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
 }
 
 struct Sphere {
@@ -33,6 +37,13 @@ struct Cylinder {
     _pad: vec2<f32>,
 }
 
+struct Capsule {
+    transform: mat4x4<f32>,
+    radius: f32,
+    half_height: f32,
+    _pad: vec2<f32>,
+}
+
 struct Plane {
     normal: vec3<f32>,
     d: f32,
@@ -46,6 +57,7 @@ struct Plane {
 @group(0) @binding(3) var<storage, read> boxes: array<Box>;
 @group(0) @binding(4) var<storage, read> cylinders: array<Cylinder>;
 @group(0) @binding(5) var<storage, read> planes: array<Plane>;
+@group(0) @binding(6) var<storage, read> capsules: array<Capsule>;
 
 // Define extents for the ground plane
 const plane_extents: vec2<f32> = vec2<f32>(25.0, 25.0);
@@ -91,6 +103,15 @@ fn sdf_cylinder(p: vec3<f32>, transform: mat4x4<f32>, radius: f32, height: f32)
     return min(max(d.x, d.y), 0.0) + length(max(d, vec2<f32>(0.0)));
 }
 
+// SDF for capsule
+fn sdf_capsule(p: vec3<f32>, transform: mat4x4<f32>, radius: f32, half_height: f32) -> f32 {
+    let local_p = world_to_object(p, transform);
+    
+    // Distance to the core segment along the Y-axis, minus the radius
+    let core = vec3<f32>(0.0, clamp(local_p.y, -half_height, half_height), 0.0);
+    return length(local_p - core) - radius;
+}
+
 // SDF for plane, rendered as a thin box
 fn sdf_plane(p: vec3<f32>, pl: Plane) -> f32 {
     // If extents are zero, treat as an infinite plane
@@ -122,7 +143,7 @@ fn smin(a: f32, b: f32, k: f32) -> f32 {
 // Structure to return both distance and object information
 struct SceneResult {
     distance: f32,
-    object_type: u32, // 0=sphere, 1=box, 2=cylinder, 3=plane
+    object_type: u32, // 0=sphere, 1=box, 2=cylinder, 3=plane, 4=capsule
     object_index: u32,
 }
 
@@ -160,6 +181,16 @@ fn scene_sdf_detailed(p: vec3<f32>) -> SceneResult {
         }
     }
     
+    // Capsules
+    for (var i = 0u; i < counts.capsules; i++) {
+        let capsule_dist = sdf_capsule(p, capsules[i].transform, capsules[i].radius, capsules[i].half_height);
+        if (capsule_dist < result.distance) {
+            result.distance = capsule_dist;
+            result.object_type = 4u;
+            result.object_index = i;
+        }
+    }
+    
     // Planes
     for (var i = 0u; i < counts.planes; i++) {
         let pl = planes[i];
@@ -291,6 +322,8 @@ fn fs_main(@builtin(position) frag_coord: vec4<f32>) -> @location(0) vec4<f32> {
         color = vec3<f32>(0.6, 0.8, 0.6); // Green for cylinders
     } else if (ray_result.object_type == 3u) { // Plane
         color = vec3<f32>(0.7, 0.7, 0.8); // Light blue-gray for planes
+    } else if (ray_result.object_type == 4u) { // Capsule
+        color = vec3<f32>(0.8, 0.7, 0.5); // Tan for capsules
     }
     
     // Apply lighting
@@ -3,8 +3,8 @@
 //! This module handles updating GPU buffers with physics simulation data,
 //! converting from physics types to GPU-compatible formats.
 
-use crate::gpu_types::{BoxGpu, CylinderGpu, PlaneGpu, SceneCounts, SphereGpu};
-use physics::{BoxBody, Cylinder, Plane, Sphere};
+use crate::gpu_types::{BoxGpu, CapsuleGpu, CylinderGpu, PlaneGpu, SceneCounts, SphereGpu};
+use physics::{BoxBody, Capsule, Cylinder, Plane, Sphere};
 
 /// Scene buffer manager
 ///
@@ -15,6 +15,7 @@ pub struct SceneManager {
     pub boxes_buffer: wgpu::Buffer,
     pub cylinders_buffer: wgpu::Buffer,
     pub planes_buffer: wgpu::Buffer,
+    pub capsules_buffer: wgpu::Buffer,
     /// Buffer containing primitive counts
     pub counts_buffer: wgpu::Buffer,
 }
@@ -30,6 +31,7 @@ impl SceneManager {
         boxes: &[BoxBody],
         cylinders: &[Cylinder],
         planes: &[Plane],
+        capsules: &[Capsule],
     ) {
         // Convert and upload spheres
         if !spheres.is_empty() {
@@ -72,12 +74,24 @@ impl SceneManager {
             );
         }
 
+        // Convert and upload capsules
+        if !capsules.is_empty() {
+            let capsule_data: Vec<CapsuleGpu> = capsules.iter().map(CapsuleGpu::from).collect();
+            queue.write_buffer(
+                &self.capsules_buffer,
+                0,
+                bytemuck::cast_slice(&capsule_data),
+            );
+        }
+
         // Update counts
         let counts = SceneCounts {
             spheres: spheres.len() as u32,
             boxes: boxes.len() as u32,
             cylinders: cylinders.len() as u32,
             planes: planes.len() as u32,
+            capsules: capsules.len() as u32,
+            _pad: [0; 3],
         };
         queue.write_buffer(&self.counts_buffer, 0, bytemuck::bytes_of(&counts));
     }
@@ -89,6 +103,8 @@ impl SceneManager {
             boxes: 0,
             cylinders: 0,
             planes: 0,
+            capsules: 0,
+            _pad: [0; 3],
         };
         queue.write_buffer(&self.counts_buffer, 0, bytemuck::bytes_of(&counts));
     }
@@ -19,13 +19,13 @@ fn debug_cartpole_orientation() {
     eprintln!("Initial orientation: [{:.3}, {:.3}, {:.3}, {:.3}]", 
              initial[0], initial[1], initial[2], initial[3]);
     
-    // The orientation should be a 30-degree rotation around Z axis
+    // The pole leans towards +X, which is a -30-degree rotation around Z axis
     // For a quaternion rotating θ around Z: q = [0, 0, sin(θ/2), cos(θ/2)]
-    // For 30° = π/6: sin(π/12) ≈ 0.259, cos(π/12) ≈ 0.966
+    // For -30° = -π/6: sin(-π/12) ≈ -0.259, cos(-π/12) ≈ 0.966
     
     assert!(initial[0].abs() < 0.01, "X should be ~0");
     assert!(initial[1].abs() < 0.01, "Y should be ~0");
-    assert!((initial[2] - 0.259).abs() < 0.01, "Z should be ~0.259");
+    assert!((initial[2] + 0.259).abs() < 0.01, "Z should be ~-0.259");
     assert!((initial[3] - 0.966).abs() < 0.01, "W should be ~0.966");
     
     // Run physics step
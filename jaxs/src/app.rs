@@ -24,7 +24,7 @@
 use anyhow::Result;
 use physics::{
     types::{Vec2, Vec3},
-    PhysicsSim, CartPoleGrid, CartPoleConfig,
+    BodyHandle, PhysicsSim, CartPoleGrid, CartPoleConfig,
 };
 use std::time::{Duration, Instant};
 use winit::event::{Event, WindowEvent, ElementState};
@@ -368,12 +368,13 @@ fn update_renderer_scene_data(renderer: &mut render::Renderer, simulation: &Phys
                        orientation[0], orientation[1], orientation[2], orientation[3]);
     }
     
-    renderer.update_scene(
-        &simulation.spheres,
-        &simulation.boxes,
-        &simulation.cylinders,
-        &simulation.planes
-    );
+    // Removed bodies keep their slots until a new body takes them
+    let spheres: Vec<_> = simulation.live(&simulation.spheres, BodyHandle::Sphere).copied().collect();
+    let boxes: Vec<_> = simulation.live(&simulation.boxes, BodyHandle::Box).copied().collect();
+    let cylinders: Vec<_> = simulation.live(&simulation.cylinders, BodyHandle::Cylinder).copied().collect();
+    let planes: Vec<_> = simulation.live(&simulation.planes, BodyHandle::Plane).copied().collect();
+    let capsules: Vec<_> = simulation.live(&simulation.capsules, BodyHandle::Capsule).copied().collect();
+    renderer.update_scene(&spheres, &boxes, &cylinders, &planes, &capsules);
 }
 
 /// Log progress every N frames for monitoring.
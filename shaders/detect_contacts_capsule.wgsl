@@ -0,0 +1,136 @@
+struct Vec3 {
+    x : f32,
+    y : f32,
+    z : f32,
+};
+
+// Core segment from `a` to `b`, inflated by `radius`.
+struct Capsule {
+    a : Vec3,
+    radius : f32,
+    b : Vec3,
+    pad : f32,
+};
+
+struct Contact {
+    capsule_a_index : u32,
+    capsule_b_index : u32,
+    point : Vec3,
+    normal : Vec3,
+    depth : f32,
+    pad : Vec3,
+};
+
+@group(0) @binding(0) var<storage, read> capsules : array<Capsule>;
+@group(0) @binding(1) var<storage, read> filters : array<vec2<u32>>;
+@group(0) @binding(2) var<storage, read_write> contacts : array<Contact>;
+
+// One [group, mask] entry per body, followed by excluded [a, b] pairs.
+// Bodies without an entry are in group 1 and collide with every group.
+fn body_filter(index : u32, count : u32) -> vec2<u32> {
+    if (index < count && index < arrayLength(&filters)) {
+        return filters[index];
+    }
+    return vec2<u32>(1u, 0xffffffffu);
+}
+
+fn pair_allowed(a : u32, b : u32, count : u32) -> bool {
+    let fa = body_filter(a, count);
+    let fb = body_filter(b, count);
+    if ((fa.x & fb.y) == 0u || (fb.x & fa.y) == 0u) {
+        return false;
+    }
+    for (var k : u32 = count; k < arrayLength(&filters); k = k + 1u) {
+        let pair = filters[k];
+        if ((pair.x == a && pair.y == b) || (pair.x == b && pair.y == a)) {
+            return false;
+        }
+    }
+    return true;
+}
+
+fn to_vec(v : Vec3) -> vec3<f32> {
+    return vec3<f32>(v.x, v.y, v.z);
+}
+
+fn from_vec(v : vec3<f32>) -> Vec3 {
+    return Vec3(v.x, v.y, v.z);
+}
+
+// Parameters (s, t) of the closest points between segments p0-p1 and q0-q1.
+fn closest_between_segments(p0 : vec3<f32>, p1 : vec3<f32>, q0 : vec3<f32>, q1 : vec3<f32>) -> vec2<f32> {
+    let eps = 1e-7;
+    let d1 = p1 - p0;
+    let d2 = q1 - q0;
+    let r = p0 - q0;
+    let a = dot(d1, d1);
+    let e = dot(d2, d2);
+    let f = dot(d2, r);
+    if (a <= eps && e <= eps) {
+        return vec2<f32>(0.0, 0.0);
+    }
+    if (a <= eps) {
+        return vec2<f32>(0.0, clamp(f / e, 0.0, 1.0));
+    }
+    let c = dot(d1, r);
+    if (e <= eps) {
+        return vec2<f32>(clamp(-c / a, 0.0, 1.0), 0.0);
+    }
+    let b = dot(d1, d2);
+    let denom = a * e - b * b;
+    var s = 0.0;
+    if (denom > eps * a * e) {
+        s = clamp((b * f - c * e) / denom, 0.0, 1.0);
+    }
+    let t = (b * s + f) / e;
+    if (t < 0.0) {
+        return vec2<f32>(clamp(-c / a, 0.0, 1.0), 0.0);
+    }
+    if (t > 1.0) {
+        return vec2<f32>(clamp((b - c) / a, 0.0, 1.0), 1.0);
+    }
+    return vec2<f32>(s, t);
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    let count = arrayLength(&capsules);
+    var out_idx : u32 = 0u;
+    for (var i : u32 = 0u; i < count; i = i + 1u) {
+        let ca = capsules[i];
+        let a0 = to_vec(ca.a);
+        let a1 = to_vec(ca.b);
+        for (var j : u32 = i + 1u; j < count; j = j + 1u) {
+            if (!pair_allowed(i, j, count)) {
+                continue;
+            }
+            let cb = capsules[j];
+            let b0 = to_vec(cb.a);
+            let b1 = to_vec(cb.b);
+            let st = closest_between_segments(a0, a1, b0, b1);
+            let pa = mix(a0, a1, st.x);
+            let pb = mix(b0, b1, st.y);
+            let delta = pb - pa;
+            let dist = length(delta);
+            let rad_sum = ca.radius + cb.radius;
+            if (dist < rad_sum) {
+                var n = vec3<f32>(0.0, 1.0, 0.0);
+                if (dist > 0.0) {
+                    n = delta / dist;
+                }
+                let depth = rad_sum - dist;
+                if (out_idx < arrayLength(&contacts)) {
+                    var c : Contact;
+                    c.capsule_a_index = i;
+                    c.capsule_b_index = j;
+                    // Halfway between the two surfaces
+                    c.point = from_vec(pa + n * (ca.radius - 0.5 * depth));
+                    c.normal = from_vec(n);
+                    c.depth = depth;
+                    contacts[out_idx] = c;
+                }
+                out_idx = out_idx + 1u;
+            }
+        }
+    }
+}
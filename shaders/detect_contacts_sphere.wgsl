@@ -16,7 +16,32 @@ struct Contact {
 };
 
 @group(0) @binding(0) var<storage, read> bodies : array<Body>;
-@group(0) @binding(1) var<storage, read_write> contacts : array<Contact>;
+@group(0) @binding(1) var<storage, read> filters : array<vec2<u32>>;
+@group(0) @binding(2) var<storage, read_write> contacts : array<Contact>;
+
+// One [group, mask] entry per body, followed by excluded [a, b] pairs.
+// Bodies without an entry are in group 1 and collide with every group.
+fn body_filter(index : u32, count : u32) -> vec2<u32> {
+    if (index < count && index < arrayLength(&filters)) {
+        return filters[index];
+    }
+    return vec2<u32>(1u, 0xffffffffu);
+}
+
+fn pair_allowed(a : u32, b : u32, count : u32) -> bool {
+    let fa = body_filter(a, count);
+    let fb = body_filter(b, count);
+    if ((fa.x & fb.y) == 0u || (fb.x & fa.y) == 0u) {
+        return false;
+    }
+    for (var k : u32 = count; k < arrayLength(&filters); k = k + 1u) {
+        let pair = filters[k];
+        if ((pair.x == a && pair.y == b) || (pair.x == b && pair.y == a)) {
+            return false;
+        }
+    }
+    return true;
+}
 
 @compute @workgroup_size(1)
 fn main() {
@@ -25,6 +50,9 @@ fn main() {
     for (var i : u32 = 0u; i < count; i = i + 1u) {
         let a = bodies[i];
         for (var j : u32 = i + 1u; j < count; j = j + 1u) {
+            if (!pair_allowed(i, j, count)) {
+                continue;
+            }
             let b = bodies[j];
             let dx = b.pos.x - a.pos.x;
             let dy = b.pos.y - a.pos.y;
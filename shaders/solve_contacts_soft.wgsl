@@ -0,0 +1,60 @@
+struct Sphere {
+    pos : vec3<f32>,
+    vel : vec3<f32>,
+    orientation : vec4<f32>,
+    angular_vel : vec3<f32>,
+};
+
+struct Contact {
+    body_index : u32,
+    nx : f32,
+    ny : f32,
+    nz : f32,
+    depth : f32,
+    inv_mass : f32,
+    friction : f32,
+    pad : f32,
+};
+
+struct SoftParams {
+    stiffness : f32,
+    damping : f32,
+    smoothing : f32,
+    dt : f32,
+};
+
+@group(0) @binding(0) var<storage, read_write> bodies : array<Sphere>;
+@group(0) @binding(1) var<storage, read> contacts : array<Contact>;
+@group(0) @binding(2) var<uniform> params : SoftParams;
+
+// Contacts may share a body, so one invocation applies them in order.
+@compute @workgroup_size(1)
+fn main() {
+    let n = arrayLength(&contacts);
+    for (var i : u32 = 0u; i < n; i = i + 1u) {
+        let c = contacts[i];
+        if (c.body_index >= arrayLength(&bodies)) { continue; }
+        var b = bodies[c.body_index];
+        let normal = vec3<f32>(c.nx, c.ny, c.nz);
+        let w = c.inv_mass;
+        let dt = params.dt;
+
+        // Softplus penetration and its sigmoid slope
+        let x = c.depth / params.smoothing;
+        let penetration = params.smoothing * (max(x, 0.0) + log(1.0 + exp(-abs(x))));
+        let activation = 1.0 / (1.0 + exp(-x));
+        let gain = activation * (params.stiffness * dt + params.damping);
+        let normal_speed = dot(b.vel, normal);
+        let impulse = max(dt * (params.stiffness * penetration - gain * normal_speed) / (1.0 + w * dt * gain), 0.0);
+        b.vel = b.vel + normal * (impulse * w);
+
+        // Regularized Coulomb friction
+        let slip = b.vel - normal * dot(b.vel, normal);
+        let limit = c.friction * impulse;
+        let regularization = params.smoothing / dt;
+        let speed = sqrt(dot(slip, slip) + regularization * regularization);
+        b.vel = b.vel - slip * (limit / (speed + limit * w) * w);
+
+        bodies[c.body_index] = b;
+    }
+}